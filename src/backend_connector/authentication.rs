@@ -16,21 +16,22 @@ impl AuthenticationTrait for ShastaClient {
     // FIXME: this is not nice but authentication/authorization will potentially move out to an
     // external crate since this is type of logic is external to each site ...
     let base_url = self
-      .base_url
+      .base_url()
       .strip_suffix("/apis")
-      .unwrap_or(&self.base_url);
+      .unwrap_or(self.base_url());
 
     let keycloak_base_url = base_url.to_string() + "/keycloak";
 
-    let token = get_token_from_shasta_endpoint(
-      &keycloak_base_url,
-      &self.root_cert,
-      username,
-      password,
-      self.socks5_proxy.as_deref(),
-    )
-    .await
-    .map_err(Error::from)?;
+    let token = self
+      .scope(get_token_from_shasta_endpoint(
+        &keycloak_base_url,
+        self.root_cert(),
+        username,
+        password,
+        self.socks5_proxy(),
+      ))
+      .await
+      .map_err(Error::from)?;
 
     self.validate_api_token(&token).await?;
 
@@ -38,13 +39,20 @@ impl AuthenticationTrait for ShastaClient {
   }
 
   async fn validate_api_token(&self, token: &str) -> Result<(), Error> {
-    authentication::validate_api_token(
-      &self.base_url,
-      token,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-    )
-    .await
-    .map_err(Error::from)
+    // Validate `token` itself, not one from the token provider
+    let client = ShastaClient {
+      token_provider: None,
+      ..self.clone()
+    };
+
+    client
+      .scope(authentication::validate_api_token(
+        self.base_url(),
+        token,
+        self.root_cert(),
+        self.socks5_proxy(),
+      ))
+      .await
+      .map_err(Error::from)
   }
 }
@@ -30,11 +30,15 @@ impl ApplySessionTrait for ShastaClient {
     /* kafka_audit: &Kafka,
     k8s: &K8sDetails, */
   ) -> Result<(String, String), Error> {
+    let execution_context =
+      crate::ExecutionContext::from_token(shasta_token).map_err(Error::from)?;
+
     crate::commands::apply_session::exec(
       self,
       gitea_token,
       gitea_base_url,
       shasta_token,
+      &execution_context,
       // k8s_api_url,
       cfs_conf_sess_name,
       playbook_yaml_file_name_opt,
@@ -148,11 +152,27 @@ impl ClusterTemplateTrait for ShastaClient {
       .map_err(Error::from)
   }
 
+  /// Refuses protected templates (see [`crate::protection`]); the
+  /// dispatcher trait has no way to ignore protection, so unprotect the
+  /// template first.
   async fn delete_template(
     &self,
     shasta_token: &str,
     bos_template_id: &str,
   ) -> Result<(), Error> {
+    let bos_template_vec = self
+      .bos_template_v2_get(shasta_token, Some(bos_template_id))
+      .await
+      .map_err(Error::from)?;
+
+    if bos_template_vec.iter().any(|bos_template| {
+      crate::protection::is_protected(bos_template.description.as_deref())
+    }) {
+      return Err(Error::from(crate::error::Error::Protected(format!(
+        "BOS sessiontemplate '{bos_template_id}'"
+      ))));
+    }
+
     self
       .bos_template_v2_delete(shasta_token, bos_template_id)
       .await
@@ -5,9 +5,7 @@ use std::pin::Pin;
 use chrono::NaiveDateTime;
 use futures::AsyncBufRead;
 #[cfg(feature = "k8s-console")]
-use futures::AsyncReadExt;
-#[cfg(feature = "k8s-console")]
-use manta_backend_dispatcher::types::K8sAuth;
+use futures::TryStreamExt;
 use manta_backend_dispatcher::{
   error::Error,
   interfaces::cfs::CfsTrait,
@@ -26,21 +24,23 @@ use manta_backend_dispatcher::{
 };
 
 use crate::ShastaClient;
+use crate::cfs::session::utils::{SessionFilter, SessionVisibility};
 use crate::common::jwt_ops;
 // `GroupExt::get_members` replaces the old inherent
 // `Group::get_members` method (the type is now generated by
 // progenitor and can't carry inherent impls in this crate).
 use crate::hsm::group::GroupExt;
 #[cfg(feature = "k8s-console")]
-use crate::common::{
-  kubernetes, vault::http_client::fetch_shasta_k8s_secrets_from_vault,
-};
+use crate::{backend_connector::kube_auth, common::kubernetes};
 
 impl CfsTrait for ShastaClient {
   type T = Pin<Box<dyn AsyncBufRead + Send>>;
 
   async fn get_cfs_health(&self) -> Result<(), Error> {
-    crate::cfs::health::test_connectivity_to_backend(self.base_url.as_str())
+    self
+      .scope(crate::cfs::health::test_connectivity_to_backend(
+        self.base_url(),
+      ))
       .await
       .map_err(Error::from)
   }
@@ -119,13 +119,13 @@ impl CfsTrait for ShastaClient {
       ));
     }
 
-    let mut hsm_group_available_vec =
-      crate::hsm::group::utils::get_group_available(
+    let mut hsm_group_available_vec = self
+      .scope(crate::hsm::group::utils::get_group_available(
         shasta_token,
-        &self.base_url,
-        &self.root_cert,
-        self.socks5_proxy.as_deref(),
-      )
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+      ))
       .await
       // .map_err(Error::from)?;
       .map_err(Error::from)?;
@@ -200,30 +200,33 @@ impl CfsTrait for ShastaClient {
       )
     };
 
-    let mut cfs_session_vec = crate::cfs::session::get_and_sort(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      min_age_opt,
-      max_age_opt,
-      status_opt,
-      cfs_session_name_opt,
-      is_succeded_opt,
-    )
-    .await
-    // .map_err(Error::from)?;
-    .map_err(Error::from)?;
+    let mut cfs_session_vec = self
+      .scope(crate::cfs::session::get_and_sort(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        min_age_opt,
+        max_age_opt,
+        status_opt,
+        cfs_session_name_opt,
+        is_succeded_opt,
+      ))
+      .await
+      // .map_err(Error::from)?;
+      .map_err(Error::from)?;
 
-    crate::cfs::session::utils::filter(
-      &mut cfs_session_vec,
-      None,
-      &hsm_group_name_vec,
-      &xname_vec,
-      type_opt,
-      limit_number_opt,
-      jwt_ops::is_user_admin(shasta_token),
-    )
+    SessionFilter {
+      visibility: Some(SessionVisibility {
+        hsm_group_names: &hsm_group_name_vec,
+        xnames: &xname_vec,
+        keep_generic: jwt_ops::is_user_admin(shasta_token),
+      }),
+      target_definition: type_opt.map(String::as_str),
+      limit: limit_number_opt.map(|limit_number| usize::from(*limit_number)),
+      ..SessionFilter::default()
+    }
+    .apply(&mut cfs_session_vec)
     // .map_err(Error::from)?;
     .map_err(Error::from)?;
 
@@ -326,9 +329,13 @@ impl CfsTrait for ShastaClient {
         .map(|bp| bp.clone().into())
         .collect();
 
+    let execution_context =
+      crate::ExecutionContext::from_token(shasta_token).map_err(Error::from)?;
+
     crate::cfs::cleanup_session::exec(
       self,
       shasta_token,
+      &execution_context,
       group_available_vec,
       &cfs_session,
       &cfs_component_vec,
@@ -350,8 +357,8 @@ impl CfsTrait for ShastaClient {
     Ok(crate::cfs::configuration::http_client::v3::types::cfs_configuration_request::CfsConfigurationRequest::create_from_repos(
             gitea_token,
             gitea_base_url,
-            &self.root_cert,
-            self.socks5_proxy.as_deref(),
+            self.root_cert(),
+            self.socks5_proxy(),
             repo_name_vec,
             local_git_commit_vec,
             playbook_file_name_opt,
@@ -389,26 +396,27 @@ impl CfsTrait for ShastaClient {
     until_opt: Option<NaiveDateTime>,
     limit_number_opt: Option<&u8>,
   ) -> Result<Vec<CfsConfigurationResponse>, Error> {
-    crate::cfs::configuration::utils::get_and_filter(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      configuration_name,
-      configuration_name_pattern,
-      hsm_group_name_vec,
-      since_opt,
-      until_opt,
-      limit_number_opt,
-    )
-    .await
-    .map(|config_vec| {
-      config_vec
-        .into_iter()
-        .map(std::convert::Into::into)
-        .collect()
-    })
-    .map_err(Error::from)
+    self
+      .scope(crate::cfs::configuration::utils::get_and_filter(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        configuration_name,
+        configuration_name_pattern,
+        hsm_group_name_vec,
+        since_opt,
+        until_opt,
+        limit_number_opt,
+      ))
+      .await
+      .map(|config_vec| {
+        config_vec
+          .into_iter()
+          .map(std::convert::Into::into)
+          .collect()
+      })
+      .map_err(Error::from)
   }
 
   async fn get_configuration_layer_details(
@@ -419,12 +427,12 @@ impl CfsTrait for ShastaClient {
     site_name: &str,
   ) -> Result<LayerDetails, Error> {
     crate::cfs::configuration::utils::get_configuration_layer_details(
-      &self.root_cert,
+      self.root_cert(),
       gitea_base_url,
       gitea_token,
       layer.into(),
       site_name,
-      self.socks5_proxy.as_deref(),
+      self.socks5_proxy(),
     )
     .await
     .map(std::convert::Into::into)
@@ -439,18 +447,19 @@ impl CfsTrait for ShastaClient {
     configuration_name: &str,
     overwrite: bool,
   ) -> Result<CfsConfigurationResponse, Error> {
-    crate::cfs::configuration::utils::create_new_configuration(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      &configuration.clone().into(),
-      configuration_name,
-      overwrite,
-    )
-    .await
-    .map(std::convert::Into::into)
-    .map_err(Error::from)
+    self
+      .scope(crate::cfs::configuration::utils::create_new_configuration(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        &configuration.clone().into(),
+        configuration_name,
+        overwrite.into(),
+      ))
+      .await
+      .map(|(cfs_configuration, _)| cfs_configuration.into())
+      .map_err(Error::from)
   }
 
   #[cfg(not(feature = "k8s-console"))]
@@ -470,18 +479,16 @@ impl CfsTrait for ShastaClient {
 
   /// Stream the concatenated stdout of a CFS session's `git-clone`
   /// init container, then its `inventory` container, then its
-  /// `ansible` container.
+  /// `ansible` container, through
+  /// [`kubernetes::SessionLogStreamer`]. The stream stops after the
+  /// first container that fails; which one is logged at error level.
   ///
   /// # Cancellation
   ///
-  /// The returned `Pin<Box<dyn AsyncBufRead + Send>>` is a `Chain`
-  /// of three hyper response-body adapters from `kube_client`. Each
-  /// segment is a thin wrapper around the response body — there's no
-  /// spawned watcher task to leak. Dropping the returned future mid-
-  /// stream drops the current segment's hyper `Response`, which
-  /// closes the connection to the Kubernetes API server. The API
-  /// server stops streaming. The downstream segments are never
-  /// attached because they're lazily constructed inside the chain.
+  /// Dropping the returned reader drops the current container's hyper
+  /// `Response`, which closes the connection to the Kubernetes API
+  /// server. The API server stops streaming. The following containers
+  /// are never attached because they're followed lazily.
   #[cfg(feature = "k8s-console")]
   async fn get_session_logs_stream(
     &self,
@@ -491,73 +498,54 @@ impl CfsTrait for ShastaClient {
     timestamps: bool,
     k8s: &K8sDetails,
   ) -> Result<Pin<Box<dyn AsyncBufRead + Send>>, Error> {
-    let shasta_k8s_secrets = match &k8s.authentication {
-      K8sAuth::Native {
-        certificate_authority_data,
-        client_certificate_data,
-        client_key_data,
-      } => {
-        serde_json::json!({ "certificate-authority-data": certificate_authority_data, "client-certificate-data": client_certificate_data, "client-key-data": client_key_data })
-      }
-      K8sAuth::Vault { base_url } => fetch_shasta_k8s_secrets_from_vault(
-        base_url,
+    // Looking up the session pod is the first call to the Kubernetes
+    // API, so it is the one retried if the cached credentials were
+    // rotated
+    let streamer = self
+      .with_kube_client(
         shasta_token,
-        site_name,
-        self.socks5_proxy.as_deref(),
-      )
-      .await
-      .map_err(Error::from)?,
-    };
-
-    let client = kubernetes::get_client(
-      &k8s.api_url,
-      shasta_k8s_secrets,
-      self.socks5_proxy.as_deref(),
-    )
-    .await
-    .map_err(Error::from)?;
-
-    let (log_stream_git_clone, exit_code) =
-      kubernetes::get_cfs_session_init_container_git_clone_logs_stream(
-        client.clone(),
-        cfs_session_name.to_string(),
-        timestamps,
+        &k8s.api_url,
+        &kube_auth(&k8s.authentication, site_name),
+        self.socks5_proxy(),
+        |client| {
+          kubernetes::SessionLogStreamer::for_cfs_session(
+            client,
+            cfs_session_name,
+            timestamps,
+            kubernetes::PodWaitTimeouts::default(),
+          )
+        },
       )
       .await
       .map_err(Error::from)?;
 
-    if exit_code != 0 {
-      log::error!(
-        "CFS session '{cfs_session_name}' git-clone init container failed with exit code {exit_code}"
-      );
-      return Ok(Box::pin(log_stream_git_clone));
-    }
-
-    let log_stream_inventory =
-      kubernetes::get_cfs_session_container_inventory_logs_stream(
-        client.clone(),
-        cfs_session_name.to_string(),
-        timestamps,
-      )
-      .await
-      .map_err(Error::from)?;
+    let cfs_session_name = cfs_session_name.to_string();
 
-    let log_stream_ansible =
-      kubernetes::get_cfs_session_container_ansible_logs_stream(
-        client,
-        cfs_session_name.to_string(),
-        timestamps,
-      )
-      .await
-      .map_err(Error::from)?;
+    let line_stream = streamer
+      .into_stream()
+      .try_filter_map(move |event| {
+        let line_opt = match event {
+          kubernetes::SessionLogEvent::Line { line, .. } => {
+            Some(format!("{line}\n"))
+          }
+          kubernetes::SessionLogEvent::ContainerExited(exit_status) => {
+            if !exit_status.success() {
+              log::error!(
+                "CFS session '{cfs_session_name}' container '{}' failed with exit code {}",
+                exit_status.container,
+                exit_status.exit_code
+              );
+            }
+            None
+          }
+        };
+        futures::future::ready(Ok(line_opt))
+      })
+      .map_err(|e| std::io::Error::other(e.to_string()));
 
-    // NOTE: here is where we convert from impl AsyncBufRead to Pin<Box<dyn AsyncBufRead>>
-    // through dynamic dispatch
-    Ok(Box::pin(
-      log_stream_git_clone
-        .chain(log_stream_inventory)
-        .chain(log_stream_ansible),
-    ))
+    // NOTE: here is where we convert from a stream of lines to
+    // Pin<Box<dyn AsyncBufRead>> through dynamic dispatch
+    Ok(Box::pin(Box::pin(line_stream).into_async_read()))
   }
 
   async fn update_runtime_configuration(
@@ -567,17 +555,18 @@ impl CfsTrait for ShastaClient {
     desired_configuration: &str,
     enabled: bool,
   ) -> Result<(), Error> {
-    crate::cfs::component::utils::update_component_list_desired_configuration(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      xnames,
-      desired_configuration,
-      enabled,
-    )
-    .await
-    .map_err(Error::from)
+    let update =
+      crate::cfs::component::utils::update_component_list_desired_configuration(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        xnames,
+        desired_configuration,
+        enabled,
+      );
+
+    self.scope(update).await.map_err(Error::from)
   }
 
   // Get all CFS sessions, IMS images and BOS sessiontemplates related to a CFS configuration
@@ -593,37 +582,38 @@ impl CfsTrait for ShastaClient {
     ),
     Error,
   > {
-    crate::cfs::configuration::utils::get_derivatives(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      configuration_name,
-    )
-    .await
-    .map(|(cfs_session_vec, bos_session_template_vec, image_vec)| {
-      (
-        cfs_session_vec.map(|cfs_session_vec| {
-          cfs_session_vec
-            .into_iter()
-            .map(std::convert::Into::into)
-            .collect()
-        }),
-        bos_session_template_vec.map(|bos_session_template_vec| {
-          bos_session_template_vec
-            .into_iter()
-            .map(std::convert::Into::into)
-            .collect()
-        }),
-        image_vec.map(|image_vec| {
-          image_vec
-            .into_iter()
-            .map(std::convert::Into::into)
-            .collect()
-        }),
-      )
-    })
-    .map_err(Error::from)
+    self
+      .scope(crate::cfs::configuration::utils::get_derivatives(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        configuration_name,
+      ))
+      .await
+      .map(|(cfs_session_vec, bos_session_template_vec, image_vec)| {
+        (
+          cfs_session_vec.map(|cfs_session_vec| {
+            cfs_session_vec
+              .into_iter()
+              .map(std::convert::Into::into)
+              .collect()
+          }),
+          bos_session_template_vec.map(|bos_session_template_vec| {
+            bos_session_template_vec
+              .into_iter()
+              .map(std::convert::Into::into)
+              .collect()
+          }),
+          image_vec.map(|image_vec| {
+            image_vec
+              .into_iter()
+              .map(std::convert::Into::into)
+              .collect()
+          }),
+        )
+      })
+      .map_err(Error::from)
   }
 
   async fn get_cfs_components(
@@ -77,6 +77,7 @@ impl DeleteConfigurationsAndDataRelatedTrait for ShastaClient {
       image_id_vec,
       cfs_session_name_vec,
       bos_sessiontemplate_name_vec,
+      crate::cfs::cleanup::DEFAULT_DELETE_RETRY,
     )
     .await
     .map_err(Error::from)
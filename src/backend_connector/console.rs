@@ -10,6 +10,7 @@ use manta_backend_dispatcher::{
 
 use crate::ShastaClient;
 use crate::{
+  backend_connector::kube_auth,
   common::vault::http_client::fetch_shasta_k8s_secrets_from_vault,
   node::console,
 };
@@ -63,79 +64,38 @@ impl ConsoleTrait for ShastaClient {
     initial_size: TermSize,
     k8s: &K8sDetails,
   ) -> Result<ConsoleAttachment, Error> {
-    let shasta_k8s_secrets = match &k8s.authentication {
-      K8sAuth::Native {
-        certificate_authority_data,
-        client_certificate_data,
-        client_key_data,
-      } => {
-        serde_json::json!({ "certificate-authority-data": certificate_authority_data, "client-certificate-data": client_certificate_data, "client-key-data": client_key_data })
-      }
-      K8sAuth::Vault {
-        base_url,
-        // secret_path: _secret_path,
-      } => fetch_shasta_k8s_secrets_from_vault(
-        base_url,
+    let node_console = self
+      .with_kube_client(
         shasta_token,
-        site_name,
-        self.socks5_proxy.as_deref(),
-      )
-      .await
-      .map_err(Error::from)?,
-    };
-
-    let mut attached: AttachedProcess =
-      console::get_container_attachment_to_conman(
-        xname,
         &k8s.api_url,
-        shasta_k8s_secrets,
-        self.socks5_proxy.as_deref(),
+        &kube_auth(&k8s.authentication, site_name),
+        self.socks5_proxy(),
+        |client| {
+          console::attach_node_with_client(
+            client,
+            xname,
+            Some(TerminalSize {
+              width: initial_size.width,
+              height: initial_size.height,
+            }),
+          )
+        },
       )
       .await
       .map_err(Error::from)?;
 
-    let pod = xname;
-    let mut terminal_size_writer: Sender<TerminalSize> =
-      attached.terminal_size().ok_or_else(|| {
-        crate::Error::ConsoleAttach {
-          pod: pod.to_string(),
-          cause: "kube exec did not provide a terminal-size channel".to_string(),
-        }
-      })?;
-    terminal_size_writer
-      .try_send(TerminalSize {
-        width: initial_size.width,
-        height: initial_size.height,
-      })
-      .map_err(|e| crate::Error::ConsoleAttach {
-        pod: pod.to_string(),
-        cause: e.to_string(),
-      })?;
-
     // Bridge manta's resize channel to kube's. The forwarder task exits
     // when the manta-side sender is dropped (caller closes the console),
-    // which then drops `terminal_size_writer` and ends kube's resize
-    // subprotocol cleanly.
-    let resize_tx = spawn_resize_forwarder(terminal_size_writer);
+    // which then drops the kube terminal-size sender and ends kube's
+    // resize subprotocol cleanly.
+    let resize_tx = spawn_resize_forwarder(node_console.terminal_size);
 
     log::info!("Connected to {xname}!");
     log::info!("Use &. key combination to exit the console.");
 
-    let stdin = attached.stdin().ok_or_else(|| {
-      crate::Error::ConsoleAttach {
-        pod: pod.to_string(),
-        cause: "kube exec did not provide a stdin stream".to_string(),
-      }
-    })?;
-    let stdout = attached.stdout().ok_or_else(|| {
-      crate::Error::ConsoleAttach {
-        pod: pod.to_string(),
-        cause: "kube exec did not provide a stdout stream".to_string(),
-      }
-    })?;
     Ok(ConsoleAttachment {
-      stdin: Box::new(stdin),
-      stdout: Box::new(stdout),
+      stdin: node_console.stdin,
+      stdout: node_console.stdout,
       resize: resize_tx,
     })
   }
@@ -159,14 +119,15 @@ impl ConsoleTrait for ShastaClient {
       K8sAuth::Vault {
         base_url,
         // secret_path: _secret_path,
-      } => fetch_shasta_k8s_secrets_from_vault(
-        base_url,
-        shasta_token,
-        site_name,
-        self.socks5_proxy.as_deref(),
-      )
-      .await
-      .map_err(Error::from)?,
+      } => self
+        .scope(fetch_shasta_k8s_secrets_from_vault(
+          base_url,
+          shasta_token,
+          site_name,
+          self.socks5_proxy(),
+        ))
+        .await
+        .map_err(Error::from)?,
     };
 
     let mut attached: AttachedProcess =
@@ -174,7 +135,9 @@ impl ConsoleTrait for ShastaClient {
         session_name,
         &k8s.api_url,
         shasta_k8s_secrets,
-        self.socks5_proxy.as_deref(),
+        self.socks5_proxy(),
+        // Not carried by `ConsoleTrait`
+        console::DEFAULT_POD_WAIT,
       )
       .await
       .map_err(Error::from)?;
@@ -9,21 +9,25 @@ use manta_backend_dispatcher::{
 };
 
 use crate::ShastaClient;
-use crate::hsm::{self, group::types::Member};
+use crate::hsm::{
+  self,
+  group::types::{GroupLabel, Member},
+};
 
 impl GroupTrait for ShastaClient {
   async fn get_group_available(
     &self,
     auth_token: &str,
   ) -> Result<Vec<FrontEndGroup>, Error> {
-    let hsm_group_vec = hsm::group::utils::get_group_available(
-      auth_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-    )
-    .await
-    .map_err(Error::from)?;
+    let hsm_group_vec = self
+      .scope(hsm::group::utils::get_group_available(
+        auth_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+      ))
+      .await
+      .map_err(Error::from)?;
 
     // Convert all HSM groups from mesa to infra
     let hsm_group_backend_vec = hsm_group_vec
@@ -38,14 +42,15 @@ impl GroupTrait for ShastaClient {
     &self,
     auth_token: &str,
   ) -> Result<Vec<String>, Error> {
-    hsm::group::utils::get_group_name_available(
-      auth_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-    )
-    .await
-    .map_err(Error::from)
+    self
+      .scope(hsm::group::utils::get_group_name_available(
+        auth_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+      ))
+      .await
+      .map_err(Error::from)
   }
 
   async fn add_group(
@@ -53,8 +58,13 @@ impl GroupTrait for ShastaClient {
     auth_token: &str,
     group: FrontEndGroup,
   ) -> Result<FrontEndGroup, Error> {
+    let mut group_payload: hsm::group::types::Group = group.clone().into();
+    group_payload.label = GroupLabel::for_new_group(&group_payload.label)
+      .map_err(Error::from)?
+      .into();
+
     let group_csm = self
-      .hsm_group_post(auth_token, group.clone().into())
+      .hsm_group_post(auth_token, group_payload)
       .await
       .map_err(Error::from)?;
 
@@ -72,15 +82,16 @@ impl GroupTrait for ShastaClient {
     auth_token: &str,
     hsm_group_name_vec: &[String],
   ) -> Result<Vec<String>, Error> {
-    hsm::group::utils::get_member_vec_from_hsm_name_vec(
-      auth_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      hsm_group_name_vec,
-    )
-    .await
-    .map_err(Error::from)
+    self
+      .scope(hsm::group::utils::get_member_vec_from_hsm_name_vec(
+        auth_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        hsm_group_name_vec,
+      ))
+      .await
+      .map_err(Error::from)
   }
 
   async fn get_group_map_and_filter_by_group_vec(
@@ -88,15 +99,16 @@ impl GroupTrait for ShastaClient {
     auth_token: &str,
     hsm_name_vec: &[&str],
   ) -> Result<HashMap<String, Vec<String>>, Error> {
-    hsm::group::utils::get_hsm_map_and_filter_by_hsm_name_vec(
-      auth_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      hsm_name_vec,
-    )
-    .await
-    .map_err(Error::from)
+    self
+      .scope(hsm::group::utils::get_hsm_map_and_filter_by_hsm_name_vec(
+        auth_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        hsm_name_vec,
+      ))
+      .await
+      .map_err(Error::from)
   }
 
   async fn get_group_map_and_filter_by_member_vec(
@@ -104,15 +116,18 @@ impl GroupTrait for ShastaClient {
     auth_token: &str,
     member_vec: &[&str],
   ) -> Result<HashMap<String, Vec<String>>, Error> {
-    hsm::group::utils::get_hsm_group_map_and_filter_by_hsm_group_member_vec(
-      auth_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      member_vec,
-    )
-    .await
-    .map_err(Error::from)
+    self
+      .scope(
+        hsm::group::utils::get_hsm_group_map_and_filter_by_hsm_group_member_vec(
+          auth_token,
+          self.base_url(),
+          self.root_cert(),
+          self.socks5_proxy(),
+          member_vec,
+        ),
+      )
+      .await
+      .map_err(Error::from)
   }
 
   async fn get_group(
@@ -183,15 +198,16 @@ impl GroupTrait for ShastaClient {
     shasta_token: &str,
     hsm_name_vec: &[&str],
   ) -> Result<HashMap<String, Vec<String>>, Error> {
-    hsm::group::utils::get_hsm_map_and_filter_by_hsm_name_vec(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      hsm_name_vec,
-    )
-    .await
-    .map_err(Error::from)
+    self
+      .scope(hsm::group::utils::get_hsm_map_and_filter_by_hsm_name_vec(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        hsm_name_vec,
+      ))
+      .await
+      .map_err(Error::from)
   }
 
   async fn post_member(
@@ -220,16 +236,17 @@ impl GroupTrait for ShastaClient {
     let mut sol: Vec<String> = Vec::new();
 
     for new_member in new_members {
-      sol = hsm::group::utils::add_member(
-        auth_token,
-        &self.base_url,
-        &self.root_cert,
-        self.socks5_proxy.as_deref(),
-        group_label,
-        new_member,
-      )
-      .await
-      .map_err(Error::from)?;
+      (sol, _) = self
+        .scope(hsm::group::utils::add_member(
+          auth_token,
+          self.base_url(),
+          self.root_cert(),
+          self.socks5_proxy(),
+          group_label,
+          new_member,
+        ))
+        .await
+        .map_err(Error::from)?;
     }
 
     Ok(sol)
@@ -254,17 +271,18 @@ impl GroupTrait for ShastaClient {
     members_to_remove: &[&str],
     members_to_add: &[&str],
   ) -> Result<(), Error> {
-    hsm::group::utils::update_hsm_group_members(
-      auth_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      group_name,
-      members_to_remove,
-      members_to_add,
-    )
-    .await
-    .map_err(Error::from)
+    self
+      .scope(hsm::group::utils::update_hsm_group_members(
+        auth_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        group_name,
+        members_to_remove,
+        members_to_add,
+      ))
+      .await
+      .map_err(Error::from)
   }
 
   // HSM/GROUP
@@ -276,17 +294,21 @@ impl GroupTrait for ShastaClient {
     new_target_hsm_members: &[&str],
     dryrun: bool,
   ) -> Result<(Vec<String>, Vec<String>), Error> {
-    hsm::group::utils::migrate_hsm_members(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      target_hsm_group_name,
-      parent_hsm_group_name,
-      new_target_hsm_members,
-      dryrun,
-    )
-    .await
-    .map_err(Error::from)
+    self
+      .scope(hsm::group::utils::migrate_hsm_members(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        target_hsm_group_name,
+        parent_hsm_group_name,
+        new_target_hsm_members,
+        dryrun,
+      ))
+      .await
+      .map(|(target_member_vec, parent_member_vec, _)| {
+        (target_member_vec, parent_member_vec)
+      })
+      .map_err(Error::from)
   }
 }
@@ -25,6 +25,7 @@ use serde_json::Value;
 
 use crate::ShastaClient;
 use crate::hsm::component::types::ComponentArrayPostArray;
+use crate::hsm::hw_inventory::ethernet_interfaces::types::ComponentEthernetInterface as HsmComponentEthernetInterface;
 
 impl HardwareInventory for ShastaClient {
   async fn get_inventory_hardware(
@@ -329,57 +330,95 @@ impl ComponentTrait for ShastaClient {
 impl ComponentEthernetInterfaceTrait for ShastaClient {
   async fn get_all_component_ethernet_interfaces(
     &self,
-    _auth_token: &str,
+    auth_token: &str,
   ) -> Result<Vec<ComponentEthernetInterface>, Error> {
-    Err(Error::Message(
-      "Get all ethernet interfaces command not implemented for this backend"
-        .to_string(),
-    ))
+    self
+      .hsm_eth_get_all(auth_token)
+      .await
+      .map(|interface_vec| {
+        interface_vec
+          .into_iter()
+          .map(|interface| {
+            HsmComponentEthernetInterface::from(interface).into()
+          })
+          .collect()
+      })
+      .map_err(Error::from)
   }
 
   async fn get_component_ethernet_interface(
     &self,
-    _auth_token: &str,
-    _eth_interface_id: &str,
+    auth_token: &str,
+    eth_interface_id: &str,
   ) -> Result<ComponentEthernetInterface, Error> {
-    Err(Error::Message(
-      "Get ethernet interfaces command not implemented for this backend"
-        .to_string(),
-    ))
+    self
+      .hsm_eth_get_one(auth_token, eth_interface_id)
+      .await
+      .map(|interface| HsmComponentEthernetInterface::from(interface).into())
+      .map_err(Error::from)
   }
 
   async fn update_component_ethernet_interface(
     &self,
-    _auth_token: &str,
-    _eth_interface_id: &str,
-    _description: Option<&str>,
-    _ip_address_mapping: (&str, &str),
+    auth_token: &str,
+    eth_interface_id: &str,
+    description: Option<&str>,
+    ip_address_mapping: (&str, &str),
   ) -> Result<Value, Error> {
-    Err(Error::Message(
-      "Update ethernet interface command not implemented for this backend"
-        .to_string(),
-    ))
+    // The PATCH payload needs the owning component, which the trait
+    // doesn't provide
+    let interface = self
+      .hsm_eth_get_one(auth_token, eth_interface_id)
+      .await
+      .map_err(Error::from)?;
+    let component_id = interface.component_id.ok_or_else(|| {
+      Error::Message(format!(
+        "Ethernet interface '{eth_interface_id}' has no component id"
+      ))
+    })?;
+
+    let response = self
+      .hsm_eth_patch(
+        auth_token,
+        eth_interface_id,
+        description,
+        &component_id,
+        ip_address_mapping,
+      )
+      .await
+      .map_err(Error::from)?;
+
+    // CSM may answer with an empty body
+    let body = response
+      .text()
+      .await
+      .map_err(|e| Error::Message(e.to_string()))?;
+    if body.trim().is_empty() {
+      Ok(Value::Null)
+    } else {
+      serde_json::from_str(&body).map_err(|e| Error::Message(e.to_string()))
+    }
   }
 
   async fn delete_all_component_ethernet_interfaces(
     &self,
-    _auth_token: &str,
+    auth_token: &str,
   ) -> Result<Value, Error> {
-    Err(Error::Message(
-      "Delete all ethernet interface command not implemented for this backend"
-        .to_string(),
-    ))
+    self
+      .hsm_eth_delete_all(auth_token)
+      .await
+      .map_err(Error::from)
   }
 
   async fn delete_component_ethernet_interface(
     &self,
-    _auth_token: &str,
-    _eth_interface_id: &str,
+    auth_token: &str,
+    eth_interface_id: &str,
   ) -> Result<Value, Error> {
-    Err(Error::Message(
-      "Delete ethernet interface command not implemented for this backend"
-        .to_string(),
-    ))
+    self
+      .hsm_eth_delete(auth_token, eth_interface_id)
+      .await
+      .map_err(Error::from)
   }
 }
 
@@ -10,6 +10,10 @@ use manta_backend_dispatcher::{
 use crate::ShastaClient;
 
 impl MigrateRestoreTrait for ShastaClient {
+  /// Restores the backup directory `image_dir` through its backup
+  /// manifest when no other file is given, see
+  /// [`crate::commands::migrate_restore::exec_from_backup`]; restores
+  /// from the explicitly given files otherwise.
   async fn migrate_restore(
     &self,
     shasta_token: &str,
@@ -23,23 +27,60 @@ impl MigrateRestoreTrait for ShastaClient {
     overwrite_image: bool,
     overwrite_template: bool,
   ) -> Result<(), Error> {
-    crate::commands::migrate_restore::exec(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      bos_file,
-      cfs_file,
-      hsm_file,
-      ims_file,
-      image_dir,
-      overwrite_group,
-      overwrite_configuration,
-      overwrite_image,
-      overwrite_template,
-    )
-    .await
-    .map_err(Error::from)
+    let execution_context =
+      crate::ExecutionContext::from_token(shasta_token).map_err(Error::from)?;
+
+    if let (None, None, None, None, Some(backup_dir)) =
+      (bos_file, cfs_file, hsm_file, ims_file, image_dir)
+    {
+      let backup_dir = std::path::Path::new(backup_dir);
+      if backup_dir
+        .join(crate::commands::migrate_backup::BACKUP_MANIFEST_FILE_NAME)
+        .is_file()
+      {
+        return self
+          .scope(crate::commands::migrate_restore::exec_from_backup(
+            shasta_token,
+            &execution_context,
+            self.base_url(),
+            self.root_cert(),
+            self.socks5_proxy(),
+            backup_dir,
+            &[],
+            overwrite_group,
+            overwrite_configuration,
+            overwrite_image,
+            overwrite_template,
+            // The trait has no way to ignore protection
+            false,
+          ))
+          .await
+          .map(|_| ())
+          .map_err(Error::from);
+      }
+    }
+
+    self
+      .scope(crate::commands::migrate_restore::exec(
+        shasta_token,
+        &execution_context,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        bos_file,
+        cfs_file,
+        hsm_file,
+        ims_file,
+        image_dir,
+        overwrite_group,
+        overwrite_configuration,
+        overwrite_image,
+        overwrite_template,
+        // The trait has no way to ignore protection
+        false,
+      ))
+      .await
+      .map_err(Error::from)
   }
 }
 
@@ -50,15 +91,17 @@ impl MigrateBackupTrait for ShastaClient {
     bos: Option<&str>,
     destination: Option<&str>,
   ) -> Result<(), Error> {
-    crate::commands::migrate_backup::exec(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      self.socks5_proxy.as_deref(),
-      bos,
-      destination,
-    )
-    .await
-    .map_err(Error::from)
+    self
+      .scope(crate::commands::migrate_backup::exec(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        self.socks5_proxy(),
+        bos,
+        destination,
+      ))
+      .await
+      .map(|_| ())
+      .map_err(Error::from)
   }
 }
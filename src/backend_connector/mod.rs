@@ -8,8 +8,8 @@
 //! Each submodule below wires one trait family to the corresponding
 //! csm-rs API surface — see the inline annotations for which trait each
 //! file implements. The impls live directly on [`crate::ShastaClient`],
-//! which carries the connection metadata (base URL, root cert, optional
-//! SOCKS5 proxy) those impls need; per-request bearer tokens are passed
+//! which carries the connection metadata (base URL, CA certificates,
+//! optional proxy) those impls need; per-request bearer tokens are passed
 //! in by the dispatcher.
 //!
 //! As a rule, dispatcher trait impls call into the domain namespaces
@@ -27,6 +27,10 @@
 //!   for a follow-up refactor to take `&ShastaClient` and lift the
 //!   logic out.
 //!
+//! [`crate::offline::OfflineBackend`] implements the read-only traits
+//! over an exported state bundle instead of a live system, see
+//! [`offline`].
+//!
 //! Consumers that talk to CSM directly should reach for
 //! [`crate::ShastaClient`] instead — this module exists specifically to
 //! satisfy the dispatcher contract.
@@ -44,6 +48,9 @@ pub mod console; // ConsoleTrait
 pub mod group; // GroupTrait
 pub mod hsm; // HardwareInventory, ComponentTrait, ComponentEthernetInterfaceTrait, RedfishEndpointTrait
 pub mod ims; // ImsTrait, GetImagesAndDetailsTrait
+// Read-only GroupTrait, BootParametersTrait, ImsTrait,
+// ClusterTemplateTrait and CfsTrait over an exported state bundle.
+pub mod offline;
 // `MigrateRestoreTrait`/`MigrateBackupTrait` and `SatTrait` are
 // implemented in terms of the CLI-shaped admin workflows under
 // `commands::{migrate_*, i_apply_sat_file}`, so they are gated behind
@@ -53,6 +60,35 @@ pub mod migrate; // MigrateRestoreTrait, MigrateBackupTrait
 pub mod pcs; // PCSTrait
 #[cfg(feature = "commands-admin")]
 pub mod sat; // SatTrait, ApplyHwClusterPin
+// Not a dispatcher trait: single entry point for frontends to follow
+// any `crate::watch::Watchable` resource.
+pub mod watch; // ShastaClient::watch
+
+/// [`crate::KubeAuth`] equivalent of the dispatcher's Kubernetes
+/// credentials; Vault secrets are read for `site_name`.
+#[cfg(feature = "k8s-console")]
+pub(crate) fn kube_auth(
+  authentication: &manta_backend_dispatcher::types::K8sAuth,
+  site_name: &str,
+) -> crate::KubeAuth {
+  use manta_backend_dispatcher::types::K8sAuth;
+
+  match authentication {
+    K8sAuth::Native {
+      certificate_authority_data,
+      client_certificate_data,
+      client_key_data,
+    } => crate::KubeAuth::Native {
+      certificate_authority_data: certificate_authority_data.clone(),
+      client_certificate_data: client_certificate_data.clone(),
+      client_key_data: client_key_data.clone(),
+    },
+    K8sAuth::Vault { base_url } => crate::KubeAuth::Vault {
+      base_url: base_url.clone(),
+      site_name: site_name.to_string(),
+    },
+  }
+}
 
 /// Backward-compatibility alias for [`crate::ShastaClient`].
 ///
@@ -0,0 +1,576 @@
+//! Read-only dispatcher trait impls for [`crate::offline::OfflineBackend`].
+//!
+//! Queries are answered from the state bundle; everything else —
+//! creating, changing or deleting resources, and anything the bundle
+//! doesn't record (session logs, Gitea layer details) — fails.
+
+use std::{collections::HashMap, pin::Pin};
+
+use chrono::NaiveDateTime;
+use futures::AsyncBufRead;
+use manta_backend_dispatcher::{
+  error::Error,
+  interfaces::{
+    bos::ClusterTemplateTrait, bss::BootParametersTrait, cfs::CfsTrait,
+    hsm::group::GroupTrait, ims::ImsTrait,
+  },
+  types::{
+    Group as FrontEndGroup, HsmActionResponse, K8sDetails,
+    bos::session_template::BosSessionTemplate,
+    bss::BootParameters as FrontEndBootParameters,
+    cfs::{
+      cfs_configuration_details::LayerDetails,
+      cfs_configuration_request::CfsConfigurationRequest,
+      cfs_configuration_response::{CfsConfigurationResponse, Layer},
+      component::Component as FrontEndComponent,
+      session::{CfsSessionGetResponse, CfsSessionPostRequest},
+    },
+    ims::{Image as FrontEndImage, PatchImage},
+  },
+};
+
+use crate::{hsm, offline::OfflineBackend};
+
+/// Error returned by operations the state bundle can't answer.
+fn unavailable(operation: &str) -> Error {
+  crate::Error::StateBundle(format!(
+    "{operation} not available in offline mode"
+  ))
+  .into()
+}
+
+fn into_vec<T, U: From<T>>(vec: Vec<T>) -> Vec<U> {
+  vec.into_iter().map(Into::into).collect()
+}
+
+impl GroupTrait for OfflineBackend {
+  async fn get_group_available(
+    &self,
+    _auth_token: &str,
+  ) -> Result<Vec<FrontEndGroup>, Error> {
+    Ok(into_vec(self.groups(None)))
+  }
+
+  async fn get_group_name_available(
+    &self,
+    _auth_token: &str,
+  ) -> Result<Vec<String>, Error> {
+    Ok(
+      self
+        .groups(None)
+        .into_iter()
+        .map(|group| group.label.0)
+        .collect(),
+    )
+  }
+
+  async fn add_group(
+    &self,
+    _auth_token: &str,
+    _group: FrontEndGroup,
+  ) -> Result<FrontEndGroup, Error> {
+    Err(unavailable("Create HSM group"))
+  }
+
+  async fn get_member_vec_from_group_name_vec(
+    &self,
+    _auth_token: &str,
+    hsm_group_name_vec: &[String],
+  ) -> Result<Vec<String>, Error> {
+    Ok(self.group_members(hsm_group_name_vec))
+  }
+
+  async fn get_group_map_and_filter_by_group_vec(
+    &self,
+    _auth_token: &str,
+    hsm_name_vec: &[&str],
+  ) -> Result<HashMap<String, Vec<String>>, Error> {
+    Ok(
+      hsm::group::utils::filter_by_hsm_group_name_and_convert_to_map(
+        hsm_name_vec,
+        &self.bundle().groups.iter().collect::<Vec<_>>(),
+      ),
+    )
+  }
+
+  async fn get_group_map_and_filter_by_member_vec(
+    &self,
+    _auth_token: &str,
+    member_vec: &[&str],
+  ) -> Result<HashMap<String, Vec<String>>, Error> {
+    Ok(
+      hsm::group::utils::filter_by_hsm_group_members_and_convert_to_map(
+        member_vec,
+        self.groups(None),
+      ),
+    )
+  }
+
+  async fn get_group(
+    &self,
+    _auth_token: &str,
+    hsm_name: &str,
+  ) -> Result<FrontEndGroup, Error> {
+    self.group(hsm_name).map(Into::into).map_err(Error::from)
+  }
+
+  async fn get_groups(
+    &self,
+    _auth_token: &str,
+    hsm_name_vec_opt: Option<&[String]>,
+  ) -> Result<Vec<FrontEndGroup>, Error> {
+    Ok(into_vec(self.groups(hsm_name_vec_opt)))
+  }
+
+  async fn delete_group(
+    &self,
+    _auth_token: &str,
+    _label: &str,
+  ) -> Result<HsmActionResponse, Error> {
+    Err(unavailable("Delete HSM group"))
+  }
+
+  async fn get_group_map_and_filter_by_group_name_vec(
+    &self,
+    shasta_token: &str,
+    hsm_name_vec: &[&str],
+  ) -> Result<HashMap<String, Vec<String>>, Error> {
+    self
+      .get_group_map_and_filter_by_group_vec(shasta_token, hsm_name_vec)
+      .await
+  }
+
+  async fn post_member(
+    &self,
+    _auth_token: &str,
+    _group_label: &str,
+    _xname: &str,
+  ) -> Result<HsmActionResponse, Error> {
+    Err(unavailable("Add HSM group member"))
+  }
+
+  async fn add_members_to_group(
+    &self,
+    _auth_token: &str,
+    _group_label: &str,
+    _new_members: &[&str],
+  ) -> Result<Vec<String>, Error> {
+    Err(unavailable("Add HSM group members"))
+  }
+
+  async fn delete_member_from_group(
+    &self,
+    _auth_token: &str,
+    _group_label: &str,
+    _xname: &str,
+  ) -> Result<(), Error> {
+    Err(unavailable("Remove HSM group member"))
+  }
+
+  async fn update_group_members(
+    &self,
+    _auth_token: &str,
+    _group_name: &str,
+    _members_to_remove: &[&str],
+    _members_to_add: &[&str],
+  ) -> Result<(), Error> {
+    Err(unavailable("Update HSM group members"))
+  }
+
+  async fn migrate_group_members(
+    &self,
+    _shasta_token: &str,
+    _target_hsm_group_name: &str,
+    _parent_hsm_group_name: &str,
+    _new_target_hsm_members: &[&str],
+    _dryrun: bool,
+  ) -> Result<(Vec<String>, Vec<String>), Error> {
+    Err(unavailable("Migrate HSM group members"))
+  }
+}
+
+impl BootParametersTrait for OfflineBackend {
+  async fn get_all_bootparameters(
+    &self,
+    _auth_token: &str,
+  ) -> Result<Vec<FrontEndBootParameters>, Error> {
+    Ok(into_vec(self.bundle().boot_parameters.clone()))
+  }
+
+  async fn get_bootparameters(
+    &self,
+    _auth_token: &str,
+    nodes: &[String],
+  ) -> Result<Vec<FrontEndBootParameters>, Error> {
+    Ok(into_vec(self.boot_parameters(nodes)))
+  }
+
+  async fn add_bootparameters(
+    &self,
+    _auth_token: &str,
+    _boot_parameters: &FrontEndBootParameters,
+  ) -> Result<(), Error> {
+    Err(unavailable("Create boot parameters"))
+  }
+
+  async fn update_bootparameters(
+    &self,
+    _auth_token: &str,
+    _boot_parameter: &FrontEndBootParameters,
+  ) -> Result<(), Error> {
+    Err(unavailable("Update boot parameters"))
+  }
+
+  async fn delete_bootparameters(
+    &self,
+    _auth_token: &str,
+    _boot_parameters: &FrontEndBootParameters,
+  ) -> Result<String, Error> {
+    Err(unavailable("Delete boot parameters"))
+  }
+}
+
+impl ImsTrait for OfflineBackend {
+  async fn get_images(
+    &self,
+    _shasta_token: &str,
+    image_id_opt: Option<&str>,
+  ) -> Result<Vec<FrontEndImage>, Error> {
+    Ok(into_vec(self.images(image_id_opt)))
+  }
+
+  async fn get_all_images(
+    &self,
+    _shasta_token: &str,
+  ) -> Result<Vec<FrontEndImage>, Error> {
+    Ok(into_vec(self.images(None)))
+  }
+
+  fn filter_images(
+    &self,
+    image_vec: &mut Vec<FrontEndImage>,
+  ) -> Result<(), Error> {
+    let mut image_aux_vec: Vec<crate::ims::image::http_client::types::Image> =
+      image_vec.iter().map(|image| image.clone().into()).collect();
+
+    crate::ims::image::utils::filter(&mut image_aux_vec);
+
+    *image_vec = into_vec(image_aux_vec);
+
+    Ok(())
+  }
+
+  async fn update_image(
+    &self,
+    _shasta_token: &str,
+    _image_id: &str,
+    _image: &PatchImage,
+  ) -> Result<(), Error> {
+    Err(unavailable("Update IMS image"))
+  }
+
+  async fn delete_image(
+    &self,
+    _shasta_token: &str,
+    _image_id: &str,
+  ) -> Result<(), Error> {
+    Err(unavailable("Delete IMS image"))
+  }
+}
+
+impl ClusterTemplateTrait for OfflineBackend {
+  async fn get_template(
+    &self,
+    _shasta_token: &str,
+    bos_session_template_id_opt: Option<&str>,
+  ) -> Result<Vec<BosSessionTemplate>, Error> {
+    Ok(into_vec(self.templates(bos_session_template_id_opt)))
+  }
+
+  async fn get_and_filter_templates(
+    &self,
+    _shasta_token: &str,
+    hsm_group_name_vec: &[String],
+    hsm_member_vec: &[String],
+    bos_sessiontemplate_name_opt: Option<&str>,
+    limit_number_opt: Option<&u8>,
+  ) -> Result<Vec<BosSessionTemplate>, Error> {
+    self
+      .filter_templates(
+        hsm_group_name_vec,
+        hsm_member_vec,
+        bos_sessiontemplate_name_opt,
+        limit_number_opt,
+      )
+      .map(into_vec)
+      .map_err(Error::from)
+  }
+
+  async fn get_all_templates(
+    &self,
+    _shasta_token: &str,
+  ) -> Result<Vec<BosSessionTemplate>, Error> {
+    Ok(into_vec(self.templates(None)))
+  }
+
+  async fn put_template(
+    &self,
+    _shasta_token: &str,
+    _bos_template: &BosSessionTemplate,
+    _bos_template_name: &str,
+  ) -> Result<BosSessionTemplate, Error> {
+    Err(unavailable("Create BOS session template"))
+  }
+
+  async fn delete_template(
+    &self,
+    _shasta_token: &str,
+    _bos_template_id: &str,
+  ) -> Result<(), Error> {
+    Err(unavailable("Delete BOS session template"))
+  }
+}
+
+impl CfsTrait for OfflineBackend {
+  type T = Pin<Box<dyn AsyncBufRead + Send>>;
+
+  /// The state bundle is always reachable.
+  async fn get_cfs_health(&self) -> Result<(), Error> {
+    Ok(())
+  }
+
+  async fn post_session(
+    &self,
+    _shasta_token: &str,
+    _session: &CfsSessionPostRequest,
+  ) -> Result<CfsSessionGetResponse, Error> {
+    Err(unavailable("Create CFS session"))
+  }
+
+  /// Only the filters applied by the CFS API itself that the bundle can
+  /// reproduce are supported: name, status, name substring, success
+  /// and limit.
+  async fn get_sessions(
+    &self,
+    _shasta_token: &str,
+    session_name_opt: Option<&String>,
+    limit_opt: Option<u8>,
+    after_id_opt: Option<String>,
+    min_age_opt: Option<String>,
+    max_age_opt: Option<String>,
+    status_opt: Option<String>,
+    name_contains_opt: Option<String>,
+    is_succeded_opt: Option<bool>,
+    tags_opt: Option<String>,
+  ) -> Result<Vec<CfsSessionGetResponse>, Error> {
+    if after_id_opt.is_some()
+      || min_age_opt.is_some()
+      || max_age_opt.is_some()
+      || tags_opt.is_some()
+    {
+      return Err(unavailable("Filtering CFS sessions by age, tags or page"));
+    }
+
+    let mut cfs_session_vec =
+      self.sessions(session_name_opt.map(String::as_str));
+
+    cfs_session_vec.retain(|cfs_session| {
+      let session_status_opt = cfs_session
+        .status
+        .as_ref()
+        .and_then(|status| status.session.as_ref())
+        .and_then(|session| session.status.as_deref());
+
+      status_opt
+        .as_deref()
+        .is_none_or(|status| session_status_opt == Some(status))
+        && name_contains_opt
+          .as_deref()
+          .is_none_or(|name_contains| cfs_session.name.contains(name_contains))
+        && is_succeded_opt
+          .is_none_or(|is_succeded| cfs_session.is_success() == is_succeded)
+    });
+
+    if let Some(limit) = limit_opt {
+      cfs_session_vec.truncate(usize::from(limit));
+    }
+
+    Ok(into_vec(cfs_session_vec))
+  }
+
+  async fn get_and_filter_sessions(
+    &self,
+    _shasta_token: &str,
+    hsm_group_name_vec: Vec<String>,
+    xname_vec: Vec<&str>,
+    min_age_opt: Option<&String>,
+    max_age_opt: Option<&String>,
+    type_opt: Option<&String>,
+    status_opt: Option<&String>,
+    cfs_session_name_opt: Option<&String>,
+    limit_number_opt: Option<&u8>,
+    is_succeded_opt: Option<bool>,
+  ) -> Result<Vec<CfsSessionGetResponse>, Error> {
+    if min_age_opt.is_some() || max_age_opt.is_some() {
+      return Err(unavailable("Filtering CFS sessions by age"));
+    }
+
+    let xname_vec: Vec<String> =
+      xname_vec.into_iter().map(str::to_string).collect();
+
+    let cfs_session_vec = self
+      .filter_sessions(
+        &hsm_group_name_vec,
+        &xname_vec,
+        type_opt,
+        status_opt.map(String::as_str),
+        cfs_session_name_opt.map(String::as_str),
+        limit_number_opt,
+        is_succeded_opt,
+      )
+      .map_err(Error::from)?;
+
+    if cfs_session_vec.is_empty() {
+      return Err(Error::SessionNotFound);
+    }
+
+    Ok(into_vec(cfs_session_vec))
+  }
+
+  async fn delete_and_cancel_session(
+    &self,
+    _shasta_token: &str,
+    _group_available_vec: &[FrontEndGroup],
+    _cfs_session: &CfsSessionGetResponse,
+    _cfs_component_vec: &[FrontEndComponent],
+    _bss_bootparameters_vec: &[FrontEndBootParameters],
+    _dry_run: bool,
+  ) -> Result<(), Error> {
+    Err(unavailable("Delete CFS session"))
+  }
+
+  async fn create_configuration_from_repos(
+    &self,
+    _gitea_token: &str,
+    _gitea_base_url: &str,
+    _repo_name_vec: &[&str],
+    _local_git_commit_vec: &[&str],
+    _playbook_file_name_opt: Option<&str>,
+  ) -> Result<CfsConfigurationRequest, Error> {
+    Err(unavailable("Create CFS configuration"))
+  }
+
+  async fn get_configuration(
+    &self,
+    _auth_token: &str,
+    configuration_name_opt: Option<&String>,
+  ) -> Result<Vec<CfsConfigurationResponse>, Error> {
+    Ok(into_vec(
+      self.configurations(configuration_name_opt.map(String::as_str)),
+    ))
+  }
+
+  async fn get_and_filter_configuration(
+    &self,
+    _shasta_token: &str,
+    configuration_name: Option<&str>,
+    configuration_name_pattern: Option<&str>,
+    hsm_group_name_vec: &[String],
+    since_opt: Option<NaiveDateTime>,
+    until_opt: Option<NaiveDateTime>,
+    limit_number_opt: Option<&u8>,
+  ) -> Result<Vec<CfsConfigurationResponse>, Error> {
+    self
+      .filter_configurations(
+        configuration_name,
+        configuration_name_pattern,
+        hsm_group_name_vec,
+        since_opt,
+        until_opt,
+        limit_number_opt,
+      )
+      .map(into_vec)
+      .map_err(Error::from)
+  }
+
+  async fn get_configuration_layer_details(
+    &self,
+    _gitea_base_url: &str,
+    _gitea_token: &str,
+    _layer: Layer,
+    _site_name: &str,
+  ) -> Result<LayerDetails, Error> {
+    Err(unavailable("CFS configuration layer details"))
+  }
+
+  async fn put_configuration(
+    &self,
+    _shasta_token: &str,
+    _configuration: &CfsConfigurationRequest,
+    _configuration_name: &str,
+    _overwrite: bool,
+  ) -> Result<CfsConfigurationResponse, Error> {
+    Err(unavailable("Create CFS configuration"))
+  }
+
+  async fn get_session_logs_stream(
+    &self,
+    _shasta_token: &str,
+    _site_name: &str,
+    _cfs_session_name: &str,
+    _timestamps: bool,
+    _k8s: &K8sDetails,
+  ) -> Result<Pin<Box<dyn AsyncBufRead + Send>>, Error> {
+    Err(unavailable("CFS session logs"))
+  }
+
+  async fn update_runtime_configuration(
+    &self,
+    _shasta_token: &str,
+    _xnames: &[String],
+    _desired_configuration: &str,
+    _enabled: bool,
+  ) -> Result<(), Error> {
+    Err(unavailable("Update CFS components"))
+  }
+
+  async fn get_derivatives(
+    &self,
+    _shasta_token: &str,
+    configuration_name: &str,
+  ) -> Result<
+    (
+      Option<Vec<CfsSessionGetResponse>>,
+      Option<Vec<BosSessionTemplate>>,
+      Option<Vec<FrontEndImage>>,
+    ),
+    Error,
+  > {
+    let (cfs_session_vec, bos_session_template_vec, image_vec) =
+      self.derivatives(configuration_name);
+
+    Ok((
+      Some(into_vec(cfs_session_vec)),
+      Some(into_vec(bos_session_template_vec)),
+      Some(into_vec(image_vec)),
+    ))
+  }
+
+  async fn get_cfs_components(
+    &self,
+    _shasta_token: &str,
+    configuration_name: Option<&str>,
+    components_ids: Option<&str>,
+    status: Option<&str>,
+  ) -> Result<Vec<FrontEndComponent>, Error> {
+    let id_vec_opt: Option<Vec<String>> = components_ids.map(|component_ids| {
+      component_ids.split(',').map(str::to_string).collect()
+    });
+
+    Ok(into_vec(self.components(
+      configuration_name,
+      id_vec_opt.as_deref(),
+      status,
+    )))
+  }
+}
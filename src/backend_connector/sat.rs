@@ -12,8 +12,9 @@
 //! `manta_backend_dispatcher::types::*` types via the existing `From`
 //! impls so the returned tuple satisfies the trait.
 //!
-//! `apply_image` is similar but returns just the produced `Image` —
-//! the `manta.image_session.*` provenance metadata stamp + PATCH is
+//! `apply_image` is similar but returns just the produced `Image`; the
+//! CFS sessions it took are reported as an event if any was retried.
+//! The `manta.image_session.*` provenance metadata stamp + PATCH is
 //! done inside
 //! [`crate::commands::i_apply_sat_file::utils::images::i_create_image_from_sat_file_serde_yaml`]
 //! before the image is returned, so callers never see the underlying
@@ -54,9 +55,17 @@ use manta_backend_dispatcher::{
 
 use crate::ShastaClient;
 use crate::{
-  commands::i_apply_sat_file::utils,
+  bos::session::reboot::RebootStrategy,
+  commands::i_apply_sat_file::{
+    command::{SatApplyOptions, SatApplyOutcome},
+    utils::{
+      self,
+      images::{ImageBuildContext, ImageNameConflictPolicy},
+    },
+  },
   common::{
-    kubernetes, vault::http_client::fetch_shasta_k8s_secrets_from_vault,
+    events, product_catalog,
+    vault::http_client::fetch_shasta_k8s_secrets_from_vault,
   },
 };
 
@@ -103,21 +112,34 @@ impl SatTrait for ShastaClient {
         ))
       })?;
 
-    let socks5_proxy = self.socks5_proxy.as_deref();
-    let shasta_k8s_secrets = fetch_shasta_k8s_secrets_from_vault(
-      vault_base_url,
-      shasta_token,
-      site_name,
-      socks5_proxy,
-    )
-    .await
-    .map_err(Error::from)?;
+    let socks5_proxy = self.socks5_proxy();
+    let shasta_k8s_secrets = self
+      .scope(fetch_shasta_k8s_secrets_from_vault(
+        vault_base_url,
+        shasta_token,
+        site_name,
+        socks5_proxy,
+      ))
+      .await
+      .map_err(Error::from)?;
 
-    let (configurations, images, session_templates, sessions) =
-      crate::commands::i_apply_sat_file::command::exec(
+    // The dispatcher traits carry no execution context: the caller acts
+    // for themselves
+    let execution_context =
+      crate::ExecutionContext::from_token(shasta_token).map_err(Error::from)?;
+
+    let SatApplyOutcome {
+      configurations,
+      images,
+      session_templates,
+      sessions,
+      ..
+    } = self
+      .scope(crate::commands::i_apply_sat_file::command::exec(
         shasta_token,
-        &self.base_url,
-        &self.root_cert,
+        &execution_context,
+        self.base_url(),
+        self.root_cert(),
         socks5_proxy,
         vault_base_url,
         site_name,
@@ -134,8 +156,23 @@ impl SatTrait for ShastaClient {
         timestamps,
         debug_on_failure,
         overwrite,
+        // Not carried by `ApplySatFileParams`; keep the old behaviour,
+        // and the trait has no way to ignore protection
+        &SatApplyOptions {
+          // Same as `sat bootprep --overwrite-images`
+          image_name_conflict_policy: if overwrite {
+            ImageNameConflictPolicy::Overwrite
+          } else {
+            ImageNameConflictPolicy::default()
+          },
+          cfs_session_retry_policy: self
+            .config()
+            .cfs_session_retry_policy()
+            .clone(),
+          ..SatApplyOptions::default()
+        },
         dry_run,
-      )
+      ))
       .await
       .map_err(Error::from)?;
 
@@ -170,32 +207,38 @@ impl SatTrait for ShastaClient {
         ))
       })?;
 
-    let socks5_proxy = self.socks5_proxy.as_deref();
-    let shasta_k8s_secrets = fetch_shasta_k8s_secrets_from_vault(
-      vault_base_url,
-      shasta_token,
-      site_name,
-      socks5_proxy,
-    )
-    .await
-    .map_err(Error::from)?;
-
-    crate::commands::i_apply_sat_file::command::validate_sat_file(
-      crate::commands::i_apply_sat_file::command::ValidateSatFileParams {
-        shasta_token,
-        shasta_base_url: &self.base_url,
-        shasta_root_cert: &self.root_cert,
-        socks5_proxy,
+    let socks5_proxy = self.socks5_proxy();
+    let shasta_k8s_secrets = self
+      .scope(fetch_shasta_k8s_secrets_from_vault(
         vault_base_url,
+        shasta_token,
         site_name,
-        k8s_api_url,
-        hsm_group_available_vec,
-        sat_template_file_yaml,
-      },
-      shasta_k8s_secrets,
-    )
-    .await
-    .map_err(|e| Error::BadRequest(e.to_string()))
+        socks5_proxy,
+      ))
+      .await
+      .map_err(Error::from)?;
+
+    self
+      .scope(
+        crate::commands::i_apply_sat_file::command::validate_sat_file(
+          crate::commands::i_apply_sat_file::command::ValidateSatFileParams {
+            shasta_token,
+            shasta_base_url: self.base_url(),
+            shasta_root_cert: self.root_cert(),
+            socks5_proxy,
+            vault_base_url,
+            site_name,
+            k8s_api_url,
+            hsm_group_available_vec,
+            sat_template_file_yaml,
+          },
+          shasta_k8s_secrets,
+        ),
+      )
+      .await
+      .and_then(|report| report.into_result(true))
+      .map(|_| ())
+      .map_err(|e| Error::BadRequest(e.to_string()))
   }
 
   async fn apply_configuration(
@@ -213,7 +256,7 @@ impl SatTrait for ShastaClient {
       dry_run,
       overwrite,
     } = params;
-    let socks5_proxy = self.socks5_proxy.as_deref();
+    let socks5_proxy = self.socks5_proxy();
 
     // Transcode the structured Value (carried as JSON end-to-end) into
     // the serde_yaml::Value the per-entry creator expects. Lossless for
@@ -228,40 +271,37 @@ impl SatTrait for ShastaClient {
     // Fetch the cray-product-catalog ConfigMap. The configurations
     // section uses it to resolve `product:` layers; gitea is used to
     // resolve branches to commits.
-    let shasta_k8s_secrets = fetch_shasta_k8s_secrets_from_vault(
-      vault_base_url,
+    let cray_product_catalog = product_catalog::fetch(
+      self,
       shasta_token,
-      site_name,
+      k8s_api_url,
+      &crate::KubeAuth::Vault {
+        base_url: vault_base_url.to_string(),
+        site_name: site_name.to_string(),
+      },
       socks5_proxy,
     )
     .await
     .map_err(Error::from)?;
-    let kube_client =
-      kubernetes::get_client(k8s_api_url, shasta_k8s_secrets, socks5_proxy)
-        .await
-        .map_err(Error::from)?;
-    let cray_product_catalog = kubernetes::try_get_configmap(
-      kube_client,
-      crate::common::kubernetes::CRAY_PRODUCT_CATALOG_CONFIGMAP,
-    )
-    .await
-    .map_err(Error::from)?;
 
-    let cfs_configuration = utils::create_cfs_configuration_from_sat_file(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      socks5_proxy,
-      gitea_base_url,
-      gitea_token,
-      &cray_product_catalog,
-      &configuration_yaml,
-      dry_run,
-      site_name,
-      overwrite,
-    )
-    .await
-    .map_err(Error::from)?;
+    let (cfs_configuration, _) = self
+      .scope(utils::create_cfs_configuration_from_sat_file(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        socks5_proxy,
+        gitea_base_url,
+        gitea_token,
+        &cray_product_catalog,
+        &configuration_yaml,
+        dry_run,
+        site_name,
+        overwrite,
+        // The trait has no way to ignore protection
+        false,
+      ))
+      .await
+      .map_err(Error::from)?;
 
     Ok(cfs_configuration.into())
   }
@@ -285,7 +325,7 @@ impl SatTrait for ShastaClient {
       timestamps,
       dry_run,
     } = params;
-    let socks5_proxy = self.socks5_proxy.as_deref();
+    let socks5_proxy = self.socks5_proxy();
 
     // Transcode JSON -> YAML -> typed SAT image shape.
     let image_yaml: serde_yaml::Value =
@@ -302,45 +342,55 @@ impl SatTrait for ShastaClient {
       })?;
 
     // Live state the per-image creator depends on.
-    let shasta_k8s_secrets = fetch_shasta_k8s_secrets_from_vault(
-      vault_base_url,
+    let cray_product_catalog = product_catalog::fetch(
+      self,
       shasta_token,
-      site_name,
+      k8s_api_url,
+      &crate::KubeAuth::Vault {
+        base_url: vault_base_url.to_string(),
+        site_name: site_name.to_string(),
+      },
       socks5_proxy,
     )
     .await
     .map_err(Error::from)?;
-    let kube_client =
-      kubernetes::get_client(k8s_api_url, shasta_k8s_secrets, socks5_proxy)
-        .await
-        .map_err(Error::from)?;
-    let cray_product_catalog = kubernetes::try_get_configmap(
-      kube_client,
-      crate::common::kubernetes::CRAY_PRODUCT_CATALOG_CONFIGMAP,
-    )
-    .await
-    .map_err(Error::from)?;
 
-    let image = utils::images::i_create_image_from_sat_file_serde_yaml(
+    let context = ImageBuildContext {
       shasta_token,
-      &self.base_url,
-      &self.root_cert,
+      shasta_base_url: self.base_url(),
+      shasta_root_cert: self.root_cert(),
       socks5_proxy,
       vault_base_url,
       site_name,
       k8s_api_url,
-      &image_struct,
-      &cray_product_catalog,
+      cray_product_catalog: &cray_product_catalog,
       ansible_verbosity,
       ansible_passthrough,
-      &ref_lookup,
       debug_on_failure,
       dry_run,
       watch_logs,
       timestamps,
-    )
-    .await
-    .map_err(Error::from)?;
+      cfs_session_retry_policy: self.config().cfs_session_retry_policy(),
+      cfs_session_wait: crate::cfs::session::utils::DEFAULT_WAIT,
+      ims_job_wait: crate::ims::job::utils::DEFAULT_WAIT,
+      ims_public_key_selector: &crate::ims::PublicKeySelector::default(),
+    };
+    let (image, image_build_report) = self
+      .scope(utils::images::i_create_image_from_sat_file_serde_yaml(
+        &context,
+        &image_struct,
+        &ref_lookup,
+      ))
+      .await
+      .map_err(Error::from)?;
+
+    if image_build_report.retries() > 0 {
+      events::info(format!(
+        "Image '{}' built after {} CFS session(s)",
+        image_build_report.image_name,
+        image_build_report.attempt_vec.len()
+      ));
+    }
 
     Ok(image.into())
   }
@@ -360,7 +410,7 @@ impl SatTrait for ShastaClient {
       ansible_passthrough,
       dry_run,
     } = params;
-    let socks5_proxy = self.socks5_proxy.as_deref();
+    let socks5_proxy = self.socks5_proxy();
 
     let image_yaml: serde_yaml::Value =
       serde_json::from_value(image).map_err(|e| {
@@ -375,39 +425,49 @@ impl SatTrait for ShastaClient {
         ))
       })?;
 
-    let shasta_k8s_secrets = fetch_shasta_k8s_secrets_from_vault(
-      vault_base_url,
+    let cray_product_catalog = product_catalog::fetch(
+      self,
       shasta_token,
-      site_name,
+      k8s_api_url,
+      &crate::KubeAuth::Vault {
+        base_url: vault_base_url.to_string(),
+        site_name: site_name.to_string(),
+      },
       socks5_proxy,
     )
     .await
     .map_err(Error::from)?;
-    let kube_client =
-      kubernetes::get_client(k8s_api_url, shasta_k8s_secrets, socks5_proxy)
-        .await
-        .map_err(Error::from)?;
-    let cray_product_catalog = kubernetes::try_get_configmap(
-      kube_client,
-      crate::common::kubernetes::CRAY_PRODUCT_CATALOG_CONFIGMAP,
-    )
-    .await
-    .map_err(Error::from)?;
 
-    let cfs_session = utils::images::create_cfs_session_for_sat_image(
+    // Only the session is created: nothing is streamed, waited for or
+    // retried
+    let context = ImageBuildContext {
       shasta_token,
-      &self.base_url,
-      &self.root_cert,
+      shasta_base_url: self.base_url(),
+      shasta_root_cert: self.root_cert(),
       socks5_proxy,
-      &image_struct,
-      &cray_product_catalog,
+      vault_base_url,
+      site_name,
+      k8s_api_url,
+      cray_product_catalog: &cray_product_catalog,
       ansible_verbosity,
       ansible_passthrough,
-      &ref_lookup,
+      debug_on_failure: false,
       dry_run,
-    )
-    .await
-    .map_err(Error::from)?;
+      watch_logs: false,
+      timestamps: false,
+      cfs_session_retry_policy: self.config().cfs_session_retry_policy(),
+      cfs_session_wait: crate::cfs::session::utils::DEFAULT_WAIT,
+      ims_job_wait: crate::ims::job::utils::DEFAULT_WAIT,
+      ims_public_key_selector: &crate::ims::PublicKeySelector::default(),
+    };
+    let cfs_session = self
+      .scope(utils::images::create_cfs_session_for_sat_image(
+        &context,
+        &image_struct,
+        &ref_lookup,
+      ))
+      .await
+      .map_err(Error::from)?;
 
     Ok(cfs_session.into())
   }
@@ -420,17 +480,18 @@ impl SatTrait for ShastaClient {
       shasta_token,
       cfs_session_name,
     } = params;
-    let socks5_proxy = self.socks5_proxy.as_deref();
+    let socks5_proxy = self.socks5_proxy();
 
-    let cfs_session = crate::cfs::session::get_one(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      socks5_proxy,
-      &cfs_session_name.to_string(),
-    )
-    .await
-    .map_err(Error::from)?;
+    let cfs_session = self
+      .scope(crate::cfs::session::get_one(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        socks5_proxy,
+        &cfs_session_name.to_string(),
+      ))
+      .await
+      .map_err(Error::from)?;
 
     // Fail fast when the CFS session produced no image — there is
     // nothing to PATCH and the upstream `collect_and_stamp_image`
@@ -445,17 +506,18 @@ impl SatTrait for ShastaClient {
     // Image's `name`); in normal mode the IMS image is fetched by id and
     // its existing name is preserved. The non-dry-run path is what this
     // public entrypoint targets, so the empty placeholder is fine.
-    let image = utils::images::collect_and_stamp_image(
-      shasta_token,
-      &self.base_url,
-      &self.root_cert,
-      socks5_proxy,
-      &cfs_session,
-      "",
-      false,
-    )
-    .await
-    .map_err(Error::from)?;
+    let image = self
+      .scope(utils::images::collect_and_stamp_image(
+        shasta_token,
+        self.base_url(),
+        self.root_cert(),
+        socks5_proxy,
+        &cfs_session,
+        "",
+        false,
+      ))
+      .await
+      .map_err(Error::from)?;
 
     Ok(image.into())
   }
@@ -472,38 +534,32 @@ impl SatTrait for ShastaClient {
       reboot,
       dry_run,
     } = params;
-    let socks5_proxy = self.socks5_proxy.as_deref();
-
-    // The existing per-section function reads the entry out of
-    // `sat_file_yaml["session_templates"][...]`. Wrap our single entry
-    // in that shape rather than extracting the (intricate, 300-line)
-    // loop body — same code path, much smaller diff. The trade-off is
-    // that the audit log fires per-element instead of per-apply.
-    let session_template_yaml: serde_yaml::Value =
+    let socks5_proxy = self.socks5_proxy();
+
+    // The per-section function processes a whole `session_templates`
+    // section; ours has a single entry. The trade-off is that the audit
+    // log fires per-element instead of per-apply.
+    let session_template: utils::sessiontemplate::SessionTemplate =
       serde_json::from_value(session_template).map_err(|e| {
         Error::Message(format!(
-          "SAT session_template value is not a valid YAML mapping: {e}"
+          "SAT session_template value is not a valid session template: {e}"
         ))
       })?;
-    let mut wrapper = serde_yaml::Mapping::new();
-    wrapper.insert(
-      serde_yaml::Value::String("session_templates".to_string()),
-      serde_yaml::Value::Sequence(vec![session_template_yaml]),
-    );
-    let synthetic = serde_yaml::Value::Mapping(wrapper);
-
-    let (mut templates, mut sessions) =
-      utils::process_session_template_section_in_sat_file(
+
+    let (mut templates, mut sessions) = self
+      .scope(utils::process_session_template_section_in_sat_file(
         shasta_token,
-        &self.base_url,
-        &self.root_cert,
+        self.base_url(),
+        self.root_cert(),
         socks5_proxy,
         ref_lookup,
         hsm_group_available_vec,
-        synthetic,
+        std::slice::from_ref(&session_template),
         reboot,
+        RebootStrategy::default(),
+        false,
         dry_run,
-      )
+      ))
       .await
       .map_err(Error::from)?;
 
@@ -530,9 +586,13 @@ impl ApplyHwClusterPin for ShastaClient {
     create_target_hsm_group: bool,
     delete_empty_parent_hsm_group: bool,
   ) -> Result<(), Error> {
+    let execution_context =
+      crate::ExecutionContext::from_token(shasta_token).map_err(Error::from)?;
+
     crate::commands::apply_hw_cluster_pin::command::exec(
       self,
       shasta_token,
+      &execution_context,
       target_hsm_group_name,
       parent_hsm_group_name,
       pattern,
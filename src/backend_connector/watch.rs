@@ -0,0 +1,74 @@
+//! `ShastaClient::watch`: follow any [`Watchable`] resource through a
+//! [`ResourceRef`], for frontends that handle all resource types alike.
+
+use futures::{StreamExt, stream::BoxStream};
+
+use crate::{
+  ShastaClient, WaitOptions,
+  bos::BosSession,
+  cfs::v3::CfsSessionGetResponse,
+  error::Error,
+  hsm::component::types::Component,
+  ims::job::types::Job,
+  watch::{ResourceRef, WatchUpdate, Watchable, watch},
+};
+
+impl ShastaClient {
+  /// Follow `resource`, polling as `wait_options` says, and yield a
+  /// [`WatchUpdate`] when it is first fetched and then every time its
+  /// state changes.
+  ///
+  /// The stream ends after a terminal update, when
+  /// `wait_options.timeout` expires, or after the first error. See
+  /// [`crate::watch::watch`] for a typed equivalent.
+  #[must_use]
+  pub fn watch(
+    &self,
+    shasta_token: &str,
+    resource: ResourceRef,
+    wait_options: WaitOptions,
+  ) -> BoxStream<'static, Result<WatchUpdate, Error>> {
+    match &resource {
+      ResourceRef::CfsSession(id) => self
+        .watch_updates::<CfsSessionGetResponse>(
+          shasta_token,
+          id,
+          &resource,
+          wait_options,
+        ),
+      ResourceRef::BosSession(id) => self.watch_updates::<BosSession>(
+        shasta_token,
+        id,
+        &resource,
+        wait_options,
+      ),
+      ResourceRef::ImsJob(id) => {
+        self.watch_updates::<Job>(shasta_token, id, &resource, wait_options)
+      }
+      ResourceRef::HsmComponent(id) => self.watch_updates::<Component>(
+        shasta_token,
+        id,
+        &resource,
+        wait_options,
+      ),
+    }
+  }
+
+  /// [`watch`] resource `id` of type `T` as [`WatchUpdate`]s about
+  /// `resource`.
+  fn watch_updates<T: Watchable>(
+    &self,
+    shasta_token: &str,
+    id: &str,
+    resource: &ResourceRef,
+    wait_options: WaitOptions,
+  ) -> BoxStream<'static, Result<WatchUpdate, Error>> {
+    let resource = resource.clone();
+
+    watch::<T>(self.clone(), shasta_token, id, wait_options)
+      .map(move |result| {
+        result.map(|value| WatchUpdate::new(resource.clone(), &value))
+      })
+      .boxed()
+  }
+}
@@ -9,6 +9,8 @@
 //! - [`template`] — session templates (the reusable definition of "boot
 //!   this image, with this CFS configuration, against these nodes").
 //! - [`session`] — sessions (a single invocation of a template).
+//! - [`v1`] — legacy v1 templates and sessions, still found on older
+//!   systems; they convert into the v2 types for display and export.
 //!
 //! Liveness/readiness probes against the BOS service itself are exposed
 //! as the [`ShastaClient::bos_health_check`](crate::ShastaClient::bos_health_check)
@@ -63,5 +65,17 @@ pub mod tests;
 // Domain-root canonical names for the most commonly used BOS types.
 // Callers should prefer these over the deep `*::http_client::v2::types::*`
 // paths so an eventual v3 bump only needs to flip these re-exports.
-pub use session::http_client::v2::types::{BosSession, Operation};
+pub use session::http_client::v2::types::{BosSession, Operation, StatusLabel};
 pub use template::http_client::v2::types::{BootSet, BosSessionTemplate, Cfs};
+pub use wrapper::BosVersion;
+
+/// Legacy BOS v1 types, returned by the `ShastaClient::bos_*_v1_*`
+/// methods. Read-only in practice: convert them into the v2 types with
+/// `From` to display or export them; the v2 types keep every field BOS
+/// v2 still understands.
+pub mod v1 {
+  pub use super::session::http_client::v1::types::BosSession;
+  pub use super::template::http_client::v1::types::{
+    BootSet, BosSessionTemplate, Cfs, Link,
+  };
+}
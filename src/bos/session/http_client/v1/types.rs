@@ -0,0 +1,119 @@
+//! Wire-format types — mirror the upstream CSM BOS v1 API; field names and
+//! shapes are dictated by the API.
+//!
+//! Read-only: BOS v1 sessions are only parsed to be displayed or
+//! exported, through their conversion into the v2 [`v2::BosSession`].
+#![allow(missing_docs)]
+
+use serde::{Deserialize, Serialize};
+
+use crate::bos::session::http_client::v2::types as v2;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BosSession {
+  /// Session id. Not part of the `/bos/v1/session/{id}` body, filled in
+  /// from the path by `ShastaClient::bos_session_v1_get`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub id: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub operation: Option<String>,
+  #[serde(rename = "templateName", alias = "templateUuid")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub template_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub limit: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub job: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub complete: Option<bool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub in_progress: Option<bool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error_count: Option<u64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub start_time: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub stop_time: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub status_link: Option<String>,
+}
+
+/// The v2 equivalent of a v1 session. The `configure` operation, which
+/// v2 doesn't have, maps to no operation; the BOA `job` and the
+/// `status_link` are dropped.
+impl From<BosSession> for v2::BosSession {
+  fn from(session: BosSession) -> Self {
+    let status_opt = session.start_time.map(|start_time| {
+      let status = if session.complete.unwrap_or(false) {
+        v2::StatusLabel::Complete
+      } else if session.in_progress.unwrap_or(false) {
+        v2::StatusLabel::Running
+      } else {
+        v2::StatusLabel::Pending
+      };
+
+      v2::Status {
+        start_time,
+        end_time: session.stop_time,
+        status,
+        error: session
+          .error_count
+          .filter(|error_count| *error_count > 0)
+          .map(|error_count| format!("{error_count} errors")),
+      }
+    });
+
+    Self {
+      name: session.id,
+      tenant: None,
+      operation: session
+        .operation
+        .and_then(|operation| operation.parse().ok()),
+      template_name: session.template_name.unwrap_or_default(),
+      limit: session.limit,
+      stage: None,
+      components: None,
+      include_disabled: None,
+      status: status_opt,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn v1_session_converts_into_v2() {
+    let session: BosSession = serde_json::from_value(serde_json::json!({
+      "complete": true,
+      "error_count": 2,
+      "in_progress": false,
+      "job": "boa-6b9ff3f7-4c5c-4d8a-9b6b-8a3c1e1e2f10",
+      "operation": "reboot",
+      "start_time": "2021-03-04T10:12:51.426279Z",
+      "status_link": "/v1/session/6b9ff3f7/status",
+      "stop_time": "2021-03-04T10:31:07.153419Z",
+      "templateUuid": "cos-2.0.30-template",
+      "limit": "x1000c0s0b0n0"
+    }))
+    .unwrap();
+
+    let session = v2::BosSession::from(BosSession {
+      id: Some("6b9ff3f7".to_string()),
+      ..session
+    });
+    assert_eq!(session.name.as_deref(), Some("6b9ff3f7"));
+    assert_eq!(session.operation, Some(v2::Operation::Reboot));
+    assert_eq!(session.template_name, "cos-2.0.30-template");
+    let status = session.status.unwrap();
+    assert_eq!(status.status, v2::StatusLabel::Complete);
+    assert_eq!(status.error.as_deref(), Some("2 errors"));
+
+    let configure = v2::BosSession::from(BosSession {
+      operation: Some("configure".to_string()),
+      ..BosSession::default()
+    });
+    assert!(configure.operation.is_none() && configure.status.is_none());
+  }
+}
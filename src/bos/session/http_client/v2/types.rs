@@ -27,7 +27,7 @@ pub struct BosSession {
   pub status: Option<Status>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Operation {
   #[serde(rename = "boot")]
@@ -75,7 +75,7 @@ pub struct Status {
   pub error: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum StatusLabel {
   #[serde(rename = "pending")]
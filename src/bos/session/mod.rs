@@ -3,5 +3,11 @@
 //! Submodules:
 //!
 //! - [`http_client`] — `ShastaClient` methods for v1 and v2.
+//! - [`reboot`] — staged shutdown + boot of a template's nodes, and
+//!   its dry-run simulation.
+//! - [`utils`] — filtering of BOS session listings and cleanup of
+//!   stale sessions before a reboot.
 
 pub mod http_client;
+pub mod reboot;
+pub mod utils;
@@ -0,0 +1,790 @@
+//! Rebooting the nodes of a BOS session template.
+//!
+//! A BOS `reboot` session shuts the nodes down and boots them again in
+//! one go; when some nodes don't power off, the whole session stalls
+//! and the nodes may not come back. [`RebootStrategy::Staged`] splits
+//! it into a `shutdown` session, a PCS check that the nodes are off,
+//! and a `boot` session, each with its own timeout. Nodes that fail to
+//! power off are left out of the boot session instead of blocking the
+//! others.
+//!
+//! [`simulate`] works out, from read-only HSM, BSS and PCS data, what
+//! either strategy would do — which BOS sessions, in which order, on
+//! which nodes, and which image each node would boot — without
+//! creating anything; dry runs report it instead of the payloads.
+
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  time::Duration,
+};
+
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::{
+  ShastaClient,
+  bos::{BosSession, BosSessionTemplate, Operation, StatusLabel},
+  bss::types::BootParameters,
+  common::{events, poll::WaitOptions},
+  error::Error,
+  hsm::group::GroupExt,
+  pcs::power_status::types::{PowerState, PowerStatus},
+};
+
+/// How to reboot the nodes of a BOS session template.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RebootStrategy {
+  /// One BOS `reboot` session.
+  #[default]
+  Reboot,
+  /// BOS `shutdown` session, wait for PCS to report the nodes off, then
+  /// BOS `boot` session. See [`staged_reboot`].
+  Staged(StageTimeouts),
+}
+
+/// Timeouts of the stages of [`RebootStrategy::Staged`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageTimeouts {
+  /// How long, and how often, to wait for PCS to report every node off
+  /// after creating the `shutdown` session.
+  pub shutdown: WaitOptions,
+  /// How long, and how often, to wait for the `boot` session to
+  /// complete.
+  pub boot: WaitOptions,
+}
+
+impl Default for StageTimeouts {
+  /// 10 minutes to shut down and 30 minutes to boot, checking every
+  /// 10 s.
+  fn default() -> Self {
+    Self {
+      shutdown: WaitOptions::new(
+        Duration::from_secs(600),
+        Duration::from_secs(10),
+      ),
+      boot: WaitOptions::new(
+        Duration::from_secs(1800),
+        Duration::from_secs(10),
+      ),
+    }
+  }
+}
+
+/// What [`staged_reboot`] did.
+#[derive(Debug, Serialize)]
+pub struct StagedReboot {
+  /// BOS session template the nodes were rebooted with.
+  pub template_name: String,
+  /// BOS `shutdown` session.
+  pub shutdown_session: BosSession,
+  /// BOS `boot` session, `None` if no node powered off in time.
+  pub boot_session: Option<BosSession>,
+  /// Nodes still on when the shutdown stage timed out; left out of the
+  /// boot session.
+  pub not_powered_off: Vec<String>,
+  /// Whether the boot session completed before the boot stage timed
+  /// out.
+  pub boot_completed: bool,
+}
+
+impl StagedReboot {
+  /// Every node was shut down and booted in time.
+  #[must_use]
+  pub fn is_success(&self) -> bool {
+    self.not_powered_off.is_empty() && self.boot_completed
+  }
+}
+
+/// A BOS session [`simulate`] expects a reboot to create.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SimulatedBatch {
+  /// Operation of the session.
+  pub operation: Operation,
+  /// Nodes the session would act on.
+  pub xname_vec: Vec<String>,
+}
+
+/// What a reboot would do to one node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SimulatedNode {
+  /// Node xname.
+  pub xname: String,
+  /// Power state PCS reports, `None` if PCS doesn't report the node.
+  pub power_state: Option<PowerState>,
+  /// IMS image the node boots now according to BSS, `None` if BSS has
+  /// no boot parameters, or no image, for it.
+  pub current_image_id: Option<String>,
+  /// IMS image of the boot set targeting the node, `None` if the boot
+  /// set has no `path`.
+  pub target_image_id: Option<String>,
+}
+
+impl SimulatedNode {
+  /// Whether the reboot would boot the node into another image.
+  #[must_use]
+  pub fn changes_image(&self) -> bool {
+    self.current_image_id != self.target_image_id
+  }
+}
+
+/// What rebooting the nodes of a BOS session template would do, see
+/// [`simulate`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RebootSimulation {
+  /// BOS session template the nodes would be rebooted with.
+  pub template_name: String,
+  /// BOS sessions the reboot would create, in order. A staged reboot
+  /// is simulated as if every node powered off in time.
+  pub batches: Vec<SimulatedBatch>,
+  /// Nodes the template targets, sorted by xname.
+  pub nodes: Vec<SimulatedNode>,
+}
+
+impl RebootSimulation {
+  /// Simulation of rebooting, with `strategy`, the nodes in
+  /// `target_image_map` (xname to the image of the boot set targeting
+  /// it), given their current BSS boot parameters and PCS power status.
+  fn new(
+    template_name: String,
+    strategy: RebootStrategy,
+    target_image_map: BTreeMap<String, Option<String>>,
+    boot_parameters_vec: &[BootParameters],
+    power_status_vec: &[PowerStatus],
+  ) -> Self {
+    let xname_vec: Vec<String> = target_image_map.keys().cloned().collect();
+
+    let operation_vec = match strategy {
+      RebootStrategy::Reboot => vec![Operation::Reboot],
+      RebootStrategy::Staged(_) => vec![Operation::Shutdown, Operation::Boot],
+    };
+
+    let batches = operation_vec
+      .into_iter()
+      .map(|operation| SimulatedBatch {
+        operation,
+        xname_vec: xname_vec.clone(),
+      })
+      .collect();
+
+    let nodes = target_image_map
+      .into_iter()
+      .map(|(xname, target_image_id)| SimulatedNode {
+        power_state: power_status_vec
+          .iter()
+          .find(|power_status| power_status.xname == xname)
+          .and_then(|power_status| power_status.power_state),
+        current_image_id: boot_parameters_vec
+          .iter()
+          .find(|boot_parameters| boot_parameters.hosts.contains(&xname))
+          .map(BootParameters::get_boot_image)
+          .filter(|image_id| !image_id.is_empty()),
+        target_image_id,
+        xname,
+      })
+      .collect();
+
+    Self {
+      template_name,
+      batches,
+      nodes,
+    }
+  }
+
+  /// Nodes the reboot would boot into another image.
+  pub fn image_changes(&self) -> impl Iterator<Item = &SimulatedNode> {
+    self.nodes.iter().filter(|node| node.changes_image())
+  }
+}
+
+/// Image of the boot set targeting each node of `bos_sessiontemplate`,
+/// keyed by xname. Boot sets target their `node_list` plus the members
+/// of their `node_groups`; a node in several boot sets gets the image
+/// of the last one.
+fn target_image_map(
+  bos_sessiontemplate: &BosSessionTemplate,
+  hsm_group_member_map: &HashMap<String, Vec<String>>,
+) -> BTreeMap<String, Option<String>> {
+  let mut target_image_map = BTreeMap::new();
+
+  for boot_set in bos_sessiontemplate
+    .boot_sets
+    .iter()
+    .flat_map(|boot_sets| boot_sets.values())
+  {
+    let image_id_opt = boot_set.path.as_deref().map(|path| {
+      path
+        .trim_start_matches("s3://boot-images/")
+        .trim_end_matches("/manifest.json")
+        .to_string()
+    });
+
+    let xname_iter = boot_set
+      .node_groups
+      .iter()
+      .flatten()
+      .filter_map(|hsm_group| hsm_group_member_map.get(hsm_group))
+      .flatten()
+      .chain(boot_set.node_list.iter().flatten());
+
+    for xname in xname_iter {
+      target_image_map.insert(xname.clone(), image_id_opt.clone());
+    }
+  }
+
+  target_image_map
+}
+
+/// Create a BOS session running `operation` on the nodes of template
+/// `template_name`, restricted to `limit_opt` if given.
+async fn create_session(
+  client: &ShastaClient,
+  shasta_token: &str,
+  template_name: &str,
+  operation: Operation,
+  limit_opt: Option<String>,
+) -> Result<BosSession, Error> {
+  let bos_session = BosSession {
+    name: None,
+    tenant: None,
+    operation: Some(operation),
+    template_name: template_name.to_string(),
+    limit: limit_opt,
+    stage: None,
+    include_disabled: None,
+    status: None,
+    components: None,
+  };
+
+  let created = client
+    .bos_session_v2_post(shasta_token, bos_session)
+    .await?;
+  events::created("BOS session", created.name.as_deref().unwrap_or_default());
+
+  Ok(created)
+}
+
+/// Xnames `bos_sessiontemplate` boots: its boot sets' `node_list` plus
+/// the members of their `node_groups`.
+async fn get_target_xname_vec(
+  client: &ShastaClient,
+  shasta_token: &str,
+  bos_sessiontemplate: &BosSessionTemplate,
+) -> Result<Vec<String>, Error> {
+  let hsm_group_name_vec = bos_sessiontemplate.get_target_hsm();
+
+  let mut xname_set: BTreeSet<String> =
+    bos_sessiontemplate.get_target_xname().into_iter().collect();
+
+  if !hsm_group_name_vec.is_empty() {
+    xname_set.extend(
+      client
+        .hsm_group_get(shasta_token, Some(&hsm_group_name_vec), None)
+        .await?
+        .iter()
+        .flat_map(GroupExt::get_members),
+    );
+  }
+
+  Ok(xname_set.into_iter().collect())
+}
+
+/// Poll PCS, as `wait_options` says, until every node in `xname_vec` is
+/// off or the timeout expires. Returns the nodes still not off.
+async fn wait_powered_off(
+  client: &ShastaClient,
+  shasta_token: &str,
+  xname_vec: &[String],
+  wait_options: WaitOptions,
+) -> Result<Vec<String>, Error> {
+  let xname_ref_vec: Vec<&str> = xname_vec.iter().map(String::as_str).collect();
+  let deadline = Instant::now() + wait_options.timeout;
+  let mut delay = wait_options.poll_interval;
+
+  loop {
+    let not_powered_off: Vec<String> = client
+      .pcs_power_status_post(shasta_token, Some(&xname_ref_vec), None, None)
+      .await?
+      .status
+      .into_iter()
+      .filter(|power_status| {
+        !matches!(power_status.power_state, Some(PowerState::Off))
+      })
+      .map(|power_status| power_status.xname)
+      .collect();
+
+    if not_powered_off.is_empty() || Instant::now() + delay > deadline {
+      return Ok(not_powered_off);
+    }
+
+    tracing::debug!(
+      "{} node(s) still not powered off. Checking again in {} secs.",
+      not_powered_off.len(),
+      delay.as_secs()
+    );
+    tokio::time::sleep(delay).await;
+    delay = wait_options.next_delay(delay);
+  }
+}
+
+/// Poll BOS, as `wait_options` says, until session `bos_session_name`
+/// completes or the timeout expires. Returns whether it completed.
+async fn wait_session_complete(
+  client: &ShastaClient,
+  shasta_token: &str,
+  bos_session_name: &str,
+  wait_options: WaitOptions,
+) -> Result<bool, Error> {
+  let deadline = Instant::now() + wait_options.timeout;
+  let mut delay = wait_options.poll_interval;
+
+  loop {
+    let completed = client
+      .bos_session_v2_get(shasta_token, Some(bos_session_name))
+      .await?
+      .first()
+      .and_then(|bos_session| bos_session.status.as_ref())
+      .is_some_and(|status| status.status == StatusLabel::Complete);
+
+    if completed || Instant::now() + delay > deadline {
+      return Ok(completed);
+    }
+
+    tracing::debug!(
+      "BOS session '{bos_session_name}' not complete yet. Checking again in {} secs.",
+      delay.as_secs()
+    );
+    tokio::time::sleep(delay).await;
+    delay = wait_options.next_delay(delay);
+  }
+}
+
+/// Reboot the nodes of `bos_sessiontemplate` in stages: create a BOS
+/// `shutdown` session, wait as `timeouts.shutdown` says for PCS to
+/// report every node off, then create a BOS `boot` session limited to
+/// the nodes that powered off and wait as `timeouts.boot` says for it
+/// to complete.
+///
+/// Nodes that don't power off in time, or a boot session that doesn't
+/// complete in time, are reported in the returned [`StagedReboot`]
+/// rather than failing the call.
+///
+/// # Errors
+///
+/// Returns [`Error::Message`] if the template targets no nodes or has
+/// no name, or another [`Error`] variant on CSM, transport, or
+/// deserialization failure.
+pub async fn staged_reboot(
+  client: &ShastaClient,
+  shasta_token: &str,
+  bos_sessiontemplate: &BosSessionTemplate,
+  timeouts: StageTimeouts,
+) -> Result<StagedReboot, Error> {
+  let template_name = bos_sessiontemplate.name.clone().ok_or_else(|| {
+    Error::Message("BOS sessiontemplate has no name".to_string())
+  })?;
+
+  let xname_vec =
+    get_target_xname_vec(client, shasta_token, bos_sessiontemplate).await?;
+  if xname_vec.is_empty() {
+    return Err(Error::Message(format!(
+      "BOS sessiontemplate '{template_name}' targets no nodes"
+    )));
+  }
+
+  // Shutdown stage
+  events::step(format!(
+    "Shutting down {} node(s) of BOS sessiontemplate '{template_name}'",
+    xname_vec.len()
+  ));
+  let shutdown_session = create_session(
+    client,
+    shasta_token,
+    &template_name,
+    Operation::Shutdown,
+    None,
+  )
+  .await?;
+
+  let not_powered_off =
+    wait_powered_off(client, shasta_token, &xname_vec, timeouts.shutdown)
+      .await?;
+
+  if !not_powered_off.is_empty() {
+    events::warning(format!(
+      "{} node(s) not powered off after {}s, leaving them out of the boot session: {}",
+      not_powered_off.len(),
+      timeouts.shutdown.timeout.as_secs(),
+      not_powered_off.join(", ")
+    ));
+  }
+
+  let powered_off_vec: Vec<String> = xname_vec
+    .into_iter()
+    .filter(|xname| !not_powered_off.contains(xname))
+    .collect();
+
+  if powered_off_vec.is_empty() {
+    return Ok(StagedReboot {
+      template_name,
+      shutdown_session,
+      boot_session: None,
+      not_powered_off,
+      boot_completed: false,
+    });
+  }
+
+  // Boot stage
+  events::step(format!(
+    "Booting {} node(s) of BOS sessiontemplate '{template_name}'",
+    powered_off_vec.len()
+  ));
+  let boot_session = create_session(
+    client,
+    shasta_token,
+    &template_name,
+    Operation::Boot,
+    (!not_powered_off.is_empty()).then(|| powered_off_vec.join(",")),
+  )
+  .await?;
+
+  let boot_completed = wait_session_complete(
+    client,
+    shasta_token,
+    boot_session.name.as_deref().unwrap_or_default(),
+    timeouts.boot,
+  )
+  .await?;
+
+  if !boot_completed {
+    events::warning(format!(
+      "BOS session '{}' not complete after {}s",
+      boot_session.name.as_deref().unwrap_or_default(),
+      timeouts.boot.timeout.as_secs()
+    ));
+  }
+
+  Ok(StagedReboot {
+    template_name,
+    shutdown_session,
+    boot_session: Some(boot_session),
+    not_powered_off,
+    boot_completed,
+  })
+}
+
+/// What rebooting the nodes of `bos_sessiontemplate` with `strategy`
+/// would do: the BOS sessions it would create, in order, and the
+/// current and target image of every node. Only reads HSM, BSS and PCS.
+///
+/// # Errors
+///
+/// Returns [`Error::Message`] if the template has no name, or another
+/// [`Error`] variant on CSM, transport, or deserialization failure.
+pub async fn simulate(
+  client: &ShastaClient,
+  shasta_token: &str,
+  bos_sessiontemplate: &BosSessionTemplate,
+  strategy: RebootStrategy,
+) -> Result<RebootSimulation, Error> {
+  let template_name = bos_sessiontemplate.name.clone().ok_or_else(|| {
+    Error::Message("BOS sessiontemplate has no name".to_string())
+  })?;
+
+  let hsm_group_name_vec = bos_sessiontemplate.get_target_hsm();
+
+  let hsm_group_member_map: HashMap<String, Vec<String>> =
+    if hsm_group_name_vec.is_empty() {
+      HashMap::new()
+    } else {
+      client
+        .hsm_group_get(shasta_token, Some(&hsm_group_name_vec), None)
+        .await?
+        .iter()
+        .map(|hsm_group| (hsm_group.label.0.clone(), hsm_group.get_members()))
+        .collect()
+    };
+
+  let target_image_map =
+    target_image_map(bos_sessiontemplate, &hsm_group_member_map);
+
+  if target_image_map.is_empty() {
+    return Ok(RebootSimulation::new(
+      template_name,
+      strategy,
+      target_image_map,
+      &[],
+      &[],
+    ));
+  }
+
+  let xname_vec: Vec<String> = target_image_map.keys().cloned().collect();
+  let xname_ref_vec: Vec<&str> = xname_vec.iter().map(String::as_str).collect();
+
+  let (boot_parameters_vec, power_status_all) = tokio::try_join!(
+    client.bss_bootparameters_get(shasta_token, &xname_vec),
+    client.pcs_power_status_post(
+      shasta_token,
+      Some(&xname_ref_vec),
+      None,
+      None
+    ),
+  )?;
+
+  Ok(RebootSimulation::new(
+    template_name,
+    strategy,
+    target_image_map,
+    &boot_parameters_vec,
+    &power_status_all.status,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, method, path},
+  };
+
+  const TEST_PEM: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBhTCCASugAwIBAgIQIRi6zePL6mKjOipn+dNuaTAKBggqhkjOPQQDAjASMRAw\n\
+DgYDVQQKEwdBY21lIENvMB4XDTE3MTAyMDE5NDMwNloXDTE4MTAyMDE5NDMwNlow\n\
+EjEQMA4GA1UEChMHQWNtZSBDbzBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABD0d\n\
+7VNhbWvZLWPuj/RtHFjvtJBEwOkhbN/BnnE8rnZR8+sbwnc/KhCk3FhnpHZnQz7B\n\
+5aETbbIgmuvewdjvSBSjYzBhMA4GA1UdDwEB/wQEAwICpDATBgNVHSUEDDAKBggr\n\
+BgEFBQcDATAPBgNVHRMBAf8EBTADAQH/MCkGA1UdEQQiMCCCDmxvY2FsaG9zdDo1\n\
+NDUzgg4xMjcuMC4wLjE6NTQ1MzAKBggqhkjOPQQDAgNIADBFAiEA2zpJEPQyz6/l\n\
+Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
+6MF9+Yw1Yy0t\n\
+-----END CERTIFICATE-----\n";
+
+  const XNAME_VEC: [&str; 2] = ["x1000c0s0b0n0", "x1000c0s0b0n1"];
+
+  fn zinal_cos() -> BosSessionTemplate {
+    serde_json::from_value(serde_json::json!({
+      "name": "zinal-cos",
+      "boot_sets": { "compute": { "node_list": XNAME_VEC } }
+    }))
+    .unwrap()
+  }
+
+  fn timeouts() -> StageTimeouts {
+    let wait_options =
+      WaitOptions::new(Duration::from_millis(300), Duration::from_millis(50));
+
+    StageTimeouts {
+      shutdown: wait_options,
+      boot: wait_options,
+    }
+  }
+
+  /// Mock CSM where PCS reports `power_state_vec` for [`XNAME_VEC`] and
+  /// BOS expects one `shutdown` session.
+  async fn csm(power_state_vec: [&str; 2]) -> MockServer {
+    let server = MockServer::start().await;
+
+    let status_vec: Vec<serde_json::Value> = XNAME_VEC
+      .iter()
+      .zip(power_state_vec)
+      .map(|(xname, power_state)| {
+        serde_json::json!({
+          "xname": xname,
+          "powerState": power_state,
+          "supportedPowerTransitions": [],
+          "lastUpdated": "2026-01-01T00:00:00Z"
+        })
+      })
+      .collect();
+    Mock::given(method("POST"))
+      .and(path("/power-control/v1/power-status"))
+      .respond_with(
+        ResponseTemplate::new(200)
+          .set_body_json(serde_json::json!({ "status": status_vec })),
+      )
+      .mount(&server)
+      .await;
+
+    Mock::given(method("POST"))
+      .and(path("/bos/v2/sessions"))
+      .and(body_partial_json(
+        serde_json::json!({ "operation": "shutdown" }),
+      ))
+      .respond_with(ResponseTemplate::new(200).set_body_json(
+        serde_json::json!({
+          "name": "shutdown-1",
+          "template_name": "zinal-cos",
+          "operation": "shutdown"
+        }),
+      ))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    server
+  }
+
+  fn shasta_client(server: &MockServer) -> ShastaClient {
+    ShastaClient::new(server.uri(), TEST_PEM.as_bytes().to_vec(), None).unwrap()
+  }
+
+  #[tokio::test]
+  async fn staged_reboot_boots_nothing_when_no_node_powers_off() {
+    let server = csm(["on", "on"]).await;
+    Mock::given(method("POST"))
+      .and(path("/bos/v2/sessions"))
+      .and(body_partial_json(
+        serde_json::json!({ "operation": "boot" }),
+      ))
+      .respond_with(ResponseTemplate::new(500))
+      .expect(0)
+      .mount(&server)
+      .await;
+
+    let report =
+      staged_reboot(&shasta_client(&server), "token", &zinal_cos(), timeouts())
+        .await
+        .unwrap();
+
+    assert!(report.boot_session.is_none());
+    assert_eq!(report.not_powered_off, XNAME_VEC);
+    assert!(!report.is_success());
+  }
+
+  #[tokio::test]
+  async fn staged_reboot_boots_only_the_nodes_that_powered_off() {
+    let server = csm(["off", "on"]).await;
+    Mock::given(method("POST"))
+      .and(path("/bos/v2/sessions"))
+      .and(body_partial_json(serde_json::json!({
+        "operation": "boot",
+        "limit": "x1000c0s0b0n0"
+      })))
+      .respond_with(ResponseTemplate::new(200).set_body_json(
+        serde_json::json!({
+          "name": "boot-1",
+          "template_name": "zinal-cos",
+          "operation": "boot"
+        }),
+      ))
+      .expect(1)
+      .mount(&server)
+      .await;
+    Mock::given(method("GET"))
+      .and(path("/bos/v2/sessions/boot-1"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(
+        serde_json::json!({
+          "name": "boot-1",
+          "template_name": "zinal-cos",
+          "status": {
+            "start_time": "2026-01-01T00:00:00Z",
+            "status": "complete"
+          }
+        }),
+      ))
+      .mount(&server)
+      .await;
+
+    let report =
+      staged_reboot(&shasta_client(&server), "token", &zinal_cos(), timeouts())
+        .await
+        .unwrap();
+
+    assert_eq!(
+      report
+        .boot_session
+        .as_ref()
+        .and_then(|bos_session| bos_session.name.as_deref()),
+      Some("boot-1")
+    );
+    assert_eq!(report.not_powered_off, ["x1000c0s0b0n1"]);
+    assert!(report.boot_completed);
+    assert!(!report.is_success());
+  }
+
+  #[test]
+  fn simulation_reports_batches_and_image_changes() {
+    let bos_sessiontemplate: BosSessionTemplate =
+      serde_json::from_value(serde_json::json!({
+        "name": "zinal-cos",
+        "boot_sets": {
+          "compute": {
+            "path": "s3://boot-images/img-2/manifest.json",
+            "node_groups": ["zinal"]
+          },
+          "uan": {
+            "node_list": ["x1000c0s0b0n0"]
+          }
+        }
+      }))
+      .unwrap();
+    let hsm_group_member_map = HashMap::from([(
+      "zinal".to_string(),
+      vec!["x1000c0s1b0n0".to_string(), "x1000c0s1b0n1".to_string()],
+    )]);
+    let boot_parameters_vec: Vec<BootParameters> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "hosts": ["x1000c0s1b0n0"],
+          "params": "root=craycps-s3:s3://boot-images/img-1/rootfs:etag:dvs"
+        },
+        {
+          "hosts": ["x1000c0s1b0n1"],
+          "params": "root=craycps-s3:s3://boot-images/img-2/rootfs:etag:dvs"
+        }
+      ]))
+      .unwrap();
+    let power_status_vec: Vec<PowerStatus> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "xname": "x1000c0s1b0n0",
+          "powerState": "on",
+          "supportedPowerTransitions": [],
+          "lastUpdated": "2026-01-01T00:00:00Z"
+        }
+      ]))
+      .unwrap();
+
+    let simulation = RebootSimulation::new(
+      "zinal-cos".to_string(),
+      RebootStrategy::Staged(StageTimeouts::default()),
+      target_image_map(&bos_sessiontemplate, &hsm_group_member_map),
+      &boot_parameters_vec,
+      &power_status_vec,
+    );
+
+    let xname_vec: Vec<String> =
+      ["x1000c0s0b0n0", "x1000c0s1b0n0", "x1000c0s1b0n1"]
+        .map(String::from)
+        .to_vec();
+    assert_eq!(
+      simulation
+        .batches
+        .iter()
+        .map(|batch| (batch.operation, batch.xname_vec.clone()))
+        .collect::<Vec<_>>(),
+      vec![
+        (Operation::Shutdown, xname_vec.clone()),
+        (Operation::Boot, xname_vec),
+      ]
+    );
+    assert_eq!(
+      simulation.nodes[1],
+      SimulatedNode {
+        xname: "x1000c0s1b0n0".to_string(),
+        power_state: Some(PowerState::On),
+        current_image_id: Some("img-1".to_string()),
+        target_image_id: Some("img-2".to_string()),
+      }
+    );
+    assert_eq!(simulation.nodes[0].current_image_id, None);
+    assert_eq!(simulation.nodes[0].target_image_id, None);
+    assert_eq!(
+      simulation
+        .image_changes()
+        .map(|node| node.xname.as_str())
+        .collect::<Vec<_>>(),
+      vec!["x1000c0s1b0n0"]
+    );
+  }
+}
@@ -0,0 +1,409 @@
+//! Helpers built on top of [`crate::ShastaClient`]`::bos_session_*` methods.
+
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::{
+  bos::{BosSession, BosSessionTemplate, StatusLabel},
+  common::events,
+  error::Error,
+  filter::{Filter, Query},
+  hsm::group::GroupExt,
+};
+
+/// Filter a vector of BOS sessions in place by template name, HSM
+/// groups targeted by the template boot sets, start time range, status
+/// and an optional row limit. Sessions are sorted by start time; with a
+/// limit, only the most recent ones are kept.
+///
+/// `bos_sessiontemplate_vec` is only used to filter by HSM group; a
+/// session matches if every HSM group its template targets is in
+/// `hsm_group_name_vec`.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+#[allow(clippy::too_many_arguments)]
+pub fn filter(
+  bos_session_vec: &mut Vec<BosSession>,
+  bos_sessiontemplate_vec: &[BosSessionTemplate],
+  template_name_vec: &[String],
+  hsm_group_name_vec: &[String],
+  since_opt: Option<NaiveDateTime>,
+  until_opt: Option<NaiveDateTime>,
+  status_opt: Option<StatusLabel>,
+  limit_number_opt: Option<&u8>,
+) -> Result<(), Error> {
+  log::debug!("Filter BOS sessions");
+
+  if !template_name_vec.is_empty() {
+    bos_session_vec.retain(|bos_session| {
+      template_name_vec.contains(&bos_session.template_name)
+    });
+  }
+
+  if !hsm_group_name_vec.is_empty() {
+    let template_name_in_groups_vec: Vec<&str> = bos_sessiontemplate_vec
+      .iter()
+      .filter(|bos_sessiontemplate| {
+        let target_hsm_vec = bos_sessiontemplate.get_target_hsm();
+        !target_hsm_vec.is_empty()
+          && target_hsm_vec
+            .iter()
+            .all(|hsm_group| hsm_group_name_vec.contains(hsm_group))
+      })
+      .filter_map(|bos_sessiontemplate| bos_sessiontemplate.name.as_deref())
+      .collect();
+
+    bos_session_vec.retain(|bos_session| {
+      template_name_in_groups_vec.contains(&bos_session.template_name.as_str())
+    });
+  }
+
+  if let Some(status) = status_opt {
+    bos_session_vec.retain(|bos_session| {
+      bos_session
+        .status
+        .as_ref()
+        .is_some_and(|bos_session_status| bos_session_status.status == status)
+    });
+  }
+
+  let mut filter = Filter::All;
+  if let Some(since) = since_opt {
+    filter = Filter::and(filter, Filter::Since(since));
+  }
+  if let Some(until) = until_opt {
+    filter = Filter::and(filter, Filter::Until(until));
+  }
+
+  Query {
+    filter,
+    limit: limit_number_opt.map(|limit_number| usize::from(*limit_number)),
+    ..Query::default()
+  }
+  .apply(bos_session_vec)
+}
+
+/// Fetch BOS sessions and [`filter`] them. BOS session templates are
+/// only fetched when filtering by HSM group.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_and_filter(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  template_name_vec: &[String],
+  hsm_group_name_vec: &[String],
+  since_opt: Option<NaiveDateTime>,
+  until_opt: Option<NaiveDateTime>,
+  status_opt: Option<StatusLabel>,
+  limit_number_opt: Option<&u8>,
+) -> Result<Vec<BosSession>, Error> {
+  let (mut bos_session_vec, bos_sessiontemplate_vec) =
+    if hsm_group_name_vec.is_empty() {
+      (
+        client.bos_session_v2_get(shasta_token, None).await?,
+        Vec::new(),
+      )
+    } else {
+      tokio::try_join!(
+        client.bos_session_v2_get(shasta_token, None),
+        client.bos_template_v2_get_all(shasta_token),
+      )?
+    };
+
+  filter(
+    &mut bos_session_vec,
+    &bos_sessiontemplate_vec,
+    template_name_vec,
+    hsm_group_name_vec,
+    since_opt,
+    until_opt,
+    status_opt,
+    limit_number_opt,
+  )?;
+
+  Ok(bos_session_vec)
+}
+
+/// A pending or running BOS session targeting nodes a new BOS session
+/// is about to boot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleSession {
+  /// BOS session name.
+  pub name: String,
+  /// BOS session template the session was created from.
+  pub template_name: String,
+  /// Session status, `None` if BOS hasn't reported one yet.
+  pub status: Option<StatusLabel>,
+  /// Nodes targeted by both the stale and the new session.
+  pub xname_vec: Vec<String>,
+}
+
+/// Xnames targeted by `bos_sessiontemplate`: its boot sets' `node_list`
+/// plus the members of their `node_groups`.
+fn template_target_xname_set(
+  bos_sessiontemplate: &BosSessionTemplate,
+  hsm_group_member_map: &HashMap<String, Vec<String>>,
+) -> BTreeSet<String> {
+  bos_sessiontemplate
+    .get_target_hsm()
+    .iter()
+    .filter_map(|hsm_group| hsm_group_member_map.get(hsm_group))
+    .flatten()
+    .cloned()
+    .chain(bos_sessiontemplate.get_target_xname())
+    .collect()
+}
+
+/// Pending or running sessions in `bos_session_vec` booting any of
+/// `target_xname_vec`.
+///
+/// A session targets the nodes of its template, narrowed down to its
+/// `limit` (xnames and HSM group names, comma separated) if it has
+/// one. Sessions whose template is gone are skipped.
+#[must_use]
+pub fn find_stale(
+  target_xname_vec: &[String],
+  bos_session_vec: &[BosSession],
+  bos_sessiontemplate_vec: &[BosSessionTemplate],
+  hsm_group_member_map: &HashMap<String, Vec<String>>,
+) -> Vec<StaleSession> {
+  let target_xname_set: BTreeSet<&String> = target_xname_vec.iter().collect();
+
+  bos_session_vec
+    .iter()
+    .filter(|bos_session| {
+      bos_session.status.as_ref().is_none_or(|status| {
+        matches!(status.status, StatusLabel::Pending | StatusLabel::Running)
+      })
+    })
+    .filter_map(|bos_session| {
+      let bos_sessiontemplate =
+        bos_sessiontemplate_vec.iter().find(|bos_sessiontemplate| {
+          bos_sessiontemplate.name.as_deref()
+            == Some(bos_session.template_name.as_str())
+        })?;
+
+      let mut session_xname_set =
+        template_target_xname_set(bos_sessiontemplate, hsm_group_member_map);
+
+      if let Some(limit) = &bos_session.limit {
+        let limit_xname_set: BTreeSet<String> = limit
+          .split(',')
+          .map(str::trim)
+          .flat_map(|limit_item| {
+            hsm_group_member_map
+              .get(limit_item)
+              .cloned()
+              .unwrap_or_else(|| vec![limit_item.to_string()])
+          })
+          .collect();
+        session_xname_set.retain(|xname| limit_xname_set.contains(xname));
+      }
+
+      let xname_vec: Vec<String> = session_xname_set
+        .into_iter()
+        .filter(|xname| target_xname_set.contains(xname))
+        .collect();
+
+      if xname_vec.is_empty() {
+        return None;
+      }
+
+      Some(StaleSession {
+        name: bos_session.name.clone().unwrap_or_default(),
+        template_name: bos_session.template_name.clone(),
+        status: bos_session.status.as_ref().map(|status| status.status),
+        xname_vec,
+      })
+    })
+    .collect()
+}
+
+/// Delete the pending or running BOS sessions booting any node
+/// `bos_sessiontemplate` targets, so a new session created from it
+/// doesn't race with them. See [`find_stale`].
+///
+/// In `dry_run` nothing is deleted. Returns the sessions deleted (or
+/// that would be deleted).
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn cancel_stale(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  bos_sessiontemplate: &BosSessionTemplate,
+  dry_run: bool,
+) -> Result<Vec<StaleSession>, Error> {
+  let (bos_session_vec, bos_sessiontemplate_vec, hsm_group_vec) = tokio::try_join!(
+    client.bos_session_v2_get(shasta_token, None),
+    client.bos_template_v2_get_all(shasta_token),
+    client.hsm_group_get_all(shasta_token),
+  )?;
+
+  let hsm_group_member_map: HashMap<String, Vec<String>> = hsm_group_vec
+    .iter()
+    .map(|hsm_group| (hsm_group.label.0.clone(), hsm_group.get_members()))
+    .collect();
+
+  let target_xname_vec: Vec<String> =
+    template_target_xname_set(bos_sessiontemplate, &hsm_group_member_map)
+      .into_iter()
+      .collect();
+
+  let stale_session_vec = find_stale(
+    &target_xname_vec,
+    &bos_session_vec,
+    &bos_sessiontemplate_vec,
+    &hsm_group_member_map,
+  );
+
+  for stale_session in &stale_session_vec {
+    if !dry_run {
+      client
+        .bos_session_v2_delete(shasta_token, &stale_session.name)
+        .await?;
+    }
+
+    events::deleted("BOS session", &stale_session.name);
+  }
+
+  Ok(stale_session_vec)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn filter_by_template_group_status_and_time() {
+    let mut bos_session_vec: Vec<BosSession> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "name": "old",
+          "template_name": "zinal-cos",
+          "status": { "start_time": "2024-01-01T00:00:00Z", "status": "complete" }
+        },
+        {
+          "name": "new",
+          "template_name": "zinal-cos",
+          "status": { "start_time": "2024-06-01T00:00:00Z", "status": "complete" }
+        },
+        {
+          "name": "running",
+          "template_name": "zinal-cos",
+          "status": { "start_time": "2024-06-02T00:00:00Z", "status": "running" }
+        },
+        {
+          "name": "other",
+          "template_name": "eiger-cos",
+          "status": { "start_time": "2024-06-01T00:00:00Z", "status": "complete" }
+        }
+      ]))
+      .unwrap();
+    let bos_sessiontemplate_vec: Vec<BosSessionTemplate> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "name": "zinal-cos",
+          "boot_sets": { "compute": { "node_groups": ["zinal"] } }
+        },
+        {
+          "name": "eiger-cos",
+          "boot_sets": { "compute": { "node_groups": ["eiger"] } }
+        }
+      ]))
+      .unwrap();
+
+    filter(
+      &mut bos_session_vec,
+      &bos_sessiontemplate_vec,
+      &[],
+      &["zinal".to_string()],
+      Some(
+        NaiveDateTime::parse_from_str(
+          "2024-03-01T00:00:00",
+          "%Y-%m-%dT%H:%M:%S",
+        )
+        .unwrap(),
+      ),
+      None,
+      Some(StatusLabel::Complete),
+      None,
+    )
+    .unwrap();
+
+    assert_eq!(
+      bos_session_vec
+        .iter()
+        .map(|bos_session| bos_session.name.as_deref().unwrap_or_default())
+        .collect::<Vec<_>>(),
+      vec!["new"]
+    );
+  }
+
+  #[test]
+  fn find_stale_matches_running_sessions_on_shared_nodes() {
+    let bos_session_vec: Vec<BosSession> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "name": "running",
+          "template_name": "zinal-cos",
+          "status": { "start_time": "2024-06-01T00:00:00Z", "status": "running" }
+        },
+        {
+          "name": "complete",
+          "template_name": "zinal-cos",
+          "status": { "start_time": "2024-06-01T00:00:00Z", "status": "complete" }
+        },
+        {
+          "name": "limited",
+          "template_name": "zinal-cos",
+          "limit": "x1000c0s0b0n1",
+          "status": { "start_time": "2024-06-01T00:00:00Z", "status": "pending" }
+        }
+      ]))
+      .unwrap();
+    let bos_sessiontemplate_vec: Vec<BosSessionTemplate> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "name": "zinal-cos",
+          "boot_sets": { "compute": { "node_groups": ["zinal"] } }
+        }
+      ]))
+      .unwrap();
+    let hsm_group_member_map = HashMap::from([(
+      "zinal".to_string(),
+      vec!["x1000c0s0b0n0".to_string(), "x1000c0s0b0n1".to_string()],
+    )]);
+
+    let stale_session_vec = find_stale(
+      &["x1000c0s0b0n0".to_string()],
+      &bos_session_vec,
+      &bos_sessiontemplate_vec,
+      &hsm_group_member_map,
+    );
+
+    assert_eq!(
+      stale_session_vec,
+      vec![StaleSession {
+        name: "running".to_string(),
+        template_name: "zinal-cos".to_string(),
+        status: Some(StatusLabel::Running),
+        xname_vec: vec!["x1000c0s0b0n0".to_string()],
+      }]
+    );
+  }
+}
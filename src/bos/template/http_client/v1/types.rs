@@ -7,6 +7,8 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use crate::bos::template::http_client::v2::types as v2;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Link {
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -202,3 +204,108 @@ impl BosSessionTemplate {
     }
   }
 }
+
+impl From<Link> for v2::Link {
+  fn from(link: Link) -> Self {
+    Self {
+      rel: link.rel,
+      href: link.href,
+    }
+  }
+}
+
+/// Only the CFS configuration name carries over: v2 templates can't
+/// point CFS at a git repository directly.
+impl From<Cfs> for v2::Cfs {
+  fn from(cfs: Cfs) -> Self {
+    Self {
+      configuration: cfs.configuration,
+    }
+  }
+}
+
+/// The boot and shutdown ordinals and the network, which BOS v2 no
+/// longer uses, are dropped.
+impl From<BootSet> for v2::BootSet {
+  fn from(boot_set: BootSet) -> Self {
+    Self {
+      name: boot_set.name,
+      path: boot_set.path,
+      cfs: None,
+      r#type: boot_set.r#type,
+      etag: boot_set.etag,
+      kernel_parameters: boot_set.kernel_parameters,
+      node_list: boot_set.node_list,
+      node_roles_groups: boot_set.node_roles_groups,
+      node_groups: boot_set.node_groups,
+      arch: None,
+      rootfs_provider: boot_set.rootfs_provider,
+      rootfs_provider_passthrough: boot_set.rootfs_provider_passthrough,
+    }
+  }
+}
+
+/// The v2 equivalent of a v1 template, for display and export. The
+/// `templateUrl`, `cfs_url`, `cfs_branch` and `partition` fields, which
+/// have no v2 counterpart, are dropped.
+impl From<BosSessionTemplate> for v2::BosSessionTemplate {
+  fn from(template: BosSessionTemplate) -> Self {
+    Self {
+      name: Some(template.name),
+      tenant: None,
+      description: template.description,
+      enable_cfs: template.enable_cfs,
+      cfs: template.cfs.map(v2::Cfs::from),
+      boot_sets: template.boot_sets.map(|boot_sets| {
+        boot_sets
+          .into_iter()
+          .map(|(name, boot_set)| (name, v2::BootSet::from(boot_set)))
+          .collect()
+      }),
+      links: template
+        .links
+        .map(|links| links.into_iter().map(v2::Link::from).collect()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn v1_template_converts_into_v2() {
+    let template: BosSessionTemplate =
+      serde_json::from_value(serde_json::json!({
+        "name": "cos-2.0.30-template",
+        "templateUrl": "",
+        "cfs_url": "https://api-gw-service-nmn.local/vcs/cray/cos-config-management.git",
+        "enable_cfs": true,
+        "cfs": {"configuration": "cos-config-2.0.30"},
+        "partition": "",
+        "boot_sets": {
+          "compute": {
+            "boot_ordinal": 2,
+            "etag": "44d82a32878a3abbe461c38b071c55bc",
+            "kernel_parameters": "console=ttyS0,115200 ip=dhcp",
+            "network": "nmn",
+            "node_roles_groups": ["Compute"],
+            "path": "s3://boot-images/2105dd38/manifest.json",
+            "rootfs_provider": "cpss3",
+            "type": "s3"
+          }
+        }
+      }))
+      .unwrap();
+
+    let template = v2::BosSessionTemplate::from(template);
+    assert_eq!(template.name.as_deref(), Some("cos-2.0.30-template"));
+    assert_eq!(template.configuration_name(), Some("cos-config-2.0.30"));
+    let boot_set = &template.boot_sets.as_ref().unwrap()["compute"];
+    assert_eq!(
+      boot_set.node_roles_groups,
+      Some(vec!["Compute".to_string()])
+    );
+    assert_eq!(serde_json::to_value(boot_set).unwrap().get("network"), None);
+  }
+}
@@ -4,7 +4,10 @@
 //! Submodules:
 //!
 //! - [`http_client`] — `ShastaClient` methods for v1 and v2.
+//! - [`schema`] — check templates against the BOS v2 schema before
+//!   they are PUT.
 //! - [`utils`] — helpers built on top of the raw client.
 
 pub mod http_client;
+pub mod schema;
 pub mod utils;
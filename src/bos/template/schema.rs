@@ -0,0 +1,337 @@
+//! Check BOS v2 session templates against the BOS schema before they
+//! are PUT.
+//!
+//! BOS answers a template it doesn't accept with a single 400 about the
+//! first problem it hits. [`Schema::check`] reports every problem at
+//! once, naming the offending field.
+//!
+//! Only one schema is supported: [`SCHEMA`], transcribed from the BOS v2
+//! API spec in `src/bos/csm_api_docs.yaml`. No schema is vendored per
+//! CSM release, since that spec is the only one csm-rs has, so templates
+//! are checked against it whatever BOS release the system runs, and the
+//! BOS version ([`crate::bos::BosVersion`]) isn't looked up for it.
+
+use std::{fmt, sync::LazyLock};
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{bos::BosSessionTemplate, error::Error};
+
+/// Pattern of session template and boot set names.
+static NAME_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^[a-zA-Z0-9](?:[-._a-zA-Z0-9]{0,125}[a-zA-Z0-9])?$")
+    .expect("valid regex")
+});
+
+/// BOS session template schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schema {
+  /// Session template fields BOS accepts on PUT.
+  pub template_fields: &'static [&'static str],
+  /// Session template fields BOS sets itself and rejects on PUT.
+  pub read_only_fields: &'static [&'static str],
+  /// Boot set fields BOS accepts.
+  pub boot_set_fields: &'static [&'static str],
+  /// Accepted boot set `arch` values.
+  pub arch_values: &'static [&'static str],
+}
+
+/// The BOS v2 session template schema.
+pub static SCHEMA: Schema = Schema {
+  template_fields: &["description", "enable_cfs", "cfs", "boot_sets"],
+  read_only_fields: &["name", "tenant", "links"],
+  boot_set_fields: &[
+    "name",
+    "path",
+    "cfs",
+    "type",
+    "etag",
+    "kernel_parameters",
+    "node_list",
+    "node_roles_groups",
+    "node_groups",
+    "arch",
+    "rootfs_provider",
+    "rootfs_provider_passthrough",
+  ],
+  arch_values: &["X86", "ARM", "Other", "Unknown"],
+};
+
+/// One way a session template doesn't match a [`Schema`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaViolation {
+  /// Dotted path of the offending field, e.g. `boot_sets.compute.path`.
+  pub field: String,
+  /// What is wrong with it.
+  pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: {}", self.field, self.message)
+  }
+}
+
+impl Schema {
+  /// Every way `bos_sessiontemplate`, to be PUT as `template_name`,
+  /// doesn't match this schema.
+  #[must_use]
+  pub fn violations(
+    &self,
+    template_name: &str,
+    bos_sessiontemplate: &BosSessionTemplate,
+  ) -> Vec<SchemaViolation> {
+    let mut violation_vec = Vec::new();
+    let mut violation = |field: String, message: &str| {
+      violation_vec.push(SchemaViolation {
+        field,
+        message: message.to_string(),
+      });
+    };
+
+    if !NAME_PATTERN.is_match(template_name) {
+      violation(
+        "name".to_string(),
+        "must be 1-127 letters, digits, '.', '-' or '_', starting and ending with a letter or digit",
+      );
+    }
+
+    let Ok(Value::Object(template)) = serde_json::to_value(bos_sessiontemplate)
+    else {
+      violation(String::new(), "not a JSON object");
+      return violation_vec;
+    };
+
+    for (field, value) in &template {
+      if self.read_only_fields.contains(&field.as_str()) {
+        violation(field.clone(), "read-only, leave it unset");
+      } else if !self.template_fields.contains(&field.as_str()) {
+        violation(field.clone(), "unknown field");
+      } else if field == "description" {
+        check_len(&mut violation, field, value, 1, 1023);
+      } else if field == "cfs" {
+        check_cfs(&mut violation, field, value);
+      }
+    }
+
+    let boot_set_map = match template.get("boot_sets") {
+      Some(Value::Object(boot_set_map)) => boot_set_map,
+      _ => {
+        violation("boot_sets".to_string(), "required");
+        return violation_vec;
+      }
+    };
+
+    if boot_set_map.is_empty() || boot_set_map.len() > 127 {
+      violation("boot_sets".to_string(), "must have 1-127 boot sets");
+    }
+
+    for (boot_set_name, boot_set) in boot_set_map {
+      let prefix = format!("boot_sets.{boot_set_name}");
+
+      if !NAME_PATTERN.is_match(boot_set_name) {
+        violation(
+          prefix.clone(),
+          "boot set name must be 1-127 letters, digits, '.', '-' or '_', starting and ending with a letter or digit",
+        );
+      }
+
+      let Value::Object(boot_set) = boot_set else {
+        violation(prefix, "not a JSON object");
+        continue;
+      };
+
+      for (field, value) in boot_set {
+        let path = format!("{prefix}.{field}");
+        match field.as_str() {
+          _ if !self.boot_set_fields.contains(&field.as_str()) => {
+            violation(path, "unknown field");
+          }
+          "path" => check_len(&mut violation, &path, value, 1, 4095),
+          "type" => check_len(&mut violation, &path, value, 1, 127),
+          "kernel_parameters" | "rootfs_provider_passthrough" => {
+            check_len(&mut violation, &path, value, 0, 4096);
+          }
+          "rootfs_provider" => check_len(&mut violation, &path, value, 1, 511),
+          "arch"
+            if !value
+              .as_str()
+              .is_some_and(|arch| self.arch_values.contains(&arch)) =>
+          {
+            violation(
+              path,
+              &format!("must be one of {}", self.arch_values.join(", ")),
+            );
+          }
+          "cfs" => check_cfs(&mut violation, &path, value),
+          _ => {}
+        }
+      }
+
+      for field in ["path", "type"] {
+        if !boot_set.contains_key(field) {
+          violation(format!("{prefix}.{field}"), "required");
+        }
+      }
+
+      if !["node_list", "node_roles_groups", "node_groups"]
+        .iter()
+        .any(|field| boot_set.contains_key(*field))
+      {
+        violation(
+          prefix,
+          "needs one of node_list, node_roles_groups or node_groups",
+        );
+      }
+    }
+
+    violation_vec
+  }
+
+  /// Fail if `bos_sessiontemplate`, to be PUT as `template_name`,
+  /// doesn't match this schema.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::BosTemplateSchema`] listing every
+  /// [`SchemaViolation`].
+  pub fn check(
+    &self,
+    template_name: &str,
+    bos_sessiontemplate: &BosSessionTemplate,
+  ) -> Result<(), Error> {
+    let violation_vec = self.violations(template_name, bos_sessiontemplate);
+
+    if violation_vec.is_empty() {
+      return Ok(());
+    }
+
+    Err(Error::BosTemplateSchema {
+      template: template_name.to_string(),
+      detail: violation_vec
+        .iter()
+        .map(|violation| format!("  - {violation}"))
+        .collect::<Vec<_>>()
+        .join("\n"),
+    })
+  }
+}
+
+/// Check `value` is a string of `min`-`max` characters.
+fn check_len(
+  violation: &mut impl FnMut(String, &str),
+  field: &str,
+  value: &Value,
+  min: usize,
+  max: usize,
+) {
+  match value.as_str().map(|s| s.chars().count()) {
+    Some(len) if (min..=max).contains(&len) => {}
+    Some(_) => {
+      violation(
+        field.to_string(),
+        &format!("must be {min}-{max} characters"),
+      );
+    }
+    None => violation(field.to_string(), "must be a string"),
+  }
+}
+
+/// Check a `cfs` object only sets a `configuration` of up to 127
+/// characters.
+fn check_cfs(
+  violation: &mut impl FnMut(String, &str),
+  field: &str,
+  value: &Value,
+) {
+  let Value::Object(cfs) = value else {
+    violation(field.to_string(), "not a JSON object");
+    return;
+  };
+
+  for (cfs_field, cfs_value) in cfs {
+    let path = format!("{field}.{cfs_field}");
+    if cfs_field == "configuration" {
+      check_len(violation, &path, cfs_value, 0, 127);
+    } else {
+      violation(path, "unknown field");
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::*;
+  use crate::bos::{BootSet, Cfs};
+
+  fn boot_set() -> BootSet {
+    BootSet {
+      name: Some("compute".to_string()),
+      path: Some("s3://boot-images/1234/manifest.json".to_string()),
+      cfs: Some(Cfs {
+        configuration: Some("cos-config".to_string()),
+      }),
+      r#type: Some("s3".to_string()),
+      etag: None,
+      kernel_parameters: None,
+      node_list: None,
+      node_roles_groups: None,
+      node_groups: Some(vec!["compute".to_string()]),
+      arch: Some("X86".to_string()),
+      rootfs_provider: None,
+      rootfs_provider_passthrough: None,
+    }
+  }
+
+  fn template(boot_set: BootSet) -> BosSessionTemplate {
+    BosSessionTemplate {
+      name: None,
+      tenant: None,
+      description: None,
+      enable_cfs: Some(true),
+      cfs: None,
+      boot_sets: Some(HashMap::from([("compute".to_string(), boot_set)])),
+      links: None,
+    }
+  }
+
+  #[test]
+  fn schema_reports_every_violation() {
+    let schema = &SCHEMA;
+
+    assert!(
+      schema
+        .check("compute-template", &template(boot_set()))
+        .is_ok()
+    );
+
+    let mut bad_boot_set = boot_set();
+    bad_boot_set.path = None;
+    bad_boot_set.arch = Some("x86_64".to_string());
+    bad_boot_set.node_groups = None;
+    let mut bad_template = template(bad_boot_set);
+    bad_template.tenant = Some("tenant-a".to_string());
+
+    let mut field_vec: Vec<String> = schema
+      .violations("-compute", &bad_template)
+      .into_iter()
+      .map(|violation| violation.field)
+      .collect();
+    field_vec.sort();
+
+    assert_eq!(
+      field_vec,
+      [
+        "boot_sets.compute",
+        "boot_sets.compute.arch",
+        "boot_sets.compute.path",
+        "name",
+        "tenant",
+      ]
+    );
+  }
+}
@@ -1,9 +1,16 @@
 //! Helpers built on top of [`crate::ShastaClient`]`::bos_template_*` methods.
 
 use crate::{
-  bos::template::http_client::v2::types::BosSessionTemplate, error::Error,
+  bos::template::http_client::v2::types::{BootSet, BosSessionTemplate},
+  common::{
+    events,
+    execution_context::{self, ExecutionContext},
+  },
+  error::Error,
+  filter::{Filter, Page, Query, configuration_glob},
+  ims::image::http_client::types::{Image, Link},
 };
-use globset::Glob;
+use serde::Serialize;
 
 /// Filter a vector of BOS session templates in place by configuration
 /// glob, target HSM groups, target xnames, and an optional row limit.
@@ -23,48 +30,78 @@ pub fn filter(
 ) -> Result<Vec<BosSessionTemplate>, Error> {
   log::debug!("Filter BOS sessiontemplates");
 
-  if let Some(configuration_name_pattern) = configuration_name_pattern_opt {
-    let glob = Glob::new(configuration_name_pattern)?.compile_matcher();
+  retain_targets(
+    bos_sessiontemplate_vec,
+    target_hsm_group_name_vec,
+    xname_vec,
+  );
 
-    bos_sessiontemplate_vec.retain(|sessiontemplate| {
-      sessiontemplate
-        .configuration_name()
-        .is_some_and(|configuration_name| glob.is_match(configuration_name))
-    });
+  // Filter by configuration name and limit the number of results to
+  // return to client
+  Query {
+    filter: configuration_name_pattern_opt
+      .map_or(Filter::All, configuration_glob),
+    limit: limit_number_opt.map(|limit_number| usize::from(*limit_number)),
+    ..Query::default()
   }
+  .apply(bos_sessiontemplate_vec)?;
 
-  // Filter by list of HSM group or xnames as target
-  if !target_hsm_group_name_vec.is_empty() || !xname_vec.is_empty() {
-    bos_sessiontemplate_vec.retain(|bos_sessiontemplate| {
-      let bos_sessiontemplate_target_hsm = bos_sessiontemplate.get_target_hsm();
-      let bos_sessiontemplate_target_xname =
-        bos_sessiontemplate.get_target_xname();
+  Ok(bos_sessiontemplate_vec.clone())
+}
 
-      !bos_sessiontemplate_target_hsm.is_empty()
-        && bos_sessiontemplate_target_hsm
-          .iter()
-          .all(|bos_st_hsm_group| {
-            target_hsm_group_name_vec.iter().any(|target_hsm_group| {
-              bos_st_hsm_group.contains(target_hsm_group)
-            })
-          })
-        || !bos_sessiontemplate_target_xname.is_empty()
-          && bos_sessiontemplate_target_xname
-            .iter()
-            .all(|target_xname| xname_vec.contains(target_xname))
-    });
-  }
+/// Like [`filter`], but keep the BOS session templates matching `query`
+/// and return the `page_size` following [`Query::after`] instead of
+/// the last `limit`. Feed [`Page::next_cursor`] back through
+/// [`Query::after`] to get the next page.
+///
+/// # Errors
+///
+/// Returns [`Error::GlobError`] if a glob pattern in `query` is
+/// invalid.
+pub fn filter_page(
+  mut bos_sessiontemplate_vec: Vec<BosSessionTemplate>,
+  target_hsm_group_name_vec: &[String],
+  xname_vec: &[String],
+  query: &Query,
+  page_size: usize,
+) -> Result<Page<BosSessionTemplate>, Error> {
+  retain_targets(
+    &mut bos_sessiontemplate_vec,
+    target_hsm_group_name_vec,
+    xname_vec,
+  );
+
+  query.page(bos_sessiontemplate_vec, page_size)
+}
 
-  if let Some(limit_number) = limit_number_opt {
-    // Limiting the number of results to return to client
-    *bos_sessiontemplate_vec = bos_sessiontemplate_vec
-      [bos_sessiontemplate_vec
-        .len()
-        .saturating_sub(*limit_number as usize)..]
-      .to_vec();
+/// Filter by list of HSM group or xnames as target
+fn retain_targets(
+  bos_sessiontemplate_vec: &mut Vec<BosSessionTemplate>,
+  target_hsm_group_name_vec: &[String],
+  xname_vec: &[String],
+) {
+  if target_hsm_group_name_vec.is_empty() && xname_vec.is_empty() {
+    return;
   }
 
-  Ok(bos_sessiontemplate_vec.clone())
+  bos_sessiontemplate_vec.retain(|bos_sessiontemplate| {
+    let bos_sessiontemplate_target_hsm = bos_sessiontemplate.get_target_hsm();
+    let bos_sessiontemplate_target_xname =
+      bos_sessiontemplate.get_target_xname();
+
+    !bos_sessiontemplate_target_hsm.is_empty()
+      && bos_sessiontemplate_target_hsm
+        .iter()
+        .all(|bos_st_hsm_group| {
+          target_hsm_group_name_vec.iter().any(|target_hsm_group| {
+            bos_st_hsm_group.contains(target_hsm_group)
+          })
+        })
+      || !bos_sessiontemplate_target_xname.is_empty()
+        && bos_sessiontemplate_target_xname
+          .iter()
+          .all(|target_xname| xname_vec.contains(target_xname))
+  });
 }
 
 /// Retain only BOS session templates whose `configuration` equals the
@@ -129,10 +166,341 @@ pub fn get_image_id_cfs_configuration_target_tuple_vec(
   image_id_cfs_configuration_from_bos_sessiontemplate
 }
 
+/// Map an IMS image architecture (`x86_64`, `aarch64`) to the value BOS
+/// expects in a boot set's `arch` field.
+fn bos_arch_for_ims_arch(ims_arch: &str) -> Option<&'static str> {
+  match ims_arch {
+    "x86_64" => Some("X86"),
+    "aarch64" => Some("ARM"),
+    _ => None,
+  }
+}
+
+/// `true` when a boot set `arch` of `X86` or `ARM` contradicts the IMS
+/// image architecture. `Other`, `Unknown` and unrecognised IMS
+/// architectures are never reported.
+fn is_arch_mismatch(boot_set_arch: &str, image_arch: &str) -> bool {
+  let checked = ["X86", "ARM"]
+    .iter()
+    .any(|arch| arch.eq_ignore_ascii_case(boot_set_arch));
+
+  checked
+    && bos_arch_for_ims_arch(image_arch)
+      .is_some_and(|expected| !expected.eq_ignore_ascii_case(boot_set_arch))
+}
+
+/// Check a boot set against the IMS image it is going to boot.
+///
+/// The boot set `arch` must match the image architecture (`X86` for
+/// `x86_64`, `ARM` for `aarch64`); the check is skipped when either side
+/// is missing or the boot set uses `Other`/`Unknown`. The boot set
+/// `path` and `etag`, when set, must equal the image's S3 manifest link,
+/// and any `s3://` location embedded in `rootfs_provider_passthrough`
+/// must live under the image's S3 prefix.
+///
+/// # Errors
+///
+/// Returns [`Error::BootSetArchMismatch`] on an architecture mismatch
+/// and [`Error::BootSetImageMismatch`] when the boot set points at
+/// other S3 artifacts.
+pub fn validate_boot_set_against_image(
+  boot_set_name: &str,
+  boot_set: &BootSet,
+  image: &Image,
+) -> Result<(), Error> {
+  let image_mismatch = |detail: String| Error::BootSetImageMismatch {
+    boot_set: boot_set_name.to_string(),
+    image: image.name.clone(),
+    detail,
+  };
+
+  if let Some((boot_set_arch, image_arch)) =
+    boot_set.arch.as_deref().zip(image.arch.as_deref()).filter(
+      |(boot_set_arch, image_arch)| is_arch_mismatch(boot_set_arch, image_arch),
+    )
+  {
+    return Err(Error::BootSetArchMismatch {
+      boot_set: boot_set_name.to_string(),
+      boot_set_arch: boot_set_arch.to_string(),
+      image: image.name.clone(),
+      image_arch: image_arch.to_string(),
+    });
+  }
+
+  let Some(link) = image.link.as_ref() else {
+    return Ok(());
+  };
+
+  if let Some(path) = boot_set.path.as_deref().filter(|path| *path != link.path)
+  {
+    return Err(image_mismatch(format!(
+      "path '{path}' differs from image manifest '{}'",
+      link.path
+    )));
+  }
+
+  if let Some((etag, image_etag)) = boot_set
+    .etag
+    .as_deref()
+    .zip(link.etag.as_deref())
+    .filter(|(etag, image_etag)| etag != image_etag)
+  {
+    return Err(image_mismatch(format!(
+      "etag '{etag}' differs from image manifest etag '{image_etag}'"
+    )));
+  }
+
+  if let Some(passthrough) = boot_set.rootfs_provider_passthrough.as_deref() {
+    let image_prefix = link
+      .path
+      .rsplit_once('/')
+      .map_or(link.path.as_str(), |(prefix, _)| prefix);
+
+    for (start, _) in passthrough.match_indices("s3://") {
+      let location = passthrough[start..]
+        .split([',', ' '])
+        .next()
+        .unwrap_or_default();
+
+      if !location.starts_with(&format!("{image_prefix}/")) {
+        return Err(image_mismatch(format!(
+          "rootfs_provider_passthrough references '{location}' outside '{image_prefix}/'"
+        )));
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// A BOS session template whose boot sets were pointed at a rebuilt IMS
+/// image by [`refresh_image_references`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RefreshedTemplate {
+  /// BOS session template name.
+  pub template_name: String,
+  /// Names of the boot sets whose `path`/`etag` were rewritten.
+  pub boot_sets: Vec<String>,
+}
+
+/// Point every boot set of `bos_sessiontemplate` that boots a stale
+/// build of the image at `link`.
+///
+/// A boot set is stale when it boots one of `stale_image_id_vec`, or
+/// boots `image_id` with an etag that no longer matches the manifest.
+/// Returns the sorted names of the boot sets that were rewritten.
+fn refresh_boot_sets(
+  bos_sessiontemplate: &mut BosSessionTemplate,
+  image_id: &str,
+  link: &Link,
+  stale_image_id_vec: &[&str],
+) -> Vec<String> {
+  let mut boot_set_name_vec = Vec::new();
+
+  for (boot_set_name, boot_set) in
+    bos_sessiontemplate.boot_sets.iter_mut().flatten()
+  {
+    let is_stale = boot_set.path.as_deref().is_some_and(|path| {
+      let boot_set_image_id = path
+        .trim_start_matches("s3://boot-images/")
+        .trim_end_matches("/manifest.json");
+
+      stale_image_id_vec.contains(&boot_set_image_id)
+        || (boot_set_image_id == image_id && boot_set.etag != link.etag)
+    });
+
+    if is_stale {
+      boot_set.path = Some(link.path.clone());
+      boot_set.etag.clone_from(&link.etag);
+      boot_set.r#type = Some(link.r#type.clone());
+      boot_set_name_vec.push(boot_set_name.clone());
+    }
+  }
+
+  boot_set_name_vec.sort();
+  boot_set_name_vec
+}
+
+/// Whether [`refresh_image_references`] may rewrite
+/// `bos_sessiontemplate`: it is one of `template_name_vec`, or all its
+/// boot sets target HSM groups of `target_hsm_group_name_vec`. Either
+/// way `execution_context` must own every HSM group it targets.
+///
+/// # Errors
+///
+/// Returns the error of [`ExecutionContext::check_owns`] if
+/// `bos_sessiontemplate` is one of `template_name_vec` but targets an
+/// HSM group `execution_context` doesn't own.
+fn is_in_refresh_scope(
+  bos_sessiontemplate: &BosSessionTemplate,
+  execution_context: &ExecutionContext,
+  target_hsm_group_name_vec: &[String],
+  template_name_vec: &[String],
+) -> Result<bool, Error> {
+  let target_hsm_vec = bos_sessiontemplate.get_target_hsm();
+
+  if bos_sessiontemplate
+    .name
+    .as_ref()
+    .is_some_and(|name| template_name_vec.contains(name))
+  {
+    for hsm_group in &target_hsm_vec {
+      execution_context.check_owns(hsm_group)?;
+    }
+
+    return Ok(true);
+  }
+
+  Ok(
+    !target_hsm_vec.is_empty()
+      && target_hsm_vec.iter().all(|hsm_group| {
+        target_hsm_group_name_vec.contains(hsm_group)
+          && execution_context.tenancy().owns(hsm_group)
+      }),
+  )
+}
+
+/// Re-point BOS session templates at the current build of an IMS image.
+///
+/// `image_name_or_id` is first matched against IMS image ids, then
+/// against image names (newest image wins). Only the session templates
+/// named in `template_name_vec`, or whose boot sets all target groups of
+/// `target_hsm_group_name_vec`, are considered, and only if the command
+/// running on the current task, or else the owner of `shasta_token`,
+/// owns every group they target. Those with a boot set
+/// booting an older image with the same name, or the same image with an
+/// outdated etag, get their `path`, `etag` and `type` rewritten from the
+/// image's S3 manifest link and are stored back in BOS. With `dry_run`
+/// nothing is written; the report is the same.
+///
+/// # Errors
+///
+/// Returns [`Error::ImageNotFound`] when no IMS image matches
+/// `image_name_or_id`, [`Error::Message`] or [`Error::Impersonation`]
+/// when a template of `template_name_vec` targets an HSM group the
+/// caller doesn't own, or another [`Error`] variant on CSM, transport,
+/// or deserialization failure.
+pub async fn refresh_image_references(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  image_name_or_id: &str,
+  target_hsm_group_name_vec: &[String],
+  template_name_vec: &[String],
+  dry_run: bool,
+) -> Result<Vec<RefreshedTemplate>, Error> {
+  let client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+  let execution_context = match execution_context::current() {
+    Some(execution_context) => execution_context,
+    None => ExecutionContext::from_token(shasta_token)?,
+  };
+
+  let image_vec = client.ims_image_get_all(shasta_token).await?;
+
+  let image = image_vec
+    .iter()
+    .find(|image| image.id.as_deref() == Some(image_name_or_id))
+    .or_else(|| {
+      image_vec
+        .iter()
+        .filter(|image| image.name == image_name_or_id)
+        .max_by(|a, b| {
+          a.created
+            .as_deref()
+            .unwrap_or("")
+            .cmp(b.created.as_deref().unwrap_or(""))
+        })
+    })
+    .ok_or_else(|| Error::ImageNotFound(image_name_or_id.to_string()))?;
+
+  let image_id = image
+    .id
+    .as_deref()
+    .ok_or_else(|| Error::ImageNotFound(image_name_or_id.to_string()))?;
+  let link = image.link.as_ref().ok_or_else(|| {
+    Error::Message(format!(
+      "IMS image '{}' has no 'link' (no S3 manifest)",
+      image.name
+    ))
+  })?;
+
+  let stale_image_id_vec: Vec<&str> = image_vec
+    .iter()
+    .filter(|stale| stale.name == image.name && stale.id != image.id)
+    .filter_map(|stale| stale.id.as_deref())
+    .collect();
+
+  let mut in_scope_vec = Vec::new();
+
+  for bos_sessiontemplate in
+    client.bos_template_v2_get_all(shasta_token).await?
+  {
+    if is_in_refresh_scope(
+      &bos_sessiontemplate,
+      &execution_context,
+      target_hsm_group_name_vec,
+      template_name_vec,
+    )? {
+      in_scope_vec.push(bos_sessiontemplate);
+    }
+  }
+
+  let mut refreshed_vec = Vec::new();
+
+  for mut bos_sessiontemplate in in_scope_vec {
+    let boot_set_name_vec = refresh_boot_sets(
+      &mut bos_sessiontemplate,
+      image_id,
+      link,
+      &stale_image_id_vec,
+    );
+
+    if boot_set_name_vec.is_empty() {
+      continue;
+    }
+
+    let Some(template_name) = bos_sessiontemplate.name.take() else {
+      events::warning("Skip BOS sessiontemplate without name");
+      continue;
+    };
+
+    if dry_run {
+      events::info(format!(
+        "Dry run mode: BOS sessiontemplate '{template_name}' boot sets {boot_set_name_vec:?} would point to image '{image_id}'"
+      ));
+    } else {
+      // `links` and `tenant` are read-only on PUT
+      bos_sessiontemplate.links = None;
+      bos_sessiontemplate.tenant = None;
+
+      client
+        .bos_template_v2_put(shasta_token, &bos_sessiontemplate, &template_name)
+        .await?;
+
+      events::info(format!(
+        "BOS sessiontemplate '{template_name}' boot sets {boot_set_name_vec:?} now point to image '{image_id}'"
+      ));
+    }
+
+    refreshed_vec.push(RefreshedTemplate {
+      template_name,
+      boot_sets: boot_set_name_vec,
+    });
+  }
+
+  Ok(refreshed_vec)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::bos::template::http_client::v2::types::{BootSet, Cfs};
+  use crate::bos::template::http_client::v2::types::Cfs;
+  use crate::hsm::group::tenancy::Tenancy;
   use std::collections::HashMap;
 
   fn template(
@@ -330,6 +698,32 @@ mod tests {
     assert_eq!(result.len(), 1);
   }
 
+  // ---------- filter_page ----------
+
+  #[test]
+  fn filter_page_pages_through_targeted_templates_by_name() {
+    let templates = vec![
+      template("t3", None, vec![("b", boot_set_for_hsm(vec!["zinal"]))]),
+      template("t1", None, vec![("b", boot_set_for_hsm(vec!["zinal"]))]),
+      template("t2", None, vec![("b", boot_set_for_hsm(vec!["daint"]))]),
+      template("t4", None, vec![("b", boot_set_for_hsm(vec!["zinal"]))]),
+    ];
+    let groups = vec!["zinal".to_string()];
+    let names = |page: &Page<BosSessionTemplate>| -> Vec<String> {
+      page.items.iter().filter_map(|t| t.name.clone()).collect()
+    };
+
+    let first =
+      filter_page(templates.clone(), &groups, &[], &Query::default(), 2)
+        .unwrap();
+    assert_eq!(names(&first), vec!["t1", "t3"]);
+
+    let query = Query::default().after(first.next_cursor.unwrap());
+    let second = filter_page(templates, &groups, &[], &query, 2).unwrap();
+    assert_eq!(names(&second), vec!["t4"]);
+    assert!(second.next_cursor.is_none());
+  }
+
   // ---------- get_image_id_cfs_configuration_target_tuple_vec ----------
 
   fn boot_set_with_path_and_groups(
@@ -411,4 +805,224 @@ mod tests {
       get_image_id_cfs_configuration_target_tuple_vec(&vec![template]);
     assert!(result.is_empty());
   }
+
+  // ---------- validate_boot_set_against_image ----------
+
+  const IMAGE_PATH: &str = "s3://boot-images/1234/manifest.json";
+
+  fn image_with_arch(arch: &str) -> Image {
+    Image {
+      name: "compute".to_string(),
+      arch: Some(arch.to_string()),
+      link: Some(Link {
+        path: IMAGE_PATH.to_string(),
+        etag: Some("abcd".to_string()),
+        r#type: "s3".to_string(),
+      }),
+      ..Default::default()
+    }
+  }
+
+  fn boot_set_for_image(arch: &str, passthrough: Option<&str>) -> BootSet {
+    BootSet {
+      path: Some(IMAGE_PATH.to_string()),
+      etag: Some("abcd".to_string()),
+      arch: Some(arch.to_string()),
+      rootfs_provider_passthrough: passthrough.map(str::to_string),
+      ..boot_set_for_hsm(vec!["zinal"])
+    }
+  }
+
+  #[test]
+  fn validate_boot_set_accepts_matching_image() {
+    let boot_set = boot_set_for_image(
+      "X86",
+      Some("sbps:v1:iqn.2023-06.csm.iscsi:_sbps-hsn._tcp.alps:300"),
+    );
+    validate_boot_set_against_image(
+      "compute",
+      &boot_set,
+      &image_with_arch("x86_64"),
+    )
+    .unwrap();
+  }
+
+  #[test]
+  fn validate_boot_set_rejects_arch_mismatch() {
+    let boot_set = boot_set_for_image("X86", None);
+    let err = validate_boot_set_against_image(
+      "compute",
+      &boot_set,
+      &image_with_arch("aarch64"),
+    )
+    .unwrap_err();
+    assert!(matches!(
+      err,
+      Error::BootSetArchMismatch { ref image_arch, .. } if image_arch == "aarch64"
+    ));
+  }
+
+  #[test]
+  fn validate_boot_set_skips_unknown_arch() {
+    let boot_set = boot_set_for_image("Other", None);
+    validate_boot_set_against_image(
+      "compute",
+      &boot_set,
+      &image_with_arch("aarch64"),
+    )
+    .unwrap();
+  }
+
+  #[test]
+  fn validate_boot_set_rejects_etag_mismatch() {
+    let mut boot_set = boot_set_for_image("ARM", None);
+    boot_set.etag = Some("stale".to_string());
+    let err = validate_boot_set_against_image(
+      "compute",
+      &boot_set,
+      &image_with_arch("aarch64"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::BootSetImageMismatch { .. }));
+  }
+
+  #[test]
+  fn validate_boot_set_checks_passthrough_s3_location() {
+    let image = image_with_arch("x86_64");
+
+    let ok = boot_set_for_image(
+      "X86",
+      Some("dvs:s3://boot-images/1234/rootfs,nmn0:300"),
+    );
+    validate_boot_set_against_image("compute", &ok, &image).unwrap();
+
+    let other = boot_set_for_image(
+      "X86",
+      Some("dvs:s3://boot-images/5678/rootfs,nmn0:300"),
+    );
+    let err =
+      validate_boot_set_against_image("compute", &other, &image).unwrap_err();
+    assert!(matches!(err, Error::BootSetImageMismatch { .. }));
+  }
+
+  // ---------- refresh_boot_sets ----------
+
+  fn rebuilt_link() -> Link {
+    Link {
+      path: "s3://boot-images/new/manifest.json".to_string(),
+      etag: Some("new-etag".to_string()),
+      r#type: "s3".to_string(),
+    }
+  }
+
+  fn boot_set_booting(image_id: &str, etag: &str) -> BootSet {
+    BootSet {
+      path: Some(format!("s3://boot-images/{image_id}/manifest.json")),
+      etag: Some(etag.to_string()),
+      ..boot_set_for_hsm(vec!["zinal"])
+    }
+  }
+
+  #[test]
+  fn refresh_boot_sets_rewrites_stale_image_ids() {
+    let mut bos_sessiontemplate = template(
+      "t1",
+      Some("c"),
+      vec![
+        ("compute", boot_set_booting("old", "old-etag")),
+        ("uan", boot_set_booting("other", "other-etag")),
+      ],
+    );
+
+    let patched = refresh_boot_sets(
+      &mut bos_sessiontemplate,
+      "new",
+      &rebuilt_link(),
+      &["old"],
+    );
+
+    assert_eq!(patched, vec!["compute".to_string()]);
+    let boot_sets = bos_sessiontemplate.boot_sets.unwrap();
+    assert_eq!(
+      boot_sets["compute"].path.as_deref(),
+      Some("s3://boot-images/new/manifest.json")
+    );
+    assert_eq!(boot_sets["compute"].etag.as_deref(), Some("new-etag"));
+    assert_eq!(boot_sets["uan"].etag.as_deref(), Some("other-etag"));
+  }
+
+  #[test]
+  fn refresh_boot_sets_rewrites_outdated_etag_only() {
+    let mut bos_sessiontemplate = template(
+      "t1",
+      Some("c"),
+      vec![
+        ("compute", boot_set_booting("new", "old-etag")),
+        ("uan", boot_set_booting("new", "new-etag")),
+      ],
+    );
+
+    let patched =
+      refresh_boot_sets(&mut bos_sessiontemplate, "new", &rebuilt_link(), &[]);
+
+    assert_eq!(patched, vec!["compute".to_string()]);
+  }
+
+  #[test]
+  fn refresh_scope_is_caller_groups_or_named_templates() {
+    let zinal = template(
+      "t1",
+      Some("c"),
+      vec![("compute", boot_set_for_hsm(vec!["zinal"]))],
+    );
+    let shared = template(
+      "t2",
+      Some("c"),
+      vec![
+        ("compute", boot_set_for_hsm(vec!["zinal"])),
+        ("uan", boot_set_for_hsm(vec!["prealps"])),
+      ],
+    );
+    let by_xname = template(
+      "t3",
+      Some("c"),
+      vec![("compute", boot_set_for_xnames(vec!["x1000c0s0b0n0"]))],
+    );
+    let groups = vec!["zinal".to_string()];
+    let admin =
+      ExecutionContext::with_tenancy("admin", Tenancy::unrestricted());
+    let in_scope =
+      |template: &BosSessionTemplate, groups: &[String], names: &[String]| {
+        is_in_refresh_scope(template, &admin, groups, names).unwrap()
+      };
+
+    assert!(in_scope(&zinal, &groups, &[]));
+    assert!(!in_scope(&shared, &groups, &[]));
+    assert!(!in_scope(&by_xname, &groups, &[]));
+    assert!(in_scope(&by_xname, &[], &["t3".to_string()]));
+    assert!(!in_scope(&zinal, &[], &[]));
+  }
+
+  #[test]
+  fn refresh_scope_rejects_named_templates_targeting_foreign_groups() {
+    let shared = template(
+      "t2",
+      Some("c"),
+      vec![
+        ("compute", boot_set_for_hsm(vec!["zinal"])),
+        ("uan", boot_set_for_hsm(vec!["prealps"])),
+      ],
+    );
+    let tenant = ExecutionContext::with_tenancy(
+      "tenant",
+      Tenancy::from_groups(vec!["zinal".to_string()]),
+    );
+    let groups = vec!["zinal".to_string(), "prealps".to_string()];
+
+    assert!(matches!(
+      is_in_refresh_scope(&shared, &tenant, &[], &["t2".to_string()]),
+      Err(Error::Message(_))
+    ));
+    assert!(!is_in_refresh_scope(&shared, &tenant, &groups, &[]).unwrap());
+  }
 }
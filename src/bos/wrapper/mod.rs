@@ -15,17 +15,14 @@
 //! file relocations — the upstream BOS spec is v2-only, so v1 cannot
 //! be routed through progenitor.
 
-use crate::{ShastaClient, bos::generated, error::Error};
+use crate::{ShastaClient, bos::generated, common::metrics, error::Error};
 
 pub(crate) fn gen_client(
   client: &ShastaClient,
   token: &str,
 ) -> Result<generated::Client, Error> {
-  let inner = crate::common::http::build_client_with_auth(
-    client.root_cert(),
-    client.socks5_proxy(),
-    Some(token),
-  )?;
+  let inner =
+    crate::common::http::build_client_with_auth(client.config(), Some(token))?;
   let baseurl = format!("{}/bos", client.base_url());
   Ok(generated::Client::new_with_client(&baseurl, inner))
 }
@@ -69,7 +66,7 @@ pub(crate) async fn run<F, Fut, T, E>(
   op: F,
 ) -> Result<T, Error>
 where
-  F: FnOnce(generated::Client) -> Fut,
+  F: Fn(generated::Client) -> Fut,
   Fut: std::future::Future<
       Output = Result<
         progenitor_client::ResponseValue<T>,
@@ -78,8 +75,7 @@ where
     >,
   E: std::fmt::Debug,
 {
-  let gc = gen_client(client, token)?;
-  match op(gc).await {
+  match metrics::send_generated(client, token, "bos", gen_client, op).await? {
     Ok(rv) => Ok(rv.into_inner()),
     Err(e) => Err(map_err(e).await),
   }
@@ -88,3 +84,5 @@ where
 mod v1;
 mod v2;
 mod health_check;
+
+pub use v2::version::BosVersion;
@@ -6,13 +6,60 @@
 //! `reqwest`.
 //!
 //! Methods present:
+//! - `bos_session_v1_get`
 //! - `bos_session_v1_post`
+//!
+//! The read-only wire-format types live at
+//! `crate::bos::session::http_client::v1::types`.
 
+use futures::future::try_join_all;
 use serde_json::{Value, json};
 
-use crate::{ShastaClient, common::http, error::Error};
+use crate::{
+  ShastaClient, bos::session::http_client::v1::types::BosSession, common::http,
+  error::Error,
+};
 
 impl ShastaClient {
+  /// Get BOS v1 session `bos_session_id_opt`, or every v1 session if
+  /// `None`. `GET /bos/v1/session` only lists session ids, so listing
+  /// then fetches every session.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn bos_session_v1_get(
+    &self,
+    token: &str,
+    bos_session_id_opt: Option<&str>,
+  ) -> Result<Vec<BosSession>, Error> {
+    log::debug!(
+      "Get BOS v1 sessions '{}'",
+      bos_session_id_opt.unwrap_or("all available")
+    );
+
+    let session_id_vec = match bos_session_id_opt {
+      Some(id) => vec![id.to_string()],
+      None => {
+        let api_url = format!("{}/bos/v1/session", self.base_url());
+        http::get_json::<Vec<String>>(self.http(), &api_url, token).await?
+      }
+    };
+
+    try_join_all(session_id_vec.into_iter().map(|id| async move {
+      let api_url = format!("{}/bos/v1/session/{id}", self.base_url());
+      let session: BosSession =
+        http::get_json(self.http(), &api_url, token).await?;
+      Ok::<_, Error>(BosSession {
+        id: Some(id),
+        ..session
+      })
+    }))
+    .await
+  }
+
   /// `POST /bos/v1/session` — create a v1 BOS session for the given
   /// template name and operation (e.g. `boot`, `reboot`, `shutdown`).
   ///
@@ -9,3 +9,4 @@
 
 mod session;
 mod template;
+pub(super) mod version;
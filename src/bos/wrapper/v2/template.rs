@@ -66,9 +66,12 @@
 //! `crate::bos::wrapper` are retained so a future spec revision can be
 //! migrated incrementally without a second scaffolding pass.
 
+use crate::common::metrics::MeteredSend;
 use crate::{
-  ShastaClient, bos::template::http_client::v2::types::BosSessionTemplate,
-  common::http, error::Error,
+  ShastaClient,
+  bos::template::http_client::v2::types::BosSessionTemplate,
+  common::{http, protection},
+  error::Error,
 };
 
 impl ShastaClient {
@@ -145,6 +148,44 @@ impl ShastaClient {
     http::put_json(self.http(), &api_url, token, bos_template).await
   }
 
+  /// Protect or unprotect BOS session template `bos_template_name`
+  /// against deletion, see [`crate::protection`].
+  ///
+  /// Fetches the template, updates the protection marker in its
+  /// description and writes it back with [`Self::bos_template_v2_put`].
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Message`] if the template does not exist, or an
+  /// [`Error`] variant on CSM, transport, or deserialization failure.
+  pub async fn bos_template_v2_set_protected(
+    &self,
+    token: &str,
+    bos_template_name: &str,
+    protected: bool,
+  ) -> Result<BosSessionTemplate, Error> {
+    let mut bos_template = self
+      .bos_template_v2_get(token, Some(bos_template_name))
+      .await?
+      .into_iter()
+      .next()
+      .ok_or_else(|| {
+        Error::Message(format!(
+          "BOS sessiontemplate '{bos_template_name}' not found"
+        ))
+      })?;
+
+    bos_template.description =
+      protection::set_protected(bos_template.description.as_deref(), protected);
+    // `links` and `tenant` are read-only on PUT
+    bos_template.links = None;
+    bos_template.tenant = None;
+
+    self
+      .bos_template_v2_put(token, &bos_template, bos_template_name)
+      .await
+  }
+
   /// Delete BOS session templates.
   ///
   /// # Errors
@@ -167,7 +208,7 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?
       .error_for_status()
@@ -0,0 +1,77 @@
+//! Wrapper for `GET /bos/v2/version`.
+//!
+//! Stays on raw `reqwest`: the generated `get_version_v2` returns the
+//! spec's `Version` with `major` / `minor` / `patch` as regex-validated
+//! string newtypes, while callers want the numeric
+//! [`BosVersion`] they can compare.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ShastaClient, common::http, error::Error};
+
+/// Version of the BOS service, as reported by
+/// [`crate::ShastaClient::bos_version_v2_get`].
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize,
+)]
+pub struct BosVersion {
+  /// Major version.
+  pub major: u32,
+  /// Minor version.
+  pub minor: u32,
+  /// Patch version.
+  pub patch: u32,
+}
+
+impl BosVersion {
+  /// Version `major.minor.patch`.
+  #[must_use]
+  pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+    Self {
+      major,
+      minor,
+      patch,
+    }
+  }
+}
+
+impl fmt::Display for BosVersion {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+  }
+}
+
+/// `GET /bos/v2/version` response; `links` is ignored.
+#[derive(Deserialize)]
+struct VersionResponse {
+  major: String,
+  minor: String,
+  patch: String,
+}
+
+impl ShastaClient {
+  /// `GET /bos/v2/version` — version of the BOS service running on the
+  /// system.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure, or [`Error::ParseStrIntError`] if BOS
+  /// reports a non-numeric version component.
+  pub async fn bos_version_v2_get(
+    &self,
+    token: &str,
+  ) -> Result<BosVersion, Error> {
+    let api_url = format!("{}/bos/v2/version", self.base_url());
+    let version: VersionResponse =
+      http::get_json(self.http(), &api_url, token).await?;
+
+    Ok(BosVersion::new(
+      version.major.parse()?,
+      version.minor.parse()?,
+      version.patch.parse()?,
+    ))
+  }
+}
@@ -0,0 +1,397 @@
+//! Boot parameters of an HSM group as a YAML bundle, for managing
+//! kernel parameters as code.
+//!
+//! [`super::utils::export`] writes the BSS boot parameters of a group's
+//! members into a [`BootParamsBundle`], with nodes booting the same
+//! kernel, initrd and kernel parameters sharing one entry.
+//! [`super::utils::import`] compares a bundle with BSS, reports the
+//! [`BundleConflict`]s and patches the nodes whose boot parameters
+//! differ from the bundle.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::Error;
+
+use super::types::BootParameters;
+
+/// Format version written to new bundles. [`BootParamsBundle::from_yaml`]
+/// rejects bundles with any other version.
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Boot parameters shared by some nodes of a [`BootParamsBundle`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleEntry {
+  /// Node xnames, sorted.
+  pub hosts: Vec<String>,
+  /// Kernel command line.
+  pub params: String,
+  /// Kernel path.
+  pub kernel: String,
+  /// Initrd path.
+  pub initrd: String,
+  /// Cloud-init data; left as is in BSS when absent.
+  #[serde(
+    default,
+    rename = "cloud-init",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub cloud_init: Option<Value>,
+}
+
+impl BundleEntry {
+  /// Whether BSS entry `boot_parameters` already boots like this entry.
+  fn matches(&self, boot_parameters: &BootParameters) -> bool {
+    self.params == boot_parameters.params
+      && self.kernel == boot_parameters.kernel
+      && self.initrd == boot_parameters.initrd
+      && (self.cloud_init.is_none()
+        || self.cloud_init == boot_parameters.cloud_init)
+  }
+
+  /// BSS patch setting `hosts` to this entry's boot parameters.
+  fn to_boot_parameters(&self, hosts: Vec<String>) -> BootParameters {
+    BootParameters {
+      hosts,
+      params: self.params.clone(),
+      kernel: self.kernel.clone(),
+      initrd: self.initrd.clone(),
+      cloud_init: self.cloud_init.clone(),
+      ..Default::default()
+    }
+  }
+}
+
+/// Boot parameters of the members of an HSM group.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BootParamsBundle {
+  /// Bundle format version, see [`BUNDLE_FORMAT_VERSION`].
+  pub version: u32,
+  /// HSM group the bundle was exported from.
+  pub hsm_group: String,
+  /// When the bundle was exported.
+  pub exported_at: DateTime<Utc>,
+  /// Entries, sorted by their first host.
+  pub entries: Vec<BundleEntry>,
+}
+
+impl BootParamsBundle {
+  /// Bundle of `hsm_group` made of `boot_parameters_vec`, the BSS
+  /// entries of its members; nodes not in `member_vec` are left out.
+  #[must_use]
+  pub fn new(
+    hsm_group: &str,
+    member_vec: &[String],
+    boot_parameters_vec: &[BootParameters],
+  ) -> Self {
+    let member_set: BTreeSet<&str> =
+      member_vec.iter().map(String::as_str).collect();
+
+    // Nodes booting the same thing share an entry
+    let mut hosts_by_boot: BTreeMap<(&str, &str, &str, String), Vec<String>> =
+      BTreeMap::new();
+    let mut cloud_init_by_boot: HashMap<String, Option<Value>> = HashMap::new();
+    for boot_parameters in boot_parameters_vec {
+      let cloud_init_key = boot_parameters
+        .cloud_init
+        .as_ref()
+        .map(Value::to_string)
+        .unwrap_or_default();
+      cloud_init_by_boot
+        .entry(cloud_init_key.clone())
+        .or_insert_with(|| boot_parameters.cloud_init.clone());
+
+      hosts_by_boot
+        .entry((
+          boot_parameters.params.as_str(),
+          boot_parameters.kernel.as_str(),
+          boot_parameters.initrd.as_str(),
+          cloud_init_key,
+        ))
+        .or_default()
+        .extend(
+          boot_parameters
+            .hosts
+            .iter()
+            .filter(|host| member_set.contains(host.as_str()))
+            .cloned(),
+        );
+    }
+
+    let mut entries: Vec<BundleEntry> = hosts_by_boot
+      .into_iter()
+      .filter(|(_, hosts)| !hosts.is_empty())
+      .map(|((params, kernel, initrd, cloud_init_key), mut hosts)| {
+        hosts.sort();
+        hosts.dedup();
+        BundleEntry {
+          hosts,
+          params: params.to_string(),
+          kernel: kernel.to_string(),
+          initrd: initrd.to_string(),
+          cloud_init: cloud_init_by_boot
+            .get(&cloud_init_key)
+            .cloned()
+            .flatten(),
+        }
+      })
+      .collect();
+    entries.sort_by(|a, b| a.hosts.cmp(&b.hosts));
+
+    Self {
+      version: BUNDLE_FORMAT_VERSION,
+      hsm_group: hsm_group.to_string(),
+      exported_at: Utc::now(),
+      entries,
+    }
+  }
+
+  /// The bundle as YAML.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::SerdeYamlError`] if serialization fails.
+  pub fn to_yaml(&self) -> Result<String, Error> {
+    Ok(serde_yaml::to_string(self)?)
+  }
+
+  /// Read a bundle written by [`Self::to_yaml`].
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::BootParamsBundle`] if `yaml` isn't a bundle, was
+  /// written in an unsupported format version, or lists a host in
+  /// several entries.
+  pub fn from_yaml(yaml: &str) -> Result<Self, Error> {
+    let value: serde_yaml::Value = serde_yaml::from_str(yaml)
+      .map_err(|e| Error::BootParamsBundle(e.to_string()))?;
+
+    // Checked before parsing the rest, whose shape depends on it
+    let version = value
+      .get("version")
+      .and_then(serde_yaml::Value::as_u64)
+      .ok_or_else(|| {
+        Error::BootParamsBundle("'version' missing or not a number".to_string())
+      })?;
+    if version != u64::from(BUNDLE_FORMAT_VERSION) {
+      return Err(Error::BootParamsBundle(format!(
+        "unsupported format version {version}, expected {BUNDLE_FORMAT_VERSION}"
+      )));
+    }
+
+    let bundle: Self = serde_yaml::from_value(value)
+      .map_err(|e| Error::BootParamsBundle(e.to_string()))?;
+
+    let mut host_set = BTreeSet::new();
+    for host in bundle.entries.iter().flat_map(|entry| &entry.hosts) {
+      if !host_set.insert(host) {
+        return Err(Error::BootParamsBundle(format!(
+          "host '{host}' is listed in several entries"
+        )));
+      }
+    }
+
+    Ok(bundle)
+  }
+
+  /// Hosts of every entry.
+  pub fn hosts(&self) -> impl Iterator<Item = &str> {
+    self
+      .entries
+      .iter()
+      .flat_map(|entry| entry.hosts.iter().map(String::as_str))
+  }
+}
+
+/// Why a node is left alone by an import.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "conflict", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum BundleConflict {
+  /// The node is a member of the group in CSM but isn't in the bundle.
+  NotInBundle {
+    /// Node xname.
+    xname: String,
+  },
+  /// The node is in the bundle but no longer a member of the group.
+  NotInGroup {
+    /// Node xname.
+    xname: String,
+  },
+  /// The node is in the bundle but BSS has no boot parameters for it.
+  NotInBss {
+    /// Node xname.
+    xname: String,
+  },
+}
+
+/// What importing a [`BootParamsBundle`] does.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportPlan {
+  /// BSS patches to apply, one per bundle entry with nodes to change.
+  pub patches: Vec<BootParameters>,
+  /// Nodes whose boot parameters already match the bundle.
+  pub unchanged: Vec<String>,
+  /// Nodes left alone, and why.
+  pub conflicts: Vec<BundleConflict>,
+}
+
+impl ImportPlan {
+  /// Plan importing `bundle` into a group currently made of
+  /// `member_vec`, whose members have the BSS entries
+  /// `boot_parameters_vec`.
+  #[must_use]
+  pub fn new(
+    bundle: &BootParamsBundle,
+    member_vec: &[String],
+    boot_parameters_vec: &[BootParameters],
+  ) -> Self {
+    let member_set: BTreeSet<&str> =
+      member_vec.iter().map(String::as_str).collect();
+    let bundle_host_set: BTreeSet<&str> = bundle.hosts().collect();
+    let boot_parameters_by_host: HashMap<&str, &BootParameters> =
+      boot_parameters_vec
+        .iter()
+        .flat_map(|boot_parameters| {
+          boot_parameters
+            .hosts
+            .iter()
+            .map(move |host| (host.as_str(), boot_parameters))
+        })
+        .collect();
+
+    let mut plan = Self::default();
+
+    for entry in &bundle.entries {
+      let mut host_to_change_vec = Vec::new();
+
+      for host in &entry.hosts {
+        if !member_set.contains(host.as_str()) {
+          plan.conflicts.push(BundleConflict::NotInGroup {
+            xname: host.clone(),
+          });
+          continue;
+        }
+
+        match boot_parameters_by_host.get(host.as_str()) {
+          None => plan.conflicts.push(BundleConflict::NotInBss {
+            xname: host.clone(),
+          }),
+          Some(boot_parameters) if entry.matches(boot_parameters) => {
+            plan.unchanged.push(host.clone());
+          }
+          Some(_) => host_to_change_vec.push(host.clone()),
+        }
+      }
+
+      if !host_to_change_vec.is_empty() {
+        plan
+          .patches
+          .push(entry.to_boot_parameters(host_to_change_vec));
+      }
+    }
+
+    plan
+      .conflicts
+      .extend(member_set.difference(&bundle_host_set).map(|xname| {
+        BundleConflict::NotInBundle {
+          xname: (*xname).to_string(),
+        }
+      }));
+
+    plan
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn boot_parameters(hosts: &[&str], params: &str) -> BootParameters {
+    BootParameters {
+      hosts: hosts.iter().map(ToString::to_string).collect(),
+      params: params.to_string(),
+      kernel: "s3://boot-images/1234/kernel".to_string(),
+      initrd: "s3://boot-images/1234/initrd".to_string(),
+      ..Default::default()
+    }
+  }
+
+  fn members(xname_vec: &[&str]) -> Vec<String> {
+    xname_vec.iter().map(ToString::to_string).collect()
+  }
+
+  #[test]
+  fn bundle_groups_members_booting_the_same_and_round_trips() {
+    let bundle = BootParamsBundle::new(
+      "zinal",
+      &members(&["x1000c0s0b0n0", "x1000c0s0b0n1", "x1000c0s0b1n0"]),
+      &[
+        boot_parameters(&["x1000c0s0b0n1"], "quiet"),
+        boot_parameters(&["x1000c0s0b0n0"], "quiet"),
+        boot_parameters(&["x1000c0s0b1n0", "x9000c0s0b0n0"], "debug"),
+      ],
+    );
+
+    assert_eq!(bundle.entries.len(), 2);
+    assert_eq!(
+      bundle.entries[0].hosts,
+      members(&["x1000c0s0b0n0", "x1000c0s0b0n1"])
+    );
+    assert_eq!(bundle.entries[1].hosts, members(&["x1000c0s0b1n0"]));
+
+    let yaml = bundle.to_yaml().unwrap();
+    assert_eq!(BootParamsBundle::from_yaml(&yaml).unwrap(), bundle);
+
+    assert!(matches!(
+      BootParamsBundle::from_yaml(&yaml.replace("version: 1", "version: 2")),
+      Err(Error::BootParamsBundle(_))
+    ));
+    assert!(matches!(
+      BootParamsBundle::from_yaml(
+        &yaml.replace("- x1000c0s0b1n0", "- x1000c0s0b0n0")
+      ),
+      Err(Error::BootParamsBundle(message)) if message.contains("several entries")
+    ));
+  }
+
+  #[test]
+  fn import_plan_patches_changed_nodes_and_reports_conflicts() {
+    let bundle = BootParamsBundle::new(
+      "zinal",
+      &members(&["x1000c0s0b0n0", "x1000c0s0b0n1", "x1000c0s0b1n0"]),
+      &[boot_parameters(
+        &["x1000c0s0b0n0", "x1000c0s0b0n1", "x1000c0s0b1n0"],
+        "quiet",
+      )],
+    );
+
+    let plan = ImportPlan::new(
+      &bundle,
+      &members(&["x1000c0s0b0n0", "x1000c0s0b0n1", "x1000c0s0b1n1"]),
+      &[
+        boot_parameters(&["x1000c0s0b0n0"], "quiet"),
+        boot_parameters(&["x1000c0s0b0n1", "x1000c0s0b1n1"], "debug"),
+      ],
+    );
+
+    assert_eq!(plan.unchanged, members(&["x1000c0s0b0n0"]));
+    assert_eq!(plan.patches.len(), 1);
+    assert_eq!(plan.patches[0].hosts, members(&["x1000c0s0b0n1"]));
+    assert_eq!(plan.patches[0].params, "quiet");
+    assert_eq!(
+      plan.conflicts,
+      vec![
+        BundleConflict::NotInGroup {
+          xname: "x1000c0s0b1n0".to_string()
+        },
+        BundleConflict::NotInBundle {
+          xname: "x1000c0s0b1n1".to_string()
+        },
+      ]
+    );
+  }
+}
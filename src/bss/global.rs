@@ -0,0 +1,278 @@
+//! BSS `Global` boot parameters.
+//!
+//! BSS keeps one entry whose `hosts` is `["Global"]`; its `params` and
+//! `cloud-init` apply to every node and carry system-wide settings such
+//! as iPXE and chain-of-trust options. A bad edit affects the whole
+//! system, so changes go through a plan/apply cycle:
+//!
+//! 1. [`crate::ShastaClient::bss_global_bootparameters_plan`] computes
+//!    the field-by-field diff of a [`GlobalBootParametersChange`]
+//!    against the live entry, without changing anything (dry run).
+//! 2. [`crate::ShastaClient::bss_global_bootparameters_apply`] applies
+//!    the change only if the caller hands back the plan's
+//!    `confirmation_token` and the entry hasn't changed in between.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{ShastaClient, error::Error};
+
+use super::types::BootParameters;
+
+/// Host name of the BSS entry holding the global boot parameters.
+pub const GLOBAL_HOST: &str = "Global";
+
+/// Fields of the `Global` entry to replace; `None` leaves a field as is.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GlobalBootParametersChange {
+  /// Kernel command line.
+  pub params: Option<String>,
+  /// Kernel path.
+  pub kernel: Option<String>,
+  /// Initrd path.
+  pub initrd: Option<String>,
+  /// Cloud-init data (`user-data`, `meta-data`, ...).
+  pub cloud_init: Option<Value>,
+}
+
+/// One field differing between the live and the proposed `Global` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldDiff {
+  /// Field name, as in the BSS API.
+  pub field: &'static str,
+  /// Current value.
+  pub current: String,
+  /// Value after applying the change.
+  pub proposed: String,
+}
+
+/// Outcome of planning a [`GlobalBootParametersChange`].
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobalBootParametersPlan {
+  /// Live `Global` entry.
+  pub current: BootParameters,
+  /// `Global` entry after applying the change.
+  pub proposed: BootParameters,
+  /// Fields that change; empty if the change is a no-op.
+  pub diff: Vec<FieldDiff>,
+  /// Token to pass to
+  /// [`crate::ShastaClient::bss_global_bootparameters_apply`]. Bound to
+  /// both the live entry and the change.
+  pub confirmation_token: String,
+}
+
+impl GlobalBootParametersPlan {
+  fn new(current: BootParameters, change: &GlobalBootParametersChange) -> Self {
+    let mut proposed = current.clone();
+    if let Some(params) = &change.params {
+      proposed.params.clone_from(params);
+    }
+    if let Some(kernel) = &change.kernel {
+      proposed.kernel.clone_from(kernel);
+    }
+    if let Some(initrd) = &change.initrd {
+      proposed.initrd.clone_from(initrd);
+    }
+    if change.cloud_init.is_some() {
+      proposed.cloud_init.clone_from(&change.cloud_init);
+    }
+
+    let cloud_init_str = |cloud_init: &Option<Value>| {
+      cloud_init
+        .as_ref()
+        .map(|value| serde_json::to_string_pretty(value).unwrap_or_default())
+        .unwrap_or_default()
+    };
+
+    let diff = [
+      ("params", current.params.clone(), proposed.params.clone()),
+      ("kernel", current.kernel.clone(), proposed.kernel.clone()),
+      ("initrd", current.initrd.clone(), proposed.initrd.clone()),
+      (
+        "cloud-init",
+        cloud_init_str(&current.cloud_init),
+        cloud_init_str(&proposed.cloud_init),
+      ),
+    ]
+    .into_iter()
+    .filter(|(_, current, proposed)| current != proposed)
+    .map(|(field, current, proposed)| FieldDiff {
+      field,
+      current,
+      proposed,
+    })
+    .collect();
+
+    let confirmation_token = confirmation_token(&current, &proposed);
+
+    Self {
+      current,
+      proposed,
+      diff,
+      confirmation_token,
+    }
+  }
+}
+
+/// Digest of the live and proposed entries: it changes if either the
+/// `Global` entry is modified by someone else or the change differs
+/// from the one planned.
+fn confirmation_token(
+  current: &BootParameters,
+  proposed: &BootParameters,
+) -> String {
+  let payload = serde_json::to_vec(&(current, proposed)).unwrap_or_default();
+  format!("{:x}", md5::compute(payload))
+}
+
+impl ShastaClient {
+  /// Get the BSS `Global` boot parameters.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::BootParametersNotFound`] if BSS has no `Global` entry, or an
+  /// [`Error`] variant on CSM, transport, or deserialization failure.
+  pub async fn bss_global_bootparameters_get(
+    &self,
+    token: &str,
+  ) -> Result<BootParameters, Error> {
+    self
+      .bss_bootparameters_get(token, &[GLOBAL_HOST.to_string()])
+      .await?
+      .into_iter()
+      .find(|boot_parameters| {
+        boot_parameters.hosts.iter().any(|host| host == GLOBAL_HOST)
+      })
+      .ok_or_else(|| Error::BootParametersNotFound(GLOBAL_HOST.to_string()))
+  }
+
+  /// Compute what `change` would do to the BSS `Global` boot parameters,
+  /// without changing them.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::bss_global_bootparameters_get`].
+  pub async fn bss_global_bootparameters_plan(
+    &self,
+    token: &str,
+    change: &GlobalBootParametersChange,
+  ) -> Result<GlobalBootParametersPlan, Error> {
+    let current = self.bss_global_bootparameters_get(token).await?;
+    Ok(GlobalBootParametersPlan::new(current, change))
+  }
+
+  /// Apply `change` to the BSS `Global` boot parameters.
+  /// `confirmation_token` must be the one returned by
+  /// [`Self::bss_global_bootparameters_plan`] for the same change. When
+  /// `dry_run` is true the plan is returned and nothing is changed.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::ValidationFailed`] if `confirmation_token` doesn't
+  /// match, because the change differs from the planned one or the
+  /// `Global` entry was modified since; re-plan and review the diff
+  /// again. Otherwise same as [`Self::bss_global_bootparameters_get`]
+  /// and [`Self::bss_bootparameters_patch`].
+  pub async fn bss_global_bootparameters_apply(
+    &self,
+    token: &str,
+    change: &GlobalBootParametersChange,
+    confirmation_token: &str,
+    dry_run: bool,
+  ) -> Result<GlobalBootParametersPlan, Error> {
+    let plan = self.bss_global_bootparameters_plan(token, change).await?;
+
+    if plan.confirmation_token != confirmation_token {
+      return Err(Error::ValidationFailed(
+        "BSS Global boot parameters confirmation token does not match the planned change",
+      ));
+    }
+
+    for field_diff in &plan.diff {
+      tracing::info!(
+        "BSS Global boot parameters '{}': '{}' -> '{}'",
+        field_diff.field,
+        field_diff.current,
+        field_diff.proposed
+      );
+    }
+
+    if dry_run {
+      tracing::info!("Dry run mode: BSS Global boot parameters not changed");
+    } else if plan.diff.is_empty() {
+      tracing::info!("BSS Global boot parameters already up to date");
+    } else {
+      self.bss_bootparameters_patch(token, &plan.proposed).await?;
+    }
+
+    Ok(plan)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn global() -> BootParameters {
+    BootParameters {
+      hosts: vec![GLOBAL_HOST.to_string()],
+      params: "ipxe.chain=on".to_string(),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn plan_diffs_changed_fields_only() {
+    let change = GlobalBootParametersChange {
+      params: Some("ipxe.chain=off".to_string()),
+      kernel: Some(String::new()),
+      ..Default::default()
+    };
+
+    let plan = GlobalBootParametersPlan::new(global(), &change);
+
+    assert_eq!(
+      plan.diff,
+      vec![FieldDiff {
+        field: "params",
+        current: "ipxe.chain=on".to_string(),
+        proposed: "ipxe.chain=off".to_string(),
+      }]
+    );
+    assert_eq!(plan.proposed.hosts, vec![GLOBAL_HOST.to_string()]);
+  }
+
+  #[test]
+  fn confirmation_token_binds_state_and_change() {
+    let change = GlobalBootParametersChange {
+      params: Some("ipxe.chain=off".to_string()),
+      ..Default::default()
+    };
+    let token =
+      GlobalBootParametersPlan::new(global(), &change).confirmation_token;
+
+    // Same state, same change
+    assert_eq!(
+      GlobalBootParametersPlan::new(global(), &change).confirmation_token,
+      token
+    );
+
+    // Global entry modified in between
+    let mut modified = global();
+    modified.params.push_str(" quiet");
+    assert_ne!(
+      GlobalBootParametersPlan::new(modified, &change).confirmation_token,
+      token
+    );
+
+    // Different change
+    let other_change = GlobalBootParametersChange {
+      params: Some("ipxe.chain=maybe".to_string()),
+      ..Default::default()
+    };
+    assert_ne!(
+      GlobalBootParametersPlan::new(global(), &other_change).confirmation_token,
+      token
+    );
+  }
+}
@@ -0,0 +1,239 @@
+//! Local journal of BSS boot parameter changes.
+//!
+//! Once enabled with [`crate::ClientConfig::with_bss_history`], every
+//! `bss_bootparameters_put` / `bss_bootparameters_patch` issued through
+//! the client appends one JSON line per node to the journal file,
+//! holding the node's boot parameters before and after the change, and
+//! the BSS the change was sent to. [`super::utils::rollback`] reads it
+//! back to restore what nodes were booting at a given time.
+//!
+//! The journal is local to the machine running csm-rs: changes made
+//! through other tools are not recorded.
+
+use std::{
+  fs::{File, OpenOptions},
+  io::{BufRead, BufReader, Write},
+  path::Path,
+  sync::{Mutex, PoisonError},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+use super::types::BootParameters;
+
+/// BSS call that changed a node's boot parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+  /// `PUT /bootparameters`.
+  Put,
+  /// `PATCH /bootparameters`.
+  Patch,
+}
+
+/// One node's boot parameters change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+  /// When the change was sent to BSS.
+  pub timestamp: DateTime<Utc>,
+  /// Base URL of the CSM API the change was sent to, so one journal can
+  /// hold several systems.
+  pub base_url: String,
+  /// Node whose boot parameters changed.
+  pub xname: String,
+  /// BSS call that made the change.
+  pub operation: Operation,
+  /// Boot parameters before the change; `None` if BSS had none for the
+  /// node.
+  pub previous: Option<BootParameters>,
+  /// Boot parameters sent to BSS.
+  pub new: BootParameters,
+}
+
+/// Held while writing so concurrent calls, possibly of clients sharing
+/// a journal, don't interleave lines.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Append `entry_vec` to the journal at `path`.
+pub(crate) fn record(
+  path: &Path,
+  entry_vec: &[HistoryEntry],
+) -> Result<(), Error> {
+  let _write_lock = WRITE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+  let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+  for entry in entry_vec {
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+  }
+
+  Ok(())
+}
+
+/// Journal entries for `boot_parameters` about to be sent to the BSS of
+/// `base_url`, taking each node's previous value from `previous_vec`.
+pub(crate) fn entries(
+  base_url: &str,
+  operation: Operation,
+  boot_parameters: &BootParameters,
+  previous_vec: &[BootParameters],
+) -> Vec<HistoryEntry> {
+  let timestamp = Utc::now();
+
+  boot_parameters
+    .hosts
+    .iter()
+    .map(|xname| HistoryEntry {
+      timestamp,
+      base_url: base_url.to_string(),
+      xname: xname.clone(),
+      operation,
+      previous: super::utils::find_boot_params_related_to_node(
+        previous_vec,
+        xname,
+      ),
+      new: boot_parameters.clone(),
+    })
+    .collect()
+}
+
+/// Read every entry of the journal at `path`, oldest first. Lines that
+/// don't parse are skipped.
+///
+/// # Errors
+///
+/// Returns [`Error::IoError`] if the journal can't be read.
+pub fn read(path: &Path) -> Result<Vec<HistoryEntry>, Error> {
+  let file = File::open(path)?;
+
+  let mut entry_vec = Vec::new();
+  for (index, line) in BufReader::new(file).lines().enumerate() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    match serde_json::from_str(&line) {
+      Ok(entry) => entry_vec.push(entry),
+      Err(e) => tracing::warn!(
+        "Skipping malformed line {} of BSS history '{}': {e}",
+        index + 1,
+        path.display()
+      ),
+    }
+  }
+
+  Ok(entry_vec)
+}
+
+/// Boot parameters `xname` of the system at `base_url` had at
+/// `timestamp` according to `entry_vec`.
+///
+/// Returns `None` if the node's boot parameters didn't change after
+/// `timestamp`, and `Some(None)` if the node had no boot parameters
+/// then.
+#[must_use]
+pub fn state_at<'a>(
+  entry_vec: &'a [HistoryEntry],
+  base_url: &str,
+  xname: &str,
+  timestamp: DateTime<Utc>,
+) -> Option<Option<&'a BootParameters>> {
+  entry_vec
+    .iter()
+    .filter(|entry| {
+      entry.base_url == base_url
+        && entry.xname == xname
+        && entry.timestamp > timestamp
+    })
+    .min_by_key(|entry| entry.timestamp)
+    .map(|entry| entry.previous.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn boot_params(xname: &str, image_id: &str) -> BootParameters {
+    BootParameters {
+      hosts: vec![xname.to_string()],
+      kernel: format!("s3://boot-images/{image_id}/kernel"),
+      ..Default::default()
+    }
+  }
+
+  fn entry(timestamp: &str, previous: Option<&str>, new: &str) -> HistoryEntry {
+    HistoryEntry {
+      timestamp: timestamp.parse().unwrap(),
+      base_url: "https://api.alps.example.com".to_string(),
+      xname: "x1000c0s0b0n0".to_string(),
+      operation: Operation::Patch,
+      previous: previous.map(|image_id| boot_params("x1000c0s0b0n0", image_id)),
+      new: boot_params("x1000c0s0b0n0", new),
+    }
+  }
+
+  #[test]
+  fn state_at_returns_value_before_first_later_change() {
+    let entry_vec = vec![
+      entry("2024-01-01T00:00:00Z", None, "a"),
+      entry("2024-02-01T00:00:00Z", Some("a"), "b"),
+      entry("2024-03-01T00:00:00Z", Some("b"), "c"),
+    ];
+
+    let kernel_at = |timestamp: &str| {
+      state_at(
+        &entry_vec,
+        "https://api.alps.example.com",
+        "x1000c0s0b0n0",
+        timestamp.parse().unwrap(),
+      )
+      .map(|state| state.map(|boot_params| boot_params.kernel.clone()))
+    };
+
+    assert_eq!(
+      kernel_at("2024-01-15T00:00:00Z"),
+      Some(Some("s3://boot-images/a/kernel".to_string()))
+    );
+    assert_eq!(kernel_at("2023-12-01T00:00:00Z"), Some(None));
+    assert_eq!(kernel_at("2024-04-01T00:00:00Z"), None);
+    assert!(
+      state_at(
+        &entry_vec,
+        "https://api.alps.example.com",
+        "x1000c0s0b0n1",
+        "2023-12-01T00:00:00Z".parse().unwrap()
+      )
+      .is_none()
+    );
+    assert!(
+      state_at(
+        &entry_vec,
+        "https://api.daint.example.com",
+        "x1000c0s0b0n0",
+        "2023-12-01T00:00:00Z".parse().unwrap()
+      )
+      .is_none()
+    );
+  }
+
+  #[test]
+  fn journal_round_trips_and_skips_bad_lines() {
+    let path = std::env::temp_dir()
+      .join(format!("csm-rs-bss-history-{}.jsonl", uuid::Uuid::new_v4()));
+
+    let first = entry("2024-01-01T00:00:00Z", None, "a");
+    std::fs::write(
+      &path,
+      format!("{}\nnot json\n", serde_json::to_string(&first).unwrap()),
+    )
+    .unwrap();
+
+    let entry_vec = read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(entry_vec.len(), 1);
+    assert_eq!(entry_vec[0].new.kernel, first.new.kernel);
+  }
+}
@@ -9,6 +9,10 @@
 //! - `wrapper` (private) — `ShastaClient` methods that issue BSS HTTP
 //!   calls. Replaces the historic `http_client` submodule.
 //! - [`types`] — request/response shapes for the BSS API.
+//! - [`bundle`] — YAML bundle of a group's boot parameters, for
+//!   managing kernel parameters as code.
+//! - [`global`] — guarded plan/apply of the `Global` boot parameters.
+//! - [`history`] — optional local journal of boot parameter changes.
 //! - [`utils`] — convenience helpers built on top of the raw client.
 //!
 //! ## How this module is built
@@ -45,7 +49,10 @@
 //! coordinated `manta-backend-dispatcher` release. The generated client
 //! is wired up and ready; the type swap is a follow-up.
 
+pub mod bundle;
 pub(crate) mod generated;
+pub mod global;
+pub mod history;
 /// Integration-style tests for the BSS namespace.
 #[cfg(test)]
 pub mod tests;
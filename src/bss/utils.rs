@@ -1,6 +1,16 @@
 //! Helpers built on top of [`crate::ShastaClient`]`::bss_*` methods.
 
-use super::types::BootParameters;
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::{error::Error, hsm::group::GroupExt};
+
+use super::{
+  bundle::{BootParamsBundle, BundleConflict, ImportPlan},
+  history,
+  types::BootParameters,
+};
 
 /// Extract the IMS image ID from a boot-images S3 path.
 ///
@@ -28,6 +38,210 @@ pub fn find_boot_params_related_to_node(
     .cloned()
 }
 
+/// Restore the kernel parameters, kernel and initrd (i.e. the boot
+/// image) `xnames` had at `to_timestamp`, according to the BSS history
+/// journal of the client in scope (see
+/// [`crate::ClientConfig::with_bss_history`]). Only changes
+/// sent to the system at `shasta_base_url` are considered. Nodes whose
+/// boot parameters didn't change since are left alone. Returns the
+/// boot parameters written back to BSS.
+///
+/// The rollback itself is recorded in the journal, so it can be undone
+/// too.
+///
+/// # Errors
+///
+/// Returns [`Error::ValidationFailed`] if BSS history is disabled, e.g.
+/// when called outside [`crate::ShastaClient::scope`], or an
+/// [`Error`] variant if the journal can't be read or on CSM, transport,
+/// or deserialization failure.
+pub async fn rollback(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  xnames: &[String],
+  to_timestamp: DateTime<Utc>,
+) -> Result<Vec<BootParameters>, Error> {
+  let client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+
+  let journal_path =
+    client
+      .config()
+      .bss_history()
+      .ok_or(Error::ValidationFailed(
+        "BSS history is disabled, nothing to roll back from",
+      ))?;
+  let entry_vec = history::read(journal_path)?;
+
+  // Nodes restored to the same values share one PATCH
+  let mut xnames_by_state: BTreeMap<(&str, &str, &str), Vec<String>> =
+    BTreeMap::new();
+
+  for xname in xnames {
+    match history::state_at(&entry_vec, shasta_base_url, xname, to_timestamp) {
+      None => {
+        tracing::info!(
+          "BSS boot parameters of '{xname}' unchanged since {to_timestamp}"
+        );
+      }
+      Some(None) => {
+        tracing::warn!(
+          "'{xname}' had no BSS boot parameters at {to_timestamp}, not rolled back"
+        );
+      }
+      Some(Some(previous)) => xnames_by_state
+        .entry((
+          previous.params.as_str(),
+          previous.kernel.as_str(),
+          previous.initrd.as_str(),
+        ))
+        .or_default()
+        .push(xname.clone()),
+    }
+  }
+
+  let mut restored_vec = Vec::new();
+  for ((params, kernel, initrd), hosts) in xnames_by_state {
+    let boot_parameters = BootParameters {
+      hosts,
+      params: params.to_string(),
+      kernel: kernel.to_string(),
+      initrd: initrd.to_string(),
+      ..Default::default()
+    };
+
+    tracing::info!(
+      "Rolling back BSS boot parameters of {:?} to {to_timestamp}",
+      boot_parameters.hosts
+    );
+    client
+      .bss_bootparameters_patch(shasta_token, &boot_parameters)
+      .await?;
+
+    restored_vec.push(boot_parameters);
+  }
+
+  Ok(restored_vec)
+}
+
+/// Export the BSS boot parameters of the members of HSM group
+/// `hsm_group_name` as a [`BootParamsBundle`].
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or deserialization
+/// failure; see the crate-level `Error` enum for the full set.
+pub async fn export(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  hsm_group_name: &str,
+) -> Result<BootParamsBundle, Error> {
+  let client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+
+  let member_vec = client
+    .hsm_group_get_one(shasta_token, hsm_group_name)
+    .await?
+    .get_members();
+  let boot_parameters_vec = client
+    .bss_bootparameters_get_multiple(shasta_token, &member_vec)
+    .await?;
+
+  Ok(BootParamsBundle::new(
+    hsm_group_name,
+    &member_vec,
+    &boot_parameters_vec,
+  ))
+}
+
+/// Set the BSS boot parameters of the members of the HSM group of
+/// `bundle` to the ones in the bundle, and return what was done.
+///
+/// Nodes whose boot parameters already match are left alone, as are
+/// the [`BundleConflict`]s: group members missing from the bundle,
+/// bundle nodes no longer in the group or without BSS boot parameters.
+/// Conflicts are logged as warnings. With `dry_run` nothing is written
+/// to BSS.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or deserialization
+/// failure; see the crate-level `Error` enum for the full set.
+pub async fn import(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  bundle: &BootParamsBundle,
+  dry_run: bool,
+) -> Result<ImportPlan, Error> {
+  let client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+
+  let member_vec = client
+    .hsm_group_get_one(shasta_token, &bundle.hsm_group)
+    .await?
+    .get_members();
+  let mut xname_vec = member_vec.clone();
+  xname_vec.extend(bundle.hosts().map(str::to_string));
+  xname_vec.sort();
+  xname_vec.dedup();
+  let boot_parameters_vec = client
+    .bss_bootparameters_get_multiple(shasta_token, &xname_vec)
+    .await?;
+
+  let plan = ImportPlan::new(bundle, &member_vec, &boot_parameters_vec);
+
+  for conflict in &plan.conflicts {
+    match conflict {
+      BundleConflict::NotInBundle { xname } => tracing::warn!(
+        "'{xname}' is a member of '{}' but not in the bundle, left alone",
+        bundle.hsm_group
+      ),
+      BundleConflict::NotInGroup { xname } => tracing::warn!(
+        "'{xname}' is in the bundle but no longer a member of '{}', left alone",
+        bundle.hsm_group
+      ),
+      BundleConflict::NotInBss { xname } => tracing::warn!(
+        "'{xname}' is in the bundle but has no BSS boot parameters, left alone"
+      ),
+    }
+  }
+
+  for boot_parameters in &plan.patches {
+    if dry_run {
+      tracing::info!(
+        "Dry run mode: Set BSS boot parameters of {:?} from bundle",
+        boot_parameters.hosts
+      );
+      continue;
+    }
+
+    tracing::info!(
+      "Setting BSS boot parameters of {:?} from bundle",
+      boot_parameters.hosts
+    );
+    client
+      .bss_bootparameters_patch(shasta_token, boot_parameters)
+      .await?;
+  }
+
+  Ok(plan)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
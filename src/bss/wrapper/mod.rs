@@ -72,20 +72,28 @@
 use core::result::Result;
 use std::time::Instant;
 
-use crate::{ShastaClient, bss::generated, common::http, error::Error};
+use crate::{
+  ShastaClient,
+  bss::generated,
+  common::{
+    http,
+    metrics::{self, MeteredSend},
+  },
+  error::Error,
+};
 
-use super::types::BootParameters;
+use super::{
+  history::{self, HistoryEntry, Operation},
+  types::BootParameters,
+};
 
 #[allow(dead_code)]
 pub(crate) fn gen_client(
   client: &ShastaClient,
   token: &str,
 ) -> Result<generated::Client, Error> {
-  let inner = crate::common::http::build_client_with_auth(
-    client.root_cert(),
-    client.socks5_proxy(),
-    Some(token),
-  )?;
+  let inner =
+    crate::common::http::build_client_with_auth(client.config(), Some(token))?;
   let baseurl = format!("{}/bss", client.base_url());
   Ok(generated::Client::new_with_client(&baseurl, inner))
 }
@@ -130,7 +138,7 @@ pub(crate) async fn run<F, Fut, T, E>(
   op: F,
 ) -> Result<T, Error>
 where
-  F: FnOnce(generated::Client) -> Fut,
+  F: Fn(generated::Client) -> Fut,
   Fut: std::future::Future<
       Output = Result<
         progenitor_client::ResponseValue<T>,
@@ -139,8 +147,7 @@ where
     >,
   E: std::fmt::Debug,
 {
-  let gc = gen_client(client, token)?;
-  match op(gc).await {
+  match metrics::send_generated(client, token, "bss", gen_client, op).await? {
     Ok(rv) => Ok(rv.into_inner()),
     Err(e) => Err(map_err(e).await),
   }
@@ -170,7 +177,7 @@ impl ShastaClient {
       .get(url_api)
       .query(&params)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -193,7 +200,8 @@ impl ShastaClient {
   }
 
   /// `GET /bss/boot/v1/bootparameters` for many xnames, parallelised
-  /// in chunks of 30 with up to 10 concurrent batches.
+  /// in chunks of 30 with up to 10 concurrent batches. `xnames` takes
+  /// `String`s as well as [`crate::XName`]s.
   ///
   /// # Errors
   ///
@@ -203,13 +211,18 @@ impl ShastaClient {
   pub async fn bss_bootparameters_get_multiple(
     &self,
     token: &str,
-    xnames: &[String],
+    xnames: &[impl AsRef<str>],
   ) -> Result<Vec<BootParameters>, Error> {
     let start = Instant::now();
 
+    let xnames: Vec<String> = xnames
+      .iter()
+      .map(|xname| xname.as_ref().to_string())
+      .collect();
+
     let client = self.clone();
     let token = token.to_string();
-    let boot_params_vec = http::parallel_batch(xnames, 30, 10, move |chunk| {
+    let boot_params_vec = http::parallel_batch(&xnames, 30, 10, move |chunk| {
       let client = client.clone();
       let token = token.clone();
       async move { client.bss_bootparameters_get(&token, &chunk).await }
@@ -242,16 +255,21 @@ impl ShastaClient {
       serde_json::to_string_pretty(&boot_parameters)?
     );
 
+    let history_entry_vec = self
+      .bss_history_entries(token, Operation::Put, &boot_parameters)
+      .await;
+
     let response = self
       .http()
       .put(api_url)
       .json(&boot_parameters)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
     if response.status().is_success() {
+      self.record_bss_history(&history_entry_vec);
       Ok(response.json().await?)
     } else {
       Err(Error::Message(response.text().await?))
@@ -277,7 +295,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&boot_parameters)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -303,19 +321,70 @@ impl ShastaClient {
   ) -> Result<(), Error> {
     let api_url = format!("{}/bss/boot/v1/bootparameters", self.base_url());
 
+    let history_entry_vec = self
+      .bss_history_entries(token, Operation::Patch, boot_parameters)
+      .await;
+
     let response = self
       .http()
       .patch(api_url)
       .json(&boot_parameters)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
     if response.status().is_success() {
+      self.record_bss_history(&history_entry_vec);
       Ok(())
     } else {
       Err(Error::Message(response.text().await?))
     }
   }
+
+  /// History entries for `boot_parameters`, or none if BSS history is
+  /// disabled (see [`super::history`]) or the nodes' current boot
+  /// parameters can't be fetched.
+  async fn bss_history_entries(
+    &self,
+    token: &str,
+    operation: Operation,
+    boot_parameters: &BootParameters,
+  ) -> Vec<HistoryEntry> {
+    if self.config().bss_history().is_none() || boot_parameters.hosts.is_empty()
+    {
+      return Vec::new();
+    }
+
+    match self
+      .bss_bootparameters_get(token, &boot_parameters.hosts)
+      .await
+    {
+      Ok(previous_vec) => history::entries(
+        self.base_url(),
+        operation,
+        boot_parameters,
+        &previous_vec,
+      ),
+      Err(e) => {
+        tracing::warn!(
+          "Could not fetch current BSS boot parameters of {:?}, change not recorded in BSS history: {e}",
+          boot_parameters.hosts
+        );
+        Vec::new()
+      }
+    }
+  }
+
+  /// Record a change already applied in BSS, if BSS history is enabled;
+  /// failing to do so doesn't fail the change.
+  fn record_bss_history(&self, history_entry_vec: &[HistoryEntry]) {
+    let Some(path) = self.config().bss_history() else {
+      return;
+    };
+
+    if let Err(e) = history::record(path, history_entry_vec) {
+      tracing::warn!("Could not record change in BSS history: {e}");
+    }
+  }
 }
@@ -8,7 +8,7 @@ use crate::{
     },
     utils::{wait_nodes_to_power_off, wait_nodes_to_power_on},
   },
-  common::http,
+  common::{http, metrics::MeteredSend, poll::WaitOptions},
   error::Error,
 };
 
@@ -41,9 +41,8 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&power_off)
-      .send()
-      .await
-      .map_err(Error::NetError)?;
+      .send_metered()
+      .await?;
     http::handle_json_response(response, "POST").await
   }
 
@@ -51,7 +50,9 @@ impl ShastaClient {
   /// as `off`.
   ///
   /// This wrapper around [`Self::capmc_node_power_off_post`] polls
-  /// `xname_status` until every target is down before returning.
+  /// `xname_status` until every target is down, or `wait_options`
+  /// times out, before returning. See
+  /// [`crate::capmc::utils::DEFAULT_WAIT`].
   ///
   /// # Errors
   ///
@@ -64,11 +65,20 @@ impl ShastaClient {
     xname_vec: Vec<String>,
     reason_opt: Option<String>,
     force: bool,
+    wait_options: WaitOptions,
   ) -> Result<XnameStatusResponse, Error> {
     // Check Nodes are shutdown
     let _ = self.capmc_node_power_status_post(token, &xname_vec).await?;
 
-    wait_nodes_to_power_off(self, token, xname_vec, reason_opt, force).await
+    wait_nodes_to_power_off(
+      self,
+      token,
+      xname_vec,
+      reason_opt,
+      force,
+      wait_options,
+    )
+    .await
   }
 
   /// Issue a CAPMC power-on request for the given xnames and return
@@ -94,14 +104,14 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&power_on)
-      .send()
-      .await
-      .map_err(Error::NetError)?;
+      .send_metered()
+      .await?;
     http::handle_json_response(response, "POST").await
   }
 
   /// Power on the given xnames and wait until CAPMC reports each one
-  /// as `on`.
+  /// as `on`, or `wait_options` times out. See
+  /// [`crate::capmc::utils::DEFAULT_WAIT`].
   ///
   /// # Errors
   ///
@@ -113,10 +123,11 @@ impl ShastaClient {
     token: &str,
     xname_vec: Vec<String>,
     reason: Option<String>,
+    wait_options: WaitOptions,
   ) -> Result<XnameStatusResponse, Error> {
     let _ = self.capmc_node_power_status_post(token, &xname_vec).await?;
 
-    wait_nodes_to_power_on(self, token, xname_vec, reason).await
+    wait_nodes_to_power_on(self, token, xname_vec, reason, wait_options).await
   }
 
   /// Issue a CAPMC reinit (power-cycle) request and return immediately.
@@ -142,9 +153,8 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&node_restart)
-      .send()
-      .await
-      .map_err(Error::NetError)?;
+      .send_metered()
+      .await?;
     http::handle_json_response(response, "POST").await
   }
 
@@ -152,7 +162,8 @@ impl ShastaClient {
   /// synchronously, waiting for each transition to complete.
   ///
   /// Implemented as `power_off_post_sync` followed by
-  /// `power_on_post_sync` for the same set of xnames.
+  /// `power_on_post_sync` for the same set of xnames, each waiting as
+  /// `wait_options` says.
   ///
   /// # Errors
   ///
@@ -165,6 +176,7 @@ impl ShastaClient {
     xname_vec: Vec<String>,
     reason_opt: Option<String>,
     force: bool,
+    wait_options: WaitOptions,
   ) -> Result<XnameStatusResponse, Error> {
     log::debug!("Power RESET node: {xname_vec:?}");
 
@@ -174,11 +186,12 @@ impl ShastaClient {
         xname_vec.clone(),
         reason_opt.clone(),
         force,
+        wait_options,
       )
       .await?;
 
     self
-      .capmc_node_power_on_post_sync(token, xname_vec, reason_opt)
+      .capmc_node_power_on_post_sync(token, xname_vec, reason_opt, wait_options)
       .await
   }
 
@@ -201,6 +214,7 @@ impl ShastaClient {
     xnames: Vec<String>,
     reason_opt: Option<String>,
     force: bool,
+    wait_options: WaitOptions,
   ) -> Result<Vec<XnameStatusResponse>, Error> {
     let mut nodes_reseted = Vec::new();
     let mut tasks = tokio::task::JoinSet::new();
@@ -217,6 +231,7 @@ impl ShastaClient {
             vec![xname],
             reason_cloned,
             force,
+            wait_options,
           )
           .await
       });
@@ -255,9 +270,8 @@ impl ShastaClient {
       .post(url_api)
       .bearer_auth(token)
       .json(&node_status_payload)
-      .send()
-      .await
-      .map_err(Error::NetError)?;
+      .send_metered()
+      .await?;
     http::handle_json_response(response, "POST").await
   }
 }
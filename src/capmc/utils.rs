@@ -5,19 +5,19 @@ use std::time::Duration;
 use crate::{
   ShastaClient,
   capmc::types::XnameStatusResponse,
-  common::poll::{PollBackoff, poll_until_with_backoff},
+  common::poll::{WaitOptions, poll_until_with_backoff},
   error::Error,
 };
 
-const POWER_TRANSITION_BACKOFF: PollBackoff = PollBackoff {
-  initial_delay: Duration::from_secs(3),
-  max_delay: Duration::from_secs(10),
-  max_attempts: 40,
-};
+/// How long [`wait_nodes_to_power_on`] and [`wait_nodes_to_power_off`]
+/// wait unless told otherwise: about 6 minutes, checking after 3 s and
+/// backing off to 10 s.
+pub const DEFAULT_WAIT: WaitOptions =
+  WaitOptions::new(Duration::from_secs(380), Duration::from_secs(3))
+    .with_backoff(Duration::from_secs(10));
 
-/// Issue repeated CAPMC power-on requests, polling status with
-/// exponential backoff until every xname reports as "on" or the
-/// attempt cap is reached.
+/// Issue repeated CAPMC power-on requests, polling status until every
+/// xname reports as "on" or `wait_options.timeout` expires.
 ///
 /// # Errors
 ///
@@ -29,9 +29,10 @@ pub async fn wait_nodes_to_power_on(
   token: &str,
   xname_vec: Vec<String>,
   reason: Option<String>,
+  wait_options: WaitOptions,
 ) -> Result<XnameStatusResponse, Error> {
   poll_until_with_backoff(
-    POWER_TRANSITION_BACKOFF,
+    wait_options.poll_backoff(),
     || async {
       if let Err(e) = client
         .capmc_node_power_on_post(token, xname_vec.clone(), reason.clone())
@@ -51,8 +52,8 @@ pub async fn wait_nodes_to_power_on(
 }
 
 /// Issue repeated CAPMC power-off requests (graceful unless `force`),
-/// polling status with exponential backoff until every xname reports
-/// "off" or the attempt cap is reached.
+/// polling status until every xname reports "off" or
+/// `wait_options.timeout` expires.
 ///
 /// # Errors
 ///
@@ -65,9 +66,10 @@ pub async fn wait_nodes_to_power_off(
   xname_vec: Vec<String>,
   reason_opt: Option<String>,
   force: bool,
+  wait_options: WaitOptions,
 ) -> Result<XnameStatusResponse, Error> {
   poll_until_with_backoff(
-    POWER_TRANSITION_BACKOFF,
+    wait_options.poll_backoff(),
     || async {
       let _ = client
         .capmc_node_power_off_post(
@@ -1,371 +1,969 @@
 //! Cascade-delete a CFS configuration along with its derived images,
 //! CFS sessions, and BOS session templates.
 //!
+//! [`DeletionPlan::build`] works out what would be deleted and what is
+//! kept because nodes depend on it, without side effects;
+//! [`DeletionPlan::execute`] deletes. Resources protected with
+//! [`crate::protection`] are kept unless the plan is built ignoring
+//! protection.
+//!
 //! Moved here from `commands::delete_configurations_and_data_related`
 //! in the architecture re-audit so the dispatcher
 //! ([`crate::backend_connector::cleanup`]) can call the domain helper
 //! directly instead of reaching across into the `commands` layer.
 
-use core::time;
-use std::collections::HashMap;
-use std::time::Instant;
+use std::fmt;
+use std::time::{Duration, Instant};
 
 use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+  ExecutionContext,
+  bos::BosSessionTemplate,
   bss::types::BootParameters,
   cfs::{
     self,
-    v2::{CfsConfigurationResponse, CfsSessionGetResponse},
+    compat::CfsApiVersion,
+    v2::{CfsConfigurationResponse, CfsSessionGetResponse, Component},
+  },
+  common::{
+    csm_snapshot::CsmSnapshot,
+    events::{self, Event},
+    poll::WaitOptions,
+    protection,
+    webhooks::{self, DeletedResource},
   },
-  common,
   error::Error,
+  hsm::{
+    self,
+    group::{GroupExt, tenancy::Tenancy},
+  },
 };
 
-/// Compute the cascade of resources reachable from a CFS configuration
-/// filter — what [`delete`] would actually remove.
-///
-/// Cross-references CFS sessions, IMS images, BOS session templates,
-/// and BSS boot parameters against the configurations matched by
-/// `configuration_name_pattern_opt` and the time window
-/// (`since_opt`/`until_opt`). Use this as a dry-run before calling
-/// [`delete`] with the same inputs.
-///
-/// Returns a tuple of
-/// `(sessions, image_refs, image_ids, boot_image_ids,
-/// session_template_refs, configurations)`.
-///
-/// # Errors
+/// How long [`delete`] retries a failed deletion unless told otherwise:
+/// 12 s, every 2 s.
+pub const DEFAULT_DELETE_RETRY: WaitOptions =
+  WaitOptions::new(Duration::from_secs(12), Duration::from_secs(2));
+
+/// A resource [`DeletionPlan`] deletes or keeps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "name", rename_all = "snake_case")]
+pub enum DeletionResource {
+  /// CFS configuration, by name.
+  CfsConfiguration(String),
+  /// CFS session, by name.
+  CfsSession(String),
+  /// BOS session template, by name.
+  BosSessionTemplate(String),
+  /// IMS image, by id.
+  ImsImage(String),
+}
+
+impl fmt::Display for DeletionResource {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::CfsConfiguration(name) => write!(f, "CFS configuration '{name}'"),
+      Self::CfsSession(name) => write!(f, "CFS session '{name}'"),
+      Self::BosSessionTemplate(name) => {
+        write!(f, "BOS sessiontemplate '{name}'")
+      }
+      Self::ImsImage(id) => write!(f, "IMS image '{id}'"),
+    }
+  }
+}
+
+/// Why [`DeletionPlan`] keeps a resource.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", content = "detail", rename_all = "snake_case")]
+pub enum BlockReason {
+  /// The CFS configuration is the desired configuration of these
+  /// nodes.
+  ConfiguresNodes(Vec<String>),
+  /// These nodes boot the IMS image.
+  BootsNodes(Vec<String>),
+  /// The resource is related to a resource kept for one of the reasons
+  /// above.
+  RelatedTo(DeletionResource),
+  /// No CFS session or BOS sessiontemplate uses the CFS configuration,
+  /// so there is nothing to tell whether it belongs to the caller.
+  NoDerivatives,
+  /// The resource is protected, see [`crate::protection`].
+  Protected,
+}
+
+impl BlockReason {
+  /// Whether `force` deletes the resource anyway. Resources configuring
+  /// or booting nodes and protected resources are never deleted.
+  #[must_use]
+  pub fn is_overridable(&self) -> bool {
+    matches!(self, Self::RelatedTo(_) | Self::NoDerivatives)
+  }
+}
+
+impl fmt::Display for BlockReason {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::ConfiguresNodes(node_vec) => write!(
+        f,
+        "used as desired configuration for nodes: {}",
+        node_vec.join(", ")
+      ),
+      Self::BootsNodes(node_vec) => {
+        write!(f, "used to boot nodes: {}", node_vec.join(", "))
+      }
+      Self::RelatedTo(resource) => write!(f, "related to {resource}"),
+      Self::NoDerivatives => {
+        f.write_str("no CFS session or BOS sessiontemplate uses it")
+      }
+      Self::Protected => f.write_str("protected against deletion"),
+    }
+  }
+}
+
+/// A resource [`DeletionPlan`] keeps, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockedResource {
+  /// The resource kept.
+  pub resource: DeletionResource,
+  /// Why it is kept.
+  pub reason: BlockReason,
+}
+
+/// What deleting a set of CFS configurations and their derivatives
+/// would remove, and what it keeps because nodes depend on it.
 ///
-/// Returns an [`Error`] variant on CSM, transport, or
-/// deserialization failure; see the crate-level `Error` enum
-/// for the full set.
-#[allow(clippy::too_many_arguments)]
-pub async fn get_data_to_delete(
+/// [`DeletionPlan::build`] only reads from CSM;
+/// [`DeletionPlan::execute`] deletes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeletionPlan {
+  /// CFS configurations to delete.
+  pub cfs_configurations: Vec<CfsConfigurationResponse>,
+  /// CFS sessions to delete.
+  pub cfs_sessions: Vec<CfsSessionGetResponse>,
+  /// BOS session templates to delete.
+  pub bos_sessiontemplates: Vec<BosSessionTemplate>,
+  /// IMS images built by the CFS sessions, to delete.
+  pub images: Vec<String>,
+  /// Resources kept, with the reason.
+  pub blocked: Vec<BlockedResource>,
+}
+
+impl DeletionPlan {
+  /// Plan the deletion of the CFS configurations matched by
+  /// `configuration_name_pattern_opt` and the time window
+  /// (`since_opt`/`until_opt`) among those related to
+  /// `hsm_name_available_vec`, with the CFS sessions, IMS images and BOS
+  /// session templates derived from them.
+  ///
+  /// Candidates outside the [`Tenancy`] of `execution_context` are left
+  /// out before validation: HSM groups it doesn't own are ignored, and
+  /// so are the CFS sessions and BOS session templates targeting them.
+  ///
+  /// Without `force`, every resource related to a configuration or image
+  /// used by nodes is kept, as are configurations nothing uses. With
+  /// `force`, only the configurations and images used by nodes are
+  /// kept.
+  ///
+  /// Protected CFS configurations and BOS session templates, and the
+  /// images those templates boot, are kept as well unless
+  /// `ignore_protection` is set.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  #[allow(clippy::too_many_arguments)]
+  pub async fn build(
+    client: &crate::ShastaClient,
+    shasta_token: &str,
+    execution_context: &ExecutionContext,
+    hsm_name_available_vec: &[String],
+    configuration_name_pattern_opt: Option<&str>,
+    since_opt: Option<NaiveDateTime>,
+    until_opt: Option<NaiveDateTime>,
+    force: bool,
+    ignore_protection: bool,
+  ) -> Result<Self, Error> {
+    Self::build_from_snapshot(
+      &CsmSnapshot::new(client, shasta_token),
+      execution_context,
+      hsm_name_available_vec,
+      configuration_name_pattern_opt,
+      since_opt,
+      until_opt,
+      force,
+      ignore_protection,
+    )
+    .await
+  }
+
+  /// [`Self::build`] on the collections of `snapshot`, fetching those
+  /// it doesn't hold yet, so the plan agrees with what the rest of the
+  /// command saw.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  #[allow(clippy::too_many_arguments)]
+  pub async fn build_from_snapshot(
+    snapshot: &CsmSnapshot,
+    execution_context: &ExecutionContext,
+    hsm_name_available_vec: &[String],
+    configuration_name_pattern_opt: Option<&str>,
+    since_opt: Option<NaiveDateTime>,
+    until_opt: Option<NaiveDateTime>,
+    force: bool,
+    ignore_protection: bool,
+  ) -> Result<Self, Error> {
+    crate::common::request_id::scope(
+      "deletion_plan",
+      build_plan(
+        snapshot,
+        execution_context,
+        hsm_name_available_vec,
+        configuration_name_pattern_opt,
+        since_opt,
+        until_opt,
+        force,
+        ignore_protection,
+      ),
+    )
+    .await
+  }
+
+  /// Pure part of [`Self::build`], split out for testing.
+  /// `cfs_configuration_vec`, `cfs_session_vec` and
+  /// `bos_sessiontemplate_vec` are the candidates, already filtered.
+  /// `protected_vec` lists the protected ones, kept whatever `force`
+  /// says.
+  #[must_use]
+  pub fn from_data(
+    cfs_configuration_vec: &[CfsConfigurationResponse],
+    cfs_session_vec: &[CfsSessionGetResponse],
+    bos_sessiontemplate_vec: &[BosSessionTemplate],
+    cfs_component_vec: &[Component],
+    bss_bootparameters_vec: &[BootParameters],
+    protected_vec: &[DeletionResource],
+    force: bool,
+  ) -> Self {
+    let mut plan = Self::default();
+
+    // Resources configuring or booting nodes are always kept
+    let mut blocked_configuration_vec: Vec<&str> = Vec::new();
+    let mut blocked_image_vec: Vec<&str> = Vec::new();
+
+    for cfs_configuration in cfs_configuration_vec {
+      let node_vec =
+        cfs::configuration::utils::nodes_with_desired_configuration(
+          &cfs_configuration.name,
+          cfs_component_vec,
+        );
+      if !node_vec.is_empty() {
+        blocked_configuration_vec.push(&cfs_configuration.name);
+        plan.blocked.push(BlockedResource {
+          resource: DeletionResource::CfsConfiguration(
+            cfs_configuration.name.clone(),
+          ),
+          reason: BlockReason::ConfiguresNodes(node_vec),
+        });
+      }
+    }
+
+    let mut candidate_image_vec: Vec<&str> =
+      cfs::session::utils::images_id_from_cfs_session(cfs_session_vec)
+        .chain(
+          bos_sessiontemplate_vec
+            .iter()
+            .flat_map(BosSessionTemplate::images_id),
+        )
+        .collect();
+    candidate_image_vec.sort_unstable();
+    candidate_image_vec.dedup();
+
+    for image_id in candidate_image_vec {
+      let node_vec =
+        get_node_vec_booting_image(image_id, bss_bootparameters_vec);
+      if !node_vec.is_empty() {
+        blocked_image_vec.push(image_id);
+        plan.blocked.push(BlockedResource {
+          resource: DeletionResource::ImsImage(image_id.to_string()),
+          reason: BlockReason::BootsNodes(node_vec),
+        });
+      }
+    }
+
+    // Protected resources are kept too, with the images the protected
+    // templates boot
+    for cfs_configuration in cfs_configuration_vec {
+      let resource =
+        DeletionResource::CfsConfiguration(cfs_configuration.name.clone());
+      if protected_vec.contains(&resource)
+        && !blocked_configuration_vec.contains(&cfs_configuration.name.as_str())
+      {
+        blocked_configuration_vec.push(&cfs_configuration.name);
+        plan.blocked.push(BlockedResource {
+          resource,
+          reason: BlockReason::Protected,
+        });
+      }
+    }
+
+    for bos_sessiontemplate in bos_sessiontemplate_vec {
+      let resource = DeletionResource::BosSessionTemplate(
+        bos_sessiontemplate.name.clone().unwrap_or_default(),
+      );
+      if !protected_vec.contains(&resource) {
+        continue;
+      }
+      for image_id in bos_sessiontemplate.images_id() {
+        if !blocked_image_vec.contains(&image_id) {
+          blocked_image_vec.push(image_id);
+          plan.blocked.push(BlockedResource {
+            resource: DeletionResource::ImsImage(image_id.to_string()),
+            reason: BlockReason::RelatedTo(resource.clone()),
+          });
+        }
+      }
+      plan.blocked.push(BlockedResource {
+        resource,
+        reason: BlockReason::Protected,
+      });
+    }
+
+    for cfs_session in cfs_session_vec {
+      let related_opt = if force {
+        None
+      } else {
+        blocked_relative(
+          cfs_session.configuration_name(),
+          cfs_session.results_id(),
+          &blocked_configuration_vec,
+          &blocked_image_vec,
+        )
+      };
+      if let Some(related) = related_opt {
+        plan.blocked.push(BlockedResource {
+          resource: DeletionResource::CfsSession(cfs_session.name.clone()),
+          reason: BlockReason::RelatedTo(related),
+        });
+        continue;
+      }
+      plan.cfs_sessions.push(cfs_session.clone());
+    }
+
+    // Images built by the CFS sessions
+    for cfs_session in cfs_session_vec {
+      for image_id in cfs_session.results_id() {
+        if blocked_image_vec.contains(&image_id)
+          || plan.images.iter().any(|id| id == image_id)
+        {
+          continue;
+        }
+        let related_opt = if force {
+          None
+        } else {
+          blocked_relative(
+            cfs_session.configuration_name(),
+            std::iter::empty(),
+            &blocked_configuration_vec,
+            &blocked_image_vec,
+          )
+        };
+        if let Some(related) = related_opt {
+          plan.blocked.push(BlockedResource {
+            resource: DeletionResource::ImsImage(image_id.to_string()),
+            reason: BlockReason::RelatedTo(related),
+          });
+          continue;
+        }
+        plan.images.push(image_id.to_string());
+      }
+    }
+
+    for bos_sessiontemplate in bos_sessiontemplate_vec {
+      if protected_vec.contains(&DeletionResource::BosSessionTemplate(
+        bos_sessiontemplate.name.clone().unwrap_or_default(),
+      )) {
+        continue;
+      }
+      let related_opt = if force {
+        None
+      } else {
+        blocked_relative(
+          bos_sessiontemplate.get_configuration(),
+          bos_sessiontemplate.images_id(),
+          &blocked_configuration_vec,
+          &blocked_image_vec,
+        )
+      };
+      if let Some(related) = related_opt {
+        plan.blocked.push(BlockedResource {
+          resource: DeletionResource::BosSessionTemplate(
+            bos_sessiontemplate.name.clone().unwrap_or_default(),
+          ),
+          reason: BlockReason::RelatedTo(related),
+        });
+        continue;
+      }
+      plan.bos_sessiontemplates.push(bos_sessiontemplate.clone());
+    }
+
+    // Configurations nothing uses can't be traced back to the caller
+    for cfs_configuration in cfs_configuration_vec {
+      if blocked_configuration_vec.contains(&cfs_configuration.name.as_str()) {
+        continue;
+      }
+
+      let has_derivatives =
+        cfs_session_vec.iter().any(|cfs_session| {
+          cfs_session.configuration_name()
+            == Some(cfs_configuration.name.as_str())
+        }) || bos_sessiontemplate_vec.iter().any(|bos_sessiontemplate| {
+          bos_sessiontemplate.get_configuration()
+            == Some(cfs_configuration.name.as_str())
+        });
+
+      if !has_derivatives && !force {
+        plan.blocked.push(BlockedResource {
+          resource: DeletionResource::CfsConfiguration(
+            cfs_configuration.name.clone(),
+          ),
+          reason: BlockReason::NoDerivatives,
+        });
+        continue;
+      }
+
+      plan.cfs_configurations.push(cfs_configuration.clone());
+    }
+
+    plan
+  }
+
+  /// Whether the plan deletes nothing.
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.cfs_configurations.is_empty()
+      && self.cfs_sessions.is_empty()
+      && self.bos_sessiontemplates.is_empty()
+      && self.images.is_empty()
+  }
+
+  /// Whether nodes depend on any resource matched, as desired
+  /// configuration or boot image.
+  #[must_use]
+  pub fn affects_nodes(&self) -> bool {
+    self.blocked.iter().any(|blocked| {
+      matches!(
+        blocked.reason,
+        BlockReason::ConfiguresNodes(_) | BlockReason::BootsNodes(_)
+      )
+    })
+  }
+
+  /// Delete the resources of the plan; the blocked ones are left
+  /// alone. See [`delete`]; the deletions are reported with
+  /// `execution_context`, and failed ones retried as `retry_options`
+  /// says ([`DEFAULT_DELETE_RETRY`] if unsure).
+  ///
+  /// The nodes targeted by the CFS sessions and BOS session templates
+  /// deleted are disabled in HSM meanwhile, see
+  /// [`hsm::component::with_disabled_for_service`].
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn execute(
+    &self,
+    client: &crate::ShastaClient,
+    shasta_token: &str,
+    execution_context: &ExecutionContext,
+    retry_options: WaitOptions,
+  ) -> Result<(), Error> {
+    let cfs_configuration_name_vec: Vec<String> = self
+      .cfs_configurations
+      .iter()
+      .map(|cfs_configuration| cfs_configuration.name.clone())
+      .collect();
+    let cfs_session_name_vec: Vec<String> = self
+      .cfs_sessions
+      .iter()
+      .map(|cfs_session| cfs_session.name.clone())
+      .collect();
+    let bos_sessiontemplate_name_vec: Vec<String> = self
+      .bos_sessiontemplates
+      .iter()
+      .filter_map(|bos_sessiontemplate| bos_sessiontemplate.name.clone())
+      .collect();
+
+    let target_xname_vec = self.target_xnames(client, shasta_token).await?;
+
+    execution_context
+      .scope(hsm::component::with_disabled_for_service(
+        client,
+        shasta_token,
+        &target_xname_vec,
+        delete(
+          client,
+          shasta_token,
+          &cfs_configuration_name_vec,
+          &self.images,
+          &cfs_session_name_vec,
+          &bos_sessiontemplate_name_vec,
+          retry_options,
+        ),
+      ))
+      .await
+  }
+
+  /// Sorted xnames of the nodes targeted by the CFS sessions and BOS
+  /// session templates of the plan, directly or through HSM groups.
+  async fn target_xnames(
+    &self,
+    client: &crate::ShastaClient,
+    shasta_token: &str,
+  ) -> Result<Vec<String>, Error> {
+    let mut target_hsm_vec: Vec<String> = Vec::new();
+    let mut target_xname_vec: Vec<String> = Vec::new();
+
+    for cfs_session in &self.cfs_sessions {
+      target_hsm_vec.extend(cfs_session.get_target_hsm().unwrap_or_default());
+      target_xname_vec
+        .extend(cfs_session.get_target_xname().unwrap_or_default());
+    }
+    for bos_sessiontemplate in &self.bos_sessiontemplates {
+      target_hsm_vec.extend(bos_sessiontemplate.get_target_hsm());
+      target_xname_vec.extend(bos_sessiontemplate.get_target_xname());
+    }
+
+    target_hsm_vec.sort();
+    target_hsm_vec.dedup();
+
+    if !target_hsm_vec.is_empty() {
+      for hsm_group in client
+        .hsm_group_get(shasta_token, Some(&target_hsm_vec), None)
+        .await?
+      {
+        target_xname_vec.extend(hsm_group.get_members());
+      }
+    }
+
+    target_xname_vec.sort();
+    target_xname_vec.dedup();
+
+    Ok(target_xname_vec)
+  }
+}
+
+/// `configuration_name_opt` if it is in `blocked_configuration_vec`,
+/// else the first of `image_id_iter` in `blocked_image_vec`.
+fn blocked_relative<'a>(
+  configuration_name_opt: Option<&str>,
+  mut image_id_iter: impl Iterator<Item = &'a str>,
+  blocked_configuration_vec: &[&str],
+  blocked_image_vec: &[&str],
+) -> Option<DeletionResource> {
+  configuration_name_opt
+    .filter(|name| blocked_configuration_vec.contains(name))
+    .map(|name| DeletionResource::CfsConfiguration(name.to_string()))
+    .or_else(|| {
+      image_id_iter
+        .find(|image_id| blocked_image_vec.contains(image_id))
+        .map(|image_id| DeletionResource::ImsImage(image_id.to_string()))
+    })
+}
+
+/// Drop the deletion candidates outside `tenancy`: CFS sessions and BOS
+/// session templates targeting HSM groups it doesn't own or nodes
+/// outside `tenant_xname_vec`, and CFS configurations those use. A
+/// configuration nothing in the tenancy uses is kept only if its name
+/// says it belongs to the tenancy.
+fn restrict_to_tenancy(
+  tenancy: &Tenancy,
+  tenant_xname_vec: &[String],
+  cfs_configuration_vec: &mut Vec<CfsConfigurationResponse>,
+  cfs_session_vec: &mut Vec<CfsSessionGetResponse>,
+  bos_sessiontemplate_vec: &mut Vec<BosSessionTemplate>,
+) {
+  if tenancy.is_unrestricted() {
+    return;
+  }
+
+  let in_tenancy = |group_vec: &[String], xname_vec: &[String]| {
+    group_vec.iter().all(|group| tenancy.owns(group))
+      && xname_vec
+        .iter()
+        .all(|xname| tenant_xname_vec.contains(xname))
+  };
+
+  let mut foreign_configuration_vec: Vec<String> = Vec::new();
+
+  cfs_session_vec.retain(|cfs_session| {
+    let keep = in_tenancy(
+      &cfs_session.get_target_hsm().unwrap_or_default(),
+      &cfs_session.get_target_xname().unwrap_or_default(),
+    );
+    if !keep {
+      foreign_configuration_vec
+        .extend(cfs_session.configuration_name().map(str::to_string));
+    }
+    keep
+  });
+
+  bos_sessiontemplate_vec.retain(|bos_sessiontemplate| {
+    let keep = in_tenancy(
+      &bos_sessiontemplate.get_target_hsm(),
+      &bos_sessiontemplate.get_target_xname(),
+    );
+    if !keep {
+      foreign_configuration_vec
+        .extend(bos_sessiontemplate.get_configuration().map(str::to_string));
+    }
+    keep
+  });
+
+  cfs_configuration_vec.retain(|cfs_configuration| {
+    let name = cfs_configuration.name.as_str();
+    let used_in_tenancy = cfs_session_vec
+      .iter()
+      .any(|cfs_session| cfs_session.configuration_name() == Some(name))
+      || bos_sessiontemplate_vec.iter().any(|bos_sessiontemplate| {
+        bos_sessiontemplate.get_configuration() == Some(name)
+      });
+
+    !foreign_configuration_vec
+      .iter()
+      .any(|foreign| foreign == name)
+      && (used_in_tenancy || tenancy.owns(name))
+  });
+}
+
+/// The protected resources among `cfs_configuration_vec` and
+/// `bos_sessiontemplate_vec`. CFS v2 configurations carry no
+/// description, so the configurations are looked up in CFS v3.
+async fn protected_resources(
   client: &crate::ShastaClient,
   shasta_token: &str,
+  cfs_configuration_vec: &[CfsConfigurationResponse],
+  bos_sessiontemplate_vec: &[BosSessionTemplate],
+) -> Result<Vec<DeletionResource>, Error> {
+  let mut protected_vec: Vec<DeletionResource> = bos_sessiontemplate_vec
+    .iter()
+    .filter(|bos_sessiontemplate| {
+      protection::is_protected(bos_sessiontemplate.description.as_deref())
+    })
+    .filter_map(|bos_sessiontemplate| bos_sessiontemplate.name.clone())
+    .map(DeletionResource::BosSessionTemplate)
+    .collect();
+
+  // CFS v2 configurations have no description to carry the marker
+  if cfs_configuration_vec.is_empty()
+    || client.cfs_api_version(shasta_token).await? == CfsApiVersion::V2
+  {
+    return Ok(protected_vec);
+  }
+
+  protected_vec.extend(
+    client
+      .cfs_configuration_v3_get(shasta_token, None)
+      .await?
+      .into_iter()
+      .filter(|cfs_configuration| {
+        protection::is_protected(cfs_configuration.description.as_deref())
+          && cfs_configuration_vec
+            .iter()
+            .any(|candidate| candidate.name == cfs_configuration.name)
+      })
+      .map(|cfs_configuration| {
+        DeletionResource::CfsConfiguration(cfs_configuration.name)
+      }),
+  );
+
+  Ok(protected_vec)
+}
+
+/// Body of [`DeletionPlan::build`], run inside its correlation scope.
+#[allow(clippy::too_many_arguments)]
+async fn build_plan(
+  snapshot: &CsmSnapshot,
+  execution_context: &ExecutionContext,
   hsm_name_available_vec: &[String],
   configuration_name_pattern_opt: Option<&str>,
   since_opt: Option<NaiveDateTime>,
   until_opt: Option<NaiveDateTime>,
-) -> Result<
-  (
-    Vec<CfsSessionGetResponse>,
-    Vec<(String, String, String)>,
-    Vec<String>,
-    Vec<String>,
-    Vec<(String, String, String)>,
-    Vec<CfsConfigurationResponse>,
-  ),
-  Error,
-> {
+  force: bool,
+  ignore_protection: bool,
+) -> Result<DeletionPlan, Error> {
+  // Only consider the groups the caller owns, so they never get to see
+  // or touch other tenants' resources
+  let tenancy = execution_context.tenancy();
+  let hsm_name_available_vec = tenancy.filter_groups(hsm_name_available_vec);
+
+  if !tenancy.is_unrestricted() && hsm_name_available_vec.is_empty() {
+    events::warning("None of the HSM groups requested belongs to you");
+    return Ok(DeletionPlan::default());
+  }
+
+  let client = snapshot.client();
+  let shasta_token = snapshot.shasta_token();
+
   // COLLECT SITE WIDE DATA FOR VALIDATION
   //
-  let xname_from_groups_vec =
-    crate::hsm::group::utils::get_member_vec_from_hsm_name_vec(
+  let xname_from_groups_vec = client
+    .scope(crate::hsm::group::utils::get_member_vec_from_hsm_name_vec(
       shasta_token,
       client.base_url(),
       client.root_cert(),
       client.socks5_proxy(),
-      hsm_name_available_vec,
-    )
+      &hsm_name_available_vec,
+    ))
     .await?;
 
   let start = Instant::now();
-  log::info!("Fetching data from the backend...");
+  events::step("Fetching data from the backend");
   let (
     cfs_component_vec,
-    mut cfs_configuration_vec,
+    cfs_configuration_vec,
     cfs_session_vec,
     bos_sessiontemplate_vec,
     bss_bootparameters_vec,
   ) = tokio::try_join!(
-    client.cfs_component_v2_get_all(shasta_token),
-    client.cfs_configuration_v2_get_all(shasta_token),
-    client.cfs_session_v2_get_all(shasta_token),
-    client.bos_template_v2_get_all(shasta_token),
-    client.bss_bootparameters_get_all(shasta_token),
+    snapshot.cfs_components(),
+    snapshot.cfs_configurations(),
+    snapshot.cfs_sessions(),
+    snapshot.bos_sessiontemplates(),
+    snapshot.bss_bootparameters(),
   )?;
+  let mut cfs_configuration_vec = cfs_configuration_vec.to_vec();
+  let mut cfs_session_vec = cfs_session_vec.to_vec();
+  let mut bos_sessiontemplate_vec = bos_sessiontemplate_vec.to_vec();
 
   let duration = start.elapsed();
-  log::info!(
+  tracing::debug!(
     "Time elapsed to fetch information from backend: {duration:?}"
   );
 
-  let mut cfs_session_to_delete_vec = cfs_session_vec.clone();
-  let mut bos_sessiontemplate_to_delete_vec = bos_sessiontemplate_vec.clone();
-
-  let keep_generic_sessions = common::jwt_ops::is_user_admin(shasta_token);
+  let keep_generic_sessions = tenancy.is_unrestricted();
 
   // Filter CFS configurations related to HSM group, configuration name or configuration name
   // pattern
   cfs::configuration::utils::filter(
     &mut cfs_configuration_vec,
     &xname_from_groups_vec,
-    &mut cfs_session_to_delete_vec,
-    &mut bos_sessiontemplate_to_delete_vec,
-    &cfs_component_vec,
+    &mut cfs_session_vec,
+    &mut bos_sessiontemplate_vec,
+    cfs_component_vec,
     configuration_name_pattern_opt,
-    hsm_name_available_vec,
+    &hsm_name_available_vec,
     since_opt,
     until_opt,
     None,
     keep_generic_sessions,
   )?;
 
-  let mut cfs_configuration_vec_filtered = cfs_configuration_vec.clone();
-
-  // Get CFS configurations related with BOS sessiontemplate
-  let cfs_configuration_name_from_bos_sessiontemplate_value_iter =
-    bos_sessiontemplate_to_delete_vec
-      .iter()
-      .map(|bos_sessiontemplate| {
-        bos_sessiontemplate.get_configuration().unwrap_or_default()
-      });
-
-  // Get CFS configurations related with CFS sessions
-  let cfs_configuration_name_from_cfs_sessions = cfs_session_to_delete_vec
-    .iter()
-    .map(|cfs_session| cfs_session.configuration_name().unwrap_or_default());
-
-  // Get list of CFS configuration names related to CFS sessions and BOS sessiontemplates
-  let mut cfs_configuration_from_bos_st_and_cfs_session_name_vec: Vec<String> =
-    cfs_configuration_name_from_bos_sessiontemplate_value_iter
-      .chain(cfs_configuration_name_from_cfs_sessions)
-      .map(str::to_string)
-      .collect();
-
-  cfs_configuration_from_bos_st_and_cfs_session_name_vec.sort();
-  cfs_configuration_from_bos_st_and_cfs_session_name_vec.dedup();
-
-  // Get list of CFS configuration serde values related to CFS sessions and BOS
-  // sessiontemplates
-  cfs_configuration_vec_filtered.retain(|cfs_configuration| {
-    cfs_configuration_from_bos_st_and_cfs_session_name_vec
-      .contains(&cfs_configuration.name)
-  });
-
-  // Get image ids from CFS sessions related to CFS configuration to delete
-  let mut image_id_vec: Vec<String> =
-    cfs::session::utils::images_id_from_cfs_session(&cfs_session_to_delete_vec)
-      .map(str::to_string)
-      .collect();
-
-  log::info!("Image ids to delete: {image_id_vec:?}");
-
-  // Get list of CFS session name, CFS configuration name and image id for CFS sessions which
-  // created an image
-  let cfs_session_cfs_configuration_image_id_tuple_vec: Vec<(
-    String,
-    String,
-    String,
-  )> = cfs_session_to_delete_vec
-    .iter()
-    .filter(|cfs_session| cfs_session.first_result_id().is_some())
-    .map(|cfs_session| {
-      (
-        cfs_session.name.clone(),
-        cfs_session
-          .configuration_name()
-          .unwrap_or_default()
-          .to_string(),
-        cfs_session
-          .first_result_id()
-          .unwrap_or_default()
-          .to_string(),
-      )
-    })
-    .collect();
-
-  // Get list of BOS sessiontemplate name, CFS configuration name and image ids for compute nodes
-  let mut bos_sessiontemplate_cfs_configuration_image_id_tuple_vec: Vec<(
-    String,
-    String,
-    String,
-  )> = Vec::new();
-
-  for bos_sessiontemplate in &bos_sessiontemplate_to_delete_vec {
-    let bos_sessiontemplate_name: &str =
-      bos_sessiontemplate.name.as_deref().unwrap_or_default();
-
-    let cfs_configuration_name: &str =
-      bos_sessiontemplate.get_configuration().unwrap_or_default();
-
-    for image_id in bos_sessiontemplate.images_id() {
-      bos_sessiontemplate_cfs_configuration_image_id_tuple_vec.push((
-        bos_sessiontemplate_name.to_string(),
-        cfs_configuration_name.to_string(),
-        image_id.to_string(),
-      ));
-    }
-  }
-
-  // Group image ids by CFS configuration names
-  let mut cfs_configuration_image_id: HashMap<&str, Vec<&str>> = HashMap::new();
-
-  for (_, cfs_configuration, image_id) in
-    &bos_sessiontemplate_cfs_configuration_image_id_tuple_vec
-  {
-    cfs_configuration_image_id
-      .entry(cfs_configuration)
-      .and_modify(|image_vec| image_vec.push(image_id))
-      .or_insert(vec![image_id]);
-  }
+  restrict_to_tenancy(
+    tenancy,
+    &xname_from_groups_vec,
+    &mut cfs_configuration_vec,
+    &mut cfs_session_vec,
+    &mut bos_sessiontemplate_vec,
+  );
 
-  for (_, cfs_configuration, image_id) in
-    &cfs_session_cfs_configuration_image_id_tuple_vec
-  {
-    cfs_configuration_image_id
-      .entry(cfs_configuration)
-      .and_modify(|image_vec| image_vec.push(image_id))
-      .or_insert(vec![image_id]);
-  }
+  let protected_vec = if ignore_protection {
+    Vec::new()
+  } else {
+    protected_resources(
+      client,
+      shasta_token,
+      &cfs_configuration_vec,
+      &bos_sessiontemplate_vec,
+    )
+    .await?
+  };
 
-  // VALIDATION
-  //
-  let mut cfs_configuration_name_used_to_configure_nodes_vec: Vec<String> =
-    Vec::new();
-  let mut image_id_used_to_boot_nodes_vec: Vec<String> = Vec::new();
-
-  // We can't allow any data deletion operation which can jeopardize the system stability,
-  // therefore we will filter the list of the CFS configurations and Images used to configure or boot nodes
-  for (cfs_configuration_name, mut image_id_vec) in cfs_configuration_image_id {
-    let mut nodes_using_cfs_configuration_as_dessired_configuration_vec =
-      cfs_component_vec
-        .iter()
-        .filter(|cfs_component| {
-          cfs_component
-            .desired_config
-            .as_ref()
-            .is_some_and(|cfg| cfg.eq(cfs_configuration_name))
-        })
-        .filter_map(|cfs_component| cfs_component.id.as_deref())
-        .collect::<Vec<&str>>();
-
-    if !nodes_using_cfs_configuration_as_dessired_configuration_vec.is_empty() {
-      cfs_configuration_name_used_to_configure_nodes_vec
-        .push(cfs_configuration_name.to_string());
-
-      nodes_using_cfs_configuration_as_dessired_configuration_vec.sort_unstable();
-
-      log::warn!(
-        "CFS configuration '{}' can't be deleted. Reason:\nCFS configuration '{}' used as desired configuration for nodes: {}",
-        cfs_configuration_name,
-        cfs_configuration_name,
-        nodes_using_cfs_configuration_as_dessired_configuration_vec.join(", ")
-      );
-    }
+  Ok(DeletionPlan::from_data(
+    &cfs_configuration_vec,
+    &cfs_session_vec,
+    &bos_sessiontemplate_vec,
+    cfs_component_vec,
+    bss_bootparameters_vec,
+    &protected_vec,
+    force,
+  ))
+}
 
-    image_id_vec.dedup();
+/// Compute the cascade of resources reachable from a CFS configuration
+/// filter — what [`delete`] would actually remove. Tuple view of
+/// [`DeletionPlan::build`] without `force`, kept for the backend
+/// dispatcher.
+///
+/// Returns a tuple of
+/// `(sessions, session_template_refs, image_ids, configuration_names,
+/// session_refs, configurations)`, where the `*_refs` are
+/// `(name, configuration name, image id)`.
+///
+/// # Errors
+///
+/// Returns
+/// [`Error::ConfigurationUsedAsRuntimeConfigurationOrUsedToBuildBootImageUsed`]
+/// if nodes use any configuration or image matched,
+/// [`Error::ConfigurationDerivativesNotFound`] if no image, CFS session
+/// or BOS sessiontemplate relates to the configurations matched, or
+/// any other [`Error`] variant on CSM, transport, or deserialization
+/// failure.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_data_to_delete(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  hsm_name_available_vec: &[String],
+  configuration_name_pattern_opt: Option<&str>,
+  since_opt: Option<NaiveDateTime>,
+  until_opt: Option<NaiveDateTime>,
+) -> Result<
+  (
+    Vec<CfsSessionGetResponse>,
+    Vec<(String, String, String)>,
+    Vec<String>,
+    Vec<String>,
+    Vec<(String, String, String)>,
+    Vec<CfsConfigurationResponse>,
+  ),
+  Error,
+> {
+  // The dispatcher carries no execution context: the caller acts for
+  // themselves
+  let execution_context = ExecutionContext::from_token(shasta_token)?;
 
-    for image_id in &image_id_vec {
-      let node_vec =
-        get_node_vec_booting_image(image_id, &bss_bootparameters_vec);
+  let plan = crate::common::request_id::scope(
+    "get_data_to_delete",
+    build_plan(
+      &CsmSnapshot::new(client, shasta_token),
+      &execution_context,
+      hsm_name_available_vec,
+      configuration_name_pattern_opt,
+      since_opt,
+      until_opt,
+      false,
+      false,
+    ),
+  )
+  .await?;
 
-      if !node_vec.is_empty() {
-        image_id_used_to_boot_nodes_vec.push(image_id.to_string());
-        log::warn!(
-          "Image '{}' used to boot nodes: {}",
-          image_id,
-          node_vec.join(", ")
-        );
-      }
-    }
+  for blocked in &plan.blocked {
+    events::warning(format!(
+      "{} can't be deleted. Reason: {}",
+      blocked.resource, blocked.reason
+    ));
   }
 
-  // Get final list of CFS configuration serde values related to CFS sessions and BOS
-  // sessiontemplates and excluding the CFS sessions to keep (in case user decides to
-  // force the deletion operation)
-  cfs_configuration_vec_filtered.retain(|cfs_configuration_value| {
-    !cfs_configuration_name_used_to_configure_nodes_vec
-      .contains(&cfs_configuration_value.name)
-  });
-
-  
-  
-
-  // EVALUATE IF NEED TO CONTINUE.
-  // CHECK IF ANY CFS CONFIGURAION OR IMAGE IS CURRENTLY USED TO CONFIGURE OR BOOT NODES
-  if !cfs_configuration_name_used_to_configure_nodes_vec.is_empty()
-    || !image_id_used_to_boot_nodes_vec.is_empty()
-  {
-    // There are CFS configuraions or Images currently used by nodes. Better to be safe and
-    // stop the process
-    log::error!(
+  // Nodes use some of the data selected. Better to be safe and stop
+  if plan.affects_nodes() {
+    tracing::error!(
       "User trying to delete configurations or images used by other clusters/nodes"
     );
     return Err(
       Error::ConfigurationUsedAsRuntimeConfigurationOrUsedToBuildBootImageUsed,
     );
   }
-  // We are safe to delete, none of the data selected for deletion is currently used as
-  // neither configure nor boot the nodes
-  cfs_configuration_from_bos_st_and_cfs_session_name_vec.retain(
-    |cfs_configuration_name| {
-      !cfs_configuration_name_used_to_configure_nodes_vec
-        .contains(&cfs_configuration_name.clone())
-    },
-  );
 
-  image_id_vec.retain(|image_id| {
-    !image_id_used_to_boot_nodes_vec.contains(&image_id.clone())
-  });
-
-  let cfs_session_cfs_configuration_image_id_tuple_filtered_vec: Vec<(
+  let bos_sessiontemplate_cfs_configuration_image_id_tuple_vec: Vec<(
     String,
     String,
     String,
-  )> = cfs_session_cfs_configuration_image_id_tuple_vec
-      .into_iter()
-      .filter(|(_, cfs_configuration_name, image_id)| {
-        !cfs_configuration_name_used_to_configure_nodes_vec
-          .contains(&cfs_configuration_name.clone())
-          && !image_id_used_to_boot_nodes_vec.contains(&image_id.clone())
+  )> = plan
+    .bos_sessiontemplates
+    .iter()
+    .flat_map(|bos_sessiontemplate| {
+      bos_sessiontemplate.images_id().map(|image_id| {
+        (
+          bos_sessiontemplate.name.clone().unwrap_or_default(),
+          bos_sessiontemplate
+            .get_configuration()
+            .unwrap_or_default()
+            .to_string(),
+          image_id.to_string(),
+        )
       })
-      .collect();
+    })
+    .collect();
 
-  let bos_sessiontemplate_cfs_configuration_image_id_tuple_filtered_vec: Vec<
-    (String, String, String),
-  > = bos_sessiontemplate_cfs_configuration_image_id_tuple_vec
-      .into_iter()
-      .filter(|(_, cfs_configuration_name, image_id)| {
-        !cfs_configuration_name_used_to_configure_nodes_vec
-          .contains(cfs_configuration_name)
-          && !image_id_used_to_boot_nodes_vec.contains(image_id)
+  let cfs_session_cfs_configuration_image_id_tuple_vec: Vec<(
+    String,
+    String,
+    String,
+  )> = plan
+    .cfs_sessions
+    .iter()
+    .filter_map(|cfs_session| {
+      cfs_session.first_result_id().map(|image_id| {
+        (
+          cfs_session.name.clone(),
+          cfs_session
+            .configuration_name()
+            .unwrap_or_default()
+            .to_string(),
+          image_id.to_string(),
+        )
       })
-      .collect();
+    })
+    .collect();
 
   // Return ERROR IF THERE IS NO DATA TO DELETE
-  if image_id_vec.is_empty()
-    && cfs_session_cfs_configuration_image_id_tuple_filtered_vec.is_empty()
-    && bos_sessiontemplate_cfs_configuration_image_id_tuple_filtered_vec
-      .is_empty()
+  if plan.images.is_empty()
+    && cfs_session_cfs_configuration_image_id_tuple_vec.is_empty()
+    && bos_sessiontemplate_cfs_configuration_image_id_tuple_vec.is_empty()
   {
     // We can't decide if CFS configuration and derivatives can be deleted.
-    log::error!(
-      "Delete configuration - Not enough information to proceed. Could not find enough information related to CFS configurations '{}' to decide is user his allowed to proceed",
-      cfs_configuration_vec
-        .iter()
-        .map(|cfs_configuration| cfs_configuration.name.clone())
-        .collect::<Vec<String>>()
-        .join(", "),
+    let cfs_configuration_names = plan
+      .cfs_configurations
+      .iter()
+      .map(|cfs_configuration| cfs_configuration.name.as_str())
+      .chain(plan.blocked.iter().filter_map(
+        |blocked| match &blocked.resource {
+          DeletionResource::CfsConfiguration(name) => Some(name.as_str()),
+          _ => None,
+        },
+      ))
+      .collect::<Vec<&str>>()
+      .join(", ");
+    tracing::error!(
+      "Delete configuration - Not enough information to proceed. Could not find enough information related to CFS configurations '{cfs_configuration_names}' to decide is user his allowed to proceed",
     );
     return Err(Error::ConfigurationDerivativesNotFound(
-      cfs_configuration_vec
-        .iter()
-        .map(|cfs_configuration| cfs_configuration.name.clone())
-        .collect::<Vec<String>>()
-        .join(", "),
+      cfs_configuration_names,
     ));
   }
 
+  // Get list of CFS configuration names related to CFS sessions and BOS sessiontemplates
+  let mut cfs_configuration_name_vec: Vec<String> =
+    plan
+      .bos_sessiontemplates
+      .iter()
+      .map(|bos_sessiontemplate| {
+        bos_sessiontemplate.get_configuration().unwrap_or_default()
+      })
+      .chain(plan.cfs_sessions.iter().map(|cfs_session| {
+        cfs_session.configuration_name().unwrap_or_default()
+      }))
+      .map(str::to_string)
+      .collect();
+  cfs_configuration_name_vec.sort();
+  cfs_configuration_name_vec.dedup();
+
+  events::info(format!("Image ids to delete: {:?}", plan.images));
+
   Ok((
-    cfs_session_to_delete_vec,
-    bos_sessiontemplate_cfs_configuration_image_id_tuple_filtered_vec,
-    image_id_vec,
-    cfs_configuration_from_bos_st_and_cfs_session_name_vec,
-    cfs_session_cfs_configuration_image_id_tuple_filtered_vec,
-    cfs_configuration_vec_filtered,
+    plan.cfs_sessions,
+    bos_sessiontemplate_cfs_configuration_image_id_tuple_vec,
+    plan.images,
+    cfs_configuration_name_vec,
+    cfs_session_cfs_configuration_image_id_tuple_vec,
+    plan.cfs_configurations,
   ))
 }
 
@@ -374,6 +972,12 @@ pub async fn get_data_to_delete(
 /// to a CFS component as a 'desired configuration' and also checks if image related to CFS
 /// configuration is used as a boot image of any node in the system.
 ///
+/// The resources deleted are reported to the configured
+/// [`webhooks`](crate::webhooks). Failed deletions of CFS sessions,
+/// BOS session templates and CFS configurations are retried as
+/// `retry_options` says ([`DEFAULT_DELETE_RETRY`] if unsure), then
+/// reported as a warning.
+///
 /// # Errors
 ///
 /// Returns an [`Error`] variant on CSM, transport, or
@@ -387,21 +991,76 @@ pub async fn delete(
   image_id_vec: &[String],
   cfs_session_name_vec: &[String],
   bos_sessiontemplate_name_vec: &[String],
+  retry_options: WaitOptions,
+) -> Result<(), Error> {
+  crate::common::request_id::scope(
+    "delete_configurations_and_data_related",
+    async {
+      let mut deleted_resource_vec = Vec::new();
+
+      let result = delete_related(
+        client,
+        shasta_token,
+        cfs_configuration_name_vec,
+        image_id_vec,
+        cfs_session_name_vec,
+        bos_sessiontemplate_name_vec,
+        retry_options,
+        &mut deleted_resource_vec,
+      )
+      .await;
+
+      // Report what was deleted even if a later deletion failed
+      if !deleted_resource_vec.is_empty() {
+        webhooks::notify(
+          client,
+          webhooks::Operation::ResourcesDeleted {
+            resources: deleted_resource_vec,
+          },
+        )
+        .await;
+      }
+
+      result
+    },
+  )
+  .await
+}
+
+/// Body of [`delete`], run inside its correlation scope. Each resource
+/// deleted is added to `deleted_resource_vec`.
+#[allow(clippy::too_many_arguments)]
+async fn delete_related(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  cfs_configuration_name_vec: &[String],
+  image_id_vec: &[String],
+  cfs_session_name_vec: &[String],
+  bos_sessiontemplate_name_vec: &[String],
+  retry_options: WaitOptions,
+  deleted_resource_vec: &mut Vec<DeletedResource>,
 ) -> Result<(), Error> {
   let shasta_client = client;
+  let mut deleted = |kind: &'static str, name: &str| {
+    events::deleted(kind, name);
+    deleted_resource_vec.push(DeletedResource {
+      kind,
+      name: name.to_string(),
+    });
+  };
   // DELETE DATA
   //
   // DELETE IMAGES
   for image_id in image_id_vec {
-    log::info!("Deleting IMS image '{image_id}'");
+    events::step(format!("Deleting IMS image '{image_id}'"));
     let image_deleted_value_rslt =
       client.ims_image_delete(shasta_token, image_id).await;
 
     // process api response
     match image_deleted_value_rslt {
-      Ok(()) => log::info!("IMS image deleted: {image_id}"),
+      Ok(()) => deleted("IMS image", image_id),
       Err(e) => {
-        log::warn!("{e}. Continue");
+        events::warning(format!("{e}. Continue"));
       }
     }
   }
@@ -413,116 +1072,121 @@ pub async fn delete(
   // Match BOS SESSIONS with the BOS SESSIONTEMPLATE RELATED
   for bos_session in bos_session_vec {
     let Some(bos_session_id) = &bos_session.name else {
-      log::warn!("BOS session has no 'name' field; skipping deletion");
+      events::warning("BOS session has no 'name' field; skipping deletion");
       continue;
     };
-    log::info!("Deleting BOS sesion '{bos_session_id}'");
 
     if bos_sessiontemplate_name_vec.contains(&bos_session.template_name) {
+      events::step(format!("Deleting BOS session '{bos_session_id}'"));
       shasta_client
         .bos_session_v2_delete(shasta_token, bos_session_id)
         .await?;
 
-      log::info!(
-        "BOS session deleted: {bos_session_id}" // For some reason CSM API to delete a BOS
-                       // session does not returns the BOS session
-                       // ID in the payload...
-      );
+      // For some reason CSM API to delete a BOS session does not
+      // returns the BOS session ID in the payload...
+      deleted("BOS session", bos_session_id);
     } else {
-      log::debug!("Ignoring BOS session template {bos_session_id}");
+      tracing::debug!("Ignoring BOS session template {bos_session_id}");
     }
   }
 
   // DELETE CFS SESSIONS
-  let max_attempts = 5;
   for cfs_session_name in cfs_session_name_vec {
-    log::info!("Deleting IMS image '{cfs_session_name}'");
-    let mut counter = 0;
-    loop {
-      let deletion_rslt = shasta_client
-        .cfs_session_v3_delete(shasta_token, cfs_session_name)
-        .await;
-
-      if deletion_rslt.is_err() && counter <= max_attempts {
-        log::warn!(
-          "Could not delete CFS session {cfs_session_name} attempt {counter} of {max_attempts}, trying again in 2 seconds..."
-        );
-        tokio::time::sleep(time::Duration::from_secs(2)).await;
-        counter += 1;
-      } else if deletion_rslt.is_err() && counter > max_attempts {
-        log::warn!(
-          "ERROR deleting CFS session {cfs_session_name}, please delete it manually.",
-        );
-        log::debug!("ERROR:\n{:#?}", deletion_rslt.unwrap_err());
-        break;
-      } else {
-        log::info!("CfS session deleted: {cfs_session_name}");
-        break;
-      }
+    events::step(format!("Deleting CFS session '{cfs_session_name}'"));
+    if delete_with_retries(
+      format!("Delete CFS session '{cfs_session_name}'"),
+      retry_options,
+      || shasta_client.cfs_session_delete(shasta_token, cfs_session_name),
+    )
+    .await
+    {
+      deleted("CFS session", cfs_session_name);
+    } else {
+      events::warning(format!(
+        "ERROR deleting CFS session {cfs_session_name}, please delete it manually.",
+      ));
     }
   }
 
   // DELETE BOS SESSIONTEMPLATES
-  let max_attempts = 5;
   for bos_sessiontemplate_name in bos_sessiontemplate_name_vec {
-    log::info!(
+    events::step(format!(
       "Deleting BOS sessiontemplate '{bos_sessiontemplate_name}'"
-    );
-    let mut counter = 0;
-    loop {
-      let deletion_rslt = shasta_client
-        .bos_template_v2_delete(shasta_token, bos_sessiontemplate_name)
-        .await;
-
-      if deletion_rslt.is_err() && counter <= max_attempts {
-        log::warn!(
-          "Could not delete BOS sessiontemplate {bos_sessiontemplate_name} attempt {counter} of {max_attempts}, trying again in 2 seconds..."
-        );
-        tokio::time::sleep(time::Duration::from_secs(2)).await;
-        counter += 1;
-      } else if deletion_rslt.is_err() && counter > max_attempts {
-        log::warn!(
-          "ERROR deleting BOS sessiontemplate {bos_sessiontemplate_name}, please delete it manually.",
-        );
-        log::debug!("ERROR:\n{:#?}", deletion_rslt.unwrap_err());
-        break;
-      } else {
-        log::info!("BOS sessiontemplate deleted: {bos_sessiontemplate_name}");
-        break;
-      }
+    ));
+    if delete_with_retries(
+      format!("Delete BOS sessiontemplate '{bos_sessiontemplate_name}'"),
+      retry_options,
+      || {
+        shasta_client
+          .bos_template_v2_delete(shasta_token, bos_sessiontemplate_name)
+      },
+    )
+    .await
+    {
+      deleted("BOS sessiontemplate", bos_sessiontemplate_name);
+    } else {
+      events::warning(format!(
+        "ERROR deleting BOS sessiontemplate {bos_sessiontemplate_name}, please delete it manually.",
+      ));
     }
   }
 
   // DELETE CFS CONFIGURATIONS
-  let max_attempts = 5;
   for cfs_configuration in cfs_configuration_name_vec {
-    log::info!("Deleting CFS configuration '{cfs_configuration}'");
-    let mut counter = 0;
-    loop {
-      let deletion_rslt = shasta_client
-        .cfs_configuration_v3_delete(shasta_token, cfs_configuration)
-        .await;
+    events::step(format!("Deleting CFS configuration '{cfs_configuration}'"));
+    if delete_with_retries(
+      format!("Delete CFS configuration '{cfs_configuration}'"),
+      retry_options,
+      || {
+        shasta_client.cfs_configuration_delete(shasta_token, cfs_configuration)
+      },
+    )
+    .await
+    {
+      deleted("CFS configuration", cfs_configuration);
+    } else {
+      events::warning(format!(
+        "ERROR deleting CFS configuration {cfs_configuration}, please delete it manually.",
+      ));
+    }
+  }
 
-      if deletion_rslt.is_err() && counter <= max_attempts {
-        log::warn!(
-          "Could not delete CFS configuration {cfs_configuration} attempt {counter} of {max_attempts}, trying again in 2 seconds..."
-        );
-        tokio::time::sleep(time::Duration::from_secs(2)).await;
-        counter += 1;
-      } else if deletion_rslt.is_err() && counter > max_attempts {
-        log::warn!(
-          "ERROR deleting CFS configuration {cfs_configuration}, please delete it manually.",
-        );
-        log::debug!("ERROR:\n{:#?}", deletion_rslt.unwrap_err());
-        break;
-      } else {
-        log::info!("CFS configuration deleted: {cfs_configuration}");
-        break;
+  Ok(())
+}
+
+/// Run `delete_resource` until it succeeds, retrying as `retry_options` says,
+/// each retry reported as [`Event::Retry`] of `operation`. Returns
+/// whether it succeeded.
+async fn delete_with_retries<F, Fut>(
+  operation: String,
+  retry_options: WaitOptions,
+  mut delete_resource: F,
+) -> bool
+where
+  F: FnMut() -> Fut,
+  Fut: Future<Output = Result<(), Error>>,
+{
+  let max_attempts = retry_options.poll_backoff().max_attempts;
+  let mut delay = retry_options.poll_interval;
+
+  for attempt in 1..=max_attempts {
+    match delete_resource().await {
+      Ok(()) => return true,
+      Err(e) if attempt < max_attempts => {
+        events::emit(Event::Retry {
+          operation: operation.clone(),
+          attempt,
+          max_attempts,
+          reason: e.to_string(),
+        });
+        tokio::time::sleep(delay).await;
+        delay = retry_options.next_delay(delay);
       }
+      Err(e) => tracing::debug!("ERROR:\n{e:#?}"),
     }
   }
 
-  Ok(())
+  false
 }
 
 /// Given a list of boot params, this function returns the list of hosts booting an `image_id`
@@ -542,3 +1206,227 @@ pub fn get_node_vec_booting_image(
 
   node_booting_image_vec
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn plan_keeps_what_nodes_use_and_force_only_releases_the_rest() {
+    let cfs_configuration_vec: Vec<CfsConfigurationResponse> =
+      serde_json::from_value(serde_json::json!([
+        { "name": "cos-config", "lastUpdated": "", "layers": [] },
+        { "name": "uan-config", "lastUpdated": "", "layers": [] },
+        { "name": "orphan-config", "lastUpdated": "", "layers": [] }
+      ]))
+      .unwrap();
+    let cfs_session_vec: Vec<CfsSessionGetResponse> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "name": "cos-session",
+          "configuration": { "name": "cos-config" },
+          "status": { "artifacts": [{ "result_id": "cos-image" }] }
+        },
+        {
+          "name": "uan-session",
+          "configuration": { "name": "uan-config" },
+          "status": { "artifacts": [{ "result_id": "uan-image" }] }
+        }
+      ]))
+      .unwrap();
+    let cfs_component_vec: Vec<Component> =
+      serde_json::from_value(serde_json::json!([
+        { "id": "x1000c0s0b0n0", "desiredConfig": "cos-config" }
+      ]))
+      .unwrap();
+
+    let plan = DeletionPlan::from_data(
+      &cfs_configuration_vec,
+      &cfs_session_vec,
+      &[],
+      &cfs_component_vec,
+      &[],
+      &[],
+      false,
+    );
+
+    assert!(plan.affects_nodes());
+    assert_eq!(
+      plan
+        .cfs_configurations
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>(),
+      vec!["uan-config"]
+    );
+    assert_eq!(plan.images, vec!["uan-image"]);
+    assert!(plan.blocked.contains(&BlockedResource {
+      resource: DeletionResource::CfsSession("cos-session".to_string()),
+      reason: BlockReason::RelatedTo(DeletionResource::CfsConfiguration(
+        "cos-config".to_string()
+      )),
+    }));
+    assert!(plan.blocked.contains(&BlockedResource {
+      resource: DeletionResource::CfsConfiguration("orphan-config".to_string()),
+      reason: BlockReason::NoDerivatives,
+    }));
+
+    let forced_plan = DeletionPlan::from_data(
+      &cfs_configuration_vec,
+      &cfs_session_vec,
+      &[],
+      &cfs_component_vec,
+      &[],
+      &[],
+      true,
+    );
+
+    // Only the configuration used by nodes is still kept
+    assert_eq!(
+      forced_plan.blocked,
+      vec![BlockedResource {
+        resource: DeletionResource::CfsConfiguration("cos-config".to_string()),
+        reason: BlockReason::ConfiguresNodes(vec!["x1000c0s0b0n0".to_string()]),
+      }]
+    );
+    assert_eq!(forced_plan.cfs_configurations.len(), 2);
+    assert_eq!(forced_plan.cfs_sessions.len(), 2);
+    assert_eq!(forced_plan.images, vec!["cos-image", "uan-image"]);
+  }
+
+  #[test]
+  fn plan_keeps_protected_resources_even_when_forced() {
+    let cfs_configuration_vec: Vec<CfsConfigurationResponse> =
+      serde_json::from_value(serde_json::json!([
+        { "name": "cos-config", "lastUpdated": "", "layers": [] }
+      ]))
+      .unwrap();
+    let bos_sessiontemplate_vec: Vec<BosSessionTemplate> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "name": "cos-template",
+          "boot_sets": {
+            "compute": { "path": "s3://boot-images/cos-image/manifest.json" }
+          },
+          "cfs": { "configuration": "cos-config" }
+        }
+      ]))
+      .unwrap();
+
+    let plan = DeletionPlan::from_data(
+      &cfs_configuration_vec,
+      &[],
+      &bos_sessiontemplate_vec,
+      &[],
+      &[],
+      &[
+        DeletionResource::CfsConfiguration("cos-config".to_string()),
+        DeletionResource::BosSessionTemplate("cos-template".to_string()),
+      ],
+      true,
+    );
+
+    assert!(plan.is_empty());
+    assert!(!plan.affects_nodes());
+    assert!(plan.blocked.contains(&BlockedResource {
+      resource: DeletionResource::BosSessionTemplate(
+        "cos-template".to_string()
+      ),
+      reason: BlockReason::Protected,
+    }));
+    assert!(plan.blocked.contains(&BlockedResource {
+      resource: DeletionResource::ImsImage("cos-image".to_string()),
+      reason: BlockReason::RelatedTo(DeletionResource::BosSessionTemplate(
+        "cos-template".to_string()
+      )),
+    }));
+  }
+
+  #[test]
+  fn restrict_to_tenancy_drops_other_tenants_resources() {
+    let mut cfs_configuration_vec: Vec<CfsConfigurationResponse> =
+      serde_json::from_value(serde_json::json!([
+        { "name": "zinal-cos-config", "lastUpdated": "", "layers": [] },
+        { "name": "shared-config", "lastUpdated": "", "layers": [] },
+        { "name": "eiger-cos-config", "lastUpdated": "", "layers": [] }
+      ]))
+      .unwrap();
+    let mut cfs_session_vec: Vec<CfsSessionGetResponse> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "name": "zinal-session",
+          "configuration": { "name": "zinal-cos-config" },
+          "target": { "groups": [{ "name": "zinal", "members": [] }] }
+        },
+        {
+          "name": "eiger-session",
+          "configuration": { "name": "shared-config" },
+          "target": { "groups": [{ "name": "eiger", "members": [] }] }
+        }
+      ]))
+      .unwrap();
+    let mut bos_sessiontemplate_vec: Vec<BosSessionTemplate> = Vec::new();
+
+    restrict_to_tenancy(
+      &Tenancy::from_groups(vec!["zinal".to_string()]),
+      &[],
+      &mut cfs_configuration_vec,
+      &mut cfs_session_vec,
+      &mut bos_sessiontemplate_vec,
+    );
+
+    assert_eq!(
+      cfs_session_vec
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect::<Vec<_>>(),
+      vec!["zinal-session"]
+    );
+    assert_eq!(
+      cfs_configuration_vec
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>(),
+      vec!["zinal-cos-config"]
+    );
+  }
+
+  #[tokio::test]
+  async fn delete_with_retries_stops_once_the_retry_time_is_spent() {
+    let retry_options =
+      WaitOptions::new(Duration::from_millis(3), Duration::from_millis(1));
+
+    let mut attempt_count = 0;
+    let deleted = delete_with_retries(
+      "Delete CFS session 'batcher-1234'".to_string(),
+      retry_options,
+      || {
+        attempt_count += 1;
+        async { Err(Error::Message("503".to_string())) }
+      },
+    )
+    .await;
+    assert!(!deleted);
+    assert_eq!(attempt_count, 4);
+
+    let mut attempt_count = 0;
+    let deleted = delete_with_retries(
+      "Delete CFS session 'batcher-1234'".to_string(),
+      retry_options,
+      || {
+        attempt_count += 1;
+        let attempt = attempt_count;
+        async move {
+          if attempt < 2 {
+            Err(Error::Message("503".to_string()))
+          } else {
+            Ok(())
+          }
+        }
+      },
+    )
+    .await;
+    assert!(deleted);
+    assert_eq!(attempt_count, 2);
+  }
+}
@@ -9,11 +9,16 @@
 use serde_json::Value;
 
 use crate::{
+  ExecutionContext,
   bss::types::BootParameters,
   cfs::{
     session::utils::get_list_xnames_related_to_session,
     v2::{CfsSessionGetResponse, Component},
   },
+  common::{
+    events,
+    webhooks::{self, DeletedResource},
+  },
   error::Error,
   hsm::group::types::Group,
 };
@@ -28,6 +33,9 @@ use crate::{
 ///
 /// # Arguments
 ///
+/// - `execution_context` — who cancels the session; sessions targeting
+///   HSM groups it can't act on are refused, and `group_available_vec`
+///   is restricted to its tenancy.
 /// - `group_available_vec` — HSM groups the caller is allowed to
 ///   target; sessions that reach outside this set are refused.
 /// - `cfs_component_vec` / `bos_bootparameters_vec` — current snapshots
@@ -35,6 +43,9 @@ use crate::{
 /// - `dry_run` — when `true`, log the intended deletions without
 ///   mutating CSM.
 ///
+/// The resources deleted are reported to the configured
+/// [`webhooks`](crate::webhooks).
+///
 /// # Errors
 ///
 /// Returns an [`Error`] variant on CSM, transport, or
@@ -42,6 +53,60 @@ use crate::{
 /// for the full set.
 #[allow(clippy::too_many_arguments)]
 pub async fn exec(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  mut group_available_vec: Vec<Group>,
+  cfs_session: &CfsSessionGetResponse,
+  cfs_component_vec: &[Component],
+  bos_bootparameters_vec: &[BootParameters],
+  dry_run: bool,
+) -> Result<(), Error> {
+  for group in cfs_session.get_target_hsm().unwrap_or_default() {
+    execution_context.check_owns(&group)?;
+  }
+  group_available_vec
+    .retain(|group| execution_context.tenancy().owns(&group.label.0));
+
+  client
+    .scope(execution_context.scope(crate::common::request_id::scope(
+      "delete_and_cancel_session",
+      async {
+        let mut deleted_resource_vec = Vec::new();
+
+        let result = delete_and_cancel(
+          client,
+          shasta_token,
+          group_available_vec,
+          cfs_session,
+          cfs_component_vec,
+          bos_bootparameters_vec,
+          dry_run,
+          &mut deleted_resource_vec,
+        )
+        .await;
+
+        // Report what was deleted even if a later deletion failed
+        if !deleted_resource_vec.is_empty() {
+          webhooks::notify(
+            client,
+            webhooks::Operation::ResourcesDeleted {
+              resources: deleted_resource_vec,
+            },
+          )
+          .await;
+        }
+
+        result
+      },
+    )))
+    .await
+}
+
+/// Body of [`exec`], run inside its correlation scope. Each resource
+/// deleted is added to `deleted_resource_vec`.
+#[allow(clippy::too_many_arguments)]
+async fn delete_and_cancel(
   client: &crate::ShastaClient,
   shasta_token: &str,
   group_available_vec: Vec<Group>,
@@ -49,10 +114,11 @@ pub async fn exec(
   cfs_component_vec: &[Component],
   bos_bootparameters_vec: &[BootParameters],
   dry_run: bool,
+  deleted_resource_vec: &mut Vec<DeletedResource>,
 ) -> Result<(), Error> {
   let cfs_session_name = &cfs_session.name;
 
-  log::debug!("Deleting session '{cfs_session_name}'");
+  tracing::debug!("Deleting session '{cfs_session_name}'");
 
   // Get xnames related to CFS session to delete:
   // - xnames belonging to HSM group related to CFS session
@@ -75,7 +141,7 @@ pub async fn exec(
   if cfs_session_target_definition == "dynamic" {
     // The CFS session is of type 'target dynamic' (runtime CFS batcher) - cancel session by
     // setting error_count to retry_policy value
-    log::info!("CFS session target definition is 'dynamic'.");
+    events::info("CFS session target definition is 'dynamic'.");
 
     let cfs_global_options =
       client.cfs_component_v3_get_options(shasta_token).await?;
@@ -108,6 +174,7 @@ pub async fn exec(
         &image_created_by_cfs_session_vec,
         bos_bootparameters_vec,
         dry_run,
+        deleted_resource_vec,
       )
       .await?;
     }
@@ -119,11 +186,18 @@ pub async fn exec(
 
   // Delete CFS session
   if dry_run {
-    log::info!("Dry Run Mode: Delete CFS session '{cfs_session_name}'");
+    events::info(format!(
+      "Dry Run Mode: Delete CFS session '{cfs_session_name}'"
+    ));
   } else {
     client
       .cfs_session_v3_delete(shasta_token, cfs_session_name)
       .await?;
+    events::deleted("CFS session", cfs_session_name);
+    deleted_resource_vec.push(DeletedResource {
+      kind: "CFS session",
+      name: cfs_session_name.clone(),
+    });
   }
 
   Ok(())
@@ -135,6 +209,7 @@ async fn delete_images(
   image_created_by_cfs_session_vec: &[&str],
   bss_bootparameters_vec_opt: &[BootParameters],
   dry_run: bool,
+  deleted_resource_vec: &mut Vec<DeletedResource>,
 ) -> Result<(), Error> {
   // Delete images
   for image_id in image_created_by_cfs_session_vec {
@@ -143,15 +218,20 @@ async fn delete_images(
       .any(|boot_parameters| boot_parameters.get_boot_image().eq(image_id));
 
     if is_image_boot_node {
-      log::info!(
+      events::warning(format!(
         "Image '{image_id}' is a boot node image. It will not be deleted."
-      );
+      ));
     } else if dry_run {
-      log::info!(
+      events::info(format!(
         "Dry Run Mode: CFS session target definition is 'image'. Deleting image '{image_id}'"
-      );
+      ));
     } else {
       client.ims_image_delete(shasta_token, image_id).await?;
+      events::deleted("IMS image", *image_id);
+      deleted_resource_vec.push(DeletedResource {
+        kind: "IMS image",
+        name: (*image_id).to_string(),
+      });
     }
   }
 
@@ -168,9 +248,9 @@ async fn cancel_session(
   dry_run: bool,
 ) -> Result<(), Error> {
   // Set CFS components error_count == retry_policy so CFS batcher stops retrying running
-  log::info!(
+  events::step(format!(
     "Set 'error_count' {retry_policy} to xnames {xname_vec:?}"
-  );
+  ));
 
   // Update CFS component error_count
   let cfs_component_vec: Vec<Component> = cfs_component_vec_opt
@@ -188,14 +268,14 @@ async fn cancel_session(
     })
     .ok_or(Error::ValidationFailed("No CFS components"))?;
 
-  log::info!(
+  tracing::debug!(
     "Update error count on nodes {xname_vec:?} to {retry_policy}"
   );
 
   if dry_run {
-    log::info!(
+    events::info(format!(
       "Dry Run Mode: Update error count on nodes {cfs_component_vec:?}"
-    );
+    ));
   } else {
     client
       .cfs_component_v2_put_component_list(shasta_token, cfs_component_vec)
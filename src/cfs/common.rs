@@ -35,7 +35,7 @@ pub async fn health_check(
   shasta_root_cert: &[u8],
   socks5_proxy: Option<&str>,
 ) -> Result<Value, Error> {
-  crate::ShastaClient::new(
+  crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
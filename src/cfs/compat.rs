@@ -0,0 +1,363 @@
+//! CFS calls working on both v2-only (CSM 1.4) and v3 (CSM 1.6) systems.
+//!
+//! The `ShastaClient::cfs_<resource>_v2_*` and `cfs_<resource>_v3_*`
+//! methods each hit one API version, so code mixing them (creating
+//! through v2, deleting through v3) breaks on systems serving only one.
+//! The version-less methods here prefer v3 and fall back to v2 when CFS
+//! doesn't serve it.
+//!
+//! Which version to use is decided once per client (and its clones) by
+//! [`ShastaClient::cfs_api_version`], probing `GET /cfs/v3/options`: a
+//! 404 or 405 means no v3. Other 404s are not taken as a reason to fall
+//! back, as they mean the resource itself is missing.
+//!
+//! Results come in the v2 types, which the rest of csm-rs works with;
+//! v3-only fields (layer sources, session logs, `debug_on_failure`,
+//! image maps) are dropped.
+
+use std::fmt;
+
+use serde_json::{Value, json};
+
+use crate::{
+  ShastaClient,
+  cfs::{v2, v3},
+  common::{http, metrics::MeteredSend},
+  error::Error,
+};
+
+/// CFS API version a client talks to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfsApiVersion {
+  /// `/cfs/v2`, the only version on CSM 1.4 and older.
+  V2,
+  /// `/cfs/v3`, CSM 1.5 and newer.
+  V3,
+}
+
+impl fmt::Display for CfsApiVersion {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::V2 => write!(f, "v2"),
+      Self::V3 => write!(f, "v3"),
+    }
+  }
+}
+
+impl ShastaClient {
+  /// CFS API version the version-less `cfs_*` methods use, probed on
+  /// the first call and shared by the clones of the client, see the
+  /// [module docs](self).
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM or transport failure other
+  /// than a 404 or 405 of `GET /cfs/v3/options`; the next call probes
+  /// again.
+  pub async fn cfs_api_version(
+    &self,
+    token: &str,
+  ) -> Result<CfsApiVersion, Error> {
+    self
+      .cfs_api_version
+      .get_or_try_init(|| async {
+        let api_url = format!("{}/cfs/v3/options", self.base_url());
+        let response = self
+          .http()
+          .get(api_url)
+          .bearer_auth(token)
+          .send_metered()
+          .await
+          .map_err(Error::NetError)?;
+
+        let version = match response.status().as_u16() {
+          404 | 405 => CfsApiVersion::V2,
+          _ => {
+            http::handle_json_or_text_response::<Value>(response).await?;
+            CfsApiVersion::V3
+          }
+        };
+        log::debug!("CFS API version: {version}");
+
+        Ok(version)
+      })
+      .await
+      .copied()
+  }
+
+  /// CFS configuration `configuration_name_opt`, or every configuration
+  /// if `None`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn cfs_configuration_get(
+    &self,
+    token: &str,
+    configuration_name_opt: Option<&str>,
+  ) -> Result<Vec<v2::CfsConfigurationResponse>, Error> {
+    match self.cfs_api_version(token).await? {
+      CfsApiVersion::V3 => Ok(
+        self
+          .cfs_configuration_v3_get(token, configuration_name_opt)
+          .await?
+          .into_iter()
+          .map(v2::CfsConfigurationResponse::from)
+          .collect(),
+      ),
+      CfsApiVersion::V2 => {
+        self
+          .cfs_configuration_v2_get(token, configuration_name_opt)
+          .await
+      }
+    }
+  }
+
+  /// Create or replace CFS configuration `configuration_name`.
+  ///
+  /// Unlike [`Self::cfs_configuration_v3_put`], an existing
+  /// configuration is replaced on v3 too, as `PUT /cfs/v2` does.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configuration_get`].
+  pub async fn cfs_configuration_put(
+    &self,
+    token: &str,
+    configuration: &v2::CfsConfigurationRequest,
+    configuration_name: &str,
+  ) -> Result<v2::CfsConfigurationResponse, Error> {
+    match self.cfs_api_version(token).await? {
+      CfsApiVersion::V3 => {
+        let api_url = format!(
+          "{}/cfs/v3/configurations/{configuration_name}",
+          self.base_url()
+        );
+        let request = v3::CfsConfigurationRequest::from(configuration);
+
+        let response = self
+          .http()
+          .put(api_url)
+          .json(&json!({ "layers": request.layers }))
+          .bearer_auth(token)
+          .send_metered()
+          .await
+          .map_err(Error::NetError)?;
+
+        http::handle_json_or_text_response::<v3::CfsConfigurationResponse>(
+          response,
+        )
+        .await
+        .map(v2::CfsConfigurationResponse::from)
+      }
+      CfsApiVersion::V2 => {
+        self
+          .cfs_configuration_v2_put(token, configuration, configuration_name)
+          .await
+      }
+    }
+  }
+
+  /// Delete CFS configuration `configuration_name`.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configuration_get`].
+  pub async fn cfs_configuration_delete(
+    &self,
+    token: &str,
+    configuration_name: &str,
+  ) -> Result<(), Error> {
+    match self.cfs_api_version(token).await? {
+      CfsApiVersion::V3 => {
+        self
+          .cfs_configuration_v3_delete(token, configuration_name)
+          .await
+      }
+      CfsApiVersion::V2 => {
+        self
+          .cfs_configuration_v2_delete(token, configuration_name)
+          .await
+      }
+    }
+  }
+
+  /// CFS session `session_name_opt`, or every session if `None`.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configuration_get`].
+  pub async fn cfs_session_get(
+    &self,
+    token: &str,
+    session_name_opt: Option<&str>,
+  ) -> Result<Vec<v2::CfsSessionGetResponse>, Error> {
+    let session_name_opt = session_name_opt.map(str::to_string);
+
+    match self.cfs_api_version(token).await? {
+      CfsApiVersion::V3 => Ok(
+        self
+          .cfs_session_v3_get(
+            token,
+            session_name_opt.as_ref(),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+          )
+          .await?
+          .into_iter()
+          .map(v2::CfsSessionGetResponse::from)
+          .collect(),
+      ),
+      CfsApiVersion::V2 => {
+        self
+          .cfs_session_v2_get(
+            token,
+            None,
+            None,
+            None,
+            session_name_opt.as_ref(),
+            None,
+          )
+          .await
+      }
+    }
+  }
+
+  /// Create CFS session `session`.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configuration_get`].
+  pub async fn cfs_session_post(
+    &self,
+    token: &str,
+    session: &v2::CfsSessionPostRequest,
+  ) -> Result<v2::CfsSessionGetResponse, Error> {
+    match self.cfs_api_version(token).await? {
+      CfsApiVersion::V3 => self
+        .cfs_session_v3_post(token, &v3::CfsSessionPostRequest::from(session))
+        .await
+        .map(v2::CfsSessionGetResponse::from),
+      CfsApiVersion::V2 => self.cfs_session_v2_post(token, session).await,
+    }
+  }
+
+  /// Delete CFS session `session_name`.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configuration_get`].
+  pub async fn cfs_session_delete(
+    &self,
+    token: &str,
+    session_name: &str,
+  ) -> Result<(), Error> {
+    match self.cfs_api_version(token).await? {
+      CfsApiVersion::V3 => {
+        self.cfs_session_v3_delete(token, session_name).await
+      }
+      CfsApiVersion::V2 => {
+        self.cfs_session_v2_delete(token, session_name).await
+      }
+    }
+  }
+
+  /// CFS components, optionally filtered by comma-separated ids and
+  /// status.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configuration_get`].
+  pub async fn cfs_component_get(
+    &self,
+    token: &str,
+    components_ids: Option<&str>,
+    status: Option<&str>,
+  ) -> Result<Vec<v2::Component>, Error> {
+    match self.cfs_api_version(token).await? {
+      CfsApiVersion::V3 => Ok(
+        self
+          .cfs_component_v3_get(token, components_ids, status)
+          .await?
+          .into_iter()
+          .map(v2::Component::from)
+          .collect(),
+      ),
+      CfsApiVersion::V2 => {
+        self
+          .cfs_component_v2_get(token, components_ids, status)
+          .await
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+  };
+
+  use super::*;
+
+  const TEST_PEM: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBhTCCASugAwIBAgIQIRi6zePL6mKjOipn+dNuaTAKBggqhkjOPQQDAjASMRAw\n\
+DgYDVQQKEwdBY21lIENvMB4XDTE3MTAyMDE5NDMwNloXDTE4MTAyMDE5NDMwNlow\n\
+EjEQMA4GA1UEChMHQWNtZSBDbzBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABD0d\n\
+7VNhbWvZLWPuj/RtHFjvtJBEwOkhbN/BnnE8rnZR8+sbwnc/KhCk3FhnpHZnQz7B\n\
+5aETbbIgmuvewdjvSBSjYzBhMA4GA1UdDwEB/wQEAwICpDATBgNVHSUEDDAKBggr\n\
+BgEFBQcDATAPBgNVHRMBAf8EBTADAQH/MCkGA1UdEQQiMCCCDmxvY2FsaG9zdDo1\n\
+NDUzgg4xMjcuMC4wLjE6NTQ1MzAKBggqhkjOPQQDAgNIADBFAiEA2zpJEPQyz6/l\n\
+Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
+6MF9+Yw1Yy0t\n\
+-----END CERTIFICATE-----\n";
+
+  #[tokio::test]
+  async fn falls_back_to_v2_when_cfs_has_no_v3() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path("/cfs/v3/options"))
+      .respond_with(ResponseTemplate::new(404))
+      .expect(1)
+      .mount(&server)
+      .await;
+    Mock::given(method("DELETE"))
+      .and(path("/cfs/v2/sessions/batcher-1"))
+      .respond_with(ResponseTemplate::new(204))
+      .expect(1)
+      .mount(&server)
+      .await;
+    Mock::given(method("DELETE"))
+      .and(path("/cfs/v2/configurations/cos-config"))
+      .respond_with(ResponseTemplate::new(204))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let client =
+      ShastaClient::new(server.uri(), TEST_PEM.as_bytes(), None).unwrap();
+    client
+      .cfs_session_delete("token", "batcher-1")
+      .await
+      .unwrap();
+    // The probe result is shared by clones
+    client
+      .clone()
+      .cfs_configuration_delete("token", "cos-config")
+      .await
+      .unwrap();
+    assert_eq!(
+      client.cfs_api_version("token").await.unwrap(),
+      CfsApiVersion::V2
+    );
+  }
+}
@@ -15,6 +15,7 @@ impl From<FrontEndState> for State {
       playbook: state.playbook,
       commit: state.commit,
       session_name: state.session_name,
+      last_updated: None,
     }
   }
 }
@@ -40,6 +41,7 @@ impl From<FrontEndComponent> for Component {
           playbook: state.playbook,
           commit: state.commit,
           session_name: state.session_name,
+          last_updated: None,
         };
         state_vec.push(state);
       }
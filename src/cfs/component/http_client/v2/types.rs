@@ -6,6 +6,8 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use crate::cfs::component::http_client::v3::types as v3;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct State {
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -18,6 +20,9 @@ pub struct State {
   #[serde(skip_serializing_if = "Option::is_none")]
   #[serde(rename = "sesisonName")]
   pub session_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  #[serde(rename = "lastUpdated")]
+  pub last_updated: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -47,3 +52,30 @@ pub struct Component {
   pub tags: Option<HashMap<String, String>>,
 }
 
+/// Downgrade a v3 component to the v2 shape; `logs` is dropped.
+impl From<v3::Component> for Component {
+  fn from(component: v3::Component) -> Self {
+    Self {
+      id: component.id,
+      state: component.state.map(|state_vec| {
+        state_vec
+          .into_iter()
+          .map(|state| State {
+            clone_url: state.clone_url,
+            playbook: state.playbook,
+            commit: state.commit,
+            session_name: state.session_name,
+            last_updated: None,
+          })
+          .collect()
+      }),
+      state_append: None,
+      desired_config: component.desired_config,
+      error_count: component.error_count,
+      retry_policy: component.retry_policy,
+      enabled: component.enabled,
+      configuration_status: component.configuration_status,
+      tags: component.tags,
+    }
+  }
+}
@@ -26,7 +26,7 @@ pub async fn update_component_desired_configuration(
     logs: None,
   };
 
-  let Ok(client) = crate::ShastaClient::new(
+  let Ok(client) = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -74,7 +74,7 @@ pub async fn update_component_list_desired_configuration(
     component_list.push(component);
   }
 
-  crate::ShastaClient::new(
+  crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -1,16 +1,19 @@
-use std::collections::BTreeMap;
-
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
 
 use crate::{
+  cfs::configuration::types::{RefResolver, ResolvedLayers},
   common::{
     gitea,
+    product_catalog::ProductCatalog,
     yaml::{as_yaml_str, yaml_seq, yaml_str},
   },
   error::Error,
 };
 
+use super::cfs_configuration_response::CfsConfigurationResponse;
+use crate::cfs::configuration::http_client::v3::types::cfs_configuration_request as v3;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Layer {
   pub name: String,
@@ -72,6 +75,69 @@ impl Layer {
   }
 }
 
+impl From<&CfsConfigurationResponse> for CfsConfigurationRequest {
+  /// Request recreating `configuration`, with every layer pinned to the
+  /// commit it currently uses.
+  fn from(configuration: &CfsConfigurationResponse) -> Self {
+    Self {
+      layers: configuration
+        .layers
+        .iter()
+        .map(|layer| Layer {
+          name: layer.name.clone().unwrap_or_default(),
+          clone_url: layer.clone_url.clone(),
+          branch: if layer.commit.is_some() {
+            None
+          } else {
+            layer.branch.clone()
+          },
+          commit: layer.commit.clone(),
+          playbook: layer.playbook.clone(),
+          special_parameters: None,
+        })
+        .collect(),
+    }
+  }
+}
+
+/// Upgrade a v2 configuration request to the v3 shape: every layer
+/// keeps its `clone_url`, none uses a CFS source.
+impl From<&CfsConfigurationRequest> for v3::CfsConfigurationRequest {
+  fn from(configuration: &CfsConfigurationRequest) -> Self {
+    let layer_vec = configuration
+      .layers
+      .iter()
+      .map(|layer| {
+        v3::Layer::new(
+          Some(layer.name.clone()),
+          Some(layer.clone_url.clone()),
+          None,
+          layer.playbook.clone(),
+          layer.commit.clone(),
+          layer.branch.clone(),
+          layer
+            .special_parameters
+            .as_ref()
+            .map(|special_parameter_vec| {
+              special_parameter_vec
+                .iter()
+                .map(|special_parameter| v3::SpecialParameter {
+                  ims_required_dkms: special_parameter.ims_required_dkms,
+                })
+                .collect()
+            }),
+        )
+      })
+      .collect();
+
+    Self {
+      description: None,
+      layers: Some(layer_vec),
+      additional_inventory: None,
+    }
+  }
+}
+
 impl Default for CfsConfigurationRequest {
   fn default() -> Self {
     Self::new()
@@ -90,6 +156,22 @@ impl CfsConfigurationRequest {
     self.layers.push(layer);
   }
 
+  /// Whether `configuration` already has these layers. A layer given by
+  /// branch matches whatever commit the branch resolved to.
+  #[must_use]
+  pub fn matches(&self, configuration: &CfsConfigurationResponse) -> bool {
+    self.layers.len() == configuration.layers.len()
+      && self.layers.iter().zip(&configuration.layers).all(
+        |(layer, existing)| {
+          existing.name.as_deref() == Some(layer.name.as_str())
+            && existing.clone_url == layer.clone_url
+            && existing.playbook == layer.playbook
+            && (layer.commit.is_none() || existing.commit == layer.commit)
+            && (layer.branch.is_none() || existing.branch == layer.branch)
+        },
+      )
+  }
+
   /// Converts a CFS configuration in the SAT file represented as a `serde_yaml::Value` into a
   /// `CfsConfigurationRequest` struct that we can use to create CFS configuration in CSM through its
   /// APIs. This function also resolves the git commit id for git layers in the SAT file if the
@@ -101,6 +183,9 @@ impl CfsConfigurationRequest {
   /// Returns the CFS configuration name and the `CfsConfigurationRequest` struct created from the
   /// SAT file.
   ///
+  /// See [`Self::from_sat_file_serde_yaml_with_report`] to also get the
+  /// refs each layer was pinned to.
+  ///
   /// # Errors
   ///
   /// Returns an [`Error`] variant on CSM, transport, or
@@ -111,18 +196,62 @@ impl CfsConfigurationRequest {
     gitea_base_url: &str,
     gitea_token: &str,
     configuration_yaml: &serde_yaml::Value,
-    cray_product_catalog: &BTreeMap<String, String>,
+    cray_product_catalog: &ProductCatalog,
     site_name: &str,
     socks5_proxy: Option<&str>,
   ) -> Result<(String, Self), Error> {
+    let (cfs_configuration_name, cfs_configuration, _) =
+      Self::from_sat_file_serde_yaml_with_report(
+        shasta_root_cert,
+        gitea_base_url,
+        gitea_token,
+        configuration_yaml,
+        cray_product_catalog,
+        site_name,
+        socks5_proxy,
+      )
+      .await?;
+
+    Ok((cfs_configuration_name, cfs_configuration))
+  }
+
+  /// Same as [`Self::from_sat_file_serde_yaml`], but also returns a
+  /// [`ResolvedLayers`] report recording, for every layer, the ref
+  /// requested in the SAT file, the commit SHA it was pinned to and
+  /// where that SHA came from.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn from_sat_file_serde_yaml_with_report(
+    shasta_root_cert: &[u8],
+    gitea_base_url: &str,
+    gitea_token: &str,
+    configuration_yaml: &serde_yaml::Value,
+    cray_product_catalog: &ProductCatalog,
+    site_name: &str,
+    socks5_proxy: Option<&str>,
+  ) -> Result<(String, Self, ResolvedLayers), Error> {
     let mut cfs_configuration = Self::new();
 
     let cfs_configuration_name =
       yaml_str(configuration_yaml, "name")?.to_string();
 
+    let mut resolved_layers = ResolvedLayers::new(&cfs_configuration_name);
+
     for layer_yaml in yaml_seq(configuration_yaml, "layers")? {
       // log::debug!("\n\n### Layer:\n{:#?}\n", layer_json);
 
+      if let Some(source_name) =
+        layer_yaml.get("source").and_then(Value::as_str)
+      {
+        return Err(Error::SatFile(format!(
+          "configuration '{cfs_configuration_name}' uses CFS source '{source_name}'; CFS sources require the v3 API"
+        )));
+      }
+
       if let Some(git_yaml) = layer_yaml.get("git") {
         // Git layer
 
@@ -137,56 +266,55 @@ impl CfsConfigurationRequest {
         let branch_value_opt =
           layer_yaml.get("git").and_then(|git| git.get("branch"));
 
+        let mut requested_ref: Option<String> = None;
+        let mut resolver = RefResolver::Unresolved;
+
         let commit_id_opt: Option<String> = if commit_id_value_opt.is_some() {
           // Git commit id
-          layer_yaml
+          resolver = RefResolver::Commit;
+          let commit_id_opt = layer_yaml
             .get("git")
             .and_then(|git| git.get("commit"))
             .and_then(Value::as_str)
-            .map(str::to_string)
+            .map(str::to_string);
+          requested_ref.clone_from(&commit_id_opt);
+          commit_id_opt
         } else if let Some(git_tag_value) = tag_value_opt {
           // Git tag
           let git_tag = as_yaml_str(git_tag_value)?;
+          resolver = RefResolver::GitTag;
+          requested_ref = Some(git_tag.to_string());
 
           log::debug!("git tag: {git_tag}");
 
-          let tag_details_rslt = gitea::http_client::get_tag_details(
-            &repo_url,
-            git_tag,
+          // Pin the tag's own sha: for an annotated tag that is the tag
+          // object, not the commit it points to. CFS' `git checkout` of
+          // it still lands on the final commit, and keeping the tag
+          // object sha lets us find the tag again through the Gitea APIs
+          // (see `get_configuration_layer_details`)
+          let resolved_ref = gitea::http_client::resolve_ref(
+            gitea_base_url,
             gitea_token,
+            &repo_url,
+            &format!("refs/tags/{git_tag}"),
             shasta_root_cert,
             socks5_proxy,
-            site_name,
           )
-          .await;
+          .await
+          .map_err(|e| {
+            Error::Message(format!(
+              "ERROR - Could not get details for git tag '{git_tag}' in CFS configuration '{cfs_configuration_name}'. Reason:\n{e:#?}"
+            ))
+          })?;
 
-          let tag_details = if let Ok(tag_details) = tag_details_rslt {
-            log::debug!("tag details:\n{tag_details:#?}");
-            tag_details
-          } else {
-            return Err(Error::Message(format!(
-              "ERROR - Could not get details for git tag '{git_tag}' in CFS configuration '{cfs_configuration_name}'. Reason:\n{tag_details_rslt:#?}"
-            )));
-          };
+          log::debug!("git tag resolved to:\n{resolved_ref:#?}");
 
-          // Assuming user sets an existing tag name. It could be an annotated tag
-          // (different object than the commit id with its own sha value) or a
-          // lightweight tag (pointer to commit id, therefore the tag will have the
-          // same sha as the commit id it points to), either way CFS session will
-          // do a `git checkout` to the sha we found here, if an annotated tag, then,
-          // git is clever enough to take us to the final commit id, if it is a
-          // lightweight tag, then there is no problem because the sha is the same
-          // as the commit id
-          // NOTE: the `id` field is the tag's sha, note we are not taking the commit id
-          // the tag points to and we should not use sha because otherwise we won't be
-          // able to fetch the annotated tag using a commit sha through the Gitea APIs
-          tag_details
-            .get("id")
-            .and_then(serde_json::Value::as_str)
-            .map(str::to_string)
+          Some(resolved_ref.sha)
         } else if let Some(branch_value) = branch_value_opt {
           // Branch name
           let branch_name = as_yaml_str(branch_value)?;
+          resolver = RefResolver::GitBranch;
+          requested_ref = Some(branch_name.to_string());
           Some(
             gitea::http_client::get_commit_pointed_by_branch(
               gitea_base_url,
@@ -212,6 +340,13 @@ impl CfsConfigurationRequest {
           branch_value_opt.and_then(Value::as_str).map(str::to_string)
         };
 
+        resolved_layers.push(
+          &layer_name,
+          requested_ref.as_deref(),
+          commit_id_opt.as_deref(),
+          resolver,
+        );
+
         let layer = Layer::new(
           repo_url,
           commit_id_opt,
@@ -233,19 +368,10 @@ impl CfsConfigurationRequest {
         let product_version = yaml_str(product_yaml, "version")?;
         let product_branch_value_opt = product_yaml.get("branch");
 
-        let product =
-          cray_product_catalog.get(product_name).ok_or_else(|| {
-            Error::CrayProductCatalog(format!(
-              "Product {product_name} not found in cray product catalog"
-            ))
-          })?;
-
-        let cos_cray_product_catalog = serde_yaml::from_str::<Value>(product)?;
-
-        let product_details = cos_cray_product_catalog
-          .get(product_version)
-          .and_then(|product| product.get("configuration"))
-          .cloned()
+        let product_details = cray_product_catalog
+          .version(product_name, product_version)?
+          .configuration
+          .as_ref()
           .ok_or_else(|| {
             Error::CrayProductCatalog(format!(
               "Product details for product name '{product_name}', product_version '{product_version}' and 'configuration' not found in cray product catalog"
@@ -258,17 +384,24 @@ impl CfsConfigurationRequest {
 
         // Manta may run outside the CSM local network therefore we have to change the
         // internal URLs for the external one
-        let repo_url = yaml_str(&product_details, "clone_url")?.replace(
-          format!("vcs.cmn.{site_name}.cscs.ch").as_str(),
-          crate::common::gitea::INTERNAL_API_HOST,
-        );
-
-        let commit_id_opt = if let Some(branch_value) = product_branch_value_opt
-        {
-          // If branch is provided, then ignore the commit id in the CRAY products table
-          let branch_name = as_yaml_str(branch_value)?;
-          Some(
-            gitea::http_client::get_commit_pointed_by_branch(
+        let repo_url = product_details
+          .clone_url
+          .as_deref()
+          .ok_or_else(|| {
+            Error::CrayProductCatalog(format!(
+              "Product details for product name '{product_name}', product_version '{product_version}' has no 'configuration.clone_url' in cray product catalog"
+            ))
+          })?
+          .replace(
+            format!("vcs.cmn.{site_name}.cscs.ch").as_str(),
+            crate::common::gitea::INTERNAL_API_HOST,
+          );
+
+        let (commit_id_opt, requested_ref, resolver) =
+          if let Some(branch_value) = product_branch_value_opt {
+            // If branch is provided, then ignore the commit id in the CRAY products table
+            let branch_name = as_yaml_str(branch_value)?;
+            let commit_id = gitea::http_client::get_commit_pointed_by_branch(
               gitea_base_url,
               gitea_token,
               shasta_root_cert,
@@ -276,14 +409,26 @@ impl CfsConfigurationRequest {
               &repo_url,
               branch_name,
             )
-            .await?,
-          )
-        } else {
-          product_details
-            .get("commit")
-            .and_then(Value::as_str)
-            .map(str::to_string)
-        };
+            .await?;
+            (
+              Some(commit_id),
+              branch_name.to_string(),
+              RefResolver::ProductBranch,
+            )
+          } else {
+            (
+              product_details.commit.clone(),
+              product_version.to_string(),
+              RefResolver::ProductCatalog,
+            )
+          };
+
+        resolved_layers.push(
+          product_name,
+          Some(requested_ref.as_str()),
+          commit_id_opt.as_deref(),
+          resolver,
+        );
 
         // IMPORTANT: CSM won't allow CFS configuration layers with both commit id and
         // branch name, therefore, we will set branch name to None if we already have a
@@ -312,6 +457,51 @@ impl CfsConfigurationRequest {
       }
     }
 
-    Ok((cfs_configuration_name, cfs_configuration))
+    log::debug!("Refs pinned for {resolved_layers}");
+
+    Ok((cfs_configuration_name, cfs_configuration, resolved_layers))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::cfs::configuration::http_client::v2::types::cfs_configuration_response::Layer as ResponseLayer;
+
+  #[test]
+  fn matches_pinned_branch_and_round_trips_existing() {
+    let existing = CfsConfigurationResponse {
+      name: "cos-config".to_string(),
+      last_updated: String::new(),
+      layers: vec![ResponseLayer {
+        name: Some("cos".to_string()),
+        clone_url: "https://vcs/cos.git".to_string(),
+        commit: Some("abc123".to_string()),
+        playbook: "site.yml".to_string(),
+        branch: Some("main".to_string()),
+      }],
+      additional_inventory: None,
+    };
+
+    let by_branch = CfsConfigurationRequest {
+      layers: vec![Layer::new(
+        "https://vcs/cos.git".to_string(),
+        None,
+        "cos".to_string(),
+        "site.yml".to_string(),
+        Some("main".to_string()),
+        None,
+        None,
+      )],
+    };
+    assert!(by_branch.matches(&existing));
+
+    let mut other_commit = CfsConfigurationRequest::from(&existing);
+    assert!(other_commit.matches(&existing));
+    assert_eq!(other_commit.layers[0].branch, None);
+
+    other_commit.layers[0].commit = Some("def456".to_string());
+    assert!(!other_commit.matches(&existing));
   }
 }
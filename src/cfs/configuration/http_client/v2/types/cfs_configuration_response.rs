@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::cfs::configuration::http_client::v3::types::cfs_configuration_response as v3;
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Layer {
   pub name: Option<String>,
@@ -108,3 +110,38 @@ impl CfsConfigurationResponse {
     self.layers.push(layer);
   }
 }
+
+/// Downgrade a v3 configuration to the v2 shape. v2 layers have no
+/// `source` field, so layers backed by a CFS source come out with an
+/// empty `clone_url`.
+impl From<v3::CfsConfigurationResponse> for CfsConfigurationResponse {
+  fn from(configuration: v3::CfsConfigurationResponse) -> Self {
+    Self {
+      name: configuration.name,
+      last_updated: configuration.last_updated,
+      layers: configuration
+        .layers
+        .into_iter()
+        .map(|layer| {
+          Layer::new(
+            layer.clone_url,
+            layer.commit,
+            layer.name,
+            layer.playbook,
+            layer.branch,
+          )
+        })
+        .collect(),
+      additional_inventory: configuration.additional_inventory.map(
+        |inventory| {
+          AdditionalInventory::new(
+            inventory.clone_url,
+            inventory.commit,
+            inventory.name,
+            inventory.branch,
+          )
+        },
+      ),
+    }
+  }
+}
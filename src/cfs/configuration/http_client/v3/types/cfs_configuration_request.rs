@@ -1,16 +1,18 @@
-use std::collections::BTreeMap;
-
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
 
 use crate::{
+  cfs::configuration::types::{RefResolver, ResolvedLayers},
   common::{
     gitea,
+    product_catalog::ProductCatalog,
     yaml::{as_yaml_str, yaml_seq, yaml_str},
   },
   error::Error,
 };
 
+use super::cfs_configuration_response::CfsConfigurationResponse;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Layer {
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -77,6 +79,50 @@ pub struct CfsConfigurationRequest {
   pub additional_inventory: Option<AdditionalInventory>,
 }
 
+impl From<&CfsConfigurationResponse> for CfsConfigurationRequest {
+  /// Request recreating `configuration`, with every layer pinned to the
+  /// commit it currently uses.
+  fn from(configuration: &CfsConfigurationResponse) -> Self {
+    Self {
+      description: None,
+      layers: Some(
+        configuration
+          .layers
+          .iter()
+          .map(|layer| Layer {
+            name: layer.name.clone(),
+            // Layers referencing a CFS source have no clone URL
+            clone_url: Some(layer.clone_url.clone())
+              .filter(|clone_url| !clone_url.is_empty()),
+            source: layer.source.clone(),
+            playbook: layer.playbook.clone(),
+            commit: layer.commit.clone(),
+            branch: if layer.commit.is_some() {
+              None
+            } else {
+              layer.branch.clone()
+            },
+            special_parameters: None,
+          })
+          .collect(),
+      ),
+      additional_inventory: configuration.additional_inventory.as_ref().map(
+        |additional_inventory| AdditionalInventory {
+          name: Some(additional_inventory.name.clone()),
+          clone_url: additional_inventory.clone_url.clone(),
+          source: None,
+          commit: additional_inventory.commit.clone(),
+          branch: if additional_inventory.commit.is_some() {
+            None
+          } else {
+            additional_inventory.branch.clone()
+          },
+        },
+      ),
+    }
+  }
+}
+
 impl Default for CfsConfigurationRequest {
   fn default() -> Self {
     Self::new()
@@ -99,6 +145,34 @@ impl CfsConfigurationRequest {
     }
   }
 
+  /// Names of the CFS sources referenced by the layers (and the
+  /// additional inventory), deduplicated, in layer order.
+  #[must_use]
+  pub fn source_name_vec(&self) -> Vec<&str> {
+    let mut source_name_vec: Vec<&str> = Vec::new();
+    let layer_sources = self
+      .layers
+      .iter()
+      .flatten()
+      .filter_map(|layer| layer.source.as_deref());
+    let inventory_source = self
+      .additional_inventory
+      .as_ref()
+      .and_then(|inventory| inventory.source.as_deref());
+
+    for source_name in layer_sources.chain(inventory_source) {
+      if !source_name_vec.contains(&source_name) {
+        source_name_vec.push(source_name);
+      }
+    }
+
+    source_name_vec
+  }
+
+  /// v3 counterpart of the v2 SAT-file parser: converts one SAT
+  /// `configurations` entry into a request, resolving tags and
+  /// branches to commit SHAs through Gitea.
+  ///
   /// # Errors
   ///
   /// Returns an [`Error`] variant on CSM, transport, or
@@ -109,17 +183,109 @@ impl CfsConfigurationRequest {
     gitea_base_url: &str,
     gitea_token: &str,
     configuration_yaml: &serde_yaml::Value,
-    cray_product_catalog: &BTreeMap<String, String>,
+    cray_product_catalog: &ProductCatalog,
     site_name: &str,
     socks5_proxy: Option<&str>,
   ) -> Result<(String, Self), Error> {
+    let (cfs_configuration_name, cfs_configuration, _) =
+      Self::from_sat_file_serde_yaml_with_report(
+        shasta_root_cert,
+        gitea_base_url,
+        gitea_token,
+        configuration_yaml,
+        cray_product_catalog,
+        site_name,
+        socks5_proxy,
+      )
+      .await?;
+
+    Ok((cfs_configuration_name, cfs_configuration))
+  }
+
+  /// Same as [`Self::from_sat_file_serde_yaml`], but also returns the
+  /// [`ResolvedLayers`] report of the refs each layer was pinned to.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn from_sat_file_serde_yaml_with_report(
+    shasta_root_cert: &[u8],
+    gitea_base_url: &str,
+    gitea_token: &str,
+    configuration_yaml: &serde_yaml::Value,
+    cray_product_catalog: &ProductCatalog,
+    site_name: &str,
+    socks5_proxy: Option<&str>,
+  ) -> Result<(String, Self, ResolvedLayers), Error> {
     let mut cfs_configuration = Self::new();
 
     let cfs_configuration_name =
       yaml_str(configuration_yaml, "name")?.to_string();
 
+    let mut resolved_layers = ResolvedLayers::new(&cfs_configuration_name);
+
     for layer_yaml in yaml_seq(configuration_yaml, "layers")? {
-      if let Some(git_yaml) = layer_yaml.get("git") {
+      if let Some(source_name) =
+        layer_yaml.get("source").and_then(Value::as_str)
+      {
+        // Source layer
+        //
+        // The repo lives outside the CSM Gitea and CFS holds its
+        // credentials, so refs can't be resolved from here: a commit is
+        // passed through as-is and a branch is left for CFS to resolve
+        // when the session runs. CFS layers have no tag field.
+
+        let layer_name = yaml_str(layer_yaml, "name")?.to_string();
+        let git_yaml_opt = layer_yaml.get("git");
+
+        if git_yaml_opt.and_then(|git| git.get("tag")).is_some() {
+          return Err(Error::SatFile(format!(
+            "layer '{layer_name}' uses CFS source '{source_name}' with a git tag; source layers only accept 'commit' or 'branch'"
+          )));
+        }
+
+        let commit_id_opt = git_yaml_opt
+          .and_then(|git| git.get("commit"))
+          .map(|v| as_yaml_str(v).map(str::to_string))
+          .transpose()?;
+        let branch_name = if commit_id_opt.is_some() {
+          None
+        } else {
+          git_yaml_opt
+            .and_then(|git| git.get("branch"))
+            .map(|v| as_yaml_str(v).map(str::to_string))
+            .transpose()?
+        };
+
+        let resolver = if commit_id_opt.is_some() {
+          RefResolver::Commit
+        } else {
+          RefResolver::Unresolved
+        };
+        resolved_layers.push(
+          &layer_name,
+          commit_id_opt.as_deref().or(branch_name.as_deref()),
+          commit_id_opt.as_deref(),
+          resolver,
+        );
+
+        let layer = Layer::new(
+          Some(layer_name),
+          None,
+          Some(source_name.to_string()),
+          layer_yaml
+            .get("playbook")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_default(),
+          commit_id_opt,
+          branch_name,
+          None,
+        );
+        cfs_configuration.add_layer(layer);
+      } else if let Some(git_yaml) = layer_yaml.get("git") {
         // Git layer
 
         let layer_name = yaml_str(layer_yaml, "name")?.to_string();
@@ -133,56 +299,55 @@ impl CfsConfigurationRequest {
         let branch_value_opt =
           layer_yaml.get("git").and_then(|git| git.get("branch"));
 
+        let mut requested_ref: Option<String> = None;
+        let mut resolver = RefResolver::Unresolved;
+
         let commit_id_opt: Option<String> = if commit_id_value_opt.is_some() {
           // Git commit id
-          layer_yaml
+          resolver = RefResolver::Commit;
+          let commit_id_opt = layer_yaml
             .get("git")
             .and_then(|git| git.get("commit"))
             .and_then(Value::as_str)
-            .map(str::to_string)
+            .map(str::to_string);
+          requested_ref.clone_from(&commit_id_opt);
+          commit_id_opt
         } else if let Some(git_tag_value) = tag_value_opt {
           // Git tag
           let git_tag = as_yaml_str(git_tag_value)?;
+          resolver = RefResolver::GitTag;
+          requested_ref = Some(git_tag.to_string());
 
           log::debug!("git tag: {git_tag}");
 
-          let tag_details_rslt = gitea::http_client::get_tag_details(
-            &repo_url,
-            git_tag,
+          // Pin the tag's own sha: for an annotated tag that is the tag
+          // object, not the commit it points to. CFS' `git checkout` of
+          // it still lands on the final commit, and keeping the tag
+          // object sha lets us find the tag again through the Gitea APIs
+          // (see `get_configuration_layer_details`)
+          let resolved_ref = gitea::http_client::resolve_ref(
+            gitea_base_url,
             gitea_token,
+            &repo_url,
+            &format!("refs/tags/{git_tag}"),
             shasta_root_cert,
             socks5_proxy,
-            site_name,
           )
-          .await;
+          .await
+          .map_err(|e| {
+            Error::Message(format!(
+              "ERROR - Could not get details for git tag '{git_tag}' in CFS configuration '{cfs_configuration_name}'. Reason:\n{e:#?}"
+            ))
+          })?;
 
-          let tag_details = if let Ok(tag_details) = tag_details_rslt {
-            log::debug!("tag details:\n{tag_details:#?}");
-            tag_details
-          } else {
-            return Err(Error::Message(format!(
-              "ERROR - Could not get details for git tag '{git_tag}' in CFS configuration '{cfs_configuration_name}'. Reason:\n{tag_details_rslt:#?}"
-            )));
-          };
+          log::debug!("git tag resolved to:\n{resolved_ref:#?}");
 
-          // Assuming user sets an existing tag name. It could be an annotated tag
-          // (different object than the commit id with its own sha value) or a
-          // lightweight tag (pointer to commit id, therefore the tag will have the
-          // same sha as the commit id it points to), either way CFS session will
-          // do a `git checkout` to the sha we found here, if an annotated tag, then,
-          // git is clever enough to take us to the final commit id, if it is a
-          // lightweight tag, then there is no problem because the sha is the same
-          // as the commit id
-          // NOTE: the `id` field is the tag's sha, note we are not taking the commit id
-          // the tag points to and we should not use sha because otherwise we won't be
-          // able to fetch the annotated tag using a commit sha through the Gitea APIs
-          tag_details
-            .get("id")
-            .and_then(serde_json::Value::as_str)
-            .map(str::to_string)
+          Some(resolved_ref.sha)
         } else if let Some(branch_value) = branch_value_opt {
           // Branch name
           let branch_name = as_yaml_str(branch_value)?;
+          resolver = RefResolver::GitBranch;
+          requested_ref = Some(branch_name.to_string());
           Some(
             gitea::http_client::get_commit_pointed_by_branch(
               gitea_base_url,
@@ -210,18 +375,37 @@ impl CfsConfigurationRequest {
             .transpose()?
         };
 
+        resolved_layers.push(
+          &layer_name,
+          requested_ref.as_deref(),
+          commit_id_opt.as_deref(),
+          resolver,
+        );
+
+        let playbook = layer_yaml
+          .get("playbook")
+          .and_then(Value::as_str)
+          .unwrap_or_default();
+
+        if let Some(commit_id) = &commit_id_opt {
+          check_playbook_exists(
+            gitea_base_url,
+            gitea_token,
+            shasta_root_cert,
+            socks5_proxy,
+            &layer_name,
+            &repo_url,
+            playbook,
+            commit_id,
+          )
+          .await?;
+        }
+
         let layer = Layer::new(
           Some(layer_name),
           Some(repo_url),
-          layer_yaml
-            .get("source")
-            .and_then(Value::as_str)
-            .map(str::to_string),
-          layer_yaml
-            .get("playbook")
-            .and_then(Value::as_str)
-            .map(str::to_string)
-            .unwrap_or_default(),
+          None,
+          playbook.to_string(),
           commit_id_opt,
           branch_name,
           None,
@@ -235,19 +419,10 @@ impl CfsConfigurationRequest {
         let product_branch_value_opt = product_yaml.get("branch");
         let product_commit_value_opt = product_yaml.get("commit");
 
-        let product =
-          cray_product_catalog.get(product_name).ok_or_else(|| {
-            Error::CrayProductCatalog(format!(
-              "Product {product_name} not found in cray product catalog"
-            ))
-          })?;
-
-        let cos_cray_product_catalog = serde_yaml::from_str::<Value>(product)?;
-
-        let product_details = cos_cray_product_catalog
-          .get(product_version)
-          .and_then(|product| product.get("configuration"))
-          .cloned()
+        let product_details = cray_product_catalog
+          .version(product_name, product_version)?
+          .configuration
+          .as_ref()
           .ok_or_else(|| {
             Error::CrayProductCatalog(format!(
               "Product details for product name '{product_name}', product_version '{product_version}' and 'configuration' not found in cray product catalog"
@@ -260,19 +435,28 @@ impl CfsConfigurationRequest {
 
         // Manta may run outside the CSM local network therefore we have to change the
         // internal URLs for the external one
-        let repo_url = yaml_str(&product_details, "clone_url")?.replace(
-          format!("vcs.cmn.{site_name}.cscs.ch").as_str(),
-          crate::common::gitea::INTERNAL_API_HOST,
-        );
+        let repo_url = product_details
+          .clone_url
+          .as_deref()
+          .ok_or_else(|| {
+            Error::CrayProductCatalog(format!(
+              "Product details for product name '{product_name}', product_version '{product_version}' has no 'configuration.clone_url' in cray product catalog"
+            ))
+          })?
+          .replace(
+            format!("vcs.cmn.{site_name}.cscs.ch").as_str(),
+            crate::common::gitea::INTERNAL_API_HOST,
+          );
 
-        let commit_id_opt = if let Some(commit_value) = product_commit_value_opt
-        {
-          commit_value.as_str().map(str::to_string)
-        } else if let Some(branch_value) = product_branch_value_opt {
-          // If branch is provided, then ignore the commit id in the CRAY products table
-          let branch_name = as_yaml_str(branch_value)?;
-          Some(
-            gitea::http_client::get_commit_pointed_by_branch(
+        let (commit_id_opt, requested_ref, resolver) =
+          if let Some(commit_value) = product_commit_value_opt {
+            let commit_id_opt = commit_value.as_str().map(str::to_string);
+            let requested_ref = commit_id_opt.clone().unwrap_or_default();
+            (commit_id_opt, requested_ref, RefResolver::Commit)
+          } else if let Some(branch_value) = product_branch_value_opt {
+            // If branch is provided, then ignore the commit id in the CRAY products table
+            let branch_name = as_yaml_str(branch_value)?;
+            let commit_id = gitea::http_client::get_commit_pointed_by_branch(
               gitea_base_url,
               gitea_token,
               shasta_root_cert,
@@ -280,14 +464,26 @@ impl CfsConfigurationRequest {
               &repo_url,
               branch_name,
             )
-            .await?,
-          )
-        } else {
-          product_details
-            .get("commit")
-            .and_then(Value::as_str)
-            .map(str::to_string)
-        };
+            .await?;
+            (
+              Some(commit_id),
+              branch_name.to_string(),
+              RefResolver::ProductBranch,
+            )
+          } else {
+            (
+              product_details.commit.clone(),
+              product_version.to_string(),
+              RefResolver::ProductCatalog,
+            )
+          };
+
+        resolved_layers.push(
+          product_name,
+          Some(requested_ref.as_str()),
+          commit_id_opt.as_deref(),
+          resolver,
+        );
 
         // IMPORTANT: CSM won't allow CFS configuration layers with both commit id and
         // branch name, therefore, we will set branch name to None if we already have a
@@ -300,6 +496,22 @@ impl CfsConfigurationRequest {
             .transpose()?
         };
 
+        let playbook = yaml_str(layer_yaml, "playbook")?;
+
+        if let Some(commit_id) = &commit_id_opt {
+          check_playbook_exists(
+            gitea_base_url,
+            gitea_token,
+            shasta_root_cert,
+            socks5_proxy,
+            product_name,
+            &repo_url,
+            playbook,
+            commit_id,
+          )
+          .await?;
+        }
+
         // Create CFS configuration layer struct
         let layer = Layer::new(
           Some(product_name.to_string()),
@@ -308,7 +520,7 @@ impl CfsConfigurationRequest {
             .get("source")
             .and_then(Value::as_str)
             .map(str::to_string),
-          yaml_str(layer_yaml, "playbook")?.to_string(),
+          playbook.to_string(),
           commit_id_opt,
           branch_name,
           None,
@@ -319,7 +531,9 @@ impl CfsConfigurationRequest {
       }
     }
 
-    Ok((cfs_configuration_name, cfs_configuration))
+    log::debug!("Refs pinned for {resolved_layers}");
+
+    Ok((cfs_configuration_name, cfs_configuration, resolved_layers))
   }
 
   /// # Errors
@@ -365,6 +579,26 @@ impl CfsConfigurationRequest {
         }
       }
 
+      let playbook = playbook_file_name_opt.unwrap_or("site.yml");
+
+      if !gitea::http_client::file_exists(
+        "https://api-gw-service-nmn.local/vcs/",
+        repo_name,
+        playbook,
+        local_last_commit,
+        gitea_token,
+        shasta_root_cert,
+        socks5_proxy,
+      )
+      .await?
+      {
+        return Err(Error::PlaybookNotFound {
+          layer: (*repo_name).to_string(),
+          playbook: playbook.to_string(),
+          commit: (*local_last_commit).to_string(),
+        });
+      }
+
       let clone_url = gitea_base_url.to_owned() + repo_name;
 
       log::debug!("clone url: {clone_url}");
@@ -378,7 +612,7 @@ impl CfsConfigurationRequest {
         )),
         Some(clone_url),
         None,
-        playbook_file_name_opt.unwrap_or("site.yml").to_string(),
+        playbook.to_string(),
         Some(local_last_commit.to_string()),
         None,
         None,
@@ -392,3 +626,52 @@ impl CfsConfigurationRequest {
     Ok(cfs_configuration)
   }
 }
+
+/// Fail with [`Error::PlaybookNotFound`] if `playbook` is missing from the
+/// Gitea repo at `repo_url` at `commit_id`, rather than letting the CFS
+/// session fail on it much later. Repos reached over SSH are not checked.
+#[allow(clippy::too_many_arguments)]
+async fn check_playbook_exists(
+  gitea_base_url: &str,
+  gitea_token: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  layer_name: &str,
+  repo_url: &str,
+  playbook: &str,
+  commit_id: &str,
+) -> Result<(), Error> {
+  if playbook.is_empty() {
+    return Ok(());
+  }
+
+  if crate::common::git_ssh::is_ssh_url(repo_url) {
+    log::debug!(
+      "Skip check of playbook '{playbook}' of layer '{layer_name}': {repo_url} is reached over SSH"
+    );
+    return Ok(());
+  }
+
+  let repo_name = gitea::http_client::get_repo_name_from_url(repo_url)?;
+
+  let exists = gitea::http_client::file_exists(
+    &format!("{gitea_base_url}/"),
+    &repo_name,
+    playbook,
+    commit_id,
+    gitea_token,
+    shasta_root_cert,
+    socks5_proxy,
+  )
+  .await?;
+
+  if exists {
+    Ok(())
+  } else {
+    Err(Error::PlaybookNotFound {
+      layer: layer_name.to_string(),
+      playbook: playbook.to_string(),
+      commit: commit_id.to_string(),
+    })
+  }
+}
@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 pub struct Layer {
   pub name: Option<String>,
   // #[serde(rename = "cloneUrl")]
+  // Absent on layers that reference a CFS source instead
+  #[serde(default)]
   pub clone_url: String,
   pub source: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -31,6 +33,8 @@ pub struct AdditionalInventory {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CfsConfigurationResponse {
   pub name: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
   // #[serde(rename = "lastUpdated")]
   pub last_updated: String,
   pub layers: Vec<Layer>,
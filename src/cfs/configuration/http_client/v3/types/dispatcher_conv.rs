@@ -222,6 +222,7 @@ impl From<FrontendCfsConfigurationResponse> for CfsConfigurationResponse {
   fn from(value: FrontendCfsConfigurationResponse) -> Self {
     CfsConfigurationResponse {
       name: value.name,
+      description: None,
       last_updated: value.last_updated,
       layers: value.layers.into_iter().map(ResponseLayer::from).collect(),
       additional_inventory: value
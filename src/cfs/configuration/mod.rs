@@ -4,7 +4,13 @@
 //! Submodules:
 //!
 //! - [`http_client`] — `ShastaClient` methods for the v2 and v3 endpoints.
-//! - [`utils`] — helpers built on top of the raw client.
+//! - [`types`] — reports produced while resolving SAT-file layers
+//!   (e.g. [`types::ResolvedLayers`]), the overwrite policy used when
+//!   creating configurations, and layer edits.
+//! - [`utils`] — helpers built on top of the raw client, e.g.
+//!   [`utils::edit`] to insert, remove, reorder or pin layers of an
+//!   existing configuration.
 
 pub mod http_client;
+pub mod types;
 pub mod utils;
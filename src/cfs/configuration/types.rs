@@ -0,0 +1,317 @@
+//! Reports produced while turning SAT-file `configurations` entries into
+//! CFS configuration requests, the overwrite policy used when creating
+//! them, and what uses an existing configuration.
+//!
+//! The SAT parser resolves git branches, tags and product-catalog
+//! entries to concrete commit SHAs before posting the configuration to
+//! CFS. [`ResolvedLayers`] records that mapping so operators can keep a
+//! record of exactly what was pinned, instead of digging it out of the
+//! debug logs.
+//!
+//! [`LayerEdit`] and [`EditedName`] describe changes to the layers of an
+//! existing configuration, applied by [`super::utils::edit`].
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::http_client::v2::types::cfs_configuration_request::Layer;
+
+/// Where the commit SHA pinned on a CFS configuration layer came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefResolver {
+  /// The SAT file already named a commit; no lookup was made.
+  Commit,
+  /// A git tag resolved through the Gitea tags API.
+  GitTag,
+  /// A git branch resolved through the Gitea refs API.
+  GitBranch,
+  /// Commit taken verbatim from the `cray-product-catalog` `ConfigMap`.
+  ProductCatalog,
+  /// Product layer whose `branch` override was resolved through Gitea.
+  ProductBranch,
+  /// Nothing to resolve; CSM picks the commit when the session runs.
+  Unresolved,
+}
+
+impl fmt::Display for RefResolver {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let s = match self {
+      RefResolver::Commit => "commit",
+      RefResolver::GitTag => "git tag",
+      RefResolver::GitBranch => "git branch",
+      RefResolver::ProductCatalog => "product catalog",
+      RefResolver::ProductBranch => "product branch",
+      RefResolver::Unresolved => "unresolved",
+    };
+    f.write_str(s)
+  }
+}
+
+/// One layer of a [`ResolvedLayers`] report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedLayer {
+  /// Layer name as it will appear in the CFS configuration.
+  pub layer_name: String,
+  /// The ref the SAT file asked for (commit, tag, branch or product
+  /// version), or `None` when the layer named none.
+  pub requested_ref: Option<String>,
+  /// Commit SHA written to the CFS layer, if any.
+  pub resolved_sha: Option<String>,
+  /// How `resolved_sha` was obtained.
+  pub resolver: RefResolver,
+}
+
+/// Per-configuration record of the refs pinned while converting a SAT
+/// file entry into a CFS configuration request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedLayers {
+  /// Name of the CFS configuration the layers belong to.
+  pub configuration_name: String,
+  /// Layers in the same order as the CFS configuration.
+  pub layers: Vec<ResolvedLayer>,
+}
+
+impl ResolvedLayers {
+  /// Empty report for `configuration_name`.
+  #[must_use]
+  pub fn new(configuration_name: &str) -> Self {
+    Self {
+      configuration_name: configuration_name.to_string(),
+      layers: Vec::new(),
+    }
+  }
+
+  /// Append one layer to the report.
+  pub fn push(
+    &mut self,
+    layer_name: &str,
+    requested_ref: Option<&str>,
+    resolved_sha: Option<&str>,
+    resolver: RefResolver,
+  ) {
+    self.layers.push(ResolvedLayer {
+      layer_name: layer_name.to_string(),
+      requested_ref: requested_ref.map(str::to_string),
+      resolved_sha: resolved_sha.map(str::to_string),
+      resolver,
+    });
+  }
+
+  /// Layers that ended up without a pinned commit.
+  pub fn unpinned(&self) -> impl Iterator<Item = &ResolvedLayer> {
+    self
+      .layers
+      .iter()
+      .filter(|layer| layer.resolved_sha.is_none())
+  }
+}
+
+impl fmt::Display for ResolvedLayers {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "CFS configuration '{}':", self.configuration_name)?;
+    for layer in &self.layers {
+      write!(
+        f,
+        "\n - {}: {} -> {} ({})",
+        layer.layer_name,
+        layer.requested_ref.as_deref().unwrap_or("-"),
+        layer.resolved_sha.as_deref().unwrap_or("-"),
+        layer.resolver
+      )?;
+    }
+    Ok(())
+  }
+}
+
+/// What [`super::utils::create_new_configuration`] does when a CFS
+/// configuration with the same name already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+  /// Fail with `Error::ConfigurationAlreadyExists`.
+  Fail,
+  /// Replace the existing configuration.
+  OverwriteAlways,
+  /// Replace the existing configuration only if its layers differ from
+  /// the new ones. With `backup`, the existing configuration is first
+  /// saved as `<name>-backup-<timestamp>`.
+  OverwriteIfChanged {
+    /// Save the existing configuration before replacing it.
+    backup: bool,
+  },
+}
+
+impl From<bool> for OverwritePolicy {
+  /// `true` is [`Self::OverwriteAlways`], `false` is [`Self::Fail`],
+  /// matching the historical `overwrite` flag.
+  fn from(overwrite: bool) -> Self {
+    if overwrite {
+      Self::OverwriteAlways
+    } else {
+      Self::Fail
+    }
+  }
+}
+
+/// What [`super::utils::create_new_configuration`] did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigurationAction {
+  /// No configuration had that name; it was created.
+  Created,
+  /// The existing configuration was replaced. `backup` names the copy
+  /// of the previous configuration, if one was made.
+  Overwritten {
+    /// Name of the backup of the previous configuration.
+    backup: Option<String>,
+  },
+  /// The existing configuration already had the requested layers; it
+  /// was left as is.
+  Unchanged,
+}
+
+impl fmt::Display for ConfigurationAction {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Created => f.write_str("created"),
+      Self::Overwritten { backup: None } => f.write_str("overwritten"),
+      Self::Overwritten {
+        backup: Some(backup),
+      } => write!(f, "overwritten, previous version saved as '{backup}'"),
+      Self::Unchanged => f.write_str("unchanged"),
+    }
+  }
+}
+
+/// One change [`super::utils::edit`] makes to the layers of a CFS
+/// configuration. Layers are referred to by name.
+#[derive(Debug, Clone)]
+pub enum LayerEdit {
+  /// Insert `layer` at `position`, or last if `position` is past the
+  /// end.
+  Insert {
+    /// Index the layer ends up at.
+    position: usize,
+    /// New layer; its name must not be taken.
+    layer: Layer,
+  },
+  /// Remove layer `name`.
+  Remove {
+    /// Layer name.
+    name: String,
+  },
+  /// Move layer `name` to `position`, or last if `position` is past the
+  /// end.
+  Move {
+    /// Layer name.
+    name: String,
+    /// Index the layer ends up at.
+    position: usize,
+  },
+  /// Pin layer `name` to `commit`, dropping its branch.
+  SetCommit {
+    /// Layer name.
+    name: String,
+    /// Commit SHA.
+    commit: String,
+  },
+}
+
+/// Name of the configuration [`super::utils::edit`] writes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum EditedName {
+  /// Replace the edited configuration.
+  #[default]
+  InPlace,
+  /// `<name>-v<N>`, `N` being one more than the highest version of
+  /// `<name>` in CFS. A `-v<N>` suffix of the edited configuration's
+  /// name is replaced, so `cos-config-v2` becomes `cos-config-v3`.
+  Versioned,
+  /// This name, which must not be taken.
+  Named(String),
+}
+
+/// Everything using a CFS configuration, see
+/// [`super::utils::usage`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigurationUsage {
+  /// CFS configuration name.
+  pub configuration_name: String,
+  /// Nodes with the configuration as desired configuration, sorted.
+  pub desired_by_nodes: Vec<String>,
+  /// CFS sessions that ran the configuration, sorted.
+  pub sessions: Vec<String>,
+  /// IMS images built by those sessions or booted by the BOS session
+  /// templates using the configuration, sorted.
+  pub images: Vec<String>,
+  /// BOS session templates using the configuration or booting one of
+  /// its images, sorted.
+  pub bos_sessiontemplates: Vec<String>,
+  /// Nodes currently booting one of `images`, sorted.
+  pub booting_nodes: Vec<String>,
+}
+
+impl ConfigurationUsage {
+  /// Whether nodes currently use the configuration, as desired
+  /// configuration or through a boot image. Deleting it, or its images,
+  /// would affect running nodes.
+  #[must_use]
+  pub fn is_in_use(&self) -> bool {
+    !self.desired_by_nodes.is_empty() || !self.booting_nodes.is_empty()
+  }
+
+  /// Whether nothing at all references the configuration.
+  #[must_use]
+  pub fn is_unused(&self) -> bool {
+    self.desired_by_nodes.is_empty()
+      && self.sessions.is_empty()
+      && self.images.is_empty()
+      && self.bos_sessiontemplates.is_empty()
+      && self.booting_nodes.is_empty()
+  }
+}
+
+impl fmt::Display for ConfigurationUsage {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "CFS configuration '{}':", self.configuration_name)?;
+    for (label, vec) in [
+      ("desired configuration of nodes", &self.desired_by_nodes),
+      ("CFS sessions", &self.sessions),
+      ("IMS images", &self.images),
+      ("BOS session templates", &self.bos_sessiontemplates),
+      ("nodes booting its images", &self.booting_nodes),
+    ] {
+      write!(f, "\n - {label} ({}): {}", vec.len(), vec.join(", "))?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn unpinned_only_returns_layers_without_sha() {
+    let mut report = ResolvedLayers::new("cos-config");
+    report.push("cos", Some("main"), Some("abc123"), RefResolver::GitBranch);
+    report.push("uan", None, None, RefResolver::Unresolved);
+
+    let unpinned: Vec<&str> =
+      report.unpinned().map(|l| l.layer_name.as_str()).collect();
+    assert_eq!(unpinned, vec!["uan"]);
+  }
+
+  #[test]
+  fn display_lists_every_layer() {
+    let mut report = ResolvedLayers::new("cos-config");
+    report.push("cos", Some("v1.0"), Some("abc123"), RefResolver::GitTag);
+
+    assert_eq!(
+      report.to_string(),
+      "CFS configuration 'cos-config':\n - cos: v1.0 -> abc123 (git tag)"
+    );
+  }
+}
@@ -2,36 +2,55 @@
 
 use crate::{
   bos::{self, template::http_client::v2::types::BosSessionTemplate},
+  bss::types::BootParameters,
   cfs::{
     self, component::http_client::v2::types::Component,
     configuration::http_client::v2::types::cfs_configuration_response::CfsConfigurationResponse,
-    session::http_client::v2::types::CfsSessionGetResponse,
+    session::{
+      http_client::v2::types::CfsSessionGetResponse,
+      utils::{SessionFilter, SessionVisibility},
+    },
   },
-  common::{self, gitea},
+  common::{self, csm_snapshot::CsmSnapshot, git_ssh, gitea},
   error::Error,
+  filter::{Filter, Filterable, Page, Query, between, name_glob},
   hsm,
   ims::image::http_client::types::Image,
 };
 
+use std::collections::{BTreeSet, HashMap};
+
 use chrono::NaiveDateTime;
-use globset::Glob;
+use futures::{StreamExt, TryStreamExt, stream};
 use serde_json::Value;
 
+use super::types::{
+  ConfigurationAction, ConfigurationUsage, EditedName, LayerEdit,
+  OverwritePolicy,
+};
+
 use super::http_client::{
-  v2::types::cfs_configuration_request::CfsConfigurationRequest,
+  v2::types::cfs_configuration_request::{
+    CfsConfigurationRequest, Layer as RequestLayer,
+  },
   v3::types::{
     cfs_configuration::LayerDetails, cfs_configuration_response::Layer,
   },
 };
 
-/// Create (or replace, when `overwrite=true`) a CFS v2 configuration
-/// by name.
+/// Create a CFS v2 configuration by name. If one with the same name
+/// already exists, `overwrite_policy` decides whether to fail, replace
+/// it, or replace it only if its layers differ (optionally saving it
+/// first as `<name>-backup-<timestamp>`).
+///
+/// Returns the configuration now in CFS and what was done.
 ///
 /// # Errors
 ///
-/// Returns an [`Error`] variant on CSM, transport, or
-/// deserialization failure; see the crate-level `Error` enum
-/// for the full set.
+/// Returns [`Error::ConfigurationAlreadyExists`] if the configuration
+/// exists and `overwrite_policy` is [`OverwritePolicy::Fail`], or an
+/// [`Error`] variant on CSM, transport, or deserialization failure;
+/// see the crate-level `Error` enum for the full set.
 pub async fn create_new_configuration(
   shasta_token: &str,
   shasta_base_url: &str,
@@ -39,29 +58,32 @@ pub async fn create_new_configuration(
   socks5_proxy: Option<&str>,
   configuration: &CfsConfigurationRequest,
   configuration_name: &str,
-  overwrite: bool,
-) -> Result<CfsConfigurationResponse, Error> {
+  overwrite_policy: OverwritePolicy,
+) -> Result<(CfsConfigurationResponse, ConfigurationAction), Error> {
   // Check if CFS configuration already exists
   log::debug!("Check CFS configuration '{configuration_name}' exists");
 
-  let shasta_client = crate::ShastaClient::new(
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
   )?;
-  let cfs_configuration_vec = shasta_client
+  let existing_opt = shasta_client
     .cfs_configuration_v2_get(shasta_token, Some(configuration_name))
     .await
     .map_err(|e| Error::Message(e.to_string()))
-    .unwrap_or_default();
+    .unwrap_or_default()
+    .into_iter()
+    .next();
 
-  // Check if CFS configuration already exists and throw an error is that is the case
-  if !cfs_configuration_vec.is_empty() {
-    if overwrite {
+  let action = match (existing_opt, overwrite_policy) {
+    (None, _) => {
       log::debug!(
-        "CFS configuration '{configuration_name}' already exists but 'overwrite' has been enabled"
+        "CFS configuration '{configuration_name}' does not exists, creating new CFS configuration"
       );
-    } else {
+      ConfigurationAction::Created
+    }
+    (Some(_), OverwritePolicy::Fail) => {
       log::warn!(
         "CFS configuration '{configuration_name}' already exists, cancel the process"
       );
@@ -69,11 +91,47 @@ pub async fn create_new_configuration(
         configuration_name.to_string(),
       ));
     }
-  }
+    (Some(_), OverwritePolicy::OverwriteAlways) => {
+      log::debug!(
+        "CFS configuration '{configuration_name}' already exists but 'overwrite' has been enabled"
+      );
+      ConfigurationAction::Overwritten { backup: None }
+    }
+    (Some(existing), OverwritePolicy::OverwriteIfChanged { .. })
+      if configuration.matches(&existing) =>
+    {
+      log::info!(
+        "CFS configuration '{configuration_name}' already up to date, leaving as is"
+      );
+      return Ok((existing, ConfigurationAction::Unchanged));
+    }
+    (Some(existing), OverwritePolicy::OverwriteIfChanged { backup }) => {
+      let backup_name_opt = if backup {
+        let backup_name = format!(
+          "{configuration_name}-backup-{}",
+          chrono::Utc::now().format("%Y%m%d%H%M%S")
+        );
+        log::info!(
+          "Saving CFS configuration '{configuration_name}' as '{backup_name}'"
+        );
+        shasta_client
+          .cfs_configuration_v2_put(
+            shasta_token,
+            &CfsConfigurationRequest::from(&existing),
+            &backup_name,
+          )
+          .await
+          .map_err(|e| Error::Message(e.to_string()))?;
+        Some(backup_name)
+      } else {
+        None
+      };
 
-  log::debug!(
-    "CFS configuration '{configuration_name}' does not exists, creating new CFS configuration"
-  );
+      ConfigurationAction::Overwritten {
+        backup: backup_name_opt,
+      }
+    }
+  };
 
   shasta_client
     .cfs_configuration_v2_put(
@@ -82,9 +140,172 @@ pub async fn create_new_configuration(
       configuration_name,
     )
     .await
+    .map(|cfs_configuration| (cfs_configuration, action))
     .map_err(|e| Error::Message(e.to_string()))
 }
 
+/// Apply `edit_vec`, in order, to the layers of `configuration`, named
+/// `configuration_name` in error messages.
+///
+/// # Errors
+///
+/// Returns [`Error::Message`] if an edit names a layer the
+/// configuration doesn't have, or inserts a layer whose name is taken.
+/// `configuration` is left partially edited.
+pub fn apply_layer_edits(
+  configuration_name: &str,
+  configuration: &mut CfsConfigurationRequest,
+  edit_vec: &[LayerEdit],
+) -> Result<(), Error> {
+  let layers = &mut configuration.layers;
+
+  for edit in edit_vec {
+    match edit {
+      LayerEdit::Insert { position, layer } => {
+        if layers.iter().any(|existing| existing.name == layer.name) {
+          return Err(Error::Message(format!(
+            "CFS configuration '{configuration_name}' already has a layer '{}'",
+            layer.name
+          )));
+        }
+        layers.insert((*position).min(layers.len()), layer.clone());
+      }
+      LayerEdit::Remove { name } => {
+        let index = layer_index(configuration_name, layers, name)?;
+        layers.remove(index);
+      }
+      LayerEdit::Move { name, position } => {
+        let index = layer_index(configuration_name, layers, name)?;
+        let layer = layers.remove(index);
+        layers.insert((*position).min(layers.len()), layer);
+      }
+      LayerEdit::SetCommit { name, commit } => {
+        let index = layer_index(configuration_name, layers, name)?;
+        layers[index].commit = Some(commit.clone());
+        layers[index].branch = None;
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Index of layer `name` in `layers` of CFS configuration
+/// `configuration_name`.
+fn layer_index(
+  configuration_name: &str,
+  layers: &[RequestLayer],
+  name: &str,
+) -> Result<usize, Error> {
+  layers
+    .iter()
+    .position(|layer| layer.name == name)
+    .ok_or_else(|| {
+      Error::Message(format!(
+        "CFS configuration '{configuration_name}' has no layer '{name}'"
+      ))
+    })
+}
+
+/// `<base>-v<N>`, `base` being `configuration_name` without its `-v<N>`
+/// suffix, if any, and `N` one more than the highest version of `base`
+/// in `existing_name_vec` (or `configuration_name` itself).
+#[must_use]
+pub fn next_version_name<'a>(
+  configuration_name: &str,
+  existing_name_vec: impl IntoIterator<Item = &'a str>,
+) -> String {
+  fn split_version(name: &str) -> Option<(&str, u32)> {
+    let (base, version) = name.rsplit_once("-v")?;
+    Some((base, version.parse().ok()?))
+  }
+
+  let (base, version_opt) = match split_version(configuration_name) {
+    Some((base, version)) => (base, Some(version)),
+    None => (configuration_name, None),
+  };
+
+  let highest_version = existing_name_vec
+    .into_iter()
+    .filter_map(split_version)
+    .filter(|(name_base, _)| *name_base == base)
+    .map(|(_, version)| version)
+    .chain(version_opt)
+    .max()
+    .unwrap_or_default();
+
+  format!("{base}-v{}", highest_version + 1)
+}
+
+/// Apply `edit_vec` to the layers of CFS configuration
+/// `configuration_name` and write the result under the name
+/// `edited_name` says. Layers keep the commit they currently use unless
+/// an edit changes it.
+///
+/// Returns the configuration written.
+///
+/// # Errors
+///
+/// Returns [`Error::ConfigurationAlreadyExists`] if
+/// [`EditedName::Named`] is taken, [`Error::Message`] if an edit
+/// doesn't apply (see [`apply_layer_edits`]), or another [`Error`]
+/// variant on CSM, transport, or deserialization failure.
+pub async fn edit(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  configuration_name: &str,
+  edit_vec: &[LayerEdit],
+  edited_name: EditedName,
+) -> Result<CfsConfigurationResponse, Error> {
+  let existing = client
+    .cfs_configuration_v2_get(shasta_token, Some(configuration_name))
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| {
+      Error::Message(format!(
+        "CFS configuration '{configuration_name}' not found"
+      ))
+    })?;
+
+  let mut configuration = CfsConfigurationRequest::from(&existing);
+  apply_layer_edits(configuration_name, &mut configuration, edit_vec)?;
+
+  let new_name = match edited_name {
+    EditedName::InPlace => configuration_name.to_string(),
+    EditedName::Versioned => {
+      let configuration_vec =
+        client.cfs_configuration_v2_get_all(shasta_token).await?;
+      next_version_name(
+        configuration_name,
+        configuration_vec
+          .iter()
+          .map(|configuration| configuration.name.as_str()),
+      )
+    }
+    EditedName::Named(new_name) => {
+      let taken = client
+        .cfs_configuration_v2_get_all(shasta_token)
+        .await?
+        .iter()
+        .any(|configuration| configuration.name == new_name);
+      if taken {
+        return Err(Error::ConfigurationAlreadyExists(new_name));
+      }
+      new_name
+    }
+  };
+
+  log::info!(
+    "Writing CFS configuration '{configuration_name}' with {} edit(s) as '{new_name}'",
+    edit_vec.len()
+  );
+
+  client
+    .cfs_configuration_v2_put(shasta_token, &configuration, &new_name)
+    .await
+}
+
 /// Filter the list of CFS configurations provided. This operation is very expensive since it is
 /// filtering by HSM group which means it needs to link CFS configurations with CFS sessions and
 /// BOS sessiontemplate. Aditionally, it will also fetch CFS components to find CFS sessions and
@@ -122,15 +343,16 @@ pub fn filter(
   );
 
   // Filter CFS sessions based on HSM groups
-  cfs::session::utils::filter(
-    cfs_session_vec,
-    configuration_name_pattern_opt,
-    hsm_group_name_vec,
-    xname_from_groups_vec,
-    None,
-    None,
-    keep_generic_sessions,
-  )?;
+  SessionFilter {
+    visibility: Some(SessionVisibility {
+      hsm_group_names: hsm_group_name_vec,
+      xnames: xname_from_groups_vec,
+      keep_generic: keep_generic_sessions,
+    }),
+    configuration_name_pattern: configuration_name_pattern_opt,
+    ..SessionFilter::default()
+  }
+  .apply(cfs_session_vec)?;
 
   // Get boot image id and desired configuration from BOS sessiontemplates
   let image_id_cfs_configuration_target_from_bos_sessiontemplate: Vec<(
@@ -182,60 +404,100 @@ pub fn filter(
         .contains(&cfs_configuration.name)
   });
 
-  // Filter CFS configurations based on user input (date range or configuration name).
-  // CFS configurations whose `last_updated` is missing or malformed can't be
-  // confirmed in-range, so they're filtered out of the date-range view.
+  filter_by_name_and_date(
+    cfs_configuration_vec,
+    configuration_name_pattern_opt,
+    since_opt,
+    until_opt,
+    limit_number_opt,
+  )
+}
+
+/// Keep the CFS configurations matching the user input (date range or
+/// configuration name), sort them by last updated date in ASC order and
+/// apply the limit. CFS configurations whose `last_updated` is missing
+/// or malformed can't be confirmed in-range, so they're filtered out of
+/// the date-range view.
+fn filter_by_name_and_date(
+  cfs_configuration_vec: &mut Vec<CfsConfigurationResponse>,
+  configuration_name_pattern_opt: Option<&str>,
+  since_opt: Option<NaiveDateTime>,
+  until_opt: Option<NaiveDateTime>,
+  limit_number_opt: Option<&u8>,
+) -> Result<Vec<CfsConfigurationResponse>, Error> {
+  let mut filter = Filter::All;
   if let (Some(since), Some(until)) = (since_opt, until_opt) {
-    cfs_configuration_vec.retain(|cfs_configuration| {
-      match chrono::DateTime::parse_from_rfc3339(
-        &cfs_configuration.last_updated,
-      ) {
-        Ok(date) => {
-          let date = date.naive_utc();
-          since <= date && date < until
-        }
-        Err(e) => {
-          log::warn!(
-            "Skipping CFS configuration with unparseable last_updated '{}': {}",
-            cfs_configuration.last_updated,
-            e
-          );
-          false
-        }
-      }
-    });
+    for cfs_configuration in
+      cfs_configuration_vec.iter().filter(|cfs_configuration| {
+        cfs_configuration.filter_timestamp().is_none()
+      })
+    {
+      log::warn!(
+        "Skipping CFS configuration with unparseable last_updated '{}'",
+        cfs_configuration.last_updated
+      );
+    }
+    filter = between(since, until);
   }
-
-  // Sort by last updated date in ASC order
-  cfs_configuration_vec.sort_by(|cfs_configuration_1, cfs_configuration_2| {
-    cfs_configuration_1
-      .last_updated
-      .cmp(&cfs_configuration_2.last_updated)
-  });
-
-  // Filter CFS configurations based on mattern matching
   if let Some(configuration_name_pattern) = configuration_name_pattern_opt {
-    let glob = Glob::new(configuration_name_pattern)?.compile_matcher();
-    cfs_configuration_vec.retain(|cfs_configuration| {
-      glob.is_match(cfs_configuration.name.clone())
-    });
+    filter = Filter::and(filter, name_glob(configuration_name_pattern));
   }
 
-  // Limiting the number of results to return to client
-  if let Some(limit_number) = limit_number_opt {
-    *cfs_configuration_vec = cfs_configuration_vec[cfs_configuration_vec
-      .len()
-      .saturating_sub(*limit_number as usize)..]
-      .to_vec();
+  Query {
+    filter,
+    limit: limit_number_opt.map(|limit_number| usize::from(*limit_number)),
+    ..Query::default()
   }
+  .apply(cfs_configuration_vec)?;
 
   Ok(cfs_configuration_vec.clone())
 }
 
+/// Which collections [`get_and_filter`] fetches besides the CFS
+/// configurations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FetchPlan {
+  /// CFS sessions, BOS session templates and the CFS components of the
+  /// HSM group members, needed to link configurations to HSM groups.
+  references: bool,
+}
+
+impl FetchPlan {
+  /// Admins not filtering by HSM group see every configuration, and a
+  /// configuration named after one of the HSM groups is kept without
+  /// looking further, so neither needs the references.
+  /// `requested_configuration_vec_opt` is the configuration fetched by
+  /// name, if one was requested.
+  fn new(
+    is_admin: bool,
+    hsm_group_name_vec: &[String],
+    requested_configuration_vec_opt: Option<&[CfsConfigurationResponse]>,
+  ) -> Self {
+    let unfiltered = is_admin && hsm_group_name_vec.is_empty();
+    let named_after_group =
+      requested_configuration_vec_opt.is_some_and(|configuration_vec| {
+        configuration_vec.iter().all(|configuration| {
+          hsm_group_name_vec
+            .iter()
+            .any(|hsm_group| configuration.name.contains(hsm_group))
+        })
+      });
+
+    Self {
+      references: !unfiltered && !named_after_group,
+    }
+  }
+}
+
 /// If filtering by HSM group, then configuration name must include HSM group name (It assumms each configuration
 /// is built for a specific cluster based on ansible vars used by the CFS session). The reason
 /// for this is because CSCS staff deletes all CFS sessions every now and then...
 ///
+/// Only what the result depends on is fetched: admins not filtering by
+/// HSM group get every configuration without the CFS sessions, BOS
+/// session templates and CFS components linking them to groups, and
+/// neither does a single configuration named after one of the groups.
+///
 /// # Errors
 ///
 /// Returns an [`Error`] variant on CSM, transport, or
@@ -254,8 +516,49 @@ pub async fn get_and_filter(
   until_opt: Option<NaiveDateTime>,
   limit_number_opt: Option<&u8>,
 ) -> Result<Vec<CfsConfigurationResponse>, Error> {
-  // Get list of configurations.
-  // Returns list with only one element if "configuration name" provided
+  let shasta_client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+  let keep_generic_sessions = common::jwt_ops::is_user_admin(shasta_token);
+
+  // A single configuration is cheap to fetch first and may spare the
+  // rest
+  let requested_configuration_vec_opt = match configuration_name {
+    Some(_) => Some(
+      shasta_client
+        .cfs_configuration_v2_get(shasta_token, configuration_name)
+        .await?,
+    ),
+    None => None,
+  };
+
+  let fetch_plan = FetchPlan::new(
+    keep_generic_sessions,
+    hsm_group_name_vec,
+    requested_configuration_vec_opt.as_deref(),
+  );
+  log::debug!("CFS configuration fetch plan: {fetch_plan:?}");
+
+  if !fetch_plan.references {
+    let mut cfs_configuration_vec = match requested_configuration_vec_opt {
+      Some(cfs_configuration_vec) => cfs_configuration_vec,
+      None => {
+        shasta_client
+          .cfs_configuration_v2_get(shasta_token, None)
+          .await?
+      }
+    };
+
+    return filter_by_name_and_date(
+      &mut cfs_configuration_vec,
+      configuration_name_pattern,
+      since_opt,
+      until_opt,
+      limit_number_opt,
+    );
+  }
 
   // COLLECT SITE WIDE DATA FOR VALIDATION
   //
@@ -269,26 +572,29 @@ pub async fn get_and_filter(
     )
     .await?;
 
-  let shasta_client = crate::ShastaClient::new(
-    shasta_base_url,
-    shasta_root_cert.to_vec(),
-    socks5_proxy.map(str::to_owned),
-  )?;
+  let fetch_configuration_vec = async {
+    match requested_configuration_vec_opt {
+      Some(cfs_configuration_vec) => Ok(cfs_configuration_vec),
+      None => {
+        shasta_client
+          .cfs_configuration_v2_get(shasta_token, None)
+          .await
+      }
+    }
+  };
   let (
     mut cfs_configuration_vec,
     mut cfs_session_vec,
     mut bos_sessiontemplate_vec,
     cfs_component_vec,
   ) = tokio::try_join!(
-    shasta_client.cfs_configuration_v2_get(shasta_token, configuration_name),
+    fetch_configuration_vec,
     shasta_client.cfs_session_v2_get_all(shasta_token),
     shasta_client.bos_template_v2_get_all(shasta_token),
     shasta_client
       .cfs_component_v2_get_parallel(shasta_token, &xname_from_groups_vec),
   )?;
 
-  let keep_generic_sessions = common::jwt_ops::is_user_admin(shasta_token);
-
   // Filter CFS configurations if user is not admin
   cfs::configuration::utils::filter(
     &mut cfs_configuration_vec,
@@ -307,6 +613,43 @@ pub async fn get_and_filter(
   Ok(cfs_configuration_vec)
 }
 
+/// Page through the CFS configurations visible from
+/// `hsm_group_name_vec`, as [`get_and_filter`] selects them, keeping the
+/// ones matching `query` and returning the `page_size` following
+/// [`Query::after`]. Feed [`Page::next_cursor`] back through
+/// [`Query::after`] to get the next page.
+///
+/// # Errors
+///
+/// Returns [`Error::GlobError`] if a glob pattern in `query` is invalid,
+/// or an [`Error`] variant on CSM, transport, or deserialization
+/// failure; see the crate-level `Error` enum for the full set.
+pub async fn get_page(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  hsm_group_name_vec: &[String],
+  query: &Query,
+  page_size: usize,
+) -> Result<Page<CfsConfigurationResponse>, Error> {
+  let cfs_configuration_vec = get_and_filter(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    None,
+    None,
+    hsm_group_name_vec,
+    None,
+    None,
+    None,
+  )
+  .await?;
+
+  query.page(cfs_configuration_vec, page_size)
+}
+
 /// Collect everything that references a CFS configuration: the CFS
 /// sessions that ran against it, the IMS images it produced, and the
 /// BOS session templates that consume those images.
@@ -330,25 +673,80 @@ pub async fn get_derivatives(
   ),
   Error,
 > {
-  // List of image ids from CFS sessions and BOS sessiontemplates related to CFS configuration
-  let mut image_id_vec: Vec<&str> = Vec::new();
-
-  let shasta_client = crate::ShastaClient::new(
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
   )?;
-  let (mut cfs_session_vec, mut bos_sessiontemplate_vec, mut ims_image_vec) = tokio::try_join!(
-    shasta_client.cfs_session_v2_get_all(shasta_token),
-    shasta_client.bos_template_v2_get_all(shasta_token),
-    shasta_client.ims_image_get_all(shasta_token),
+
+  get_derivatives_from_snapshot(
+    &CsmSnapshot::new(&shasta_client, shasta_token),
+    configuration_name,
+  )
+  .await
+}
+
+/// [`get_derivatives`] on the collections of `snapshot`, fetching those
+/// it doesn't hold yet.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn get_derivatives_from_snapshot(
+  snapshot: &CsmSnapshot,
+  configuration_name: &str,
+) -> Result<
+  (
+    Option<Vec<CfsSessionGetResponse>>,
+    Option<Vec<BosSessionTemplate>>,
+    Option<Vec<Image>>,
+  ),
+  Error,
+> {
+  let (cfs_session_vec, bos_sessiontemplate_vec, ims_image_vec) = tokio::try_join!(
+    snapshot.cfs_sessions(),
+    snapshot.bos_sessiontemplates(),
+    snapshot.ims_images(),
   )?;
 
+  let (cfs_session_vec, bos_sessiontemplate_vec, ims_image_vec) =
+    filter_derivatives(
+      configuration_name,
+      cfs_session_vec.to_vec(),
+      bos_sessiontemplate_vec.to_vec(),
+      ims_image_vec.to_vec(),
+    );
+
+  Ok((
+    Some(cfs_session_vec),
+    Some(bos_sessiontemplate_vec),
+    Some(ims_image_vec),
+  ))
+}
+
+/// Pure part of [`get_derivatives`]: keep the CFS sessions, BOS session
+/// templates and IMS images related to CFS configuration
+/// `configuration_name`.
+#[must_use]
+pub fn filter_derivatives(
+  configuration_name: &str,
+  mut cfs_session_vec: Vec<CfsSessionGetResponse>,
+  mut bos_sessiontemplate_vec: Vec<BosSessionTemplate>,
+  mut ims_image_vec: Vec<Image>,
+) -> (
+  Vec<CfsSessionGetResponse>,
+  Vec<BosSessionTemplate>,
+  Vec<Image>,
+) {
+  // List of image ids from CFS sessions and BOS sessiontemplates related to CFS configuration
+  let mut image_id_vec: Vec<&str> = Vec::new();
+
   // Filter CFS sessions
-  cfs::session::utils::filter_by_cofiguration(
-    &mut cfs_session_vec,
-    configuration_name,
-  );
+  let session_filter =
+    SessionFilter::default().configuration(configuration_name);
+  cfs_session_vec.retain(|cfs_session| session_filter.matches(cfs_session));
 
   // Filter BOS sessiontemplate
   bos_sessiontemplate_vec.retain(|bos_sessiontemplate| {
@@ -378,13 +776,164 @@ pub async fn get_derivatives(
     image_id_vec.contains(&image.id.as_deref().unwrap_or_default())
   });
 
-  Ok((
-    Some(cfs_session_vec),
-    Some(bos_sessiontemplate_vec),
-    Some(ims_image_vec),
+  (cfs_session_vec, bos_sessiontemplate_vec, ims_image_vec)
+}
+
+/// What uses CFS configuration `configuration_name`: nodes with it as
+/// desired configuration, CFS sessions that ran it, images they built,
+/// BOS session templates using it or its images, and nodes booting
+/// those images. Read-only; the same checks guard
+/// [`crate::cfs::cleanup::get_data_to_delete`].
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn usage(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  configuration_name: &str,
+) -> Result<ConfigurationUsage, Error> {
+  usage_from_snapshot(
+    &CsmSnapshot::new(client, shasta_token),
+    configuration_name,
+  )
+  .await
+}
+
+/// [`usage`] on the collections of `snapshot`, fetching those it
+/// doesn't hold yet.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn usage_from_snapshot(
+  snapshot: &CsmSnapshot,
+  configuration_name: &str,
+) -> Result<ConfigurationUsage, Error> {
+  let (
+    cfs_component_vec,
+    cfs_session_vec,
+    bos_sessiontemplate_vec,
+    bss_bootparameters_vec,
+  ) = tokio::try_join!(
+    snapshot.cfs_components(),
+    snapshot.cfs_sessions(),
+    snapshot.bos_sessiontemplates(),
+    snapshot.bss_bootparameters(),
+  )?;
+
+  Ok(usage_from(
+    configuration_name,
+    cfs_component_vec,
+    cfs_session_vec,
+    bos_sessiontemplate_vec,
+    bss_bootparameters_vec,
   ))
 }
 
+/// Pure part of [`usage`], split out for testing.
+#[must_use]
+pub fn usage_from(
+  configuration_name: &str,
+  cfs_component_vec: &[Component],
+  cfs_session_vec: &[CfsSessionGetResponse],
+  bos_sessiontemplate_vec: &[BosSessionTemplate],
+  bss_bootparameters_vec: &[BootParameters],
+) -> ConfigurationUsage {
+  let cfs_session_vec: Vec<&CfsSessionGetResponse> = cfs_session_vec
+    .iter()
+    .filter(|cfs_session| {
+      cfs_session.configuration_name() == Some(configuration_name)
+    })
+    .collect();
+
+  let mut image_id_vec: Vec<String> = cfs_session_vec
+    .iter()
+    .flat_map(|cfs_session| cfs_session.results_id())
+    .map(str::to_string)
+    .collect();
+
+  let bos_sessiontemplate_vec: Vec<&BosSessionTemplate> =
+    bos_sessiontemplate_vec
+      .iter()
+      .filter(|bos_sessiontemplate| {
+        bos_sessiontemplate.get_configuration() == Some(configuration_name)
+          || bos_sessiontemplate
+            .images_id()
+            .any(|image_id| image_id_vec.iter().any(|id| id == image_id))
+      })
+      .collect();
+
+  image_id_vec.extend(
+    bos_sessiontemplate_vec
+      .iter()
+      .flat_map(|bos_sessiontemplate| bos_sessiontemplate.images_id())
+      .map(str::to_string),
+  );
+  image_id_vec.sort();
+  image_id_vec.dedup();
+
+  let mut session_name_vec: Vec<String> = cfs_session_vec
+    .iter()
+    .map(|cfs_session| cfs_session.name.clone())
+    .collect();
+  session_name_vec.sort();
+
+  let mut bos_sessiontemplate_name_vec: Vec<String> = bos_sessiontemplate_vec
+    .iter()
+    .filter_map(|bos_sessiontemplate| bos_sessiontemplate.name.clone())
+    .collect();
+  bos_sessiontemplate_name_vec.sort();
+
+  let mut booting_node_vec: Vec<String> = image_id_vec
+    .iter()
+    .flat_map(|image_id| {
+      cfs::cleanup::get_node_vec_booting_image(image_id, bss_bootparameters_vec)
+    })
+    .collect();
+  booting_node_vec.sort();
+  booting_node_vec.dedup();
+
+  ConfigurationUsage {
+    configuration_name: configuration_name.to_string(),
+    desired_by_nodes: nodes_with_desired_configuration(
+      configuration_name,
+      cfs_component_vec,
+    ),
+    sessions: session_name_vec,
+    images: image_id_vec,
+    bos_sessiontemplates: bos_sessiontemplate_name_vec,
+    booting_nodes: booting_node_vec,
+  }
+}
+
+/// Nodes with CFS configuration `configuration_name` as desired
+/// configuration, sorted.
+#[must_use]
+pub fn nodes_with_desired_configuration(
+  configuration_name: &str,
+  cfs_component_vec: &[Component],
+) -> Vec<String> {
+  let mut node_vec: Vec<String> = cfs_component_vec
+    .iter()
+    .filter(|cfs_component| {
+      cfs_component.desired_config.as_deref() == Some(configuration_name)
+    })
+    .filter_map(|cfs_component| cfs_component.id.clone())
+    .collect();
+
+  node_vec.sort_unstable();
+
+  node_vec
+}
+
+/// Layers [`get_configurations_layer_details`] resolves at once.
+pub const LAYER_DETAILS_CONCURRENCY: usize = 10;
+
 /// Resolve a CFS configuration layer to its detailed view by calling
 /// Gitea for the layer's repo metadata (commit message, author, etc.).
 ///
@@ -401,32 +950,139 @@ pub async fn get_configuration_layer_details(
   site_name: &str,
   socks5_proxy: Option<&str>,
 ) -> Result<LayerDetails, Error> {
-  let commit_id: String =
-    layer.commit.clone().unwrap_or("Not defined".to_string());
-  let mut branch_name_vec: Vec<String> = Vec::new();
-  let mut tag_name_vec: Vec<String> = Vec::new();
-  let commit_sha;
-
-  let repo_ref_vec_rslt = gitea::http_client::get_all_refs_from_repo_url(
+  let repo_ref_vec = get_repo_refs(
+    shasta_root_cert,
     gitea_base_url,
     gitea_token,
     &layer.clone_url,
-    shasta_root_cert,
     socks5_proxy,
   )
   .await;
 
-  let repo_ref_vec = match repo_ref_vec_rslt {
-    Ok(value) => value,
-    Err(error) => {
-      log::warn!(
-        "Could not fetch repo '{}' refs. Reason:\n{:#?}",
-        layer.clone_url,
-        error
-      );
-      vec![]
-    }
-  };
+  layer_details_from_refs(
+    shasta_root_cert,
+    gitea_base_url,
+    gitea_token,
+    layer,
+    &repo_ref_vec,
+    site_name,
+    socks5_proxy,
+  )
+  .await
+}
+
+/// [`get_configuration_layer_details`] of every layer of one or many
+/// configurations, `LAYER_DETAILS_CONCURRENCY` layers at a time. The
+/// refs of each repo are listed once, however many layers use it.
+///
+/// `configuration_layer_vec` holds the layers of each configuration;
+/// the details come back in the same shape and order.
+///
+/// # Errors
+///
+/// Same as [`get_configuration_layer_details`]; the first failing
+/// layer fails the whole call.
+pub async fn get_configurations_layer_details(
+  shasta_root_cert: &[u8],
+  gitea_base_url: &str,
+  gitea_token: &str,
+  configuration_layer_vec: Vec<Vec<Layer>>,
+  site_name: &str,
+  socks5_proxy: Option<&str>,
+) -> Result<Vec<Vec<LayerDetails>>, Error> {
+  let clone_url_set: BTreeSet<String> = configuration_layer_vec
+    .iter()
+    .flatten()
+    .map(|layer| layer.clone_url.clone())
+    .collect();
+
+  let repo_ref_map: HashMap<String, Vec<Value>> = stream::iter(clone_url_set)
+    .map(|clone_url| async move {
+      let repo_ref_vec = get_repo_refs(
+        shasta_root_cert,
+        gitea_base_url,
+        gitea_token,
+        &clone_url,
+        socks5_proxy,
+      )
+      .await;
+      (clone_url, repo_ref_vec)
+    })
+    .buffer_unordered(LAYER_DETAILS_CONCURRENCY)
+    .collect()
+    .await;
+
+  let layer_count_vec: Vec<usize> =
+    configuration_layer_vec.iter().map(Vec::len).collect();
+
+  let layer_details_vec: Vec<LayerDetails> =
+    stream::iter(configuration_layer_vec.into_iter().flatten())
+      .map(|layer| {
+        let repo_ref_vec = repo_ref_map
+          .get(&layer.clone_url)
+          .map_or(&[][..], Vec::as_slice);
+        layer_details_from_refs(
+          shasta_root_cert,
+          gitea_base_url,
+          gitea_token,
+          layer,
+          repo_ref_vec,
+          site_name,
+          socks5_proxy,
+        )
+      })
+      .buffered(LAYER_DETAILS_CONCURRENCY)
+      .try_collect()
+      .await?;
+
+  let mut layer_details_iter = layer_details_vec.into_iter();
+  Ok(
+    layer_count_vec
+      .into_iter()
+      .map(|layer_count| {
+        layer_details_iter.by_ref().take(layer_count).collect()
+      })
+      .collect(),
+  )
+}
+
+/// Refs of the repo at `clone_url`, none if Gitea can't list them.
+async fn get_repo_refs(
+  shasta_root_cert: &[u8],
+  gitea_base_url: &str,
+  gitea_token: &str,
+  clone_url: &str,
+  socks5_proxy: Option<&str>,
+) -> Vec<Value> {
+  gitea::http_client::get_all_refs_from_repo_url(
+    gitea_base_url,
+    gitea_token,
+    clone_url,
+    shasta_root_cert,
+    socks5_proxy,
+  )
+  .await
+  .unwrap_or_else(|error| {
+    log::warn!("Could not fetch repo '{clone_url}' refs. Reason:\n{error:#?}");
+    vec![]
+  })
+}
+
+/// [`get_configuration_layer_details`] with the refs of the layer's repo
+/// already listed in `repo_ref_vec`.
+async fn layer_details_from_refs(
+  shasta_root_cert: &[u8],
+  gitea_base_url: &str,
+  gitea_token: &str,
+  layer: Layer,
+  repo_ref_vec: &[Value],
+  site_name: &str,
+  socks5_proxy: Option<&str>,
+) -> Result<LayerDetails, Error> {
+  let commit_id: String =
+    layer.commit.clone().unwrap_or("Not defined".to_string());
+  let mut branch_name_vec: Vec<String> = Vec::new();
+  let mut tag_name_vec: Vec<String> = Vec::new();
 
   let mut ref_value_vec: Vec<&Value> = repo_ref_vec
     .iter()
@@ -470,34 +1126,27 @@ pub async fn get_configuration_layer_details(
         Error::GitRepoShape("tag name".to_string())
       })?;
 
-      let git_repo_tag_url = ref_value
-        .get("url")
-        .and_then(Value::as_str)
-        .ok_or_else(|| {
-          Error::GitRepoShape("tag url".to_string())
-        })?;
+      let tag_ref = format!("refs/tags/{tag_name}");
+      let resolved_ref = if git_ssh::is_ssh_url(&layer.clone_url) {
+        git_ssh::resolve_ref(&layer.clone_url, &tag_ref).await?
+      } else {
+        let repo_name =
+          gitea::http_client::get_repo_name_from_url(&layer.clone_url)?;
 
-      let commit_sha_value = gitea::http_client::get_commit_from_tag(
-        git_repo_tag_url,
-        tag_name,
-        gitea_token,
-        shasta_root_cert,
-        socks5_proxy,
-        site_name,
-      )
-      .await?;
-
-      commit_sha = commit_sha_value
-        .pointer("/commit/sha")
-        .and_then(Value::as_str)
-        .ok_or_else(|| {
-          Error::GitRepoShape(
-            "commit sha from git repo tag".to_string(),
-          )
-        })?;
+        gitea::http_client::resolve_ref_from_refs(
+          gitea_base_url,
+          gitea_token,
+          &repo_name,
+          repo_ref_vec,
+          &tag_ref,
+          shasta_root_cert,
+          socks5_proxy,
+        )
+        .await?
+      };
 
       let annotated_tag_commit_sha =
-        [commit_id.clone(), commit_sha.to_string()];
+        [commit_id.clone(), resolved_ref.target_sha];
 
       ref_value_vec = repo_ref_vec
         .iter()
@@ -552,17 +1201,19 @@ pub async fn get_configuration_layer_details(
     branch_name_vec.push(cfs_config_layer_branch.clone());
   }
 
-  let commit_id_opt = layer.commit.as_ref();
+  // Commit details need the Gitea API, not reachable for SSH repos
+  let commit_id_opt = layer
+    .commit
+    .as_ref()
+    .filter(|_| !git_ssh::is_ssh_url(&layer.clone_url));
 
   let gitea_commit_details: serde_json::Value =
     if let Some(commit_id) = commit_id_opt {
-      let repo_name = layer
-        .clone_url
-        .trim_start_matches("https://api-gw-service-nmn.local/vcs/")
-        .trim_end_matches(".git");
+      let repo_name =
+        gitea::http_client::get_repo_name_from_url(&layer.clone_url)?;
 
       gitea::http_client::get_commit_details_from_external_url(
-        repo_name,
+        &repo_name,
         commit_id,
         gitea_token,
         shasta_root_cert,
@@ -596,3 +1247,179 @@ pub async fn get_configuration_layer_details(
     &layer.playbook,
   ))
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn usage_from_follows_sessions_images_and_templates() {
+    let cfs_component_vec: Vec<Component> =
+      serde_json::from_value(serde_json::json!([
+        { "id": "x1000c0s0b0n0", "desiredConfig": "cos-config" },
+        { "id": "x1000c0s0b0n1", "desiredConfig": "other-config" }
+      ]))
+      .unwrap();
+    let cfs_session_vec: Vec<CfsSessionGetResponse> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "name": "cos-image-build",
+          "configuration": { "name": "cos-config" },
+          "status": { "artifacts": [{ "result_id": "img-1" }] }
+        },
+        { "name": "other-session", "configuration": { "name": "other-config" } }
+      ]))
+      .unwrap();
+    let bos_sessiontemplate_vec: Vec<BosSessionTemplate> =
+      serde_json::from_value(serde_json::json!([
+        {
+          "name": "cos-template",
+          "boot_sets": {
+            "compute": { "path": "s3://boot-images/img-1/manifest.json" }
+          }
+        },
+        { "name": "other-template", "cfs": { "configuration": "other-config" } }
+      ]))
+      .unwrap();
+    let bss_bootparameters_vec: Vec<BootParameters> =
+      serde_json::from_value(serde_json::json!([{
+        "hosts": ["x1000c0s0b0n2"],
+        "params": "root=craycps-s3:s3://boot-images/img-1/rootfs:etag:dvs"
+      }]))
+      .unwrap();
+
+    let usage = usage_from(
+      "cos-config",
+      &cfs_component_vec,
+      &cfs_session_vec,
+      &bos_sessiontemplate_vec,
+      &bss_bootparameters_vec,
+    );
+
+    assert_eq!(usage.desired_by_nodes, vec!["x1000c0s0b0n0"]);
+    assert_eq!(usage.sessions, vec!["cos-image-build"]);
+    assert_eq!(usage.images, vec!["img-1"]);
+    assert_eq!(usage.bos_sessiontemplates, vec!["cos-template"]);
+    assert_eq!(usage.booting_nodes, vec!["x1000c0s0b0n2"]);
+    assert!(usage.is_in_use());
+  }
+
+  #[test]
+  fn fetch_plan_skips_references_when_not_needed() {
+    let zinal_vec = vec!["zinal".to_string()];
+    let configuration_vec: Vec<CfsConfigurationResponse> =
+      serde_json::from_value(serde_json::json!([
+        { "name": "zinal-cos-config", "lastUpdated": "", "layers": [] }
+      ]))
+      .unwrap();
+
+    assert!(!FetchPlan::new(true, &[], None).references);
+    assert!(FetchPlan::new(false, &[], None).references);
+    assert!(FetchPlan::new(true, &zinal_vec, None).references);
+    assert!(
+      !FetchPlan::new(false, &zinal_vec, Some(&configuration_vec)).references
+    );
+    assert!(
+      FetchPlan::new(false, &["eiger".to_string()], Some(&configuration_vec))
+        .references
+    );
+  }
+
+  fn request_layer(name: &str, branch: &str) -> RequestLayer {
+    RequestLayer::new(
+      format!("https://vcs/cray/{name}.git"),
+      None,
+      name.to_string(),
+      "site.yml".to_string(),
+      Some(branch.to_string()),
+      None,
+      None,
+    )
+  }
+
+  #[test]
+  fn apply_layer_edits_in_order() {
+    let mut configuration = CfsConfigurationRequest::new();
+    configuration.add_layer(request_layer("cos", "main"));
+    configuration.add_layer(request_layer("uan", "main"));
+    configuration.add_layer(request_layer("site", "main"));
+
+    apply_layer_edits(
+      "cos-config",
+      &mut configuration,
+      &[
+        LayerEdit::Remove {
+          name: "uan".to_string(),
+        },
+        LayerEdit::Insert {
+          position: 0,
+          layer: request_layer("csm", "main"),
+        },
+        LayerEdit::Move {
+          name: "site".to_string(),
+          position: 1,
+        },
+        LayerEdit::SetCommit {
+          name: "cos".to_string(),
+          commit: "abc123".to_string(),
+        },
+      ],
+    )
+    .unwrap();
+
+    let layers: Vec<(&str, Option<&str>, Option<&str>)> = configuration
+      .layers
+      .iter()
+      .map(|layer| {
+        (
+          layer.name.as_str(),
+          layer.commit.as_deref(),
+          layer.branch.as_deref(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      layers,
+      vec![
+        ("csm", None, Some("main")),
+        ("site", None, Some("main")),
+        ("cos", Some("abc123"), None),
+      ]
+    );
+
+    assert!(matches!(
+      apply_layer_edits(
+        "cos-config",
+        &mut configuration,
+        &[LayerEdit::Remove {
+          name: "uan".to_string()
+        }],
+      ),
+      Err(Error::Message(_))
+    ));
+    assert!(matches!(
+      apply_layer_edits(
+        "cos-config",
+        &mut configuration,
+        &[LayerEdit::Insert {
+          position: 9,
+          layer: request_layer("cos", "main"),
+        }],
+      ),
+      Err(Error::Message(_))
+    ));
+  }
+
+  #[test]
+  fn next_version_name_bumps_highest_version() {
+    let existing = ["cos-config", "cos-config-v1", "cos-config-v3", "uan-v7"];
+
+    assert_eq!(next_version_name("cos-config", existing), "cos-config-v4");
+    assert_eq!(
+      next_version_name("cos-config-v1", existing),
+      "cos-config-v4"
+    );
+    assert_eq!(next_version_name("uan", []), "uan-v1");
+    assert_eq!(next_version_name("site-v2", []), "site-v3");
+  }
+}
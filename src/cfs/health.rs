@@ -2,6 +2,7 @@
 
 use std::time::Duration;
 
+use crate::common::{http, metrics::MeteredSend};
 use crate::error::Error;
 
 /// Verify connectivity to the CSM CFS service by issuing `GET /cfs/healthz`
@@ -16,13 +17,9 @@ use crate::error::Error;
 pub async fn test_connectivity_to_backend(
   shasta_base_url: &str,
 ) -> Result<(), Error> {
-  let client;
-
-  let client_builder =
-    reqwest::Client::builder().connect_timeout(Duration::new(3, 0));
-
-  // Build client
-  client = client_builder.build().unwrap();
+  let client = http::client_config(&[], None)
+    .apply(reqwest::Client::builder().connect_timeout(Duration::new(3, 0)))?
+    .build()?;
 
   let api_url = shasta_base_url.to_owned() + "/cfs/healthz";
 
@@ -30,7 +27,7 @@ pub async fn test_connectivity_to_backend(
 
   client
     .get(api_url)
-    .send()
+    .send_metered()
     .await?
     .error_for_status()
     .map(|_| ())
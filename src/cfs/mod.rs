@@ -10,13 +10,18 @@
 //! - [`configuration`] — CFS configurations (v2 and v3 endpoints).
 //! - [`component`] — per-node component records (v2 and v3 endpoints).
 //! - [`session`] — CFS sessions (v2 and v3 endpoints).
+//! - [`source`] — CFS sources, git repos with CFS-stored credentials
+//!   (v3 only).
 //! - [`common`] — shared helpers used across the CFS resources.
+//! - [`compat`] — version-less configuration, session and component
+//!   calls preferring v3 and falling back to v2.
 //! - [`cleanup`] — cascade-delete a CFS configuration along with the
 //!   IMS images, CFS sessions, and BOS templates derived from it.
 //! - [`health`] — liveness/readiness checks for the CFS service itself.
 //!
 //! The v3 endpoints are preferred on CSM releases that expose them; the
-//! v2 endpoints are kept for sites still on older CSM.
+//! v2 endpoints are kept for sites still on older CSM. Code that must
+//! run on both uses the [`compat`] methods.
 //!
 //! ## How this module is built
 //!
@@ -52,11 +57,13 @@
 pub mod cleanup;
 pub mod cleanup_session;
 pub mod common;
+pub mod compat;
 pub mod component;
 pub mod configuration;
 pub(crate) mod generated;
 pub mod health;
 pub mod session;
+pub mod source;
 mod wrapper;
 /// Integration-style tests for the CFS namespace.
 #[cfg(test)]
@@ -97,4 +104,7 @@ pub mod v3 {
     CfsSessionGetResponse, CfsSessionPostRequest, Configuration, Session,
     Status, Target,
   };
+  pub use super::source::types::{
+    CfsSource, CfsSourceCreateRequest, CfsSourceUpdateRequest,
+  };
 }
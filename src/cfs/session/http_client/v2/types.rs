@@ -6,6 +6,8 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use crate::cfs::session::http_client::v3::types as v3;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CfsSessionGetResponse {
   pub name: String,
@@ -306,3 +308,92 @@ impl CfsSessionPostRequest {
       .collect()
   }
 }
+
+/// Downgrade a v3 session to the v2 shape. v2 has no `logs`,
+/// `debug_on_failure`, image map or IMS job, so they are dropped.
+impl From<v3::CfsSessionGetResponse> for CfsSessionGetResponse {
+  fn from(session: v3::CfsSessionGetResponse) -> Self {
+    Self {
+      name: session.name,
+      configuration: session.configuration.map(|configuration| Configuration {
+        name: configuration.name,
+        limit: configuration.limit,
+      }),
+      ansible: session.ansible.map(|ansible| Ansible {
+        config: ansible.config,
+        limit: ansible.limit,
+        verbosity: ansible.verbosity,
+        passthrough: ansible.passthrough,
+      }),
+      target: session.target.map(Target::from),
+      status: session.status.map(|status| Status {
+        artifacts: status.artifacts.map(|artifact_vec| {
+          artifact_vec
+            .into_iter()
+            .map(|artifact| Artifact {
+              image_id: artifact.image_id,
+              result_id: artifact.result_id,
+              r#type: artifact.r#type,
+            })
+            .collect()
+        }),
+        session: status.session.map(|session| Session {
+          job: session.job,
+          completion_time: session.completion_time,
+          start_time: session.start_time,
+          status: session.status,
+          succeeded: session.succeeded,
+        }),
+      }),
+      tags: session.tags,
+    }
+  }
+}
+
+impl From<v3::Target> for Target {
+  fn from(target: v3::Target) -> Self {
+    Self {
+      definition: target.definition,
+      groups: target.groups.map(|group_vec| {
+        group_vec
+          .into_iter()
+          .map(|group| Group {
+            name: group.name,
+            members: group.members,
+          })
+          .collect()
+      }),
+    }
+  }
+}
+
+/// Upgrade a v2 session request to the v3 shape, without image map and
+/// with `debug_on_failure` off.
+impl From<&CfsSessionPostRequest> for v3::CfsSessionPostRequest {
+  fn from(session: &CfsSessionPostRequest) -> Self {
+    Self {
+      name: session.name.clone(),
+      configuration_name: session.configuration_name.clone(),
+      configuration_limit: session.configuration_limit.clone(),
+      ansible_limit: session.ansible_limit.clone(),
+      ansible_config: session.ansible_config.clone(),
+      ansible_verbosity: session.ansible_verbosity,
+      ansible_passthrough: session.ansible_passthrough.clone(),
+      target: v3::Target {
+        definition: session.target.definition.clone(),
+        groups: session.target.groups.as_ref().map(|group_vec| {
+          group_vec
+            .iter()
+            .map(|group| v3::Group {
+              name: group.name.clone(),
+              members: group.members.clone(),
+            })
+            .collect()
+        }),
+        image_map: None,
+      },
+      tags: session.tags.clone(),
+      debug_on_failure: false,
+    }
+  }
+}
@@ -15,7 +15,8 @@ use crate::error::Error;
 
 #[cfg(feature = "k8s-console")]
 use crate::common::{
-  kubernetes::{self, i_print_cfs_session_logs},
+  kubernetes::{self, PodWaitTimeouts, i_print_cfs_session_logs},
+  poll::WaitOptions,
   vault::http_client::fetch_shasta_k8s_secrets_from_vault,
 };
 
@@ -33,7 +34,7 @@ pub async fn get_one(
   socks5_proxy: Option<&str>,
   session_name: &String,
 ) -> Result<CfsSessionGetResponse, Error> {
-  let cfs_session_vec = crate::ShastaClient::new(
+  let cfs_session_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -70,7 +71,7 @@ pub async fn get_and_sort(
   session_name_opt: Option<&String>,
   is_succeded_opt: Option<bool>,
 ) -> Result<Vec<CfsSessionGetResponse>, Error> {
-  let mut cfs_session_vec = crate::ShastaClient::new(
+  let mut cfs_session_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -109,7 +110,7 @@ pub async fn post(
   log::info!("Create CFS session '{}'", session.name);
   log::debug!("Create CFS session request payload:\n{session:#?}");
 
-  crate::ShastaClient::new(
+  crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -118,8 +119,8 @@ pub async fn post(
   .await
 }
 
-/// Creates a CFS session and waits for it to finish. When `watch_logs`
-/// is true the session's container logs are streamed line-by-line
+/// Creates a CFS session and waits for it to finish, as `wait_options`
+/// says. When `watch_logs` is true the session's container logs are streamed line-by-line
 /// through `log::info!` (no direct stdout writes).
 ///
 /// Requires the `k8s-console` Cargo feature because the watch-logs
@@ -144,6 +145,7 @@ pub async fn i_post_sync(
   session: &CfsSessionPostRequest,
   watch_logs: bool,
   timestamps: bool,
+  wait_options: WaitOptions,
 ) -> Result<CfsSessionGetResponse, Error> {
   // Create CFS session
   log::info!("Create CFS session '{}'", session.name);
@@ -176,7 +178,13 @@ pub async fn i_post_sync(
       kubernetes::get_client(k8s_api_url, shasta_k8s_secrets, socks5_proxy)
         .await?;
 
-    i_print_cfs_session_logs(client, &cfs_session_name, timestamps).await?;
+    i_print_cfs_session_logs(
+      client,
+      &cfs_session_name,
+      timestamps,
+      PodWaitTimeouts::default().capped_at(wait_options.timeout),
+    )
+    .await?;
   }
 
   // User does not want the CFS logs but we still need to wait for the CFS session to
@@ -187,6 +195,7 @@ pub async fn i_post_sync(
     shasta_root_cert,
     socks5_proxy,
     &cfs_session_name,
+    wait_options,
   )
   .await?;
 
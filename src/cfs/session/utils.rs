@@ -1,8 +1,12 @@
 //! Helpers built on top of `ShastaClient::cfs_session_*` methods.
 
+use std::time::Duration;
+
 use crate::{
   cfs,
+  common::poll::WaitOptions,
   error::Error,
+  filter::{Cursor, Filter, Page, Query, configuration_glob},
   hsm::group::{
     GroupExt,
     hacks::{filter_roles_and_subroles, filter_system_hsm_group_names},
@@ -11,7 +15,6 @@ use crate::{
 };
 
 use super::http_client::v2::types::CfsSessionGetResponse;
-use globset::Glob;
 
 /// `true` if the CFS session's target HSM groups overlap with any HSM
 /// group in `group_available` (used to enforce per-user visibility).
@@ -81,6 +84,182 @@ pub fn is_session_image_generic(cfs_session: &CfsSessionGetResponse) -> bool {
   }
 }
 
+/// HSM groups and nodes whose CFS sessions a caller may see.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionVisibility<'a> {
+  /// HSM groups the caller has access to. Sessions targeting any of
+  /// them are visible.
+  pub hsm_group_names: &'a [String],
+  /// Nodes the caller has access to. Sessions whose `ansible.limit`
+  /// names any of them are visible.
+  pub xnames: &'a [String],
+  /// Also show generic image sessions (see
+  /// [`is_session_image_generic`]), whose images any group can use.
+  /// Callers pass `true` for admins.
+  pub keep_generic: bool,
+}
+
+impl SessionVisibility<'_> {
+  /// `true` if `cfs_session` is visible. Sessions with neither target
+  /// groups nor an `ansible.limit` never are.
+  #[must_use]
+  pub fn allows(&self, cfs_session: &CfsSessionGetResponse) -> bool {
+    cfs_session.get_target_hsm().is_some_and(|target_hsm_vec| {
+      (self.keep_generic && is_session_image_generic(cfs_session))
+        || target_hsm_vec
+          .iter()
+          .any(|target_hsm| self.hsm_group_names.contains(target_hsm))
+    }) || cfs_session
+      .get_target_xname()
+      .is_some_and(|target_xname_vec| {
+        target_xname_vec
+          .iter()
+          .any(|target_xname| self.xnames.contains(target_xname))
+      })
+  }
+}
+
+/// Which CFS sessions [`SessionFilter::apply`] keeps. Every criterion
+/// left unset matches everything, so the default keeps every session;
+/// set the ones needed, directly or through the builder methods:
+///
+/// ```
+/// use csm_rs::cfs::session::utils::{SessionFilter, SessionVisibility};
+///
+/// let hsm_group_names = ["zinal".to_string()];
+/// let filter = SessionFilter::default()
+///   .visible_to(SessionVisibility {
+///     hsm_group_names: &hsm_group_names,
+///     ..SessionVisibility::default()
+///   })
+///   .configuration_glob("zinal-*")
+///   .limit(10);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionFilter<'a> {
+  /// Keep only sessions visible to this caller.
+  pub visibility: Option<SessionVisibility<'a>>,
+  /// Keep only sessions of the CFS configuration with this exact name.
+  pub configuration_name: Option<&'a str>,
+  /// Keep only sessions whose CFS configuration name matches this glob.
+  pub configuration_name_pattern: Option<&'a str>,
+  /// Keep only sessions with this target definition (`image` or
+  /// `dynamic`).
+  pub target_definition: Option<&'a str>,
+  /// Keep only the `limit` most recent sessions.
+  pub limit: Option<usize>,
+}
+
+impl<'a> SessionFilter<'a> {
+  /// Keep only sessions visible as `visibility` says.
+  #[must_use]
+  pub fn visible_to(mut self, visibility: SessionVisibility<'a>) -> Self {
+    self.visibility = Some(visibility);
+    self
+  }
+
+  /// Keep only sessions of CFS configuration `configuration_name`.
+  #[must_use]
+  pub fn configuration(mut self, configuration_name: &'a str) -> Self {
+    self.configuration_name = Some(configuration_name);
+    self
+  }
+
+  /// Keep only sessions whose CFS configuration name matches glob
+  /// `pattern`.
+  #[must_use]
+  pub fn configuration_glob(mut self, pattern: &'a str) -> Self {
+    self.configuration_name_pattern = Some(pattern);
+    self
+  }
+
+  /// Keep only sessions with target definition `target_definition`.
+  #[must_use]
+  pub fn target_definition(mut self, target_definition: &'a str) -> Self {
+    self.target_definition = Some(target_definition);
+    self
+  }
+
+  /// Keep only the `limit` most recent sessions.
+  #[must_use]
+  pub fn limit(mut self, limit: usize) -> Self {
+    self.limit = Some(limit);
+    self
+  }
+
+  /// `true` if `cfs_session` passes the visibility, configuration name
+  /// and target definition criteria. The configuration glob and the
+  /// limit are only applied by [`SessionFilter::apply`].
+  #[must_use]
+  pub fn matches(&self, cfs_session: &CfsSessionGetResponse) -> bool {
+    self
+      .visibility
+      .is_none_or(|visibility| visibility.allows(cfs_session))
+      && self.configuration_name.is_none_or(|configuration_name| {
+        cfs_session.configuration_name() == Some(configuration_name)
+      })
+      && self.target_definition.is_none_or(|target_definition| {
+        cfs_session.get_target_def().as_deref() == Some(target_definition)
+      })
+  }
+
+  /// Retain the sessions of `cfs_session_vec` this filter keeps, sorted
+  /// by start time, oldest first.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::GlobError`] if
+  /// [`SessionFilter::configuration_name_pattern`] is not a valid
+  /// glob.
+  pub fn apply(
+    &self,
+    cfs_session_vec: &mut Vec<CfsSessionGetResponse>,
+  ) -> Result<(), Error> {
+    log::debug!("Filter CFS sessions: {self:?}");
+
+    cfs_session_vec.retain(|cfs_session| self.matches(cfs_session));
+
+    Query {
+      limit: self.limit,
+      ..self.query()
+    }
+    .apply(cfs_session_vec)
+  }
+
+  /// Like [`SessionFilter::apply`], but return the `page_size` sessions
+  /// following `after_opt` (or the first ones) instead of the last
+  /// [`SessionFilter::limit`]. Feed [`Page::next_cursor`] back as
+  /// `after_opt` to get the next page.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::GlobError`] if
+  /// [`SessionFilter::configuration_name_pattern`] is not a valid
+  /// glob.
+  pub fn page(
+    &self,
+    mut cfs_session_vec: Vec<CfsSessionGetResponse>,
+    after_opt: Option<Cursor>,
+    page_size: usize,
+  ) -> Result<Page<CfsSessionGetResponse>, Error> {
+    cfs_session_vec.retain(|cfs_session| self.matches(cfs_session));
+
+    Query {
+      after: after_opt,
+      ..self.query()
+    }
+    .page(cfs_session_vec, page_size)
+  }
+
+  fn query(&self) -> Query {
+    Query::new(
+      self
+        .configuration_name_pattern
+        .map_or(Filter::All, configuration_glob),
+    )
+  }
+}
+
 /// Filter CFS sessions in place by configuration-name glob, HSM groups
 /// the caller has access to, optional xname allow-list, session type,
 /// row limit, and a flag controlling whether "generic" (non-targeted)
@@ -91,6 +270,10 @@ pub fn is_session_image_generic(cfs_session: &CfsSessionGetResponse) -> bool {
 /// Returns an [`Error`] variant on CSM, transport, or
 /// deserialization failure; see the crate-level `Error` enum
 /// for the full set.
+#[deprecated(
+  since = "1.0.0-beta.19",
+  note = "use `SessionFilter` with `SessionVisibility` instead"
+)]
 pub fn filter(
   cfs_session_vec: &mut Vec<CfsSessionGetResponse>,
   configuration_name_pattern_opt: Option<&str>,
@@ -100,75 +283,70 @@ pub fn filter(
   limit_number_opt: Option<&u8>,
   keep_generic_sessions: bool,
 ) -> Result<(), Error> {
-  log::debug!("Filter CFS sessions by groups");
-  log::debug!(
-    "HSM groups to filter from: {hsm_group_name_available_vec:?}"
-  );
-  log::debug!("Xnames to filter from: {xname_available_vec:?}");
-
-  if let Some(configuration_name_pattern) = configuration_name_pattern_opt {
-    let glob = Glob::new(configuration_name_pattern)?.compile_matcher();
-
-    cfs_session_vec.retain(|cfs_session| {
-      cfs_session
-        .configuration_name()
-        .is_some_and(|configuration_name| glob.is_match(configuration_name))
-    });
-  }
-
-  // Checks either target.groups contains hsm_group_name or ansible.limit is a subset of
-  // hsm_group.members.ids
-  cfs_session_vec.retain(|cfs_session| {
-    cfs_session.get_target_hsm().is_some_and(|target_hsm_vec| {
-      (keep_generic_sessions && is_session_image_generic(cfs_session))
-        || target_hsm_vec.iter().any(|target_hsm| {
-          hsm_group_name_available_vec
-            .iter()
-            .any(|hsm_group_name| target_hsm.contains(hsm_group_name))
-        })
-    }) || cfs_session
-      .get_target_xname()
-      .is_some_and(|target_xname_vec| {
-        target_xname_vec
-          .iter()
-          .any(|target_xname| xname_available_vec.contains(target_xname))
-      })
-  });
-
-  if type_opt.is_some() {
-    cfs_session_vec
-      .retain(|cfs_session| cfs_session.get_target_def() == type_opt.cloned());
+  SessionFilter {
+    visibility: Some(SessionVisibility {
+      hsm_group_names: hsm_group_name_available_vec,
+      xnames: xname_available_vec,
+      keep_generic: keep_generic_sessions,
+    }),
+    configuration_name: None,
+    configuration_name_pattern: configuration_name_pattern_opt,
+    target_definition: type_opt.map(String::as_str),
+    limit: limit_number_opt.map(|limit_number| usize::from(*limit_number)),
   }
+  .apply(cfs_session_vec)
+}
 
-  // Sort CFS sessions by start time order ASC
-  cfs_session_vec.sort_by(|a, b| {
-    a.get_start_time()
-      .unwrap_or_default()
-      .cmp(&b.get_start_time().unwrap_or_default())
-  });
+/// Like [`filter`], but keep the CFS sessions matching `query` and
+/// return the `page_size` following [`Query::after`] instead of the
+/// last `limit`. Feed [`Page::next_cursor`] back through
+/// [`Query::after`] to get the next page.
+///
+/// # Errors
+///
+/// Returns [`Error::GlobError`] if a glob pattern in `query` is
+/// invalid.
+#[deprecated(
+  since = "1.0.0-beta.19",
+  note = "use `SessionFilter::page` with `SessionVisibility` instead"
+)]
+pub fn filter_page(
+  mut cfs_session_vec: Vec<CfsSessionGetResponse>,
+  hsm_group_name_available_vec: &[String],
+  xname_available_vec: &[String],
+  type_opt: Option<&String>,
+  keep_generic_sessions: bool,
+  query: &Query,
+  page_size: usize,
+) -> Result<Page<CfsSessionGetResponse>, Error> {
+  let session_filter = SessionFilter {
+    visibility: Some(SessionVisibility {
+      hsm_group_names: hsm_group_name_available_vec,
+      xnames: xname_available_vec,
+      keep_generic: keep_generic_sessions,
+    }),
+    target_definition: type_opt.map(String::as_str),
+    ..SessionFilter::default()
+  };
 
-  if let Some(limit_number) = limit_number_opt {
-    // Limiting the number of results to return to client
-    *cfs_session_vec = cfs_session_vec
-      [cfs_session_vec.len().saturating_sub(*limit_number as usize)..]
-      .to_vec();
-  }
+  cfs_session_vec.retain(|cfs_session| session_filter.matches(cfs_session));
 
-  Ok(())
+  query.page(cfs_session_vec, page_size)
 }
 
 /// Filter CFS sessions to the ones related to a CFS configuration
+#[deprecated(
+  since = "1.0.0-beta.19",
+  note = "use `SessionFilter::configuration` instead"
+)]
 pub fn filter_by_cofiguration(
   cfs_session_vec: &mut Vec<CfsSessionGetResponse>,
   cfs_configuration_name: &str,
 ) {
-  log::debug!(
-    "Filter CFS sessions by configuration name '{cfs_configuration_name}'"
-  );
+  let session_filter =
+    SessionFilter::default().configuration(cfs_configuration_name);
 
-  cfs_session_vec.retain(|cfs_session| {
-    cfs_session.configuration_name() == Some(cfs_configuration_name)
-  });
+  cfs_session_vec.retain(|cfs_session| session_filter.matches(cfs_session));
 }
 
 /// Filter CFS sessions related to a list of HSM group names and a list of nodes and filter
@@ -291,10 +469,15 @@ pub fn images_id_from_cfs_session(
   image_id_vec.into_iter()
 }
 
-/// Wait for a CFS session to finish. Polls with exponential backoff
-/// (2 s → 30 s, max 200 attempts ≈ 100 min wall-clock cap, matching
-/// the prior constant-delay budget) until the session's
-/// `status.session.status` reaches `"complete"`.
+/// How long [`wait_cfs_session_to_finish`] waits unless told
+/// otherwise: 100 minutes, checking after 2 s and backing off to 30 s.
+pub const DEFAULT_WAIT: WaitOptions =
+  WaitOptions::new(Duration::from_secs(6000), Duration::from_secs(2))
+    .with_backoff(Duration::from_secs(30));
+
+/// Wait for a CFS session to finish: poll, as `wait_options` says, until
+/// the session's `status.session.status` reaches `"complete"` or
+/// `wait_options.timeout` expires.
 ///
 /// # Errors
 ///
@@ -307,15 +490,10 @@ pub async fn wait_cfs_session_to_finish(
   shasta_root_cert: &[u8],
   socks5_proxy: Option<&str>,
   cfs_session_id: &str,
+  wait_options: WaitOptions,
 ) -> Result<(), Error> {
-  let backoff = crate::common::poll::PollBackoff {
-    initial_delay: std::time::Duration::from_secs(2),
-    max_delay: std::time::Duration::from_secs(30),
-    max_attempts: 200,
-  };
-
   let status = crate::common::poll::poll_until_with_backoff(
-    backoff,
+    wait_options.poll_backoff(),
     || async {
       let cfs_session_vec = cfs::session::get_and_sort(
         shasta_token,
@@ -391,6 +569,9 @@ pub async fn get_list_xnames_related_to_session(
 }
 
 #[cfg(test)]
+// `filter`, `filter_page` and `filter_by_cofiguration` keep their tests
+// until removed
+#[allow(deprecated)]
 mod tests {
   use super::*;
   use crate::cfs::session::http_client::v2::types::{
@@ -784,6 +965,203 @@ mod tests {
     assert_eq!(names, vec!["s3", "s4"]);
   }
 
+  #[test]
+  fn filter_page_pages_through_matching_sessions() {
+    let sessions: Vec<CfsSessionGetResponse> = [
+      ("s1", "zinal-1.0", "2024-01-03T00:00:00Z"),
+      ("s2", "daint-1.0", "2024-01-02T00:00:00Z"),
+      ("s3", "zinal-2.0", "2024-01-01T00:00:00Z"),
+      ("s4", "zinal-3.0", "2024-01-04T00:00:00Z"),
+    ]
+    .into_iter()
+    .map(|(name, config, start_time)| {
+      let mut s = session_with_start(name, start_time);
+      s.target = session_with_target_hsm(name, "dynamic", vec!["zinal"]).target;
+      s.configuration = session_with_config(name, config).configuration;
+      s
+    })
+    .collect();
+    let groups = ["zinal".to_string()];
+
+    let query = Query::new(configuration_glob("zinal-*"));
+    let first =
+      filter_page(sessions.clone(), &groups, &[], None, false, &query, 2)
+        .unwrap();
+    assert_eq!(names(&first.items), ["s3", "s1"]);
+
+    let query = query.after(first.next_cursor.unwrap());
+    let second =
+      filter_page(sessions, &groups, &[], None, false, &query, 2).unwrap();
+    assert_eq!(names(&second.items), ["s4"]);
+    assert!(second.next_cursor.is_none());
+  }
+
+  // ---------- SessionFilter ----------
+
+  fn names(sessions: &[CfsSessionGetResponse]) -> Vec<&str> {
+    sessions.iter().map(|s| s.name.as_str()).collect()
+  }
+
+  /// One session of each kind a tenant or admin may or may not see
+  fn visibility_sessions() -> Vec<CfsSessionGetResponse> {
+    vec![
+      session("untargeted"),
+      session_with_target_hsm("zinal-dyn", "dynamic", vec!["zinal"]),
+      session_with_target_hsm("zinal-img", "image", vec!["zinal", "Compute"]),
+      session_with_target_hsm("daint-dyn", "dynamic", vec!["daint"]),
+      session_with_target_hsm("prefix-dyn", "dynamic", vec!["zinal-cpu"]),
+      session_with_target_hsm("generic-img", "image", vec!["Compute"]),
+      session_with_target_hsm("generic-dyn", "dynamic", vec!["Compute"]),
+      session_with_ansible_limit("own-node", "x1000c0s0b0n0,x9999c0s0b0n0"),
+      session_with_ansible_limit("other-node", "x9999c0s0b0n0"),
+    ]
+  }
+
+  fn filter_visibility(keep_generic: bool) -> Vec<String> {
+    let hsm_group_names = ["zinal".to_string()];
+    let xnames = ["x1000c0s0b0n0".to_string()];
+    let mut sessions = visibility_sessions();
+
+    SessionFilter::default()
+      .visible_to(SessionVisibility {
+        hsm_group_names: &hsm_group_names,
+        xnames: &xnames,
+        keep_generic,
+      })
+      .apply(&mut sessions)
+      .unwrap();
+
+    let mut names: Vec<String> = sessions.into_iter().map(|s| s.name).collect();
+    names.sort();
+    names
+  }
+
+  #[test]
+  fn session_filter_default_keeps_every_session() {
+    let mut sessions = visibility_sessions();
+    SessionFilter::default().apply(&mut sessions).unwrap();
+    assert_eq!(sessions.len(), visibility_sessions().len());
+  }
+
+  #[test]
+  fn session_filter_tenant_sees_own_groups_and_nodes_only() {
+    assert_eq!(
+      filter_visibility(false),
+      ["own-node", "zinal-dyn", "zinal-img"]
+    );
+  }
+
+  #[test]
+  fn session_filter_admin_also_sees_generic_image_sessions() {
+    // Generic dynamic sessions and other groups' sessions stay hidden
+    assert_eq!(
+      filter_visibility(true),
+      ["generic-img", "own-node", "zinal-dyn", "zinal-img"]
+    );
+  }
+
+  #[test]
+  fn session_filter_group_names_match_exactly() {
+    let hsm_group_names = ["zinal".to_string()];
+    let visibility = SessionVisibility {
+      hsm_group_names: &hsm_group_names,
+      ..SessionVisibility::default()
+    };
+    assert!(!visibility.allows(&session_with_target_hsm(
+      "s",
+      "dynamic",
+      vec!["zinal-cpu"]
+    )));
+  }
+
+  #[test]
+  fn session_filter_without_access_sees_nothing() {
+    let mut sessions = visibility_sessions();
+    SessionFilter::default()
+      .visible_to(SessionVisibility::default())
+      .apply(&mut sessions)
+      .unwrap();
+    assert!(sessions.is_empty());
+  }
+
+  #[test]
+  fn session_filter_composes_criteria() {
+    let mut sessions: Vec<CfsSessionGetResponse> = [
+      ("s1", "dynamic", "zinal-1.0", "2024-01-01T00:00:00Z"),
+      ("s2", "image", "zinal-1.0", "2024-01-02T00:00:00Z"),
+      ("s3", "dynamic", "zinal-2.0", "2024-01-03T00:00:00Z"),
+      ("s4", "dynamic", "zinal-1.0", "2024-01-04T00:00:00Z"),
+      ("s5", "dynamic", "daint-1.0", "2024-01-05T00:00:00Z"),
+    ]
+    .into_iter()
+    .map(|(name, defn, config, start_time)| {
+      let mut s = session_with_start(name, start_time);
+      s.target = session_with_target_hsm(name, defn, vec!["zinal"]).target;
+      s.configuration = session_with_config(name, config).configuration;
+      s
+    })
+    .collect();
+    let hsm_group_names = ["zinal".to_string()];
+
+    let session_filter = SessionFilter::default()
+      .visible_to(SessionVisibility {
+        hsm_group_names: &hsm_group_names,
+        ..SessionVisibility::default()
+      })
+      .target_definition("dynamic")
+      .configuration_glob("zinal-*");
+
+    let mut all = sessions.clone();
+    session_filter.apply(&mut all).unwrap();
+    assert_eq!(names(&all), ["s1", "s3", "s4"]);
+
+    let mut exact = sessions.clone();
+    session_filter
+      .configuration("zinal-1.0")
+      .apply(&mut exact)
+      .unwrap();
+    assert_eq!(names(&exact), ["s1", "s4"]);
+
+    session_filter.limit(2).apply(&mut sessions).unwrap();
+    assert_eq!(names(&sessions), ["s3", "s4"]);
+  }
+
+  #[test]
+  fn session_filter_rejects_invalid_glob() {
+    let mut sessions = vec![session_with_config("s1", "zinal")];
+    assert!(
+      SessionFilter::default()
+        .configuration_glob("zinal-[")
+        .apply(&mut sessions)
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn session_filter_pages_through_matches() {
+    let sessions: Vec<CfsSessionGetResponse> = [
+      ("s1", "zinal-1.0", "2024-01-03T00:00:00Z"),
+      ("s2", "daint-1.0", "2024-01-02T00:00:00Z"),
+      ("s3", "zinal-2.0", "2024-01-01T00:00:00Z"),
+      ("s4", "zinal-3.0", "2024-01-04T00:00:00Z"),
+    ]
+    .into_iter()
+    .map(|(name, config, start_time)| {
+      let mut s = session_with_start(name, start_time);
+      s.configuration = session_with_config(name, config).configuration;
+      s
+    })
+    .collect();
+    let session_filter = SessionFilter::default().configuration_glob("zinal-*");
+
+    let first = session_filter.page(sessions.clone(), None, 2).unwrap();
+    assert_eq!(names(&first.items), ["s3", "s1"]);
+
+    let second = session_filter.page(sessions, first.next_cursor, 2).unwrap();
+    assert_eq!(names(&second.items), ["s4"]);
+    assert!(second.next_cursor.is_none());
+  }
+
   // ---------- images_id_from_cfs_session ----------
 
   #[test]
@@ -0,0 +1,15 @@
+//! CFS sources — named git repositories, with credentials stored by CFS
+//! in Vault, that configuration layers can reference instead of a
+//! `clone_url`. Only exposed by the v3 API.
+//!
+//! Submodules:
+//!
+//! - [`types`] — wire-format request/response structs.
+//! - [`utils`] — helpers built on top of the raw client (e.g. checking
+//!   that the sources a configuration references exist).
+//!
+//! The `ShastaClient::cfs_source_v3_*` methods live in
+//! `crate::cfs::wrapper::v3::source`.
+
+pub mod types;
+pub mod utils;
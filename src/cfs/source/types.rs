@@ -0,0 +1,94 @@
+//! Serde types for the CFS v3 sources endpoint.
+//!
+//! These mirror the upstream CSM `OpenAPI` schema; field names and shapes
+//! are dictated by the API.
+#![allow(missing_docs)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CfsSourceCredentials {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub authentication_method: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub secret_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub username: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CfsSourceCaCert {
+  pub configmap_name: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub configmap_namespace: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CfsSource {
+  pub name: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub last_updated: Option<String>,
+  pub clone_url: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub credentials: Option<CfsSourceCredentials>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ca_cert: Option<CfsSourceCaCert>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CfsSourceVecResponse {
+  pub sources: Vec<CfsSource>,
+  pub next: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CfsSourceCreateCredentials {
+  pub authentication_method: String,
+  pub username: String,
+  pub password: String,
+}
+
+impl CfsSourceCreateCredentials {
+  /// Username/password credentials, the only authentication method CFS
+  /// currently accepts.
+  #[must_use]
+  pub fn password(username: &str, password: &str) -> Self {
+    Self {
+      authentication_method: "password".to_string(),
+      username: username.to_string(),
+      password: password.to_string(),
+    }
+  }
+}
+
+/// Body of `POST /cfs/v3/sources`. `name` defaults to `clone_url` on
+/// the CFS side when omitted.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CfsSourceCreateRequest {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  pub clone_url: String,
+  pub credentials: CfsSourceCreateCredentials,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ca_cert: Option<CfsSourceCaCert>,
+}
+
+/// Body of `PATCH /cfs/v3/sources/{source_id}`. Only the fields set
+/// are changed.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CfsSourceUpdateRequest {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub clone_url: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub credentials: Option<CfsSourceCredentials>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ca_cert: Option<CfsSourceCaCert>,
+}
@@ -0,0 +1,41 @@
+//! Helpers built on top of `ShastaClient::cfs_source_v3_*` methods.
+
+use crate::error::Error;
+
+/// Check that every CFS source in `source_names` is registered in CFS.
+///
+/// Fetches the full source list once and compares names locally, so
+/// callers validating a whole configuration pay a single round trip.
+///
+/// # Errors
+///
+/// Returns [`Error::CfsSourceNotFound`] naming the first missing
+/// source, or another [`Error`] variant on CSM, transport, or
+/// deserialization failure.
+pub async fn validate_sources_exist(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  source_names: &[&str],
+) -> Result<(), Error> {
+  if source_names.is_empty() {
+    return Ok(());
+  }
+
+  let source_vec = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?
+  .cfs_source_v3_get(shasta_token, None)
+  .await?;
+
+  match source_names
+    .iter()
+    .find(|name| !source_vec.iter().any(|source| source.name == **name))
+  {
+    Some(missing) => Err(Error::CfsSourceNotFound((*missing).to_string())),
+    None => Ok(()),
+  }
+}
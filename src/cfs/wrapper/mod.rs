@@ -15,7 +15,7 @@
 //! etc.) hold `impl ShastaClient { pub async fn cfs_*() }` blocks that
 //! delegate to the generated client via the `run` adapter.
 
-use crate::{ShastaClient, cfs::generated, error::Error};
+use crate::{ShastaClient, cfs::generated, common::metrics, error::Error};
 
 /// Build a generated CFS `Client` bound to the caller's token. Re-uses
 /// the shared `http::build_client_with_auth` helper so timeout / TLS /
@@ -24,11 +24,8 @@ pub(crate) fn gen_client(
   client: &ShastaClient,
   token: &str,
 ) -> Result<generated::Client, Error> {
-  let inner = crate::common::http::build_client_with_auth(
-    client.root_cert(),
-    client.socks5_proxy(),
-    Some(token),
-  )?;
+  let inner =
+    crate::common::http::build_client_with_auth(client.config(), Some(token))?;
   // CFS basePath: csm-rs's `base_url` already ends in `/apis`; CFS
   // operations live under `/cfs/...` (v2 and v3 prefixes are part of
   // the operation paths).
@@ -86,7 +83,7 @@ pub(crate) async fn run<F, Fut, T, E>(
   op: F,
 ) -> Result<T, Error>
 where
-  F: FnOnce(generated::Client) -> Fut,
+  F: Fn(generated::Client) -> Fut,
   Fut: std::future::Future<
       Output = Result<
         progenitor_client::ResponseValue<T>,
@@ -95,8 +92,7 @@ where
     >,
   E: std::fmt::Debug,
 {
-  let gc = gen_client(client, token)?;
-  match op(gc).await {
+  match metrics::send_generated(client, token, "cfs", gen_client, op).await? {
     Ok(rv) => Ok(rv.into_inner()),
     Err(e) => Err(map_err(e).await),
   }
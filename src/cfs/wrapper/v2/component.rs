@@ -53,6 +53,7 @@
 
 use std::time::Instant;
 
+use crate::common::metrics::MeteredSend;
 use crate::{
   ShastaClient,
   cfs::component::http_client::v2::types::Component,
@@ -85,7 +86,7 @@ impl ShastaClient {
       .get(api_url)
       .query(&[("ids", components_ids), ("status", status)])
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -129,7 +130,7 @@ impl ShastaClient {
       .http()
       .get(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -243,7 +244,7 @@ impl ShastaClient {
         ("limit", Some(&stupid_limit.to_string())),
       ])
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -315,7 +316,7 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
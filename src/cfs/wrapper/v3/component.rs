@@ -69,6 +69,7 @@ use std::time::Instant;
 
 use serde_json::Value;
 
+use crate::common::metrics::MeteredSend;
 use crate::{
   ShastaClient,
   cfs::component::http_client::v3::types::{Component, ComponentVec},
@@ -97,7 +98,7 @@ impl ShastaClient {
       .http()
       .get(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -126,7 +127,7 @@ impl ShastaClient {
       .get(api_url)
       .query(&[("ids", components_ids), ("status", status)])
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -156,7 +157,7 @@ impl ShastaClient {
       .http()
       .get(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -243,7 +244,7 @@ impl ShastaClient {
         ("limit", Some(&stupid_limit.to_string())),
       ])
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -279,7 +280,7 @@ impl ShastaClient {
       .patch(api_url)
       .bearer_auth(token)
       .json(&component)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -307,7 +308,7 @@ impl ShastaClient {
       .patch(api_url)
       .bearer_auth(token)
       .json(&component_list)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -384,7 +385,7 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
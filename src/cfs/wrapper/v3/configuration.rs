@@ -71,6 +71,7 @@
 //!   safe to revisit alongside the future swap of the public response
 //!   type.
 
+use crate::common::metrics::MeteredSend;
 use crate::{
   ShastaClient,
   cfs::configuration::http_client::v3::types::{
@@ -79,7 +80,7 @@ use crate::{
       CfsConfigurationResponse, CfsConfigurationVecResponse,
     },
   },
-  common::http,
+  common::{http, protection},
   error::Error,
 };
 
@@ -116,7 +117,7 @@ impl ShastaClient {
       .get(api_url)
       .query(&[("limit", STUPID_LIMIT)])
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -180,7 +181,11 @@ impl ShastaClient {
       configuration_name
     );
 
-    let request_payload = serde_json::json!({ "layers": configuration.layers });
+    let mut request_payload =
+      serde_json::json!({ "layers": configuration.layers });
+    if let Some(description) = &configuration.description {
+      request_payload["description"] = description.clone().into();
+    }
     log::debug!(
       "CFS configuration request payload:\n{}",
       serde_json::to_string_pretty(&request_payload)
@@ -192,7 +197,92 @@ impl ShastaClient {
       .put(api_url)
       .json(&request_payload)
       .bearer_auth(token)
-      .send()
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    http::handle_json_or_text_response(response).await
+  }
+
+  /// Protect or unprotect CFS configuration `configuration_name`
+  /// against deletion, see [`crate::protection`].
+  ///
+  /// `PUT /cfs/v3/configurations/{configuration_name}` with the layers
+  /// and additional inventory of the current configuration and its
+  /// description with the protection marker updated. Unlike
+  /// [`Self::cfs_configuration_v3_put`], this replaces the existing
+  /// configuration.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn cfs_configuration_v3_set_protected(
+    &self,
+    token: &str,
+    configuration_name: &str,
+    protected: bool,
+  ) -> Result<CfsConfigurationResponse, Error> {
+    let configuration = self
+      .cfs_configuration_v3_get(token, Some(configuration_name))
+      .await?
+      .into_iter()
+      .next()
+      .ok_or_else(|| {
+        Error::Message(format!(
+          "CFS configuration '{configuration_name}' not found"
+        ))
+      })?;
+
+    // CFS resolves branches into commits; sending both back is rejected
+    let layers = configuration
+      .layers
+      .into_iter()
+      .map(|mut layer| {
+        if layer.branch.is_some() {
+          layer.commit = None;
+        }
+        layer
+      })
+      .collect::<Vec<_>>();
+
+    let mut request_payload = serde_json::json!({ "layers": layers });
+    if let Some(additional_inventory) = configuration.additional_inventory {
+      let mut inventory_payload = serde_json::json!({
+        "clone_url": additional_inventory.clone_url,
+        "name": additional_inventory.name,
+      });
+      if let Some(branch) = additional_inventory.branch {
+        inventory_payload["branch"] = branch.into();
+      } else if let Some(commit) = additional_inventory.commit {
+        inventory_payload["commit"] = commit.into();
+      }
+      request_payload["additional_inventory"] = inventory_payload;
+    }
+    if let Some(description) =
+      protection::set_protected(configuration.description.as_deref(), protected)
+    {
+      request_payload["description"] = description.into();
+    }
+    log::debug!(
+      "CFS configuration request payload:\n{}",
+      serde_json::to_string_pretty(&request_payload)
+        .unwrap_or_else(|e| format!("<serialize error: {e}>"))
+    );
+
+    let api_url = format!(
+      "{}/cfs/v3/configurations/{}",
+      self.base_url(),
+      configuration_name
+    );
+
+    let response = self
+      .http()
+      .put(api_url)
+      .json(&request_payload)
+      .bearer_auth(token)
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
@@ -1,5 +1,5 @@
 //! `manta`-facing CFS v3 wrapper methods. Per-resource sub-modules
-//! (`component`, `configuration`, `session`) attach
+//! (`component`, `configuration`, `session`, `source`) attach
 //! `impl ShastaClient { pub async fn cfs_<resource>_v3_*() }` blocks
 //! to the public client. Each sub-module's docstring records the
 //! per-method routing decision (generated client vs raw reqwest).
@@ -10,3 +10,4 @@
 mod component;
 mod configuration;
 mod session;
+mod source;
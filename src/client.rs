@@ -1,18 +1,46 @@
 //! [`ShastaClient`] — connection-pool-owning entry point for talking to a
 //! Shasta CSM API.
 //!
-//! Holds the base URL, root certificate, optional SOCKS5 proxy, and a
-//! pre-built `reqwest::Client` (with its connection pool, TLS context,
-//! and DNS resolver). The bearer token is **not** stored on the client
-//! — it is passed per request.
+//! Holds the base URL, the [`ClientConfig`] (trusted CA certificates,
+//! client identity, proxy), and a pre-built `reqwest::Client` (with its
+//! connection pool, TLS context, and DNS resolver). The bearer token is
+//! **not** stored on the client — it is passed per request. A client may
+//! instead carry an [`AuthTokenProvider`]
+//! ([`ShastaClient::with_token_provider`]) that hands out a fresh token
+//! for each request of long operations.
+//!
+//! The [`crate::commands`] taking a `ShastaClient` run inside
+//! [`ShastaClient::scope`], so the helpers they call, including those
+//! taking the base URL, root cert and proxy as arguments, reuse the
+//! caller's client and its token provider instead of building their own.
+//!
+//! [`ShastaClient::connect`] also checks the connection and the token
+//! up-front and captures the CSM service versions, see
+//! [`crate::common::handshake`].
 //!
 //! Construct one `ShastaClient` per Shasta installation and reuse it
 //! across calls; clones are cheap (`reqwest::Client` is reference-
 //! counted internally).
+//!
+//! With the `k8s-console` feature it also caches the `kube::Client`s used
+//! for console and log calls (`ShastaClient::kube_client`); clones share
+//! that cache.
+
+use std::{future::Future, sync::Arc};
 
+use crate::cfs::compat::CfsApiVersion;
+use crate::common::auth_provider::{self, AuthTokenProvider};
+use crate::common::client_config::ClientConfig;
+use crate::common::handshake::CsmVersions;
 use crate::common::http;
+#[cfg(feature = "k8s-console")]
+use crate::common::kubernetes::{self, KubeAuth, KubeClientCache};
 use crate::error::Error;
 
+tokio::task_local! {
+  static SCOPED_CLIENT: ShastaClient;
+}
+
 /// Connection details + a reusable `reqwest::Client` for one Shasta CSM
 /// installation. Token is passed per request, not stored.
 ///
@@ -47,9 +75,13 @@ use crate::error::Error;
 #[derive(Debug, Clone)]
 pub struct ShastaClient {
   pub(crate) base_url: String,
-  pub(crate) root_cert: Vec<u8>,
-  pub(crate) socks5_proxy: Option<String>,
+  pub(crate) config: ClientConfig,
   pub(crate) http: reqwest::Client,
+  pub(crate) token_provider: Option<Arc<dyn AuthTokenProvider>>,
+  pub(crate) versions: Option<Arc<CsmVersions>>,
+  pub(crate) cfs_api_version: Arc<tokio::sync::OnceCell<CfsApiVersion>>,
+  #[cfg(feature = "k8s-console")]
+  pub(crate) kube_clients: Arc<KubeClientCache>,
 }
 
 impl ShastaClient {
@@ -68,36 +100,325 @@ impl ShastaClient {
     root_cert: impl Into<Vec<u8>>,
     socks5_proxy: Option<String>,
   ) -> Result<Self, Error> {
-    let root_cert = root_cert.into();
-    let http = http::build_client(&root_cert, socks5_proxy.as_deref())?;
+    let mut config = ClientConfig::new().with_ca_certs(root_cert.into());
+    if let Some(proxy) = socks5_proxy {
+      config = config.with_proxy(proxy);
+    }
+    Self::with_config(base_url, config)
+  }
+
+  /// Build a new client whose HTTP clients all trust the CA certificates,
+  /// authenticate with the client identity and go through the proxy of
+  /// `config`.
+  ///
+  /// ```no_run
+  /// # fn example() -> Result<(), csm_rs::Error> {
+  /// use csm_rs::{ClientConfig, ShastaClient};
+  ///
+  /// let config = ClientConfig::new()
+  ///   .with_ca_certs(std::fs::read("/etc/shasta/root-ca.crt").unwrap())
+  ///   .with_ca_certs(std::fs::read("/etc/shasta/intermediate.crt").unwrap())
+  ///   .with_proxy("http://proxy.example.com:3128");
+  /// let client =
+  ///   ShastaClient::with_config("https://api.shasta.example.com", config)?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::NetError`] if a CA certificate, the client identity
+  /// or the proxy URL is malformed, or `reqwest::Client::build` fails.
+  #[must_use = "constructing a ShastaClient without using it is a no-op"]
+  pub fn with_config(
+    base_url: impl Into<String>,
+    config: ClientConfig,
+  ) -> Result<Self, Error> {
+    let http = http::build_client_with_auth(&config, None)?;
     Ok(Self {
       base_url: base_url.into(),
-      root_cert,
-      socks5_proxy,
+      config,
       http,
+      token_provider: None,
+      versions: None,
+      cfs_api_version: Arc::default(),
+      #[cfg(feature = "k8s-console")]
+      kube_clients: Arc::default(),
     })
   }
 
+  /// The same client, getting its bearer tokens from `token_provider`
+  /// ([`ShastaClient::token`], [`ShastaClient::with_token`] and
+  /// [`crate::watch::watch`]). The requests of its methods carry a token
+  /// from the provider instead of their `token` argument, and are
+  /// retried once with a new one if CSM answers 401.
+  #[must_use]
+  pub fn with_token_provider(
+    mut self,
+    token_provider: Arc<dyn AuthTokenProvider>,
+  ) -> Self {
+    self.token_provider = Some(token_provider);
+    self
+  }
+
+  /// The [`AuthTokenProvider`] of the client, if one was set.
+  #[must_use]
+  pub fn token_provider(&self) -> Option<&Arc<dyn AuthTokenProvider>> {
+    self.token_provider.as_ref()
+  }
+
+  /// A valid bearer token from the client's [`AuthTokenProvider`], to
+  /// pass to the methods taking `token: &str`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Message`] if the client has no token provider, or
+  /// the error of the provider.
+  pub async fn token(&self) -> Result<String, Error> {
+    match &self.token_provider {
+      Some(token_provider) => token_provider.token().await,
+      None => Err(Error::Message(
+        "ShastaClient has no token provider, pass the token explicitly"
+          .to_string(),
+      )),
+    }
+  }
+
+  /// `token` if the client has no [`AuthTokenProvider`], else a fresh
+  /// token from the provider. Lets long operations started with a token
+  /// keep going past its expiry when a provider is set.
+  pub(crate) async fn fresh_token(&self, token: &str) -> Result<String, Error> {
+    match &self.token_provider {
+      Some(token_provider) => token_provider.token().await,
+      None => Ok(token.to_string()),
+    }
+  }
+
+  /// Run `op` with a fresh token from [`ShastaClient::token`]. If CSM
+  /// answers 401 (token revoked or expired early), the provider is told
+  /// to [invalidate](AuthTokenProvider::invalidate) it and `op` is
+  /// retried once with a new token.
+  ///
+  /// ```no_run
+  /// # async fn example(
+  /// #   client: csm_rs::ShastaClient,
+  /// # ) -> Result<(), csm_rs::Error> {
+  /// let images = client
+  ///   .with_token(|token| {
+  ///     let client = client.clone();
+  ///     async move { client.ims_image_get_all(&token).await }
+  ///   })
+  ///   .await?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  ///
+  /// # Errors
+  ///
+  /// Returns the error of [`ShastaClient::token`] or of `op`.
+  pub async fn with_token<T, F, Fut>(&self, op: F) -> Result<T, Error>
+  where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+  {
+    match op(self.token().await?).await {
+      Err(e) if auth_provider::is_unauthorized(&e) => {
+        log::info!("CSM rejected the bearer token, requesting a new one");
+        if let Some(token_provider) = &self.token_provider {
+          token_provider.invalidate().await;
+        }
+        op(self.token().await?).await
+      }
+      result => result,
+    }
+  }
+
   /// The Shasta API base URL (e.g. `https://api.shasta.example.com`).
   #[must_use]
   pub fn base_url(&self) -> &str {
     &self.base_url
   }
 
-  /// The PEM-encoded root certificate trusted for HTTPS calls.
+  /// The PEM-encoded CA certificates trusted for HTTPS calls.
   #[must_use]
   pub fn root_cert(&self) -> &[u8] {
-    &self.root_cert
+    self.config.ca_bundle()
   }
 
-  /// The SOCKS5 proxy URL, if one was configured.
+  /// The proxy URL, if one was configured.
   #[must_use]
   pub fn socks5_proxy(&self) -> Option<&str> {
-    self.socks5_proxy.as_deref()
+    self.config.proxy()
+  }
+
+  /// The settings of the client, see [`ClientConfig`].
+  #[must_use]
+  pub fn config(&self) -> &ClientConfig {
+    &self.config
   }
 
-  pub(crate) fn http(&self) -> &reqwest::Client {
-    &self.http
+  /// `kube::Client` for the Kubernetes API at `k8s_api_url`, built on
+  /// first use and shared with every clone of this client afterwards.
+  ///
+  /// Clients are cached per `(k8s_api_url, auth, socks5_proxy)`;
+  /// `socks5_proxy` applies to this Kubernetes API only and may differ
+  /// from the proxy used for CSM calls. `shasta_token` reads
+  /// [`KubeAuth::Vault`] credentials when the client is built, and those
+  /// clients are also cached per user of the token, so one user's
+  /// credentials are never handed to another.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] if the credentials can't be read or the
+  /// client can't be built from them.
+  #[cfg(feature = "k8s-console")]
+  pub async fn kube_client(
+    &self,
+    shasta_token: &str,
+    k8s_api_url: &str,
+    auth: &KubeAuth,
+    socks5_proxy: Option<&str>,
+  ) -> Result<kube::Client, Error> {
+    self
+      .kube_clients
+      .get(shasta_token, k8s_api_url, auth, socks5_proxy)
+      .await
+  }
+
+  /// Run `op` with the cached [`ShastaClient::kube_client`]. If the
+  /// Kubernetes API answers 401 (rotated credentials), the cached client
+  /// is dropped, the credentials are read again and `op` is retried
+  /// once with a fresh client.
+  ///
+  /// # Errors
+  ///
+  /// Returns the error of [`ShastaClient::kube_client`] or of `op`.
+  #[cfg(feature = "k8s-console")]
+  pub async fn with_kube_client<T, F, Fut>(
+    &self,
+    shasta_token: &str,
+    k8s_api_url: &str,
+    auth: &KubeAuth,
+    socks5_proxy: Option<&str>,
+    op: F,
+  ) -> Result<T, Error>
+  where
+    F: Fn(kube::Client) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+  {
+    let client = self
+      .kube_client(shasta_token, k8s_api_url, auth, socks5_proxy)
+      .await?;
+
+    match op(client).await {
+      Err(e) if kubernetes::is_unauthorized(&e) => {
+        log::info!(
+          "Kubernetes API '{k8s_api_url}' rejected cached credentials, refreshing them"
+        );
+        self.kube_clients.invalidate(
+          shasta_token,
+          k8s_api_url,
+          auth,
+          socks5_proxy,
+        );
+        let client = self
+          .kube_client(shasta_token, k8s_api_url, auth, socks5_proxy)
+          .await?;
+        op(client).await
+      }
+      result => result,
+    }
+  }
+
+  /// Run `fut` with this client as the one csm-rs calls to its base URL
+  /// go through: functions taking connection details instead of a
+  /// `ShastaClient` reuse it, and requests sent to its base URL get their
+  /// bearer token from its [`AuthTokenProvider`], if any, retried once
+  /// with a new token if CSM answers 401.
+  pub async fn scope<F: Future>(&self, fut: F) -> F::Output {
+    SCOPED_CLIENT.scope(self.clone(), fut).await
+  }
+
+  /// The client of the enclosing [`ShastaClient::scope`] if it talks to
+  /// `base_url`, else a new one with the [`ClientConfig`] of the scoped
+  /// client but trusting `root_cert` and going through `socks5_proxy`
+  /// when given, or outside a scope, built from the arguments like
+  /// [`ShastaClient::new`]. Used by the functions taking connection
+  /// details, so they keep the caller's TLS settings and token provider.
+  ///
+  /// # Errors
+  ///
+  /// Returns the error of [`ShastaClient::with_config`].
+  pub(crate) fn scoped_or_new(
+    base_url: impl Into<String>,
+    root_cert: impl Into<Vec<u8>>,
+    socks5_proxy: Option<String>,
+  ) -> Result<Self, Error> {
+    let base_url = base_url.into();
+    match current() {
+      Some(client) if client.serves(&base_url) => Ok(client),
+      Some(client) => {
+        // Another system: its certificate and proxy may differ from the
+        // scoped client's
+        let root_cert = root_cert.into();
+        let mut config = client.config;
+        if !root_cert.is_empty() {
+          config = config.without_ca_certs().with_ca_certs(root_cert);
+        }
+        if let Some(proxy) = socks5_proxy {
+          config = config.with_proxy(proxy);
+        }
+        Self::with_config(base_url, config)
+      }
+      None => Self::new(base_url, root_cert, socks5_proxy),
+    }
+  }
+
+  pub(crate) fn http(&self) -> http::CsmHttp<'_> {
+    http::CsmHttp::new(
+      &self.http,
+      self.token_provider.as_ref(),
+      Some(self.config.rate_limiter()),
+    )
+  }
+
+  /// Whether `url` is under the base URL of the client.
+  fn serves(&self, url: &str) -> bool {
+    url
+      .strip_prefix(self.base_url.trim_end_matches('/'))
+      .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+  }
+}
+
+/// Client of the enclosing [`ShastaClient::scope`], if any.
+pub(crate) fn current() -> Option<ShastaClient> {
+  SCOPED_CLIENT.try_with(Clone::clone).ok()
+}
+
+/// `f` of the [`ClientConfig`] of the client of the enclosing
+/// [`ShastaClient::scope`], if any.
+pub(crate) fn scoped_config<T>(
+  f: impl FnOnce(&ClientConfig) -> T,
+) -> Option<T> {
+  SCOPED_CLIENT.try_with(|client| f(&client.config)).ok()
+}
+
+/// Client of the enclosing [`ShastaClient::scope`] if `url` is under its
+/// base URL.
+pub(crate) fn current_for(url: &str) -> Option<ShastaClient> {
+  current().filter(|client| client.serves(url))
+}
+
+/// Carry the enclosing [`ShastaClient::scope`], if any, into `fut`, for
+/// futures handed to `tokio::spawn`.
+pub(crate) fn propagate<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+  let client_opt = current();
+
+  async move {
+    match client_opt {
+      Some(client) => SCOPED_CLIENT.scope(client, fut).await,
+      None => fut.await,
+    }
   }
 }
 
@@ -145,6 +466,24 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
     assert_eq!(client.socks5_proxy(), Some("socks5://localhost:9050"));
   }
 
+  #[test]
+  fn with_config_trusts_every_ca_and_uses_the_proxy() {
+    let client = ShastaClient::with_config(
+      "https://api.example.com",
+      ClientConfig::new()
+        .with_ca_certs(TEST_PEM)
+        .with_ca_certs(TEST_PEM)
+        .with_proxy("https://proxy.example.com:3128"),
+    )
+    .expect("client with CA bundle and proxy should succeed");
+
+    assert_eq!(client.root_cert(), [TEST_PEM, TEST_PEM].concat().as_bytes());
+    assert_eq!(
+      client.socks5_proxy(),
+      Some("https://proxy.example.com:3128")
+    );
+  }
+
   #[test]
   fn new_with_invalid_proxy_url_fails() {
     let result = ShastaClient::new(
@@ -174,6 +513,121 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
     assert_eq!(client.socks5_proxy(), cloned.socks5_proxy());
   }
 
+  #[tokio::test]
+  async fn token_comes_from_the_token_provider() {
+    let client = ShastaClient::new(
+      "https://api.example.com",
+      TEST_PEM.as_bytes().to_vec(),
+      None,
+    )
+    .unwrap();
+    assert!(client.token().await.is_err());
+    assert_eq!(client.fresh_token("explicit").await.unwrap(), "explicit");
+
+    let client =
+      client.with_token_provider(Arc::new(crate::StaticToken::new("provided")));
+    assert_eq!(client.token().await.unwrap(), "provided");
+    assert_eq!(client.fresh_token("explicit").await.unwrap(), "provided");
+  }
+
+  #[tokio::test]
+  async fn scoped_or_new_reuses_the_scoped_client_of_the_same_url() {
+    let client = ShastaClient::new(
+      "https://api.example.com",
+      TEST_PEM.as_bytes().to_vec(),
+      None,
+    )
+    .unwrap()
+    .with_token_provider(Arc::new(crate::StaticToken::new("provided")));
+
+    client
+      .scope(async {
+        let scoped =
+          ShastaClient::scoped_or_new("https://api.example.com", "", None)
+            .unwrap();
+        assert!(scoped.token_provider().is_some());
+        assert_eq!(scoped.root_cert(), TEST_PEM.as_bytes());
+
+        let other =
+          ShastaClient::scoped_or_new("https://api.example.org", "", None)
+            .unwrap();
+        assert!(other.token_provider().is_none());
+        assert_eq!(other.root_cert(), TEST_PEM.as_bytes());
+        assert!(current_for("https://api.example.com.evil/apis").is_none());
+      })
+      .await;
+  }
+
+  #[tokio::test]
+  async fn settings_of_the_scoped_client_apply_in_its_scope() {
+    let naming_policy = crate::NamingPolicy {
+      cfs_session_name: "{user}-{configuration}".to_string(),
+      ..Default::default()
+    };
+    let client = ShastaClient::with_config(
+      "https://api.example.com",
+      ClientConfig::new().with_naming_policy(naming_policy.clone()),
+    )
+    .unwrap();
+
+    assert_eq!(
+      crate::common::naming::current(),
+      crate::NamingPolicy::default()
+    );
+    client
+      .scope(async {
+        assert_eq!(crate::common::naming::current(), naming_policy);
+      })
+      .await;
+  }
+
+  #[tokio::test]
+  async fn scoped_or_new_trusts_the_given_cert_for_another_url() {
+    // Self-signed, unrelated to TEST_PEM
+    const OTHER_PEM: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBjTCCATOgAwIBAgIUA2ZOwVLGYcyKKZVMgCDkFumoQxwwCgYIKoZIzj0EAwIw\n\
+GzEZMBcGA1UEAwwQa3ViZXJuZXRlcy1hZG1pbjAgFw0yNjEwMTYxNDA1NTJaGA8y\n\
+MTI2MDkyMjE0MDU1MlowGzEZMBcGA1UEAwwQa3ViZXJuZXRlcy1hZG1pbjBZMBMG\n\
+ByqGSM49AgEGCCqGSM49AwEHA0IABMuLJ3ytog33CF7IktrFrV8olJDihl7OUPSS\n\
+eNSSs/wo6NcQ6j6FQQjycwNRhH0w5VDWy/r2BylA+gNjMjnqYv2jUzBRMB0GA1Ud\n\
+DgQWBBTFjHLKqhmlALAzMg+Ew+RkE/IUbTAfBgNVHSMEGDAWgBTFjHLKqhmlALAz\n\
+Mg+Ew+RkE/IUbTAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0gAMEUCIG7V\n\
+f1WOvlnFdV57d/4wpYWanCaHpk2BQl7FTy1V4UuGAiEA5aurZY5/xg4GoBbnvyEp\n\
+AuBLE7LBRCI8KRyT4ueNr6k=\n\
+-----END CERTIFICATE-----\n";
+
+    let client = ShastaClient::with_config(
+      "https://api.example.com",
+      ClientConfig::new()
+        .with_ca_certs(TEST_PEM)
+        .with_proxy("http://proxy.example.com:3128")
+        .with_rate_limit(None),
+    )
+    .unwrap();
+
+    client
+      .scope(async {
+        let other = ShastaClient::scoped_or_new(
+          "https://api.example.org",
+          OTHER_PEM,
+          Some("socks5h://127.0.0.1:1080".to_string()),
+        )
+        .unwrap();
+        assert_eq!(other.root_cert(), OTHER_PEM.as_bytes());
+        assert_eq!(other.socks5_proxy(), Some("socks5h://127.0.0.1:1080"));
+        assert_eq!(other.config().rate_limit(), None);
+
+        let same = ShastaClient::scoped_or_new(
+          "https://api.example.com",
+          OTHER_PEM,
+          None,
+        )
+        .unwrap();
+        assert_eq!(same.root_cert(), TEST_PEM.as_bytes());
+      })
+      .await;
+  }
+
   #[test]
   fn accepts_owned_and_borrowed_strings_via_into() {
     // String
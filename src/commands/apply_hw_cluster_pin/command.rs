@@ -3,10 +3,12 @@
 use std::collections::HashMap;
 
 use crate::{
+  ExecutionContext,
   commands::apply_hw_cluster_pin::utils::{
     calculate_hsm_hw_component_summary, get_hsm_node_hw_component_counter,
     resolve_hw_description_to_xnames,
   },
+  common::events,
   error::Error,
   hsm::{self, group::types::Group},
 };
@@ -33,11 +35,44 @@ use crate::{
 ///
 /// # Errors
 ///
-/// Returns an [`Error`] variant on CSM, transport, or
-/// deserialization failure; see the crate-level `Error` enum
-/// for the full set.
+/// Returns an [`Error`] variant if `execution_context` can't act on
+/// both groups, or on CSM, transport, or deserialization failure; see
+/// the crate-level `Error` enum for the full set.
 #[allow(clippy::too_many_arguments)]
 pub async fn exec(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  target_hsm_group_name: &str,
+  parent_hsm_group_name: &str,
+  pattern: &str,
+  nodryrun: bool,
+  create_target_hsm_group: bool,
+  delete_empty_parent_hsm_group: bool,
+) -> Result<(), Error> {
+  let _timer =
+    crate::common::metrics::CommandTimer::start("apply_hw_cluster_pin");
+
+  execution_context.check_owns(target_hsm_group_name)?;
+  execution_context.check_owns(parent_hsm_group_name)?;
+
+  client
+    .scope(execution_context.scope(pin(
+      client,
+      shasta_token,
+      target_hsm_group_name,
+      parent_hsm_group_name,
+      pattern,
+      nodryrun,
+      create_target_hsm_group,
+      delete_empty_parent_hsm_group,
+    )))
+    .await
+}
+
+/// Body of [`exec`], run inside its execution context.
+#[allow(clippy::too_many_arguments)]
+async fn pin(
   client: &crate::ShastaClient,
   shasta_token: &str,
   target_hsm_group_name: &str,
@@ -55,7 +90,7 @@ pub async fn exec(
 
   let pattern = format!("{target_hsm_group_name}:{pattern}");
 
-  log::info!("pattern: {pattern}");
+  log::debug!("pattern: {pattern}");
 
   // lcm -> used to normalize and quantify memory capacity
   let mem_lcm = 16384; // 1024 * 16
@@ -92,7 +127,7 @@ pub async fn exec(
       .insert(hw_component_counter[0].to_string(), count);
   }
 
-  log::info!(
+  log::debug!(
     "User defined hw components with counters: {user_defined_target_hsm_hw_component_count_hashmap:?}"
   );
 
@@ -121,16 +156,16 @@ pub async fn exec(
     }
     Err(_) => {
       if create_target_hsm_group {
-        log::info!(
+        events::info(format!(
           "Target HSM group {target_hsm_group_name} does not exist, but the option to create the group has been selected, creating it now."
-        );
+        ));
         if nodryrun {
           // Post-progenitor field shapes: `label` and `exclusive_group`
           // wrap a `String` in `ResourceName(pub String)`; `tags` is
           // a `Vec<ResourceName>` (no `Option`).
-          use crate::hsm::group::types::ResourceName;
+          use crate::hsm::group::types::{GroupLabel, ResourceName};
           let group = Group {
-            label: ResourceName(target_hsm_group_name.to_string()),
+            label: GroupLabel::for_new_group(target_hsm_group_name)?.into(),
             description: None,
             tags: vec![],
             members: None,
@@ -138,6 +173,7 @@ pub async fn exec(
           };
 
           let _ = shasta_client.hsm_group_post(shasta_token, group).await?;
+          events::created("HSM group", target_hsm_group_name);
         } else {
           return Err(Error::ValidationFailed(
             "Dryrun selected, cannot create the new group and continue.",
@@ -291,11 +327,42 @@ pub async fn exec(
     .map(|(xname, _)| xname)
     .collect::<Vec<String>>();
 
+  // *********************************************************************************************************
+  // DISABLE NODES CHANGING GROUP IN CSM
+
+  // Nodes moving between the parent and target groups are disabled in HSM while their
+  // membership changes so monitoring doesn't alarm on them, then re-enabled below.
+  let moved_hsm_node_vec: Vec<String> = target_hsm_node_vec
+    .iter()
+    .filter(|xname| !target_hsm_group_member_vec.contains(xname))
+    .chain(
+      target_hsm_group_member_vec
+        .iter()
+        .filter(|xname| !target_hsm_node_vec.contains(xname)),
+    )
+    .cloned()
+    .collect();
+
+  let disabled_hsm_node_vec = if nodryrun && !moved_hsm_node_vec.is_empty() {
+    events::step(format!(
+      "Disabling {} nodes changing HSM group",
+      moved_hsm_node_vec.len()
+    ));
+    hsm::component::disable_for_service(
+      shasta_client,
+      shasta_token,
+      &moved_hsm_node_vec,
+    )
+    .await?
+  } else {
+    Vec::new()
+  };
+
   // *********************************************************************************************************
   // UPDATE TARGET HSM GROUP IN CSM
-  log::info!(
+  events::step(format!(
     "Updating target HSM group '{target_hsm_group_name}' members"
-  );
+  ));
   if nodryrun {
     // The target HSM group will never be empty, the way the pattern works it'll always
     // contain at least one node, so there is no need to add code to delete it if it's empty.
@@ -316,14 +383,16 @@ pub async fn exec(
     )
     .await;
   } else {
-    log::info!("Dry run enabled, not modifying the HSM groups on the system.");
+    events::info(
+      "Dry run enabled, not modifying the HSM groups on the system.",
+    );
   }
 
   // *********************************************************************************************************
   // UPDATE PARENT GROUP IN CSM
-  log::info!(
+  events::step(format!(
     "Updating parent HSM group '{parent_hsm_group_name}' members"
-  );
+  ));
   if nodryrun {
     // The parent group might be out of resources after applying this, so it's safe to check
     // if there are still nodes there and, delete it after moving out the resources.
@@ -347,14 +416,14 @@ pub async fn exec(
     .await;
     if parent_group_will_be_empty {
       if delete_empty_parent_hsm_group {
-        log::info!(
+        events::info(format!(
           "Parent HSM group {parent_hsm_group_name} is now empty and the option to delete empty groups has been selected, removing it."
-        );
+        ));
         match shasta_client
           .hsm_group_delete_group(shasta_token, parent_hsm_group_name)
           .await
         {
-          Ok(_) => log::info!("HSM group removed successfully."),
+          Ok(_) => events::deleted("HSM group", parent_hsm_group_name),
           Err(e2) => log::debug!(
             "Error removing the HSM group. This always fails, ignore please. Reported: {e2}"
           ),
@@ -366,8 +435,23 @@ pub async fn exec(
       }
     }
   } else {
-    log::info!("Dry run enabled, not modifying the HSM groups on the system.");
+    events::info(
+      "Dry run enabled, not modifying the HSM groups on the system.",
+    );
   }
+
+  // *********************************************************************************************************
+  // RE-ENABLE NODES CHANGING GROUP IN CSM
+  if !disabled_hsm_node_vec.is_empty() {
+    events::step(format!(
+      "Re-enabling {} nodes changing HSM group",
+      disabled_hsm_node_vec.len()
+    ));
+    shasta_client
+      .hsm_component_patch_enabled(shasta_token, &disabled_hsm_node_vec, true)
+      .await?;
+  }
+
   // *********************************************************************************************************
   // RETURN VALUES
 
@@ -375,9 +459,9 @@ pub async fn exec(
   // PRINT SOLUTIONS
 
   // Print target HSM data
-  log::info!(
+  events::info(format!(
     "HSM '{target_hsm_group_name}' hw component summary: {target_hsm_hw_component_summary_hashmap:?}"
-  );
+  ));
 
   let target_hsm_group_value = serde_json::json!({
       "label": target_hsm_group_name,
@@ -386,16 +470,15 @@ pub async fn exec(
       "tags": []
   });
 
-  log::info!(
-    "{}",
+  events::info(
     serde_json::to_string_pretty(&target_hsm_group_value)
-      .expect("infallible: json!{} -> string")
+      .expect("infallible: json!{} -> string"),
   );
 
   // Print parent HSM data
-  log::info!(
+  events::info(format!(
     "HSM '{parent_hsm_group_name}' hw component summary: {parent_hsm_hw_component_summary_hashmap:?}"
-  );
+  ));
 
   let parent_hsm_group_value = serde_json::json!({
       "label": parent_hsm_group_name,
@@ -404,10 +487,9 @@ pub async fn exec(
       "tags": []
   });
 
-  log::info!(
-    "{}",
+  events::info(
     serde_json::to_string_pretty(&parent_hsm_group_value)
-      .expect("infallible: json!{} -> string")
+      .expect("infallible: json!{} -> string"),
   );
 
   Ok(())
@@ -531,7 +531,7 @@ pub async fn get_node_hw_component_count(
   hsm_member: &str,
   user_defined_hw_profile_vec: Vec<String>,
 ) -> Result<(String, Vec<String>, Vec<u64>), Error> {
-  let hw_inventory = crate::ShastaClient::new(
+  let hw_inventory = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
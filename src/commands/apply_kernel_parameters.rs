@@ -0,0 +1,263 @@
+//! Change the kernel parameters of every node of an HSM group.
+//!
+//! The new parameters are written to BSS, so they take effect the next
+//! time the nodes boot. Nodes whose kernel command line is already as
+//! asked are left alone. The changed nodes can be staged for reboot
+//! with a BOS staged session: BOS records the reboot against the nodes
+//! and runs it when an administrator applies the staged state.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::{
+  ExecutionContext, ShastaClient,
+  bos::{BosSession, Operation},
+  bss::types::BootParameters,
+  common::events,
+  error::Error,
+  hsm::group::GroupExt,
+};
+
+/// How [`exec`] combines the given kernel parameters with the ones
+/// already in BSS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelParamsMode {
+  /// Add the parameters, overwriting the value of those already set.
+  Add,
+  /// Replace the whole kernel command line with the parameters.
+  Replace,
+  /// Remove the parameters; values are ignored, `key` and `key=value`
+  /// both remove `key`.
+  Remove,
+}
+
+impl KernelParamsMode {
+  /// Apply `kernel_params` to `boot_parameters` in this mode. Returns
+  /// whether the kernel command line changed, regardless of parameter
+  /// order.
+  #[must_use]
+  pub fn apply(
+    self,
+    boot_parameters: &mut BootParameters,
+    kernel_params: &str,
+  ) -> bool {
+    let before = param_set(&boot_parameters.params);
+
+    match self {
+      Self::Add => {
+        boot_parameters.update_kernel_params(kernel_params);
+        boot_parameters.add_kernel_params(kernel_params);
+      }
+      Self::Replace => {
+        boot_parameters.apply_kernel_params(kernel_params);
+      }
+      Self::Remove => {
+        boot_parameters.delete_kernel_params(kernel_params);
+      }
+    }
+
+    before != param_set(&boot_parameters.params)
+  }
+}
+
+/// What [`exec`] changed.
+#[derive(Debug, Default, Serialize)]
+pub struct KernelParamsChange {
+  /// Nodes whose kernel parameters changed (or would change, on a dry
+  /// run), sorted.
+  pub changed: Vec<String>,
+  /// Nodes whose kernel parameters were already as asked, sorted.
+  pub unchanged: Vec<String>,
+  /// Group members BSS has no boot parameters for, sorted.
+  pub missing_boot_params: Vec<String>,
+  /// BOS staged session created for the changed nodes, if any.
+  pub staged_session: Option<BosSession>,
+}
+
+/// Apply `kernel_params` in `mode` to the BSS boot parameters of every
+/// member of HSM group `hsm_group_name`.
+///
+/// With `stage_reboot_template` set, a BOS staged `reboot` session with
+/// that session template is created for the changed nodes, so they pick
+/// up the new parameters when the staged state is applied. Nothing is
+/// written on a dry run.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant if `execution_context` can't act on
+/// `hsm_group_name`, or on CSM, transport, or deserialization failure;
+/// see the crate-level `Error` enum for the full set. Nodes updated
+/// before a failing BSS update keep their new parameters.
+#[allow(clippy::too_many_arguments)]
+pub async fn exec(
+  client: &ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  hsm_group_name: &str,
+  kernel_params: &str,
+  mode: KernelParamsMode,
+  stage_reboot_template: Option<&str>,
+  dry_run: bool,
+) -> Result<KernelParamsChange, Error> {
+  let _timer =
+    crate::common::metrics::CommandTimer::start("apply_kernel_parameters");
+
+  execution_context.check_owns(hsm_group_name)?;
+
+  client
+    .scope(execution_context.scope(apply(
+      client,
+      shasta_token,
+      hsm_group_name,
+      kernel_params,
+      mode,
+      stage_reboot_template,
+      dry_run,
+    )))
+    .await
+}
+
+/// Body of [`exec`], run inside its execution context.
+async fn apply(
+  client: &ShastaClient,
+  shasta_token: &str,
+  hsm_group_name: &str,
+  kernel_params: &str,
+  mode: KernelParamsMode,
+  stage_reboot_template: Option<&str>,
+  dry_run: bool,
+) -> Result<KernelParamsChange, Error> {
+  events::step(format!("Resolving members of HSM group '{hsm_group_name}'"));
+  let member_vec = client
+    .hsm_group_get_one(shasta_token, hsm_group_name)
+    .await?
+    .get_members();
+
+  let boot_parameters_vec = client
+    .bss_bootparameters_get_multiple(shasta_token, &member_vec)
+    .await?;
+
+  let mut change = KernelParamsChange::default();
+  let mut changed_boot_parameters_vec = Vec::new();
+
+  for mut boot_parameters in boot_parameters_vec {
+    let hosts = boot_parameters.hosts.clone();
+    if mode.apply(&mut boot_parameters, kernel_params) {
+      change.changed.extend(hosts);
+      changed_boot_parameters_vec.push(boot_parameters);
+    } else {
+      change.unchanged.extend(hosts);
+    }
+  }
+
+  let seen: BTreeSet<&String> =
+    change.changed.iter().chain(&change.unchanged).collect();
+  change.missing_boot_params = member_vec
+    .iter()
+    .filter(|xname| !seen.contains(xname))
+    .cloned()
+    .collect();
+  change.changed.sort();
+  change.unchanged.sort();
+  change.missing_boot_params.sort();
+
+  if !change.missing_boot_params.is_empty() {
+    events::warning(format!(
+      "No BSS boot parameters for {}",
+      change.missing_boot_params.join(", ")
+    ));
+  }
+
+  if change.changed.is_empty() {
+    events::info("Kernel parameters already up to date");
+    return Ok(change);
+  }
+
+  if dry_run {
+    events::info(format!(
+      "Dry run: would update kernel parameters of {}",
+      change.changed.join(", ")
+    ));
+    return Ok(change);
+  }
+
+  events::step(format!(
+    "Updating kernel parameters of {} nodes",
+    change.changed.len()
+  ));
+  for boot_parameters in changed_boot_parameters_vec {
+    client
+      .bss_bootparameters_put(shasta_token, boot_parameters)
+      .await?;
+  }
+
+  if let Some(template_name) = stage_reboot_template {
+    let bos_session = BosSession {
+      name: None,
+      tenant: None,
+      operation: Some(Operation::Reboot),
+      template_name: template_name.to_string(),
+      limit: Some(change.changed.join(",")),
+      stage: Some(true),
+      include_disabled: None,
+      status: None,
+      components: None,
+    };
+
+    let created = client
+      .bos_session_v2_post(shasta_token, bos_session)
+      .await?;
+    events::created(
+      "BOS staged session",
+      created.name.as_deref().unwrap_or_default(),
+    );
+    change.staged_session = Some(created);
+  }
+
+  Ok(change)
+}
+
+/// Kernel parameters of `params`, ignoring order.
+fn param_set(params: &str) -> BTreeSet<&str> {
+  params.split_whitespace().collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn boot_parameters(params: &str) -> BootParameters {
+    BootParameters {
+      hosts: vec!["x1000c0s0b0n0".to_string()],
+      macs: None,
+      nids: None,
+      params: params.to_string(),
+      kernel: String::new(),
+      initrd: String::new(),
+      cloud_init: None,
+    }
+  }
+
+  #[test]
+  fn kernel_params_mode_reports_changes() {
+    let mut bp = boot_parameters("quiet console=ttyS0 crashkernel=512M");
+    assert!(!KernelParamsMode::Add.apply(&mut bp, "console=ttyS0 quiet"));
+    assert!(KernelParamsMode::Add.apply(&mut bp, "console=tty1 nosmt"));
+    assert_eq!(
+      param_set(&bp.params),
+      BTreeSet::from(["console=tty1", "crashkernel=512M", "nosmt", "quiet"])
+    );
+
+    assert!(!KernelParamsMode::Remove.apply(&mut bp, "hugepages"));
+    assert!(KernelParamsMode::Remove.apply(&mut bp, "nosmt crashkernel"));
+    assert_eq!(
+      param_set(&bp.params),
+      BTreeSet::from(["console=tty1", "quiet"])
+    );
+
+    assert!(!KernelParamsMode::Replace.apply(&mut bp, "quiet console=tty1"));
+    assert!(KernelParamsMode::Replace.apply(&mut bp, "quiet"));
+    assert_eq!(bp.params, "quiet");
+  }
+}
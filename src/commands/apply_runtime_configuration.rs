@@ -0,0 +1,412 @@
+//! Set the runtime CFS configuration of a set of nodes and follow the
+//! CFS batcher until it has configured them.
+//!
+//! CFS doesn't run a session for a runtime configuration change right
+//! away: the CFS batcher picks up components whose desired
+//! configuration isn't applied yet, groups them in batches of up to
+//! `batch_size` nodes and starts a session per batch once
+//! `batch_window` has passed. A component whose sessions failed
+//! `retry_policy` times is marked failed and left alone until its
+//! error count is cleared. [`exec`] sets the desired configuration,
+//! enables the components and then polls their configuration status.
+
+use std::{collections::BTreeSet, time::Duration};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::{
+  ExecutionContext, ShastaClient,
+  cfs::component::http_client::v3::types::Component,
+  common::{events, poll::WaitOptions},
+  error::Error,
+  hsm::group::GroupExt,
+  node::utils::validate_xname_format,
+};
+
+/// Sensible default for [`exec`]: the batcher may need several batch
+/// windows and Ansible runs, so up to 2 hours, checking after 10 s and
+/// then backing off to 1 min.
+pub const DEFAULT_WAIT: WaitOptions =
+  WaitOptions::new(Duration::from_secs(7200), Duration::from_secs(10))
+    .with_backoff(Duration::from_secs(60));
+
+/// Nodes to configure.
+#[derive(Debug, Clone, Copy)]
+pub enum Target<'a> {
+  /// Every member of an HSM group.
+  HsmGroup(&'a str),
+  /// These xnames.
+  Xnames(&'a [String]),
+}
+
+/// Why [`exec`] stopped watching the CFS batcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum RuntimeConfigurationOutcome {
+  /// No node is pending any more; up to `max_failed` of them failed.
+  Configured,
+  /// More nodes failed than allowed.
+  FailureThreshold,
+  /// Some nodes were still pending when the wait timed out.
+  TimedOut,
+  /// The CFS batcher is disabled, so no node will be configured until
+  /// it is enabled again.
+  BatcherDisabled,
+}
+
+/// Configuration status of the nodes [`exec`] configured.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuntimeConfigurationProgress {
+  /// CFS configuration the nodes were set to.
+  pub configuration: String,
+  /// Nodes configured with `configuration`, sorted.
+  pub configured: Vec<String>,
+  /// Nodes CFS failed to configure, sorted.
+  pub failed: Vec<String>,
+  /// Nodes still waiting for the batcher, or being configured, sorted.
+  pub pending: Vec<String>,
+  /// Why [`exec`] stopped watching.
+  pub outcome: RuntimeConfigurationOutcome,
+}
+
+/// Set `configuration` as the desired configuration of the `target`
+/// nodes, enable their CFS components, and follow the CFS batcher as
+/// `wait_options` says until every node is configured or more than
+/// `max_failed` nodes failed.
+///
+/// With `clear_error_count`, the components' error counts are reset so
+/// the batcher retries nodes that already used up their retries.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant if `configuration` or the HSM group
+/// doesn't exist, an xname is malformed, `execution_context` can't act
+/// on the `target` nodes, or on CSM, transport, or deserialization
+/// failure; see the crate-level `Error` enum for the full set. Nodes
+/// failing or the wait timing out are reported in
+/// [`RuntimeConfigurationProgress::outcome`], not as errors.
+#[allow(clippy::too_many_arguments)]
+pub async fn exec(
+  client: &ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  target: Target<'_>,
+  configuration: &str,
+  clear_error_count: bool,
+  max_failed: usize,
+  wait_options: WaitOptions,
+) -> Result<RuntimeConfigurationProgress, Error> {
+  let _timer =
+    crate::common::metrics::CommandTimer::start("apply_runtime_configuration");
+
+  let xname_vec = match target {
+    Target::HsmGroup(hsm_group_name) => {
+      execution_context.check_owns(hsm_group_name)?;
+      client
+        .hsm_group_get_one(shasta_token, hsm_group_name)
+        .await?
+        .get_members()
+    }
+    Target::Xnames(xname_vec) => {
+      if let Some(xname) =
+        xname_vec.iter().find(|xname| !validate_xname_format(xname))
+      {
+        return Err(Error::Message(format!("Invalid xname '{xname}'")));
+      }
+      check_xnames_owned(client, shasta_token, execution_context, xname_vec)
+        .await?;
+      xname_vec.to_vec()
+    }
+  };
+
+  client
+    .scope(execution_context.scope(apply(
+      client,
+      shasta_token,
+      xname_vec,
+      configuration,
+      clear_error_count,
+      max_failed,
+      wait_options,
+    )))
+    .await
+}
+
+/// Fail unless every xname of `xname_vec` belongs to a group of the
+/// tenancy of `execution_context`.
+async fn check_xnames_owned(
+  client: &ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  xname_vec: &[String],
+) -> Result<(), Error> {
+  let Some(group_vec) = execution_context.tenancy().groups() else {
+    return Ok(());
+  };
+
+  let member_vec = client
+    .scope(crate::hsm::group::utils::get_member_vec_from_hsm_name_vec(
+      shasta_token,
+      client.base_url(),
+      client.root_cert(),
+      client.socks5_proxy(),
+      group_vec,
+    ))
+    .await?;
+
+  match xname_vec.iter().find(|xname| !member_vec.contains(xname)) {
+    Some(xname) => execution_context.check_owns(xname),
+    None => Ok(()),
+  }
+}
+
+/// Body of [`exec`], run inside its execution context.
+async fn apply(
+  client: &ShastaClient,
+  shasta_token: &str,
+  xname_vec: Vec<String>,
+  configuration: &str,
+  clear_error_count: bool,
+  max_failed: usize,
+  wait_options: WaitOptions,
+) -> Result<RuntimeConfigurationProgress, Error> {
+  // Fails if the configuration doesn't exist
+  client
+    .cfs_configuration_v3_get(shasta_token, Some(configuration))
+    .await?;
+
+  let cfs_options = client.cfs_component_v3_get_options(shasta_token).await?;
+  let batcher_disabled = cfs_options
+    .get("batcher_disable")
+    .and_then(Value::as_bool)
+    .unwrap_or(false);
+
+  if !clear_error_count {
+    warn_retries_used_up(client, shasta_token, &xname_vec, &cfs_options)
+      .await?;
+  }
+
+  events::step(format!(
+    "Setting runtime configuration '{configuration}' on {} nodes",
+    xname_vec.len()
+  ));
+  let component_list = xname_vec
+    .iter()
+    .map(|xname| Component {
+      id: Some(xname.clone()),
+      state: None,
+      desired_config: Some(configuration.to_string()),
+      error_count: clear_error_count.then_some(0),
+      retry_policy: None,
+      enabled: Some(true),
+      configuration_status: None,
+      tags: None,
+      logs: None,
+    })
+    .collect();
+  client
+    .cfs_component_v3_patch_component_list(shasta_token, component_list)
+    .await?;
+
+  if batcher_disabled {
+    events::warning(
+      "CFS batcher is disabled, nodes won't be configured until it is enabled",
+    );
+    return Ok(RuntimeConfigurationProgress {
+      configuration: configuration.to_string(),
+      configured: Vec::new(),
+      failed: Vec::new(),
+      pending: sorted(xname_vec),
+      outcome: RuntimeConfigurationOutcome::BatcherDisabled,
+    });
+  }
+
+  events::info(format!(
+    "CFS batcher runs up to {} nodes per session, {}s after a node needs configuration",
+    cfs_options
+      .get("batch_size")
+      .and_then(Value::as_u64)
+      .unwrap_or_default(),
+    cfs_options
+      .get("batch_window")
+      .and_then(Value::as_u64)
+      .unwrap_or_default(),
+  ));
+
+  let deadline = Instant::now() + wait_options.timeout;
+  let mut delay = wait_options.poll_interval;
+  let mut last_counts = None;
+
+  loop {
+    let component_vec = client
+      .cfs_component_v3_get_query_batch(shasta_token, None, &xname_vec, None)
+      .await?;
+    let mut progress = tally(configuration, &xname_vec, &component_vec);
+
+    let counts = (
+      progress.configured.len(),
+      progress.failed.len(),
+      progress.pending.len(),
+    );
+    if last_counts != Some(counts) {
+      events::info(format!(
+        "CFS batcher: {} configured, {} failed, {} pending",
+        counts.0, counts.1, counts.2
+      ));
+      last_counts = Some(counts);
+    }
+
+    if progress.failed.len() > max_failed {
+      events::warning(format!(
+        "Giving up, CFS failed to configure {}",
+        progress.failed.join(", ")
+      ));
+      progress.outcome = RuntimeConfigurationOutcome::FailureThreshold;
+      return Ok(progress);
+    }
+
+    if progress.pending.is_empty() {
+      return Ok(progress);
+    }
+
+    if Instant::now() + delay > deadline {
+      events::warning(format!(
+        "Nodes still pending after {}s: {}",
+        wait_options.timeout.as_secs(),
+        progress.pending.join(", ")
+      ));
+      progress.outcome = RuntimeConfigurationOutcome::TimedOut;
+      return Ok(progress);
+    }
+
+    tokio::time::sleep(delay).await;
+    delay = wait_options.next_delay(delay);
+  }
+}
+
+/// Warn about the `xname_vec` components the batcher won't retry
+/// because their error count reached their retry policy.
+async fn warn_retries_used_up(
+  client: &ShastaClient,
+  shasta_token: &str,
+  xname_vec: &[String],
+  cfs_options: &Value,
+) -> Result<(), Error> {
+  let default_retry_policy = cfs_options
+    .get("default_batcher_retry_policy")
+    .and_then(Value::as_u64);
+
+  let mut used_up: Vec<String> = client
+    .cfs_component_v3_get_query_batch(shasta_token, None, xname_vec, None)
+    .await?
+    .into_iter()
+    .filter(|component| {
+      match (
+        component.error_count,
+        component.retry_policy.or(default_retry_policy),
+      ) {
+        (Some(error_count), Some(retry_policy)) => error_count >= retry_policy,
+        _ => false,
+      }
+    })
+    .filter_map(|component| component.id)
+    .collect();
+
+  if !used_up.is_empty() {
+    used_up.sort();
+    events::warning(format!(
+      "CFS won't retry {}: error count reached the retry policy; clear the error count to retry them",
+      used_up.join(", ")
+    ));
+  }
+
+  Ok(())
+}
+
+/// Configuration status of `xname_vec` according to `component_vec`.
+/// Nodes with no component, or not yet set to `configuration`, are
+/// pending. `outcome` is [`RuntimeConfigurationOutcome::Configured`].
+fn tally(
+  configuration: &str,
+  xname_vec: &[String],
+  component_vec: &[Component],
+) -> RuntimeConfigurationProgress {
+  let mut configured = Vec::new();
+  let mut failed = Vec::new();
+  let mut seen = BTreeSet::new();
+
+  for component in component_vec {
+    let Some(xname) = component.id.as_ref() else {
+      continue;
+    };
+    if component.desired_config.as_deref() != Some(configuration) {
+      continue;
+    }
+    match component.configuration_status.as_deref() {
+      Some("configured") => configured.push(xname.clone()),
+      Some("failed") => failed.push(xname.clone()),
+      _ => continue,
+    }
+    seen.insert(xname);
+  }
+
+  let pending = xname_vec
+    .iter()
+    .filter(|xname| !seen.contains(xname))
+    .cloned()
+    .collect();
+
+  RuntimeConfigurationProgress {
+    configuration: configuration.to_string(),
+    configured: sorted(configured),
+    failed: sorted(failed),
+    pending: sorted(pending),
+    outcome: RuntimeConfigurationOutcome::Configured,
+  }
+}
+
+fn sorted(mut xname_vec: Vec<String>) -> Vec<String> {
+  xname_vec.sort();
+  xname_vec
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn component(xname: &str, desired_config: &str, status: &str) -> Component {
+    Component {
+      id: Some(xname.to_string()),
+      state: None,
+      desired_config: Some(desired_config.to_string()),
+      error_count: None,
+      retry_policy: None,
+      enabled: Some(true),
+      configuration_status: Some(status.to_string()),
+      tags: None,
+      logs: None,
+    }
+  }
+
+  #[test]
+  fn tally_counts_only_the_new_configuration() {
+    let xname_vec: Vec<String> = ["x1", "x2", "x3", "x4", "x5"]
+      .into_iter()
+      .map(String::from)
+      .collect();
+    let component_vec = [
+      component("x2", "new", "configured"),
+      component("x1", "new", "failed"),
+      component("x3", "new", "pending"),
+      // Not picked up the new desired configuration yet
+      component("x4", "old", "configured"),
+    ];
+
+    let progress = tally("new", &xname_vec, &component_vec);
+
+    assert_eq!(progress.configured, ["x2"]);
+    assert_eq!(progress.failed, ["x1"]);
+    assert_eq!(progress.pending, ["x3", "x4", "x5"]);
+  }
+}
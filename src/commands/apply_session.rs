@@ -1,9 +1,14 @@
 //! Run a CFS session against a set of nodes.
 
 use crate::{
+  ExecutionContext,
   cfs::{
     self, v2::CfsSessionPostRequest, v3::CfsConfigurationRequest,
   },
+  common::{
+    events,
+    naming::{self, NamingContext},
+  },
   error::Error,
   node::utils::validate_xnames_format_and_membership_against_single_hsm,
 };
@@ -16,15 +21,16 @@ use serde_json::Value;
 ///
 /// # Errors
 ///
-/// Returns an [`Error`] variant on CSM, transport, or
-/// deserialization failure; see the crate-level `Error` enum
-/// for the full set.
+/// Returns an [`Error`] variant if `execution_context` can't act on
+/// `hsm_group`, or on CSM, transport, or deserialization failure; see
+/// the crate-level `Error` enum for the full set.
 #[allow(clippy::too_many_arguments)]
 pub async fn exec(
   client: &crate::ShastaClient,
   gitea_token: &str,
   gitea_base_url: &str,
   shasta_token: &str,
+  execution_context: &ExecutionContext,
   cfs_conf_sess_name: Option<&str>,
   playbook_yaml_file_name_opt: Option<&str>,
   hsm_group: Option<&str>,
@@ -34,6 +40,46 @@ pub async fn exec(
   ansible_verbosity: Option<&str>,
   ansible_passthrough: Option<&str>,
   // watch_logs: bool,
+) -> Result<(String, String), Error> {
+  let _timer = crate::common::metrics::CommandTimer::start("apply_session");
+
+  if let Some(hsm_group) = hsm_group {
+    execution_context.check_owns(hsm_group)?;
+  }
+
+  client
+    .scope(execution_context.scope(apply(
+      client,
+      gitea_token,
+      gitea_base_url,
+      shasta_token,
+      cfs_conf_sess_name,
+      playbook_yaml_file_name_opt,
+      hsm_group,
+      repo_name_vec,
+      repo_last_commit_id_vec,
+      ansible_limit,
+      ansible_verbosity,
+      ansible_passthrough,
+    )))
+    .await
+}
+
+/// Body of [`exec`], run inside its execution context.
+#[allow(clippy::too_many_arguments)]
+async fn apply(
+  client: &crate::ShastaClient,
+  gitea_token: &str,
+  gitea_base_url: &str,
+  shasta_token: &str,
+  cfs_conf_sess_name: Option<&str>,
+  playbook_yaml_file_name_opt: Option<&str>,
+  hsm_group: Option<&str>,
+  repo_name_vec: &[&str],
+  repo_last_commit_id_vec: &[&str],
+  ansible_limit: Option<&str>,
+  ansible_verbosity: Option<&str>,
+  ansible_passthrough: Option<&str>,
 ) -> Result<(String, String), Error> {
   let shasta_base_url = client.base_url();
   let shasta_root_cert = client.root_cert();
@@ -145,6 +191,7 @@ pub async fn exec(
       gitea_token,
       gitea_base_url,
       shasta_token,
+      hsm_group_value_opt,
       Some(&xname_list.join(",")), // Convert Hashset to String with comma separator, need to convert to Vec first following https://stackoverflow.com/a/47582249/1918003
       ansible_verbosity.map(|s| s.parse::<u8>().unwrap_or(2)),
       ansible_passthrough,
@@ -164,6 +211,10 @@ pub async fn exec(
 /// around the lower-level CFS session APIs when nodes might still be
 /// running a previous configuration.
 ///
+/// The session is named after the
+/// [`NamingPolicy`](crate::NamingPolicy) of the client in scope, with
+/// `hsm_group` as its `{group}` variable.
+///
 /// # Errors
 ///
 /// Returns an [`Error`] variant on CSM, transport, or
@@ -179,6 +230,7 @@ pub async fn check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
   gitea_token: &str,
   gitea_base_url: &str,
   shasta_token: &str,
+  hsm_group: Option<&str>,
   limit: Option<&str>,
   ansible_verbosity: Option<u8>,
   ansible_passthrough: Option<&str>,
@@ -233,9 +285,9 @@ pub async fn check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
     .map(str::trim)
     .collect(); // TODO: remove duplicates... sort() + dedup() ???
 
-  log::info!(
+  events::info(format!(
     "Nodes with cfs session running or pending: {nodes_in_running_or_pending_cfs_session:?}"
-  );
+  ));
 
   // NOTE: nodes can be a list of xnames or hsm group name
 
@@ -316,12 +368,13 @@ pub async fn check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
     )
     .await?
     .name;
+  events::created("CFS configuration", &cfs_configuration_name);
 
   // Create dynamic CFS session
-  let cfs_session_name = format!(
-    "{}-{}",
-    cfs_configuration_name,
-    chrono::Utc::now().format("%Y%m%d%H%M%S")
+  let cfs_session_name = naming::current().cfs_session_name(
+    &NamingContext::new(shasta_token)
+      .with("configuration", cfs_configuration_name.as_str())
+      .with("group", hsm_group.unwrap_or_default()),
   );
 
   let session = CfsSessionPostRequest::new(
@@ -344,6 +397,7 @@ pub async fn check_nodes_are_ready_to_run_cfs_configuration_and_run_cfs_session(
   )
   .await?
   .name;
+  events::created("CFS session", &cfs_session_name);
 
   Ok(cfs_session_name)
 }
@@ -0,0 +1,330 @@
+//! Build an image with a CFS session, outside of a SAT file.
+//!
+//! The base image comes from IMS (an existing image, or one built from
+//! a recipe by an IMS job) or from a product in the
+//! `cray-product-catalog`. A CFS session with an `image` target then
+//! customizes it with a CFS configuration, and the resulting IMS image
+//! is stamped with the session's provenance. This is the same pipeline
+//! a SAT file's `images` section goes through
+//! ([`crate::commands::i_apply_sat_file::utils::images`]), without
+//! having to write one.
+
+use std::{
+  collections::{BTreeMap, HashMap},
+  sync::Arc,
+};
+
+use crate::{
+  ExecutionContext, ShastaClient,
+  commands::i_apply_sat_file::utils::{
+    image::{self, Base, BaseOrIms, Filter, ImageBaseIms},
+    images::{
+      CfsSessionRetryPolicy, ImageBuildContext, ImageBuildReport,
+      i_create_image_from_sat_file_serde_yaml,
+    },
+  },
+  common::{
+    naming::NamingContext,
+    poll::WaitOptions,
+    product_catalog::{ArtifactKind, ArtifactSelection, ProductCatalog},
+    session_profile::{self, SessionProfile},
+  },
+  error::Error,
+  ims::{PublicKeySelector, image::http_client::types::Image},
+};
+
+/// Base image of [`exec`].
+#[derive(Debug, Clone)]
+pub enum ImageSource {
+  /// Existing IMS image.
+  ImsImage {
+    /// IMS image id.
+    id: String,
+  },
+  /// Image an IMS job builds from an IMS recipe.
+  ImsRecipe {
+    /// IMS recipe name.
+    name: String,
+    /// Recipe template variable overrides.
+    template_dictionary: Option<BTreeMap<String, String>>,
+  },
+  /// Image, or recipe to build one from, listed in the
+  /// `cray-product-catalog` for a product version.
+  Product {
+    /// Product name.
+    name: String,
+    /// Product version.
+    version: String,
+    /// Whether to take one of the version's images or build one from
+    /// one of its recipes.
+    kind: ArtifactKind,
+    /// Which of the version's artifacts to take, the first one if
+    /// `None`.
+    filter: Option<Filter>,
+    /// Which artifact to take when several match `filter`.
+    select: ArtifactSelection,
+    /// Recipe template variable overrides, only with
+    /// [`ArtifactKind::Recipe`].
+    template_dictionary: Option<BTreeMap<String, String>>,
+  },
+}
+
+impl ImageSource {
+  /// SAT file `images` entry building `image_name` from this base.
+  fn to_sat_image(
+    &self,
+    image_name: &str,
+    configuration: &str,
+    groups: &[String],
+  ) -> image::Image {
+    let (base, template_dictionary) = match self {
+      Self::ImsImage { id } => (
+        Base::Ims {
+          ims: ImageBaseIms::IdType {
+            id: id.clone(),
+            r#type: "image".to_string(),
+          },
+        },
+        None,
+      ),
+      Self::ImsRecipe {
+        name,
+        template_dictionary,
+      } => (
+        Base::Ims {
+          ims: ImageBaseIms::NameType {
+            name: name.clone(),
+            r#type: "recipe".to_string(),
+          },
+        },
+        template_dictionary.clone(),
+      ),
+      Self::Product {
+        name,
+        version,
+        kind,
+        filter,
+        select,
+        template_dictionary,
+      } => (
+        Base::Product {
+          product: image::Product {
+            name: name.clone(),
+            version: Some(version.clone()),
+            r#type: match kind {
+              ArtifactKind::Image => "image",
+              ArtifactKind::Recipe => "recipe",
+            }
+            .to_string(),
+            filter: filter.clone(),
+            select: *select,
+          },
+        },
+        template_dictionary.clone(),
+      ),
+    };
+
+    image::Image {
+      name: image_name.to_string(),
+      base_or_ims: BaseOrIms::Base { base },
+      configuration: Some(configuration.to_string()),
+      configuration_group_names: Some(groups.to_vec()),
+      ref_name: None,
+      description: None,
+      template_dictionary,
+      archs: None,
+      arch: None,
+    }
+  }
+}
+
+/// How [`exec`] builds the image.
+#[derive(Debug, Clone)]
+pub struct BuildImageOptions<'a> {
+  /// Vault base URL, to read the Kubernetes credentials with.
+  pub vault_base_url: &'a str,
+  /// Site whose Kubernetes credentials to read from Vault.
+  pub site_name: &'a str,
+  /// Kubernetes API URL. Kubernetes is only used to stream the CFS
+  /// session logs with `watch_logs`, and to read the Ansible log of a
+  /// failed session when `cfs_session_retry_policy` allows a retry.
+  pub k8s_api_url: &'a str,
+  /// `cray-product-catalog`, needed for [`ImageSource::Product`].
+  pub cray_product_catalog: Option<Arc<ProductCatalog>>,
+  /// Ansible verbosity of the CFS session.
+  pub ansible_verbosity: Option<u8>,
+  /// Extra Ansible arguments of the CFS session.
+  pub ansible_passthrough: Option<&'a str>,
+  /// CFS session options to use where `ansible_verbosity` and
+  /// `ansible_passthrough` are `None`; its `ansible_limit` and
+  /// `require_dkms` apply too.
+  pub session_profile: Option<&'a SessionProfile>,
+  /// Stream the CFS session logs through `tracing`.
+  pub watch_logs: bool,
+  /// Prefix streamed log lines with their timestamp.
+  pub timestamps: bool,
+  /// Create nothing; return a placeholder image.
+  pub dry_run: bool,
+  /// When to re-submit a CFS session that failed.
+  pub cfs_session_retry_policy: CfsSessionRetryPolicy,
+  /// How long, and how often, to wait for the CFS session.
+  pub cfs_session_wait: WaitOptions,
+  /// How long, and how often, to wait for the IMS job building the base
+  /// image from a recipe.
+  pub ims_job_wait: WaitOptions,
+  /// IMS public key passed to the IMS job.
+  pub ims_public_key_selector: PublicKeySelector,
+}
+
+impl Default for BuildImageOptions<'_> {
+  /// No Kubernetes access, no log streaming, no retries, and the CFS
+  /// session and IMS job default waits.
+  fn default() -> Self {
+    Self {
+      vault_base_url: "",
+      site_name: "",
+      k8s_api_url: "",
+      cray_product_catalog: None,
+      ansible_verbosity: None,
+      ansible_passthrough: None,
+      session_profile: None,
+      watch_logs: false,
+      timestamps: false,
+      dry_run: false,
+      cfs_session_retry_policy: CfsSessionRetryPolicy::default(),
+      cfs_session_wait: crate::cfs::session::utils::DEFAULT_WAIT,
+      ims_job_wait: crate::ims::job::utils::DEFAULT_WAIT,
+      ims_public_key_selector: PublicKeySelector::default(),
+    }
+  }
+}
+
+/// Build IMS image `image_name` from `base`, customized by CFS
+/// configuration `configuration` for the Ansible groups `groups`.
+///
+/// Returns the image built and every CFS session submitted to build
+/// it. `groups` are Ansible groups, not HSM groups, so
+/// `execution_context` only tags the changes made.
+///
+/// # Errors
+///
+/// Returns [`Error::Message`] for an [`ImageSource::Product`] base
+/// without `options.cray_product_catalog`, [`Error::SatFile`] if the
+/// base can't be resolved, a group is not available to the caller, or
+/// the IMS job or CFS session fails, or another [`Error`] variant on
+/// CSM, transport, or deserialization failure.
+#[allow(clippy::too_many_arguments)]
+pub async fn exec(
+  client: &ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  image_name: &str,
+  base: &ImageSource,
+  configuration: &str,
+  groups: &[String],
+  options: &BuildImageOptions<'_>,
+) -> Result<(Image, ImageBuildReport), Error> {
+  let _timer = crate::common::metrics::CommandTimer::start("build_image");
+
+  let cray_product_catalog = match (&options.cray_product_catalog, base) {
+    (Some(cray_product_catalog), _) => Arc::clone(cray_product_catalog),
+    (None, ImageSource::Product { name, .. }) => {
+      return Err(Error::Message(format!(
+        "Building image '{image_name}' from product '{name}' needs the cray-product-catalog"
+      )));
+    }
+    (None, _) => Arc::default(),
+  };
+
+  let image_yaml = base.to_sat_image(image_name, configuration, groups);
+
+  let session_profile = options
+    .session_profile
+    .map(|profile| profile.render(&NamingContext::new(shasta_token)))
+    .unwrap_or_default();
+
+  let context = ImageBuildContext {
+    shasta_token,
+    shasta_base_url: client.base_url(),
+    shasta_root_cert: client.root_cert(),
+    socks5_proxy: client.socks5_proxy(),
+    vault_base_url: options.vault_base_url,
+    site_name: options.site_name,
+    k8s_api_url: options.k8s_api_url,
+    cray_product_catalog: &cray_product_catalog,
+    ansible_verbosity: options
+      .ansible_verbosity
+      .or(session_profile.ansible_verbosity),
+    ansible_passthrough: options
+      .ansible_passthrough
+      .or(session_profile.ansible_passthrough.as_deref()),
+    debug_on_failure: false,
+    dry_run: options.dry_run,
+    watch_logs: options.watch_logs,
+    timestamps: options.timestamps,
+    cfs_session_retry_policy: &options.cfs_session_retry_policy,
+    cfs_session_wait: options.cfs_session_wait,
+    ims_job_wait: options.ims_job_wait,
+    ims_public_key_selector: &options.ims_public_key_selector,
+  };
+  let ref_name_image_id_hashmap = HashMap::new();
+  let build = i_create_image_from_sat_file_serde_yaml(
+    &context,
+    &image_yaml,
+    &ref_name_image_id_hashmap,
+  );
+
+  client
+    .scope(
+      execution_context
+        .scope(session_profile::scope(session_profile.clone(), build)),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn image_source_maps_to_sat_image_base() {
+    let groups = ["compute".to_string()];
+
+    let sat_image = ImageSource::ImsRecipe {
+      name: "cos-recipe".to_string(),
+      template_dictionary: None,
+    }
+    .to_sat_image("cos-image", "cos-config", &groups);
+    assert!(matches!(
+      sat_image.base_or_ims,
+      BaseOrIms::Base {
+        base: Base::Ims {
+          ims: ImageBaseIms::NameType { ref name, ref r#type },
+        },
+      } if name == "cos-recipe" && r#type == "recipe"
+    ));
+    assert_eq!(sat_image.configuration.as_deref(), Some("cos-config"));
+    assert_eq!(
+      sat_image.configuration_group_names.as_deref(),
+      Some(&groups[..])
+    );
+
+    let sat_image = ImageSource::Product {
+      name: "cos".to_string(),
+      version: "2.5.0".to_string(),
+      kind: ArtifactKind::Image,
+      filter: None,
+      select: ArtifactSelection::Latest,
+      template_dictionary: None,
+    }
+    .to_sat_image("cos-image", "cos-config", &groups);
+    assert!(matches!(
+      sat_image.base_or_ims,
+      BaseOrIms::Base {
+        base: Base::Product { ref product },
+      } if product.r#type == "image"
+        && product.version.as_deref() == Some("2.5.0")
+        && product.select == ArtifactSelection::Latest
+    ));
+  }
+}
@@ -3,4 +3,7 @@
 //! `commands::*::exec` surface still find a stable entry point under
 //! `csm_rs::commands::delete_configurations_and_data_related::*`.
 
-pub use crate::cfs::cleanup::{delete, get_data_to_delete};
+pub use crate::cfs::cleanup::{
+  BlockReason, BlockedResource, DEFAULT_DELETE_RETRY, DeletionPlan,
+  DeletionResource, delete, get_data_to_delete,
+};
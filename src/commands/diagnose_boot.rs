@@ -0,0 +1,660 @@
+//! Diagnose why a node doesn't boot.
+//!
+//! Gathers what each CSM service knows about one node — its BSS boot
+//! parameters, the IMS image they point at, the latest BOS session
+//! targeting it, its HSM state, its DHCP lease and its CFS component —
+//! together with the tail of its console log, and derives the likely
+//! causes of a failed boot from them.
+//!
+//! A service that can't be queried is reported as a warning and listed
+//! in [`BootDiagnosis::unavailable`]; the diagnosis carries on with
+//! what the other services know.
+
+use std::{fmt, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+  NodeXName,
+  bos::{BosSession, BosSessionTemplate, StatusLabel},
+  bss::{types::BootParameters, utils::find_boot_params_related_to_node},
+  cfs::component::http_client::v3::types::Component as CfsComponent,
+  common::events,
+  error::Error,
+  hsm::component::types::Component as HsmComponent,
+  ims::image::http_client::types::Image,
+  kea::utils::{NodeLease, get_node_leases},
+};
+
+/// Number of console log lines kept in [`BootDiagnosis::console_tail`].
+pub const CONSOLE_TAIL_LINES: usize = 50;
+
+/// Console messages, lowercased, telling the node got no DHCP answer.
+const DHCP_FAILURE_PATTERNS: [&str; 5] = [
+  "pxe-e51",
+  "no dhcp",
+  "dhcp failed",
+  "dhcp timeout",
+  "no configuration methods succeeded",
+];
+
+/// The IMS image a node's BSS boot parameters point at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImageCheck {
+  /// IMS image id, as found in the kernel parameters.
+  pub image_id: String,
+  /// IMS image name, `None` if the image doesn't exist in IMS.
+  pub image_name: Option<String>,
+  /// Etag of the image manifest in IMS.
+  pub etag: Option<String>,
+  /// Etag the BOS session template of [`BootDiagnosis::bos_session`]
+  /// expects for the image, if it sets one.
+  pub expected_etag: Option<String>,
+}
+
+/// The latest BOS session targeting the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BosSessionSummary {
+  /// Session name.
+  pub name: String,
+  /// BOS session template the session runs.
+  pub template_name: String,
+  /// `boot`, `reboot` or `shutdown`.
+  pub operation: Option<String>,
+  /// Session status.
+  pub status: Option<StatusLabel>,
+  /// When the session started.
+  pub start_time: Option<String>,
+  /// Error BOS reported for the session, if any.
+  pub error: Option<String>,
+}
+
+/// The node's component in HSM.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HsmStatus {
+  /// HSM state, e.g. `Ready` or `Off`.
+  pub state: Option<String>,
+  /// HSM flag, e.g. `OK` or `Alert`.
+  pub flag: Option<String>,
+  /// Whether the component is enabled; HSM treats a missing value as
+  /// enabled.
+  pub enabled: Option<bool>,
+}
+
+/// The node's component in CFS.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CfsStatus {
+  /// CFS configuration the node should have.
+  pub desired_config: Option<String>,
+  /// `unconfigured`, `pending`, `failed` or `configured`.
+  pub configuration_status: Option<String>,
+  /// Failed configuration attempts since the desired configuration
+  /// last changed.
+  pub error_count: Option<u64>,
+}
+
+/// A likely reason for the node not booting, most specific first in
+/// [`BootDiagnosis::suspected_causes`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "cause", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum SuspectedCause {
+  /// BSS has no boot parameters for the node.
+  NoBootParameters,
+  /// The boot parameters don't reference an IMS image.
+  NoBootImage,
+  /// The boot parameters reference an image that doesn't exist in IMS.
+  MissingImage {
+    /// IMS image id.
+    image_id: String,
+  },
+  /// The image has no manifest etag in IMS, or not the one the BOS
+  /// session template expects.
+  EtagMismatch {
+    /// IMS image id.
+    image_id: String,
+    /// Etag in the BOS session template.
+    expected: Option<String>,
+    /// Etag in IMS.
+    actual: Option<String>,
+  },
+  /// The node holds no active DHCP lease, or its console shows a DHCP
+  /// failure.
+  DhcpFailure {
+    /// Console line showing the failure, if any.
+    console_line: Option<String>,
+  },
+  /// The latest BOS session targeting the node failed.
+  BosSessionFailed {
+    /// Session name.
+    session: String,
+    /// Error BOS reported.
+    error: String,
+  },
+  /// CFS failed to configure the node.
+  CfsFailed {
+    /// CFS configuration the node should have.
+    configuration: Option<String>,
+    /// Failed configuration attempts.
+    error_count: Option<u64>,
+  },
+  /// The node is disabled in HSM, so BOS and CFS skip it.
+  NodeDisabled,
+}
+
+impl fmt::Display for SuspectedCause {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NoBootParameters => f.write_str("BSS has no boot parameters"),
+      Self::NoBootImage => {
+        f.write_str("boot parameters don't reference an IMS image")
+      }
+      Self::MissingImage { image_id } => {
+        write!(f, "boot image '{image_id}' doesn't exist in IMS")
+      }
+      Self::EtagMismatch {
+        image_id,
+        expected,
+        actual,
+      } => write!(
+        f,
+        "boot image '{image_id}' etag is {} in IMS but {} in the BOS \
+         session template",
+        actual.as_deref().unwrap_or("missing"),
+        expected.as_deref().unwrap_or("missing")
+      ),
+      Self::DhcpFailure {
+        console_line: Some(line),
+      } => write!(f, "DHCP failure on console: {line}"),
+      Self::DhcpFailure { console_line: None } => {
+        f.write_str("no active DHCP lease")
+      }
+      Self::BosSessionFailed { session, error } => {
+        write!(f, "BOS session '{session}' failed: {error}")
+      }
+      Self::CfsFailed {
+        configuration,
+        error_count,
+      } => write!(
+        f,
+        "CFS failed to apply configuration '{}' ({} attempts)",
+        configuration.as_deref().unwrap_or("unknown"),
+        error_count.unwrap_or_default()
+      ),
+      Self::NodeDisabled => f.write_str("node is disabled in HSM"),
+    }
+  }
+}
+
+/// What CSM knows about one node's boot, and what likely went wrong.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BootDiagnosis {
+  /// Node xname.
+  pub xname: String,
+  /// Kernel parameters in BSS, `None` if BSS has none for the node.
+  pub kernel_params: Option<String>,
+  /// Image the kernel parameters boot.
+  pub image: Option<ImageCheck>,
+  /// Latest BOS session targeting the node.
+  pub bos_session: Option<BosSessionSummary>,
+  /// Node state in HSM.
+  pub hsm: Option<HsmStatus>,
+  /// Node DHCP lease.
+  pub dhcp_lease: Option<NodeLease>,
+  /// Node status in CFS.
+  pub cfs: Option<CfsStatus>,
+  /// Last [`CONSOLE_TAIL_LINES`] lines of the console log, if one was
+  /// given.
+  pub console_tail: Vec<String>,
+  /// Services that couldn't be queried.
+  pub unavailable: Vec<String>,
+  /// Likely causes of the failed boot; empty if nothing looks wrong.
+  pub suspected_causes: Vec<SuspectedCause>,
+}
+
+/// Diagnose the boot of node `xname`. `console_log_path_opt` is the
+/// node's console log, as written by
+/// `node::console::capture_console_logs`; its tail is included and
+/// searched for DHCP failures.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidXName`] if `xname` isn't a node xname.
+/// Failures to query a CSM service are not errors, see the module
+/// documentation.
+pub async fn diagnose_boot(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  xname: &str,
+  console_log_path_opt: Option<&Path>,
+) -> Result<BootDiagnosis, Error> {
+  let _timer = crate::common::metrics::CommandTimer::start("diagnose_boot");
+
+  let xname = NodeXName::parse(xname)?.to_string();
+
+  crate::common::request_id::scope(
+    "diagnose_boot",
+    gather(client, shasta_token, xname, console_log_path_opt),
+  )
+  .await
+}
+
+/// Body of [`diagnose_boot`], run inside its correlation scope.
+async fn gather(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  xname: String,
+  console_log_path_opt: Option<&Path>,
+) -> Result<BootDiagnosis, Error> {
+  events::step(format!("Diagnosing boot of node '{xname}'"));
+
+  let xname_vec = vec![xname.clone()];
+  let mut unavailable = Vec::new();
+
+  let (
+    boot_param_result,
+    hsm_result,
+    membership_result,
+    cfs_result,
+    lease_result,
+    session_result,
+    template_result,
+  ) = tokio::join!(
+    client.bss_bootparameters_get(shasta_token, &xname_vec),
+    client.hsm_component_get_one(shasta_token, &xname),
+    client.hsm_memberships_get_xname(shasta_token, &xname),
+    client.cfs_component_v3_get_single_by_id(shasta_token, &xname),
+    get_node_leases(client, shasta_token, &xname_vec),
+    client.bos_session_v2_get(shasta_token, None),
+    client.bos_template_v2_get_all(shasta_token),
+  );
+
+  let boot_params_opt =
+    ok_or_unavailable("BSS", boot_param_result, &mut unavailable).and_then(
+      |boot_param_vec| {
+        find_boot_params_related_to_node(&boot_param_vec, &xname)
+      },
+    );
+  let hsm_component_opt: Option<HsmComponent> =
+    ok_or_unavailable("HSM", hsm_result, &mut unavailable);
+  let group_vec =
+    ok_or_unavailable("HSM memberships", membership_result, &mut unavailable)
+      .map(|membership| membership.group_labels)
+      .unwrap_or_default();
+  let cfs_component_opt: Option<CfsComponent> =
+    ok_or_unavailable("CFS", cfs_result, &mut unavailable);
+  let lease_opt = ok_or_unavailable("Kea", lease_result, &mut unavailable)
+    .and_then(|lease_vec| lease_vec.into_iter().next());
+  let session_vec =
+    ok_or_unavailable("BOS sessions", session_result, &mut unavailable)
+      .unwrap_or_default();
+  let template_vec = ok_or_unavailable(
+    "BOS session templates",
+    template_result,
+    &mut unavailable,
+  )
+  .unwrap_or_default();
+
+  let image_id_opt = boot_params_opt
+    .as_ref()
+    .map(BootParameters::get_boot_image)
+    .filter(|image_id| !image_id.is_empty());
+
+  let image_opt = match &image_id_opt {
+    Some(image_id) => {
+      match client.ims_image_get(shasta_token, Some(image_id)).await {
+        Err(Error::ImageNotFound(_)) => None,
+        result => ok_or_unavailable("IMS", result, &mut unavailable)
+          .and_then(|image_vec| image_vec.into_iter().next()),
+      }
+    }
+    None => None,
+  };
+
+  let console_tail = match console_log_path_opt {
+    Some(path) => match tokio::fs::read_to_string(path).await {
+      Ok(console_log) => tail(&console_log, CONSOLE_TAIL_LINES),
+      Err(e) => {
+        events::warning(format!(
+          "Could not read console log '{}': {e}",
+          path.display()
+        ));
+        Vec::new()
+      }
+    },
+    None => Vec::new(),
+  };
+
+  let latest_session_opt =
+    latest_session(&session_vec, &template_vec, &xname, &group_vec);
+
+  let image = image_id_opt.map(|image_id| {
+    let expected_etag = latest_session_opt
+      .and_then(|session| {
+        template_vec.iter().find(|template| {
+          template.name.as_deref() == Some(session.template_name.as_str())
+        })
+      })
+      .and_then(|template| boot_set_etag(template, &image_id));
+
+    image_check(image_id, image_opt.as_ref(), expected_etag)
+  });
+
+  let mut diagnosis = BootDiagnosis {
+    xname,
+    kernel_params: boot_params_opt.map(|boot_params| boot_params.params),
+    image,
+    bos_session: latest_session_opt.map(bos_session_summary),
+    hsm: hsm_component_opt.map(|component| HsmStatus {
+      state: component.state.as_ref().map(ToString::to_string),
+      flag: component.flag.as_ref().map(ToString::to_string),
+      enabled: component.enabled,
+    }),
+    dhcp_lease: lease_opt,
+    cfs: cfs_component_opt.map(|component| CfsStatus {
+      desired_config: component.desired_config,
+      configuration_status: component.configuration_status,
+      error_count: component.error_count,
+    }),
+    console_tail,
+    unavailable,
+    suspected_causes: Vec::new(),
+  };
+  diagnosis.suspected_causes = suspected_causes(&diagnosis);
+
+  Ok(diagnosis)
+}
+
+/// `result`'s value, or `None` after reporting `service` as unavailable.
+fn ok_or_unavailable<T>(
+  service: &str,
+  result: Result<T, Error>,
+  unavailable: &mut Vec<String>,
+) -> Option<T> {
+  match result {
+    Ok(value) => Some(value),
+    Err(e) => {
+      events::warning(format!("Could not query {service}: {e}"));
+      unavailable.push(service.to_string());
+      None
+    }
+  }
+}
+
+/// Last `count` lines of `text`.
+fn tail(text: &str, count: usize) -> Vec<String> {
+  let line_vec: Vec<&str> = text.lines().collect();
+  line_vec[line_vec.len().saturating_sub(count)..]
+    .iter()
+    .map(|line| (*line).to_string())
+    .collect()
+}
+
+/// Most recently started BOS session targeting `xname`, directly or
+/// through one of the HSM groups in `group_vec`: through its `limit`
+/// if it has one, otherwise through its session template.
+fn latest_session<'a>(
+  session_vec: &'a [BosSession],
+  template_vec: &[BosSessionTemplate],
+  xname: &str,
+  group_vec: &[String],
+) -> Option<&'a BosSession> {
+  let targets_node = |target: &str| {
+    target == xname || group_vec.iter().any(|group| group == target)
+  };
+
+  session_vec
+    .iter()
+    .filter(|session| match &session.limit {
+      Some(limit) if !limit.is_empty() => limit.split(',').any(targets_node),
+      _ => template_vec
+        .iter()
+        .find(|template| {
+          template.name.as_deref() == Some(session.template_name.as_str())
+        })
+        .is_some_and(|template| {
+          template
+            .get_target()
+            .iter()
+            .any(|target| targets_node(target))
+        }),
+    })
+    .max_by_key(|session| {
+      session
+        .status
+        .as_ref()
+        .map(|status| status.start_time.as_str())
+    })
+}
+
+/// Etag `template` expects for the boot set booting `image_id`.
+fn boot_set_etag(
+  template: &BosSessionTemplate,
+  image_id: &str,
+) -> Option<String> {
+  template
+    .boot_sets
+    .iter()
+    .flatten()
+    .find(|(_, boot_set)| {
+      boot_set
+        .path
+        .as_deref()
+        .is_some_and(|path| path.contains(image_id))
+    })
+    .and_then(|(_, boot_set)| boot_set.etag.clone())
+}
+
+fn image_check(
+  image_id: String,
+  image_opt: Option<&Image>,
+  expected_etag: Option<String>,
+) -> ImageCheck {
+  ImageCheck {
+    image_id,
+    image_name: image_opt.map(|image| image.name.clone()),
+    etag: image_opt
+      .and_then(|image| image.link.as_ref())
+      .and_then(|link| link.etag.clone()),
+    expected_etag,
+  }
+}
+
+fn bos_session_summary(session: &BosSession) -> BosSessionSummary {
+  BosSessionSummary {
+    name: session.name.clone().unwrap_or_default(),
+    template_name: session.template_name.clone(),
+    operation: session.operation.as_ref().map(ToString::to_string),
+    status: session.status.as_ref().map(|status| status.status),
+    start_time: session
+      .status
+      .as_ref()
+      .map(|status| status.start_time.clone()),
+    error: session
+      .status
+      .as_ref()
+      .and_then(|status| status.error.clone()),
+  }
+}
+
+/// Likely causes of a failed boot, from what `diagnosis` gathered.
+/// Information from a service that couldn't be queried doesn't count
+/// as missing.
+fn suspected_causes(diagnosis: &BootDiagnosis) -> Vec<SuspectedCause> {
+  let mut cause_vec = Vec::new();
+  let queried =
+    |service: &str| !diagnosis.unavailable.iter().any(|s| s == service);
+
+  if diagnosis.kernel_params.is_none() {
+    if queried("BSS") {
+      cause_vec.push(SuspectedCause::NoBootParameters);
+    }
+  } else if diagnosis.image.is_none() {
+    cause_vec.push(SuspectedCause::NoBootImage);
+  }
+
+  if let Some(image) = &diagnosis.image {
+    if image.image_name.is_none() {
+      if queried("IMS") {
+        cause_vec.push(SuspectedCause::MissingImage {
+          image_id: image.image_id.clone(),
+        });
+      }
+    } else if image.etag.is_none()
+      || image
+        .expected_etag
+        .as_ref()
+        .is_some_and(|expected| Some(expected) != image.etag.as_ref())
+    {
+      cause_vec.push(SuspectedCause::EtagMismatch {
+        image_id: image.image_id.clone(),
+        expected: image.expected_etag.clone(),
+        actual: image.etag.clone(),
+      });
+    }
+  }
+
+  let dhcp_console_line_opt = diagnosis
+    .console_tail
+    .iter()
+    .find(|line| {
+      let line = line.to_lowercase();
+      DHCP_FAILURE_PATTERNS
+        .iter()
+        .any(|pattern| line.contains(pattern))
+    })
+    .cloned();
+  let lease_missing = queried("Kea")
+    && diagnosis
+      .dhcp_lease
+      .as_ref()
+      .is_none_or(|node_lease| !node_lease.active);
+  if dhcp_console_line_opt.is_some() || lease_missing {
+    cause_vec.push(SuspectedCause::DhcpFailure {
+      console_line: dhcp_console_line_opt,
+    });
+  }
+
+  if let Some(session) = &diagnosis.bos_session
+    && let Some(error) = &session.error
+  {
+    cause_vec.push(SuspectedCause::BosSessionFailed {
+      session: session.name.clone(),
+      error: error.clone(),
+    });
+  }
+
+  if let Some(cfs) = &diagnosis.cfs
+    && cfs.configuration_status.as_deref() == Some("failed")
+  {
+    cause_vec.push(SuspectedCause::CfsFailed {
+      configuration: cfs.desired_config.clone(),
+      error_count: cfs.error_count,
+    });
+  }
+
+  if diagnosis
+    .hsm
+    .as_ref()
+    .is_some_and(|hsm| hsm.enabled == Some(false))
+  {
+    cause_vec.push(SuspectedCause::NodeDisabled);
+  }
+
+  cause_vec
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn diagnosis() -> BootDiagnosis {
+    BootDiagnosis {
+      xname: "x1000c0s0b0n0".to_string(),
+      kernel_params: Some(
+        "root=craycps-s3:s3://boot-images/img/rootfs:etag:dvs".to_string(),
+      ),
+      image: Some(ImageCheck {
+        image_id: "img".to_string(),
+        image_name: Some("compute".to_string()),
+        etag: Some("abc".to_string()),
+        expected_etag: Some("abc".to_string()),
+      }),
+      bos_session: None,
+      hsm: None,
+      dhcp_lease: None,
+      cfs: None,
+      console_tail: Vec::new(),
+      unavailable: vec!["Kea".to_string()],
+      suspected_causes: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn healthy_node_has_no_suspected_causes() {
+    assert!(suspected_causes(&diagnosis()).is_empty());
+  }
+
+  #[test]
+  fn flags_missing_image_and_wrong_etag() {
+    let mut missing = diagnosis();
+    missing.image.as_mut().unwrap().image_name = None;
+    assert_eq!(
+      suspected_causes(&missing),
+      [SuspectedCause::MissingImage {
+        image_id: "img".to_string()
+      }]
+    );
+
+    let mut wrong_etag = diagnosis();
+    wrong_etag.image.as_mut().unwrap().expected_etag = Some("def".to_string());
+    assert_eq!(
+      suspected_causes(&wrong_etag),
+      [SuspectedCause::EtagMismatch {
+        image_id: "img".to_string(),
+        expected: Some("def".to_string()),
+        actual: Some("abc".to_string()),
+      }]
+    );
+  }
+
+  #[test]
+  fn flags_dhcp_and_cfs_failures() {
+    let mut failing = diagnosis();
+    failing.console_tail = vec![
+      "Booting from NIC".to_string(),
+      "PXE-E51: No DHCP or proxy DHCP offers were received.".to_string(),
+    ];
+    failing.cfs = Some(CfsStatus {
+      desired_config: Some("compute-1.0".to_string()),
+      configuration_status: Some("failed".to_string()),
+      error_count: Some(3),
+    });
+
+    assert_eq!(
+      suspected_causes(&failing),
+      [
+        SuspectedCause::DhcpFailure {
+          console_line: Some(failing.console_tail[1].clone()),
+        },
+        SuspectedCause::CfsFailed {
+          configuration: Some("compute-1.0".to_string()),
+          error_count: Some(3),
+        },
+      ]
+    );
+
+    let mut no_lease = diagnosis();
+    no_lease.unavailable.clear();
+    assert_eq!(
+      suspected_causes(&no_lease),
+      [SuspectedCause::DhcpFailure { console_line: None }]
+    );
+  }
+
+  #[test]
+  fn tail_keeps_last_lines() {
+    assert_eq!(tail("a\nb\nc\n", 2), ["b", "c"]);
+    assert_eq!(tail("a", 5), ["a"]);
+  }
+}
@@ -0,0 +1,376 @@
+//! Delete the Kubernetes objects of long completed CFS sessions.
+//!
+//! CFS runs every session as a Kubernetes job in the `services`
+//! namespace, whose pod carries a `cfsession=<session name>` label.
+//! Depending on the CFS TTL settings these jobs and pods can outlive the
+//! session by weeks and pile up in etcd. [`exec`] finds the sessions
+//! completed more than a number of days ago whose job or pods still
+//! exist and deletes those objects, a batch of sessions at a time.
+//!
+//! The CFS sessions themselves are left alone.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use futures::StreamExt;
+use k8s_openapi::api::{batch::v1::Job, core::v1::Pod};
+use kube::{
+  Api, ResourceExt,
+  api::{DeleteParams, ListParams},
+};
+use serde::Serialize;
+
+use crate::{
+  ExecutionContext,
+  cfs::v2::CfsSessionGetResponse,
+  common::events,
+  error::Error,
+  filter::{self, Filter, Query},
+};
+
+/// Namespace CFS runs the session jobs in.
+pub const NAMESPACE: &str = "services";
+
+/// Label naming the CFS session of a pod.
+const SESSION_LABEL: &str = "cfsession";
+
+/// Kubernetes objects left behind by one completed CFS session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleSession {
+  /// CFS session name.
+  pub session: String,
+  /// Kubernetes job of the session, if it still exists.
+  pub job: Option<String>,
+  /// Pods of the session still existing, sorted.
+  pub pods: Vec<String>,
+}
+
+/// Objects reclaimed by [`exec`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GcReport {
+  /// Sessions whose objects were deleted, or would be in dry run mode.
+  pub sessions: Vec<StaleSession>,
+  /// Kubernetes jobs deleted.
+  pub jobs_deleted: usize,
+  /// Pods deleted.
+  pub pods_deleted: usize,
+  /// Objects that could not be deleted.
+  pub failed: usize,
+}
+
+/// Sessions in `session_vec` completed before `cutoff` that still have
+/// a job in `job_name_set` or pods in `session_pod_map` (session name
+/// to pod names), sorted by session name.
+#[must_use]
+pub fn plan(
+  session_vec: &[CfsSessionGetResponse],
+  cutoff: NaiveDateTime,
+  job_name_set: &HashSet<String>,
+  session_pod_map: &HashMap<String, Vec<String>>,
+) -> Vec<StaleSession> {
+  let mut stale_session_vec: Vec<StaleSession> = session_vec
+    .iter()
+    .filter_map(|session| {
+      let session_status = session.status.as_ref()?.session.as_ref()?;
+
+      let completion_time =
+        filter::parse_timestamp(session_status.completion_time.as_deref()?)?;
+      if completion_time >= cutoff {
+        return None;
+      }
+
+      let job = session_status
+        .job
+        .clone()
+        .filter(|job| job_name_set.contains(job));
+      let mut pods = session_pod_map
+        .get(&session.name)
+        .cloned()
+        .unwrap_or_default();
+      pods.sort();
+
+      (job.is_some() || !pods.is_empty()).then(|| StaleSession {
+        session: session.name.clone(),
+        job,
+        pods,
+      })
+    })
+    .collect();
+
+  stale_session_vec.sort_by(|a, b| a.session.cmp(&b.session));
+
+  stale_session_vec
+}
+
+/// Delete the jobs and pods of the CFS sessions matching `filter` that
+/// completed more than `older_than_days` days ago, `batch_size`
+/// sessions at a time.
+///
+/// Every session cleaned up is reported as an [`crate::Event`]. With
+/// `dry_run` nothing is deleted. An object that can't be deleted is
+/// reported as a warning and counted in [`GcReport::failed`]. Unless
+/// `execution_context` is unrestricted, only the sessions targeting HSM
+/// groups it can act on are considered.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant if the CFS sessions or the Kubernetes
+/// jobs and pods can't be listed; see the crate-level `Error` enum for
+/// the full set.
+#[allow(clippy::too_many_arguments)]
+pub async fn exec(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  kube_client: kube::Client,
+  filter: Filter,
+  older_than_days: u32,
+  batch_size: usize,
+  dry_run: bool,
+) -> Result<GcReport, Error> {
+  let _timer =
+    crate::common::metrics::CommandTimer::start("gc_cfs_session_pods");
+
+  client
+    .scope(execution_context.scope(crate::common::request_id::scope(
+      "gc_cfs_session_pods",
+      gc(
+        client,
+        shasta_token,
+        execution_context,
+        kube_client,
+        filter,
+        older_than_days,
+        batch_size,
+        dry_run,
+      ),
+    )))
+    .await
+}
+
+/// Body of [`exec`], run inside its correlation scope.
+#[allow(clippy::too_many_arguments)]
+async fn gc(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  kube_client: kube::Client,
+  filter: Filter,
+  older_than_days: u32,
+  batch_size: usize,
+  dry_run: bool,
+) -> Result<GcReport, Error> {
+  let job_api: Api<Job> = Api::namespaced(kube_client.clone(), NAMESPACE);
+  let pod_api: Api<Pod> = Api::namespaced(kube_client, NAMESPACE);
+
+  events::step("Fetch CFS sessions and their Kubernetes objects");
+  let (mut session_vec, job_list, pod_list) = tokio::try_join!(
+    client.cfs_session_v2_get_all(shasta_token),
+    async {
+      job_api
+        .list(&ListParams::default())
+        .await
+        .map_err(Error::from)
+    },
+    async {
+      pod_api
+        .list(&ListParams::default().labels(SESSION_LABEL))
+        .await
+        .map_err(Error::from)
+    },
+  )?;
+
+  Query::new(filter).apply(&mut session_vec)?;
+
+  // Tenants only reclaim the sessions targeting their own groups
+  let tenancy = execution_context.tenancy();
+  if !tenancy.is_unrestricted() {
+    session_vec.retain(|session| {
+      let group_vec = session.get_target_hsm().unwrap_or_default();
+      !group_vec.is_empty() && group_vec.iter().all(|group| tenancy.owns(group))
+    });
+  }
+
+  let job_name_set: HashSet<String> =
+    job_list.items.iter().map(ResourceExt::name_any).collect();
+
+  let mut session_pod_map: HashMap<String, Vec<String>> = HashMap::new();
+  for pod in &pod_list.items {
+    if let Some(session_name) = pod.labels().get(SESSION_LABEL) {
+      session_pod_map
+        .entry(session_name.clone())
+        .or_default()
+        .push(pod.name_any());
+    }
+  }
+
+  let cutoff =
+    (Utc::now() - Duration::days(i64::from(older_than_days))).naive_utc();
+
+  let stale_session_vec =
+    plan(&session_vec, cutoff, &job_name_set, &session_pod_map);
+
+  if stale_session_vec.is_empty() {
+    events::info(format!(
+      "No CFS session completed more than {older_than_days} days ago has Kubernetes objects left"
+    ));
+    return Ok(GcReport::default());
+  }
+
+  if dry_run {
+    for stale_session in &stale_session_vec {
+      events::info(format!(
+        "Dry run mode: Delete job {:?} and {} pod(s) of CFS session '{}'",
+        stale_session.job,
+        stale_session.pods.len(),
+        stale_session.session
+      ));
+    }
+
+    return Ok(GcReport {
+      sessions: stale_session_vec,
+      ..GcReport::default()
+    });
+  }
+
+  events::step(format!(
+    "Delete Kubernetes objects of {} CFS sessions",
+    stale_session_vec.len()
+  ));
+
+  let outcome_vec: Vec<GcReport> = futures::stream::iter(&stale_session_vec)
+    .map(|stale_session| delete(&job_api, &pod_api, stale_session))
+    .buffer_unordered(batch_size.max(1))
+    .collect()
+    .await;
+
+  let mut report =
+    outcome_vec
+      .into_iter()
+      .fold(GcReport::default(), |mut report, outcome| {
+        report.jobs_deleted += outcome.jobs_deleted;
+        report.pods_deleted += outcome.pods_deleted;
+        report.failed += outcome.failed;
+        report
+      });
+  report.sessions = stale_session_vec;
+
+  events::info(format!(
+    "Deleted {} job(s) and {} pod(s) of {} CFS sessions, {} failure(s)",
+    report.jobs_deleted,
+    report.pods_deleted,
+    report.sessions.len(),
+    report.failed
+  ));
+
+  Ok(report)
+}
+
+/// Delete the pods, then the job, of `stale_session`. Objects already
+/// gone are neither counted as deleted nor as failed.
+async fn delete(
+  job_api: &Api<Job>,
+  pod_api: &Api<Pod>,
+  stale_session: &StaleSession,
+) -> GcReport {
+  let mut outcome = GcReport::default();
+
+  for pod_name in &stale_session.pods {
+    match pod_api.delete(pod_name, &DeleteParams::default()).await {
+      Ok(_) => {
+        outcome.pods_deleted += 1;
+        events::deleted("Kubernetes pod", pod_name);
+      }
+      Err(kube::Error::Api(response)) if response.code == 404 => {}
+      Err(e) => {
+        outcome.failed += 1;
+        events::warning(format!("Could not delete pod '{pod_name}': {e}"));
+      }
+    }
+  }
+
+  if let Some(job_name) = &stale_session.job {
+    match job_api.delete(job_name, &DeleteParams::background()).await {
+      Ok(_) => {
+        outcome.jobs_deleted += 1;
+        events::deleted("Kubernetes job", job_name);
+      }
+      Err(kube::Error::Api(response)) if response.code == 404 => {}
+      Err(e) => {
+        outcome.failed += 1;
+        events::warning(format!("Could not delete job '{job_name}': {e}"));
+      }
+    }
+  }
+
+  outcome
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn session(
+    name: &str,
+    completion_time: Option<&str>,
+  ) -> CfsSessionGetResponse {
+    serde_json::from_value(json!({
+      "name": name,
+      "status": {
+        "session": {
+          "job": format!("cfs-{name}"),
+          "completionTime": completion_time,
+          "startTime": "2024-01-01T00:00:00",
+          "status": if completion_time.is_some() { "complete" } else { "running" },
+        }
+      }
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn plan_keeps_old_completed_sessions_with_objects_left() {
+    let session_vec = vec![
+      session("old", Some("2024-01-02T00:00:00")),
+      session("old-no-objects", Some("2024-01-02T00:00:00")),
+      session("old-pods-only", Some("2024-01-02T00:00:00Z")),
+      session("recent", Some("2024-03-01T00:00:00")),
+      session("running", None),
+    ];
+    let job_name_set: HashSet<String> =
+      ["cfs-old", "cfs-recent", "cfs-running"]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let session_pod_map = HashMap::from([
+      (
+        "old".to_string(),
+        vec!["cfs-old-b".to_string(), "cfs-old-a".to_string()],
+      ),
+      (
+        "old-pods-only".to_string(),
+        vec!["cfs-old-pods-only-a".to_string()],
+      ),
+      ("recent".to_string(), vec!["cfs-recent-a".to_string()]),
+    ]);
+    let cutoff = filter::parse_timestamp("2024-02-01T00:00:00").unwrap();
+
+    assert_eq!(
+      plan(&session_vec, cutoff, &job_name_set, &session_pod_map),
+      vec![
+        StaleSession {
+          session: "old".to_string(),
+          job: Some("cfs-old".to_string()),
+          pods: vec!["cfs-old-a".to_string(), "cfs-old-b".to_string()],
+        },
+        StaleSession {
+          session: "old-pods-only".to_string(),
+          job: None,
+          pods: vec!["cfs-old-pods-only-a".to_string()],
+        },
+      ]
+    );
+  }
+}
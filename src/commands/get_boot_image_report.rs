@@ -0,0 +1,276 @@
+//! Report which boot images the nodes of each HSM group are booting.
+//!
+//! Combines BSS boot parameters (what each node boots), IMS images (image
+//! names) and BOS session templates (what each group is meant to boot) to
+//! answer "which images is this group running, on how many nodes, and
+//! which nodes are behind?".
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::{
+  bos::BosSessionTemplate,
+  bss::types::BootParameters,
+  error::Error,
+  hsm::group::{GroupExt, types::Group},
+  ims::image::http_client::types::Image,
+};
+
+/// Nodes of one HSM group booting the same IMS image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BootImageUsage {
+  /// IMS image id, as found in the node's BSS kernel parameters.
+  pub image_id: String,
+  /// IMS image name, `None` if the image no longer exists in IMS.
+  pub image_name: Option<String>,
+  /// Nodes booting this image.
+  pub xnames: Vec<String>,
+}
+
+impl BootImageUsage {
+  /// Number of nodes booting this image.
+  #[must_use]
+  pub fn node_count(&self) -> usize {
+    self.xnames.len()
+  }
+}
+
+/// Boot image usage for one HSM group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupBootImageReport {
+  /// HSM group label.
+  pub hsm_group: String,
+  /// Latest BOS session template targeting the group: among the
+  /// templates listing the group in a boot set's `node_groups`, the one
+  /// booting the most recently created IMS image.
+  pub latest_bos_template: Option<String>,
+  /// Image booted by `latest_bos_template`.
+  pub expected_image_id: Option<String>,
+  /// Images booted by the group members, most used first.
+  pub images: Vec<BootImageUsage>,
+  /// Members booting an image other than `expected_image_id`.
+  pub mismatched_nodes: Vec<String>,
+  /// Members with no BSS boot parameters or no image in them.
+  pub unknown_nodes: Vec<String>,
+}
+
+/// Build a [`GroupBootImageReport`] for every HSM group in
+/// `hsm_group_name_vec`, or for every HSM group on the system if the
+/// list is empty.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn get_boot_image_report(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  hsm_group_name_vec: &[String],
+) -> Result<Vec<GroupBootImageReport>, Error> {
+  let _timer =
+    crate::common::metrics::CommandTimer::start("get_boot_image_report");
+
+  let group_vec = if hsm_group_name_vec.is_empty() {
+    client.hsm_group_get_all(shasta_token).await?
+  } else {
+    client
+      .hsm_group_get(shasta_token, Some(hsm_group_name_vec), None)
+      .await?
+  };
+
+  let mut xname_vec: Vec<String> =
+    group_vec.iter().flat_map(GroupExt::get_members).collect();
+  xname_vec.sort();
+  xname_vec.dedup();
+
+  let (boot_param_vec, image_vec, bos_sessiontemplate_vec) = tokio::try_join!(
+    client.bss_bootparameters_get_multiple(shasta_token, &xname_vec),
+    client.ims_image_get_all(shasta_token),
+    client.bos_template_v2_get_all(shasta_token),
+  )?;
+
+  Ok(build_report(
+    &group_vec,
+    &boot_param_vec,
+    &image_vec,
+    &bos_sessiontemplate_vec,
+  ))
+}
+
+/// Pure part of [`get_boot_image_report`], split out for testing.
+fn build_report(
+  group_vec: &[Group],
+  boot_param_vec: &[BootParameters],
+  image_vec: &[Image],
+  bos_sessiontemplate_vec: &[BosSessionTemplate],
+) -> Vec<GroupBootImageReport> {
+  let image_by_id: HashMap<&str, &Image> = image_vec
+    .iter()
+    .filter_map(|image| image.id.as_deref().map(|id| (id, image)))
+    .collect();
+
+  let boot_image_by_xname: HashMap<&str, String> = boot_param_vec
+    .iter()
+    .flat_map(|boot_params| {
+      let image_id = boot_params.get_boot_image();
+      boot_params
+        .hosts
+        .iter()
+        .map(move |xname| (xname.as_str(), image_id.clone()))
+    })
+    .filter(|(_, image_id)| !image_id.is_empty())
+    .collect();
+
+  group_vec
+    .iter()
+    .map(|group| {
+      let hsm_group = group.label.as_str().to_string();
+
+      let latest_opt = bos_sessiontemplate_vec
+        .iter()
+        .filter(|template| template.get_target_hsm().contains(&hsm_group))
+        .filter_map(|template| {
+          let image_id = template.images_id().next()?;
+          let created = image_by_id
+            .get(image_id)
+            .and_then(|image| image.created.as_deref())
+            .unwrap_or("");
+          Some((created, template, image_id))
+        })
+        .max_by_key(|(created, _, _)| *created);
+
+      let expected_image_id =
+        latest_opt.map(|(_, _, image_id)| image_id.to_string());
+
+      let mut xnames_by_image: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+      let mut mismatched_nodes = Vec::new();
+      let mut unknown_nodes = Vec::new();
+
+      for xname in group.get_members() {
+        let Some(image_id) = boot_image_by_xname.get(xname.as_str()) else {
+          unknown_nodes.push(xname);
+          continue;
+        };
+
+        if expected_image_id
+          .as_ref()
+          .is_some_and(|expected| expected != image_id)
+        {
+          mismatched_nodes.push(xname.clone());
+        }
+
+        xnames_by_image
+          .entry(image_id.as_str())
+          .or_default()
+          .push(xname);
+      }
+
+      let mut images: Vec<BootImageUsage> = xnames_by_image
+        .into_iter()
+        .map(|(image_id, xnames)| BootImageUsage {
+          image_id: image_id.to_string(),
+          image_name: image_by_id.get(image_id).map(|image| image.name.clone()),
+          xnames,
+        })
+        .collect();
+      images.sort_by_key(|usage| std::cmp::Reverse(usage.node_count()));
+
+      GroupBootImageReport {
+        hsm_group,
+        latest_bos_template: latest_opt
+          .and_then(|(_, template, _)| template.name.clone()),
+        expected_image_id,
+        images,
+        mismatched_nodes,
+        unknown_nodes,
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bos::BootSet;
+
+  fn image(id: &str, name: &str, created: &str) -> Image {
+    Image {
+      id: Some(id.to_string()),
+      name: name.to_string(),
+      created: Some(created.to_string()),
+      ..Default::default()
+    }
+  }
+
+  fn boot_params(xname: &str, image_id: &str) -> BootParameters {
+    BootParameters {
+      hosts: vec![xname.to_string()],
+      params: format!(
+        "root=craycps-s3:s3://boot-images/{image_id}/rootfs:etag:dvs"
+      ),
+      ..Default::default()
+    }
+  }
+
+  fn template(name: &str, group: &str, image_id: &str) -> BosSessionTemplate {
+    let boot_set = BootSet {
+      name: None,
+      path: Some(format!("s3://boot-images/{image_id}/manifest.json")),
+      cfs: None,
+      r#type: None,
+      etag: None,
+      kernel_parameters: None,
+      node_list: None,
+      node_roles_groups: None,
+      node_groups: Some(vec![group.to_string()]),
+      arch: None,
+      rootfs_provider: None,
+      rootfs_provider_passthrough: None,
+    };
+
+    BosSessionTemplate {
+      name: Some(name.to_string()),
+      tenant: None,
+      description: None,
+      enable_cfs: None,
+      cfs: None,
+      boot_sets: Some(HashMap::from([("compute".to_string(), boot_set)])),
+      links: None,
+    }
+  }
+
+  #[test]
+  fn report_counts_images_and_flags_nodes_behind_latest_template() {
+    let group = Group::new_with_members(
+      "zinal",
+      Some(vec!["x1000c0s0b0n0", "x1000c0s0b0n1", "x1000c0s0b1n0"]),
+    );
+
+    let report = build_report(
+      &[group],
+      &[
+        boot_params("x1000c0s0b0n0", "new"),
+        boot_params("x1000c0s0b0n1", "old"),
+      ],
+      &[
+        image("old", "compute-1.0", "2024-01-01T00:00:00"),
+        image("new", "compute-1.1", "2024-02-01T00:00:00"),
+      ],
+      &[
+        template("zinal-1.0", "zinal", "old"),
+        template("zinal-1.1", "zinal", "new"),
+        template("daint-1.1", "daint", "new"),
+      ],
+    );
+
+    assert_eq!(report.len(), 1);
+    let zinal = &report[0];
+    assert_eq!(zinal.latest_bos_template.as_deref(), Some("zinal-1.1"));
+    assert_eq!(zinal.expected_image_id.as_deref(), Some("new"));
+    assert_eq!(zinal.images.len(), 2);
+    assert_eq!(zinal.mismatched_nodes, vec!["x1000c0s0b0n1".to_string()]);
+    assert_eq!(zinal.unknown_nodes, vec!["x1000c0s0b1n0".to_string()]);
+  }
+}
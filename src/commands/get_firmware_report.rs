@@ -0,0 +1,508 @@
+//! Report the firmware of the nodes of an HSM group against a baseline.
+//!
+//! Joins the HSM Redfish endpoints (the BMC of each node), a FAS
+//! snapshot (the firmware currently installed behind each BMC) and the
+//! HSM hardware inventory (node models), and compares every firmware
+//! target with the version a [`FirmwareBaseline`] expects for the node
+//! model.
+//!
+//! The baseline is a YAML file listing the expected version per target,
+//! optionally restricted to some node models:
+//!
+//! ```yaml
+//! targets:
+//!   - target: BMC
+//!     version: nc.1.9.4
+//!   - target: BIOS
+//!     version: ex425.bios-1.7.3
+//!     models:
+//!       - HPE Cray EX425
+//! ```
+//!
+//! An entry listing the node model wins over one listing no model.
+
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use chrono::Utc;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  common::{events, poll::WaitOptions, xname::NodeXName},
+  error::Error,
+  fas::types::{
+    Snapshot, SnapshotParameters, SnapshotTarget, StateComponentFilter,
+  },
+  hsm::group::GroupExt,
+};
+
+/// How long [`get_firmware_report`] waits for the FAS snapshot unless
+/// told otherwise: 10 minutes, checking after 5 s and backing off to
+/// 30 s.
+pub const DEFAULT_WAIT: WaitOptions =
+  WaitOptions::new(Duration::from_secs(600), Duration::from_secs(5))
+    .with_backoff(Duration::from_secs(30));
+
+/// Nodes whose hardware inventory is read concurrently.
+const INVENTORY_BATCH_SIZE: usize = 10;
+
+/// Expected firmware version of one target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineTarget {
+  /// FAS target name, without the `Node<N>.` prefix, e.g. `BIOS`.
+  pub target: String,
+  /// Expected firmware version.
+  pub version: String,
+  /// Node models the entry applies to, every model if empty.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub models: Vec<String>,
+}
+
+/// Expected firmware versions, read from a YAML file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareBaseline {
+  /// Expected version per target.
+  pub targets: Vec<BaselineTarget>,
+}
+
+impl FirmwareBaseline {
+  /// Parse a baseline.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::SerdeYamlError`] if `yaml` isn't a baseline.
+  pub fn from_yaml(yaml: &str) -> Result<Self, Error> {
+    Ok(serde_yaml::from_str(yaml)?)
+  }
+
+  /// Read a baseline from the YAML file at `path`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant if the file can't be read or isn't a
+  /// baseline.
+  pub fn read(path: &Path) -> Result<Self, Error> {
+    Self::from_yaml(&std::fs::read_to_string(path)?)
+  }
+
+  /// Version of `target` expected on nodes of model `model`.
+  #[must_use]
+  pub fn expected(&self, target: &str, model: Option<&str>) -> Option<&str> {
+    let mut entry_iter =
+      self.targets.iter().filter(|entry| entry.target == target);
+
+    entry_iter
+      .clone()
+      .find(|entry| {
+        model.is_some_and(|model| entry.models.iter().any(|m| m == model))
+      })
+      .or_else(|| entry_iter.find(|entry| entry.models.is_empty()))
+      .map(|entry| entry.version.as_str())
+  }
+}
+
+/// Firmware of one target of a node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FirmwareTargetStatus {
+  /// FAS target name, without the `Node<N>.` prefix.
+  pub target: String,
+  /// Version installed, `None` if FAS could not read it.
+  pub current: Option<String>,
+  /// Version the baseline expects, `None` if it has none for the
+  /// target.
+  pub expected: Option<String>,
+  /// Whether both versions are known and differ.
+  pub out_of_date: bool,
+  /// Why the installed version is unknown.
+  pub error: Option<String>,
+}
+
+/// Firmware of one node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeFirmware {
+  /// Node xname.
+  pub xname: String,
+  /// Node model, from the HSM hardware inventory.
+  pub model: Option<String>,
+  /// FQDN of the node BMC Redfish endpoint, `None` if HSM has none.
+  pub redfish_endpoint: Option<String>,
+  /// Firmware targets, sorted by name.
+  pub targets: Vec<FirmwareTargetStatus>,
+}
+
+impl NodeFirmware {
+  /// Whether any target is out of date.
+  #[must_use]
+  pub fn is_out_of_date(&self) -> bool {
+    self.targets.iter().any(|target| target.out_of_date)
+  }
+}
+
+/// Firmware of the nodes of an HSM group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FirmwareReport {
+  /// HSM group label.
+  pub hsm_group: String,
+  /// When FAS read the firmware.
+  pub capture_time: Option<String>,
+  /// Nodes, sorted by xname.
+  pub nodes: Vec<NodeFirmware>,
+}
+
+impl FirmwareReport {
+  /// Nodes with a target out of date.
+  pub fn out_of_date_nodes(&self) -> impl Iterator<Item = &NodeFirmware> {
+    self.nodes.iter().filter(|node| node.is_out_of_date())
+  }
+}
+
+/// Build the [`FirmwareReport`] of HSM group `hsm_group_name` against
+/// `baseline`, waiting for the FAS snapshot as `wait_options` says.
+///
+/// The FAS snapshot taken for the report is deleted afterwards.
+///
+/// # Errors
+///
+/// Returns [`Error::Message`] if the FAS snapshot isn't ready in time,
+/// or an [`Error`] variant on CSM, transport, or deserialization
+/// failure; see the crate-level `Error` enum for the full set.
+pub async fn get_firmware_report(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  hsm_group_name: &str,
+  baseline: &FirmwareBaseline,
+  wait_options: WaitOptions,
+) -> Result<FirmwareReport, Error> {
+  let _timer =
+    crate::common::metrics::CommandTimer::start("get_firmware_report");
+
+  let mut xname_vec = client
+    .hsm_group_get_one(shasta_token, hsm_group_name)
+    .await?
+    .get_members();
+  xname_vec.sort();
+  xname_vec.dedup();
+
+  let mut bmc_xname_vec: Vec<String> = xname_vec
+    .iter()
+    .filter_map(|xname| NodeXName::parse(xname).ok())
+    .map(|xname| xname.bmc_xname().to_string())
+    .collect();
+  bmc_xname_vec.sort();
+  bmc_xname_vec.dedup();
+
+  events::step(format!(
+    "Read HSM Redfish endpoints and node models of HSM group '{hsm_group_name}'"
+  ));
+  let (redfish_endpoint_array, model_vec) = tokio::try_join!(
+    client.hsm_redfish_get(
+      shasta_token,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None
+    ),
+    async {
+      futures::stream::iter(&xname_vec)
+        .map(|xname| async move {
+          let hw_inventory = client
+            .hsm_hw_inventory_get_query(shasta_token, xname)
+            .await?;
+          let model = hw_inventory
+            .nodes
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|node| node.populated_fru?.node_fru_info.model);
+          Ok::<_, Error>((xname.clone(), model))
+        })
+        .buffer_unordered(INVENTORY_BATCH_SIZE)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, Error>>()
+    },
+  )?;
+
+  let fqdn_by_bmc: HashMap<String, String> = redfish_endpoint_array
+    .redfish_endpoints
+    .unwrap_or_default()
+    .into_iter()
+    .filter_map(|redfish_endpoint| {
+      Some((redfish_endpoint.id, redfish_endpoint.fqdn?))
+    })
+    .collect();
+  let model_by_xname: HashMap<String, String> = model_vec
+    .into_iter()
+    .filter_map(|(xname, model)| Some((xname, model?)))
+    .collect();
+
+  events::step(format!("Take FAS snapshot of {} BMCs", bmc_xname_vec.len()));
+  let snapshot_name = client
+    .fas_snapshot_post(
+      shasta_token,
+      &SnapshotParameters {
+        name: format!(
+          "csm-rs-firmware-{hsm_group_name}-{}",
+          Utc::now().timestamp()
+        ),
+        expiration_time: Some(
+          (Utc::now() + chrono::Duration::days(1)).to_rfc3339(),
+        ),
+        state_component_filter: Some(StateComponentFilter {
+          xnames: bmc_xname_vec,
+        }),
+      },
+    )
+    .await?;
+
+  let snapshot_result = crate::common::poll::poll_until_with_backoff(
+    wait_options.poll_backoff(),
+    || client.fas_snapshot_get(shasta_token, &snapshot_name),
+    |snapshot| snapshot.ready,
+  )
+  .await;
+
+  if let Err(e) = client
+    .fas_snapshot_delete(shasta_token, &snapshot_name)
+    .await
+  {
+    events::warning(format!(
+      "Could not delete FAS snapshot '{snapshot_name}': {e}"
+    ));
+  }
+
+  let snapshot = snapshot_result?;
+  if !snapshot.ready {
+    return Err(Error::Message(format!(
+      "FAS snapshot '{snapshot_name}' not ready after {}s",
+      wait_options.timeout.as_secs()
+    )));
+  }
+
+  Ok(FirmwareReport {
+    hsm_group: hsm_group_name.to_string(),
+    capture_time: snapshot.capture_time.clone(),
+    nodes: build_report(
+      &xname_vec,
+      &fqdn_by_bmc,
+      &model_by_xname,
+      &snapshot,
+      baseline,
+    ),
+  })
+}
+
+/// Pure part of [`get_firmware_report`], split out for testing.
+fn build_report(
+  xname_vec: &[String],
+  fqdn_by_bmc: &HashMap<String, String>,
+  model_by_xname: &HashMap<String, String>,
+  snapshot: &Snapshot,
+  baseline: &FirmwareBaseline,
+) -> Vec<NodeFirmware> {
+  xname_vec
+    .iter()
+    .map(|xname| {
+      let node_xname = NodeXName::parse(xname).ok();
+      let bmc_xname = node_xname
+        .as_ref()
+        .map(|node_xname| node_xname.bmc_xname().to_string());
+      let model = model_by_xname.get(xname).cloned();
+
+      let device_opt = snapshot
+        .devices
+        .iter()
+        .find(|device| Some(&device.xname) == bmc_xname.as_ref());
+
+      // Targets named `Node<N>.<target>` belong to node N of the BMC
+      let mut target_map: HashMap<String, &SnapshotTarget> = HashMap::new();
+      for target in device_opt
+        .map(|device| &device.targets)
+        .into_iter()
+        .flatten()
+      {
+        match target.name.split_once('.') {
+          Some((node, name)) if node.starts_with("Node") => {
+            if node_xname.as_ref().is_some_and(|node_xname| {
+              node == format!("Node{}", node_xname.node())
+            }) {
+              target_map.insert(name.to_string(), target);
+            }
+          }
+          _ => {
+            target_map.insert(target.name.clone(), target);
+          }
+        }
+      }
+
+      let mut target_name_vec: Vec<String> =
+        target_map.keys().cloned().collect();
+      target_name_vec.extend(
+        baseline
+          .targets
+          .iter()
+          .filter(|entry| {
+            baseline.expected(&entry.target, model.as_deref()).is_some()
+          })
+          .map(|entry| entry.target.clone()),
+      );
+      target_name_vec.sort();
+      target_name_vec.dedup();
+
+      let targets = target_name_vec
+        .into_iter()
+        .map(|target_name| {
+          let target_opt = target_map.get(&target_name);
+          let current =
+            target_opt.and_then(|target| target.firmware_version.clone());
+          let expected = baseline
+            .expected(&target_name, model.as_deref())
+            .map(str::to_string);
+
+          let error = if current.is_some() {
+            None
+          } else {
+            Some(
+              target_opt
+                .and_then(|target| target.error.clone())
+                .or_else(|| device_opt.and_then(|device| device.error.clone()))
+                .unwrap_or_else(|| "not in FAS snapshot".to_string()),
+            )
+          };
+
+          FirmwareTargetStatus {
+            out_of_date: current.is_some()
+              && expected.is_some()
+              && current != expected,
+            target: target_name,
+            current,
+            expected,
+            error,
+          }
+        })
+        .collect();
+
+      NodeFirmware {
+        xname: xname.clone(),
+        model,
+        redfish_endpoint: bmc_xname
+          .and_then(|bmc_xname| fqdn_by_bmc.get(&bmc_xname).cloned()),
+        targets,
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fas::types::SnapshotDevice;
+
+  const BASELINE: &str = "
+targets:
+  - target: BMC
+    version: nc.1.9.4
+  - target: BIOS
+    version: bios-1.7.3
+  - target: BIOS
+    version: bios-2.0.0
+    models:
+      - HPE Cray EX425
+";
+
+  fn target(name: &str, firmware_version: &str) -> SnapshotTarget {
+    SnapshotTarget {
+      name: name.to_string(),
+      firmware_version: Some(firmware_version.to_string()),
+      error: None,
+    }
+  }
+
+  #[test]
+  fn baseline_prefers_model_specific_entry() {
+    let baseline = FirmwareBaseline::from_yaml(BASELINE).unwrap();
+
+    assert_eq!(baseline.expected("BMC", None), Some("nc.1.9.4"));
+    assert_eq!(baseline.expected("BIOS", None), Some("bios-1.7.3"));
+    assert_eq!(
+      baseline.expected("BIOS", Some("HPE Cray EX425")),
+      Some("bios-2.0.0")
+    );
+    assert_eq!(baseline.expected("HSN", None), None);
+  }
+
+  #[test]
+  fn report_joins_snapshot_models_and_baseline() {
+    let baseline = FirmwareBaseline::from_yaml(BASELINE).unwrap();
+    let snapshot = Snapshot {
+      name: "snap".to_string(),
+      ready: true,
+      devices: vec![SnapshotDevice {
+        xname: "x1000c0s0b0".to_string(),
+        targets: vec![
+          target("BMC", "nc.1.9.4"),
+          target("Node0.BIOS", "bios-1.7.3"),
+          target("Node1.BIOS", "bios-2.0.0"),
+        ],
+        error: None,
+      }],
+      ..Default::default()
+    };
+    let model_by_xname = HashMap::from([(
+      "x1000c0s0b0n0".to_string(),
+      "HPE Cray EX425".to_string(),
+    )]);
+    let fqdn_by_bmc = HashMap::from([(
+      "x1000c0s0b0".to_string(),
+      "x1000c0s0b0.hmn".to_string(),
+    )]);
+
+    let node_vec = build_report(
+      &[
+        "x1000c0s0b0n0".to_string(),
+        "x1000c0s0b0n1".to_string(),
+        "x1000c0s1b0n0".to_string(),
+      ],
+      &fqdn_by_bmc,
+      &model_by_xname,
+      &snapshot,
+      &baseline,
+    );
+
+    // Model specific BIOS baseline, older BIOS installed
+    assert_eq!(
+      node_vec[0].redfish_endpoint.as_deref(),
+      Some("x1000c0s0b0.hmn")
+    );
+    assert_eq!(node_vec[0].targets.len(), 2);
+    assert_eq!(node_vec[0].targets[0].target, "BIOS");
+    assert_eq!(
+      node_vec[0].targets[0].current.as_deref(),
+      Some("bios-1.7.3")
+    );
+    assert_eq!(
+      node_vec[0].targets[0].expected.as_deref(),
+      Some("bios-2.0.0")
+    );
+    assert!(node_vec[0].is_out_of_date());
+
+    // Generic BIOS baseline, newer BIOS installed
+    assert_eq!(
+      node_vec[1].targets[0].current.as_deref(),
+      Some("bios-2.0.0")
+    );
+    assert!(node_vec[1].targets[0].out_of_date);
+    assert!(!node_vec[1].targets[1].out_of_date);
+
+    // BMC missing from the snapshot
+    assert!(!node_vec[2].is_out_of_date());
+    assert_eq!(node_vec[2].redfish_endpoint, None);
+    assert_eq!(
+      node_vec[2].targets[0].error.as_deref(),
+      Some("not in FAS snapshot")
+    );
+  }
+}
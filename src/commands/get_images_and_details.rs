@@ -22,6 +22,9 @@ pub async fn get_images_and_details(
   id_opt: Option<&str>,
   limit_number: Option<&u8>,
 ) -> Result<Vec<(Image, String, String, bool)>, Error> {
+  let _timer =
+    crate::common::metrics::CommandTimer::start("get_images_and_details");
+
   crate::ims::image::utils::get_with_details(
     client,
     shasta_token,
@@ -1,23 +1,36 @@
 //! Entry-point function for the apply-SAT-file workflow.
 
-use std::{
-  collections::{BTreeMap, HashMap},
-  time::Instant,
-};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
 use serde_yaml::Value;
 
 use crate::{
-  bos::{BosSession, BosSessionTemplate},
-  cfs::v2::CfsConfigurationResponse,
+  ExecutionContext,
+  bos::{BosSession, BosSessionTemplate, session::reboot::RebootStrategy},
+  cfs::{configuration::types::ResolvedLayers, v2::CfsConfigurationResponse},
   commands::{
     apply_hw_cluster_pin,
-    i_apply_sat_file::utils::{self, SatFile},
+    i_apply_sat_file::provenance::{self, SatProvenance},
+    i_apply_sat_file::utils::{
+      self, SatFile,
+      images::{
+        CfsSessionRetryPolicy, ImageBuildContext, ImageBuildReport,
+        ImageNameConflictPolicy,
+      },
+      validation::ValidationReport,
+    },
+  },
+  common::{
+    events, kubernetes,
+    naming::NamingContext,
+    poll::WaitOptions,
+    product_catalog::{self, ProductCatalog},
+    session_profile::{self, SessionProfile},
+    webhooks,
   },
-  common::kubernetes::{self},
   error::Error,
   hsm::group::utils::update_hsm_group_members,
-  ims::Image as ImsImage,
+  ims::{Image as ImsImage, PublicKeySelector},
 };
 
 /// Borrowed bundle of connection, auth, and feature-flag inputs shared
@@ -29,6 +42,7 @@ use crate::{
 /// shareable across `await` points.
 struct SatApplyContext<'a> {
   shasta_token: &'a str,
+  execution_context: &'a ExecutionContext,
   shasta_base_url: &'a str,
   shasta_root_cert: &'a [u8],
   socks5_proxy: Option<&'a str>,
@@ -37,7 +51,7 @@ struct SatApplyContext<'a> {
   k8s_api_url: &'a str,
   gitea_base_url: &'a str,
   gitea_token: &'a str,
-  hsm_group_available_vec: &'a [String],
+  hsm_group_available_vec: Vec<String>,
   ansible_verbosity: Option<u8>,
   ansible_passthrough: Option<&'a str>,
   reboot: bool,
@@ -45,9 +59,91 @@ struct SatApplyContext<'a> {
   timestamps: bool,
   debug_on_failure: bool,
   overwrite: bool,
+  options: &'a SatApplyOptions<'a>,
   dry_run: bool,
 }
 
+/// How [`exec`] applies the SAT file, beyond what `sat bootprep` asks
+/// for on its command line.
+#[derive(Debug, Clone)]
+pub struct SatApplyOptions<'a> {
+  /// Product catalog to validate against; when `None` it is read from
+  /// Kubernetes (see [`crate::product_catalog::fetch`] for caching).
+  pub cray_product_catalog: Option<Arc<ProductCatalog>>,
+  /// CFS session options to use where `ansible_verbosity_opt`,
+  /// `ansible_passthrough_opt` and `debug_on_failure` don't say
+  /// otherwise; its `ansible_limit` and `require_dkms` apply to the
+  /// image builds. See [`SessionProfile`].
+  pub session_profile: Option<&'a SessionProfile>,
+  /// With `reboot`, one BOS `reboot` session or a staged shutdown +
+  /// boot; see [`RebootStrategy`].
+  pub reboot_strategy: RebootStrategy,
+  /// With `reboot`, first delete pending or running BOS sessions
+  /// booting any of the target nodes; otherwise they race with the
+  /// reboot and often make it fail.
+  pub cancel_stale_sessions: bool,
+  /// What to do with `images` entries whose name is already taken in
+  /// IMS (skip, rebuild and delete the old image, rebuild under a new
+  /// name, or build a duplicate).
+  pub image_name_conflict_policy: ImageNameConflictPolicy,
+  /// Let `overwrite` replace protected CFS configurations, and
+  /// [`ImageNameConflictPolicy::Overwrite`] delete images booted by
+  /// protected BOS session templates (see [`crate::protection`]);
+  /// otherwise they fail with [`Error::Protected`].
+  pub ignore_protection: bool,
+  /// When to re-submit an image-build CFS session that failed on a
+  /// transient Ansible error (repo timeouts, registry pulls, ...).
+  pub cfs_session_retry_policy: CfsSessionRetryPolicy,
+  /// How long, and how often, to wait for each CFS session building an
+  /// `images` entry.
+  pub cfs_session_wait: WaitOptions,
+  /// How long, and how often, to wait for each IMS job building the
+  /// base image of an `images` entry before giving up on it.
+  pub ims_job_wait: WaitOptions,
+  /// Which IMS public key the IMS jobs building base images are
+  /// created with.
+  pub ims_public_key_selector: PublicKeySelector,
+}
+
+impl Default for SatApplyOptions<'_> {
+  /// What `sat bootprep` does: one BOS `reboot` session, duplicate
+  /// image names allowed, no retries, and the CFS session and IMS job
+  /// default waits.
+  fn default() -> Self {
+    Self {
+      cray_product_catalog: None,
+      session_profile: None,
+      reboot_strategy: RebootStrategy::default(),
+      cancel_stale_sessions: false,
+      image_name_conflict_policy: ImageNameConflictPolicy::default(),
+      ignore_protection: false,
+      cfs_session_retry_policy: CfsSessionRetryPolicy::default(),
+      cfs_session_wait: crate::cfs::session::utils::DEFAULT_WAIT,
+      ims_job_wait: crate::ims::job::utils::DEFAULT_WAIT,
+      ims_public_key_selector: PublicKeySelector::default(),
+    }
+  }
+}
+
+/// What [`exec`] created from each section of a SAT file.
+#[derive(Debug, Default)]
+pub struct SatApplyOutcome {
+  /// CFS configurations created from `configurations`.
+  pub configurations: Vec<CfsConfigurationResponse>,
+  /// Refs the layers of each of `configurations` were pinned to, in the
+  /// same order.
+  pub resolved_layers: Vec<ResolvedLayers>,
+  /// IMS images built from `images`.
+  pub images: Vec<ImsImage>,
+  /// CFS sessions submitted to build each of `images`, in the same
+  /// order.
+  pub image_build_reports: Vec<ImageBuildReport>,
+  /// BOS session templates created from `session_templates`.
+  pub session_templates: Vec<BosSessionTemplate>,
+  /// BOS sessions rebooting the nodes, empty unless `reboot` is `true`.
+  pub sessions: Vec<BosSession>,
+}
+
 /// Apply a SAT (System Admin Toolkit) template file against a Shasta system.
 ///
 /// Parses `sat_template_file_yaml`, validates each section against the
@@ -65,24 +161,28 @@ struct SatApplyContext<'a> {
 /// # Arguments
 ///
 /// - `sat_template_file_yaml` — the parsed SAT file as YAML.
+/// - `execution_context` — who applies the SAT file; tags the changes
+///   made and restricts `hsm_group_available_vec` to its tenancy.
 /// - `hsm_group_available_vec` — HSM groups the caller is allowed to
 ///   target; used to reject SAT files that reference out-of-scope groups.
 /// - `shasta_k8s_secrets` / `k8s_api_url` — credentials for the in-cluster
 ///   `cray-product-catalog` `ConfigMap` lookup.
 /// - `dry_run` — when `true`, validates and logs the intended actions
-///   without mutating CSM.
-/// - `overwrite` — replace existing CFS configurations / images with the
-///   same name instead of failing.
+///   without mutating CSM; a requested reboot is simulated from live
+///   HSM, BSS and PCS data (see [`crate::bos::session::reboot::simulate`]).
+/// - `overwrite` — replace existing CFS configurations with the same
+///   name instead of failing.
 /// - `reboot` — after creating BOS session templates, also reboot the
 ///   target nodes through them.
+/// - `options` — image build, reboot and protection settings; see
+///   [`SatApplyOptions`].
 ///
 /// # Returns
 ///
-/// `(configurations, images, session_templates, sessions)` — the
-/// artifacts created from each section of the SAT file. In `dry_run`
-/// mode the same tuple is returned populated with the artifacts that
-/// *would* have been created. `sessions` is empty unless `reboot` is
-/// `true`.
+/// The [`SatApplyOutcome`]: the artifacts created from each section of
+/// the SAT file, and the refs each configuration layer was pinned to.
+/// In `dry_run` mode it holds the artifacts that *would* have been
+/// created.
 ///
 /// # Errors
 ///
@@ -90,12 +190,15 @@ struct SatApplyContext<'a> {
 /// live CSM state fails, or any underlying API call (CFS, IMS, BOS, HSM,
 /// Kubernetes) fails.
 ///
-/// When `watch_logs` is true the CFS-session container logs are
-/// streamed line-by-line through `log::info!`; output is routed by the
-/// caller's `log` backend rather than written directly to stdout.
+/// Progress is reported as [`crate::Event`]s to the
+/// [`crate::EventSink`] of the client in scope. When `watch_logs` is
+/// true the CFS-session container logs are streamed line-by-line
+/// through `tracing::info!`; output is routed by the caller's `tracing`
+/// subscriber rather than written directly to stdout.
 #[allow(clippy::too_many_arguments)]
 pub async fn exec(
   shasta_token: &str,
+  execution_context: &ExecutionContext,
   shasta_base_url: &str,
   shasta_root_cert: &[u8],
   socks5_proxy: Option<&str>,
@@ -114,18 +217,19 @@ pub async fn exec(
   timestamps: bool,
   debug_on_failure: bool,
   overwrite: bool,
+  options: &SatApplyOptions<'_>,
   dry_run: bool,
-) -> Result<
-  (
-    Vec<CfsConfigurationResponse>,
-    Vec<ImsImage>,
-    Vec<BosSessionTemplate>,
-    Vec<BosSession>,
-  ),
-  Error,
-> {
+) -> Result<SatApplyOutcome, Error> {
+  let _timer = crate::common::metrics::CommandTimer::start("apply_sat_file");
+
+  let session_profile = options
+    .session_profile
+    .map(|profile| profile.render(&NamingContext::new(shasta_token)))
+    .unwrap_or_default();
+
   let ctx = SatApplyContext {
     shasta_token,
+    execution_context,
     shasta_base_url,
     shasta_root_cert,
     socks5_proxy,
@@ -134,94 +238,135 @@ pub async fn exec(
     k8s_api_url,
     gitea_base_url,
     gitea_token,
-    hsm_group_available_vec,
-    ansible_verbosity: ansible_verbosity_opt,
-    ansible_passthrough: ansible_passthrough_opt,
+    hsm_group_available_vec: execution_context
+      .filter_groups(hsm_group_available_vec),
+    ansible_verbosity: ansible_verbosity_opt
+      .or(session_profile.ansible_verbosity),
+    ansible_passthrough: ansible_passthrough_opt
+      .or(session_profile.ansible_passthrough.as_deref()),
     reboot,
     watch_logs,
     timestamps,
-    debug_on_failure,
+    debug_on_failure: debug_on_failure || session_profile.debug_on_failure,
     overwrite,
+    options,
     dry_run,
   };
 
+  execution_context
+    .scope(crate::common::request_id::scope("apply_sat_file", async {
+      // Stamp everything the run creates with where it comes from
+      let provenance = SatProvenance::new(
+        crate::common::request_id::current().unwrap_or_default(),
+        ctx.shasta_token,
+        &sat_template_file_yaml,
+      )?;
+      events::info(format!("SAT apply run id: {}", provenance.run_id));
+
+      provenance::scope(
+        provenance,
+        session_profile::scope(
+          session_profile.clone(),
+          apply(&ctx, shasta_k8s_secrets, sat_template_file_yaml),
+        ),
+      )
+      .await
+    }))
+    .await
+}
+
+/// Body of [`exec`], run inside its correlation scope.
+async fn apply(
+  ctx: &SatApplyContext<'_>,
+  shasta_k8s_secrets: serde_json::Value,
+  sat_template_file_yaml: serde_yaml::Value,
+) -> Result<SatApplyOutcome, Error> {
   // GET DATA
   //
   // Parse the SAT file and fetch the live CSM / k8s state it is validated
   // against.
-  let (sat_file, cray_product_catalog, configuration_vec, image_vec, ims_recipe_vec) =
-    gather_sat_apply_data(
-      &ctx,
-      shasta_k8s_secrets,
-      &sat_template_file_yaml,
-    )
+  let (
+    sat_file,
+    cray_product_catalog,
+    configuration_vec,
+    image_vec,
+    ims_recipe_vec,
+  ) = gather_sat_apply_data(ctx, shasta_k8s_secrets, &sat_template_file_yaml)
     .await?;
 
   // VALIDATION
   //
-  // Validate the SAT file sections against the live CSM state.
+  // Validate the SAT file sections against the live CSM state. Warnings
+  // are logged and don't stop the apply
   validate_sat_file_sections(
-    &ctx,
+    ctx,
     &sat_file,
     &cray_product_catalog,
     image_vec,
     configuration_vec,
     ims_recipe_vec,
   )
-  .await?;
+  .await?
+  .into_result(true)?;
 
   // PROCESS SAT FILE
   //
   // Process "hardware" / "clusters" section in SAT file
-  process_hardware_section(&ctx, &sat_file).await?;
+  process_hardware_section(ctx, &sat_file).await?;
 
   // Process "configurations" section in SAT file
-  let cfs_configurations_created = process_configurations_section(
-    &ctx,
-    &cray_product_catalog,
-    &sat_template_file_yaml,
-  )
-  .await?;
+  let (cfs_configurations_created, resolved_layers_vec) =
+    process_configurations_section(
+      ctx,
+      &cray_product_catalog,
+      &sat_template_file_yaml,
+    )
+    .await?;
 
   // Process "images" section in SAT file
   //
-  log::info!("Process images section in SAT file");
+  events::step("Process images section in SAT file");
   let image_struct_vec = sat_file.images.as_deref().unwrap_or_default();
   // List of image.ref_name already processed
   let mut ref_name_processed_hashmap: HashMap<String, String> = HashMap::new();
 
-  let images_created: Vec<ImsImage> =
+  let image_build_context = ImageBuildContext {
+    shasta_token: ctx.shasta_token,
+    shasta_base_url: ctx.shasta_base_url,
+    shasta_root_cert: ctx.shasta_root_cert,
+    socks5_proxy: ctx.socks5_proxy,
+    vault_base_url: ctx.vault_base_url,
+    site_name: ctx.site_name,
+    k8s_api_url: ctx.k8s_api_url,
+    cray_product_catalog: &cray_product_catalog,
+    ansible_verbosity: ctx.ansible_verbosity,
+    ansible_passthrough: ctx.ansible_passthrough,
+    debug_on_failure: ctx.debug_on_failure,
+    dry_run: ctx.dry_run,
+    watch_logs: ctx.watch_logs,
+    timestamps: ctx.timestamps,
+    cfs_session_retry_policy: &ctx.options.cfs_session_retry_policy,
+    cfs_session_wait: ctx.options.cfs_session_wait,
+    ims_job_wait: ctx.options.ims_job_wait,
+    ims_public_key_selector: &ctx.options.ims_public_key_selector,
+  };
+  let (images_created, image_build_report_vec) =
     utils::i_import_images_section_in_sat_file(
-      ctx.shasta_token,
-      ctx.shasta_base_url,
-      ctx.shasta_root_cert,
-      ctx.socks5_proxy,
-      ctx.vault_base_url,
-      ctx.site_name,
-      ctx.k8s_api_url,
+      &image_build_context,
       &mut ref_name_processed_hashmap,
       image_struct_vec,
-      &cray_product_catalog,
-      ctx.ansible_verbosity,
-      ctx.ansible_passthrough,
-      ctx.debug_on_failure,
-      ctx.dry_run,
-      ctx.watch_logs,
-      ctx.timestamps,
+      ctx.options.image_name_conflict_policy,
+      ctx.options.ignore_protection,
     )
     .await?;
 
-  log::info!(
-    "Images created: {:?}",
-    images_created
-      .iter()
-      .filter_map(|i| i.id.as_deref())
-      .collect::<Vec<&str>>()
-  );
+  for image in &images_created {
+    events::created("IMS image", image.id.as_deref().unwrap_or_default());
+  }
 
   // Process "session_templates" section in SAT file
   //
-  log::info!("Process session_template section in SAT file");
+  events::step("Process session_template section in SAT file");
   let (sessiontemplates_created, bos_sessions_created) =
     utils::process_session_template_section_in_sat_file(
       ctx.shasta_token,
@@ -229,24 +374,58 @@ pub async fn exec(
       ctx.shasta_root_cert,
       ctx.socks5_proxy,
       ref_name_processed_hashmap,
-      ctx.hsm_group_available_vec,
-      sat_template_file_yaml,
+      &ctx.hsm_group_available_vec,
+      sat_file.session_templates.as_deref().unwrap_or_default(),
       ctx.reboot,
+      ctx.options.reboot_strategy,
+      ctx.options.cancel_stale_sessions,
       ctx.dry_run,
     )
     .await?;
 
-  Ok((
-    cfs_configurations_created,
-    images_created,
-    sessiontemplates_created,
-    bos_sessions_created,
-  ))
+  if !ctx.dry_run {
+    let shasta_client = crate::ShastaClient::scoped_or_new(
+      ctx.shasta_base_url,
+      ctx.shasta_root_cert.to_vec(),
+      ctx.socks5_proxy.map(str::to_owned),
+    )?;
+    webhooks::notify(
+      &shasta_client,
+      webhooks::Operation::SatFileApplied {
+        configurations: cfs_configurations_created
+          .iter()
+          .map(|configuration| configuration.name.clone())
+          .collect(),
+        images: images_created
+          .iter()
+          .filter_map(|image| image.id.clone())
+          .collect(),
+        session_templates: sessiontemplates_created
+          .iter()
+          .filter_map(|sessiontemplate| sessiontemplate.name.clone())
+          .collect(),
+        sessions: bos_sessions_created
+          .iter()
+          .filter_map(|session| session.name.clone())
+          .collect(),
+      },
+    )
+    .await;
+  }
+
+  Ok(SatApplyOutcome {
+    configurations: cfs_configurations_created,
+    resolved_layers: resolved_layers_vec,
+    images: images_created,
+    image_build_reports: image_build_report_vec,
+    session_templates: sessiontemplates_created,
+    sessions: bos_sessions_created,
+  })
 }
 
 /// Parse the SAT file into a [`SatFile`] and fetch the live state it is
 /// validated against: the `cray-product-catalog` `ConfigMap` from Kubernetes
-/// and the current CFS configurations, IMS images and IMS recipes from CSM.
+/// (unless the caller provided the catalog) and the current CFS configurations, IMS images and IMS recipes from CSM.
 async fn gather_sat_apply_data(
   ctx: &SatApplyContext<'_>,
   shasta_k8s_secrets: serde_json::Value,
@@ -254,33 +433,39 @@ async fn gather_sat_apply_data(
 ) -> Result<
   (
     SatFile,
-    BTreeMap<String, String>,
+    Arc<ProductCatalog>,
     Vec<CfsConfigurationResponse>,
     Vec<ImsImage>,
     Vec<crate::ims::recipe::types::RecipeGetResponse>,
   ),
   Error,
 > {
-  // Get k8s credentials needed to check HPE/Cray product catalog in k8s
-  let kube_client = kubernetes::get_client(
-    ctx.k8s_api_url,
-    shasta_k8s_secrets,
-    ctx.socks5_proxy,
-  )
-  .await?;
+  // Get HPE product catalog from k8s unless the caller provided it
+  let cray_product_catalog = match &ctx.options.cray_product_catalog {
+    Some(cray_product_catalog) => Arc::clone(cray_product_catalog),
+    None => {
+      product_catalog::cached(ctx.k8s_api_url, || async {
+        let kube_client = kubernetes::get_client(
+          ctx.k8s_api_url,
+          shasta_k8s_secrets,
+          ctx.socks5_proxy,
+        )
+        .await?;
 
-  // Get HPE product catalog from k8s
-  let cray_product_catalog =
-    kubernetes::try_get_configmap(
-      kube_client,
-      crate::common::kubernetes::CRAY_PRODUCT_CATALOG_CONFIGMAP,
-    )
-    .await?;
+        kubernetes::try_get_configmap(
+          kube_client,
+          kubernetes::CRAY_PRODUCT_CATALOG_CONFIGMAP,
+        )
+        .await
+      })
+      .await?
+    }
+  };
 
   // Get data from CSM
   let start = Instant::now();
-  log::info!("Fetching data from the backend...");
-  let shasta_client = crate::ShastaClient::new(
+  events::step("Fetching data from the backend");
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     ctx.shasta_base_url,
     ctx.shasta_root_cert.to_vec(),
     ctx.socks5_proxy.map(str::to_owned),
@@ -292,7 +477,7 @@ async fn gather_sat_apply_data(
   )?;
 
   let duration = start.elapsed();
-  log::info!(
+  tracing::debug!(
     "Time elapsed to fetch information from backend: {duration:?}"
   );
 
@@ -309,18 +494,18 @@ async fn gather_sat_apply_data(
 }
 
 /// Validate the `configurations`, `images` and `session_templates` sections of
-/// the SAT file against the live CSM state.
+/// the SAT file against the live CSM state, collecting every problem found.
 ///
 /// `image_vec` / `configuration_vec` / `ims_recipe_vec` are the live CSM
 /// snapshots; they are consumed here (forwarded to the images validator).
 async fn validate_sat_file_sections(
   ctx: &SatApplyContext<'_>,
   sat_file: &SatFile,
-  cray_product_catalog: &BTreeMap<String, String>,
+  cray_product_catalog: &ProductCatalog,
   image_vec: Vec<ImsImage>,
   configuration_vec: Vec<CfsConfigurationResponse>,
   ims_recipe_vec: Vec<crate::ims::recipe::types::RecipeGetResponse>,
-) -> Result<(), Error> {
+) -> Result<ValidationReport, Error> {
   let configuration_struct_vec =
     sat_file.configurations.as_deref().unwrap_or_default();
   let image_struct_vec = sat_file.images.as_deref().unwrap_or_default();
@@ -328,37 +513,74 @@ async fn validate_sat_file_sections(
     sat_file.session_templates.as_deref().unwrap_or_default();
 
   // Validate 'configurations' section
-  utils::validate_sat_file_configurations_section(
+  let mut report = utils::validate_sat_file_configurations_section(
     configuration_struct_vec,
     image_struct_vec,
     bos_session_template_struct_vec,
-  )?;
+  );
+
+  // Validate the CFS sources referenced by 'configurations' exist
+  for configuration in configuration_struct_vec {
+    let source_name_vec: Vec<&str> = configuration
+      .layers
+      .iter()
+      .filter_map(|layer| match &layer.layer_type {
+        utils::configuration::LayerType::Source { source, .. } => {
+          Some(source.as_str())
+        }
+        _ => None,
+      })
+      .collect();
+
+    match crate::cfs::source::utils::validate_sources_exist(
+      ctx.shasta_token,
+      ctx.shasta_base_url,
+      ctx.shasta_root_cert,
+      ctx.socks5_proxy,
+      &source_name_vec,
+    )
+    .await
+    {
+      Ok(()) => {}
+      Err(Error::CfsSourceNotFound(source_name)) => report.error(
+        format!("configurations[{}].layers.source", configuration.name),
+        format!("CFS source '{source_name}' not found"),
+      ),
+      Err(e) => return Err(e),
+    }
+  }
 
   // Validate 'images' section
-  utils::validate_sat_file_images_section(
+  report.merge(utils::validate_sat_file_images_section(
     image_struct_vec,
     configuration_struct_vec,
-    ctx.hsm_group_available_vec,
+    &ctx.hsm_group_available_vec,
     cray_product_catalog,
     image_vec,
     configuration_vec,
     ims_recipe_vec,
-  )?;
+  ));
 
   // Validate 'session_template' section
-  utils::validate_sat_file_session_template_section(
-    ctx.shasta_token,
-    ctx.shasta_base_url,
-    ctx.shasta_root_cert,
-    ctx.socks5_proxy,
-    image_struct_vec,
-    configuration_struct_vec,
-    bos_session_template_struct_vec,
-    ctx.hsm_group_available_vec,
-  )
-  .await?;
+  report.merge(
+    utils::validate_sat_file_session_template_section(
+      ctx.shasta_token,
+      ctx.shasta_base_url,
+      ctx.shasta_root_cert,
+      ctx.socks5_proxy,
+      image_struct_vec,
+      configuration_struct_vec,
+      bos_session_template_struct_vec,
+      &ctx.hsm_group_available_vec,
+    )
+    .await?,
+  );
 
-  Ok(())
+  for warning in report.warnings() {
+    events::warning(format!("SAT file: {}: {}", warning.path, warning.message));
+  }
+
+  Ok(report)
 }
 
 /// Process the `hardware` section of the SAT file: apply component patterns to
@@ -369,23 +591,23 @@ async fn process_hardware_section(
   sat_file: &SatFile,
 ) -> Result<(), Error> {
   let hardware_patterns = sat_file.hardware.as_deref().unwrap_or_default();
-  log::info!("hardware pattern: {hardware_patterns:?}");
+  tracing::debug!("hardware pattern: {hardware_patterns:?}");
 
   for hw in hardware_patterns {
     let target_hsm_group_name = hw.target.as_str();
     let parent_hsm_group_name = hw.parent.as_str();
 
     if let Some(pattern) = hw.pattern.as_deref() {
-      log::info!(
+      events::step(format!(
         "Processing hw component pattern for '{pattern}' for target HSM group '{target_hsm_group_name}' and parent HSM group '{parent_hsm_group_name}'"
-      );
+      ));
       // When applying a SAT file, assume the caller does not want to
       // create new HSM groups or delete empty parent HSM groups (the
       // last three booleans below). This could be made configurable.
       if ctx.dry_run {
-        log::info!("Dry run: Create HSM groups based on hardware pattern");
+        events::info("Dry run: Create HSM groups based on hardware pattern");
       } else {
-        let client = crate::ShastaClient::new(
+        let client = crate::ShastaClient::scoped_or_new(
           ctx.shasta_base_url,
           ctx.shasta_root_cert.to_vec(),
           ctx.socks5_proxy.map(str::to_owned),
@@ -393,6 +615,7 @@ async fn process_hardware_section(
         apply_hw_cluster_pin::command::exec(
           &client,
           ctx.shasta_token,
+          ctx.execution_context,
           target_hsm_group_name,
           parent_hsm_group_name,
           pattern,
@@ -418,14 +641,14 @@ async fn process_hardware_section(
         .map(str::to_string)
         .collect();
 
-      log::info!(
+      events::step(format!(
         "Processing new nodes '{nodes}' for target HSM group '{target_hsm_group_name}'",
-      );
+      ));
 
       if ctx.dry_run {
-        log::info!(
+        events::info(format!(
           "Dry Run mode: Update HSM group '{target_hsm_group_name}' members to:\n{new_target_hsm_group_members_vec:?}"
-        );
+        ));
       } else {
         update_hsm_group_members(
           ctx.shasta_token,
@@ -451,23 +674,25 @@ async fn process_hardware_section(
 }
 
 /// Process the `configurations` section of the SAT file, creating a CFS
-/// configuration for each entry and returning the created configurations.
+/// configuration for each entry and returning the created configurations
+/// with the refs their layers were pinned to.
 async fn process_configurations_section(
   ctx: &SatApplyContext<'_>,
-  cray_product_catalog: &BTreeMap<String, String>,
+  cray_product_catalog: &ProductCatalog,
   sat_template_file_yaml: &serde_yaml::Value,
-) -> Result<Vec<CfsConfigurationResponse>, Error> {
+) -> Result<(Vec<CfsConfigurationResponse>, Vec<ResolvedLayers>), Error> {
   let configuration_yaml_vec_opt = sat_template_file_yaml
     .get("configurations")
     .and_then(Value::as_sequence);
 
-  log::info!("Process configurations section in SAT file");
+  events::step("Process configurations section in SAT file");
   let mut cfs_configurations_created: Vec<CfsConfigurationResponse> =
     Vec::new();
+  let mut resolved_layers_vec: Vec<ResolvedLayers> = Vec::new();
 
   for configuration_yaml in configuration_yaml_vec_opt.unwrap_or(&vec![])
   {
-    let cfs_configuration: CfsConfigurationResponse =
+    let (cfs_configuration, resolved_layers) =
       utils::create_cfs_configuration_from_sat_file(
         ctx.shasta_token,
         ctx.shasta_base_url,
@@ -480,15 +705,18 @@ async fn process_configurations_section(
         ctx.dry_run,
         ctx.site_name,
         ctx.overwrite,
+        ctx.options.ignore_protection,
       )
       .await?;
 
-    log::info!("CFS configuration '{}' created", cfs_configuration.name);
+    events::created("CFS configuration", &cfs_configuration.name);
+    events::info(format!("Refs pinned for {resolved_layers}"));
 
     cfs_configurations_created.push(cfs_configuration);
+    resolved_layers_vec.push(resolved_layers);
   }
 
-  Ok(cfs_configurations_created)
+  Ok((cfs_configurations_created, resolved_layers_vec))
 }
 
 /// Parameters for [`validate_sat_file`].
@@ -529,9 +757,13 @@ pub struct ValidateSatFileParams<'a> {
 /// recipe lists, parses the SAT YAML, and runs the same per-section
 /// validators the apply pipeline runs.
 ///
-/// Returns `Ok(())` if the SAT file would apply cleanly given the
-/// current CSM state; returns the first validation [`Error`]
-/// encountered otherwise (fail-fast — see the design doc).
+/// Returns a [`ValidationReport`] with every problem found in the SAT
+/// file given the current CSM state; use
+/// [`ValidationReport::can_proceed`] or
+/// [`ValidationReport::into_result`] to decide whether to apply it.
+/// An [`Error`] is only returned if the live state can't be fetched or
+/// the token can't be decoded. The SAT file is validated with the
+/// tenancy of the owner of the token.
 ///
 /// `shasta_k8s_secrets` is the Vault-fetched k8s credential blob;
 /// taken as a separate argument to mirror `apply_sat_file::exec`'s
@@ -539,12 +771,15 @@ pub struct ValidateSatFileParams<'a> {
 pub async fn validate_sat_file(
   params: ValidateSatFileParams<'_>,
   shasta_k8s_secrets: serde_json::Value,
-) -> Result<(), Error> {
+) -> Result<ValidationReport, Error> {
+  let execution_context = ExecutionContext::from_token(params.shasta_token)?;
+
   // Reuse the existing context struct. Fields not read by the
   // gather + validate path get empty defaults; the validator never
   // reaches the apply phase so these stay inert.
   let ctx = SatApplyContext {
     shasta_token: params.shasta_token,
+    execution_context: &execution_context,
     shasta_base_url: params.shasta_base_url,
     shasta_root_cert: params.shasta_root_cert,
     socks5_proxy: params.socks5_proxy,
@@ -553,7 +788,8 @@ pub async fn validate_sat_file(
     k8s_api_url: params.k8s_api_url,
     gitea_base_url: "",
     gitea_token: "",
-    hsm_group_available_vec: params.hsm_group_available_vec,
+    hsm_group_available_vec: execution_context
+      .filter_groups(params.hsm_group_available_vec),
     ansible_verbosity: None,
     ansible_passthrough: None,
     reboot: false,
@@ -561,6 +797,7 @@ pub async fn validate_sat_file(
     timestamps: false,
     debug_on_failure: false,
     overwrite: false,
+    options: &SatApplyOptions::default(),
     dry_run: true,
   };
 
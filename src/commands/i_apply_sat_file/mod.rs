@@ -3,10 +3,16 @@
 //! Submodules:
 //!
 //! - [`command`] — the entry-point `exec` function.
+//! - [`plan`] — compare a SAT file with CSM and report what applying it
+//!   would create, update or skip.
+//! - [`provenance`] — provenance stamped on the resources a run creates,
+//!   and listing what a given run created.
 //! - [`utils`] — section-level helpers (configurations, images, session
 //!   templates) used by the workflow.
 
 pub mod command;
+pub mod plan;
+pub mod provenance;
 /// Integration tests for the SAT-file apply workflow.
 #[cfg(test)]
 pub mod tests;
@@ -14,3 +20,5 @@ pub mod utils;
 
 #[doc(inline)]
 pub use command::exec;
+#[doc(inline)]
+pub use plan::plan;
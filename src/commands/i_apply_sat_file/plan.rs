@@ -0,0 +1,611 @@
+//! Delta planning for the apply-SAT-file workflow.
+//!
+//! [`plan`] compares every `configurations`, `images` and
+//! `session_templates` entry of a SAT file with what is already in CSM
+//! and returns a [`ChangeSet`] saying, per entry, whether applying it
+//! would create, update or leave things as they are. Feeding
+//! [`ChangeSet::prune`]'s output to [`super::exec`] (with
+//! [`ImageNameConflictPolicy::Overwrite`] so updated images replace
+//! the old ones) then only touches what changed, which makes repeated
+//! applies of the same SAT file fast and idempotent.
+//!
+//! [`ImageNameConflictPolicy::Overwrite`]: super::utils::images::ImageNameConflictPolicy::Overwrite
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::{
+  bos::BosSessionTemplate,
+  cfs,
+  commands::i_apply_sat_file::utils::{
+    SatFile, image,
+    images::{
+      get_image_name_or_ref_name_to_process_struct,
+      get_next_image_in_sat_file_to_process_struct,
+    },
+    session_template_naming_context,
+    sessiontemplate::{self, SessionTemplate},
+  },
+  common::{
+    events,
+    naming::{self, NamingContext},
+  },
+  error::Error,
+  ims::Image as ImsImage,
+};
+
+/// What applying a SAT-file entry would do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+  /// Nothing with that name exists in CSM yet.
+  Create,
+  /// Something with that name exists but differs from the SAT file.
+  Update,
+  /// CSM already matches the SAT file.
+  Skip,
+}
+
+/// SAT-file section a [`PlannedChange`] belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SatResource {
+  /// A `configurations` entry (CFS configuration).
+  Configuration,
+  /// An `images` entry (IMS image).
+  Image,
+  /// A `session_templates` entry (BOS session template).
+  SessionTemplate,
+}
+
+/// Planned outcome for one SAT-file entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedChange {
+  /// Section the entry comes from.
+  pub resource: SatResource,
+  /// Entry name, as written in the SAT file.
+  pub name: String,
+  /// What applying the entry would do.
+  pub action: ChangeAction,
+  /// Human-readable reason for `action`.
+  pub reason: String,
+  /// Id of the matching CSM object for skipped images, so references to
+  /// them can be rewritten when the entry is pruned.
+  pub existing_id: Option<String>,
+}
+
+/// Per-entry result of [`plan`], in SAT-file order (configurations,
+/// then images, then session templates).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeSet {
+  /// One entry per SAT-file configuration, image and session template.
+  pub changes: Vec<PlannedChange>,
+}
+
+impl ChangeSet {
+  /// Planned change for the `resource` entry called `name`, if any.
+  #[must_use]
+  pub fn get(
+    &self,
+    resource: SatResource,
+    name: &str,
+  ) -> Option<&PlannedChange> {
+    self
+      .changes
+      .iter()
+      .find(|change| change.resource == resource && change.name == name)
+  }
+
+  /// True when applying the SAT file would not change anything.
+  #[must_use]
+  pub fn is_noop(&self) -> bool {
+    self
+      .changes
+      .iter()
+      .all(|change| change.action == ChangeAction::Skip)
+  }
+
+  /// Remove skipped entries from `sat_template_file_yaml`.
+  ///
+  /// `image_ref`s pointing at a skipped image, from another image's
+  /// `base` or from a session template, are rewritten to the id of the
+  /// existing IMS image so the pruned file still resolves.
+  #[must_use]
+  pub fn prune(&self, sat_template_file_yaml: &Value) -> Value {
+    let mut pruned_yaml = sat_template_file_yaml.clone();
+
+    let is_skipped = |resource: SatResource, name: &str| {
+      self
+        .get(resource, name)
+        .is_some_and(|change| change.action == ChangeAction::Skip)
+    };
+
+    // `image_ref` (ref_name, falling back to name) -> existing image id
+    let skipped_image_ref_map: BTreeMap<String, String> = pruned_yaml
+      .get("images")
+      .and_then(Value::as_sequence)
+      .into_iter()
+      .flatten()
+      .filter_map(|image_yaml| {
+        let name = image_yaml.get("name").and_then(Value::as_str)?;
+        let change = self.get(SatResource::Image, name)?;
+        if change.action != ChangeAction::Skip {
+          return None;
+        }
+        let ref_name = image_yaml
+          .get("ref_name")
+          .and_then(Value::as_str)
+          .unwrap_or(name);
+        Some((ref_name.to_string(), change.existing_id.clone()?))
+      })
+      .collect();
+
+    for (section, resource) in [
+      ("configurations", SatResource::Configuration),
+      ("images", SatResource::Image),
+      ("session_templates", SatResource::SessionTemplate),
+    ] {
+      if let Some(entry_vec) = pruned_yaml
+        .get_mut(section)
+        .and_then(Value::as_sequence_mut)
+      {
+        entry_vec.retain(|entry| {
+          entry
+            .get("name")
+            .and_then(Value::as_str)
+            .is_none_or(|name| !is_skipped(resource, name))
+        });
+      }
+    }
+
+    for image_yaml in pruned_yaml
+      .get_mut("images")
+      .and_then(Value::as_sequence_mut)
+      .into_iter()
+      .flatten()
+    {
+      let Some(base) = image_yaml.get_mut("base") else {
+        continue;
+      };
+      if let Some(image_id) = base
+        .get("image_ref")
+        .and_then(Value::as_str)
+        .and_then(|image_ref| skipped_image_ref_map.get(image_ref))
+      {
+        *base = ims_image_id_yaml(image_id, Some("image"));
+      }
+    }
+
+    for session_template_yaml in pruned_yaml
+      .get_mut("session_templates")
+      .and_then(Value::as_sequence_mut)
+      .into_iter()
+      .flatten()
+    {
+      let Some(image) = session_template_yaml.get_mut("image") else {
+        continue;
+      };
+      if let Some(image_id) = image
+        .get("image_ref")
+        .and_then(Value::as_str)
+        .and_then(|image_ref| skipped_image_ref_map.get(image_ref))
+      {
+        *image = ims_image_id_yaml(image_id, None);
+      }
+    }
+
+    pruned_yaml
+  }
+}
+
+/// `ims: { id: <image_id>[, type: <image_type>] }` as found in SAT
+/// image bases and session template images.
+fn ims_image_id_yaml(image_id: &str, image_type_opt: Option<&str>) -> Value {
+  let mut ims = serde_yaml::Mapping::new();
+  ims.insert("id".into(), image_id.into());
+  if let Some(image_type) = image_type_opt {
+    ims.insert("type".into(), image_type.into());
+  }
+
+  let mut image = serde_yaml::Mapping::new();
+  image.insert("ims".into(), Value::Mapping(ims));
+  Value::Mapping(image)
+}
+
+impl fmt::Display for ChangeSet {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, change) in self.changes.iter().enumerate() {
+      if i > 0 {
+        writeln!(f)?;
+      }
+      write!(
+        f,
+        "{:?} {:?} '{}': {}",
+        change.action, change.resource, change.name, change.reason
+      )?;
+    }
+    Ok(())
+  }
+}
+
+/// Compare a SAT file with the current CSM state and return what
+/// applying it would change. Nothing is created or modified.
+///
+/// Configuration layers are resolved exactly as the apply workflow
+/// does (branches and tags to commits through Gitea, product layers
+/// through `cray_product_catalog`), so a branch that moved shows up as
+/// an update. Images are matched by name (and by arch when the SAT
+/// entry filters on one) and are planned for rebuild when their CFS
+/// configuration changes. Session templates are compared by content
+/// and planned for update when their image or configuration changes.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant if the SAT file is malformed, or on
+/// CSM, Gitea, transport, or deserialization failure.
+#[allow(clippy::too_many_arguments)]
+pub async fn plan(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  gitea_base_url: &str,
+  gitea_token: &str,
+  cray_product_catalog: &ProductCatalog,
+  sat_template_file_yaml: &Value,
+  site_name: &str,
+) -> Result<ChangeSet, Error> {
+  let sat_file: SatFile =
+    serde_yaml::from_str(&serde_yaml::to_string(sat_template_file_yaml)?)?;
+
+  let client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+  let (configuration_vec, image_vec, session_template_vec) = tokio::try_join!(
+    client.cfs_configuration_v3_get(shasta_token, None),
+    client.ims_image_get_all(shasta_token),
+    client.bos_template_v2_get_all(shasta_token),
+  )?;
+
+  let mut change_set = ChangeSet::default();
+
+  for configuration_yaml in sat_template_file_yaml
+    .get("configurations")
+    .and_then(Value::as_sequence)
+    .into_iter()
+    .flatten()
+  {
+    let (configuration_name, configuration, _) =
+      cfs::v3::CfsConfigurationRequest::from_sat_file_serde_yaml_with_report(
+        shasta_root_cert,
+        gitea_base_url,
+        gitea_token,
+        configuration_yaml,
+        cray_product_catalog,
+        site_name,
+        socks5_proxy,
+      )
+      .await?;
+
+    let existing_configuration = configuration_vec
+      .iter()
+      .find(|existing| existing.name == configuration_name);
+    let (action, reason) =
+      configuration_action(&configuration, existing_configuration);
+
+    change_set.changes.push(PlannedChange {
+      resource: SatResource::Configuration,
+      name: configuration_name,
+      action,
+      reason,
+      existing_id: None,
+    });
+  }
+
+  // Images are planned in dependency order so a rebuilt base image
+  // also rebuilds the images built on top of it
+  let sat_image_vec = sat_file.images.unwrap_or_default();
+  let mut planned_ref_name_vec: Vec<String> = Vec::new();
+  while let Some(sat_image) = get_next_image_in_sat_file_to_process_struct(
+    &sat_image_vec,
+    &planned_ref_name_vec,
+  ) {
+    let dependency_changed =
+      image_dependency_changed(&sat_image, &sat_image_vec, &change_set);
+    let (action, reason, existing_id) =
+      image_action(&sat_image, &image_vec, dependency_changed);
+
+    change_set.changes.push(PlannedChange {
+      resource: SatResource::Image,
+      name: sat_image.name.clone(),
+      action,
+      reason,
+      existing_id,
+    });
+    planned_ref_name_vec
+      .push(get_image_name_or_ref_name_to_process_struct(&sat_image));
+  }
+
+  for sat_session_template in sat_file.session_templates.unwrap_or_default() {
+    let boot_image_name_opt =
+      sat_session_template_image_name(&sat_session_template, &sat_image_vec);
+    let dependency_changed = [
+      change_set.get(
+        SatResource::Configuration,
+        &sat_session_template.configuration,
+      ),
+      boot_image_name_opt
+        .as_deref()
+        .and_then(|name| change_set.get(SatResource::Image, name)),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|change| change.action != ChangeAction::Skip);
+
+    let boot_image_id_vec: Vec<String> = match &sat_session_template.image {
+      sessiontemplate::Image::Ims {
+        ims: sessiontemplate::ImsDetails::Id { id },
+      } => vec![id.clone()],
+      _ => image_vec
+        .iter()
+        .filter(|image| Some(&image.name) == boot_image_name_opt.as_ref())
+        .filter_map(|image| image.id.clone())
+        .collect(),
+    };
+
+    // Created templates are named after the naming policy
+    let bos_session_template_name =
+      naming::current().bos_template_name(&session_template_naming_context(
+        &sat_session_template,
+        NamingContext::new(shasta_token),
+      ));
+    let existing_session_template = session_template_vec.iter().find(|st| {
+      st.name.as_deref() == Some(bos_session_template_name.as_str())
+    });
+    let name = sat_session_template.name.clone();
+    let (action, reason) = session_template_action(
+      sat_session_template,
+      existing_session_template,
+      &boot_image_id_vec,
+      dependency_changed,
+    );
+
+    change_set.changes.push(PlannedChange {
+      resource: SatResource::SessionTemplate,
+      name,
+      action,
+      reason,
+      existing_id: None,
+    });
+  }
+
+  events::info(format!("SAT file change set:\n{change_set}"));
+
+  Ok(change_set)
+}
+
+/// Compare a resolved configuration request with the CFS configuration
+/// of the same name, if any.
+pub(crate) fn configuration_action(
+  configuration: &cfs::v3::CfsConfigurationRequest,
+  existing_opt: Option<&cfs::v3::CfsConfigurationResponse>,
+) -> (ChangeAction, String) {
+  let Some(existing) = existing_opt else {
+    return (ChangeAction::Create, "not in CFS".to_string());
+  };
+
+  let layer_vec = configuration.layers.as_deref().unwrap_or_default();
+
+  if layer_vec.len() != existing.layers.len() {
+    return (
+      ChangeAction::Update,
+      format!(
+        "layer count differs ({} in SAT file, {} in CFS)",
+        layer_vec.len(),
+        existing.layers.len()
+      ),
+    );
+  }
+
+  for (layer, existing_layer) in layer_vec.iter().zip(&existing.layers) {
+    // CFS fills in the commit of branch layers, so only compare the
+    // branch when the SAT file did not pin a commit
+    let same_ref = match &layer.commit {
+      Some(commit) => existing_layer.commit.as_ref() == Some(commit),
+      None => existing_layer.branch == layer.branch,
+    };
+
+    if layer.name != existing_layer.name
+      || layer.clone_url.as_deref().unwrap_or_default()
+        != existing_layer.clone_url
+      || layer.source != existing_layer.source
+      || layer.playbook != existing_layer.playbook
+      || !same_ref
+    {
+      return (
+        ChangeAction::Update,
+        format!(
+          "layer '{}' differs",
+          layer.name.as_deref().unwrap_or_default()
+        ),
+      );
+    }
+  }
+
+  (ChangeAction::Skip, "layers match".to_string())
+}
+
+/// True if the CFS configuration or the base image (`base.image_ref`)
+/// of `sat_image` is planned to change.
+fn image_dependency_changed(
+  sat_image: &image::Image,
+  sat_image_vec: &[image::Image],
+  change_set: &ChangeSet,
+) -> bool {
+  let configuration_change = sat_image
+    .configuration
+    .as_deref()
+    .and_then(|name| change_set.get(SatResource::Configuration, name));
+
+  let base_image_change = match &sat_image.base_or_ims {
+    image::BaseOrIms::Base {
+      base: image::Base::ImageRef { image_ref },
+    } => sat_image_vec
+      .iter()
+      .find(|base_image| {
+        get_image_name_or_ref_name_to_process_struct(base_image) == *image_ref
+      })
+      .and_then(|base_image| {
+        change_set.get(SatResource::Image, &base_image.name)
+      }),
+    _ => None,
+  };
+
+  [configuration_change, base_image_change]
+    .into_iter()
+    .flatten()
+    .any(|change| change.action != ChangeAction::Skip)
+}
+
+/// Decide whether `sat_image` needs building, given the IMS images and
+/// whether anything it is built from is changing.
+pub(crate) fn image_action(
+  sat_image: &image::Image,
+  image_vec: &[ImsImage],
+  dependency_changed: bool,
+) -> (ChangeAction, String, Option<String>) {
+  let arch_opt = match &sat_image.base_or_ims {
+    image::BaseOrIms::Base {
+      base: image::Base::Product { product },
+    } => product
+      .filter
+      .as_ref()
+      .and_then(image::Filter::arch)
+      .map(image::Arch::ims_arch),
+    _ => None,
+  };
+
+  let existing_opt = image_vec
+    .iter()
+    .filter(|image| image.name == sat_image.name)
+    .filter(|image| {
+      arch_opt.is_none_or(|arch| image.arch.as_deref() == Some(arch))
+    })
+    .max_by(|a, b| a.created.cmp(&b.created));
+
+  match existing_opt {
+    None => (ChangeAction::Create, "not in IMS".to_string(), None),
+    Some(_) if dependency_changed => (
+      ChangeAction::Update,
+      "configuration or base image changes".to_string(),
+      None,
+    ),
+    Some(existing) => (
+      ChangeAction::Skip,
+      "image with the same name already in IMS".to_string(),
+      existing.id.clone(),
+    ),
+  }
+}
+
+/// Name of the IMS image a SAT session template boots, when it is given
+/// by name (directly or through a SAT image `image_ref`).
+fn sat_session_template_image_name(
+  sat_session_template: &SessionTemplate,
+  sat_image_vec: &[image::Image],
+) -> Option<String> {
+  use sessiontemplate::{Image, ImsDetails};
+
+  match &sat_session_template.image {
+    Image::Ims {
+      ims: ImsDetails::Name { name },
+    }
+    | Image::ImageName(name) => Some(name.clone()),
+    Image::Ims {
+      ims: ImsDetails::Id { .. },
+    } => None,
+    Image::ImageRef { image_ref } => sat_image_vec
+      .iter()
+      .find(|sat_image| {
+        get_image_name_or_ref_name_to_process_struct(sat_image) == *image_ref
+      })
+      .map(|sat_image| sat_image.name.clone()),
+  }
+}
+
+/// Compare a SAT session template with the BOS session template of the
+/// same name, if any. `boot_image_id_vec` holds the ids of the IMS
+/// images the SAT entry may boot.
+pub(crate) fn session_template_action(
+  sat_session_template: SessionTemplate,
+  existing_opt: Option<&BosSessionTemplate>,
+  boot_image_id_vec: &[String],
+  dependency_changed: bool,
+) -> (ChangeAction, String) {
+  let Some(existing) = existing_opt else {
+    return (ChangeAction::Create, "not in BOS".to_string());
+  };
+
+  if dependency_changed {
+    return (
+      ChangeAction::Update,
+      "boot image or configuration changes".to_string(),
+    );
+  }
+
+  let Ok(wanted) = BosSessionTemplate::try_from(sat_session_template) else {
+    return (
+      ChangeAction::Update,
+      "SAT entry could not be compared".to_string(),
+    );
+  };
+
+  if wanted.configuration_name() != existing.configuration_name() {
+    return (ChangeAction::Update, "configuration differs".to_string());
+  }
+
+  let wanted_boot_set_map = wanted.boot_sets.unwrap_or_default();
+  let existing_boot_set_map = existing.boot_sets.clone().unwrap_or_default();
+
+  if wanted_boot_set_map.len() != existing_boot_set_map.len() {
+    return (ChangeAction::Update, "boot sets differ".to_string());
+  }
+
+  for (property, wanted_boot_set) in wanted_boot_set_map {
+    let Some(existing_boot_set) = existing_boot_set_map.get(&property) else {
+      return (
+        ChangeAction::Update,
+        format!("boot set '{property}' not in BOS"),
+      );
+    };
+
+    let boots_image = existing_boot_set.path.as_deref().is_some_and(|path| {
+      boot_image_id_vec
+        .iter()
+        .any(|image_id| path.contains(image_id.as_str()))
+    });
+
+    if !boots_image
+      || wanted_boot_set.kernel_parameters
+        != existing_boot_set.kernel_parameters
+      || wanted_boot_set.node_list != existing_boot_set.node_list
+      || wanted_boot_set.node_groups != existing_boot_set.node_groups
+      || wanted_boot_set.node_roles_groups
+        != existing_boot_set.node_roles_groups
+      || wanted_boot_set.rootfs_provider != existing_boot_set.rootfs_provider
+      || wanted_boot_set.rootfs_provider_passthrough
+        != existing_boot_set.rootfs_provider_passthrough
+    {
+      return (
+        ChangeAction::Update,
+        format!("boot set '{property}' differs"),
+      );
+    }
+  }
+
+  (ChangeAction::Skip, "content matches".to_string())
+}
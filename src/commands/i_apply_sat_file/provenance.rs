@@ -0,0 +1,292 @@
+//! Provenance of the resources an apply-SAT run creates.
+//!
+//! Every run of [`super::exec`] stamps the CFS configurations, IMS
+//! images and BOS session templates it creates with a
+//! [`SatProvenance`]: the run id (the correlation id of the run, also
+//! sent as `X-Request-ID` and in the `sat_file_applied` webhook event),
+//! the SHA-256 of the SAT file, the csm-rs version, the time the run
+//! started and the user owning the Shasta token.
+//!
+//! Where it is stored depends on what the service lets csm-rs keep:
+//!
+//! - IMS images: JSON under the [`METADATA_KEY`] metadata entry.
+//! - BOS session templates and CFS v3 configurations: a
+//!   [`DESCRIPTION_MARKER`] line followed by the JSON, appended to the
+//!   description.
+//!
+//! CFS v2 configurations have no description, so configurations the
+//! SAT file creates through the v2 API (no layer uses a CFS `source`)
+//! carry no provenance.
+//!
+//! [`resources_from_sat_run`] lists what a given run created.
+
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+  ShastaClient, bos::BosSessionTemplate, cfs::v3::CfsConfigurationResponse,
+  common::jwt_ops, error::Error, ims::Image,
+};
+
+/// Marker preceding the provenance JSON in descriptions.
+pub const DESCRIPTION_MARKER: &str = "csm-rs-provenance: ";
+
+/// IMS image metadata entry holding the provenance JSON.
+pub const METADATA_KEY: &str = "sat_provenance";
+
+tokio::task_local! {
+  static PROVENANCE: SatProvenance;
+}
+
+/// Where a resource created by an apply-SAT run comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SatProvenance {
+  /// Id of the apply-SAT run.
+  pub run_id: String,
+  /// Lowercase hex SHA-256 of the SAT file, serialized as YAML.
+  pub sat_file_sha256: String,
+  /// Tool and version that applied the SAT file, e.g. `csm-rs/0.1.0`.
+  pub generator: String,
+  /// When the run started.
+  pub timestamp: DateTime<Utc>,
+  /// `preferred_username` of the Shasta token, empty if the token has
+  /// none.
+  pub user: String,
+}
+
+impl SatProvenance {
+  /// Provenance of the resources run `run_id` creates from
+  /// `sat_file_yaml` on behalf of the owner of `shasta_token`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::SerdeYamlError`] if the SAT file can't be serialized.
+  pub fn new(
+    run_id: impl Into<String>,
+    shasta_token: &str,
+    sat_file_yaml: &serde_yaml::Value,
+  ) -> Result<Self, Error> {
+    let sat_file_sha256 = Sha256::digest(serde_yaml::to_string(sat_file_yaml)?)
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect();
+
+    Ok(Self {
+      run_id: run_id.into(),
+      sat_file_sha256,
+      generator: format!("csm-rs/{}", env!("CARGO_PKG_VERSION")),
+      timestamp: Utc::now(),
+      user: jwt_ops::get_preferred_username(shasta_token).unwrap_or_default(),
+    })
+  }
+
+  /// Provenance as one line of JSON.
+  #[must_use]
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(self).unwrap_or_default()
+  }
+
+  /// `description` with the provenance appended on its own line.
+  #[must_use]
+  pub fn annotate(&self, description: Option<&str>) -> String {
+    match description.filter(|description| !description.is_empty()) {
+      Some(description) => {
+        format!("{description}\n{DESCRIPTION_MARKER}{}", self.to_json())
+      }
+      None => format!("{DESCRIPTION_MARKER}{}", self.to_json()),
+    }
+  }
+
+  /// Provenance appended to `description` by [`Self::annotate`], if
+  /// any.
+  #[must_use]
+  pub fn from_description(description: &str) -> Option<Self> {
+    let (_, json) = description.rsplit_once(DESCRIPTION_MARKER)?;
+
+    serde_json::Deserializer::from_str(json)
+      .into_iter::<Self>()
+      .next()?
+      .ok()
+  }
+
+  /// Provenance stored in the metadata of `image`, if any.
+  #[must_use]
+  pub fn from_image(image: &Image) -> Option<Self> {
+    serde_json::from_str(image.label(METADATA_KEY)?).ok()
+  }
+}
+
+/// Run `fut` with `provenance` stamped on the resources it creates.
+pub(crate) async fn scope<F: Future>(
+  provenance: SatProvenance,
+  fut: F,
+) -> F::Output {
+  PROVENANCE.scope(provenance, fut).await
+}
+
+/// Provenance of the apply-SAT run on the current task, if any.
+pub(crate) fn current() -> Option<SatProvenance> {
+  PROVENANCE.try_with(Clone::clone).ok()
+}
+
+/// `description` annotated with the provenance of the current run, or
+/// left as is outside of one.
+pub(crate) fn annotate_description(
+  description: Option<String>,
+) -> Option<String> {
+  match current() {
+    Some(provenance) => Some(provenance.annotate(description.as_deref())),
+    None => description,
+  }
+}
+
+/// Resources created by one apply-SAT run.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SatRunResources {
+  /// CFS configurations created through the v3 API.
+  pub configurations: Vec<CfsConfigurationResponse>,
+  /// IMS images built.
+  pub images: Vec<Image>,
+  /// BOS session templates created.
+  pub session_templates: Vec<BosSessionTemplate>,
+}
+
+/// List the CFS configurations, IMS images and BOS session templates
+/// created by apply-SAT run `run_id`.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or deserialization
+/// failure; see the crate-level `Error` enum for the full set.
+pub async fn resources_from_sat_run(
+  client: &ShastaClient,
+  shasta_token: &str,
+  run_id: &str,
+) -> Result<SatRunResources, Error> {
+  let (configuration_vec, image_vec, session_template_vec) = tokio::try_join!(
+    client.cfs_configuration_v3_get(shasta_token, None),
+    client.ims_image_get_all(shasta_token),
+    client.bos_template_v2_get_all(shasta_token),
+  )?;
+
+  let from_run = |provenance_opt: Option<SatProvenance>| {
+    provenance_opt.is_some_and(|provenance| provenance.run_id == run_id)
+  };
+
+  Ok(SatRunResources {
+    configurations: configuration_vec
+      .into_iter()
+      .filter(|configuration| {
+        from_run(
+          configuration
+            .description
+            .as_deref()
+            .and_then(SatProvenance::from_description),
+        )
+      })
+      .collect(),
+    images: image_vec
+      .into_iter()
+      .filter(|image| from_run(SatProvenance::from_image(image)))
+      .collect(),
+    session_templates: session_template_vec
+      .into_iter()
+      .filter(|session_template| {
+        from_run(
+          session_template
+            .description
+            .as_deref()
+            .and_then(SatProvenance::from_description),
+        )
+      })
+      .collect(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn provenance() -> SatProvenance {
+    SatProvenance {
+      run_id: "7d1c5e0a".to_string(),
+      sat_file_sha256: "ab".repeat(32),
+      generator: "csm-rs/0.0.0".to_string(),
+      timestamp: DateTime::UNIX_EPOCH,
+      user: "alice".to_string(),
+    }
+  }
+
+  #[test]
+  fn new_hashes_sat_file() {
+    let sat_file_yaml: serde_yaml::Value =
+      serde_yaml::from_str("configurations: []").unwrap();
+
+    let provenance =
+      SatProvenance::new("run", "not a jwt", &sat_file_yaml).unwrap();
+
+    assert_eq!(provenance.run_id, "run");
+    assert_eq!(provenance.sat_file_sha256.len(), 64);
+    assert_eq!(
+      provenance.sat_file_sha256,
+      SatProvenance::new("other run", "not a jwt", &sat_file_yaml)
+        .unwrap()
+        .sat_file_sha256
+    );
+    assert!(provenance.generator.starts_with("csm-rs/"));
+    assert_eq!(provenance.user, "");
+  }
+
+  #[test]
+  fn description_round_trips() {
+    let provenance = provenance();
+
+    let description = provenance.annotate(Some("Created by alice"));
+    assert!(description.starts_with("Created by alice\ncsm-rs-provenance: {"));
+    assert_eq!(
+      SatProvenance::from_description(&description),
+      Some(provenance.clone())
+    );
+
+    assert_eq!(
+      SatProvenance::from_description(&provenance.annotate(None)),
+      Some(provenance)
+    );
+    assert_eq!(SatProvenance::from_description("Created by alice"), None);
+    assert_eq!(
+      SatProvenance::from_description("csm-rs-provenance: {broken"),
+      None
+    );
+  }
+
+  #[test]
+  fn image_metadata_round_trips() {
+    let provenance = provenance();
+    let image =
+      Image::default().with_labels([(METADATA_KEY, provenance.to_json())]);
+
+    assert_eq!(SatProvenance::from_image(&image), Some(provenance));
+    assert_eq!(SatProvenance::from_image(&Image::default()), None);
+  }
+
+  #[tokio::test]
+  async fn annotate_description_only_inside_a_run() {
+    assert_eq!(
+      annotate_description(Some("template".to_string())),
+      Some("template".to_string())
+    );
+
+    let description = scope(provenance(), async {
+      annotate_description(Some("template".to_string()))
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+      SatProvenance::from_description(&description),
+      Some(provenance())
+    );
+  }
+}
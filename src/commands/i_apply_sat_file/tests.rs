@@ -7,13 +7,20 @@ use crate::{
   commands::i_apply_sat_file::utils::{
     configuration, image,
     images::{
-      get_image_name_or_ref_name_to_process_struct,
-      get_next_image_in_sat_file_to_process_struct,
+      CfsSessionRetryPolicy, ImageNameConflictPolicy, ImageNameResolution,
+      expand_multi_arch_images, get_image_name_or_ref_name_to_process_struct,
+      get_next_image_in_sat_file_to_process_struct, recipe_template_dictionary,
+      resolve_image_name_conflict,
     },
     validate_sat_file_images_section,
+    validate_sat_file_session_template_section,
+    validation::ValidationReport,
+  },
+  common::product_catalog::ProductCatalog,
+  ims::{
+    image::http_client::types::Image,
+    recipe::types::{RecipeGetResponse, RecipeKeyValuePair},
   },
-  error::Error,
-  ims::{image::http_client::types::Image, recipe::types::RecipeGetResponse},
 };
 
 /// Test function "`get_ref_name`" so it falls back to "name" field if "`ref_name`" is missing
@@ -123,6 +130,83 @@ fn test_get_next_image_to_process_3() {
   assert_eq!(next_image_to_process_2.unwrap().name, "final_image");
 }
 
+/// Images with `archs` are built once per architecture, and images
+/// based on them are based on the build for the same architecture
+#[test]
+fn test_expand_multi_arch_images() {
+  let image_vec: Vec<image::Image> = serde_yaml::from_str(
+    r#"
+    - name: base_image
+      ref_name: base_cos_image
+      archs: [x86_64, aarch64]
+      base:
+        product:
+          name: cos
+          type: recipe
+          version: "2.4.139"
+    - name: final_image
+      ref_name: compute_image
+      archs: [aarch64]
+      base:
+        image_ref: base_cos_image
+    - name: other_image
+      base:
+        image_ref: compute_image
+    "#,
+  )
+  .unwrap();
+
+  let expanded_image_vec = expand_multi_arch_images(&image_vec);
+
+  let summary: Vec<(String, String, Option<image::Arch>)> = expanded_image_vec
+    .iter()
+    .map(|image_yaml| {
+      (
+        image_yaml.name.clone(),
+        get_image_name_or_ref_name_to_process_struct(image_yaml),
+        image_yaml.arch,
+      )
+    })
+    .collect();
+  assert_eq!(
+    summary,
+    [
+      (
+        "base_image-x86_64".to_string(),
+        "base_cos_image-x86_64".to_string(),
+        Some(image::Arch::X86_64)
+      ),
+      (
+        "base_image-aarch64".to_string(),
+        "base_cos_image-aarch64".to_string(),
+        Some(image::Arch::Aarch64)
+      ),
+      (
+        "final_image-aarch64".to_string(),
+        "compute_image-aarch64".to_string(),
+        Some(image::Arch::Aarch64)
+      ),
+      ("other_image".to_string(), "other_image".to_string(), None),
+    ]
+  );
+
+  assert!(matches!(
+    &expanded_image_vec[0].base_or_ims,
+    image::BaseOrIms::Base {
+      base: image::Base::Product { product },
+    } if matches!(
+      product.filter,
+      Some(image::Filter::Arch { arch: image::Arch::X86_64 })
+    )
+  ));
+  assert!(matches!(
+    &expanded_image_vec[2].base_or_ims,
+    image::BaseOrIms::Base {
+      base: image::Base::ImageRef { image_ref },
+    } if image_ref == "base_cos_image-aarch64"
+  ));
+}
+
 #[test]
 fn test_get_next_image_to_process_4() {
   let image_vec: Vec<image::Image> = serde_yaml::from_str(
@@ -174,7 +258,7 @@ fn test_get_next_image_to_process_4() {
 /// Reason: configuration assigned to image found in SAT
 #[test]
 fn test_old_image_format_in_sat_file_pass_because_configuration_found_in_sat() {
-  let cray_product_catalog = &BTreeMap::<String, String>::new();
+  let cray_product_catalog = &ProductCatalog::default();
 
   let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
     r"
@@ -215,7 +299,7 @@ fn test_old_image_format_in_sat_file_pass_because_configuration_found_in_sat() {
   let ims_recipes = vec![];
 
   assert!(
-    validate_sat_file_images_section(
+    !validate_sat_file_images_section(
       image_vec_in_sat_file.as_slice(),
       configuration_vec_in_sat_file.as_slice(),
       hsm_group_available_vec,
@@ -224,17 +308,71 @@ fn test_old_image_format_in_sat_file_pass_because_configuration_found_in_sat() {
       configuration_vec_in_csm,
       ims_recipes
     )
-    .is_ok()
+    .has_errors()
   );
 }
 
+/// Test SAT file
+/// Test image section in SAT file
+/// Result: FAIL
+/// Reason: configuration group name is not a valid HSM group label
+#[test]
+fn test_image_in_sat_file_fail_because_configuration_group_name_invalid() {
+  let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
+    r"
+            - name: my-image-name
+              ims:
+                id: my-base-image-id
+                is_recipe: false
+              configuration: my-configuration-name
+              configuration_group_names:
+                - Compute
+                - tenant/a
+            ",
+  )
+  .unwrap();
+
+  let configuration_vec_in_sat_file: Vec<configuration::Configuration> =
+    serde_yaml::from_str(
+      r"
+            - name: my-configuration-name
+              layers: []
+            ",
+    )
+    .unwrap();
+
+  let image_vec_in_csm = vec![Image {
+    id: Some("my-base-image-id".to_string()),
+    name: "my-base-image-name".to_string(),
+    ..Default::default()
+  }];
+
+  let report = validate_sat_file_images_section(
+    image_vec_in_sat_file.as_slice(),
+    configuration_vec_in_sat_file.as_slice(),
+    &["tenant/a".to_string()],
+    &ProductCatalog::default(),
+    image_vec_in_csm,
+    vec![],
+    vec![],
+  );
+
+  let error_vec: Vec<_> = report.errors().collect();
+  assert_eq!(error_vec.len(), 1);
+  assert_eq!(
+    error_vec[0].path,
+    "images[my-image-name].configuration_group_names"
+  );
+  assert!(error_vec[0].message.contains("'/' not allowed"));
+}
+
 /// Test SAT file
 /// Test image section in OLD format in SAT file
 /// Result: PASS
 /// Reason: configuration assigned to image found in CSM
 #[test]
 fn test_old_image_format_in_sat_file_pass_because_configuration_found_in_csm() {
-  let cray_product_catalog = &BTreeMap::<String, String>::new();
+  let cray_product_catalog = &ProductCatalog::default();
 
   let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
     r"
@@ -279,7 +417,7 @@ fn test_old_image_format_in_sat_file_pass_because_configuration_found_in_csm() {
   let ims_recipes = vec![];
 
   assert!(
-    validate_sat_file_images_section(
+    !validate_sat_file_images_section(
       image_vec_in_sat_file.as_slice(),
       configuration_vec_in_sat_file.as_slice(),
       hsm_group_available_vec,
@@ -288,7 +426,7 @@ fn test_old_image_format_in_sat_file_pass_because_configuration_found_in_csm() {
       configuration_vec_in_csm,
       ims_recipes
     )
-    .is_ok()
+    .has_errors()
   );
 }
 
@@ -299,7 +437,7 @@ fn test_old_image_format_in_sat_file_pass_because_configuration_found_in_csm() {
 #[test]
 fn test_sat_file_image_section_fails_because_base_image_id_could_not_be_found()
 {
-  let cray_product_catalog = &BTreeMap::<String, String>::new();
+  let cray_product_catalog = &ProductCatalog::default();
 
   let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
     r"
@@ -342,7 +480,7 @@ fn test_sat_file_image_section_fails_because_base_image_id_could_not_be_found()
       configuration_vec_in_csm,
       ims_recipes
     )
-    .is_err()
+    .has_errors()
   );
 }
 
@@ -389,17 +527,17 @@ fn test_sat_file_image_section_fails_because_base_image_receipe_could_not_be_fou
 
   let ims_recipes = vec![];
 
-  let validation_rslt: Result<(), Error> = validate_sat_file_images_section(
+  let validation_report: ValidationReport = validate_sat_file_images_section(
     image_vec_in_sat_file.as_slice(),
     configuration_vec_in_sat_file.as_slice(),
     hsm_group_available_vec,
-    &cray_product_catalog,
+    &ProductCatalog::parse(&cray_product_catalog),
     image_vec_in_csm,
     configuration_vec_in_csm,
     ims_recipes,
   );
 
-  assert!(validation_rslt.is_err());
+  assert!(validation_report.has_errors());
 }
 
 /// Test SAT file
@@ -409,7 +547,7 @@ fn test_sat_file_image_section_fails_because_base_image_receipe_could_not_be_fou
 #[test]
 fn test_sat_file_image_section_fails_because_base_image_recipe_name_could_not_be_found()
  {
-  let cray_product_catalog = BTreeMap::<String, String>::new();
+  let cray_product_catalog = ProductCatalog::default();
 
   let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
     r"
@@ -448,9 +586,10 @@ fn test_sat_file_image_section_fails_because_base_image_recipe_name_could_not_be
     recipe_type: String::new(),
     linux_distribution: String::new(),
     name: "fake-my-ims-recipe".to_string(),
+    template_dictionary: None,
   }];
 
-  let validation_rslt: Result<(), Error> = validate_sat_file_images_section(
+  let validation_report: ValidationReport = validate_sat_file_images_section(
     image_vec_in_sat_file.as_slice(),
     configuration_vec_in_sat_file.as_slice(),
     hsm_group_available_vec,
@@ -460,7 +599,7 @@ fn test_sat_file_image_section_fails_because_base_image_recipe_name_could_not_be
     ims_recipes,
   );
 
-  assert!(validation_rslt.is_err());
+  assert!(validation_report.has_errors());
 }
 
 /// Test SAT file
@@ -470,7 +609,7 @@ fn test_sat_file_image_section_fails_because_base_image_recipe_name_could_not_be
 #[test]
 fn test_sat_file_image_section_pass_because_base_image_recipe_name_could_not_be_found()
  {
-  let cray_product_catalog = BTreeMap::<String, String>::new();
+  let cray_product_catalog = ProductCatalog::default();
 
   let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
     r"
@@ -509,9 +648,10 @@ fn test_sat_file_image_section_pass_because_base_image_recipe_name_could_not_be_
     recipe_type: String::new(),
     linux_distribution: String::new(),
     name: "my-ims-recipe-name".to_string(),
+    template_dictionary: None,
   }];
 
-  let validation_rslt: Result<(), Error> = validate_sat_file_images_section(
+  let validation_report: ValidationReport = validate_sat_file_images_section(
     image_vec_in_sat_file.as_slice(),
     configuration_vec_in_sat_file.as_slice(),
     hsm_group_available_vec,
@@ -521,7 +661,7 @@ fn test_sat_file_image_section_pass_because_base_image_recipe_name_could_not_be_
     ims_recipes,
   );
 
-  assert!(validation_rslt.is_ok());
+  assert!(!validation_report.has_errors());
 }
 
 /// Test SAT file
@@ -531,7 +671,7 @@ fn test_sat_file_image_section_pass_because_base_image_recipe_name_could_not_be_
 #[test]
 fn test_sat_file_image_section_fail_because_base_image_name_could_not_be_found()
 {
-  let cray_product_catalog = BTreeMap::<String, String>::new();
+  let cray_product_catalog = ProductCatalog::default();
 
   let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
     r"
@@ -572,7 +712,7 @@ fn test_sat_file_image_section_fail_because_base_image_name_could_not_be_found()
 
   let ims_recipes = vec![];
 
-  let validation_rslt: Result<(), Error> = validate_sat_file_images_section(
+  let validation_report: ValidationReport = validate_sat_file_images_section(
     image_vec_in_sat_file.as_slice(),
     configuration_vec_in_sat_file.as_slice(),
     hsm_group_available_vec,
@@ -582,7 +722,7 @@ fn test_sat_file_image_section_fail_because_base_image_name_could_not_be_found()
     ims_recipes,
   );
 
-  assert!(validation_rslt.is_err());
+  assert!(validation_report.has_errors());
 }
 
 /// Test SAT file
@@ -592,7 +732,7 @@ fn test_sat_file_image_section_fail_because_base_image_name_could_not_be_found()
 #[test]
 fn test_sat_file_image_section_pass_because_base_image_name_could_not_be_found()
 {
-  let cray_product_catalog = BTreeMap::<String, String>::new();
+  let cray_product_catalog = ProductCatalog::default();
 
   let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
     r"
@@ -633,7 +773,7 @@ fn test_sat_file_image_section_pass_because_base_image_name_could_not_be_found()
 
   let ims_recipes = vec![];
 
-  let validation_rslt: Result<(), Error> = validate_sat_file_images_section(
+  let validation_report: ValidationReport = validate_sat_file_images_section(
     image_vec_in_sat_file.as_slice(),
     configuration_vec_in_sat_file.as_slice(),
     hsm_group_available_vec,
@@ -643,7 +783,7 @@ fn test_sat_file_image_section_pass_because_base_image_name_could_not_be_found()
     ims_recipes,
   );
 
-  assert!(validation_rslt.is_ok());
+  assert!(!validation_report.has_errors());
 }
 
 /// Test SAT file
@@ -652,7 +792,7 @@ fn test_sat_file_image_section_pass_because_base_image_name_could_not_be_found()
 /// Reason: HSM groups assigned to an image are wrong
 #[test]
 fn test_sat_file_image_section_fail_because_hsm_groups_are_wrong() {
-  let cray_product_catalog = BTreeMap::<String, String>::new();
+  let cray_product_catalog = ProductCatalog::default();
 
   let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
     r"
@@ -693,7 +833,7 @@ fn test_sat_file_image_section_fail_because_hsm_groups_are_wrong() {
 
   let ims_recipes = vec![];
 
-  let validation_rslt: Result<(), Error> = validate_sat_file_images_section(
+  let validation_report: ValidationReport = validate_sat_file_images_section(
     image_vec_in_sat_file.as_slice(),
     configuration_vec_in_sat_file.as_slice(),
     hsm_group_available_vec,
@@ -703,7 +843,7 @@ fn test_sat_file_image_section_fail_because_hsm_groups_are_wrong() {
     ims_recipes,
   );
 
-  assert!(validation_rslt.is_err());
+  assert!(validation_report.has_errors());
 }
 
 /// Test SAT file
@@ -712,7 +852,7 @@ fn test_sat_file_image_section_fail_because_hsm_groups_are_wrong() {
 /// Reason: Image can miss 'configuration' section
 #[test]
 fn test_sat_file_image_section_pass_if_configuration_missing() {
-  let cray_product_catalog = BTreeMap::<String, String>::new();
+  let cray_product_catalog = ProductCatalog::default();
 
   let image_vec_in_sat_file: Vec<image::Image> = serde_yaml::from_str(
     r"
@@ -742,7 +882,7 @@ fn test_sat_file_image_section_pass_if_configuration_missing() {
 
   let ims_recipes = vec![];
 
-  let validation_rslt: Result<(), Error> = validate_sat_file_images_section(
+  let validation_report: ValidationReport = validate_sat_file_images_section(
     image_vec_in_sat_file.as_slice(),
     configuration_vec_in_sat_file.as_slice(),
     hsm_group_available_vec,
@@ -752,5 +892,539 @@ fn test_sat_file_image_section_pass_if_configuration_missing() {
     ims_recipes,
   );
 
-  assert!(validation_rslt.is_ok());
+  assert!(!validation_report.has_errors());
+}
+
+fn sat_image_named(name: &str) -> image::Image {
+  serde_yaml::from_str(&format!(
+    r#"
+    name: {name}
+    base:
+      product:
+        name: cos
+        type: recipe
+        version: "2.4.139"
+    "#
+  ))
+  .unwrap()
+}
+
+fn ims_image(id: &str, name: &str, created: &str) -> Image {
+  Image {
+    id: Some(id.to_string()),
+    created: Some(created.to_string()),
+    name: name.to_string(),
+    ..Default::default()
+  }
+}
+
+/// Each conflict policy only kicks in when IMS already has an image
+/// with the same name
+#[test]
+fn test_image_name_conflict_policy() {
+  let image_yaml = sat_image_named("compute");
+  let existing_image_vec = vec![
+    ims_image("id-1", "compute", "2024-01-01T00:00:00"),
+    ims_image("id-2", "compute", "2024-06-01T00:00:00"),
+    ims_image("id-3", "compute-1", "2024-06-01T00:00:00"),
+  ];
+
+  // No conflict
+  assert!(matches!(
+    resolve_image_name_conflict(
+      ImageNameConflictPolicy::Skip,
+      &sat_image_named("uan"),
+      &existing_image_vec,
+    ),
+    ImageNameResolution::Build(ref image, ref superseded)
+      if image.name == "uan" && superseded.is_empty()
+  ));
+
+  assert!(matches!(
+    resolve_image_name_conflict(
+      ImageNameConflictPolicy::Duplicate,
+      &image_yaml,
+      &existing_image_vec,
+    ),
+    ImageNameResolution::Build(ref image, ref superseded)
+      if image.name == "compute" && superseded.is_empty()
+  ));
+
+  // Skip reuses the newest image with that name
+  assert!(matches!(
+    resolve_image_name_conflict(
+      ImageNameConflictPolicy::Skip,
+      &image_yaml,
+      &existing_image_vec,
+    ),
+    ImageNameResolution::Reuse(ref image) if image.id.as_deref() == Some("id-2")
+  ));
+
+  assert!(matches!(
+    resolve_image_name_conflict(
+      ImageNameConflictPolicy::Overwrite,
+      &image_yaml,
+      &existing_image_vec,
+    ),
+    ImageNameResolution::Build(ref image, ref superseded)
+      if image.name == "compute" && superseded == &["id-1", "id-2"]
+  ));
+
+  // "compute-1" is taken too
+  assert!(matches!(
+    resolve_image_name_conflict(
+      ImageNameConflictPolicy::Rename,
+      &image_yaml,
+      &existing_image_vec,
+    ),
+    ImageNameResolution::Build(ref image, ref superseded)
+      if image.name == "compute-2" && superseded.is_empty()
+  ));
+}
+
+// ---------- plan ----------
+
+fn v3_configuration_request(
+  commit: &str,
+) -> crate::cfs::v3::CfsConfigurationRequest {
+  use crate::cfs::configuration::http_client::v3::types::cfs_configuration_request::Layer as RequestLayer;
+
+  let mut configuration = crate::cfs::v3::CfsConfigurationRequest::new();
+  configuration.add_layer(RequestLayer::new(
+    Some("cos".to_string()),
+    Some("https://api-gw-service-nmn.local/vcs/cray/cos.git".to_string()),
+    None,
+    "site.yml".to_string(),
+    Some(commit.to_string()),
+    None,
+    None,
+  ));
+  configuration
+}
+
+fn v3_configuration_response(
+  commit: &str,
+) -> crate::cfs::v3::CfsConfigurationResponse {
+  use crate::cfs::configuration::http_client::v3::types::cfs_configuration_response::Layer as ResponseLayer;
+
+  crate::cfs::v3::CfsConfigurationResponse {
+    name: "cos-config".to_string(),
+    description: None,
+    last_updated: String::new(),
+    layers: vec![ResponseLayer {
+      name: Some("cos".to_string()),
+      clone_url: "https://api-gw-service-nmn.local/vcs/cray/cos.git"
+        .to_string(),
+      source: None,
+      commit: Some(commit.to_string()),
+      playbook: "site.yml".to_string(),
+      branch: Some("main".to_string()),
+    }],
+    additional_inventory: None,
+  }
+}
+
+#[test]
+fn test_plan_configuration_action() {
+  use crate::commands::i_apply_sat_file::plan::{
+    ChangeAction, configuration_action,
+  };
+
+  let request = v3_configuration_request("abc123");
+
+  assert_eq!(configuration_action(&request, None).0, ChangeAction::Create);
+  assert_eq!(
+    configuration_action(&request, Some(&v3_configuration_response("abc123")))
+      .0,
+    ChangeAction::Skip
+  );
+  assert_eq!(
+    configuration_action(&request, Some(&v3_configuration_response("def456")))
+      .0,
+    ChangeAction::Update
+  );
+}
+
+#[test]
+fn test_plan_image_rebuilt_when_dependency_changes() {
+  use crate::commands::i_apply_sat_file::plan::{ChangeAction, image_action};
+
+  let sat_image = sat_image_named("compute");
+  let image_vec = vec![ims_image("id-1", "compute", "2024-01-01T00:00:00")];
+
+  assert_eq!(image_action(&sat_image, &[], false).0, ChangeAction::Create);
+
+  let (action, _, existing_id) = image_action(&sat_image, &image_vec, false);
+  assert_eq!(action, ChangeAction::Skip);
+  assert_eq!(existing_id.as_deref(), Some("id-1"));
+
+  assert_eq!(
+    image_action(&sat_image, &image_vec, true).0,
+    ChangeAction::Update
+  );
+}
+
+/// Pruning drops skipped entries and points `image_ref`s at the
+/// existing IMS image
+#[test]
+fn test_plan_prune_rewrites_image_refs() {
+  use crate::commands::i_apply_sat_file::plan::{
+    ChangeAction, ChangeSet, PlannedChange, SatResource,
+  };
+
+  let sat_file_yaml: serde_yaml::Value = serde_yaml::from_str(
+    r#"
+    configurations:
+    - name: cos-config
+      layers: []
+    images:
+    - name: base
+      ref_name: base_ref
+      base:
+        product:
+          name: cos
+          type: recipe
+          version: "2.4.139"
+    - name: compute
+      base:
+        image_ref: base_ref
+      configuration: cos-config
+    session_templates:
+    - name: compute-template
+      image:
+        image_ref: base_ref
+      configuration: cos-config
+      bos_parameters:
+        boot_sets:
+          compute:
+            node_groups: [compute]
+    "#,
+  )
+  .unwrap();
+
+  let change =
+    |resource, name: &str, action, existing_id: Option<&str>| PlannedChange {
+      resource,
+      name: name.to_string(),
+      action,
+      reason: String::new(),
+      existing_id: existing_id.map(str::to_string),
+    };
+  let change_set = ChangeSet {
+    changes: vec![
+      change(
+        SatResource::Configuration,
+        "cos-config",
+        ChangeAction::Skip,
+        None,
+      ),
+      change(SatResource::Image, "base", ChangeAction::Skip, Some("id-1")),
+      change(SatResource::Image, "compute", ChangeAction::Update, None),
+      change(
+        SatResource::SessionTemplate,
+        "compute-template",
+        ChangeAction::Update,
+        None,
+      ),
+    ],
+  };
+
+  let pruned = change_set.prune(&sat_file_yaml);
+
+  assert!(pruned["configurations"].as_sequence().unwrap().is_empty());
+  let image_vec = pruned["images"].as_sequence().unwrap();
+  assert_eq!(image_vec.len(), 1);
+  assert_eq!(image_vec[0]["name"].as_str(), Some("compute"));
+  assert_eq!(image_vec[0]["base"]["ims"]["id"].as_str(), Some("id-1"));
+  assert_eq!(image_vec[0]["base"]["ims"]["type"].as_str(), Some("image"));
+  assert_eq!(
+    pruned["session_templates"][0]["image"]["ims"]["id"].as_str(),
+    Some("id-1")
+  );
+}
+
+/// The SAT to BOS session template conversion carries every boot set
+/// field, and the S3 artifacts of the boot image when one is given
+#[test]
+fn test_sat_session_template_to_bos_session_template() {
+  use crate::{
+    bos::BosSessionTemplate,
+    commands::i_apply_sat_file::utils::sessiontemplate, error::Error,
+    ims::image::http_client::types::Link,
+  };
+
+  let sat_session_template: sessiontemplate::SessionTemplate =
+    serde_yaml::from_str(
+      r"
+      name: compute-template
+      image:
+        image_ref: compute_ref
+      configuration: cos-config
+      bos_parameters:
+        boot_sets:
+          compute:
+            arch: ARM
+            kernel_parameters: ip=dhcp quiet
+            node_roles_groups: [Compute]
+            rootfs_provider: sbps
+            rootfs_provider_passthrough: sbps:v1:iqn.2023-06.csm.iscsi:_sbps-hsn._tcp
+      ",
+    )
+    .unwrap();
+
+  let bos_session_template =
+    BosSessionTemplate::try_from(sat_session_template.clone()).unwrap();
+  let boot_set = &bos_session_template.boot_sets.as_ref().unwrap()["compute"];
+  assert_eq!(boot_set.kernel_parameters.as_deref(), Some("ip=dhcp quiet"));
+  assert_eq!(boot_set.arch.as_deref(), Some("ARM"));
+  assert_eq!(
+    boot_set.node_roles_groups,
+    Some(vec!["Compute".to_string()])
+  );
+  assert_eq!(boot_set.rootfs_provider.as_deref(), Some("sbps"));
+  assert_eq!(boot_set.path, None);
+  assert_eq!(
+    bos_session_template.configuration_name(),
+    Some("cos-config")
+  );
+
+  let mut boot_image = ims_image("id-1", "compute", "2024-01-01T00:00:00");
+  boot_image.link = Some(Link {
+    path: "s3://boot-images/id-1/manifest.json".to_string(),
+    etag: Some("etag-1".to_string()),
+    r#type: "s3".to_string(),
+  });
+  let bos_session_template =
+    BosSessionTemplate::try_from((sat_session_template.clone(), &boot_image))
+      .unwrap();
+  let boot_set = &bos_session_template.boot_sets.as_ref().unwrap()["compute"];
+  assert_eq!(
+    boot_set.path.as_deref(),
+    Some("s3://boot-images/id-1/manifest.json")
+  );
+  assert_eq!(boot_set.r#type.as_deref(), Some("s3"));
+  assert_eq!(boot_set.etag.as_deref(), Some("etag-1"));
+
+  boot_image.link = None;
+  assert!(matches!(
+    BosSessionTemplate::try_from((sat_session_template.clone(), &boot_image)),
+    Err(Error::SatFile(_))
+  ));
+
+  let mut without_kernel_parameters = sat_session_template;
+  without_kernel_parameters
+    .bos_parameters
+    .boot_sets
+    .get_mut("compute")
+    .unwrap()
+    .kernel_parameters = None;
+  match BosSessionTemplate::try_from(without_kernel_parameters) {
+    Err(Error::SatFileField { path, .. }) => assert_eq!(
+      path,
+      "session_templates[compute-template].bos_parameters.boot_sets.compute.kernel_parameters"
+    ),
+    other => panic!("expected a SAT file field error, got {other:?}"),
+  }
+}
+
+/// Session templates of a `sat bootprep` input file, as published with
+/// CSM, convert to BOS session templates boot set by boot set
+#[test]
+fn test_sat_bootprep_session_templates_to_bos_session_templates() {
+  use crate::{
+    bos::BosSessionTemplate, commands::i_apply_sat_file::utils::SatFile,
+  };
+
+  let sat_file: SatFile = serde_yaml::from_str(
+    r#"
+    schema_version: 1.0.2
+    session_templates:
+    - name: "{{default.note}}compute-{{recipe.version}}{{default.suffix}}"
+      image:
+        image_ref: compute_image
+      configuration: "{{default.note}}compute-{{recipe.version}}{{default.suffix}}"
+      bos_parameters:
+        boot_sets:
+          compute:
+            kernel_parameters: ip=dhcp quiet spire_join_token=${SPIRE_JOIN_TOKEN}
+            node_roles_groups:
+            - Compute
+            rootfs_provider_passthrough: "dvs:api-gw-service-nmn.local:300:hsn0,nmn0:0"
+    - name: uan-template
+      image:
+        ims:
+          name: uan-image
+      configuration: uan-config
+      bos_parameters:
+        boot_sets:
+          uan:
+            arch: X86
+            kernel_parameters: ip=dhcp quiet
+            node_roles_group:
+            - Application_UAN
+            rootfs_provider: sbps
+            rootfs_provider_passthrough: sbps:v1:iqn.2023-06.csm.iscsi:_sbps-hsn._tcp.{group}.{boot_set}:300
+          uan_gpu:
+            kernel_parameters: ip=dhcp quiet
+            node_groups:
+            - zinal
+            - zinal_gpu
+            rootfs_provider: sbps
+            rootfs_provider_passthrough: sbps:v1:iqn.2023-06.csm.iscsi:_sbps-hsn._tcp.{group}.{boot_set}:300
+    "#,
+  )
+  .unwrap();
+
+  let bos_session_template_vec: Vec<BosSessionTemplate> = sat_file
+    .session_templates
+    .unwrap()
+    .into_iter()
+    .map(|session_template| {
+      BosSessionTemplate::try_from(session_template).unwrap()
+    })
+    .collect();
+
+  let compute =
+    &bos_session_template_vec[0].boot_sets.as_ref().unwrap()["compute"];
+  assert_eq!(compute.node_roles_groups, Some(vec!["Compute".to_string()]));
+  assert_eq!(
+    compute.kernel_parameters.as_deref(),
+    Some("ip=dhcp quiet spire_join_token=${SPIRE_JOIN_TOKEN}")
+  );
+  assert_eq!(
+    compute.rootfs_provider_passthrough.as_deref(),
+    Some("dvs:api-gw-service-nmn.local:300:hsn0,nmn0:0")
+  );
+  assert_eq!(
+    bos_session_template_vec[0].configuration_name(),
+    Some("{{default.note}}compute-{{recipe.version}}{{default.suffix}}")
+  );
+
+  let boot_set_map = bos_session_template_vec[1].boot_sets.as_ref().unwrap();
+  assert_eq!(
+    boot_set_map["uan"].node_roles_groups,
+    Some(vec!["Application_UAN".to_string()])
+  );
+  assert_eq!(boot_set_map["uan"].arch.as_deref(), Some("X86"));
+  assert_eq!(
+    boot_set_map["uan"].rootfs_provider_passthrough.as_deref(),
+    Some("sbps:v1:iqn.2023-06.csm.iscsi:_sbps-hsn._tcp..uan:300")
+  );
+  assert_eq!(
+    boot_set_map["uan_gpu"].node_groups,
+    Some(vec!["zinal".to_string(), "zinal_gpu".to_string()])
+  );
+  assert_eq!(
+    boot_set_map["uan_gpu"]
+      .rootfs_provider_passthrough
+      .as_deref(),
+    Some(
+      "sbps:v1:iqn.2023-06.csm.iscsi:_sbps-hsn._tcp.zinal,zinal_gpu.uan_gpu:300"
+    )
+  );
+}
+
+#[test]
+fn test_recipe_template_dictionary_rejects_undeclared_keys() {
+  let recipe = RecipeGetResponse {
+    name: "cos-recipe".to_string(),
+    template_dictionary: Some(vec![RecipeKeyValuePair {
+      key: "CSM_RELEASE_VERSION".to_string(),
+      value: "1.5.0".to_string(),
+    }]),
+    ..Default::default()
+  };
+
+  assert_eq!(recipe_template_dictionary(&recipe, None).unwrap(), None);
+
+  let overrides =
+    BTreeMap::from([("CSM_RELEASE_VERSION".to_string(), "1.6.0".to_string())]);
+  assert_eq!(
+    recipe_template_dictionary(&recipe, Some(&overrides)).unwrap(),
+    Some(vec![RecipeKeyValuePair {
+      key: "CSM_RELEASE_VERSION".to_string(),
+      value: "1.6.0".to_string(),
+    }])
+  );
+
+  let overrides =
+    BTreeMap::from([("SLES_VERSION".to_string(), "15sp5".to_string())]);
+  assert!(recipe_template_dictionary(&recipe, Some(&overrides)).is_err());
+}
+
+/// Every boot set is validated, not only 'compute' / 'uan', and all
+/// problems are reported together
+#[tokio::test]
+async fn test_sat_file_session_template_section_validates_all_boot_sets() {
+  let sat_file: SatFile = serde_yaml::from_str(
+    r"
+    configurations:
+    - name: cos-config
+      layers: []
+    images:
+    - name: compute
+      ref_name: base_ref
+      base:
+        image_ref: other_ref
+    session_templates:
+    - name: compute-template
+      image:
+        image_ref: base_ref
+      configuration: cos-config
+      bos_parameters:
+        boot_sets:
+          compute:
+            node_groups: [zinal]
+          gpu:
+            node_groups: [other-tenant]
+          login:
+            kernel_parameters: quiet
+    ",
+  )
+  .unwrap();
+
+  let report = validate_sat_file_session_template_section(
+    "token",
+    "https://api.example.com/apis",
+    &[],
+    None,
+    sat_file.images.as_deref().unwrap_or_default(),
+    sat_file.configurations.as_deref().unwrap_or_default(),
+    sat_file.session_templates.as_deref().unwrap_or_default(),
+    &["zinal".to_string()],
+  )
+  .await
+  .unwrap();
+
+  let error_path_vec: Vec<&str> =
+    report.errors().map(|issue| issue.path.as_str()).collect();
+
+  assert_eq!(
+    error_path_vec,
+    vec![
+      "session_templates[compute-template].bos_parameters.boot_sets.gpu.node_groups",
+      "session_templates[compute-template].bos_parameters.boot_sets.login",
+    ]
+  );
+}
+
+#[test]
+fn test_cfs_session_retry_policy_matches_transient_failures() {
+  let policy = CfsSessionRetryPolicy::new(3);
+
+  assert_eq!(
+    policy
+      .transient_match(
+        "TASK [zypper] fatal: Failed to download metadata for repo 'SLES'"
+      )
+      .unwrap(),
+    Some(r"(?i)failed to (download|synchronize cache for repo)")
+  );
+  assert_eq!(
+    policy
+      .transient_match("TASK [lustre] fatal: 'lnet' is undefined")
+      .unwrap(),
+    None
+  );
+  assert_eq!(CfsSessionRetryPolicy::default().max_attempts, 1);
 }
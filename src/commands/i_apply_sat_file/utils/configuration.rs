@@ -31,9 +31,20 @@ pub enum Git {
   GitTag { url: String, tag: String },
 }
 
+/// Ref of a layer backed by a CFS source. The repo URL comes from the
+/// source, and CFS has no tag field, so only commit or branch apply.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(untagged)] // <-- this is important. More info https://serde.rs/enum-representations.html#untagged
+pub enum SourceRef {
+  SourceCommit { commit: String },
+  SourceBranch { branch: String },
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(untagged)] // <-- this is important. More info https://serde.rs/enum-representations.html#untagged
 pub enum LayerType {
+  // Must stay before `Git`: a source layer also carries a `git` key
+  Source { source: String, git: SourceRef },
   Git { git: Git },
   Product { product: Product },
 }
@@ -1,19 +1,31 @@
-use std::collections::BTreeMap;
-
 use crate::{
   cfs::{
     self,
+    configuration::types::ResolvedLayers,
     v2::{CfsConfigurationRequest, CfsConfigurationResponse},
   },
+  commands::i_apply_sat_file::provenance,
+  common::{events, product_catalog::ProductCatalog, protection},
   error::Error,
 };
 
-use super::{configuration, image, sessiontemplate};
+use super::{
+  configuration, image, sessiontemplate, validation::ValidationReport,
+};
 
 #[allow(clippy::too_many_arguments)]
 /// Create a CFS configuration from a single SAT-file `configurations`
 /// entry — resolves Git/product layer references, validates them, and
 /// posts to CFS.
+///
+/// Returns the created configuration together with the
+/// [`ResolvedLayers`] report of the commit each layer was pinned to. In
+/// dry-run mode the configuration is a placeholder but the report is
+/// the real resolution result.
+///
+/// With `overwrite`, a protected configuration (see
+/// [`crate::protection`]) is only replaced if `ignore_protection` is
+/// set.
 pub async fn create_cfs_configuration_from_sat_file(
   shasta_token: &str,
   shasta_base_url: &str,
@@ -21,18 +33,37 @@ pub async fn create_cfs_configuration_from_sat_file(
   socks5_proxy: Option<&str>,
   gitea_base_url: &str,
   gitea_token: &str,
-  cray_product_catalog: &BTreeMap<String, String>,
+  cray_product_catalog: &ProductCatalog,
   sat_file_configuration_yaml: &serde_yaml::Value,
   dry_run: bool,
   site_name: &str,
   overwrite: bool,
-) -> Result<CfsConfigurationResponse, Error> {
-  log::debug!(
+  ignore_protection: bool,
+) -> Result<(CfsConfigurationResponse, ResolvedLayers), Error> {
+  tracing::debug!(
     "Convert CFS configuration in SAT file (yaml):\n{sat_file_configuration_yaml:#?}"
   );
 
-  let (cfs_configuration_name, cfs_configuration) =
-    CfsConfigurationRequest::from_sat_file_serde_yaml(
+  if uses_cfs_sources(sat_file_configuration_yaml) {
+    return create_cfs_configuration_v3_from_sat_file(
+      shasta_token,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      gitea_base_url,
+      gitea_token,
+      cray_product_catalog,
+      sat_file_configuration_yaml,
+      dry_run,
+      site_name,
+      overwrite,
+      ignore_protection,
+    )
+    .await;
+  }
+
+  let (cfs_configuration_name, cfs_configuration, resolved_layers) =
+    CfsConfigurationRequest::from_sat_file_serde_yaml_with_report(
       shasta_root_cert,
       gitea_base_url,
       gitea_token,
@@ -44,10 +75,11 @@ pub async fn create_cfs_configuration_from_sat_file(
     .await?;
 
   if dry_run {
-    log::debug!(
+    tracing::debug!(
       "Dry run mode: Create CFS configuration:\n{}",
       serde_json::to_string_pretty(&cfs_configuration)?
     );
+    events::info(format!("Dry run mode: Refs pinned for {resolved_layers}"));
 
     // Generate mock CFS configuration
     let cfs_configuration = CfsConfigurationResponse {
@@ -58,40 +90,157 @@ pub async fn create_cfs_configuration_from_sat_file(
     };
 
     // Return mock CFS configuration
-    Ok(cfs_configuration)
+    Ok((cfs_configuration, resolved_layers))
   } else {
-    cfs::configuration::utils::create_new_configuration(
-      shasta_token,
-      shasta_base_url,
+    let (cfs_configuration, action) =
+      cfs::configuration::utils::create_new_configuration(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        socks5_proxy,
+        &cfs_configuration,
+        &cfs_configuration_name,
+        overwrite.into(),
+      )
+      .await?;
+
+    tracing::debug!("CFS configuration '{cfs_configuration_name}' {action}");
+
+    Ok((cfs_configuration, resolved_layers))
+  }
+}
+
+/// True if any layer of the SAT-file configuration references a CFS
+/// source, which only the v3 API understands.
+fn uses_cfs_sources(sat_file_configuration_yaml: &serde_yaml::Value) -> bool {
+  sat_file_configuration_yaml
+    .get("layers")
+    .and_then(serde_yaml::Value::as_sequence)
+    .is_some_and(|layers| {
+      layers.iter().any(|layer| layer.get("source").is_some())
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+/// v3 flavour of [`create_cfs_configuration_from_sat_file`] for
+/// configurations with layers backed by CFS sources. Returns the
+/// configuration downgraded to the v2 shape the rest of the SAT
+/// pipeline works with. That the sources are registered is checked when
+/// the SAT file is validated, before anything is created.
+async fn create_cfs_configuration_v3_from_sat_file(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  gitea_base_url: &str,
+  gitea_token: &str,
+  cray_product_catalog: &ProductCatalog,
+  sat_file_configuration_yaml: &serde_yaml::Value,
+  dry_run: bool,
+  site_name: &str,
+  overwrite: bool,
+  ignore_protection: bool,
+) -> Result<(CfsConfigurationResponse, ResolvedLayers), Error> {
+  let (cfs_configuration_name, mut cfs_configuration, resolved_layers) =
+    cfs::v3::CfsConfigurationRequest::from_sat_file_serde_yaml_with_report(
       shasta_root_cert,
+      gitea_base_url,
+      gitea_token,
+      sat_file_configuration_yaml,
+      cray_product_catalog,
+      site_name,
       socks5_proxy,
+    )
+    .await?;
+  cfs_configuration.description =
+    provenance::annotate_description(cfs_configuration.description.take());
+
+  if dry_run {
+    tracing::debug!(
+      "Dry run mode: Create CFS configuration:\n{}",
+      serde_json::to_string_pretty(&cfs_configuration)?
+    );
+    events::info(format!("Dry run mode: Refs pinned for {resolved_layers}"));
+
+    let cfs_configuration = CfsConfigurationResponse {
+      name: cfs_configuration_name,
+      last_updated: String::new(),
+      layers: Vec::new(),
+      additional_inventory: None,
+    };
+
+    return Ok((cfs_configuration, resolved_layers));
+  }
+
+  let shasta_client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+
+  // `cfs_configuration_v3_put` refuses to replace an existing
+  // configuration, so honour `overwrite` by deleting it first
+  let existing_configuration_vec = if overwrite {
+    shasta_client
+      .cfs_configuration_v3_get(shasta_token, Some(&cfs_configuration_name))
+      .await
+      .unwrap_or_default()
+  } else {
+    Vec::new()
+  };
+
+  if !existing_configuration_vec.is_empty() {
+    if !ignore_protection
+      && existing_configuration_vec.iter().any(|cfs_configuration| {
+        protection::is_protected(cfs_configuration.description.as_deref())
+      })
+    {
+      return Err(Error::Protected(format!(
+        "CFS configuration '{cfs_configuration_name}'"
+      )));
+    }
+
+    tracing::debug!(
+      "CFS configuration '{cfs_configuration_name}' already exists but 'overwrite' has been enabled"
+    );
+    shasta_client
+      .cfs_configuration_v3_delete(shasta_token, &cfs_configuration_name)
+      .await?;
+  }
+
+  let cfs_configuration = shasta_client
+    .cfs_configuration_v3_put(
+      shasta_token,
       &cfs_configuration,
       &cfs_configuration_name,
-      overwrite,
     )
-    .await
-  }
+    .await?;
+
+  Ok((cfs_configuration.into(), resolved_layers))
 }
 
 /// Pre-flight check that the SAT file's `configurations` section is
 /// self-consistent with its `images` and `session_templates` sections
 /// (no orphan references, no empty configuration with referencing
 /// downstream entries).
+#[must_use]
 pub fn validate_sat_file_configurations_section(
   configuration_yaml_vec: &[configuration::Configuration],
   image_yaml_vec_opt: &[image::Image],
   sessiontemplate_yaml_vec_opt: &[sessiontemplate::SessionTemplate],
-) -> Result<(), Error> {
+) -> ValidationReport {
+  let mut report = ValidationReport::new();
+
   // Validate 'configurations' sections
   if !configuration_yaml_vec.is_empty()
     && image_yaml_vec_opt.is_empty()
     && sessiontemplate_yaml_vec_opt.is_empty()
   {
-    return Err(Error::Message(
-        "Incorrect SAT file. Please define either an 'images' or a 'session_templates' section. Exit"
-            .to_string(),
-      ));
+    report.error(
+      "configurations",
+      "Incorrect SAT file. Please define either an 'images' or a 'session_templates' section",
+    );
   }
 
-  Ok(())
+  report
 }
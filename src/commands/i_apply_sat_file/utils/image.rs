@@ -2,11 +2,18 @@
 //! file; field names and shapes are dictated by the SAT format.
 #![allow(missing_docs)]
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use strum_macros::AsRefStr;
 
-#[derive(Deserialize, Serialize, Debug, Clone, AsRefStr)]
-#[serde(untagged)] // <-- this is important. More info https://serde.rs/enum-representations.html#untagged
+use crate::common::product_catalog::ArtifactSelection;
+
+// Not untagged: an untagged unit variant only matches `null`, never
+// the "aarch64" / "x86_64" strings of the SAT file
+#[derive(
+  Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, AsRefStr,
+)]
 pub enum Arch {
   #[serde(rename(serialize = "aarch64", deserialize = "aarch64"))]
   Aarch64,
@@ -14,6 +21,29 @@ pub enum Arch {
   X86_64,
 }
 
+impl Arch {
+  /// IMS name of the architecture, also the suffix of the images built
+  /// for it out of an image with `archs`.
+  #[must_use]
+  pub fn ims_arch(self) -> &'static str {
+    match self {
+      Self::Aarch64 => "aarch64",
+      Self::X86_64 => "x86_64",
+    }
+  }
+
+  /// Architecture of BOS boot set `arch` value `boot_set_arch` (`ARM`,
+  /// `X86`), `None` for `Other` and `Unknown`.
+  #[must_use]
+  pub fn from_boot_set_arch(boot_set_arch: &str) -> Option<Self> {
+    match boot_set_arch {
+      "ARM" => Some(Self::Aarch64),
+      "X86" => Some(Self::X86_64),
+      _ => None,
+    }
+  }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(untagged)] // <-- this is important. More info https://serde.rs/enum-representations.html#untagged
 pub enum ImageIms {
@@ -35,6 +65,35 @@ pub enum Filter {
   Prefix { prefix: String },
   Wildcard { wildcard: String },
   Arch { arch: Arch },
+  Regex { regex: String },
+  // A list of filters, all of which must match
+  All(Vec<Filter>),
+}
+
+impl Filter {
+  /// Architecture the filter selects, if any.
+  #[must_use]
+  pub fn arch(&self) -> Option<Arch> {
+    match self {
+      Self::Arch { arch } => Some(*arch),
+      Self::All(filter_vec) => filter_vec.iter().find_map(Self::arch),
+      Self::Prefix { .. } | Self::Wildcard { .. } | Self::Regex { .. } => None,
+    }
+  }
+}
+
+impl From<&Filter> for crate::common::product_catalog::ArtifactFilter {
+  fn from(filter: &Filter) -> Self {
+    match filter {
+      Filter::Prefix { prefix } => Self::Prefix(prefix.clone()),
+      Filter::Wildcard { wildcard } => Self::Wildcard(wildcard.clone()),
+      Filter::Arch { arch } => Self::Arch(arch.as_ref().to_string()),
+      Filter::Regex { regex } => Self::Regex(regex.clone()),
+      Filter::All(filter_vec) => {
+        Self::All(filter_vec.iter().map(Self::from).collect())
+      }
+    }
+  }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -44,6 +103,14 @@ pub struct Product {
   pub version: Option<String>,
   pub r#type: String,
   pub filter: Option<Filter>,
+  // Which artifact to take when several match `filter`: `only` (fail)
+  // or `latest`
+  #[serde(default, skip_serializing_if = "is_only")]
+  pub select: ArtifactSelection,
+}
+
+fn is_only(selection: &ArtifactSelection) -> bool {
+  *selection == ArtifactSelection::Only
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -75,4 +142,16 @@ pub struct Image {
   pub ref_name: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub description: Option<String>,
+  // Recipe template variable overrides, only for images built from an
+  // IMS recipe
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub template_dictionary: Option<BTreeMap<String, String>>,
+  // Build the image once per architecture, as '<name>-<arch>' with ref
+  // name '<ref_name>-<arch>'; see `images::expand_multi_arch_images`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub archs: Option<Vec<Arch>>,
+  // Architecture this entry is built for, set on the entries
+  // `images::expand_multi_arch_images` expands `archs` into
+  #[serde(skip)]
+  pub arch: Option<Arch>,
 }
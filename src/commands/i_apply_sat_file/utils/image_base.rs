@@ -0,0 +1,340 @@
+//! Resolution of the base image a SAT file image is configured on top
+//! of.
+//!
+//! A SAT image names its base in one of six ways, each an
+//! [`ImageBaseResolver`] variant:
+//!
+//! ```yaml
+//! base: {ims: {id: 4e1b..., type: image}} # ImsImage
+//! base: {ims: {name: cos-recipe, type: recipe}} # ImsRecipe
+//! base: {product: {name: cos, version: 2.3.101, type: image}} # ProductImage
+//! base: {product: {name: cos, version: 2.3.101, type: recipe}} # ProductRecipe
+//! base: {image_ref: base_cos_image} # ImageRef
+//! ims: {id: 4e1b..., is_recipe: false} # LegacyIms
+//! ```
+//!
+//! [`ImageBaseResolver::from_image`] picks the variant, rejecting bases
+//! csm-rs can't build, and [`ImageBaseResolver::resolve`] returns the
+//! id of the IMS image, building it with an IMS job for recipes.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+  common::{
+    poll::WaitOptions,
+    product_catalog::{ArtifactFilter, ArtifactKind, ProductCatalog},
+  },
+  error::Error,
+  ims::PublicKeySelector,
+};
+
+use super::{
+  image,
+  images::{
+    process_sat_file_image_ims_type_recipe,
+    process_sat_file_image_old_version_struct,
+    process_sat_file_image_product_type_ims_recipe,
+  },
+};
+
+/// What resolving the base image of SAT image `image_name` needs
+/// besides the base itself.
+pub(crate) struct ImageBaseContext<'a> {
+  pub(crate) shasta_token: &'a str,
+  pub(crate) shasta_base_url: &'a str,
+  pub(crate) shasta_root_cert: &'a [u8],
+  pub(crate) socks5_proxy: Option<&'a str>,
+  pub(crate) cray_product_catalog: &'a ProductCatalog,
+  /// Ids of the images built earlier in the same run, by `ref_name`.
+  pub(crate) ref_name_image_id_hashmap: &'a HashMap<String, String>,
+  pub(crate) image_name: &'a str,
+  /// Recipe template variable overrides of the SAT image.
+  pub(crate) template_dictionary_opt: Option<&'a BTreeMap<String, String>>,
+  pub(crate) arch_opt: Option<image::Arch>,
+  pub(crate) dry_run: bool,
+  pub(crate) ims_job_wait: WaitOptions,
+  pub(crate) ims_public_key_selector: &'a PublicKeySelector,
+}
+
+/// Base of a SAT image, see the [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ImageBaseResolver<'a> {
+  /// `base.ims` of type `image`: an existing IMS image.
+  ImsImage { id: &'a str },
+  /// `base.ims` of type `recipe`: built from the IMS recipe `name`.
+  ImsRecipe { name: &'a str },
+  /// `base.product` of type `image`: an image of the product catalog.
+  ProductImage { product: &'a image::Product },
+  /// `base.product` of type `recipe`: built from a recipe of the product
+  /// catalog.
+  ProductRecipe { product: &'a image::Product },
+  /// `base.image_ref`: another image of the SAT file.
+  ImageRef { image_ref: &'a str },
+  /// `ims` of SAT files predating `base`.
+  LegacyIms { ims: &'a image::ImageIms },
+}
+
+impl<'a> ImageBaseResolver<'a> {
+  /// The resolver of the base of `image_yaml`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::SatFile`] if the `type` of `base.ims` or
+  /// `base.product` is not one csm-rs builds from.
+  pub(crate) fn from_image(
+    image_yaml: &'a image::Image,
+  ) -> Result<Self, Error> {
+    let base = match &image_yaml.base_or_ims {
+      image::BaseOrIms::Ims { ims } => return Ok(Self::LegacyIms { ims }),
+      image::BaseOrIms::Base { base } => base,
+    };
+
+    match base {
+      image::Base::ImageRef { image_ref } => Ok(Self::ImageRef { image_ref }),
+      image::Base::Ims {
+        ims: image::ImageBaseIms::NameType { name, r#type },
+      } if r#type == "recipe" => Ok(Self::ImsRecipe { name }),
+      image::Base::Ims {
+        ims: image::ImageBaseIms::IdType { id, r#type },
+      } if r#type == "image" => Ok(Self::ImsImage { id }),
+      image::Base::Ims { .. } => Err(Error::SatFile(
+        "Can't process SAT file 'images.base.ims' is missing. Exit".to_string(),
+      )),
+      image::Base::Product { product } => {
+        match ArtifactKind::parse(&product.r#type) {
+          Some(ArtifactKind::Image) => Ok(Self::ProductImage { product }),
+          Some(ArtifactKind::Recipe) => Ok(Self::ProductRecipe { product }),
+          None => Err(Error::SatFile(
+            "Can't process SAT file, field 'images.base.product.type' must be either 'images' or 'recipes'. Exit".to_string(),
+          )),
+        }
+      }
+    }
+  }
+
+  /// Id of the base IMS image, built first for the recipe variants
+  /// (not in `context.dry_run` mode).
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::CrayProductCatalog`] if the product catalog has no
+  /// matching artifact, [`Error::AmbiguousProductImage`] if it has
+  /// several, [`Error::SatFile`] if a recipe or its IMS job fails, or
+  /// the CSM error of the IMS calls.
+  pub(crate) async fn resolve(
+    self,
+    context: &ImageBaseContext<'_>,
+  ) -> Result<String, Error> {
+    match self {
+      Self::ImsImage { id } => {
+        tracing::debug!("SAT file - 'image.base.ims' job of type 'image'");
+        Ok(id.to_string())
+      }
+      Self::ImsRecipe { name } => {
+        tracing::debug!("SAT file - 'image.base.ims' job of type 'recipe'");
+        process_sat_file_image_ims_type_recipe(context, name).await
+      }
+      Self::ProductImage { product } => {
+        tracing::debug!(
+          "SAT file - 'image.base.product' job based on IMS images"
+        );
+        product_artifact_id(context, product, ArtifactKind::Image)
+      }
+      Self::ProductRecipe { product } => {
+        tracing::debug!(
+          "SAT file - 'image.base.product' job based on IMS recipes"
+        );
+        let recipe_id =
+          product_artifact_id(context, product, ArtifactKind::Recipe)?;
+        process_sat_file_image_product_type_ims_recipe(context, &recipe_id)
+          .await
+      }
+      Self::ImageRef { image_ref } => {
+        tracing::debug!("SAT file - 'image.base.image_ref' job");
+        // Refs not built in this run are passed on as is
+        Ok(
+          context
+            .ref_name_image_id_hashmap
+            .get(image_ref)
+            .map_or(image_ref, String::as_str)
+            .to_string(),
+        )
+      }
+      Self::LegacyIms { ims } => {
+        tracing::debug!(
+          "SAT file - 'image.ims' job ('images' section in SAT file is outdated - switching to backward compatibility)"
+        );
+        process_sat_file_image_old_version_struct(ims)
+      }
+    }
+  }
+}
+
+/// IMS id of the `kind` artifact of `product` in the product catalog
+/// picked by its `filter` and `select`. Without `filter` the first
+/// artifact is used.
+fn product_artifact_id(
+  context: &ImageBaseContext<'_>,
+  product: &image::Product,
+  kind: ArtifactKind,
+) -> Result<String, Error> {
+  let product_name = &product.name;
+  let product_version = product.version.as_ref().ok_or_else(|| {
+    Error::YamlShape(format!(
+      "SAT file: image base.product '{product_name}' is missing 'version'"
+    ))
+  })?;
+
+  Ok(
+    context
+      .cray_product_catalog
+      .version(product_name, product_version)?
+      .select_artifact(
+        kind,
+        product.filter.as_ref().map(ArtifactFilter::from).as_ref(),
+        product.select,
+        context.image_name,
+      )?
+      .to_string(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+  };
+
+  use super::*;
+
+  const TEST_PEM: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBhTCCASugAwIBAgIQIRi6zePL6mKjOipn+dNuaTAKBggqhkjOPQQDAjASMRAw\n\
+DgYDVQQKEwdBY21lIENvMB4XDTE3MTAyMDE5NDMwNloXDTE4MTAyMDE5NDMwNlow\n\
+EjEQMA4GA1UEChMHQWNtZSBDbzBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABD0d\n\
+7VNhbWvZLWPuj/RtHFjvtJBEwOkhbN/BnnE8rnZR8+sbwnc/KhCk3FhnpHZnQz7B\n\
+5aETbbIgmuvewdjvSBSjYzBhMA4GA1UdDwEB/wQEAwICpDATBgNVHSUEDDAKBggr\n\
+BgEFBQcDATAPBgNVHRMBAf8EBTADAQH/MCkGA1UdEQQiMCCCDmxvY2FsaG9zdDo1\n\
+NDUzgg4xMjcuMC4wLjE6NTQ1MzAKBggqhkjOPQQDAgNIADBFAiEA2zpJEPQyz6/l\n\
+Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
+6MF9+Yw1Yy0t\n\
+-----END CERTIFICATE-----\n";
+
+  fn image(base_yaml: &str) -> image::Image {
+    serde_yaml::from_str(&format!(
+      "name: compute-image\nconfiguration: compute-config\n{base_yaml}"
+    ))
+    .unwrap()
+  }
+
+  fn catalog() -> ProductCatalog {
+    ProductCatalog::parse(&BTreeMap::from([(
+      "cos".to_string(),
+      "
+2.3.101:
+  images:
+    cray-shasta-compute-sles15sp3.x86_64-2.3.101:
+      id: 1a2b
+  recipes:
+    cray-shasta-compute-sles15sp3.x86_64-2.3.101:
+      id: 3c4d
+"
+      .to_string(),
+    )]))
+  }
+
+  async fn resolve(
+    image_yaml: &image::Image,
+    shasta_base_url: &str,
+  ) -> Result<String, Error> {
+    let cray_product_catalog = catalog();
+    let ref_name_image_id_hashmap =
+      HashMap::from([("base_cos_image".to_string(), "5e6f".to_string())]);
+    let context = ImageBaseContext {
+      shasta_token: "token",
+      shasta_base_url,
+      shasta_root_cert: TEST_PEM.as_bytes(),
+      socks5_proxy: None,
+      cray_product_catalog: &cray_product_catalog,
+      ref_name_image_id_hashmap: &ref_name_image_id_hashmap,
+      image_name: &image_yaml.name,
+      template_dictionary_opt: None,
+      arch_opt: None,
+      dry_run: true,
+      ims_job_wait: WaitOptions::default(),
+      ims_public_key_selector: &PublicKeySelector::Newest,
+    };
+
+    ImageBaseResolver::from_image(image_yaml)?
+      .resolve(&context)
+      .await
+  }
+
+  #[tokio::test]
+  async fn resolves_bases_without_ims_jobs() {
+    let unreachable = "http://127.0.0.1:1";
+    for (base_yaml, image_id) in [
+      ("base: {ims: {id: 4e1b, type: image}}", "4e1b"),
+      ("base: {image_ref: base_cos_image}", "5e6f"),
+      ("base: {image_ref: existing-image}", "existing-image"),
+      (
+        "base: {product: {name: cos, version: 2.3.101, type: image}}",
+        "1a2b",
+      ),
+      (
+        "base: {product: {name: cos, version: 2.3.101, type: image, filter: [{prefix: cray-}, {regex: compute}], select: latest}}",
+        "1a2b",
+      ),
+      ("ims: {id: 7a8b, is_recipe: false}", "7a8b"),
+    ] {
+      assert_eq!(
+        resolve(&image(base_yaml), unreachable).await.unwrap(),
+        image_id,
+        "{base_yaml}"
+      );
+    }
+
+    for base_yaml in [
+      "base: {ims: {name: cos-recipe, type: image}}",
+      "base: {product: {name: cos, version: 2.3.101, type: kernel}}",
+      "ims: {id: 7a8b, is_recipe: true}",
+    ] {
+      assert!(matches!(
+        resolve(&image(base_yaml), unreachable).await,
+        Err(Error::SatFile(_))
+      ));
+    }
+    assert!(matches!(
+      resolve(
+        &image("base: {product: {name: cos, version: 9.9.9, type: image}}"),
+        unreachable
+      )
+      .await,
+      Err(Error::CrayProductCatalog(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn product_recipe_builds_with_the_ims_public_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path("/ims/v3/public-keys"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(
+        serde_json::json!([{
+          "id": "key-1",
+          "created": "2024-01-01T00:00:00Z",
+          "name": "mgmt root key",
+          "public_key": "ssh-ed25519 AAAA root@ncn-m001"
+        }]),
+      ))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let image_yaml =
+      image("base: {product: {name: cos, version: 2.3.101, type: recipe}}");
+    let image_id = resolve(&image_yaml, &server.uri()).await.unwrap();
+    // Dry run: the IMS job is not submitted
+    assert!(uuid::Uuid::parse_str(&image_id).is_ok());
+  }
+}
@@ -1,7 +1,8 @@
 use std::collections::{BTreeMap, HashMap};
 
 use chrono::Local;
-use serde_json::Map;
+use regex::Regex;
+use serde::Serialize;
 use uuid::Uuid;
 
 use crate::{
@@ -12,20 +13,25 @@ use crate::{
       CfsSessionPostRequest, Configuration, Group, Session, Status, Target,
     },
   },
+  commands::i_apply_sat_file::provenance,
   error::Error,
   hsm,
-  ims::{self},
+  ims::{self, PublicKeySelector, image::labels},
 };
 
 use crate::common::{
-  kubernetes::{self, i_print_cfs_session_logs},
+  events,
+  kubernetes::{self, PodWaitTimeouts, i_print_cfs_session_logs},
+  poll::WaitOptions,
+  product_catalog::{ArtifactFilter, ArtifactKind, ProductCatalog},
+  session_profile,
   vault::http_client::fetch_shasta_k8s_secrets_from_vault,
 };
 
 use super::{
-  configuration,
-  image::{self, Filter},
+  configuration, image, image_base::ImageBaseContext,
   session_templates::get_base_image_id_from_sat_file_image_yaml,
+  validation::ValidationReport,
 };
 
 /// Analyze a list of images in SAT file and returns the image to process next.
@@ -107,43 +113,400 @@ pub fn get_image_name_or_ref_name_to_process_struct(
   }
 }
 
+/// Expand every image with `archs` into one image per architecture,
+/// named `<name>-<arch>` with ref name `<ref_name>-<arch>`, so each one
+/// gets its own IMS job and CFS session.
+///
+/// An expanded image built from a product without a `filter` takes the
+/// product's artifact for its architecture, and one based on another
+/// multi-arch image (`base.image_ref`) is based on that image's build
+/// for the same architecture. Images without `archs` are kept as they
+/// are.
+#[must_use]
+pub fn expand_multi_arch_images(
+  image_yaml_vec: &[image::Image],
+) -> Vec<image::Image> {
+  let multi_arch_ref_name_map: HashMap<String, &[image::Arch]> = image_yaml_vec
+    .iter()
+    .filter_map(|image_yaml| {
+      image_yaml.archs.as_deref().map(|arch_vec| {
+        (
+          get_image_name_or_ref_name_to_process_struct(image_yaml),
+          arch_vec,
+        )
+      })
+    })
+    .collect();
+
+  let mut expanded_image_yaml_vec = Vec::new();
+
+  for image_yaml in image_yaml_vec {
+    let Some(arch_vec) = &image_yaml.archs else {
+      expanded_image_yaml_vec.push(image_yaml.clone());
+      continue;
+    };
+
+    let ref_name = get_image_name_or_ref_name_to_process_struct(image_yaml);
+
+    for &arch in arch_vec {
+      let suffix = arch.ims_arch();
+
+      let mut arch_image_yaml = image_yaml.clone();
+      arch_image_yaml.name = format!("{}-{suffix}", image_yaml.name);
+      arch_image_yaml.ref_name = Some(format!("{ref_name}-{suffix}"));
+      arch_image_yaml.archs = None;
+      arch_image_yaml.arch = Some(arch);
+
+      if let image::BaseOrIms::Base { base } = &mut arch_image_yaml.base_or_ims
+      {
+        match base {
+          image::Base::Product { product } if product.filter.is_none() => {
+            product.filter = Some(image::Filter::Arch { arch });
+          }
+          image::Base::ImageRef { image_ref }
+            if multi_arch_ref_name_map
+              .get(image_ref.as_str())
+              .is_some_and(|base_arch_vec| base_arch_vec.contains(&arch)) =>
+          {
+            *image_ref = format!("{image_ref}-{suffix}");
+          }
+          _ => {}
+        }
+      }
+
+      expanded_image_yaml_vec.push(arch_image_yaml);
+    }
+  }
+
+  expanded_image_yaml_vec
+}
+
+/// What to do when a SAT-file image would be built under a name an
+/// IMS image already uses (`sat bootprep --overwrite-images` and
+/// friends).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageNameConflictPolicy {
+  /// Build anyway and leave IMS with several images sharing the name.
+  /// This is how the `images` section behaved before the policy
+  /// existed.
+  #[default]
+  Duplicate,
+  /// Keep the existing image and don't build. SAT images depending on
+  /// this one through `base.image_ref` use the existing image.
+  Skip,
+  /// Build, then delete the superseded images. IMS permanent deletion
+  /// also removes their S3 manifest and artifacts.
+  Overwrite,
+  /// Build under `<name>-<n>`, using the first free suffix.
+  Rename,
+}
+
+/// Outcome of checking a SAT-file image against the images already in
+/// IMS.
+#[derive(Debug)]
+pub(crate) enum ImageNameResolution {
+  /// Build this (possibly renamed) image, then delete these superseded
+  /// image ids.
+  Build(image::Image, Vec<String>),
+  /// Don't build; reuse this existing image.
+  Reuse(ims::image::http_client::types::Image),
+}
+
+/// Apply `policy` to `image_yaml` given the images already in IMS.
+pub(crate) fn resolve_image_name_conflict(
+  policy: ImageNameConflictPolicy,
+  image_yaml: &image::Image,
+  existing_image_vec: &[ims::image::http_client::types::Image],
+) -> ImageNameResolution {
+  let conflict_vec: Vec<&ims::image::http_client::types::Image> =
+    existing_image_vec
+      .iter()
+      .filter(|existing_image| existing_image.name == image_yaml.name)
+      .collect();
+
+  if conflict_vec.is_empty() {
+    return ImageNameResolution::Build(image_yaml.clone(), Vec::new());
+  }
+
+  match policy {
+    ImageNameConflictPolicy::Duplicate => {
+      ImageNameResolution::Build(image_yaml.clone(), Vec::new())
+    }
+    ImageNameConflictPolicy::Skip => {
+      // Several images may already share the name; reuse the newest
+      let newest = conflict_vec
+        .into_iter()
+        .max_by(|a, b| a.created.cmp(&b.created))
+        .cloned()
+        .unwrap_or_default();
+      ImageNameResolution::Reuse(newest)
+    }
+    ImageNameConflictPolicy::Overwrite => ImageNameResolution::Build(
+      image_yaml.clone(),
+      conflict_vec
+        .iter()
+        .filter_map(|existing_image| existing_image.id.clone())
+        .collect(),
+    ),
+    ImageNameConflictPolicy::Rename => {
+      // One of the first `len + 1` suffixes is always free
+      let new_name = (1..=existing_image_vec.len() + 1)
+        .map(|n| format!("{}-{n}", image_yaml.name))
+        .find(|candidate| {
+          !existing_image_vec
+            .iter()
+            .any(|existing_image| existing_image.name == *candidate)
+        })
+        .unwrap_or_default();
+      let mut renamed_image_yaml = image_yaml.clone();
+      renamed_image_yaml.name = new_name;
+      ImageNameResolution::Build(renamed_image_yaml, Vec::new())
+    }
+  }
+}
+
+/// Ansible log patterns (regexes) that usually mean an image-build CFS
+/// session failed because of the network or a registry rather than
+/// the playbooks.
+pub const DEFAULT_TRANSIENT_ANSIBLE_PATTERNS: [&str; 6] = [
+  r"(?i)(timed out|timeout (error|exceeded))",
+  r"(?i)connection (refused|reset)",
+  r"(?i)could not resolve host",
+  r"(?i)failed to (download|synchronize cache for repo)",
+  r"(?i)error pulling image",
+  r"(?i)(502 bad gateway|503 service unavailable)",
+];
+
+/// When to re-submit an image-build CFS session that failed.
+///
+/// A failed session is deleted and submitted again if its Ansible log
+/// matches one of `transient_pattern_vec` and fewer than
+/// `max_attempts` sessions were submitted. The default never retries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfsSessionRetryPolicy {
+  /// Sessions submitted at most, including the first one.
+  pub max_attempts: u32,
+  /// Regexes matched against the Ansible log of a failed session.
+  pub transient_pattern_vec: Vec<String>,
+}
+
+impl Default for CfsSessionRetryPolicy {
+  fn default() -> Self {
+    Self::new(1)
+  }
+}
+
+impl CfsSessionRetryPolicy {
+  /// Submit up to `max_attempts` sessions, retrying on
+  /// [`DEFAULT_TRANSIENT_ANSIBLE_PATTERNS`].
+  #[must_use]
+  pub fn new(max_attempts: u32) -> Self {
+    Self {
+      max_attempts,
+      transient_pattern_vec: DEFAULT_TRANSIENT_ANSIBLE_PATTERNS
+        .iter()
+        .map(ToString::to_string)
+        .collect(),
+    }
+  }
+
+  /// First of `transient_pattern_vec` matching `ansible_log`, if any.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Message`] if a pattern is not a valid regex.
+  pub fn transient_match(
+    &self,
+    ansible_log: &str,
+  ) -> Result<Option<&str>, Error> {
+    for pattern in &self.transient_pattern_vec {
+      let regex =
+        Regex::new(pattern).map_err(|e| Error::Message(e.to_string()))?;
+      if regex.is_match(ansible_log) {
+        return Ok(Some(pattern));
+      }
+    }
+
+    Ok(None)
+  }
+}
+
+/// What building the images of a SAT file needs besides the images
+/// themselves: where CSM, Vault and Kubernetes are, and how to build.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageBuildContext<'a> {
+  /// Shasta API authentication token.
+  pub shasta_token: &'a str,
+  /// Shasta API base URL.
+  pub shasta_base_url: &'a str,
+  /// Root CA certificate for validating Shasta TLS connections.
+  pub shasta_root_cert: &'a [u8],
+  /// Optional SOCKS5 proxy URL for routing Shasta API requests.
+  pub socks5_proxy: Option<&'a str>,
+  /// Vault base URL, to read the Kubernetes credentials with.
+  pub vault_base_url: &'a str,
+  /// Site whose Kubernetes credentials to read from Vault.
+  pub site_name: &'a str,
+  /// Kubernetes API URL. Kubernetes is only used to stream the CFS
+  /// session logs with `watch_logs`, and to read the Ansible log of a
+  /// failed session when `cfs_session_retry_policy` allows a retry.
+  pub k8s_api_url: &'a str,
+  /// `cray-product-catalog`, to resolve `base.product`.
+  pub cray_product_catalog: &'a ProductCatalog,
+  /// Ansible verbosity of the CFS sessions.
+  pub ansible_verbosity: Option<u8>,
+  /// Extra Ansible arguments of the CFS sessions.
+  pub ansible_passthrough: Option<&'a str>,
+  /// Keep what a failed build leaves behind for debugging. Not acted on
+  /// yet.
+  pub debug_on_failure: bool,
+  /// Create nothing; build placeholder images.
+  pub dry_run: bool,
+  /// Stream the CFS session logs through `tracing`.
+  pub watch_logs: bool,
+  /// Prefix streamed log lines with their timestamp.
+  pub timestamps: bool,
+  /// When to re-submit a CFS session that failed.
+  pub cfs_session_retry_policy: &'a CfsSessionRetryPolicy,
+  /// How long, and how often, to wait for each CFS session.
+  pub cfs_session_wait: WaitOptions,
+  /// How long, and how often, to wait for each IMS job building a base
+  /// image from a recipe.
+  pub ims_job_wait: WaitOptions,
+  /// IMS public key passed to the IMS jobs.
+  pub ims_public_key_selector: &'a PublicKeySelector,
+}
+
+/// How one CFS session building an image ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum BuildAttemptOutcome {
+  /// The session succeeded.
+  Succeeded,
+  /// The session failed with an Ansible log matching a transient
+  /// pattern, and was re-submitted.
+  Retried {
+    /// Pattern the log matched.
+    pattern: String,
+  },
+}
+
+/// One CFS session submitted to build an image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildAttempt {
+  /// CFS session name.
+  pub cfs_session_name: String,
+  /// How the session ended.
+  #[serde(flatten)]
+  pub outcome: BuildAttemptOutcome,
+}
+
+/// Every CFS session submitted to build one SAT-file image.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImageBuildReport {
+  /// SAT-file image name.
+  pub image_name: String,
+  /// Sessions in submission order; the last one succeeded.
+  pub attempt_vec: Vec<BuildAttempt>,
+}
+
+impl ImageBuildReport {
+  /// Number of sessions re-submitted after a transient failure.
+  #[must_use]
+  pub fn retries(&self) -> usize {
+    self
+      .attempt_vec
+      .iter()
+      .filter(|attempt| {
+        matches!(attempt.outcome, BuildAttemptOutcome::Retried { .. })
+      })
+      .count()
+  }
+}
+
 /// Build every entry in the SAT file's `images` section: import the
-/// base recipe / image and run the associated CFS session. When
-/// `watch_logs` is true the CFS session's container logs are streamed
-/// line-by-line through `log::debug!`.
+/// base recipe / image and run the associated CFS session. Entries
+/// with `archs` are built once per architecture (see
+/// [`expand_multi_arch_images`]). When
+/// `context.watch_logs` is true the CFS session's container logs are
+/// streamed line-by-line through `tracing::debug!`.
+///
+/// Returns the produced `Image`s and, in the same order, the
+/// [`ImageBuildReport`] of the CFS sessions each one took.
 ///
-/// Returns only the produced `Image`s. The
-/// [`i_create_image_from_sat_file_serde_yaml`] per-image helper now
-/// returns a `(Image, CfsSessionGetResponse)` tuple; this bulk path
-/// destructures and discards the session because the whole-SAT-file
-/// flow does not emit per-image provenance metadata. The single-image
-/// flow ([`crate::backend_connector::sat::Csm`]'s `apply_image`) is
-/// where metadata stamping is wired up.
-#[allow(clippy::too_many_arguments)]
+/// Images whose name is already taken in IMS are handled according to
+/// `image_name_conflict_policy`. Images skipped under
+/// [`ImageNameConflictPolicy::Skip`] are not part of the returned lists.
+/// Under [`ImageNameConflictPolicy::Overwrite`], an image booted by a
+/// protected BOS session template (see [`crate::protection`]) is only
+/// deleted if `ignore_protection` is set.
+/// Image-build CFS sessions failing on a transient Ansible error are
+/// re-submitted according to `context.cfs_session_retry_policy`.
 pub async fn i_import_images_section_in_sat_file(
-  shasta_token: &str,
-  shasta_base_url: &str,
-  shasta_root_cert: &[u8],
-  socks5_proxy: Option<&str>,
-  vault_base_url: &str,
-  site_name: &str,
-  k8s_api_url: &str,
+  context: &ImageBuildContext<'_>,
   ref_name_processed_hashmap: &mut HashMap<String, String>,
   // image_yaml_vec: &[serde_yaml::Value],
   image_yaml_vec: &[image::Image],
-  cray_product_catalog: &BTreeMap<String, String>,
-  ansible_verbosity_opt: Option<u8>,
-  ansible_passthrough_opt: Option<&str>,
-  debug_on_failure: bool, // tag: &str,
-  dry_run: bool,
-  watch_logs: bool,
-  timestamps: bool,
-) -> Result<Vec<ims::image::http_client::types::Image>, Error> {
+  image_name_conflict_policy: ImageNameConflictPolicy,
+  ignore_protection: bool,
+) -> Result<
+  (
+    Vec<ims::image::http_client::types::Image>,
+    Vec<ImageBuildReport>,
+  ),
+  Error,
+> {
   if image_yaml_vec.is_empty() {
-    log::warn!("No images found in SAT file. Nothing to process.");
-    return Ok(Vec::new());
+    events::warning("No images found in SAT file. Nothing to process.");
+    return Ok((Vec::new(), Vec::new()));
   }
 
+  let image_yaml_vec = &expand_multi_arch_images(image_yaml_vec);
+
+  let ImageBuildContext {
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    dry_run,
+    ..
+  } = *context;
+
+  let client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+
+  // Images already in IMS, to detect name collisions. Not needed when
+  // duplicates are allowed
+  let mut existing_image_vec =
+    if image_name_conflict_policy == ImageNameConflictPolicy::Duplicate {
+      Vec::new()
+    } else {
+      client.ims_image_get_all(shasta_token).await?
+    };
+
+  // Images booted by protected BOS session templates, which superseded
+  // images must not be
+  let protected_image_id_vec: Vec<String> = if image_name_conflict_policy
+    == ImageNameConflictPolicy::Overwrite
+    && !ignore_protection
+  {
+    let bos_sessiontemplate_vec =
+      client.bos_template_v2_get_all(shasta_token).await?;
+    bos_sessiontemplate_vec
+      .iter()
+      .filter(|bos_sessiontemplate| {
+        protection::is_protected(bos_sessiontemplate.description.as_deref())
+      })
+      .flat_map(|bos_sessiontemplate| bos_sessiontemplate.images_id())
+      .map(str::to_string)
+      .collect()
+  } else {
+    Vec::new()
+  };
+
   // Get an image to process (the image either has no dependency or it's image dependency has
   // already ben processed)
   let mut next_image_to_process_opt: Option<image::Image> =
@@ -156,39 +519,114 @@ pub async fn i_import_images_section_in_sat_file(
     );
 
   // Process images
-  log::debug!("Processing image '{next_image_to_process_opt:?}'");
+  tracing::debug!("Processing image '{next_image_to_process_opt:?}'");
   let mut images_created: Vec<ims::image::http_client::types::Image> =
     Vec::new();
+  let mut image_build_report_vec: Vec<ImageBuildReport> = Vec::new();
 
   while let Some(image_yaml) = &next_image_to_process_opt {
-    let image = i_create_image_from_sat_file_serde_yaml(
-      shasta_token,
-      shasta_base_url,
-      shasta_root_cert,
-      socks5_proxy,
-      vault_base_url,
-      site_name,
-      k8s_api_url,
-      image_yaml,
-      cray_product_catalog,
-      ansible_verbosity_opt,
-      ansible_passthrough_opt,
+    let (image_yaml_to_build, superseded_image_id_vec) =
+      match resolve_image_name_conflict(
+        image_name_conflict_policy,
+        image_yaml,
+        &existing_image_vec,
+      ) {
+        ImageNameResolution::Build(image_yaml_to_build, superseded) => {
+          // Refuse before building, so a protected image isn't left
+          // with a duplicate
+          if let Some(protected_image_id) = superseded
+            .iter()
+            .find(|image_id| protected_image_id_vec.contains(*image_id))
+          {
+            return Err(Error::Protected(format!(
+              "IMS image '{protected_image_id}'"
+            )));
+          }
+          (image_yaml_to_build, superseded)
+        }
+        ImageNameResolution::Reuse(existing_image) => {
+          events::info(format!(
+            "Image '{}' already exists ({}), skip building it",
+            image_yaml.name,
+            existing_image.id.as_deref().unwrap_or("<no id>")
+          ));
+
+          ref_name_processed_hashmap.insert(
+            get_image_name_or_ref_name_to_process_struct(image_yaml),
+            existing_image.id.unwrap_or_default(),
+          );
+
+          next_image_to_process_opt =
+            get_next_image_in_sat_file_to_process_struct(
+              image_yaml_vec,
+              &ref_name_processed_hashmap
+                .keys()
+                .cloned()
+                .collect::<Vec<String>>(),
+            );
+          continue;
+        }
+      };
+
+    if image_yaml_to_build.name != image_yaml.name {
+      events::info(format!(
+        "Image '{}' already exists, build it as '{}'",
+        image_yaml.name, image_yaml_to_build.name
+      ));
+    }
+
+    let (image, image_build_report) = i_create_image_from_sat_file_serde_yaml(
+      context,
+      &image_yaml_to_build,
       ref_name_processed_hashmap,
-      debug_on_failure,
-      dry_run,
-      watch_logs,
-      timestamps,
     )
     .await?;
 
+    if image_build_report.retries() > 0 {
+      events::info(format!(
+        "Image '{}' built after {} CFS session(s)",
+        image_build_report.image_name,
+        image_build_report.attempt_vec.len()
+      ));
+    }
+
+    // Only drop the superseded images once the replacement is built
+    for superseded_image_id in &superseded_image_id_vec {
+      if dry_run {
+        events::info(format!(
+          "Dry run mode: Delete superseded image '{superseded_image_id}'"
+        ));
+        continue;
+      }
+
+      tracing::debug!(
+        "Delete image '{superseded_image_id}' superseded by '{}'",
+        image.id.as_deref().unwrap_or_default()
+      );
+      client
+        .ims_image_delete(shasta_token, superseded_image_id)
+        .await?;
+      events::deleted("IMS image", superseded_image_id);
+    }
+    existing_image_vec.retain(|existing_image| {
+      existing_image
+        .id
+        .as_ref()
+        .is_none_or(|id| !superseded_image_id_vec.contains(id))
+    });
+
     let image_id = image.id.clone().unwrap_or_default();
 
+    // Keyed on the SAT-file name so `base.image_ref` still matches a
+    // renamed image
     ref_name_processed_hashmap.insert(
       get_image_name_or_ref_name_to_process_struct(image_yaml),
       image_id,
     );
 
+    existing_image_vec.push(image.clone());
     images_created.push(image);
+    image_build_report_vec.push(image_build_report);
 
     next_image_to_process_opt = get_next_image_in_sat_file_to_process_struct(
       image_yaml_vec,
@@ -199,7 +637,7 @@ pub async fn i_import_images_section_in_sat_file(
     );
   }
 
-  Ok(images_created)
+  Ok((images_created, image_build_report_vec))
 }
 
 /// Provenance metadata key namespace stamped onto each IMS image
@@ -214,8 +652,8 @@ const META_CONFIG: &str = "manta.image_session.configuration";
 
 /// Build one image entry from a SAT file YAML node: resolve the base
 /// (recipe or existing image), create the IMS image, kick off a CFS
-/// session, stream its container logs through `log::debug!` if
-/// `watch_logs` is on, then call [`stamp_image_session_metadata`] to
+/// session, stream its container logs through `tracing::debug!` if
+/// `context.watch_logs` is on, then call [`stamp_image_session_metadata`] to
 /// fill in `manta.image_session.*` and PATCH the image back so the
 /// metadata survives the request.
 ///
@@ -225,59 +663,99 @@ const META_CONFIG: &str = "manta.image_session.configuration";
 /// itself was built successfully and a missing
 /// `manta.image_session.*` annotation can be backfilled.
 ///
-/// In `dry_run` mode no CFS session is created and no PATCH is
+/// A CFS session failing with an Ansible log matching
+/// `context.cfs_session_retry_policy` is deleted and submitted again;
+/// every session submitted is recorded in the returned
+/// [`ImageBuildReport`].
+///
+/// In `context.dry_run` mode no CFS session is created and no PATCH is
 /// attempted; the function returns a fake `Image` with a synthetic
 /// `DRYRUN_<uuid>` id.
-#[allow(clippy::too_many_arguments)]
 pub async fn i_create_image_from_sat_file_serde_yaml(
-  shasta_token: &str,
-  shasta_base_url: &str,
-  shasta_root_cert: &[u8],
-  socks5_proxy: Option<&str>,
-  vault_base_url: &str,
-  site_name: &str,
-  k8s_api_url: &str,
+  context: &ImageBuildContext<'_>,
   // image_yaml: &serde_yaml::Value, // NOTE: image may be an IMS job or a CFS session
   image_yaml: &image::Image,
-  cray_product_catalog: &BTreeMap<String, String>,
-  ansible_verbosity_opt: Option<u8>,
-  ansible_passthrough_opt: Option<&str>,
   ref_name_image_id_hashmap: &HashMap<String, String>,
-  _debug_on_failure: bool,
-  dry_run: bool,
-  watch_logs: bool,
-  timestamps: bool,
-) -> Result<ims::image::http_client::types::Image, Error> {
-  let cfs_session = create_cfs_session_for_sat_image(
+) -> Result<(ims::image::http_client::types::Image, ImageBuildReport), Error> {
+  let ImageBuildContext {
     shasta_token,
     shasta_base_url,
     shasta_root_cert,
     socks5_proxy,
-    image_yaml,
-    cray_product_catalog,
-    ansible_verbosity_opt,
-    ansible_passthrough_opt,
-    ref_name_image_id_hashmap,
     dry_run,
-  )
-  .await?;
+    cfs_session_retry_policy,
+    ..
+  } = *context;
 
-  let cfs_session = wait_or_stream_cfs_session(
-    shasta_token,
-    shasta_base_url,
-    shasta_root_cert,
-    socks5_proxy,
-    vault_base_url,
-    site_name,
-    k8s_api_url,
-    cfs_session,
-    watch_logs,
-    timestamps,
-    dry_run,
-  )
-  .await?;
+  let cfs_session_request =
+    get_session_from_image_yaml(context, image_yaml, ref_name_image_id_hashmap)
+      .await?;
+
+  let mut image_build_report = ImageBuildReport {
+    image_name: image_yaml.name.clone(),
+    attempt_vec: Vec::new(),
+  };
+
+  let cfs_session = loop {
+    let cfs_session = post_cfs_session_for_sat_image(
+      shasta_token,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      cfs_session_request.clone(),
+      dry_run,
+    )
+    .await?;
+
+    let cfs_session = wait_or_stream_cfs_session(context, cfs_session).await?;
+
+    if cfs_session.is_success() {
+      image_build_report.attempt_vec.push(BuildAttempt {
+        cfs_session_name: cfs_session.name.clone(),
+        outcome: BuildAttemptOutcome::Succeeded,
+      });
+      break cfs_session;
+    }
+
+    let attempt = u32::try_from(image_build_report.attempt_vec.len() + 1)
+      .unwrap_or(u32::MAX);
+    let transient_pattern_opt =
+      if attempt < cfs_session_retry_policy.max_attempts {
+        get_transient_failure_pattern(context, &cfs_session.name).await?
+      } else {
+        None
+      };
+
+    let Some(pattern) = transient_pattern_opt else {
+      return Err(Error::SatFile(format!(
+        "CFS session '{}' failed after {attempt} attempt(s). Exit",
+        cfs_session.name
+      )));
+    };
+
+    events::emit(events::Event::Retry {
+      operation: format!("CFS session '{}'", cfs_session.name),
+      attempt,
+      max_attempts: cfs_session_retry_policy.max_attempts,
+      reason: format!("Ansible log matches transient pattern '{pattern}'"),
+    });
+    image_build_report.attempt_vec.push(BuildAttempt {
+      cfs_session_name: cfs_session.name.clone(),
+      outcome: BuildAttemptOutcome::Retried { pattern },
+    });
+
+    // Free the session name for the next attempt
+    crate::ShastaClient::scoped_or_new(
+      shasta_base_url,
+      shasta_root_cert.to_vec(),
+      socks5_proxy.map(str::to_owned),
+    )?
+    .cfs_session_v3_delete(shasta_token, &cfs_session.name)
+    .await?;
+    events::deleted("CFS session", &cfs_session.name);
+  };
 
-  collect_and_stamp_image(
+  let mut image = collect_and_stamp_image(
     shasta_token,
     shasta_base_url,
     shasta_root_cert,
@@ -286,11 +764,96 @@ pub async fn i_create_image_from_sat_file_serde_yaml(
     &image_yaml.name,
     dry_run,
   )
+  .await?;
+
+  // Label the image with the SAT file entry and the configuration that
+  // built it, and record the SAT apply run it comes from. Best effort,
+  // like the provenance stamp.
+  let provenance_json_opt =
+    provenance::current().map(|provenance| provenance.to_json());
+  let mut label_vec = vec![
+    (
+      labels::SAT_REF_NAME,
+      image_yaml.ref_name.as_deref().unwrap_or(&image_yaml.name),
+    ),
+    (labels::BUILT_BY, labels::BUILT_BY_CSM_RS),
+  ];
+  if let Some(configuration) = cfs_session.configuration_name() {
+    label_vec.push((labels::CONFIGURATION, configuration));
+  }
+  if let Some(provenance_json) = &provenance_json_opt {
+    label_vec.push((provenance::METADATA_KEY, provenance_json.as_str()));
+  }
+
+  if dry_run {
+    image = image.with_labels(label_vec);
+  } else if let Some(image_id) = image.id.clone() {
+    match crate::ShastaClient::scoped_or_new(
+      shasta_base_url,
+      shasta_root_cert.to_vec(),
+      socks5_proxy.map(str::to_owned),
+    )?
+    .ims_image_add_labels(shasta_token, &image_id, &label_vec)
+    .await
+    {
+      Ok(labelled_image) => image = labelled_image,
+      Err(e) => {
+        events::warning(format!("Could not label image {image_id}: {e}"));
+      }
+    }
+  }
+
+  Ok((image, image_build_report))
+}
+
+/// Pattern of `context.cfs_session_retry_policy` matching the Ansible
+/// log of failed CFS session `cfs_session_name`, `None` if there is
+/// none or the log can't be read.
+async fn get_transient_failure_pattern(
+  context: &ImageBuildContext<'_>,
+  cfs_session_name: &str,
+) -> Result<Option<String>, Error> {
+  let shasta_k8s_secrets = fetch_shasta_k8s_secrets_from_vault(
+    context.vault_base_url,
+    context.shasta_token,
+    context.site_name,
+    context.socks5_proxy,
+  )
+  .await?;
+
+  let client = kubernetes::get_client(
+    context.k8s_api_url,
+    shasta_k8s_secrets,
+    context.socks5_proxy,
+  )
+  .await?;
+
+  let ansible_log = match kubernetes::get_cfs_session_container_ansible_log(
+    client,
+    cfs_session_name,
+    PodWaitTimeouts::default().capped_at(context.cfs_session_wait.timeout),
+  )
   .await
+  {
+    Ok(ansible_log) => ansible_log,
+    Err(e) => {
+      events::warning(format!(
+        "Could not read the Ansible log of CFS session '{cfs_session_name}', not retrying it: {e}"
+      ));
+      return Ok(None);
+    }
+  };
+
+  Ok(
+    context
+      .cfs_session_retry_policy
+      .transient_match(&ansible_log)?
+      .map(str::to_string),
+  )
 }
 
 /// Part 1: build the CFS session request from the SAT-file image YAML
-/// and create it. In `dry_run` mode returns a synthetic
+/// and create it. In `context.dry_run` mode returns a synthetic
 /// `CfsSessionGetResponse` (`DRYRUN-<uuid>` result id) with no network
 /// call. Otherwise POSTs to CFS and returns the just-created session —
 /// the response carries only the session name and initial status, so
@@ -302,35 +865,38 @@ pub async fn i_create_image_from_sat_file_serde_yaml(
 /// itself (e.g. the manta-cli SAT-image build pipeline) instead of
 /// going through [`i_create_image_from_sat_file_serde_yaml`]'s
 /// monolithic flow.
-#[allow(clippy::too_many_arguments)]
 pub async fn create_cfs_session_for_sat_image(
+  context: &ImageBuildContext<'_>,
+  image_yaml: &image::Image,
+  ref_name_image_id_hashmap: &HashMap<String, String>,
+) -> Result<CfsSessionGetResponse, Error> {
+  let cfs_session =
+    get_session_from_image_yaml(context, image_yaml, ref_name_image_id_hashmap)
+      .await?;
+
+  post_cfs_session_for_sat_image(
+    context.shasta_token,
+    context.shasta_base_url,
+    context.shasta_root_cert,
+    context.socks5_proxy,
+    cfs_session,
+    context.dry_run,
+  )
+  .await
+}
+
+/// Create the CFS session built by [`create_cfs_session_for_sat_image`].
+/// In `dry_run` mode returns a synthetic, already complete session.
+async fn post_cfs_session_for_sat_image(
   shasta_token: &str,
   shasta_base_url: &str,
   shasta_root_cert: &[u8],
   socks5_proxy: Option<&str>,
-  image_yaml: &image::Image,
-  cray_product_catalog: &BTreeMap<String, String>,
-  ansible_verbosity_opt: Option<u8>,
-  ansible_passthrough_opt: Option<&str>,
-  ref_name_image_id_hashmap: &HashMap<String, String>,
+  cfs_session: CfsSessionPostRequest,
   dry_run: bool,
 ) -> Result<CfsSessionGetResponse, Error> {
-  let cfs_session = get_session_from_image_yaml(
-    shasta_token,
-    shasta_base_url,
-    shasta_root_cert,
-    socks5_proxy,
-    image_yaml,
-    ref_name_image_id_hashmap,
-    cray_product_catalog,
-    ansible_verbosity_opt,
-    ansible_passthrough_opt,
-    dry_run,
-  )
-  .await?;
-
   if dry_run {
-    log::debug!(
+    tracing::debug!(
       "Dry run mode: Create CFS session:\n{}",
       serde_json::to_string_pretty(&cfs_session)?
     );
@@ -394,7 +960,7 @@ pub async fn create_cfs_session_for_sat_image(
       }),
     };
 
-    log::debug!(
+    tracing::debug!(
       "Dry run mode: CFS session created:\n{}",
       serde_json::to_string_pretty(&mock_cfs_session)?
     );
@@ -416,29 +982,34 @@ pub async fn create_cfs_session_for_sat_image(
 }
 
 /// Part 2: drive a just-POSTed CFS session to completion. When
-/// `watch_logs` is true the session's container logs are streamed
-/// line-by-line through `log::info!`; either way the function blocks
-/// until the session finishes and returns the final
-/// `CfsSessionGetResponse`. Errors with `Error::SatFile` if the
-/// session ends without `is_success()`.
+/// `context.watch_logs` is true the session's container logs are
+/// streamed line-by-line through `tracing::info!`; either way the
+/// function blocks until the session finishes, or
+/// `context.cfs_session_wait` times out, and returns the final
+/// `CfsSessionGetResponse`, whether it succeeded or not.
 ///
-/// In `dry_run` mode no waiting happens — the input session is
+/// In `context.dry_run` mode no waiting happens — the input session is
 /// returned unchanged (it was already mocked as "complete" by
 /// [`create_cfs_session_for_sat_image`]).
-#[allow(clippy::too_many_arguments)]
 async fn wait_or_stream_cfs_session(
-  shasta_token: &str,
-  shasta_base_url: &str,
-  shasta_root_cert: &[u8],
-  socks5_proxy: Option<&str>,
-  vault_base_url: &str,
-  site_name: &str,
-  k8s_api_url: &str,
+  context: &ImageBuildContext<'_>,
   cfs_session: CfsSessionGetResponse,
-  watch_logs: bool,
-  timestamps: bool,
-  dry_run: bool,
 ) -> Result<CfsSessionGetResponse, Error> {
+  let ImageBuildContext {
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    vault_base_url,
+    site_name,
+    k8s_api_url,
+    watch_logs,
+    timestamps,
+    cfs_session_wait,
+    dry_run,
+    ..
+  } = *context;
+
   if dry_run {
     return Ok(cfs_session);
   }
@@ -446,7 +1017,7 @@ async fn wait_or_stream_cfs_session(
   let cfs_session_name = cfs_session.name.clone();
 
   if watch_logs {
-    log::info!("Fetching logs form CFS session {cfs_session_name} ...");
+    events::step(format!("Fetching logs from CFS session {cfs_session_name}"));
     let shasta_k8s_secrets = fetch_shasta_k8s_secrets_from_vault(
       vault_base_url,
       shasta_token,
@@ -459,7 +1030,13 @@ async fn wait_or_stream_cfs_session(
       kubernetes::get_client(k8s_api_url, shasta_k8s_secrets, socks5_proxy)
         .await?;
 
-    i_print_cfs_session_logs(client, &cfs_session_name, timestamps).await?;
+    i_print_cfs_session_logs(
+      client,
+      &cfs_session_name,
+      timestamps,
+      PodWaitTimeouts::default().capped_at(cfs_session_wait.timeout),
+    )
+    .await?;
   }
 
   cfs::session::utils::wait_cfs_session_to_finish(
@@ -468,26 +1045,18 @@ async fn wait_or_stream_cfs_session(
     shasta_root_cert,
     socks5_proxy,
     &cfs_session_name,
+    cfs_session_wait,
   )
   .await?;
 
-  let cfs_session = cfs::session::get_one(
+  cfs::session::get_one(
     shasta_token,
     shasta_base_url,
     shasta_root_cert,
     socks5_proxy,
     &cfs_session_name,
   )
-  .await?;
-
-  if !cfs_session.is_success() {
-    return Err(Error::SatFile(format!(
-      "CFS session '{}' failed. Exit",
-      cfs_session.name
-    )));
-  }
-
-  Ok(cfs_session)
+  .await
 }
 
 /// Part 3: fetch the IMS image produced by the (already-complete) CFS
@@ -529,7 +1098,7 @@ pub async fn collect_and_stamp_image(
     };
     stamp_image_session_metadata(&mut image, cfs_session);
 
-    log::debug!(
+    tracing::debug!(
       "Dry run mode: Image created:\n{}",
       serde_json::to_string_pretty(&image)?
     );
@@ -537,9 +1106,9 @@ pub async fn collect_and_stamp_image(
     return Ok(image);
   }
 
-  log::debug!("Image '{image_name}' ({image_id}) created");
+  tracing::debug!("Image '{image_name}' ({image_id}) created");
 
-  let client = crate::ShastaClient::new(
+  let client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -562,11 +1131,11 @@ pub async fn collect_and_stamp_image(
       .ims_image_patch(shasta_token, &image_id_for_patch, &patch)
       .await
     {
-      log::warn!(
+      events::warning(format!(
         "image_session metadata PATCH failed for image \
          {image_id_for_patch}: {e}; image built but provenance not \
          persisted",
-      );
+      ));
     }
   }
 
@@ -581,7 +1150,7 @@ pub async fn collect_and_stamp_image(
 ///
 /// Returns `true` when all three keys were written, `false` when any
 /// required field on the session was missing or unserialisable (a
-/// `warn` line names the missing field). The `false` path is the
+/// warning event names the missing field). The `false` path is the
 /// signal to the caller that there is nothing new to PATCH.
 fn stamp_image_session_metadata(
   image: &mut ims::image::http_client::types::Image,
@@ -602,18 +1171,18 @@ fn stamp_image_session_metadata(
     .and_then(|g| g.members.first())
     .cloned()
   else {
-    log::warn!(
+    events::warning(format!(
       "CFS session for image {image_id_for_log} has no \
        target.groups[0].members[0]; skipping image_session metadata stamp",
-    );
+    ));
     return false;
   };
 
   let Some(configuration) = cfs_session.configuration_name() else {
-    log::warn!(
+    events::warning(format!(
       "CFS session for image {image_id_for_log} has no \
        configuration.name; skipping image_session metadata stamp",
-    );
+    ));
     return false;
   };
   let configuration = configuration.to_string();
@@ -622,10 +1191,10 @@ fn stamp_image_session_metadata(
   let groups_json = match serde_json::to_string(&groups) {
     Ok(s) => s,
     Err(e) => {
-      log::warn!(
+      events::warning(format!(
         "could not JSON-encode HSM groups {groups:?} for image \
          {image_id_for_log}: {e}; skipping image_session metadata stamp",
-      );
+      ));
       return false;
     }
   };
@@ -637,27 +1206,17 @@ fn stamp_image_session_metadata(
   true
 }
 
-#[allow(clippy::too_many_arguments)]
 async fn get_session_from_image_yaml(
-  shasta_token: &str,
-  shasta_base_url: &str,
-  shasta_root_cert: &[u8],
-  socks5_proxy: Option<&str>,
+  context: &ImageBuildContext<'_>,
   // image_yaml: Value,
   image_yaml: &image::Image,
   ref_name_image_id_hashmap: &HashMap<String, String>,
-  cray_product_catalog: &BTreeMap<String, String>,
-  ansible_verbosity_opt: Option<u8>,
-  ansible_passthrough_opt: Option<&str>,
-  dry_run: bool,
 ) -> Result<CfsSessionPostRequest, Error> {
   // Collect CFS session details from SAT file
   // Get CFS image name from SAT file
   let image_name = image_yaml.name.clone();
 
-  log::debug!(
-    "Creating CFS session related to build image '{image_name}'"
-  );
+  tracing::debug!("Creating CFS session related to build image '{image_name}'");
 
   // Get CFS configuration related to CFS session in SAT file
   let configuration_name =
@@ -678,42 +1237,42 @@ async fn get_session_from_image_yaml(
   // VALIDATION: make sure grups in SAT.images "CFS session" are valid
   // NOTE: this is temporary until we get rid off "group" names as ansible folder names
   let invalid_groups: Vec<String> =
-    hsm::group::hacks::validate_groups_auth_token(&groups_name, shasta_token)?;
+    hsm::group::hacks::validate_groups_auth_token(
+      &groups_name,
+      context.shasta_token,
+    )?;
 
   if !invalid_groups.is_empty() {
-    log::debug!("CFS session group validation - failed");
+    tracing::debug!("CFS session group validation - failed");
 
     return Err(Error::SatFile(format!(
       "Please fix 'images' section in SAT file.\nInvalid groups: {invalid_groups:?}"
     )));
   }
-  log::debug!("CFS session group validation - passed");
+  tracing::debug!("CFS session group validation - passed");
 
   let base_image_id = get_base_image_id_from_sat_file_image_yaml(
-    shasta_token,
-    shasta_base_url,
-    shasta_root_cert,
-    socks5_proxy,
+    context,
     image_yaml,
     ref_name_image_id_hashmap,
-    cray_product_catalog,
-    &image_name,
-    dry_run,
   )
   .await?;
 
   // Create a CFS session
-  log::debug!("Creating CFS session");
+  tracing::debug!("Creating CFS session");
 
   // Create CFS session
   let session_name = image_name.clone();
 
+  let ansible_limit_opt =
+    session_profile::current().and_then(|profile| profile.ansible_limit);
+
   let cfs_session = CfsSessionPostRequest::new(
     session_name,
     configuration_name,
-    None,
-    ansible_verbosity_opt,
-    ansible_passthrough_opt,
+    ansible_limit_opt.as_deref(),
+    context.ansible_verbosity,
+    context.ansible_passthrough,
     true,
     Some(&groups_name),
     Some(&base_image_id),
@@ -722,26 +1281,54 @@ async fn get_session_from_image_yaml(
   Ok(cfs_session)
 }
 
+/// Build the base image of SAT image `context.image_name` with an IMS
+/// job from IMS recipe `recipe_id`, for `context.arch_opt` if set.
+/// Returns the id of the image built.
 pub(super) async fn process_sat_file_image_product_type_ims_recipe(
-  shasta_token: &str,
-  shasta_base_url: &str,
-  shasta_root_cert: &[u8],
-  socks5_proxy: Option<&str>,
+  context: &ImageBaseContext<'_>,
   recipe_id: &str,
-  image_name: &str,
-  dry_run: bool,
 ) -> Result<String, Error> {
-  let root_ims_key_name = "mgmt root key";
+  let ImageBaseContext {
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    image_name,
+    template_dictionary_opt,
+    arch_opt,
+    dry_run,
+    ims_job_wait,
+    ims_public_key_selector,
+    ..
+  } = *context;
 
-  // Get root public ssh key
-  let root_public_ssh_key = crate::ShastaClient::new(
+  let client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
-  )?
-  .ims_public_keys_v3_get_single(shasta_token, root_ims_key_name)
-  .await?
-  .ok_or_else(|| Error::ImsKeyNotFound(root_ims_key_name.to_string()))?;
+  )?;
+
+  // Only fetch the recipe if its template variables need validating
+  let template_dictionary = match template_dictionary_opt {
+    Some(template_dictionary) => {
+      let recipe = client
+        .ims_recipe_get(shasta_token, Some(recipe_id))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+          Error::SatFile(format!("IMS recipe '{recipe_id}' - not found"))
+        })?;
+
+      recipe_template_dictionary(&recipe, Some(template_dictionary))?
+    }
+    None => None,
+  };
+
+  // Get the public ssh key to access the IMS job with
+  let root_public_ssh_key = client
+    .ims_public_keys_v3_select(shasta_token, ims_public_key_selector)
+    .await?;
 
   let root_public_ssh_key_id = root_public_ssh_key.id.ok_or_else(|| {
     Error::Message(
@@ -761,7 +1348,8 @@ pub(super) async fn process_sat_file_image_product_type_ims_recipe(
     ssh_containers: None, // Should this be None ???
     enable_debug: Some(false),
     build_env_size: Some(15),
-    require_dkms: None, // FIXME: check SAT file and see if this value needs to be set
+    require_dkms: session_profile::current()
+      .and_then(|profile| profile.require_dkms),
     id: None,
     created: None,
     status: None,
@@ -770,47 +1358,55 @@ pub(super) async fn process_sat_file_image_product_type_ims_recipe(
     kubernetes_configmap: None,
     resultant_image_id: None,
     kubernetes_namespace: None,
-    arch: None,
+    arch: arch_opt.map(|arch| arch.ims_arch().to_string()),
+    template_dictionary,
   };
 
-  let ims_job = if dry_run {
-    log::debug!(
+  if dry_run {
+    tracing::debug!(
       "Dry run mode: Create IMS job:\n{}",
       serde_json::to_string_pretty(&ims_job)?
     );
-    let mut dry_run_ims_job = ims_job;
-    dry_run_ims_job.resultant_image_id = Some(Uuid::new_v4().to_string());
-    dry_run_ims_job
-  } else {
-    crate::ShastaClient::new(
-      shasta_base_url,
-      shasta_root_cert.to_vec(),
-      socks5_proxy.map(str::to_owned),
-    )?
-    .ims_job_post_sync(shasta_token, &ims_job)
-    .await?
-  };
+    return Ok(Uuid::new_v4().to_string());
+  }
 
-  ims_job.resultant_image_id.ok_or_else(|| {
-    Error::SatFile(format!(
-      "IMS job for image '{image_name}' did not produce a resultant_image_id"
-    ))
-  })
+  build_image_with_ims_job(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    &ims_job,
+    image_name,
+    ims_job_wait,
+  )
+  .await
 }
 
+/// Build the base image of SAT image `context.image_name` with an IMS
+/// job from the IMS recipe named `recipe_name`, for `context.arch_opt`
+/// if set. Returns the id of the image built.
 pub(super) async fn process_sat_file_image_ims_type_recipe(
-  shasta_token: &str,
-  shasta_base_url: &str,
-  shasta_root_cert: &[u8],
-  socks5_proxy: Option<&str>,
+  context: &ImageBaseContext<'_>,
   recipe_name: &str,
-  image_name: &str,
-  dry_run: bool,
 ) -> Result<String, Error> {
+  let ImageBaseContext {
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    image_name,
+    template_dictionary_opt,
+    arch_opt,
+    dry_run,
+    ims_job_wait,
+    ims_public_key_selector,
+    ..
+  } = *context;
+
   // Base image needs to be created from a IMS job using an IMS recipe
   // Get all IMS recipes
   let recipe_detail_vec: Vec<ims::recipe::types::RecipeGetResponse> =
-    crate::ShastaClient::new(
+    crate::ShastaClient::scoped_or_new(
       shasta_base_url,
       shasta_root_cert.to_vec(),
       socks5_proxy.map(str::to_owned),
@@ -823,7 +1419,7 @@ pub(super) async fn process_sat_file_image_ims_type_recipe(
     .iter()
     .find(|recipe| recipe.name == recipe_name);
 
-  log::debug!("IMS recipe details:\n{recipe_detail_opt:#?}");
+  tracing::debug!("IMS recipe details:\n{recipe_detail_opt:#?}");
 
   // Check recipe with requested name exists
   let recipe_detail = recipe_detail_opt.ok_or_else(|| {
@@ -835,19 +1431,19 @@ pub(super) async fn process_sat_file_image_ims_type_recipe(
     Error::SatFile(format!("IMS recipe '{recipe_name}' has no 'id' field"))
   })?;
 
-  log::debug!("IMS recipe id found '{recipe_id}'");
+  tracing::debug!("IMS recipe id found '{recipe_id}'");
 
-  let root_ims_key_name = "mgmt root key";
+  let template_dictionary =
+    recipe_template_dictionary(recipe_detail, template_dictionary_opt)?;
 
-  // Get root public ssh key
-  let root_public_ssh_key = crate::ShastaClient::new(
+  // Get the public ssh key to access the IMS job with
+  let root_public_ssh_key = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
   )?
-  .ims_public_keys_v3_get_single(shasta_token, root_ims_key_name)
-  .await?
-  .ok_or_else(|| Error::ImsKeyNotFound(root_ims_key_name.to_string()))?;
+  .ims_public_keys_v3_select(shasta_token, ims_public_key_selector)
+  .await?;
 
   let root_public_ssh_key_id = root_public_ssh_key.id.ok_or_else(|| {
     Error::Message(
@@ -866,7 +1462,8 @@ pub(super) async fn process_sat_file_image_ims_type_recipe(
     ssh_containers: None, // Should this be None ???
     enable_debug: Some(false),
     build_env_size: Some(15),
-    require_dkms: None, // FIXME: check SAT file and see if this value needs to be set
+    require_dkms: session_profile::current()
+      .and_then(|profile| profile.require_dkms),
     id: None,
     created: None,
     status: None,
@@ -875,32 +1472,131 @@ pub(super) async fn process_sat_file_image_ims_type_recipe(
     kubernetes_configmap: None,
     resultant_image_id: None,
     kubernetes_namespace: None,
-    arch: None,
+    arch: arch_opt.map(|arch| arch.ims_arch().to_string()),
+    template_dictionary,
   };
 
-  let ims_job = if dry_run {
-    log::debug!(
+  if dry_run {
+    tracing::debug!(
       "Dry run mode: Create IMS job:\n{}",
       serde_json::to_string_pretty(&ims_job)?
     );
-    ims_job
-  } else {
-    crate::ShastaClient::new(
-      shasta_base_url,
-      shasta_root_cert.to_vec(),
-      socks5_proxy.map(str::to_owned),
-    )?
-    .ims_job_post_sync(shasta_token, &ims_job)
-    .await?
+    return Err(Error::SatFile(format!(
+      "IMS job for image '{image_name}' did not produce a resultant_image_id"
+    )));
+  }
+
+  build_image_with_ims_job(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    &ims_job,
+    image_name,
+    ims_job_wait,
+  )
+  .await
+}
+
+/// Template variable overrides for an IMS job building `recipe`, as
+/// IMS expects them.
+///
+/// # Errors
+///
+/// Returns [`Error::SatFile`] if an override is not a template variable
+/// declared by `recipe`.
+pub(crate) fn recipe_template_dictionary(
+  recipe: &ims::recipe::types::RecipeGetResponse,
+  template_dictionary_opt: Option<&BTreeMap<String, String>>,
+) -> Result<Option<Vec<ims::recipe::types::RecipeKeyValuePair>>, Error> {
+  let Some(template_dictionary) = template_dictionary_opt else {
+    return Ok(None);
   };
 
-  log::debug!("IMS job response:\n{ims_job:#?}");
+  let declared_key_vec: Vec<&str> = recipe
+    .template_dictionary
+    .iter()
+    .flatten()
+    .map(|key_value| key_value.key.as_str())
+    .collect();
 
-  ims_job.resultant_image_id.ok_or_else(|| {
-    Error::SatFile(format!(
-      "IMS job for image '{image_name}' did not produce a resultant_image_id"
-    ))
-  })
+  let unknown_key_vec: Vec<&str> = template_dictionary
+    .keys()
+    .map(String::as_str)
+    .filter(|key| !declared_key_vec.contains(key))
+    .collect();
+
+  if !unknown_key_vec.is_empty() {
+    return Err(Error::SatFile(format!(
+      "IMS recipe '{}' does not declare template variables {unknown_key_vec:?} (declared: {declared_key_vec:?})",
+      recipe.name
+    )));
+  }
+
+  Ok(Some(
+    template_dictionary
+      .iter()
+      .map(|(key, value)| ims::recipe::types::RecipeKeyValuePair {
+        key: key.clone(),
+        value: value.clone(),
+      })
+      .collect(),
+  ))
+}
+
+/// Submit `ims_job` and wait, as `ims_job_wait` says, for it to build the
+/// base image of SAT image `image_name`. Returns the id of the image
+/// built.
+async fn build_image_with_ims_job(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  ims_job: &ims::job::types::Job,
+  image_name: &str,
+  ims_job_wait: WaitOptions,
+) -> Result<String, Error> {
+  let ims_job = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?
+  .ims_job_post(shasta_token, ims_job)
+  .await?;
+
+  tracing::debug!("IMS job response:\n{ims_job:#?}");
+
+  let ims_job_id = ims_job.id.ok_or_else(|| {
+    Error::Message("IMS job creation response is missing 'id'".to_string())
+  })?;
+
+  let job_outcome = ims::job::utils::wait(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    &ims_job_id,
+    ims_job_wait,
+  )
+  .await?;
+
+  match job_outcome {
+    ims::job::utils::JobOutcome::Success {
+      image_id: Some(image_id),
+    } => Ok(image_id),
+    ims::job::utils::JobOutcome::Success { image_id: None } => {
+      Err(Error::SatFile(format!(
+        "IMS job for image '{image_name}' did not produce a resultant_image_id"
+      )))
+    }
+    ims::job::utils::JobOutcome::Error { reason } => Err(Error::SatFile(
+      format!("IMS job for image '{image_name}' failed: {reason}"),
+    )),
+    ims::job::utils::JobOutcome::TimedOut => Err(Error::SatFile(format!(
+      "IMS job '{ims_job_id}' for image '{image_name}' did not finish within {}s",
+      ims_job_wait.timeout.as_secs()
+    ))),
+  }
 }
 
 pub(super) fn process_sat_file_image_old_version_struct(
@@ -917,135 +1613,37 @@ pub(super) fn process_sat_file_image_old_version_struct(
   }
 }
 
-/// Apply a SAT-file `Filter` (prefix/wildcard rules) to a list of
-/// product catalog images and return the matching ones.
-pub fn filter_product_catalog_images(
-  filter: &Filter,
-  image_map: Map<String, serde_json::Value>,
-  image_name: &str,
-) -> Result<String, Error> {
-  if let Filter::Arch { arch } = filter {
-    // Search image in product catalog and filter by arch
-    let image_key_vec = image_map
-      .keys()
-      .collect::<Vec<_>>()
-      .into_iter()
-      .filter(|product| product.split('.').next_back().eq(&Some(arch.as_ref())))
-      .collect::<Vec<_>>();
-
-    if image_key_vec.is_empty() {
-      Err(Error::CrayProductCatalog(format!(
-        "Product catalog for image '{image_name}' not found. Exit"
-      )))
-    } else if image_key_vec.len() > 1 {
-      Err(Error::CrayProductCatalog(format!(
-        "Product catalog for image '{image_name}' multiple items found. Exit"
-      )))
-    } else {
-      let image_key: &String = image_key_vec[0];
-      image_map
-        .get(image_key)
-        .and_then(|image_value| image_value.get("id"))
-        .and_then(serde_json::Value::as_str)
-        .map(str::to_string)
-        .ok_or_else(|| {
-          Error::CrayProductCatalog(format!(
-            "Product catalog entry '{image_key}' for image '{image_name}' has no 'id' field"
-          ))
-        })
-    }
-  // } else if let Some(wildcard) = filter.get("wildcard") {
-  } else if let Filter::Wildcard { wildcard } = filter {
-    // Search image in product catalog and filter by wildcard
-    let image_key_vec = image_map
-      .keys()
-      .filter(|product| product.contains(wildcard.as_str()))
-      .collect::<Vec<_>>();
-
-    if image_key_vec.is_empty() {
-      Err(Error::CrayProductCatalog(format!(
-        "Product catalog for image '{image_name}' not found. Exit"
-      )))
-    } else if image_key_vec.len() > 1 {
-      Err(Error::CrayProductCatalog(format!(
-        "Product catalog for image '{image_name}' multiple items found. Exit"
-      )))
-    } else {
-      let image_key = image_key_vec[0];
-      image_map
-        .get(image_key)
-        .and_then(|image_value| image_value.get("id"))
-        .and_then(serde_json::Value::as_str)
-        .map(str::to_string)
-        .ok_or_else(|| {
-          Error::CrayProductCatalog(format!(
-            "Product catalog entry '{image_key}' for image '{image_name}' has no 'id' field"
-          ))
-        })
-    }
-  // } else if let Some(prefix) = filter.get("prefix") {
-  } else if let Filter::Prefix { prefix } = filter {
-    // Search image in product catalog and filter by prefix
-    let image_key_vec = image_map
-      .keys()
-      .filter(|product| product.strip_prefix(prefix.as_str()).is_some())
-      .collect::<Vec<_>>();
-
-    if image_key_vec.is_empty() {
-      Err(Error::CrayProductCatalog(format!(
-        "Product catalog for image '{image_name}' not found. Exit"
-      )))
-    } else if image_key_vec.len() > 1 {
-      Err(Error::CrayProductCatalog(format!(
-        "Product catalog for image '{image_name}' multiple items found. Exit"
-      )))
-    } else {
-      let image_key = image_key_vec[0];
-      image_map
-        .get(image_key)
-        .and_then(|image_value| image_value.get("id"))
-        .and_then(serde_json::Value::as_str)
-        .map(str::to_string)
-        .ok_or_else(|| {
-          Error::CrayProductCatalog(format!(
-            "Product catalog entry '{image_key}' for image '{image_name}' has no 'id' field"
-          ))
-        })
-    }
-  } else {
-    Err(Error::CrayProductCatalog(format!(
-      "Product catalog for image '{image_name}' not found. Exit"
-    )))
-  }
-}
-
-/// Pre-flight validation for a SAT file's `images` section: rejects
+/// Pre-flight validation for a SAT file's `images` section: reports
 /// entries that reference unknown configurations, out-of-scope HSM
 /// groups, or unavailable product catalog images.
+#[must_use]
 pub fn validate_sat_file_images_section(
   image_yaml_vec: &[image::Image],
   configuration_yaml_vec: &[configuration::Configuration],
   hsm_group_available_vec: &[String],
-  cray_product_catalog: &BTreeMap<String, String>,
+  cray_product_catalog: &ProductCatalog,
   image_vec: Vec<ims::image::http_client::types::Image>,
   configuration_vec: Vec<CfsConfigurationResponse>,
   ims_recipe_vec: Vec<ims::recipe::types::RecipeGetResponse>,
-) -> Result<(), Error> {
+) -> ValidationReport {
+  let mut report = ValidationReport::new();
+
   // Validate 'images' section in SAT file
 
   for image_yaml in image_yaml_vec {
     // Validate image
     let image_name = &image_yaml.name;
+    let path = format!("images[{image_name}]");
 
-    log::debug!("Validate 'image' '{image_name}'");
+    tracing::debug!("Validate 'image' '{image_name}'");
 
     if let image::BaseOrIms::Ims { ims } = &image_yaml.base_or_ims {
       if let image::ImageIms::IdIsRecipe { id, is_recipe: _ } = ims {
         // Validate base image
-        log::debug!("Validate 'image' '{image_name}' base image '{id}'");
+        tracing::debug!("Validate 'image' '{image_name}' base image '{id}'");
 
         // Old format
-        log::debug!(
+        tracing::debug!(
           "Searching image.ims.id (old format - backward compatibility) '{id}' in CSM",
         );
 
@@ -1056,17 +1654,17 @@ pub fn validate_sat_file_images_section(
         );
 
         if !is_image_base_id_in_csm {
-          return Err(Error::SatFile(format!(
-            "Could not find base image id '{}' in image '{}'. Exit",
-            id, image_yaml.name
-          )));
+          report.error(
+            format!("{path}.ims.id"),
+            format!("Could not find base image id '{id}' in CSM"),
+          );
         }
       }
     } else if let image::BaseOrIms::Base { base } = &image_yaml.base_or_ims {
       if let image::Base::ImageRef { image_ref } = base {
         // New format
         // Validate base image
-        log::debug!(
+        tracing::debug!(
           "Validate 'image' '{image_name}' base image '{image_ref}'"
         );
 
@@ -1079,95 +1677,81 @@ pub fn validate_sat_file_images_section(
         });
 
         if !image_found {
-          return Err(Error::SatFile(format!(
-            "Could not find image with ref name '{}' in SAT file. Cancelling image build proccess. Exit",
-            image_ref.as_str(),
-          )));
+          report.error(
+            format!("{path}.base.image_ref"),
+            format!(
+              "Could not find image with ref name '{image_ref}' in SAT file"
+            ),
+          );
         }
       // } else if let Some(image_base_product) = image_yaml["base"].get("product")
       } else if let image::Base::Product { product } = base {
         // Check if the 'Cray/HPE product' in CSM exists
 
-        log::debug!("Image '{image_name}' base.base.product");
-        log::debug!("SAT file - 'image.base.product' job");
+        tracing::debug!("Image '{image_name}' base.base.product");
+        tracing::debug!("SAT file - 'image.base.product' job");
 
         // Base image created from a cray product
 
         let product_name = &product.name;
 
-        let product_version = product.version.as_ref().ok_or_else(|| {
-          Error::YamlShape(format!(
-            "SAT file: image '{image_name}' base.product '{product_name}' is missing 'version'"
-          ))
-        })?;
+        let product_version_opt = product.version.as_ref();
+        if product_version_opt.is_none() {
+          report.error(
+            format!("{path}.base.product.version"),
+            format!("base.product '{product_name}' is missing 'version'"),
+          );
+        }
 
         let product_type = &product.r#type;
 
-        let product_catalog_rslt = &serde_yaml::from_str::<serde_json::Value>(
-          cray_product_catalog
-            .get(product_name)
-            .unwrap_or(&String::new()),
-        );
-
-        let Ok(product_catalog) = product_catalog_rslt else {
-          return Err(Error::CrayProductCatalog(format!(
-            "Product catalog for image '{image_name}' not found. Exit"
-          )));
-        };
-
-        let product_type_opt = product_catalog
-          .get(product_version)
-          .and_then(|product_version| product_version.get(product_type.clone()))
-          .cloned();
-
-        let product_type_opt = if let Some(product_type) = product_type_opt {
-          product_type.as_object().cloned()
-        } else {
-          return Err(Error::CrayProductCatalog(format!(
-            "Product catalog for image '{image_name}' not found. Exit"
-          )));
-        };
-
-        let image_map: Map<String, serde_json::Value> =
-          if let Some(product_type) = &product_type_opt {
-            product_type.clone()
-          } else {
-            return Err(Error::CrayProductCatalog(format!(
-              "Product catalog for image '{image_name}' not found. Exit"
-            )));
-          };
+        let kind_opt = ArtifactKind::parse(product_type);
+        if kind_opt.is_none() {
+          report.error(
+            format!("{path}.base.product.type"),
+            format!(
+              "base.product type '{product_type}' must be either 'image' or 'recipe'"
+            ),
+          );
+        }
 
-        log::debug!(
-          "CRAY product catalog items related to product name '{product_name}', product version '{product_version}' and product type '{product_type}':\n{product_type_opt:#?}"
-        );
+        if let (Some(product_version), Some(kind)) =
+          (product_version_opt, kind_opt)
+        {
+          // Without 'image.product.filter' the first catalog entry is used
+          let artifact_rslt = cray_product_catalog
+          .version(product_name, product_version)
+          .and_then(|product_version_details| {
+            tracing::debug!(
+              "CRAY product catalog items related to product name '{product_name}', product version '{product_version}' and product type '{product_type}':\n{product_version_details:#?}"
+            );
 
-        if let Some(filter) = &product.filter {
-          let image_recipe_id =
-            filter_product_catalog_images(filter, image_map, image_name);
-          image_recipe_id.is_ok()
-        } else {
-          // There is no 'image.product.filter' value defined in SAT file. Check Cray
-          // product catalog only has 1 image. Othewise fail
-          log::debug!(
-            "No 'image.product.filter' defined in SAT file. Checking Cray product catalog only/must have 1 image"
-          );
-          image_map
-            .values()
-            .next()
-            .is_some_and(|value| value.get("id").is_some())
-        };
+            product_version_details
+              .select_artifact(
+                kind,
+                product.filter.as_ref().map(ArtifactFilter::from).as_ref(),
+                product.select,
+                image_name,
+              )
+              .map(|_| ())
+          });
+
+          if let Err(e) = artifact_rslt {
+            report.error(format!("{path}.base.product"), e.to_string());
+          }
+        }
       // } else if let Some(image_base_ims_yaml) = image_yaml["base"].get("ims") {
       } else if let image::Base::Ims { ims } = base {
         // Check if the image exists
 
-        log::debug!("Image '{image_name}' base.base.ims");
+        tracing::debug!("Image '{image_name}' base.base.ims");
         if let image::ImageBaseIms::NameType { name, r#type } = ims {
           // if let Some(image_base_ims_name_yaml) = ims.get("name") {
           let image_base_ims_name_to_find = name;
 
           // Search image in SAT file
 
-          log::debug!(
+          tracing::debug!(
             "Searching base image '{image_base_ims_name_to_find}' related to image '{image_name}' in SAT file"
           );
 
@@ -1176,16 +1760,16 @@ pub fn validate_sat_file_images_section(
             .any(|image_yaml| image_yaml.name.eq(name));
 
           if !image_found {
-            log::warn!(
+            events::warning(format!(
               "Base image '{image_base_ims_name_to_find}' not found in SAT file, looking in CSM"
-            );
+            ));
 
             let image_base_ims_type = r#type;
             if image_base_ims_type.eq("recipe") {
               // Base IMS type is a recipe
               // Search in CSM (IMS Recipe)
 
-              log::debug!(
+              tracing::debug!(
                 "Searching base image recipe '{image_base_ims_name_to_find}' related to image '{image_name}' in CSM"
               );
 
@@ -1194,15 +1778,18 @@ pub fn validate_sat_file_images_section(
                 .any(|recipe| recipe.name.eq(image_base_ims_name_to_find));
 
               if !image_found {
-                return Err(Error::SatFile(format!(
-                  "Could not find IMS recipe '{image_base_ims_name_to_find}' in CSM. Cancelling image build proccess. Exit",
-                )));
+                report.error(
+                  format!("{path}.base.ims.name"),
+                  format!(
+                    "Could not find IMS recipe '{image_base_ims_name_to_find}' in SAT file or CSM"
+                  ),
+                );
               }
             } else {
               // Base IMS type is an image
               // Search in CSM (IMS Image)
 
-              log::debug!(
+              tracing::debug!(
                 "Searching base image '{image_base_ims_name_to_find}' related to image '{image_name}' in CSM"
               );
 
@@ -1213,35 +1800,35 @@ pub fn validate_sat_file_images_section(
                 .any(|image| image.name.contains(image_base_ims_name_to_find));
 
               if !image_found {
-                return Err(Error::SatFile(format!(
-                  "Could not find image base '{image_base_ims_name_to_find}' in image '{image_name}'. Cancelling image build proccess. Exit"
-                )));
+                report.error(
+                  format!("{path}.base.ims.name"),
+                  format!(
+                    "Could not find base image '{image_base_ims_name_to_find}' in SAT file or CSM"
+                  ),
+                );
               }
             }
           }
         } else {
-          log::warn!(
-            "Image '{image_name}' is missing the field 'base.ims.name'. Exit"
+          report.warning(
+            format!("{path}.base.ims"),
+            "base image given without 'name', not checked",
           );
         }
       } else {
-        return Err(Error::SatFile(format!(
-          "Image '{image_name}' yaml not recognised. Exit"
-        )));
+        report.error(format!("{path}.base"), "base not recognised");
       }
     } else {
-      return Err(Error::SatFile(format!(
-        "Image '{image_name}' neither have 'ims' nor 'base' value. Exit"
-      )));
+      report.error(path.clone(), "neither have 'ims' nor 'base' value");
     }
 
     // Validate CFS configuration exists (image.configuration)
-    log::debug!("Validate 'image' '{image_name}' configuration");
+    tracing::debug!("Validate 'image' '{image_name}' configuration");
 
     if let Some(configuration_yaml) = image_yaml.configuration.as_ref() {
       let configuration_name_to_find = configuration_yaml;
 
-      log::debug!(
+      tracing::debug!(
         "Searching configuration name '{configuration_name_to_find}' related to image '{image_name}' in SAT file"
       );
 
@@ -1252,11 +1839,11 @@ pub fn validate_sat_file_images_section(
 
       if !configuration_found {
         // CFS configuration in image not found in SAT file, searching in CSM
-        log::warn!(
+        events::warning(format!(
           "Configuration '{configuration_name_to_find}' not found in SAT file, looking in CSM"
-        );
+        ));
 
-        log::debug!(
+        tracing::debug!(
           "Searching configuration name '{}' related to image '{}' in CSM",
           configuration_name_to_find,
           image_yaml.name
@@ -1267,14 +1854,17 @@ pub fn validate_sat_file_images_section(
         });
 
         if !configuration_found {
-          return Err(Error::SatFile(format!(
-            "Could not find configuration '{configuration_name_to_find}' in image '{image_name}'. Cancelling image build proccess. Exit"
-          )));
+          report.error(
+            format!("{path}.configuration"),
+            format!(
+              "Could not find configuration '{configuration_name_to_find}' in SAT file or CSM"
+            ),
+          );
         }
       }
 
       // Validate user has access to HSM groups in 'image' section
-      log::debug!("Validate 'image' '{image_name}' HSM groups");
+      tracing::debug!("Validate 'image' '{image_name}' HSM groups");
 
       // Strip site-wide group names — see `hsm::group::hacks` module
       // docs for why.
@@ -1287,26 +1877,36 @@ pub fn validate_sat_file_images_section(
         );
 
       if configuration_group_names_vec.is_empty() {
-        return Err(Error::SatFile(format!(
-          "Image '{image_name}' must have group name values assigned to it. Canceling image build process. Exit"
-        )));
+        report.error(
+          format!("{path}.configuration_group_names"),
+          "must have group name values assigned to it",
+        );
       }
+      // Labels HSM would reject can't name the nodes to configure
+      for group_name in &configuration_group_names_vec {
+        if let Err(e) = hsm::group::types::GroupLabel::parse(group_name) {
+          report
+            .error(format!("{path}.configuration_group_names"), e.to_string());
+        }
+      }
+      // Role groups ("Compute", "Application_UAN", ...) aren't HSM
+      // groups and need no access
       for hsm_group in
         configuration_group_names_vec.iter().filter(|&hsm_group| {
-          !hsm_group.eq_ignore_ascii_case("Compute")
-            && !hsm_group.eq_ignore_ascii_case("Application")
-            && !hsm_group.eq_ignore_ascii_case("Application_UAN")
+          hsm::component::NodeRole::from_ansible_group(hsm_group).is_none()
         })
       {
         if !hsm_group_available_vec.contains(hsm_group) {
-          return Err(Error::SatFile(format!(
-            "HSM group '{}' in image '{}' not allowed, List of HSM groups available:\n{:?}. Exit",
-            hsm_group, image_yaml.name, hsm_group_available_vec
-          )));
+          report.error(
+            format!("{path}.configuration_group_names"),
+            format!(
+              "HSM group '{hsm_group}' not allowed, List of HSM groups available {hsm_group_available_vec:?}"
+            ),
+          );
         }
       }
     }
   }
 
-  Ok(())
+  report
 }
@@ -2,12 +2,14 @@
 //! conversions from SAT sections to BOS/CFS/IMS shapes, and per-section
 //! orchestration submodules.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use crate::{
   bos::{BootSet, BosSessionTemplate, Cfs},
-  commands::i_apply_sat_file::utils::sessiontemplate::Arch,
+  commands::i_apply_sat_file::{provenance, utils::sessiontemplate::Arch},
+  common::naming::{self, NamingContext},
   error::Error,
+  ims::Image as ImsImage,
 };
 use image::Image;
 use serde::{Deserialize, Serialize};
@@ -182,56 +184,134 @@ impl SatFile {
 /// struct to represent the `session_templates` section in SAT file
 pub mod sessiontemplate;
 
+/// `context` with the naming variables of SAT session template
+/// `sat_session_template`: its `{name}`, `{configuration}` and, as
+/// `{group}`, the HSM groups its boot sets target.
+pub(crate) fn session_template_naming_context(
+  sat_session_template: &SessionTemplate,
+  context: NamingContext,
+) -> NamingContext {
+  let group_set: BTreeSet<&str> = sat_session_template
+    .bos_parameters
+    .boot_sets
+    .values()
+    .flat_map(|boot_set| boot_set.node_groups.iter().flatten())
+    .map(String::as_str)
+    .collect();
+
+  context
+    .with("name", sat_session_template.name.as_str())
+    .with("configuration", sat_session_template.configuration.as_str())
+    .with("group", group_set.into_iter().collect::<Vec<_>>().join(","))
+}
+
 /// Convert from `sessiontemplate` in SAT file to mesa `BosSessionTemplate`.
 ///
+/// Description and boot set names follow the
+/// [`NamingPolicy`](crate::NamingPolicy) of the client in scope; the
+/// template keeps the SAT entry name. The boot sets carry no S3 `path`,
+/// `type` or `etag`: the SAT entry only references its boot image,
+/// convert from `(SessionTemplate, &Image)` to resolve them.
+///
 /// Example from <https://doc.rust-lang.org/rust-by-example/conversion/try_from_try_into.html>.
 impl TryFrom<SessionTemplate> for BosSessionTemplate {
-  type Error = ();
+  type Error = Error;
 
   fn try_from(
     value: SessionTemplate,
   ) -> Result<BosSessionTemplate, Self::Error> {
-    let b_st_cfs = Cfs {
-      configuration: Some(value.configuration),
-    };
+    bos_session_template_from_sat(value, None)
+  }
+}
 
-    let mut boot_set_map: HashMap<String, BootSet> = HashMap::new();
-
-    for (property, boot_set) in value.bos_parameters.boot_sets {
-      let boot_set = BootSet {
-        name: Some(format!(
-          "Boot set property '{property}' created by manta from SAT file"
-        )),
-        path: None,
-        r#type: None,
-        etag: None,
-        kernel_parameters: None,
-        node_list: boot_set.node_list,
-        node_roles_groups: boot_set.node_roles_group,
-        node_groups: boot_set.node_groups,
-        rootfs_provider: boot_set.rootfs_provider,
-        rootfs_provider_passthrough: boot_set.rootfs_provider_passthrough,
-        cfs: Some(b_st_cfs.clone()),
-        arch: boot_set.arch.as_ref().map(Arch::to_string),
-      };
+/// Convert from `sessiontemplate` in SAT file and the IMS image its boot
+/// sets boot to mesa `BosSessionTemplate`, with the S3 `path`, `type`
+/// and `etag` of the image manifest in every boot set.
+impl TryFrom<(SessionTemplate, &ImsImage)> for BosSessionTemplate {
+  type Error = Error;
 
-      boot_set_map.insert(property, boot_set);
-    }
+  fn try_from(
+    (value, boot_image): (SessionTemplate, &ImsImage),
+  ) -> Result<BosSessionTemplate, Self::Error> {
+    bos_session_template_from_sat(value, Some(boot_image))
+  }
+}
+
+/// `value` as a BOS session template, its boot sets booting
+/// `boot_image_opt` if given.
+///
+/// # Errors
+///
+/// Returns [`Error::SatFileField`] if a boot set has no
+/// `kernel_parameters`, or [`Error::SatFile`] if `boot_image_opt` has no
+/// S3 manifest.
+fn bos_session_template_from_sat(
+  value: SessionTemplate,
+  boot_image_opt: Option<&ImsImage>,
+) -> Result<BosSessionTemplate, Error> {
+  let naming_policy = naming::current();
+  let naming_context =
+    session_template_naming_context(&value, NamingContext::now());
+
+  let mut boot_set_map: HashMap<String, BootSet> = HashMap::new();
 
-    let b_st = BosSessionTemplate {
-      name: Some(value.name),
-      description: Some(
-        "BOS sessiontemplate created by manta from SAT file".to_string(),
-      ),
-      enable_cfs: Some(true),
-      cfs: Some(b_st_cfs),
-      boot_sets: Some(boot_set_map),
-      links: None,
-      tenant: None,
+  for (property, boot_set) in &value.bos_parameters.boot_sets {
+    let path = session_templates::sat_boot_set_path(&value, property);
+    let kernel_parameters =
+      session_templates::boot_set_kernel_parameters(&path, boot_set)?;
+    let (image_path, image_type, image_etag) = match boot_image_opt {
+      Some(boot_image) => {
+        let (image_path, image_type, image_etag) =
+          session_templates::boot_image_artifacts(&path, boot_image)?;
+        (
+          Some(image_path.to_string()),
+          Some(image_type.to_string()),
+          Some(image_etag.to_string()),
+        )
+      }
+      None => (None, None, None),
+    };
+
+    let boot_set = BootSet {
+      name: Some(naming_policy.boot_set_name(
+        &naming_context.clone().with("boot_set", property.as_str()),
+      )),
+      path: image_path,
+      r#type: image_type,
+      etag: image_etag,
+      kernel_parameters: Some(kernel_parameters.to_string()),
+      node_list: boot_set.node_list.clone(),
+      node_roles_groups: boot_set.node_roles_groups.clone(),
+      node_groups: boot_set.node_groups.clone(),
+      rootfs_provider: boot_set.rootfs_provider.clone(),
+      rootfs_provider_passthrough:
+        session_templates::render_rootfs_provider_passthrough(
+          &naming_context,
+          property,
+          boot_set,
+        ),
+      cfs: Some(Cfs {
+        configuration: Some(value.configuration.clone()),
+      }),
+      arch: boot_set.arch.as_ref().map(Arch::to_string),
     };
 
-    Ok(b_st)
+    boot_set_map.insert(property.clone(), boot_set);
   }
+
+  Ok(BosSessionTemplate {
+    name: Some(value.name),
+    description: provenance::annotate_description(Some(
+      naming_policy.bos_template_description(&naming_context),
+    )),
+    enable_cfs: Some(true),
+    cfs: Some(Cfs {
+      configuration: Some(value.configuration),
+    }),
+    boot_sets: Some(boot_set_map),
+    links: None,
+    tenant: None,
+  })
 }
 
 /// struct to represent the `images` section in SAT file
@@ -244,11 +324,15 @@ pub mod configuration;
 /// CFS configuration creation helpers driven by a SAT file's
 /// `configurations` section.
 pub(crate) mod configurations;
+/// Resolution of the base image of a SAT file image.
+pub(crate) mod image_base;
 /// IMS image build helpers driven by a SAT file's `images` section.
 pub mod images;
 /// BOS session template creation helpers driven by a SAT file's
 /// `session_templates` section.
 pub(crate) mod session_templates;
+/// Problems found while validating a SAT file.
+pub mod validation;
 
 // Re-export the orchestration helpers actually called through
 // `utils::name` at the original paths. Restricted to `pub(crate)` —
@@ -1,11 +1,18 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeSet, HashMap, hash_map::Entry};
 
-use serde_yaml::Value;
 use uuid::Uuid;
 
 use crate::{
-  bos::{BootSet, BosSession, BosSessionTemplate, Cfs, Operation},
-  common::{self, yaml::yaml_str},
+  bos::{
+    BootSet, BosSession, BosSessionTemplate, Cfs, Operation,
+    session::reboot::{self, RebootStrategy},
+    template::{schema::SCHEMA, utils::validate_boot_set_against_image},
+  },
+  commands::i_apply_sat_file::provenance,
+  common::{
+    self, events,
+    naming::{self, NamingContext},
+  },
   error::Error,
   hsm,
   ims::{self, image::http_client::types::Link},
@@ -14,18 +21,21 @@ use crate::{
 
 use super::{
   configuration, image,
-  images::{
-    filter_product_catalog_images, process_sat_file_image_ims_type_recipe,
-    process_sat_file_image_old_version_struct,
-    process_sat_file_image_product_type_ims_recipe,
-  },
+  image_base::{ImageBaseContext, ImageBaseResolver},
+  images::ImageBuildContext,
   sessiontemplate,
+  validation::ValidationReport,
 };
 
 #[allow(clippy::too_many_arguments)]
 /// Pre-flight validation for the SAT file's `session_templates`
-/// section: rejects entries referencing missing images, unknown
+/// section: reports entries referencing missing images, unknown
 /// configurations, or out-of-scope HSM groups / xnames.
+///
+/// # Errors
+///
+/// Returns an [`Error`] only if CSM can't be queried; problems in the
+/// SAT file are recorded in the returned [`ValidationReport`].
 pub async fn validate_sat_file_session_template_section(
   shasta_token: &str,
   shasta_base_url: &str,
@@ -35,51 +45,39 @@ pub async fn validate_sat_file_session_template_section(
   configuration_yaml_vec: &[configuration::Configuration],
   session_template_yaml_vec: &[sessiontemplate::SessionTemplate],
   hsm_group_available_vec: &[String],
-) -> Result<(), Error> {
+) -> Result<ValidationReport, Error> {
+  let mut report = ValidationReport::new();
+
   // Validate 'session_template' section in SAT file
-  log::debug!("Validate 'session_template' section in SAT file");
+  tracing::debug!("Validate 'session_template' section in SAT file");
   for session_template_yaml in session_template_yaml_vec {
     // Validate session_template
-    log::debug!(
+    tracing::debug!(
       "Validate 'session_template' '{}'",
       session_template_yaml.name
     );
+    let path = format!("session_templates[{}]", session_template_yaml.name);
 
-    // Validate user has access to HSM groups in 'session_template' section
-    log::debug!(
-      "Validate 'session_template' '{}' HSM groups",
+    // Validate user has access to the targets of every boot set in
+    // 'session_template' section
+    tracing::debug!(
+      "Validate 'session_template' '{}' boot sets",
       session_template_yaml.name
     );
 
-    let bos_session_template_hsm_groups: Vec<String> =
-      if let Some(boot_sets_compute) = session_template_yaml
-        .bos_parameters
-        .boot_sets
-        .get("compute")
-      {
-        boot_sets_compute.node_groups.clone().unwrap_or_default()
-      } else if let Some(boot_sets_uan) =
-        session_template_yaml.bos_parameters.boot_sets.get("uan")
-      {
-        boot_sets_uan.node_groups.clone().unwrap_or_default()
-      } else {
-        return Err(Error::SatFile(
-          "No HSM group found in session_templates section in SAT file"
-            .to_string(),
-        ));
-      };
-
-    for hsm_group in bos_session_template_hsm_groups {
-      if !hsm_group_available_vec.contains(&hsm_group) {
-        return Err(Error::SatFile(format!(
-          "HSM group '{}' in session_templates {} not allowed, List of HSM groups available {:?}. Exit",
-          hsm_group, session_template_yaml.name, hsm_group_available_vec
-        )));
-      }
-    }
+    validate_session_template_boot_sets(
+      shasta_token,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      session_template_yaml,
+      hsm_group_available_vec,
+      &mut report,
+    )
+    .await;
 
     // Validate boot image (session_template.image)
-    log::debug!(
+    tracing::debug!(
       "Validate 'session_template' '{}' boot image",
       session_template_yaml.name
     );
@@ -90,16 +88,17 @@ pub async fn validate_sat_file_session_template_section(
     {
       // Validate image_ref (session_template.image.image_ref). Search in SAT file for any
       // image with images[].ref_name
-      log::debug!("Searching ref_name '{ref_name_to_find}' in SAT file");
+      tracing::debug!("Searching ref_name '{ref_name_to_find}' in SAT file");
 
       let image_ref_name_found = image_yaml_vec
         .iter()
         .any(|image| image.ref_name.eq(&Some(ref_name_to_find).cloned()));
 
       if !image_ref_name_found {
-        return Err(Error::SatFile(format!(
-          "Could not find image ref '{ref_name_to_find}' in SAT file. Exit"
-        )));
+        report.error(
+          format!("{path}.image.image_ref"),
+          format!("Could not find image ref '{ref_name_to_find}' in SAT file"),
+        );
       }
     } else if let sessiontemplate::Image::Ims { ims } =
       &session_template_yaml.image
@@ -109,7 +108,7 @@ pub async fn validate_sat_file_session_template_section(
           name: image_name_substr_to_find,
         } => {
           // Validate image name (session_template.image.ims.name). Search in SAT file and CSM
-          log::debug!(
+          tracing::debug!(
             "Searching image name '{}' related to session template '{}' in SAT file",
             image_name_substr_to_find,
             session_template_yaml.name
@@ -118,45 +117,50 @@ pub async fn validate_sat_file_session_template_section(
           let mut image_found = image_yaml_vec
             .iter()
             .any(|image| image.name.eq(image_name_substr_to_find));
+          let mut image_error = format!(
+            "Could not find image name '{image_name_substr_to_find}' in SAT file or CSM"
+          );
 
           if !image_found {
-            log::warn!(
+            events::warning(format!(
               "Image name '{image_name_substr_to_find}' not found in SAT file, looking in CSM"
-            );
-            log::debug!(
+            ));
+            tracing::debug!(
               "Searching image name '{}' related to session template '{}' in CSM",
               image_name_substr_to_find,
               session_template_yaml.name
             );
 
-            image_found = ims::image::utils::try_get_by_name(
+            match ims::image::utils::get_strict(
               shasta_token,
               shasta_base_url,
               shasta_root_cert,
               socks5_proxy,
               image_name_substr_to_find,
-              Some(&1),
             )
             .await
-            .is_ok();
+            {
+              Ok(_) => image_found = true,
+              Err(e @ Error::AmbiguousImageName { .. }) => {
+                image_error = e.to_string();
+              }
+              Err(_) => {}
+            }
           }
 
           if !image_found {
-            return Err(Error::SatFile(format!(
-              "Could not find image name '{}' in session_template '{}'. Exit",
-              image_name_substr_to_find, session_template_yaml.name
-            )));
+            report.error(format!("{path}.image.ims.name"), image_error);
           }
         }
         sessiontemplate::ImsDetails::Id { id: image_id } => {
           // Validate image id (session_template.image.ims.id) in CSM
-          log::debug!(
+          tracing::debug!(
             "Searching image id '{}' related to session template '{}' in CSM",
             image_id,
             session_template_yaml.name
           );
 
-          let image_found = crate::ShastaClient::new(
+          let image_found = crate::ShastaClient::scoped_or_new(
             shasta_base_url,
             shasta_root_cert.to_vec(),
             socks5_proxy.map(str::to_owned),
@@ -166,10 +170,10 @@ pub async fn validate_sat_file_session_template_section(
           .is_ok();
 
           if !image_found {
-            return Err(Error::SatFile(format!(
-              "Could not find image id '{}' in session_template '{}'. Exit",
-              image_id, session_template_yaml.name
-            )));
+            report.error(
+              format!("{path}.image.ims.id"),
+              format!("Could not find image id '{image_id}' in CSM"),
+            );
           }
         }
       }
@@ -177,26 +181,27 @@ pub async fn validate_sat_file_session_template_section(
       &session_template_yaml.image
     {
       // Validate image name (session_template.image.image). Search in SAT file for any
-      log::debug!("Searching image name '{image_name}' in SAT file");
+      tracing::debug!("Searching image name '{image_name}' in SAT file");
 
       let image_ref_name_found = image_yaml_vec
         .iter()
         .any(|image| image.name.eq(&image_name.clone()));
 
       if !image_ref_name_found {
-        return Err(Error::SatFile(format!(
-          "Could not find image '{image_name}' in SAT file. Exit"
-        )));
+        report.error(
+          format!("{path}.image"),
+          format!("Could not find image '{image_name}' in SAT file"),
+        );
       }
     }
 
     // Validate configuration
-    log::debug!(
+    tracing::debug!(
       "Validate 'session_template' '{}' configuration",
       session_template_yaml.name
     );
 
-    log::debug!(
+    tracing::debug!(
       "Searching configuration name '{}' related to session template '{}' in CSM in SAT file",
       session_template_yaml.configuration,
       session_template_yaml.name
@@ -211,14 +216,14 @@ pub async fn validate_sat_file_session_template_section(
 
     if !configuration_found {
       // CFS configuration in session_template not found in SAT file, searching in CSM
-      log::warn!("Configuration not found in SAT file, looking in CSM");
-      log::debug!(
+      events::warning("Configuration not found in SAT file, looking in CSM");
+      tracing::debug!(
         "Searching configuration name '{}' related to session_template '{}' in CSM",
         session_template_yaml.configuration,
         session_template_yaml.name
       );
 
-      configuration_found = crate::ShastaClient::new(
+      configuration_found = crate::ShastaClient::scoped_or_new(
         shasta_base_url,
         shasta_root_cert.to_vec(),
         socks5_proxy.map(str::to_owned),
@@ -231,21 +236,125 @@ pub async fn validate_sat_file_session_template_section(
       .is_ok();
 
       if !configuration_found {
-        return Err(Error::SatFile(format!(
-          "Could not find configuration '{}' in session_template '{}'. Exit",
-          session_template_yaml.configuration, session_template_yaml.name,
-        )));
+        report.error(
+          format!("{path}.configuration"),
+          format!(
+            "Could not find configuration '{}' in SAT file or CSM",
+            session_template_yaml.configuration
+          ),
+        );
       }
     }
   }
 
-  Ok(())
+  Ok(report)
+}
+
+/// Validate the targets of every boot set in `session_template_yaml`:
+/// `node_groups` must be HSM groups the user has access to, `node_list`
+/// xnames must belong to them, and `node_roles_groups` are only allowed
+/// for admins (empty `hsm_group_available_vec`). A boot set must target
+/// nodes through at least one of them.
+///
+/// Problems of every boot set are recorded in `report`.
+async fn validate_session_template_boot_sets(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  session_template_yaml: &sessiontemplate::SessionTemplate,
+  hsm_group_available_vec: &[String],
+  report: &mut ValidationReport,
+) {
+  let path = format!(
+    "session_templates[{}].bos_parameters.boot_sets",
+    session_template_yaml.name
+  );
+
+  if session_template_yaml.bos_parameters.boot_sets.is_empty() {
+    report.error(path.clone(), "no boot sets");
+  }
+
+  // Sorted so errors are reported in a stable order
+  let mut boot_set_vec: Vec<_> = session_template_yaml
+    .bos_parameters
+    .boot_sets
+    .iter()
+    .collect();
+  boot_set_vec.sort_by_key(|(boot_set_name, _)| *boot_set_name);
+
+  for (boot_set_name, boot_set) in boot_set_vec {
+    tracing::debug!("Validate '{path}.{boot_set_name}'");
+
+    let node_group_vec = hsm::group::hacks::filter_system_hsm_group_names(
+      boot_set.node_groups.clone().unwrap_or_default(),
+    );
+    let node_list = boot_set.node_list.as_deref().unwrap_or_default();
+    let node_roles_group_vec =
+      boot_set.node_roles_groups.as_deref().unwrap_or_default();
+
+    if node_group_vec.is_empty()
+      && node_list.is_empty()
+      && node_roles_group_vec.is_empty()
+    {
+      report.error(
+        format!("{path}.{boot_set_name}"),
+        "no node_groups, node_list or node_roles_groups",
+      );
+      continue;
+    }
+
+    for node_group in &node_group_vec {
+      if !hsm_group_available_vec.contains(node_group) {
+        report.error(
+          format!("{path}.{boot_set_name}.node_groups"),
+          format!(
+            "HSM group '{node_group}' not allowed, List of HSM groups available {hsm_group_available_vec:?}"
+          ),
+        );
+      }
+    }
+
+    // Users with tenant role are not allowed to create BOS
+    // sessiontemplates based on node roles, admin tenants are
+    if !hsm_group_available_vec.is_empty() && !node_roles_group_vec.is_empty() {
+      report.error(
+        format!("{path}.{boot_set_name}.node_roles_groups"),
+        format!(
+          "user type tenant can't use node roles {node_roles_group_vec:?}"
+        ),
+      );
+    }
+
+    if node_list.is_empty() {
+      continue;
+    }
+
+    if let Err(e) = validate_target_hsm_members(
+      shasta_token,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      &node_list.iter().map(String::as_str).collect::<Vec<&str>>(),
+    )
+    .await
+    {
+      report.error(format!("{path}.{boot_set_name}.node_list"), e.to_string());
+    }
+  }
 }
 
 #[allow(clippy::too_many_arguments)]
 /// Apply every entry in the SAT file's `session_templates` section:
 /// rewrite image references using the freshly-built image IDs (from
-/// `ref_name_processed_hashmap`) and PUT each template into BOS.
+/// `ref_name_processed_hashmap`) and PUT each template into BOS, after
+/// checking it against the BOS v2 schema (see
+/// [`crate::bos::template::schema::Schema::check`]).
+///
+/// With `reboot`, the nodes of each template are rebooted as
+/// `reboot_strategy` says. With `cancel_stale_sessions`, pending or
+/// running BOS sessions booting any of those nodes are deleted first;
+/// see [`crate::bos::session::utils::cancel_stale`].
 pub async fn process_session_template_section_in_sat_file(
   shasta_token: &str,
   shasta_base_url: &str,
@@ -253,123 +362,65 @@ pub async fn process_session_template_section_in_sat_file(
   socks5_proxy: Option<&str>,
   ref_name_processed_hashmap: HashMap<String, String>,
   hsm_group_available_vec: &[String],
-  sat_file_yaml: Value,
+  session_template_yaml_vec: &[sessiontemplate::SessionTemplate],
   reboot: bool,
+  reboot_strategy: RebootStrategy,
+  cancel_stale_sessions: bool,
   dry_run: bool,
 ) -> Result<(Vec<BosSessionTemplate>, Vec<BosSession>), Error> {
-  let empty_vec = Vec::new();
-  let bos_session_template_list_yaml = sat_file_yaml
-    .get("session_templates")
-    .and_then(Value::as_sequence)
-    .unwrap_or(&empty_vec);
-
-  if bos_session_template_list_yaml.is_empty() {
-    log::warn!(
-      "No 'session_templates' section found in SAT file. Skipping session template processing"
+  if session_template_yaml_vec.is_empty() {
+    events::warning(
+      "No 'session_templates' section found in SAT file. Skipping session template processing",
     );
     return Ok((Vec::new(), Vec::new()));
   }
 
-  let mut bos_st_created_vec: Vec<BosSessionTemplate> = Vec::new();
-  let mut bos_sessions_created: Vec<BosSession> = Vec::new();
+  // Malformed templates are reported before anything is created
+  for sat_session_template in session_template_yaml_vec {
+    for (boot_set_name, boot_set) in
+      &sat_session_template.bos_parameters.boot_sets
+    {
+      boot_set_kernel_parameters(
+        &sat_boot_set_path(sat_session_template, boot_set_name),
+        boot_set,
+      )?;
+    }
+  }
 
-  for bos_sessiontemplate_yaml in bos_session_template_list_yaml {
-    // Get boot image details in BOS sessiontemplate. This is needed to create the BOS
-    // sessiontemplate BootSets
-    let image_details: ims::image::http_client::types::Image =
-      if let Some(bos_sessiontemplate_image) =
-        bos_sessiontemplate_yaml.get("image")
-      {
-        let (image_reference, is_image_id) =
-          get_image_reference_from_bos_sessiontemplate_yaml(
-            bos_sessiontemplate_image,
-            &ref_name_processed_hashmap,
-          )?;
-        if dry_run {
-          let dry_run_mock_image =
-            get_image_details_from_bos_sessiontemplate_yaml(
-              shasta_token,
-              shasta_base_url,
-              shasta_root_cert,
-              socks5_proxy,
-              &image_reference,
-              is_image_id,
-            )
-            .await
-            .unwrap_or_else(|_| {
-              // In dry run mode, generate a mock image
-
-              if is_image_id {
-                // Image reference is an image ID
-                ims::image::http_client::types::Image {
-                  id: Some(image_reference.clone()),
-                  created: None,
-                  name: "dryrun_image".to_string(),
-                  link: Some(Link {
-                    path: "dryrun_path".to_string(),
-                    etag: Some("dryrun_etag".to_string()),
-                    r#type: "dryrun_type".to_string(),
-                  }),
-                  arch: None,
-                  metadata: None,
-                }
-              } else {
-                // Image reference is an image name
-                ims::image::http_client::types::Image {
-                  id: None,
-                  created: None,
-                  name: image_reference.clone(),
-                  link: Some(Link {
-                    path: "dryrun_path".to_string(),
-                    etag: Some("dryrun_etag".to_string()),
-                    r#type: "dryrun_type".to_string(),
-                  }),
-                  arch: None,
-                  metadata: None,
-                }
-              }
-            });
+  let client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
 
-          log::debug!(
-            "Dry run mode: Generate mock Image\n{}",
-            serde_json::to_string_pretty(&dry_run_mock_image)?
-          );
+  let mut bos_st_created_vec: Vec<BosSessionTemplate> = Vec::new();
+  let mut bos_sessions_created: Vec<BosSession> = Vec::new();
 
-          dry_run_mock_image
-        } else {
-          get_image_details_from_bos_sessiontemplate_yaml(
-            shasta_token,
-            shasta_base_url,
-            shasta_root_cert,
-            socks5_proxy,
-            &image_reference,
-            is_image_id,
-          )
-          .await?
-        }
-      } else {
-        return Err(Error::SatFile(
-          "ERROR: no 'image' section in session_template.\nExit".to_string(),
-        ));
-      };
+  for sat_session_template in session_template_yaml_vec {
+    let path = format!("session_templates[{}]", sat_session_template.name);
 
-    log::debug!("Image with name '{}' found", image_details.name);
+    // Boot image of each boot set `arch`; an image built for several
+    // architectures has one build per architecture
+    let mut image_details_map: HashMap<
+      Option<String>,
+      ims::image::http_client::types::Image,
+    > = HashMap::new();
 
     // Get CFS configuration to configure the nodes
     let bos_session_template_configuration_name =
-      yaml_str(bos_sessiontemplate_yaml, "configuration")?.to_string();
+      sat_session_template.configuration.clone();
 
     // Check CFS configuration exists in CSM
-    log::debug!(
+    tracing::debug!(
       "Looking for CFS configuration with name: {bos_session_template_configuration_name}"
     );
 
     if dry_run {
-      log::debug!(
+      tracing::debug!(
         "Dry run mode: CFS configuration '{bos_session_template_configuration_name}' found in CSM."
       );
     } else {
-      crate::ShastaClient::new(
+      crate::ShastaClient::scoped_or_new(
         shasta_base_url,
         shasta_root_cert.to_vec(),
         socks5_proxy.map(str::to_owned),
@@ -381,63 +432,61 @@ pub async fn process_session_template_section_in_sat_file(
       .await?;
     }
 
-    // let ims_image_name = image_details.name.to_string();
-    let image_link = image_details.link.as_ref().ok_or_else(|| {
-      Error::SatFile(format!(
-        "IMS image '{}' has no 'link' (no S3 manifest)",
-        image_details.name
-      ))
-    })?;
-    let ims_image_etag: &str = image_link.etag.as_deref().ok_or_else(|| {
-      Error::SatFile(format!(
-        "IMS image '{}' link has no 'etag'",
-        image_details.name
-      ))
-    })?;
-    let ims_image_path: &str = image_link.path.as_ref();
-    let ims_image_type: &str = image_link.r#type.as_ref();
-
-    let bos_sessiontemplate_name = bos_sessiontemplate_yaml
-      .get("name")
-      .and_then(Value::as_str)
-      .map(str::to_string)
-      .unwrap_or_default();
+    let sat_sessiontemplate_name = sat_session_template.name.as_str();
+
+    // Name the template and its boot sets after the naming policy
+    let naming_policy = naming::current();
+    let naming_context = NamingContext::new(shasta_token)
+      .with("name", sat_sessiontemplate_name)
+      .with(
+        "configuration",
+        bos_session_template_configuration_name.as_str(),
+      );
 
     let mut boot_set_vec: HashMap<String, BootSet> = HashMap::new();
+    let mut node_group_set: BTreeSet<String> = BTreeSet::new();
+
+    // Sorted so boot sets are processed in a stable order
+    let mut sat_boot_set_vec: Vec<_> = sat_session_template
+      .bos_parameters
+      .boot_sets
+      .iter()
+      .collect();
+    sat_boot_set_vec.sort_by_key(|(boot_set_name, _)| *boot_set_name);
+
+    for (parameter_str, sat_boot_set) in sat_boot_set_vec {
+      let boot_set_path =
+        sat_boot_set_path(sat_session_template, parameter_str);
+      let arch_opt = sat_boot_set
+        .arch
+        .as_ref()
+        .map(sessiontemplate::Arch::to_string);
+
+      let image_details: &ims::image::http_client::types::Image =
+        match image_details_map.entry(arch_opt.clone()) {
+          Entry::Occupied(entry) => entry.into_mut(),
+          Entry::Vacant(entry) => entry.insert(
+            get_boot_set_image(
+              shasta_token,
+              shasta_base_url,
+              shasta_root_cert,
+              socks5_proxy,
+              &path,
+              &sat_session_template.image,
+              &ref_name_processed_hashmap,
+              arch_opt.as_deref(),
+              dry_run,
+            )
+            .await?,
+          ),
+        };
 
-    let boot_sets_mapping = bos_sessiontemplate_yaml
-      .get("bos_parameters")
-      .and_then(|bos_parameters| bos_parameters.get("boot_sets"))
-      .and_then(Value::as_mapping)
-      .ok_or_else(|| {
-        Error::YamlShape(
-          "SAT file: session_template is missing 'bos_parameters.boot_sets'"
-            .to_string(),
-        )
-      })?;
-    for (parameter, boot_set) in boot_sets_mapping {
-      let kernel_parameters = boot_set
-        .get("kernel_parameters")
-        .and_then(Value::as_str)
-        .ok_or_else(|| {
-          Error::YamlShape(
-            "SAT file: boot_set is missing 'kernel_parameters'".to_string(),
-          )
-        })?;
-      let arch_opt = boot_set
-        .get("arch")
-        .and_then(Value::as_str)
-        .map(str::to_string);
-
-      let node_roles_groups_opt: Option<Vec<String>> = boot_set
-        .get("node_roles_groups")
-        .and_then(Value::as_sequence)
-        .and_then(|node_role_groups| {
-          node_role_groups
-            .iter()
-            .map(|hsm_group_value| hsm_group_value.as_str().map(str::to_string))
-            .collect()
-        });
+      tracing::debug!("Image with name '{}' found", image_details.name);
+
+      let (ims_image_path, ims_image_type, ims_image_etag) =
+        boot_image_artifacts(&boot_set_path, image_details)?;
+
+      let node_roles_groups_opt = sat_boot_set.node_roles_groups.clone();
 
       // Validate/check user can create BOS sessiontemplates based on node roles. Users
       // with tenant role are not allowed to create BOS sessiontemplates based on node roles
@@ -453,44 +502,26 @@ pub async fn process_session_template_section_in_sat_file(
         ));
       }
 
-      let node_groups_opt: Option<Vec<String>> = boot_set
-        .get("node_groups")
-        .and_then(Value::as_sequence)
-        .and_then(|node_group| {
-          node_group
-            .iter()
-            .map(|hsm_group_value| hsm_group_value.as_str().map(str::to_string))
-            .collect()
-        });
-
       // Strip site-wide group names — see `hsm::group::hacks` module
       // docs for why.
-      let node_groups_opt = node_groups_opt.map(|node_groups| {
-        hsm::group::hacks::filter_system_hsm_group_names(node_groups)
-      });
+      let node_groups_opt =
+        sat_boot_set.node_groups.clone().map(|node_groups| {
+          hsm::group::hacks::filter_system_hsm_group_names(node_groups)
+        });
+      node_group_set.extend(node_groups_opt.iter().flatten().cloned());
 
       // Validate/check HSM groups in YAML file session_templates.bos_parameters.boot_sets.<parameter>.node_groups matches with
       // Check hsm groups in SAT file includes the hsm_group_param
       for node_group in node_groups_opt.clone().unwrap_or_default() {
         if !hsm_group_available_vec.contains(&node_group) {
           return Err(Error::SatFile(format!(
-            "User does not have access to HSM group '{node_group}' in SAT file under session_templates.bos_parameters.boot_sets.compute.node_groups section. Exit"
+            "User does not have access to HSM group '{node_group}' in SAT file under '{boot_set_path}.node_groups'. Exit"
           )));
         }
       }
 
       // Validate user has access to the xnames in the BOS sessiontemplate
-      let node_list_opt: Option<Vec<String>> = boot_set
-        .get("node_list")
-        .and_then(Value::as_sequence)
-        .and_then(|node_list| {
-          node_list
-            .iter()
-            .map(|node_value_value| {
-              node_value_value.as_str().map(str::to_string)
-            })
-            .collect()
-        });
+      let node_list_opt = sat_boot_set.node_list.clone();
 
       // Validate user has access to the list of nodes in BOS sessiontemplate
       if let Some(node_list) = &node_list_opt {
@@ -511,21 +542,21 @@ pub async fn process_session_template_section_in_sat_file(
         configuration: Some(bos_session_template_configuration_name.clone()),
       };
 
-      let rootfs_provider = boot_set
-        .get("rootfs_provider")
-        .and_then(Value::as_str)
-        .map(str::to_string);
-      let rootfs_provider_passthrough = boot_set
-        .get("rootfs_provider_passthrough")
-        .and_then(Value::as_str)
-        .map(str::to_string);
+      let rootfs_provider = sat_boot_set.rootfs_provider.clone();
+      let rootfs_provider_passthrough = render_rootfs_provider_passthrough(
+        &naming_context,
+        parameter_str,
+        sat_boot_set,
+      );
 
       let boot_set = BootSet {
         name: None,
         path: Some(ims_image_path.to_string()),
         r#type: Some(ims_image_type.to_string()),
         etag: Some(ims_image_etag.to_string()),
-        kernel_parameters: Some(kernel_parameters.to_string()),
+        kernel_parameters: Some(
+          boot_set_kernel_parameters(&boot_set_path, sat_boot_set)?.to_string(),
+        ),
         node_list: node_list_opt,
         node_roles_groups: node_roles_groups_opt,
         node_groups: node_groups_opt,
@@ -535,10 +566,23 @@ pub async fn process_session_template_section_in_sat_file(
         arch: arch_opt,
       };
 
-      let parameter_str = parameter.as_str().ok_or_else(|| {
-        Error::YamlShape("SAT file: boot_set key is not a string".to_string())
-      })?;
-      boot_set_vec.insert(parameter_str.to_string(), boot_set);
+      // Catch arch / S3 artifact mismatches now rather than when the
+      // nodes fail to boot
+      validate_boot_set_against_image(parameter_str, &boot_set, image_details)?;
+
+      boot_set_vec.insert(parameter_str.clone(), boot_set);
+    }
+
+    let naming_context = naming_context.with(
+      "group",
+      node_group_set.into_iter().collect::<Vec<_>>().join(","),
+    );
+    let bos_sessiontemplate_name =
+      naming_policy.bos_template_name(&naming_context);
+    for (parameter, boot_set) in &mut boot_set_vec {
+      boot_set.name = Some(naming_policy.boot_set_name(
+        &naming_context.clone().with("boot_set", parameter.as_str()),
+      ));
     }
 
     let cfs = Cfs {
@@ -547,7 +591,9 @@ pub async fn process_session_template_section_in_sat_file(
 
     let create_bos_session_template_payload = BosSessionTemplate {
       name: None,
-      description: None,
+      description: provenance::annotate_description(Some(
+        naming_policy.bos_template_description(&naming_context),
+      )),
       enable_cfs: Some(true),
       cfs: Some(cfs),
       boot_sets: Some(boot_set_vec),
@@ -555,8 +601,14 @@ pub async fn process_session_template_section_in_sat_file(
       tenant: None,
     };
 
+    // Checked before the PUT so a rejected field is reported up front
+    SCHEMA.check(
+      &bos_sessiontemplate_name,
+      &create_bos_session_template_payload,
+    )?;
+
     if dry_run {
-      log::debug!(
+      tracing::debug!(
         "Dry run mode: Create BOS sessiontemplate:\n{}",
         serde_json::to_string_pretty(&create_bos_session_template_payload)?
       );
@@ -564,28 +616,22 @@ pub async fn process_session_template_section_in_sat_file(
       // Generate a mock name for the BOS session template
       let dry_run_bos_sessiontemplate_name =
         format!("DRYRUN_{}", Uuid::new_v4());
-      log::debug!(
+      tracing::debug!(
         "Dry Run Mode: BOS sessiontemplate name '{dry_run_bos_sessiontemplate_name}' created"
       );
       let mut mock_template = create_bos_session_template_payload.clone();
       mock_template.name = Some(dry_run_bos_sessiontemplate_name);
       bos_st_created_vec.push(mock_template);
     } else {
-      let bos_sessiontemplate = crate::ShastaClient::new(
-        shasta_base_url,
-        shasta_root_cert.to_vec(),
-        socks5_proxy.map(str::to_owned),
-      )?
-      .bos_template_v2_put(
-        shasta_token,
-        &create_bos_session_template_payload,
-        &bos_sessiontemplate_name,
-      )
-      .await?;
+      let bos_sessiontemplate = client
+        .bos_template_v2_put(
+          shasta_token,
+          &create_bos_session_template_payload,
+          &bos_sessiontemplate_name,
+        )
+        .await?;
 
-      log::debug!(
-        "BOS sessiontemplate name '{bos_sessiontemplate_name}' created"
-      );
+      events::created("BOS sessiontemplate", &bos_sessiontemplate_name);
 
       if bos_sessiontemplate.name.is_none() {
         return Err(Error::SatFile(
@@ -597,16 +643,71 @@ pub async fn process_session_template_section_in_sat_file(
   }
 
   // Create BOS session. Note: reboot operation shuts down the nodes and they may not start
-  // up... hence we will split the reboot into 2 operations shutdown and start
+  // up... `RebootStrategy::Staged` splits the reboot into 2 operations shutdown and boot
 
   if reboot {
-    log::debug!("Rebooting");
+    tracing::debug!("Rebooting");
 
     for bos_st in &bos_st_created_vec {
       let bos_st_name = bos_st.name.clone().unwrap_or_default();
-      log::debug!(
+
+      if cancel_stale_sessions {
+        let stale_session_vec = crate::bos::session::utils::cancel_stale(
+          &client,
+          shasta_token,
+          bos_st,
+          dry_run,
+        )
+        .await?;
+
+        if !stale_session_vec.is_empty() {
+          events::info(format!(
+            "Cancelled {} stale BOS session(s) targeting the nodes of BOS sessiontemplate '{bos_st_name}': {}",
+            stale_session_vec.len(),
+            stale_session_vec
+              .iter()
+              .map(|stale_session| stale_session.name.as_str())
+              .collect::<Vec<_>>()
+              .join(", ")
+          ));
+        }
+      }
+
+      if dry_run {
+        let simulation =
+          reboot::simulate(&client, shasta_token, bos_st, reboot_strategy)
+            .await?;
+        report_reboot_simulation(&simulation);
+        tracing::debug!(
+          "Dry run mode: Reboot simulation:\n{}",
+          serde_json::to_string_pretty(&simulation)?
+        );
+        continue;
+      }
+
+      if let RebootStrategy::Staged(timeouts) = reboot_strategy {
+        let staged_reboot =
+          reboot::staged_reboot(&client, shasta_token, bos_st, timeouts)
+            .await?;
+        if !staged_reboot.is_success() {
+          let boot_status = match &staged_reboot.boot_session {
+            None => "not created",
+            Some(_) if staged_reboot.boot_completed => "complete",
+            Some(_) => "not complete",
+          };
+          events::warning(format!(
+            "Staged reboot of BOS sessiontemplate '{bos_st_name}' did not succeed: {} node(s) not powered off, boot session {boot_status}",
+            staged_reboot.not_powered_off.len()
+          ));
+        }
+        bos_sessions_created.push(staged_reboot.shutdown_session);
+        bos_sessions_created.extend(staged_reboot.boot_session);
+        continue;
+      }
+
+      events::step(format!(
         "Creating BOS session for BOS sessiontemplate '{bos_st_name}' with action 'reboot'"
-      );
+      ));
 
       // BOS session v2
       let bos_session = BosSession {
@@ -621,21 +722,10 @@ pub async fn process_session_template_section_in_sat_file(
         components: None,
       };
 
-      if dry_run {
-        log::debug!(
-          "Dry run mode: Create BOS session:\n{}",
-          serde_json::to_string_pretty(&bos_session)?
-        );
-      } else {
-        let created = crate::ShastaClient::new(
-          shasta_base_url,
-          shasta_root_cert.to_vec(),
-          socks5_proxy.map(str::to_owned),
-        )?
+      let created = client
         .bos_session_v2_post(shasta_token, bos_session)
         .await?;
-        bos_sessions_created.push(created);
-      }
+      bos_sessions_created.push(created);
     }
   }
 
@@ -643,96 +733,254 @@ pub async fn process_session_template_section_in_sat_file(
   let user = common::jwt_ops::get_name(shasta_token)?;
   let username = common::jwt_ops::get_preferred_username(shasta_token)?;
 
-  log::debug!(target: "app::audit", "User: {user} ({username}) ; Operation: Apply cluster");
+  tracing::debug!(target: "app::audit", "User: {user} ({username}) ; Operation: Apply cluster");
 
   Ok((bos_st_created_vec, bos_sessions_created))
 }
 
+/// Tell the user what a dry-run reboot would do: the BOS sessions it
+/// would create, in order, and the nodes that would boot another image.
+fn report_reboot_simulation(simulation: &reboot::RebootSimulation) {
+  for (position, batch) in simulation.batches.iter().enumerate() {
+    events::info(format!(
+      "Dry run: BOS session {}/{} would run '{}' on {} node(s) of BOS sessiontemplate '{}'",
+      position + 1,
+      simulation.batches.len(),
+      batch.operation,
+      batch.xname_vec.len(),
+      simulation.template_name
+    ));
+  }
+
+  for node in simulation.image_changes() {
+    events::info(format!(
+      "Dry run: {} would boot image '{}' instead of '{}'",
+      node.xname,
+      node.target_image_id.as_deref().unwrap_or("none"),
+      node.current_image_id.as_deref().unwrap_or("none")
+    ));
+  }
+}
+
+/// IMS image booted by the boot sets with `arch` `arch_opt` of the SAT
+/// session template at YAML path `path` whose `image` is
+/// `bos_sessiontemplate_image`. In `dry_run` mode a mock image stands in
+/// for one not found in IMS.
+#[allow(clippy::too_many_arguments)]
+async fn get_boot_set_image(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  path: &str,
+  bos_sessiontemplate_image: &sessiontemplate::Image,
+  ref_name_processed_hashmap: &HashMap<String, String>,
+  arch_opt: Option<&str>,
+  dry_run: bool,
+) -> Result<ims::image::http_client::types::Image, Error> {
+  let (image_reference, is_image_id) =
+    get_image_reference_from_bos_sessiontemplate_yaml(
+      path,
+      bos_sessiontemplate_image,
+      ref_name_processed_hashmap,
+      arch_opt,
+    )?;
+  if dry_run {
+    let dry_run_mock_image = get_image_details_from_bos_sessiontemplate_yaml(
+      shasta_token,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      &image_reference,
+      is_image_id,
+    )
+    .await
+    .unwrap_or_else(|_| {
+      // In dry run mode, generate a mock image
+
+      if is_image_id {
+        // Image reference is an image ID
+        ims::image::http_client::types::Image {
+          id: Some(image_reference.clone()),
+          created: None,
+          name: "dryrun_image".to_string(),
+          link: Some(Link {
+            path: "dryrun_path".to_string(),
+            etag: Some("dryrun_etag".to_string()),
+            r#type: "dryrun_type".to_string(),
+          }),
+          arch: None,
+          metadata: None,
+        }
+      } else {
+        // Image reference is an image name
+        ims::image::http_client::types::Image {
+          id: None,
+          created: None,
+          name: image_reference.clone(),
+          link: Some(Link {
+            path: "dryrun_path".to_string(),
+            etag: Some("dryrun_etag".to_string()),
+            r#type: "dryrun_type".to_string(),
+          }),
+          arch: None,
+          metadata: None,
+        }
+      }
+    });
+
+    tracing::debug!(
+      "Dry run mode: Generate mock Image\n{}",
+      serde_json::to_string_pretty(&dry_run_mock_image)?
+    );
+
+    Ok(dry_run_mock_image)
+  } else {
+    get_image_details_from_bos_sessiontemplate_yaml(
+      shasta_token,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      &image_reference,
+      is_image_id,
+    )
+    .await
+  }
+}
+
 /// Returns image reference related to a session template in SAT file.
 /// An image refenrece can be:
 ///     - `image_name`
 ///     - `image_id`
-/// Image names are supposed to be fetched using '`get_fuzzy`' function (so we increase the probablity of finding the image in CSM if it was created using 'sat bootprep --overwrite-images') while image ids can be fetched
+/// Image names are supposed to be fetched using '`get_strict`' function (so we increase the probablity of finding the image in CSM if it was created using 'sat bootprep --overwrite-images') while image ids can be fetched
 /// by just 'get' function
 /// This function returns a tuple with the image reference and a boolean indicating whether the image is
 /// an image id or not
+/// An `image_ref` to an image built for several architectures resolves to its build for the boot
+/// set architecture `arch_opt`
+/// Errors name the offending field by its YAML path under `path`, the
+/// session template's path
 fn get_image_reference_from_bos_sessiontemplate_yaml(
-  bos_sessiontemplate_image: &Value,
+  path: &str,
+  bos_sessiontemplate_image: &sessiontemplate::Image,
   ref_name_processed_hashmap: &HashMap<String, String>,
+  arch_opt: Option<&str>,
 ) -> Result<(String, bool), Error> {
-  if let Some(bos_sessiontemplate_image_ims) =
-    bos_sessiontemplate_image.get("ims")
-  {
-    // Get boot image to configure the nodes
-    if let Some(bos_session_template_image_ims_name) =
-      bos_sessiontemplate_image_ims.get("name")
-    {
-      // BOS sessiontemplate boot image defined by name
-      let image_name = bos_session_template_image_ims_name
-        .as_str()
-        .ok_or_else(|| {
-          Error::YamlShape(
-            "SAT file: session_template image.ims.name is not a string"
-              .to_string(),
-          )
-        })?
-        .to_string();
-
-      Ok((image_name, false))
-    } else if let Some(bos_session_template_image_ims_id) =
-      bos_sessiontemplate_image_ims.get("id")
-    {
-      // BOS sessiontemplate boot image defined by id
-      let image_id = bos_session_template_image_ims_id
-        .as_str()
-        .ok_or_else(|| {
-          Error::YamlShape(
-            "SAT file: session_template image.ims.id is not a string"
-              .to_string(),
-          )
-        })?
-        .to_string();
+  match bos_sessiontemplate_image {
+    // BOS sessiontemplate boot image defined by name
+    sessiontemplate::Image::Ims {
+      ims: sessiontemplate::ImsDetails::Name { name },
+    } => Ok((name.clone(), false)),
+    // BOS sessiontemplate boot image defined by id
+    sessiontemplate::Image::Ims {
+      ims: sessiontemplate::ImsDetails::Id { id },
+    } => Ok((id.clone(), true)),
+    // BOS sessiontemplate boot image defined by image_ref
+    sessiontemplate::Image::ImageRef { image_ref } => {
+      // Build of a multi-arch image for the boot set architecture
+      let arch_image_ref_opt = arch_opt
+        .and_then(image::Arch::from_boot_set_arch)
+        .map(|arch| format!("{image_ref}-{}", arch.ims_arch()));
+
+      let image_id = ref_name_processed_hashmap
+        .get(image_ref)
+        .or_else(|| {
+          arch_image_ref_opt.as_ref().and_then(|arch_image_ref| {
+            ref_name_processed_hashmap.get(arch_image_ref)
+          })
+        })
+        .cloned()
+        .ok_or_else(|| Error::SatFileField {
+          path: format!("{path}.image.image_ref"),
+          detail: format!(
+            "'{image_ref}' not found in processed image set (boot sets booting an image built for several architectures need an 'arch')"
+          ),
+        })?;
 
       Ok((image_id, true))
-    } else {
-      Err(Error::SatFile("neither 'image.ims.name' nor 'image.ims.id' fields defined in session_template.".to_string()))
     }
-  } else if let Some(bos_session_template_image_image_ref) =
-    bos_sessiontemplate_image.get("image_ref")
-  {
-    // BOS sessiontemplate boot image defined by image_ref
-    let image_ref = bos_session_template_image_image_ref
-      .as_str()
-      .ok_or_else(|| {
-        Error::YamlShape(
-          "SAT file: session_template image.image_ref is not a string"
-            .to_string(),
-        )
-      })?
-      .to_string();
-
-    let image_id = ref_name_processed_hashmap
-      .get(&image_ref)
-      .cloned()
-      .ok_or_else(|| {
-        Error::YamlShape(format!(
-          "SAT file: image_ref '{image_ref}' not found in processed image set"
-        ))
-      })?;
-
-    Ok((image_id, true))
-  } else if let Some(image_name_substring) = bos_sessiontemplate_image.as_str()
-  {
-    let image_name = image_name_substring;
     // Backward compatibility
-    // Get base image details
-
-    Ok((image_name.to_string(), false))
-  } else {
-    Err(Error::SatFile("neither 'image.ims' nor 'image.image_ref' nor 'image.<image id>' sections found in session_template.image.\nExit".to_string()))
+    sessiontemplate::Image::ImageName(image_name) => {
+      Ok((image_name.clone(), false))
+    }
   }
 }
 
+/// S3 `path`, `type` and `etag` of the manifest of `image`, booted by
+/// the boot set at YAML path `path`.
+pub(super) fn boot_image_artifacts<'a>(
+  path: &str,
+  image: &'a ims::image::http_client::types::Image,
+) -> Result<(&'a str, &'a str, &'a str), Error> {
+  let image_link = image.link.as_ref().ok_or_else(|| {
+    Error::SatFile(format!(
+      "IMS image '{}' booted by '{path}' has no 'link' (no S3 manifest)",
+      image.name
+    ))
+  })?;
+  let etag = image_link.etag.as_deref().ok_or_else(|| {
+    Error::SatFile(format!(
+      "IMS image '{}' booted by '{path}' has no 'link.etag'",
+      image.name
+    ))
+  })?;
+
+  Ok((image_link.path.as_str(), image_link.r#type.as_str(), etag))
+}
+
+/// YAML path of boot set `boot_set_name` of `sat_session_template`.
+pub(super) fn sat_boot_set_path(
+  sat_session_template: &sessiontemplate::SessionTemplate,
+  boot_set_name: &str,
+) -> String {
+  format!(
+    "session_templates[{}].bos_parameters.boot_sets.{boot_set_name}",
+    sat_session_template.name
+  )
+}
+
+/// `rootfs_provider_passthrough` of boot set `boot_set_name` with the
+/// naming variables of `naming_context` replaced, see
+/// [`crate::NamingPolicy`]. `{group}` is the boot set's own
+/// `node_groups`.
+pub(super) fn render_rootfs_provider_passthrough(
+  naming_context: &NamingContext,
+  boot_set_name: &str,
+  boot_set: &sessiontemplate::BootSet,
+) -> Option<String> {
+  boot_set.rootfs_provider_passthrough.as_deref().map(
+    |rootfs_provider_passthrough| {
+      naming_context
+        .clone()
+        .with("boot_set", boot_set_name)
+        .with(
+          "group",
+          boot_set
+            .node_groups
+            .as_deref()
+            .unwrap_or_default()
+            .join(","),
+        )
+        .render(rootfs_provider_passthrough)
+    },
+  )
+}
+
+/// Kernel parameters of the boot set at YAML path `path`, which BOS
+/// requires.
+pub(super) fn boot_set_kernel_parameters<'a>(
+  path: &str,
+  boot_set: &'a sessiontemplate::BootSet,
+) -> Result<&'a str, Error> {
+  boot_set
+    .kernel_parameters
+    .as_deref()
+    .ok_or_else(|| Error::SatFileField {
+      path: format!("{path}.kernel_parameters"),
+      detail: "is missing".to_string(),
+    })
+}
+
 async fn get_image_details_from_bos_sessiontemplate_yaml(
   shasta_token: &str,
   shasta_base_url: &str,
@@ -742,7 +990,7 @@ async fn get_image_details_from_bos_sessiontemplate_yaml(
   is_image_id: bool,
 ) -> Result<ims::image::http_client::types::Image, Error> {
   if is_image_id {
-    crate::ShastaClient::new(
+    crate::ShastaClient::scoped_or_new(
       shasta_base_url,
       shasta_root_cert.to_vec(),
       socks5_proxy.map(str::to_owned),
@@ -756,207 +1004,269 @@ async fn get_image_details_from_bos_sessiontemplate_yaml(
         .ok_or_else(|| Error::ImageNotFound(image_reference.to_string()))
     })
   } else {
-    ims::image::utils::try_get_by_name(
+    ims::image::utils::get_strict(
       shasta_token,
       shasta_base_url,
       shasta_root_cert,
       socks5_proxy,
       image_reference,
-      Some(&1),
     )
     .await
-    .and_then(|image_vec| {
-      image_vec
-        .first()
-        .cloned()
-        .ok_or_else(|| Error::ImageNotFound(image_reference.to_string()))
-    })
   }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Id of the base image of SAT image `image_yaml`, built first if it
+/// is a recipe; see [`ImageBaseResolver`].
 pub(super) async fn get_base_image_id_from_sat_file_image_yaml(
-  shasta_token: &str,
-  shasta_base_url: &str,
-  shasta_root_cert: &[u8],
-  socks5_proxy: Option<&str>,
-  // image_yaml: &Value,
+  build_context: &ImageBuildContext<'_>,
   image_yaml: &image::Image,
-  _ref_name_image_id_hashmap: &HashMap<String, String>,
-  cray_product_catalog: &BTreeMap<String, String>,
-  image_name: &str,
-  dry_run: bool,
+  ref_name_image_id_hashmap: &HashMap<String, String>,
 ) -> Result<String, Error> {
-  // Get/process base image
-  // if let Some(sat_file_image_ims_value_yaml) = image_yaml.get("ims") {
-  let base_image_id: String = if let image::BaseOrIms::Ims { ims } =
-    &image_yaml.base_or_ims
-  {
-    // ----------- BASE IMAGE - BACKWARD COMPATIBILITY WITH PREVIOUS SAT FILE
-    log::debug!(
-      "SAT file - 'image.ims' job ('images' section in SAT file is outdated - switching to backward compatibility)"
+  let context = ImageBaseContext {
+    shasta_token: build_context.shasta_token,
+    shasta_base_url: build_context.shasta_base_url,
+    shasta_root_cert: build_context.shasta_root_cert,
+    socks5_proxy: build_context.socks5_proxy,
+    cray_product_catalog: build_context.cray_product_catalog,
+    ref_name_image_id_hashmap,
+    image_name: &image_yaml.name,
+    template_dictionary_opt: image_yaml.template_dictionary.as_ref(),
+    arch_opt: image_yaml.arch,
+    dry_run: build_context.dry_run,
+    ims_job_wait: build_context.ims_job_wait,
+    ims_public_key_selector: build_context.ims_public_key_selector,
+  };
+
+  ImageBaseResolver::from_image(image_yaml)?
+    .resolve(&context)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_yaml::Value;
+
+  use super::*;
+
+  const SESSION_TEMPLATE: &str = r"
+name: compute
+image:
+  image_ref: compute-image
+configuration: compute-config
+bos_parameters:
+  boot_sets:
+    compute:
+      arch: X86
+      kernel_parameters: ip=dhcp quiet
+      node_groups:
+        - zinal
+      node_list:
+        - x1000c0s0b0n0
+      node_roles_groups:
+        - Compute
+      rootfs_provider: sbps
+      rootfs_provider_passthrough: sbps:v1:iqn.2023-06.csm.iscsi:_sbps-hsn._tcp
+";
+
+  fn parse(yaml: &str) -> Result<sessiontemplate::SessionTemplate, Error> {
+    Ok(serde_yaml::from_str(yaml)?)
+  }
+
+  /// Everything [`process_session_template_section_in_sat_file`] checks
+  /// before talking to CSM.
+  fn check(
+    session_template: &sessiontemplate::SessionTemplate,
+  ) -> Result<(), Error> {
+    for (boot_set_name, boot_set) in &session_template.bos_parameters.boot_sets
+    {
+      boot_set_kernel_parameters(
+        &sat_boot_set_path(session_template, boot_set_name),
+        boot_set,
+      )?;
+    }
+    get_image_reference_from_bos_sessiontemplate_yaml(
+      "session_templates[t]",
+      &session_template.image,
+      &HashMap::from([("compute-image".to_string(), "4e1b".to_string())]),
+      None,
+    )
+    .map(|_| ())
+  }
+
+  #[test]
+  fn session_template_round_trips() {
+    let session_template = parse(SESSION_TEMPLATE).unwrap();
+
+    let boot_set = &session_template.bos_parameters.boot_sets["compute"];
+    assert_eq!(boot_set.arch, Some(sessiontemplate::Arch::X86));
+    assert_eq!(
+      boot_set.node_roles_groups,
+      Some(vec!["Compute".to_string()])
     );
 
-    process_sat_file_image_old_version_struct(ims)?
-  // } else if let Some(sat_file_image_base_value_yaml) = image_yaml.get("base") {
-  } else if let image::BaseOrIms::Base { base } = &image_yaml.base_or_ims {
-    /* if let Some(sat_file_image_base_image_ref_value_yaml) =
-      sat_file_image_base_value_yaml.get("image_ref")
-    { */
-    if let image::Base::ImageRef { image_ref } = base {
-      log::debug!("SAT file - 'image.base.image_ref' job");
-
-      image_ref.clone()
-    /* } else if let Some(sat_file_image_base_ims_value_yaml) =
-      sat_file_image_base_value_yaml.get("ims")
-    { */
-    } else if let image::Base::Ims { ims } = base {
-      if let image::ImageBaseIms::NameType { name, r#type } = ims {
-        log::debug!("SAT file - 'image.base.ims' job");
-        if r#type == "recipe" {
-          log::debug!("SAT file - 'image.base.ims' job of type 'recipe'");
-
-          process_sat_file_image_ims_type_recipe(
-            shasta_token,
-            shasta_base_url,
-            shasta_root_cert,
-            socks5_proxy,
-            name,
-            image_name,
-            dry_run,
-          )
-          .await?
-        } else {
-          return Err(Error::SatFile(
-            "Can't process SAT file 'images.base.ims' is missing. Exit"
-              .to_string(),
-          ));
-        }
-      } else if let image::Base::Ims { ims } = base {
-        #[allow(clippy::collapsible_match)]
-        if let image::ImageBaseIms::IdType { id, r#type } = ims {
-          if r#type == "image" {
-            log::debug!("SAT file - 'image.base.ims' job of type 'image'");
-
-            id.clone()
-          } else {
-            return Err(Error::SatFile(
-              "Can't process SAT file 'images.base.ims' is missing. Exit"
-                .to_string(),
-            ));
-          }
-        } else {
-          return Err(Error::SatFile(
-            "Can't process SAT file 'images.base.ims' is missing. Exit"
-              .to_string(),
-          ));
-        }
-      } else {
-        return Err(Error::SatFile(
-          "Can't process SAT file 'images.base.ims' is missing. Exit"
-            .to_string(),
-        ));
-      }
-    // ----------- BASE IMAGE - CRAY PRODUCT CATALOG
-    /* } else if let Some(sat_file_image_base_product_value_yaml) =
-      sat_file_image_base_value_yaml.get("product")
-    { */
-    } else if let image::Base::Product { product } = base {
-      log::debug!("SAT file - 'image.base.product' job");
-      // Base image created from a cray product
-      let product_name = &product.name;
-
-      let product_version = product.version.as_ref().ok_or_else(|| {
-        Error::YamlShape(format!(
-          "SAT file: image base.product '{product_name}' is missing 'version'"
-        ))
-      })?;
-
-      let product_type = &product.r#type;
-
-      let product_image_map = serde_yaml::from_str::<serde_json::Value>(
-        &cray_product_catalog[product_name],
-      )?[product_version][product_type]
-        .as_object()
-        .cloned()
-        .ok_or_else(|| {
-          Error::SatFile(format!(
-            "Cray product catalog: '{product_name}.{product_version}.{product_type}' is missing or not an object"
-          ))
-        })?;
+    let yaml = serde_yaml::to_string(&session_template).unwrap();
+    assert!(yaml.contains("node_roles_groups"));
+    assert_eq!(parse(&yaml).unwrap(), session_template);
+  }
 
-      let image_id = if let Some(filter) = product.filter.as_ref() {
-        filter_product_catalog_images(
-          filter,
-          product_image_map.clone(),
-          image_name,
-        )?
-      } else {
-        // There is no 'image.product.filter' value defined in SAT file. Check Cray
-        // product catalog only has 1 image. Othewise fail
-        log::debug!(
-          "No 'image.product.filter' defined in SAT file. Checking Cray product catalog only/must have 1 image"
-        );
-        product_image_map
-          .values()
-          .next()
-          .and_then(|value| value.get("id"))
-          .and_then(serde_json::Value::as_str)
-          .map(str::to_string)
-          .ok_or_else(|| {
-            Error::SatFile(format!(
-              "Cray product catalog: '{product_name}.{product_version}.{product_type}' has no entries with an 'id' field"
-            ))
-          })?
-      };
+  #[test]
+  fn session_template_images_round_trip() {
+    for image in [
+      "ims: {name: compute-image}",
+      "ims: {id: 4e1b}",
+      "image_ref: compute-image",
+      "compute-image",
+    ] {
+      let yaml = SESSION_TEMPLATE.replace(
+        "image:\n  image_ref: compute-image",
+        &format!("image: {image}"),
+      );
+      let session_template = parse(&yaml).unwrap();
 
-      // ----------- BASE IMAGE - CRAY PRODUCT CATALOG TYPE RECIPE
-      if product_type == "recipes" {
-        // Create base image from an IMS job (the 'id' field in
-        // images[].base.product.id is the id of the IMS recipe used to
-        // build the new base image)
+      let round_tripped =
+        parse(&serde_yaml::to_string(&session_template).unwrap()).unwrap();
+      assert_eq!(round_tripped, session_template, "image: {image}");
+    }
+  }
 
-        log::debug!("SAT file - 'image.base.product' job based on IMS recipes");
+  #[test]
+  fn node_roles_group_spelling_is_still_read() {
+    let session_template =
+      parse(&SESSION_TEMPLATE.replace("node_roles_groups", "node_roles_group"))
+        .unwrap();
 
-        let product_recipe_id = image_id.clone();
+    assert_eq!(
+      session_template.bos_parameters.boot_sets["compute"].node_roles_groups,
+      Some(vec!["Compute".to_string()])
+    );
+  }
 
-        process_sat_file_image_product_type_ims_recipe(
-          shasta_token,
-          shasta_base_url,
-          shasta_root_cert,
-          socks5_proxy,
-          &product_recipe_id,
-          image_name,
-          dry_run,
-        )
-        .await?
+  #[test]
+  fn malformed_session_templates_are_rejected() {
+    let boot_sets = |boot_sets: &str| {
+      format!(
+        "{{name: t, image: x, configuration: c, bos_parameters: {{boot_sets: {boot_sets}}}}}"
+      )
+    };
+    let corpus = [
+      ("{image: x, configuration: c}".to_string(), "name"),
+      ("{name: t, image: x}".to_string(), "configuration"),
+      ("{name: t, configuration: c}".to_string(), "image"),
+      (
+        "{name: t, image: x, configuration: c}".to_string(),
+        "bos_parameters",
+      ),
+      (
+        "{name: t, image: x, configuration: c, bos_parameters: {}}".to_string(),
+        "boot_sets",
+      ),
+      (boot_sets("[a]"), "boot_sets"),
+      (
+        boot_sets("{compute: {kernel_parameters: k, node_groups: zinal}}"),
+        "node_groups",
+      ),
+      (
+        boot_sets(
+          "{compute: {kernel_parameters: k, node_list: [x1000c0s0b0n0, {a: b}]}}",
+        ),
+        "node_list",
+      ),
+    ];
+
+    for (yaml, field) in corpus {
+      let error = parse(&yaml).unwrap_err().to_string();
+      assert!(error.contains(field), "SAT file: {yaml}\nerror: {error}");
+    }
+  }
 
-        // ----------- BASE IMAGE - CRAY PRODUCT CATALOG TYPE IMAGE
-      } else if product_type == "images" {
-        // Base image already created and its id is available in the Cray
-        // product catalog
+  #[test]
+  fn missing_kernel_parameters_report_field_path() {
+    let session_template = parse(
+      "{name: t, image: x, configuration: c, bos_parameters: {boot_sets: {compute: {}}}}",
+    )
+    .unwrap();
+
+    match check(&session_template) {
+      Err(Error::SatFileField { path, .. }) => assert_eq!(
+        path,
+        "session_templates[t].bos_parameters.boot_sets.compute.kernel_parameters"
+      ),
+      other => panic!("expected a SAT file field error, got {other:?}"),
+    }
+  }
 
-        log::debug!("SAT file - 'image.base.product' job based on IMS images");
+  #[test]
+  fn unknown_image_ref_reports_field_path() {
+    let session_template = parse(
+      &SESSION_TEMPLATE.replace("image_ref: compute-image", "image_ref: other"),
+    )
+    .unwrap();
 
-        log::debug!("Getting base image id from Cray product catalog");
+    match check(&session_template) {
+      Err(Error::SatFileField { path, .. }) => {
+        assert_eq!(path, "session_templates[t].image.image_ref");
+      }
+      other => panic!("expected a SAT file field error, got {other:?}"),
+    }
+  }
 
-        image_id
-      } else {
-        return Err(Error::SatFile(
-          "Can't process SAT file, field 'images.base.product.type' must be either 'images' or 'recipes'. Exit".to_string(),
-        ));
+  /// Every field of a well-formed template, removed or replaced by a
+  /// value of another type, is rejected or processed, never a panic.
+  #[test]
+  fn mutated_session_templates_never_panic() {
+    fn mutants(value: &Value) -> Vec<Value> {
+      let replacements = [
+        Value::Null,
+        Value::Bool(true),
+        Value::Number(1.into()),
+        Value::String("x".to_string()),
+        Value::Sequence(vec![Value::Null]),
+        Value::Mapping(serde_yaml::Mapping::new()),
+      ];
+
+      let mut mutant_vec = Vec::new();
+      match value {
+        Value::Mapping(mapping) => {
+          for (key, field) in mapping {
+            let mut without = mapping.clone();
+            without.remove(key);
+            mutant_vec.push(Value::Mapping(without));
+
+            for mutated_field in
+              replacements.iter().cloned().chain(mutants(field))
+            {
+              let mut mutant = mapping.clone();
+              mutant.insert(key.clone(), mutated_field);
+              mutant_vec.push(Value::Mapping(mutant));
+            }
+          }
+        }
+        Value::Sequence(sequence) => {
+          for (index, item) in sequence.iter().enumerate() {
+            for mutated_item in
+              replacements.iter().cloned().chain(mutants(item))
+            {
+              let mut mutant = sequence.clone();
+              mutant[index] = mutated_item;
+              mutant_vec.push(Value::Sequence(mutant));
+            }
+          }
+        }
+        _ => {}
       }
-    } else {
-      return Err(Error::SatFile(
-        "Can't process SAT file 'images.base.product' is missing. Exit"
-          .to_string(),
-      ));
+      mutant_vec
     }
-  } else {
-    return Err(Error::SatFile(
-      "Can't process SAT file 'images.base' is missing. Exit".to_string(),
-    ));
-  };
 
-  Ok(base_image_id)
+    let value: Value = serde_yaml::from_str(SESSION_TEMPLATE).unwrap();
+    let mutant_vec = mutants(&value);
+    assert!(mutant_vec.len() > 100);
+
+    for mutant in mutant_vec {
+      if let Ok(session_template) =
+        serde_yaml::from_value::<sessiontemplate::SessionTemplate>(mutant)
+      {
+        let _ = check(&session_template);
+      }
+    }
+  }
 }
@@ -7,7 +7,7 @@ use strum_macros::Display;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct SessionTemplate {
   pub name: String,
   pub image: Image,
@@ -15,14 +15,14 @@ pub struct SessionTemplate {
   pub bos_parameters: BosParamters,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(untagged)] // <-- this is important. More info https://serde.rs/enum-representations.html#untagged
 pub enum ImsDetails {
   Name { name: String },
   Id { id: String },
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(untagged)] // <-- this is important. More info https://serde.rs/enum-representations.html#untagged
 pub enum Image {
   Ims { ims: ImsDetails },
@@ -30,12 +30,12 @@ pub enum Image {
   ImageName(String),
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct BosParamters {
   pub boot_sets: HashMap<String, BootSet>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct BootSet {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub arch: Option<Arch>,
@@ -45,8 +45,9 @@ pub struct BootSet {
   pub network: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub node_list: Option<Vec<String>>,
-  #[serde(skip_serializing_if = "Option::is_none")]
-  pub node_roles_group: Option<Vec<String>>,
+  // Older SAT files spell it 'node_roles_group'
+  #[serde(skip_serializing_if = "Option::is_none", alias = "node_roles_group")]
+  pub node_roles_groups: Option<Vec<String>>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub node_groups: Option<Vec<String>>,
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -55,7 +56,9 @@ pub struct BootSet {
   pub rootfs_provider_passthrough: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Display)]
+#[derive(
+  Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq, Eq,
+)]
 pub enum Arch {
   X86,
   ARM,
@@ -0,0 +1,171 @@
+//! Outcome of validating a SAT file.
+//!
+//! The `validate_sat_file_*` section validators record every problem
+//! they find in a [`ValidationReport`] instead of returning on the
+//! first one, so a SAT file can be fixed in one pass. Each
+//! [`ValidationIssue`] points at the offending entry with a path such
+//! as `images[compute].configuration`.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::error::Error;
+
+/// How serious a [`ValidationIssue`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  /// The SAT file can't be applied as is.
+  Error,
+  /// The SAT file can be applied, but part of it wasn't checked or may
+  /// not do what is intended.
+  Warning,
+}
+
+/// One problem found in a SAT file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+  /// How serious the problem is.
+  pub severity: Severity,
+  /// Section and field the problem is about, e.g.
+  /// `session_templates[compute].bos_parameters.boot_sets.gpu.node_groups`.
+  pub path: String,
+  /// What is wrong.
+  pub message: String,
+}
+
+/// Every problem found while validating a SAT file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+  issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+  /// Empty report.
+  #[must_use]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Record an error at `path`.
+  pub fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+    self.push(Severity::Error, path.into(), message.into());
+  }
+
+  /// Record a warning at `path`.
+  pub fn warning(
+    &mut self,
+    path: impl Into<String>,
+    message: impl Into<String>,
+  ) {
+    self.push(Severity::Warning, path.into(), message.into());
+  }
+
+  fn push(&mut self, severity: Severity, path: String, message: String) {
+    self.issues.push(ValidationIssue {
+      severity,
+      path,
+      message,
+    });
+  }
+
+  /// Append the issues of `other`.
+  pub fn merge(&mut self, other: Self) {
+    self.issues.extend(other.issues);
+  }
+
+  /// Every issue, in the order found.
+  #[must_use]
+  pub fn issues(&self) -> &[ValidationIssue] {
+    &self.issues
+  }
+
+  /// Issues of severity [`Severity::Error`].
+  pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+    self
+      .issues
+      .iter()
+      .filter(|issue| issue.severity == Severity::Error)
+  }
+
+  /// Issues of severity [`Severity::Warning`].
+  pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+    self
+      .issues
+      .iter()
+      .filter(|issue| issue.severity == Severity::Warning)
+  }
+
+  /// Whether any error was found.
+  #[must_use]
+  pub fn has_errors(&self) -> bool {
+    self.errors().next().is_some()
+  }
+
+  /// Whether no issue at all was found.
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.issues.is_empty()
+  }
+
+  /// Whether the SAT file may be applied: never with errors, and with
+  /// warnings only if `proceed_on_warnings`.
+  #[must_use]
+  pub fn can_proceed(&self, proceed_on_warnings: bool) -> bool {
+    !self.has_errors()
+      && (proceed_on_warnings || self.warnings().next().is_none())
+  }
+
+  /// `Ok(self)` if [`Self::can_proceed`], the report as an error
+  /// otherwise.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::SatFileValidation`] listing every issue.
+  pub fn into_result(self, proceed_on_warnings: bool) -> Result<Self, Error> {
+    if self.can_proceed(proceed_on_warnings) {
+      Ok(self)
+    } else {
+      Err(Error::SatFileValidation(self.to_string()))
+    }
+  }
+}
+
+impl fmt::Display for ValidationReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (index, issue) in self.issues.iter().enumerate() {
+      if index > 0 {
+        writeln!(f)?;
+      }
+      let severity = match issue.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+      };
+      write!(f, "{severity}: {}: {}", issue.path, issue.message)?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn warnings_only_proceed_when_allowed() {
+    let mut report = ValidationReport::new();
+    report.warning("images[compute].base.ims", "not checked");
+
+    assert!(report.can_proceed(true));
+    assert!(!report.can_proceed(false));
+
+    report.error("images[compute].configuration", "not found");
+    assert!(!report.can_proceed(true));
+    assert_eq!(
+      report.clone().into_result(true).unwrap_err().to_string(),
+      "CSM-RS > SAT file validation failed:\nwarning: images[compute].base.ims: not checked\nerror: images[compute].configuration: not found"
+    );
+  }
+}
@@ -1,25 +1,261 @@
 //! Back up a BOS session template and its dependencies (CFS/HSM/IMS) to disk.
+//!
+//! A backup is a directory:
+//!
+//! ```text
+//! <destination>/
+//!   backup-manifest.json      // BackupManifest: what is in the backup
+//!   <template>.json           // BOS session template
+//!   <template>-hsm.json       // HSM groups targeted by the template
+//!   <configuration>.json      // CFS configuration of the template
+//!   <image id>-ims.json       // IMS image record, one per boot image
+//!   <image id>/               // image artifacts downloaded from S3
+//!     manifest.json
+//!     initrd
+//!     kernel
+//!     rootfs
+//! ```
+//!
+//! The manifest records the md5 checksum of every file, so
+//! [`crate::commands::migrate_restore`] can check the backup is complete
+//! and intact before it writes anything.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use humansize::DECIMAL;
+use serde::{Deserialize, Serialize};
 
 use crate::commands::migrate_restore;
+use crate::common::events;
 use crate::error::Error;
 use crate::{bos, ims};
-use humansize::DECIMAL;
-use std::fs::File;
-use std::path::Path;
 
-// use crate::commands::i_migrate_restore;
+/// Name of the manifest file in a backup directory.
+pub const BACKUP_MANIFEST_FILE_NAME: &str = "backup-manifest.json";
+
+/// Format version written to new manifests. [`BackupManifest::read`]
+/// rejects manifests with any other version.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// S3 bucket holding the boot image artifacts.
+const BOOT_IMAGE_BUCKET: &str = "boot-images";
+
+/// Files of a boot image in [`BOOT_IMAGE_BUCKET`].
+pub(crate) const IMAGE_ARTIFACT_FILE_NAMES: [&str; 4] =
+  ["manifest.json", "initrd", "kernel", "rootfs"];
+
+/// What a file of a backup holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupFileKind {
+  /// BOS session template.
+  BosSessionTemplate,
+  /// CFS configuration.
+  CfsConfiguration,
+  /// HSM groups.
+  HsmGroups,
+  /// IMS image record.
+  ImsImage,
+  /// Boot image artifact (S3 manifest, initrd, kernel or rootfs).
+  ImageArtifact,
+}
+
+/// One file of a backup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupFile {
+  /// What the file holds.
+  pub kind: BackupFileKind,
+  /// Path relative to the backup directory.
+  pub path: String,
+  /// md5 checksum, lowercase hex.
+  pub md5: String,
+  /// IMS image the file belongs to, for IMS image records and image
+  /// artifacts.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub image_id: Option<String>,
+}
+
+/// One boot image of a backup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupImage {
+  /// IMS image id on the source system.
+  pub id: String,
+  /// IMS image name.
+  pub name: String,
+}
+
+/// Content of a backup directory, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+  /// Manifest format version, see [`BACKUP_FORMAT_VERSION`].
+  pub version: u32,
+  /// When the backup was taken.
+  pub created_at: DateTime<Utc>,
+  /// Name of the BOS session template backed up.
+  pub bos_session_template: String,
+  /// Name of the CFS configuration of the template.
+  pub cfs_configuration: String,
+  /// HSM groups targeted by the template, sorted.
+  #[serde(default)]
+  pub hsm_groups: Vec<String>,
+  /// Boot images of the template.
+  #[serde(default)]
+  pub images: Vec<BackupImage>,
+  /// Every file of the backup except the manifest itself.
+  #[serde(default)]
+  pub files: Vec<BackupFile>,
+}
+
+impl BackupManifest {
+  /// Files holding `kind`.
+  pub fn files_of(
+    &self,
+    kind: BackupFileKind,
+  ) -> impl Iterator<Item = &BackupFile> {
+    self.files.iter().filter(move |file| file.kind == kind)
+  }
+
+  /// Write the manifest to `backup_dir`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::IoError`] if the file can't be written.
+  pub fn write(&self, backup_dir: &Path) -> Result<(), Error> {
+    std::fs::write(
+      backup_dir.join(BACKUP_MANIFEST_FILE_NAME),
+      serde_json::to_string_pretty(self)?,
+    )?;
+    Ok(())
+  }
+
+  /// Read the manifest of `backup_dir`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::IoError`] if the file can't be read, and
+  /// [`Error::MigrateOp`] if it isn't a manifest or was written in an
+  /// unsupported format version.
+  pub fn read(backup_dir: &Path) -> Result<Self, Error> {
+    Self::from_json(&std::fs::read_to_string(
+      backup_dir.join(BACKUP_MANIFEST_FILE_NAME),
+    )?)
+  }
+
+  fn from_json(json: &str) -> Result<Self, Error> {
+    let value: serde_json::Value = serde_json::from_str(json)
+      .map_err(|e| Error::MigrateOp(format!("backup manifest: {e}")))?;
+
+    // Checked before parsing the rest, whose shape depends on it
+    let version = value
+      .get("version")
+      .and_then(serde_json::Value::as_u64)
+      .ok_or_else(|| {
+        Error::MigrateOp(
+          "backup manifest: 'version' missing or not a number".to_string(),
+        )
+      })?;
+    if version != u64::from(BACKUP_FORMAT_VERSION) {
+      return Err(Error::MigrateOp(format!(
+        "backup manifest: unsupported format version {version}, expected {BACKUP_FORMAT_VERSION}"
+      )));
+    }
+
+    serde_json::from_value(value)
+      .map_err(|e| Error::MigrateOp(format!("backup manifest: {e}")))
+  }
+
+  /// Check every file of the manifest is in `backup_dir` with the
+  /// recorded checksum.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::MigrateOp`] listing the files missing or
+  /// modified.
+  pub fn verify(&self, backup_dir: &Path) -> Result<(), Error> {
+    let mut problem_vec = Vec::new();
+
+    for file in &self.files {
+      let path = backup_dir.join(&file.path);
+      if !path.is_file() {
+        problem_vec.push(format!("{} is missing", file.path));
+        continue;
+      }
+      let md5 = format!("{:x}", migrate_restore::file_md5sum(path)?);
+      if md5 != file.md5 {
+        problem_vec.push(format!(
+          "{} has checksum {md5}, expected {}",
+          file.path, file.md5
+        ));
+      }
+    }
+
+    if problem_vec.is_empty() {
+      Ok(())
+    } else {
+      Err(Error::MigrateOp(format!(
+        "backup in {} is incomplete or modified: {}",
+        backup_dir.display(),
+        problem_vec.join("; ")
+      )))
+    }
+  }
+}
+
+/// Record `path` (relative to `backup_dir`) in `manifest` with its
+/// checksum.
+fn add_file(
+  manifest: &mut BackupManifest,
+  backup_dir: &Path,
+  kind: BackupFileKind,
+  path: String,
+  image_id: Option<&str>,
+) -> Result<(), Error> {
+  let md5 = format!(
+    "{:x}",
+    migrate_restore::file_md5sum(backup_dir.join(&path))?
+  );
+
+  manifest.files.push(BackupFile {
+    kind,
+    path,
+    md5,
+    image_id: image_id.map(str::to_string),
+  });
+
+  Ok(())
+}
+
+/// Write `value` as pretty-printed JSON to `backup_dir/path` and record
+/// it in `manifest`.
+fn write_json_file<T: Serialize>(
+  manifest: &mut BackupManifest,
+  backup_dir: &Path,
+  kind: BackupFileKind,
+  path: String,
+  image_id: Option<&str>,
+  value: &T,
+) -> Result<(), Error> {
+  let file_path = backup_dir.join(&path);
+  std::fs::write(&file_path, serde_json::to_string_pretty(value)?)?;
+  events::created("Backup file", file_path.to_string_lossy());
+
+  add_file(manifest, backup_dir, kind, path, image_id)
+}
 
 /// Back up one BOS session template — plus the IMS image artefacts and
 /// CFS/HSM metadata it references — to a local directory.
 ///
-/// Produces a self-contained on-disk snapshot that
-/// [`crate::commands::migrate_restore::exec`] can re-import on another
-/// system.
+/// Produces a self-contained on-disk backup, laid out as described in
+/// the [module docs](self), that
+/// [`crate::commands::migrate_restore::exec_from_backup`] can restore
+/// on another system.
 ///
 /// # Arguments
 ///
 /// - `bos` — name of the BOS session template to back up. Required.
-/// - `destination` — directory to write the archive into; created if
+/// - `destination` — directory to write the backup into; created if
 ///   missing. Required.
 ///
 /// # Errors
@@ -34,9 +270,9 @@ pub async fn exec(
   socks5_proxy: Option<&str>,
   bos: Option<&str>,
   destination: Option<&str>,
-  /* prehook: Option<&String>,
-  posthook: Option<&String>, */
-) -> Result<(), Error> {
+) -> Result<BackupManifest, Error> {
+  let _timer = crate::common::metrics::CommandTimer::start("migrate_backup");
+
   let bos = bos.ok_or_else(|| {
     Error::MigrateOp("Error, --bos argument is required.".to_string())
   })?;
@@ -44,255 +280,278 @@ pub async fn exec(
     Error::MigrateOp("Error, --destination argument is required.".to_string())
   })?;
 
-  let dest_path = Path::new(destination);
-  let bucket_name = "boot-images";
-  let files2download = ["manifest.json", "initrd", "kernel", "rootfs"];
-  let files2download_count = files2download.len() + 4; // manifest.json, initrd, kernel, rootfs, bos, cfs, hsm, ims
+  let backup_dir = Path::new(destination);
   log::debug!("Create directory '{destination}'");
-  std::fs::create_dir_all(dest_path).map_err(|e| {
+  std::fs::create_dir_all(backup_dir).map_err(|e| {
     Error::MigrateOp(format!(
       "Unable to create directory {}: {}",
-      dest_path.to_string_lossy(),
+      backup_dir.to_string_lossy(),
       e
     ))
   })?;
-  let bos_file_name = String::from(bos) + ".json";
-  let bos_file_path = dest_path.join(bos_file_name);
-
-  let hsm_file_name = String::from(bos) + "-hsm.json";
-  let hsm_file_path = dest_path.join(hsm_file_name);
 
-  let _empty_hsm_group_name: Vec<String> = Vec::new();
-  let mut bos_templates = crate::ShastaClient::new(
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
-  )?
-  .bos_template_v2_get(shasta_token, Some(bos))
-  .await?;
+  )?;
+
+  // BOS ------------------------------------------------------------------------------------
+  events::step(format!("Backing up BOS session template {bos}"));
+
+  let mut bos_template_vec = shasta_client
+    .bos_template_v2_get(shasta_token, Some(bos))
+    .await?;
 
   let _ =
-    bos::template::utils::filter(&mut bos_templates, None, &[], &[], None);
+    bos::template::utils::filter(&mut bos_template_vec, None, &[], &[], None);
+
+  // We don't expect other BOS templates in the array
+  let bos_template = bos_template_vec
+    .into_iter()
+    .next()
+    .ok_or_else(|| Error::MigrateOp("No BOS template found!".to_string()))?;
+
+  let configuration_name = bos_template
+    .get_configuration()
+    .ok_or_else(|| {
+      Error::MigrateOp(format!(
+        "BOS template '{bos}': no CFS configuration referenced"
+      ))
+    })?
+    .to_string();
+
+  let hsm_group_name_vec: Vec<String> = bos_template
+    .get_target_hsm()
+    .into_iter()
+    .map(|hsm_group_name| hsm_group_name.replace('\"', ""))
+    .collect::<BTreeSet<String>>()
+    .into_iter()
+    .collect();
+
+  // Several boot sets may boot the same image
+  let image_id_vec: Vec<String> = bos_template
+    .images_id()
+    .map(str::to_string)
+    .collect::<BTreeSet<String>>()
+    .into_iter()
+    .collect();
+
+  if image_id_vec.is_empty() {
+    return Err(Error::MigrateOp(format!(
+      "BOS template '{bos}': no boot image referenced"
+    )));
+  }
 
-  let mut download_counter = 1;
+  let mut manifest = BackupManifest {
+    version: BACKUP_FORMAT_VERSION,
+    created_at: Utc::now(),
+    bos_session_template: bos.to_string(),
+    cfs_configuration: configuration_name.clone(),
+    hsm_groups: hsm_group_name_vec.clone(),
+    images: Vec::new(),
+    files: Vec::new(),
+  };
 
-  if bos_templates.is_empty() {
-    return Err(Error::MigrateOp("No BOS template found!".to_string()));
+  write_json_file(
+    &mut manifest,
+    backup_dir,
+    BackupFileKind::BosSessionTemplate,
+    format!("{bos}.json"),
+    None,
+    &bos_template,
+  )?;
+
+  // HSM group -----------------------------------------------------------------------------
+  events::step(format!(
+    "Backing up HSM groups {hsm_group_name_vec:?} of BOS session template {bos}"
+  ));
+
+  let hsm_group_vec = if hsm_group_name_vec.is_empty() {
+    events::warning(format!(
+      "BOS template '{bos}' doesn't target any HSM group"
+    ));
+    Vec::new()
   } else {
-    // BOS ------------------------------------------------------------------------------------
-    let bos_file = File::create(&bos_file_path)?;
-
-    log::info!(
-      "Downloading BOS session template {} to {} [{}/{}]",
-      bos,
-      &bos_file_path.clone().to_string_lossy(),
-      &download_counter,
-      &files2download_count
-    );
-
-    // Save to file only the first one returned, we don't expect other BOS templates in the array
-    let _bosjson = serde_json::to_writer_pretty(&bos_file, &bos_templates[0]);
-    download_counter += 1;
-
-    // HSM group -----------------------------------------------------------------------------
-
-    let hsm_file = File::create(&hsm_file_path)?;
-    log::info!(
-      "Downloading HSM configuration in bos template {} to {} [{}/{}]",
-      bos,
-      &hsm_file_path.clone().to_string_lossy(),
-      &download_counter,
-      &files2download_count
-    );
-    download_counter += 1;
-
-    let hsm_group_name = bos_templates
-      .first()
-      .and_then(|first_bos_template| first_bos_template.boot_sets.as_ref())
-      .and_then(|boot_sets| boot_sets.get("compute"))
-      .and_then(|compute_boot_set| compute_boot_set.node_groups.as_ref())
-      .and_then(|node_groups| node_groups.first())
-      .map(|node_group| node_group.replace('\"', ""))
-      .ok_or_else(|| {
+    shasta_client
+      .hsm_group_get(shasta_token, Some(&hsm_group_name_vec), None)
+      .await?
+  };
+
+  write_json_file(
+    &mut manifest,
+    backup_dir,
+    BackupFileKind::HsmGroups,
+    format!("{bos}-hsm.json"),
+    None,
+    &hsm_group_vec,
+  )?;
+
+  // CFS ------------------------------------------------------------------------------------
+  events::step(format!("Backing up CFS configuration {configuration_name}"));
+
+  let cfs_configuration = shasta_client
+    .cfs_configuration_v3_get(shasta_token, Some(&configuration_name))
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| {
+      Error::MigrateOp(format!(
+        "CFS configuration '{configuration_name}' referenced by BOS template '{bos}' not found"
+      ))
+    })?;
+
+  write_json_file(
+    &mut manifest,
+    backup_dir,
+    BackupFileKind::CfsConfiguration,
+    format!("{configuration_name}.json"),
+    None,
+    &cfs_configuration,
+  )?;
+
+  // Image ----------------------------------------------------------------------------------
+  let sts_value = ims::s3_client::s3_auth(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+  )
+  .await
+  .map_err(|e| Error::MigrateOp(e.to_string()))?;
+
+  for image_id in &image_id_vec {
+    events::step(format!("Backing up IMS image {image_id}"));
+
+    let ims_record = shasta_client
+      .ims_image_get(shasta_token, Some(image_id))
+      .await
+      .map_err(|e| {
         Error::MigrateOp(format!(
-          "BOS template '{bos}': no 'compute' boot_set or no node_groups"
+          "image related to BOS session template {image_id} not found: {e}"
         ))
       })?;
 
-    let hsm_group_json = crate::ShastaClient::new(
-      shasta_base_url,
-      shasta_root_cert.to_vec(),
-      socks5_proxy.map(str::to_owned),
-    )?
-    .hsm_group_get(shasta_token, Some(std::slice::from_ref(&hsm_group_name)), None)
-    .await?;
-
-    log::debug!("{:#?}", &hsm_group_json);
-    let _hsmjson = serde_json::to_writer_pretty(&hsm_file, &hsm_group_json);
-
-    // CFS ------------------------------------------------------------------------------------
-    let configuration_name: &String = bos_templates
+    let image_name = ims_record
       .first()
-      .and_then(|first_bos_template| first_bos_template.cfs.as_ref())
-      .and_then(|cfs_value| cfs_value.configuration.as_ref())
+      .map(|image| image.name.clone())
       .ok_or_else(|| {
+        Error::MigrateOp(format!("IMS image '{image_id}' not found"))
+      })?;
+
+    write_json_file(
+      &mut manifest,
+      backup_dir,
+      BackupFileKind::ImsImage,
+      format!("{image_id}-ims.json"),
+      Some(image_id),
+      &ims_record,
+    )?;
+
+    let image_dir = backup_dir.join(image_id);
+    for file in IMAGE_ARTIFACT_FILE_NAMES {
+      let src = format!("{image_id}/{file}");
+      let object_size = ims::s3_client::s3_get_object_size(
+        &sts_value,
+        socks5_proxy,
+        &src,
+        BOOT_IMAGE_BUCKET,
+      )
+      .await
+      .unwrap_or(-1);
+      events::step(format!(
+        "Downloading image file {} ({}) to {}",
+        &src,
+        humansize::format_size(object_size as u64, DECIMAL),
+        image_dir.join(file).to_string_lossy()
+      ));
+
+      ims::s3_client::s3_download_object(
+        &sts_value,
+        socks5_proxy,
+        &src,
+        BOOT_IMAGE_BUCKET,
+        &image_dir.to_string_lossy(),
+      )
+      .await
+      .map_err(|error| {
         Error::MigrateOp(format!(
-          "BOS template '{bos}': no CFS configuration referenced"
+          "unable to download file {} from s3. Error returned: {}",
+          &src, error
         ))
       })?;
 
-    let cfs_configurations = crate::ShastaClient::new(
-      shasta_base_url,
-      shasta_root_cert.to_vec(),
-      socks5_proxy.map(str::to_owned),
-    )?
-    .cfs_configuration_v3_get(shasta_token, Some(configuration_name))
-    .await?;
+      add_file(
+        &mut manifest,
+        backup_dir,
+        BackupFileKind::ImageArtifact,
+        PathBuf::from(image_id)
+          .join(file)
+          .to_string_lossy()
+          .into_owned(),
+        Some(image_id),
+      )?;
+    }
 
-    let cfs_file_name =
-      String::from(configuration_name.clone().as_str()) + ".json";
-    let cfs_file_path = dest_path.join(&cfs_file_name);
-    let cfs_file = File::create(&cfs_file_path)?;
+    manifest.images.push(BackupImage {
+      id: image_id.clone(),
+      name: image_name,
+    });
+  }
 
-    log::info!(
-      "Downloading CFS configuration {} to {} [{}/{}]",
-      &configuration_name,
-      &cfs_file_path.clone().to_string_lossy(),
-      &download_counter,
-      &files2download_count
-    );
+  manifest.write(backup_dir)?;
 
-    // Save to file only the first one returned, we don't expect other BOS templates in the array
-    let _cfsjson =
-      serde_json::to_writer_pretty(&cfs_file, &cfs_configurations[0]);
+  events::created(
+    "Backup",
+    format!(
+      "{} ({} files, {} images)",
+      backup_dir.join(BACKUP_MANIFEST_FILE_NAME).display(),
+      manifest.files.len(),
+      manifest.images.len()
+    ),
+  );
 
-    download_counter += 1;
+  Ok(manifest)
+}
 
-    // Image ----------------------------------------------------------------------------------
-    let boot_sets = bos_templates
-      .first()
-      .and_then(|first_bos_template| first_bos_template.boot_sets.as_ref())
-      .ok_or_else(|| {
-        Error::MigrateOp(format!("BOS template '{bos}': no boot_sets defined"))
-      })?;
-    for boot_sets_value in boot_sets.values() {
-      if let Some(path) = &boot_sets_value.path {
-        let image_id_related_to_bos_sessiontemplate = path
-          .trim_start_matches("s3://boot-images/")
-          .trim_end_matches("/manifest.json")
-          .to_string();
-
-        log::info!(
-          "Get image details for ID {image_id_related_to_bos_sessiontemplate}"
-        );
-        let ims_file_name = String::from(
-          image_id_related_to_bos_sessiontemplate.clone().as_str(),
-        ) + "-ims.json";
-
-        let ims_file_path = dest_path.join(&ims_file_name);
-        let ims_file = File::create(&ims_file_path)?;
-
-        log::info!(
-          "Downloading IMS image record {} to {} [{}/{}]",
-          &image_id_related_to_bos_sessiontemplate,
-          &ims_file_path.clone().to_string_lossy(),
-          &download_counter,
-          &files2download_count
-        );
-        match crate::ShastaClient::new(
-          shasta_base_url,
-          shasta_root_cert.to_vec(),
-          socks5_proxy.map(str::to_owned),
-        )?
-        .ims_image_get(
-          shasta_token,
-          Some(&image_id_related_to_bos_sessiontemplate),
-        )
-        .await
-        {
-          Ok(ims_record) => {
-            serde_json::to_writer_pretty(&ims_file, &ims_record)?;
-            let image_id =
-              image_id_related_to_bos_sessiontemplate.clone().clone();
-            log::info!(
-              "Image ID found related to BOS sessiontemplate {bos} is {image_id_related_to_bos_sessiontemplate}"
-            );
-            let sts_value = match ims::s3_client::s3_auth(
-              shasta_token,
-              shasta_base_url,
-              shasta_root_cert,
-              socks5_proxy,
-            )
-            .await
-            {
-              Ok(sts_value) => sts_value,
-
-              Err(error) => return Err(Error::MigrateOp(error.to_string())),
-            };
-            for file in files2download {
-              let dest = String::from(destination) + "/" + &image_id;
-              let src = image_id.clone() + "/" + file;
-              let object_size = ims::s3_client::s3_get_object_size(
-                &sts_value,
-                socks5_proxy,
-                &src,
-                bucket_name,
-              )
-              .await
-              .unwrap_or(-1);
-              log::info!(
-                "Downloading image file {} ({}) to {}/{} [{}/{}]",
-                &src,
-                humansize::format_size(object_size as u64, DECIMAL),
-                &dest,
-                &file,
-                &download_counter,
-                &files2download_count
-              );
-              match ims::s3_client::s3_download_object(
-                &sts_value,
-                socks5_proxy,
-                &src,
-                bucket_name,
-                &dest,
-              )
-              .await
-              {
-                Ok(_result) => {
-                  download_counter += 1;
-                }
-                Err(error) => {
-                  return Err(Error::MigrateOp(format!(
-                    "unable to download file {} from s3. Error returned: {}",
-                    &src, error
-                  )));
-                }
-              }
-            } // for file in files2download
-            log::info!("\nDone, the following image bundle was generated:");
-            log::info!("\tBOS file: {}", &bos_file_path.to_string_lossy());
-            log::info!("\tCFS file: {}", &cfs_file_path.to_string_lossy());
-            log::info!("\tHSM file: {}", &hsm_file_path.to_string_lossy());
-            log::info!("\tIMS file: {}", &ims_file_path.to_string_lossy());
-            let ims_image_name = migrate_restore::get_image_name_from_ims_file(
-              &ims_file_path.to_string_lossy(),
-            )?;
-            log::info!("\tImage name: {ims_image_name}");
-            for file in files2download {
-              let dest = String::from(destination);
-              let src = image_id.clone() + "/" + file;
-              log::info!("\t\tfile: {dest}/{src}");
-            }
-          }
-          Err(e) => {
-            return Err(Error::MigrateOp(format!(
-              "image related to BOS session template {image_id_related_to_bos_sessiontemplate} not found: {e}"
-            )));
-          }
-        }
-      }
-    }
-  }
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-  Ok(())
+  #[test]
+  fn manifest_round_trips_and_verifies_checksums() {
+    let backup_dir = std::env::temp_dir()
+      .join(format!("csm-rs-migrate-backup-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&backup_dir).unwrap();
+
+    let mut manifest = BackupManifest {
+      version: BACKUP_FORMAT_VERSION,
+      created_at: Utc::now(),
+      bos_session_template: "zinal-cos".to_string(),
+      cfs_configuration: "zinal-cos-config".to_string(),
+      hsm_groups: vec!["zinal".to_string()],
+      images: Vec::new(),
+      files: Vec::new(),
+    };
+    write_json_file(
+      &mut manifest,
+      &backup_dir,
+      BackupFileKind::HsmGroups,
+      "zinal-cos-hsm.json".to_string(),
+      None,
+      &serde_json::json!([]),
+    )
+    .unwrap();
+    manifest.write(&backup_dir).unwrap();
+
+    let read = BackupManifest::read(&backup_dir).unwrap();
+    assert_eq!(read, manifest);
+    assert!(read.verify(&backup_dir).is_ok());
+
+    std::fs::write(backup_dir.join("zinal-cos-hsm.json"), "[{}]").unwrap();
+    let verify_rslt = read.verify(&backup_dir);
+    std::fs::remove_dir_all(&backup_dir).unwrap();
+
+    assert!(matches!(verify_rslt, Err(Error::MigrateOp(_))));
+  }
 }
@@ -1,18 +1,27 @@
 //! Restore a system from the bundle produced by [`crate::commands::migrate_backup`].
+//!
+//! [`exec_from_backup`] restores a whole backup directory, driven by its
+//! backup manifest; [`exec`] restores from explicitly given files.
 
+use crate::ExecutionContext;
 use crate::bos::BosSessionTemplate;
 use crate::cfs::v3::{CfsConfigurationRequest, CfsConfigurationResponse};
+use crate::commands::migrate_backup::{
+  BackupFile, BackupFileKind, BackupManifest, IMAGE_ARTIFACT_FILE_NAMES,
+};
+use crate::common::{events, protection};
 use crate::hsm::group::types::Group;
 use crate::ims;
-use crate::ims::image::utils::{get_by_name, get_fuzzy};
-use crate::ims::s3_client::BAR_FORMAT;
+use crate::ims::image::utils::{MatchMode, get_by_name, get_fuzzy};
+use crate::ims::s3_client::{BAR_FORMAT, progress_bar};
 use crate::ims::{Image, Link};
 use chrono::Local;
 use humansize::DECIMAL;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::ProgressStyle;
 use md5::Digest;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -55,16 +64,19 @@ fn default_version() -> String {
 /// `Option` only because of the CLI surface that consumes this).
 ///
 /// The four `overwrite_*` flags replace existing CSM resources with
-/// the same name instead of failing.
+/// the same name instead of failing. Protected CFS configurations and
+/// BOS sessiontemplates (see [`crate::protection`]) are only replaced
+/// if `ignore_protection` is set.
 ///
 /// # Errors
 ///
-/// Returns an [`Error`] variant on CSM, transport, or
-/// deserialization failure; see the crate-level `Error` enum
-/// for the full set.
+/// Returns an [`Error`] variant if `execution_context` can't act on an
+/// HSM group to restore, or on CSM, transport, or deserialization
+/// failure; see the crate-level `Error` enum for the full set.
 #[allow(clippy::too_many_arguments)]
 pub async fn exec(
   shasta_token: &str,
+  execution_context: &ExecutionContext,
   shasta_base_url: &str,
   shasta_root_cert: &[u8],
   socks5_proxy: Option<&str>,
@@ -77,6 +89,49 @@ pub async fn exec(
   overwrite_configuration: bool,
   overwrite_image: bool,
   overwrite_template: bool,
+  ignore_protection: bool,
+) -> Result<(), Error> {
+  let _timer = crate::common::metrics::CommandTimer::start("migrate_restore");
+
+  execution_context
+    .scope(restore(
+      shasta_token,
+      execution_context,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      bos_file,
+      cfs_file,
+      hsm_file,
+      ims_file,
+      image_dir,
+      overwrite_group,
+      overwrite_configuration,
+      overwrite_image,
+      overwrite_template,
+      ignore_protection,
+    ))
+    .await
+}
+
+/// Body of [`exec`], run inside its execution context.
+#[allow(clippy::too_many_arguments)]
+async fn restore(
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  bos_file: Option<&str>,
+  cfs_file: Option<&str>,
+  hsm_file: Option<&str>,
+  ims_file: Option<&str>,
+  image_dir: Option<&str>,
+  overwrite_group: bool,
+  overwrite_configuration: bool,
+  overwrite_image: bool,
+  overwrite_template: bool,
+  ignore_protection: bool,
 ) -> Result<(), Error> {
   fn require<'a>(opt: Option<&'a str>, name: &str) -> Result<&'a str, Error> {
     opt.ok_or_else(|| {
@@ -104,28 +159,21 @@ pub async fn exec(
   }
 
   // ========================================================================================================
-  let current_timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
-  let mut ims_image_manifest = ImageManifest {
-    created: current_timestamp.to_string(),
-    version: "1.0".to_string(),
-    artifacts: vec![],
-  };
-
   let backup_ims_file = ims_file.to_string();
   let backup_cfs_file = cfs_file.to_string();
   let backup_bos_file = bos_file.to_string();
   let backup_hsm_file = hsm_file.to_string();
 
   let ims_image_name: String = get_image_name_from_ims_file(&backup_ims_file)?;
-  log::info!(" Image name: {ims_image_name}");
+  events::info(format!(" Image name: {ims_image_name}"));
 
   let initrd_path = format!("{image_dir}/initrd");
   let kernel_path = format!("{image_dir}/kernel");
   let rootfs_path = format!("{image_dir}/rootfs");
 
-  log::info!("\tinitrd file: {initrd_path}");
-  log::info!("\tkernel file: {kernel_path}");
-  log::info!("\trootfs file: {rootfs_path}");
+  events::info(format!("\tinitrd file: {initrd_path}"));
+  events::info(format!("\tkernel file: {kernel_path}"));
+  events::info(format!("\trootfs file: {rootfs_path}"));
 
   // These should come from the manifest, but let's assume these values are correct
   let vec_backup_image_files = vec![initrd_path, kernel_path, rootfs_path];
@@ -139,58 +187,21 @@ pub async fn exec(
     }
   }
 
-  log::info!("Calculating image artifact checksum...");
-  calculate_image_checksums(&mut ims_image_manifest, &vec_backup_image_files)?;
-
-  // Do we have another image with this name?
-  log::info!("\n\nRegistering image with IMS...");
-  let ims_image_id_rslt = ims_register_image(
+  let ims_image_id = restore_image(
     shasta_token,
     shasta_base_url,
     shasta_root_cert,
     socks5_proxy,
     &ims_image_name,
-    overwrite_image,
-  )
-  .await;
-
-  let ims_image_id: String = match ims_image_id_rslt {
-    Ok(value) => value,
-    Err(e) => {
-      return Err(Error::MigrateOp(format!("{e}")));
-    }
-  };
-
-  log::info!("IMS image ID: {}", &ims_image_id);
-
-  log::info!("\nUploading image artifacts to s3...");
-  s3_upload_image_artifacts(
-    shasta_token,
-    shasta_base_url,
-    shasta_root_cert,
-    socks5_proxy,
-    &ims_image_id,
-    &mut ims_image_manifest,
     &vec_backup_image_files,
-  )
-  .await?;
-  log::info!("\nUpdating IMS image record with the new location in s3...");
-  log::debug!(
-    "Updating image record with location of the newly generated manifest.json data"
-  );
-  ims_update_image_add_manifest(
-    shasta_token,
-    shasta_base_url,
-    shasta_root_cert,
-    socks5_proxy,
-    &ims_image_name,
-    &ims_image_id,
+    overwrite_image,
   )
   .await?;
 
-  log::info!("\nCreating HSM group...");
+  events::step("Creating HSM group");
   create_hsm_group_from_file(
     shasta_token,
+    execution_context,
     shasta_base_url,
     shasta_root_cert,
     socks5_proxy,
@@ -199,7 +210,7 @@ pub async fn exec(
   )
   .await?;
 
-  log::info!("\nUploading CFS configuration...");
+  events::step("Uploading CFS configuration");
   // create a new CFS configuration based on the original CFS file backed up previously
   // this operation is simple as the file only has git repos and commits
   create_cfs_config(
@@ -209,10 +220,11 @@ pub async fn exec(
     socks5_proxy,
     &backup_cfs_file,
     overwrite_configuration,
+    ignore_protection,
   )
   .await?;
 
-  log::info!("\nUploading BOS sessiontemplate...");
+  events::step("Uploading BOS sessiontemplate");
 
   // Create a new BOS session template based on the original BOS file backed previously
   create_bos_sessiontemplate(
@@ -223,11 +235,12 @@ pub async fn exec(
     &backup_bos_file,
     &ims_image_id,
     overwrite_template,
+    ignore_protection,
   )
   .await?;
 
-  log::info!(
-    "\nDone, the image bundle, HSM group, CFS configuration and BOS sessiontemplate have been restored."
+  events::info(
+    "Done, the image bundle, HSM group, CFS configuration and BOS sessiontemplate have been restored.",
   );
 
   // ========================================================================================================
@@ -235,6 +248,582 @@ pub async fn exec(
   Ok(())
 }
 
+/// A backup directory read by [`load_backup`].
+#[derive(Debug, Clone)]
+struct LoadedBackup {
+  manifest: BackupManifest,
+  bos_sessiontemplate: BosSessionTemplate,
+  cfs_configuration_name: String,
+  cfs_configuration: CfsConfigurationRequest,
+  hsm_group_vec: Vec<Group>,
+}
+
+/// The one file of `kind` in the backup in `backup_dir`.
+fn backup_file_path(
+  manifest: &BackupManifest,
+  backup_dir: &Path,
+  kind: BackupFileKind,
+) -> Result<PathBuf, Error> {
+  let mut file_iter = manifest.files_of(kind);
+
+  match (file_iter.next(), file_iter.next()) {
+    (Some(file), None) => Ok(backup_dir.join(&file.path)),
+    (None, _) => Err(Error::MigrateOp(format!(
+      "backup manifest lists no {kind:?} file"
+    ))),
+    (Some(_), Some(_)) => Err(Error::MigrateOp(format!(
+      "backup manifest lists more than one {kind:?} file"
+    ))),
+  }
+}
+
+fn read_json_file<T: serde::de::DeserializeOwned>(
+  path: &Path,
+) -> Result<T, Error> {
+  serde_json::from_reader(BufReader::new(File::open(path)?)).map_err(|e| {
+    Error::MigrateOp(format!("unable to parse {}: {e}", path.display()))
+  })
+}
+
+/// Read the backup in `backup_dir` and check it is complete, intact and
+/// consistent, see [`check_backup_references`].
+fn load_backup(backup_dir: &Path) -> Result<LoadedBackup, Error> {
+  let manifest = BackupManifest::read(backup_dir)?;
+  manifest.verify(backup_dir)?;
+
+  let bos_sessiontemplate: BosSessionTemplate =
+    read_json_file(&backup_file_path(
+      &manifest,
+      backup_dir,
+      BackupFileKind::BosSessionTemplate,
+    )?)?;
+  let cfs_configuration: CfsConfigurationResponse = read_json_file(
+    &backup_file_path(&manifest, backup_dir, BackupFileKind::CfsConfiguration)?,
+  )?;
+  let hsm_group_vec: Vec<Group> = read_json_file(&backup_file_path(
+    &manifest,
+    backup_dir,
+    BackupFileKind::HsmGroups,
+  )?)?;
+
+  check_backup_references(
+    &manifest,
+    &bos_sessiontemplate,
+    &cfs_configuration.name,
+    &hsm_group_vec,
+  )?;
+
+  Ok(LoadedBackup {
+    cfs_configuration: CfsConfigurationRequest::from(&cfs_configuration),
+    cfs_configuration_name: cfs_configuration.name,
+    manifest,
+    bos_sessiontemplate,
+    hsm_group_vec,
+  })
+}
+
+/// Check every resource the BOS sessiontemplate of a backup references
+/// is in the backup: its CFS configuration, the HSM groups its boot sets
+/// target and, for each boot image, the IMS record and the initrd,
+/// kernel and rootfs artifacts.
+///
+/// Pure part of [`load_backup`], split out for testing.
+fn check_backup_references(
+  manifest: &BackupManifest,
+  bos_sessiontemplate: &BosSessionTemplate,
+  cfs_configuration_name: &str,
+  hsm_group_vec: &[Group],
+) -> Result<(), Error> {
+  let mut problem_vec = Vec::new();
+
+  if bos_sessiontemplate.name.as_deref()
+    != Some(manifest.bos_session_template.as_str())
+  {
+    problem_vec.push(format!(
+      "BOS sessiontemplate file is not '{}'",
+      manifest.bos_session_template
+    ));
+  }
+
+  if cfs_configuration_name != manifest.cfs_configuration {
+    problem_vec.push(format!(
+      "CFS configuration file is '{cfs_configuration_name}', expected '{}'",
+      manifest.cfs_configuration
+    ));
+  }
+  if bos_sessiontemplate.get_configuration() != Some(cfs_configuration_name) {
+    problem_vec.push(format!(
+      "BOS sessiontemplate doesn't use CFS configuration '{cfs_configuration_name}'"
+    ));
+  }
+
+  let hsm_group_name_vec: Vec<&str> = hsm_group_vec
+    .iter()
+    .map(|group| group.label.0.as_str())
+    .collect();
+  for hsm_group_name in bos_sessiontemplate.get_target_hsm() {
+    if !hsm_group_name_vec.contains(&hsm_group_name.as_str()) {
+      problem_vec.push(format!("HSM group '{hsm_group_name}' not backed up"));
+    }
+  }
+
+  for image_id in bos_sessiontemplate.images_id() {
+    if !manifest.images.iter().any(|image| image.id == image_id) {
+      problem_vec.push(format!("IMS image '{image_id}' not backed up"));
+      continue;
+    }
+
+    let image_file_vec: Vec<&BackupFile> = manifest
+      .files
+      .iter()
+      .filter(|file| file.image_id.as_deref() == Some(image_id))
+      .collect();
+
+    if !image_file_vec
+      .iter()
+      .any(|file| file.kind == BackupFileKind::ImsImage)
+    {
+      problem_vec.push(format!("IMS record of image '{image_id}' missing"));
+    }
+    for artifact in IMAGE_ARTIFACT_FILE_NAMES {
+      if !image_file_vec.iter().any(|file| {
+        file.kind == BackupFileKind::ImageArtifact
+          && Path::new(&file.path).file_name()
+            == Some(std::ffi::OsStr::new(artifact))
+      }) {
+        problem_vec.push(format!("{artifact} of image '{image_id}' missing"));
+      }
+    }
+  }
+
+  if problem_vec.is_empty() {
+    Ok(())
+  } else {
+    Err(Error::MigrateOp(format!(
+      "backup of BOS sessiontemplate '{}' is inconsistent: {}",
+      manifest.bos_session_template,
+      problem_vec.join("; ")
+    )))
+  }
+}
+
+/// `url` with the first prefix of `url_remap` it starts with replaced
+/// by the new prefix; `url` unchanged if none matches.
+#[must_use]
+pub fn remap_url(url: &str, url_remap: &[(String, String)]) -> String {
+  url_remap
+    .iter()
+    .find_map(|(from, to)| {
+      url
+        .strip_prefix(from.as_str())
+        .map(|rest| format!("{to}{rest}"))
+    })
+    .unwrap_or_else(|| url.to_string())
+}
+
+/// Remap the clone URLs of the layers and additional inventory of
+/// `cfs_configuration`, see [`remap_url`].
+fn remap_cfs_configuration_urls(
+  cfs_configuration: &mut CfsConfigurationRequest,
+  url_remap: &[(String, String)],
+) {
+  for layer in cfs_configuration.layers.iter_mut().flatten() {
+    if let Some(clone_url) = layer.clone_url.as_mut() {
+      *clone_url = remap_url(clone_url, url_remap);
+    }
+  }
+
+  if let Some(additional_inventory) =
+    cfs_configuration.additional_inventory.as_mut()
+  {
+    additional_inventory.clone_url =
+      remap_url(&additional_inventory.clone_url, url_remap);
+  }
+}
+
+/// Point every boot set of `bos_sessiontemplate` booting an image of
+/// `image_id_map` (id in the backup to id restored) at the restored
+/// image.
+fn remap_boot_set_images(
+  bos_sessiontemplate: &mut BosSessionTemplate,
+  image_id_map: &HashMap<String, String>,
+) {
+  for boot_set in bos_sessiontemplate
+    .boot_sets
+    .iter_mut()
+    .flat_map(|b| b.values_mut())
+  {
+    let new_image_id = boot_set.path.as_deref().and_then(|path| {
+      image_id_map.get(
+        path
+          .trim_start_matches("s3://boot-images/")
+          .trim_end_matches("/manifest.json"),
+      )
+    });
+
+    if let Some(new_image_id) = new_image_id {
+      boot_set.path =
+        Some(format!("s3://boot-images/{new_image_id}/manifest.json"));
+    }
+  }
+}
+
+/// Check none of the resources of `backup` exists on the target system,
+/// unless the matching `overwrite_*` flag is set.
+#[allow(clippy::too_many_arguments)]
+async fn check_restore_conflicts(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  backup: &LoadedBackup,
+  overwrite_group: bool,
+  overwrite_configuration: bool,
+  overwrite_image: bool,
+  overwrite_template: bool,
+) -> Result<(), Error> {
+  let shasta_client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+
+  let mut conflict_vec = Vec::new();
+
+  if !overwrite_group && !backup.hsm_group_vec.is_empty() {
+    let existing_group_vec = shasta_client
+      .hsm_group_get(shasta_token, Some(&backup.manifest.hsm_groups), None)
+      .await?;
+    conflict_vec.extend(
+      existing_group_vec
+        .iter()
+        .map(|group| format!("HSM group '{}'", group.label.0)),
+    );
+  }
+
+  if !overwrite_configuration
+    && !shasta_client
+      .cfs_configuration_v3_get(
+        shasta_token,
+        Some(&backup.cfs_configuration_name),
+      )
+      .await?
+      .is_empty()
+  {
+    conflict_vec.push(format!(
+      "CFS configuration '{}'",
+      backup.cfs_configuration_name
+    ));
+  }
+
+  if !overwrite_image {
+    for image in &backup.manifest.images {
+      if !get_by_name(
+        shasta_token,
+        shasta_base_url,
+        shasta_root_cert,
+        socks5_proxy,
+        &[String::new()], // hsm_group_name
+        &image.name,
+        None,
+      )
+      .await?
+      .is_empty()
+      {
+        conflict_vec.push(format!("IMS image '{}'", image.name));
+      }
+    }
+  }
+
+  if !overwrite_template
+    && !shasta_client
+      .bos_template_v2_get(
+        shasta_token,
+        Some(&backup.manifest.bos_session_template),
+      )
+      .await?
+      .is_empty()
+  {
+    conflict_vec.push(format!(
+      "BOS sessiontemplate '{}'",
+      backup.manifest.bos_session_template
+    ));
+  }
+
+  if conflict_vec.is_empty() {
+    Ok(())
+  } else {
+    Err(Error::MigrateOp(format!(
+      "{} already exist(s) and the matching --overwrite-* flag was not set",
+      conflict_vec.join(", ")
+    )))
+  }
+}
+
+/// Restore the backup directory written by
+/// [`crate::commands::migrate_backup::exec`].
+///
+/// Before writing anything, checks the backup is complete and intact
+/// (checksums of the backup manifest), that it holds every resource the
+/// BOS sessiontemplate references, and that none of them already exists
+/// on the target system unless the matching `overwrite_*` flag is set.
+///
+/// Then recreates the HSM groups, re-uploads every boot image to S3 and
+/// registers it in IMS, and recreates the CFS configuration and the BOS
+/// sessiontemplate, pointing its boot sets at the new images.
+///
+/// # Arguments
+///
+/// - `backup_dir` — backup directory, holding the backup manifest.
+/// - `url_remap` — `(from, to)` URL prefixes; clone URLs of the CFS
+///   configuration starting with `from` get it replaced by `to`, e.g.
+///   to move to the VCS of the target site. First match wins.
+/// - `ignore_protection` — let the `overwrite_*` flags replace
+///   protected CFS configurations and BOS sessiontemplates (see
+///   [`crate::protection`]).
+///
+/// Returns the ids of the restored IMS images, keyed by their id in the
+/// backup.
+///
+/// # Errors
+///
+/// Returns [`Error::MigrateOp`] if the backup is incomplete, modified
+/// or inconsistent, or conflicts with existing resources, an error if
+/// `execution_context` can't act on an HSM group to restore, and any
+/// other [`Error`] variant on CSM, transport, or deserialization
+/// failure.
+#[allow(clippy::too_many_arguments)]
+pub async fn exec_from_backup(
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  backup_dir: &Path,
+  url_remap: &[(String, String)],
+  overwrite_group: bool,
+  overwrite_configuration: bool,
+  overwrite_image: bool,
+  overwrite_template: bool,
+  ignore_protection: bool,
+) -> Result<HashMap<String, String>, Error> {
+  let _timer = crate::common::metrics::CommandTimer::start("migrate_restore");
+
+  execution_context
+    .scope(restore_from_backup(
+      shasta_token,
+      execution_context,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      backup_dir,
+      url_remap,
+      overwrite_group,
+      overwrite_configuration,
+      overwrite_image,
+      overwrite_template,
+      ignore_protection,
+    ))
+    .await
+}
+
+/// Body of [`exec_from_backup`], run inside its execution context.
+#[allow(clippy::too_many_arguments)]
+async fn restore_from_backup(
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  backup_dir: &Path,
+  url_remap: &[(String, String)],
+  overwrite_group: bool,
+  overwrite_configuration: bool,
+  overwrite_image: bool,
+  overwrite_template: bool,
+  ignore_protection: bool,
+) -> Result<HashMap<String, String>, Error> {
+  events::step(format!("Checking backup in {}", backup_dir.display()));
+  let mut backup = load_backup(backup_dir)?;
+
+  check_restore_conflicts(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    &backup,
+    overwrite_group,
+    overwrite_configuration,
+    overwrite_image,
+    overwrite_template,
+  )
+  .await?;
+
+  // HSM groups ---------------------------------------------------------------------------
+  if backup.hsm_group_vec.is_empty() {
+    events::warning(format!(
+      "BOS sessiontemplate '{}' doesn't target any HSM group",
+      backup.manifest.bos_session_template
+    ));
+  } else {
+    events::step("Creating HSM groups");
+    create_hsm_group_from_file(
+      shasta_token,
+      execution_context,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      &backup_file_path(
+        &backup.manifest,
+        backup_dir,
+        BackupFileKind::HsmGroups,
+      )?
+      .to_string_lossy(),
+      overwrite_group,
+    )
+    .await?;
+  }
+
+  // Images -------------------------------------------------------------------------------
+  let mut image_id_map = HashMap::new();
+  for image in &backup.manifest.images {
+    events::step(format!("Restoring IMS image {}", image.name));
+
+    let image_file_vec: Vec<String> = ["initrd", "kernel", "rootfs"]
+      .iter()
+      .map(|file| {
+        backup_dir
+          .join(&image.id)
+          .join(file)
+          .to_string_lossy()
+          .into_owned()
+      })
+      .collect();
+
+    let new_image_id = restore_image(
+      shasta_token,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      &image.name,
+      &image_file_vec,
+      overwrite_image,
+    )
+    .await?;
+
+    image_id_map.insert(image.id.clone(), new_image_id);
+  }
+
+  // CFS configuration --------------------------------------------------------------------
+  events::step(format!(
+    "Creating CFS configuration {}",
+    backup.cfs_configuration_name
+  ));
+  remap_cfs_configuration_urls(&mut backup.cfs_configuration, url_remap);
+  put_cfs_configuration(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    &backup.cfs_configuration_name,
+    &backup.cfs_configuration,
+    overwrite_configuration,
+    ignore_protection,
+  )
+  .await?;
+
+  // BOS sessiontemplate ------------------------------------------------------------------
+  events::step(format!(
+    "Creating BOS sessiontemplate {}",
+    backup.manifest.bos_session_template
+  ));
+  remap_boot_set_images(&mut backup.bos_sessiontemplate, &image_id_map);
+  put_bos_sessiontemplate(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    &backup.bos_sessiontemplate,
+    overwrite_template,
+    ignore_protection,
+  )
+  .await?;
+
+  events::info(format!(
+    "Restored BOS sessiontemplate {} from {}",
+    backup.manifest.bos_session_template,
+    backup_dir.display()
+  ));
+
+  Ok(image_id_map)
+}
+
+/// Register image `ims_image_name` in IMS, upload the artifacts in
+/// `image_file_vec` (initrd, kernel and rootfs) to S3 and point the IMS
+/// record at them. Returns the id of the new IMS image.
+#[allow(clippy::ptr_arg)]
+async fn restore_image(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  ims_image_name: &str,
+  image_file_vec: &Vec<String>,
+  overwrite: bool,
+) -> Result<String, Error> {
+  let current_timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
+  let mut ims_image_manifest = ImageManifest {
+    created: current_timestamp.to_string(),
+    version: "1.0".to_string(),
+    artifacts: vec![],
+  };
+
+  events::step("Calculating image artifact checksum");
+  calculate_image_checksums(&mut ims_image_manifest, image_file_vec)?;
+
+  // Do we have another image with this name?
+  events::step("Registering image with IMS");
+  let ims_image_id = ims_register_image(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    ims_image_name,
+    overwrite,
+  )
+  .await
+  .map_err(|e| Error::MigrateOp(format!("{e}")))?;
+
+  events::info(format!("IMS image ID: {}", &ims_image_id));
+
+  events::step("Uploading image artifacts to s3");
+  s3_upload_image_artifacts(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    &ims_image_id,
+    &mut ims_image_manifest,
+    image_file_vec,
+  )
+  .await?;
+  events::step("Updating IMS image record with the new location in s3");
+  log::debug!(
+    "Updating image record with location of the newly generated manifest.json data"
+  );
+  ims_update_image_add_manifest(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    ims_image_name,
+    &ims_image_id,
+  )
+  .await?;
+
+  Ok(ims_image_id)
+}
+
 async fn create_bos_sessiontemplate(
   shasta_token: &str,
   shasta_base_url: &str,
@@ -243,22 +832,63 @@ async fn create_bos_sessiontemplate(
   bos_file: &str,
   ims_image_id: &str,
   overwrite: bool,
+  ignore_protection: bool,
 ) -> Result<(), Error> {
   let file_content = File::open(bos_file)?;
 
-  let bos_json: BosSessionTemplate =
+  let mut bos_sessiontemplate: BosSessionTemplate =
     serde_json::from_reader(BufReader::new(file_content))?;
 
-  let bos_sessiontemplate_name = bos_json.name.ok_or_else(|| {
-    Error::MigrateOp(
-      "BOS sessiontemplate file is missing the 'name' field".to_string(),
-    )
-  })?;
-
   // BOS sessiontemplates need the new ID of the image!
+  let path_modified = format!("s3://boot-images/{ims_image_id}/manifest.json");
+
+  bos_sessiontemplate
+    .boot_sets
+    .as_mut()
+    .and_then(|boot_sets| boot_sets.get_mut("compute"))
+    .ok_or_else(|| {
+      Error::MigrateOp(
+        "BOS sessiontemplate has no 'compute' boot_set to update".to_string(),
+      )
+    })?
+    .path = Some(path_modified);
+
+  log::debug!("BOS sessiontemplate modified:\n{:#?}", &bos_sessiontemplate);
+
+  put_bos_sessiontemplate(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    &bos_sessiontemplate,
+    overwrite,
+    ignore_protection,
+  )
+  .await
+}
+
+/// Creates BOS sessiontemplate `bos_sessiontemplate`, deleting the one
+/// with the same name first if `overwrite` is set. A protected one is
+/// only deleted if `ignore_protection` is set too.
+async fn put_bos_sessiontemplate(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  bos_sessiontemplate: &BosSessionTemplate,
+  overwrite: bool,
+  ignore_protection: bool,
+) -> Result<(), Error> {
+  let bos_sessiontemplate_name =
+    bos_sessiontemplate.name.clone().ok_or_else(|| {
+      Error::MigrateOp(
+        "BOS sessiontemplate file is missing the 'name' field".to_string(),
+      )
+    })?;
+
   log::debug!("BOS sessiontemplate name: {}", &bos_sessiontemplate_name);
 
-  let shasta_client = crate::ShastaClient::new(
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -276,6 +906,16 @@ async fn create_bos_sessiontemplate(
 
   if !vector.is_empty() {
     if overwrite {
+      if !ignore_protection
+        && vector.iter().any(|existing| {
+          protection::is_protected(existing.description.as_deref())
+        })
+      {
+        return Err(Error::Protected(format!(
+          "BOS sessiontemplate '{bos_sessiontemplate_name}'"
+        )));
+      }
+
       match shasta_client
         .bos_template_v2_delete(shasta_token, &bos_sessiontemplate_name)
         .await
@@ -297,40 +937,17 @@ async fn create_bos_sessiontemplate(
     }
   }
 
-  let file_content = File::open(bos_file)?;
-
-  let mut bos_sessiontemplate: BosSessionTemplate =
-    serde_json::from_reader(BufReader::new(file_content))?;
-
-  let path_modified =
-    format!("s3://boot-images/{ims_image_id}/manifest.json");
-
-  bos_sessiontemplate
-    .boot_sets
-    .as_mut()
-    .and_then(|boot_sets| boot_sets.get_mut("compute"))
-    .ok_or_else(|| {
-      Error::MigrateOp(
-        "BOS sessiontemplate has no 'compute' boot_set to update".to_string(),
-      )
-    })?
-    .path = Some(path_modified);
-
-  log::debug!("BOS sessiontemplate loaded:\n{bos_sessiontemplate:#?}");
-  log::debug!("BOS sessiontemplate modified:\n{:#?}", &bos_sessiontemplate);
-
   match shasta_client
     .bos_template_v2_put(
       shasta_token,
-      &bos_sessiontemplate,
+      bos_sessiontemplate,
       &bos_sessiontemplate_name,
     )
     .await
   {
-    Ok(_result) => log::info!(
-      "Ok, BOS session template {} created successfully.",
-      &bos_sessiontemplate_name
-    ),
+    Ok(_result) => {
+      events::created("BOS sessiontemplate", &bos_sessiontemplate_name);
+    }
     Err(e1) => {
       return Err(Error::MigrateOp(format!(
         "unable to create BOS session template: {e1}"
@@ -350,6 +967,7 @@ async fn create_cfs_config(
   socks5_proxy: Option<&str>,
   cfs_file: &str,
   overwrite: bool,
+  ignore_protection: bool,
 ) -> Result<(), Error> {
   let file_content = File::open(cfs_file)?;
 
@@ -359,14 +977,45 @@ async fn create_cfs_config(
   // CFS needs to be cleaned up when loading into the system, the filed lastUpdate should not exist
   let cfs_config_name = cfs_configuration.name;
 
+  let file_content = File::open(cfs_file)?;
+
+  let cfs_configuration: CfsConfigurationRequest =
+    serde_json::from_reader(BufReader::new(file_content))?;
+
+  put_cfs_configuration(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    &cfs_config_name,
+    &cfs_configuration,
+    overwrite,
+    ignore_protection,
+  )
+  .await
+}
+
+/// Creates CFS configuration `cfs_config_name`, deleting the existing
+/// one first if `overwrite` is set. A protected one is only deleted if
+/// `ignore_protection` is set too.
+async fn put_cfs_configuration(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  cfs_config_name: &str,
+  cfs_configuration: &CfsConfigurationRequest,
+  overwrite: bool,
+  ignore_protection: bool,
+) -> Result<(), Error> {
   // Get all CFS configurations, this is ugly
-  let shasta_client = crate::ShastaClient::new(
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
   )?;
   let cfs_config_vec = shasta_client
-    .cfs_configuration_v3_get(shasta_token, Some(&cfs_config_name))
+    .cfs_configuration_v3_get(shasta_token, Some(cfs_config_name))
     .await
     .map_err(|error| {
       Error::MigrateOp(format!("Unable to fetch CFS configuration: {error}"))
@@ -379,8 +1028,18 @@ async fn create_cfs_config(
       ));
     }
 
+    if !ignore_protection
+      && cfs_config_vec.iter().any(|cfs_configuration| {
+        protection::is_protected(cfs_configuration.description.as_deref())
+      })
+    {
+      return Err(Error::Protected(format!(
+        "CFS configuration '{cfs_config_name}'"
+      )));
+    }
+
     match shasta_client
-      .cfs_configuration_v3_delete(shasta_token, cfs_config_name.as_str())
+      .cfs_configuration_v3_delete(shasta_token, cfs_config_name)
       .await
     {
       Ok(()) => {
@@ -397,27 +1056,15 @@ async fn create_cfs_config(
   // At this point we're sure there's either no CFS config with that name
   // or that the user wants to overwrite it, so let's do it
 
-  let file_content = File::open(cfs_file)?;
-
-  let cfs_configuration: CfsConfigurationRequest =
-    serde_json::from_reader(BufReader::new(file_content))?;
-
   log::debug!("CFS config:\n{:#?}", &cfs_configuration);
 
   match shasta_client
-    .cfs_configuration_v3_put(
-      shasta_token,
-      &cfs_configuration,
-      cfs_config_name.as_str(),
-    )
+    .cfs_configuration_v3_put(shasta_token, cfs_configuration, cfs_config_name)
     .await
   {
     Ok(result) => {
       log::debug!("Ok, result: {result:#?}");
-      log::info!(
-        "Ok, CFS configuration {} created successfully.",
-        &cfs_config_name
-      );
+      events::created("CFS configuration", cfs_config_name);
     }
     Err(e1) => {
       return Err(Error::MigrateOp(format!(
@@ -447,9 +1094,12 @@ async fn ims_update_image_add_manifest(
     &[String::new()], // hsm_group_name
     Some(ims_image_name),
     None,
+    MatchMode::Fuzzy,
   )
   .await
   {
+    // Several images sharing the name is fine, the record exists
+    Err(Error::AmbiguousImageName { .. }) => {}
     Ok(vector) => {
       if vector.is_empty() {
         return Err(Error::MigrateOp(format!(
@@ -496,7 +1146,7 @@ async fn ims_update_image_add_manifest(
     metadata: None,
   };
 
-  let patch_result = match crate::ShastaClient::new(
+  let patch_result = match crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -560,22 +1210,19 @@ async fn s3_upload_image_artifacts(
     let file_size = match fs::metadata(file) {
       Ok(file_metadata) => humansize::format_size(file_metadata.len(), DECIMAL),
       Err(e) => {
-        log::warn!(
+        events::warning(format!(
           "Unable to fetch file metadata info, faking the value. Error: {e}"
-        );
+        ));
         "-1".to_string()
       }
     };
 
     let full_object_path =
       format!("{}/{}", &object_path, &filename.to_string_lossy());
-    log::info!(
+    events::info(format!(
       "File {:?} ({}) to s3://{}/{}.",
-      &file,
-      &file_size,
-      &bucket_name,
-      &full_object_path
-    );
+      &file, &file_size, &bucket_name, &full_object_path
+    ));
     let etag: String = if fs::metadata(file)?.len() > 1024 * 1024 * 5 {
       match ims::s3_client::s3_multipart_upload_object(
         &sts_value,
@@ -607,7 +1254,7 @@ async fn s3_upload_image_artifacts(
       .await
       {
         Ok(result) => {
-          log::info!("Ok");
+          events::info("Ok");
           result
         }
         Err(error) => {
@@ -691,12 +1338,10 @@ async fn s3_upload_image_artifacts(
 
   log::debug!("Uploading the new manifest.json file");
   let manifest_full_object_path = format!("{}/manifest.json", &object_path);
-  log::info!(
+  events::info(format!(
     "File {:?} -> s3://{}/{}.",
-    &new_manifest_file_name,
-    &bucket_name,
-    &manifest_full_object_path
-  );
+    &new_manifest_file_name, &bucket_name, &manifest_full_object_path
+  ));
 
   match ims::s3_client::s3_upload_object(
     &sts_value,
@@ -708,7 +1353,7 @@ async fn s3_upload_image_artifacts(
   .await
   {
     Ok(_result) => {
-      log::info!("OK");
+      events::info("OK");
     }
     Err(error) => {
       return Err(Error::MigrateOp(format!(
@@ -723,7 +1368,7 @@ async fn s3_upload_image_artifacts(
 /// Return the md5sum of a file. Returns `Err` if the file cannot be
 /// opened, its metadata cannot be read, or an I/O error occurs while
 /// reading.
-fn file_md5sum(filename: PathBuf) -> Result<Digest, Error> {
+pub(crate) fn file_md5sum(filename: PathBuf) -> Result<Digest, Error> {
   log::debug!("File {}...", filename.display());
 
   let f = File::open(&filename).map_err(|e| {
@@ -743,7 +1388,7 @@ fn file_md5sum(filename: PathBuf) -> Result<Digest, Error> {
   let buf_len = len.min(100_000_000) as usize;
   let mut buf = BufReader::with_capacity(buf_len, f);
   let mut context = md5::Context::new();
-  let bar = ProgressBar::new(len);
+  let bar = progress_bar(len);
   // BAR_FORMAT is a compile-time constant — template parse is infallible.
   bar.set_style(
     ProgressStyle::with_template(BAR_FORMAT).expect("BAR_FORMAT is valid"),
@@ -783,13 +1428,13 @@ fn calculate_image_checksums(
     let file_size = match fs::metadata(file) {
       Ok(file_metadata) => humansize::format_size(file_metadata.len(), DECIMAL),
       Err(e) => {
-        log::warn!(
+        events::warning(format!(
           "Unable to fetch file metadata info, faking the value. Error: {e}"
-        );
+        ));
         "-1".to_string()
       }
     };
-    log::info!("File {:?} ({})...", &file, &file_size);
+    events::info(format!("File {:?} ({})...", &file, &file_size));
     let artifact;
     let mut fp = PathBuf::new();
     fp.push(file);
@@ -866,7 +1511,7 @@ async fn ims_register_image(
     )));
   }
 
-  let json_response = crate::ShastaClient::new(
+  let json_response = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -920,12 +1565,14 @@ pub fn get_image_name_from_ims_file(
 // Anything in this function is critical, so the asserts will kill further processing
 /// # Errors
 ///
-/// Returns an [`Error`] variant on CSM, transport, or
-/// deserialization failure; see the crate-level `Error` enum
-/// for the full set.
+/// Returns an [`Error`] variant if `execution_context` can't act on one
+/// of the groups, checked before any is created, or on CSM, transport,
+/// or deserialization failure; see the crate-level `Error` enum for the
+/// full set.
 pub async fn create_hsm_group_from_file(
   // backend: &StaticBackendDispatcher,
   shasta_token: &str,
+  execution_context: &ExecutionContext,
   shasta_base_url: &str,
   shasta_root_cert: &[u8],
   socks5_proxy: Option<&str>,
@@ -939,7 +1586,11 @@ pub async fn create_hsm_group_from_file(
 
   let group_vec: Vec<Group> = serde_json::from_str(&hsm_data)?;
 
-  let shasta_client = crate::ShastaClient::new(
+  for group in &group_vec {
+    execution_context.check_owns(&group.label.0)?;
+  }
+
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -979,15 +1630,12 @@ pub async fn create_hsm_group_from_file(
       .await
     {
       Ok(group) => {
-        log::info!(
-          "The HSM group {} has been created successfully.",
-          &group.label.0
-        );
+        events::created("HSM group", &group.label.0);
       }
       Err(error) => {
         if error.to_string().to_lowercase().contains("409") {
           if overwrite {
-            log::info!("Looks like you want to continue");
+            events::info("Looks like you want to continue");
             match shasta_client
               .hsm_group_delete_group(shasta_token, &group.label.0)
               .await
@@ -999,10 +1647,7 @@ pub async fn create_hsm_group_from_file(
                   .await
                 {
                   Ok(_json) => {
-                    log::info!(
-                      "The HSM group {} has been created successfully.",
-                      &group.label.0
-                    );
+                    events::created("HSM group", &group.label.0);
                   }
                   Err(e) => {
                     log::error!("Error message {e}");
@@ -1036,3 +1681,102 @@ pub async fn create_hsm_group_from_file(
 
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn backup_references_are_checked_and_remapped() {
+    let mut bos_sessiontemplate: BosSessionTemplate =
+      serde_json::from_value(serde_json::json!({
+        "name": "zinal-cos",
+        "cfs": { "configuration": "zinal-cos-config" },
+        "boot_sets": {
+          "compute": {
+            "path": "s3://boot-images/old-id/manifest.json",
+            "node_groups": ["zinal"]
+          }
+        }
+      }))
+      .unwrap();
+    let hsm_group_vec: Vec<Group> =
+      serde_json::from_value(serde_json::json!([{ "label": "zinal" }]))
+        .unwrap();
+
+    let mut manifest = BackupManifest {
+      version: crate::commands::migrate_backup::BACKUP_FORMAT_VERSION,
+      created_at: chrono::Utc::now(),
+      bos_session_template: "zinal-cos".to_string(),
+      cfs_configuration: "zinal-cos-config".to_string(),
+      hsm_groups: vec!["zinal".to_string()],
+      images: vec![crate::commands::migrate_backup::BackupImage {
+        id: "old-id".to_string(),
+        name: "zinal-cos-image".to_string(),
+      }],
+      files: vec![BackupFile {
+        kind: BackupFileKind::ImsImage,
+        path: "old-id-ims.json".to_string(),
+        md5: String::new(),
+        image_id: Some("old-id".to_string()),
+      }],
+    };
+    for artifact in IMAGE_ARTIFACT_FILE_NAMES {
+      manifest.files.push(BackupFile {
+        kind: BackupFileKind::ImageArtifact,
+        path: format!("old-id/{artifact}"),
+        md5: String::new(),
+        image_id: Some("old-id".to_string()),
+      });
+    }
+
+    assert!(
+      check_backup_references(
+        &manifest,
+        &bos_sessiontemplate,
+        "zinal-cos-config",
+        &hsm_group_vec,
+      )
+      .is_ok()
+    );
+
+    // rootfs missing, and the HSM group not backed up
+    manifest.files.pop();
+    assert!(matches!(
+      check_backup_references(
+        &manifest,
+        &bos_sessiontemplate,
+        "zinal-cos-config",
+        &[],
+      ),
+      Err(Error::MigrateOp(msg))
+        if msg.contains("rootfs of image 'old-id' missing")
+          && msg.contains("HSM group 'zinal' not backed up")
+    ));
+
+    let url_remap = vec![(
+      "https://api-gw-service-nmn.local/vcs/".to_string(),
+      "https://vcs.target.example/".to_string(),
+    )];
+    assert_eq!(
+      remap_url(
+        "https://api-gw-service-nmn.local/vcs/cray/cos-config-management.git",
+        &url_remap
+      ),
+      "https://vcs.target.example/cray/cos-config-management.git"
+    );
+    assert_eq!(
+      remap_url("https://github.com/other.git", &url_remap),
+      "https://github.com/other.git"
+    );
+
+    remap_boot_set_images(
+      &mut bos_sessiontemplate,
+      &HashMap::from([("old-id".to_string(), "new-id".to_string())]),
+    );
+    assert_eq!(
+      bos_sessiontemplate.images_id().collect::<Vec<_>>(),
+      vec!["new-id"]
+    );
+  }
+}
@@ -4,37 +4,80 @@
 //! orchestrating several lower-level calls across [`crate::cfs`],
 //! [`crate::ims`], [`crate::bos`], [`crate::hsm`], etc.
 //!
+//! User-facing progress (steps, created and deleted resources, warnings,
+//! retries) is reported as [`crate::Event`]s to the [`crate::EventSink`]
+//! set with [`crate::ClientConfig::with_event_sink`] on the client whose
+//! [`crate::ShastaClient::scope`] the command runs in, stdout by default.
+//!
+//! The commands changing the system take an [`crate::ExecutionContext`]
+//! next to the Shasta token: the token authenticates the CSM calls,
+//! the context says which HSM groups the command may touch and who
+//! the changes are attributed to in the webhook events.
+//!
 //! Submodules:
 //!
 //! - [`apply_hw_cluster_pin`] — apply a hardware pattern to (re)compose
 //!   an HSM group from a parent group.
+//! - [`apply_kernel_parameters`] — add, replace or remove the kernel
+//!   parameters of an HSM group's nodes in BSS, optionally staging a
+//!   reboot.
+//! - [`apply_runtime_configuration`] — set the runtime CFS
+//!   configuration of nodes and follow the CFS batcher until they are
+//!   configured.
 //! - [`apply_session`] — run a CFS session against a set of nodes.
 //! - [`delete_and_cancel_session`] — cancel an in-flight CFS session and
 //!   clean up its derived resources.
 //! - [`delete_configurations_and_data_related`] — remove a CFS
 //!   configuration along with its dependent images and session templates.
+//! - [`diagnose_boot`] — correlate what BSS, IMS, BOS, HSM, Kea and
+//!   CFS know about a node with its console log to explain a failed
+//!   boot.
+//! - [`gc_cfs_session_pods`] — delete the Kubernetes jobs and pods
+//!   left behind by long completed CFS sessions. Requires the
+//!   `k8s-console` Cargo feature.
+//! - [`get_boot_image_report`] — per HSM group, which images the nodes
+//!   boot and which nodes lag behind the group's latest BOS template.
+//! - [`get_firmware_report`] — per node of an HSM group, the firmware
+//!   FAS reads behind its BMC, flagged against a YAML baseline.
 //! - [`get_images_and_details`] — fetch IMS images plus the CFS
 //!   configurations and BOS templates that reference them.
+//! - [`restore_groups`] — reconcile HSM groups with a snapshot taken by
+//!   [`snapshot_groups`], with a diff preview.
+//! - [`snapshot_groups`] — export every HSM group to a versioned JSON
+//!   file.
 //!
 //! The following live behind the `commands-admin` Cargo feature
 //! because they are CLI-shaped (file I/O, YAML parsing, progress bars)
 //! rather than composable library primitives:
 //!
+//! - `build_image` — build an image from an IMS image or recipe, or a
+//!   product, with a CFS session, without a SAT file.
 //! - `i_apply_sat_file` — apply a SAT (System Admin Toolkit) YAML file.
 //! - `migrate_backup` / `migrate_restore` — export or import the
 //!   CSM-side artifacts required to move a cluster between systems.
 
 pub mod apply_hw_cluster_pin;
+pub mod apply_kernel_parameters;
+pub mod apply_runtime_configuration;
 pub mod apply_session;
 pub mod delete_and_cancel_session;
 pub mod delete_configurations_and_data_related;
+pub mod diagnose_boot;
+#[cfg(feature = "k8s-console")]
+pub mod gc_cfs_session_pods;
+pub mod get_boot_image_report;
+pub mod get_firmware_report;
 pub mod get_images_and_details;
+pub mod restore_groups;
+pub mod snapshot_groups;
 
 // Admin-CLI orchestration workflows (file I/O, YAML parsing, S3
 // progress bars, reboot timing). Gated behind the `commands-admin`
 // Cargo feature so the default library surface stays focused on
 // composable CSM primitives.
 #[cfg(feature = "commands-admin")]
+pub mod build_image;
+#[cfg(feature = "commands-admin")]
 pub mod i_apply_sat_file;
 #[cfg(feature = "commands-admin")]
 pub mod migrate_backup;
@@ -0,0 +1,406 @@
+//! Reconcile HSM groups with a [`GroupSnapshot`].
+//!
+//! [`plan`] diffs the snapshot against the live groups into a list of
+//! [`GroupChange`]s, which [`exec`] reports (the diff preview) and,
+//! unless in dry run, applies. Changes are applied in the order they are
+//! listed: members are removed before they are added elsewhere, so
+//! moving nodes between groups of the same exclusive group works.
+//!
+//! HSM can't change the exclusive group of an existing group; a group
+//! whose exclusive group differs from the snapshot is reported and left
+//! as is.
+
+use std::{
+  collections::{BTreeSet, HashMap},
+  fmt,
+};
+
+use serde::Serialize;
+
+use crate::{
+  ExecutionContext,
+  commands::snapshot_groups::{GroupSnapshot, SnapshotGroup},
+  common::events,
+  error::Error,
+  hsm::group::{
+    GroupExt,
+    types::{Group, Member},
+  },
+};
+
+/// One change needed to make the live HSM groups match a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum GroupChange {
+  /// Remove `xnames` from group `label`.
+  RemoveMembers {
+    /// HSM group label.
+    label: String,
+    /// Members to remove.
+    xnames: Vec<String>,
+  },
+  /// Replace the description and/or tags of group `label`; `None`
+  /// leaves the field as is.
+  UpdateGroup {
+    /// HSM group label.
+    label: String,
+    /// New description.
+    description: Option<String>,
+    /// New tags.
+    tags: Option<Vec<String>>,
+  },
+  /// Create a group missing from CSM, with its members.
+  CreateGroup(SnapshotGroup),
+  /// Add `xnames` to group `label`.
+  AddMembers {
+    /// HSM group label.
+    label: String,
+    /// Members to add.
+    xnames: Vec<String>,
+  },
+  /// Delete group `label`, which is not in the snapshot.
+  DeleteGroup {
+    /// HSM group label.
+    label: String,
+  },
+}
+
+impl GroupChange {
+  /// Label of the HSM group changed.
+  #[must_use]
+  pub fn label(&self) -> &str {
+    match self {
+      Self::RemoveMembers { label, .. }
+      | Self::UpdateGroup { label, .. }
+      | Self::AddMembers { label, .. }
+      | Self::DeleteGroup { label } => label,
+      Self::CreateGroup(group) => &group.label,
+    }
+  }
+}
+
+impl fmt::Display for GroupChange {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::RemoveMembers { label, xnames } => {
+        write!(f, "- {label}: members {}", xnames.join(","))
+      }
+      Self::UpdateGroup {
+        label,
+        description,
+        tags,
+      } => {
+        write!(f, "~ {label}:")?;
+        if let Some(description) = description {
+          write!(f, " description '{description}'")?;
+        }
+        if let Some(tags) = tags {
+          write!(f, " tags [{}]", tags.join(","))?;
+        }
+        Ok(())
+      }
+      Self::CreateGroup(group) => write!(
+        f,
+        "+ {}: new group with members {}",
+        group.label,
+        group.members.join(",")
+      ),
+      Self::AddMembers { label, xnames } => {
+        write!(f, "+ {label}: members {}", xnames.join(","))
+      }
+      Self::DeleteGroup { label } => write!(f, "- {label}: delete group"),
+    }
+  }
+}
+
+/// Changes that make `group_vec` (the live HSM groups) match
+/// `snapshot`. Groups missing from the snapshot are deleted only if
+/// `prune`.
+#[must_use]
+pub fn plan(
+  snapshot: &GroupSnapshot,
+  group_vec: &[Group],
+  prune: bool,
+) -> Vec<GroupChange> {
+  let current_by_label: HashMap<&str, &Group> = group_vec
+    .iter()
+    .map(|group| (group.label.0.as_str(), group))
+    .collect();
+
+  let mut remove_vec = Vec::new();
+  let mut update_vec = Vec::new();
+  let mut create_vec = Vec::new();
+  let mut add_vec = Vec::new();
+
+  for expected in &snapshot.groups {
+    let Some(current) = current_by_label.get(expected.label.as_str()) else {
+      create_vec.push(GroupChange::CreateGroup(expected.clone()));
+      continue;
+    };
+
+    let current_description = current
+      .description
+      .as_deref()
+      .filter(|description| !description.is_empty());
+    let description = (current_description != expected.description.as_deref())
+      .then(|| expected.description.clone().unwrap_or_default());
+
+    let current_tags: BTreeSet<&str> =
+      current.tags.iter().map(|tag| tag.0.as_str()).collect();
+    let expected_tags: BTreeSet<&str> =
+      expected.tags.iter().map(String::as_str).collect();
+    let tags = (current_tags != expected_tags).then(|| expected.tags.clone());
+
+    if description.is_some() || tags.is_some() {
+      update_vec.push(GroupChange::UpdateGroup {
+        label: expected.label.clone(),
+        description,
+        tags,
+      });
+    }
+
+    let current_exclusive_group = current
+      .exclusive_group
+      .as_ref()
+      .map(|exclusive_group| exclusive_group.0.as_str())
+      .filter(|exclusive_group| !exclusive_group.is_empty());
+    if current_exclusive_group != expected.exclusive_group.as_deref() {
+      events::warning(format!(
+        "HSM group '{}' exclusive group is {:?} but {:?} in the snapshot; HSM can't change it, leaving as is",
+        expected.label, current_exclusive_group, expected.exclusive_group
+      ));
+    }
+
+    let current_members: BTreeSet<String> =
+      current.get_members().into_iter().collect();
+    let expected_members: BTreeSet<String> =
+      expected.members.iter().cloned().collect();
+
+    let xnames: Vec<String> = current_members
+      .difference(&expected_members)
+      .cloned()
+      .collect();
+    if !xnames.is_empty() {
+      remove_vec.push(GroupChange::RemoveMembers {
+        label: expected.label.clone(),
+        xnames,
+      });
+    }
+
+    let xnames: Vec<String> = expected_members
+      .difference(&current_members)
+      .cloned()
+      .collect();
+    if !xnames.is_empty() {
+      add_vec.push(GroupChange::AddMembers {
+        label: expected.label.clone(),
+        xnames,
+      });
+    }
+  }
+
+  let mut delete_label_vec: Vec<&str> = if prune {
+    group_vec
+      .iter()
+      .map(|group| group.label.0.as_str())
+      .filter(|label| snapshot.group(label).is_none())
+      .collect()
+  } else {
+    Vec::new()
+  };
+  delete_label_vec.sort_unstable();
+  let delete_vec =
+    delete_label_vec
+      .into_iter()
+      .map(|label| GroupChange::DeleteGroup {
+        label: label.to_string(),
+      });
+
+  remove_vec
+    .into_iter()
+    .chain(update_vec)
+    .chain(create_vec)
+    .chain(add_vec)
+    .chain(delete_vec)
+    .collect()
+}
+
+/// Make the live HSM groups match `snapshot`.
+///
+/// Every change is reported as an [`crate::Event`] before anything is
+/// applied. With `dry_run` nothing is changed. Groups not in the
+/// snapshot are deleted only if `prune`, and only among the groups
+/// `execution_context` can act on.
+///
+/// Returns the planned changes.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant if the snapshot changes a group
+/// `execution_context` can't act on, or on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set. Changes applied before the failing one are not
+/// rolled back.
+pub async fn exec(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  snapshot: &GroupSnapshot,
+  prune: bool,
+  dry_run: bool,
+) -> Result<Vec<GroupChange>, Error> {
+  let _timer = crate::common::metrics::CommandTimer::start("restore_groups");
+
+  let mut group_vec = client.hsm_group_get_all(shasta_token).await?;
+  group_vec.retain(|group| execution_context.tenancy().owns(&group.label.0));
+
+  let change_vec = plan(snapshot, &group_vec, prune);
+  for change in &change_vec {
+    execution_context.check_owns(change.label())?;
+  }
+
+  client
+    .scope(execution_context.scope(apply(
+      client,
+      shasta_token,
+      change_vec,
+      dry_run,
+    )))
+    .await
+}
+
+/// Report `change_vec` and, unless `dry_run`, apply it. Body of
+/// [`exec`], run inside its execution context.
+async fn apply(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  change_vec: Vec<GroupChange>,
+  dry_run: bool,
+) -> Result<Vec<GroupChange>, Error> {
+
+  if change_vec.is_empty() {
+    events::info("HSM groups already match the snapshot");
+    return Ok(change_vec);
+  }
+
+  for change in &change_vec {
+    events::info(change.to_string());
+  }
+
+  if dry_run {
+    events::info("Dry run mode: HSM groups not changed");
+    return Ok(change_vec);
+  }
+
+  for change in &change_vec {
+    match change {
+      GroupChange::RemoveMembers { label, xnames } => {
+        for xname in xnames {
+          client
+            .hsm_group_delete_member(shasta_token, label, xname)
+            .await?;
+        }
+      }
+      GroupChange::UpdateGroup {
+        label,
+        description,
+        tags,
+      } => {
+        client
+          .hsm_group_patch(
+            shasta_token,
+            label,
+            description.as_deref(),
+            tags.as_deref(),
+          )
+          .await?;
+      }
+      GroupChange::CreateGroup(group) => {
+        client
+          .hsm_group_post(shasta_token, group.to_group())
+          .await?;
+        events::created("HSM group", &group.label);
+      }
+      GroupChange::AddMembers { label, xnames } => {
+        for xname in xnames {
+          client
+            .hsm_group_post_member(
+              shasta_token,
+              label,
+              Member {
+                id: Some(xname.clone()),
+              },
+            )
+            .await?;
+        }
+      }
+      GroupChange::DeleteGroup { label } => {
+        client.hsm_group_delete_group(shasta_token, label).await?;
+        events::deleted("HSM group", label);
+      }
+    }
+  }
+
+  Ok(change_vec)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::hsm::group::types::ResourceName;
+
+  #[test]
+  fn plan_orders_removals_before_additions() {
+    let snapshot = GroupSnapshot::new(&[
+      Group::new_with_members("zinal", Some(vec!["x1", "x2"])),
+      Group::new_with_members("eiger", Some(vec!["x3"])),
+      Group::new_with_members("daint", Some(vec!["x4"])),
+    ]);
+
+    let mut zinal = Group::new_with_members("zinal", Some(vec!["x1"]));
+    zinal.description = Some("moved".to_string());
+    let live = vec![
+      zinal,
+      Group::new_with_members("eiger", Some(vec!["x2", "x3"])),
+      Group::new_with_members("tmp", None),
+    ];
+
+    let expected_daint = snapshot.group("daint").unwrap().clone();
+    assert_eq!(
+      plan(&snapshot, &live, true),
+      vec![
+        GroupChange::RemoveMembers {
+          label: "eiger".to_string(),
+          xnames: vec!["x2".to_string()],
+        },
+        GroupChange::UpdateGroup {
+          label: "zinal".to_string(),
+          description: Some(String::new()),
+          tags: None,
+        },
+        GroupChange::CreateGroup(expected_daint),
+        GroupChange::AddMembers {
+          label: "zinal".to_string(),
+          xnames: vec!["x2".to_string()],
+        },
+        GroupChange::DeleteGroup {
+          label: "tmp".to_string(),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn plan_ignores_tag_order_and_keeps_extra_groups_without_prune() {
+    let mut group = Group::new_with_members("zinal", None);
+    group.tags =
+      vec![ResourceName("b".to_string()), ResourceName("a".to_string())];
+    let snapshot = GroupSnapshot::new(&[group.clone()]);
+
+    group.tags.reverse();
+    let live = vec![group, Group::new_with_members("tmp", None)];
+
+    assert!(plan(&snapshot, &live, false).is_empty());
+  }
+}
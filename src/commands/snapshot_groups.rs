@@ -0,0 +1,218 @@
+//! Export every HSM group to a versioned JSON file.
+//!
+//! The snapshot records each group's label, description, tags,
+//! exclusive group and members, so the grouping can be put back with
+//! [`crate::commands::restore_groups`] after a large re-grouping (e.g.
+//! through [`crate::commands::apply_hw_cluster_pin`]) goes wrong.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  common::events,
+  error::Error,
+  hsm::group::{
+    GroupExt,
+    types::{Group, Members, ResourceName, XNameRw100},
+  },
+};
+
+/// Format version written to new snapshots. [`GroupSnapshot::read`]
+/// rejects files with any other version.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// One HSM group as recorded in a [`GroupSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotGroup {
+  /// HSM group label.
+  pub label: String,
+  /// Group description.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  /// Group tags, sorted.
+  #[serde(default)]
+  pub tags: Vec<String>,
+  /// Exclusive group the group belongs to.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub exclusive_group: Option<String>,
+  /// Member xnames, sorted.
+  #[serde(default)]
+  pub members: Vec<String>,
+}
+
+impl SnapshotGroup {
+  fn from_group(group: &Group) -> Self {
+    let mut tags: Vec<String> =
+      group.tags.iter().map(|tag| tag.0.clone()).collect();
+    tags.sort();
+
+    let mut members = group.get_members();
+    members.sort();
+
+    Self {
+      label: group.label.0.clone(),
+      description: group
+        .description
+        .clone()
+        .filter(|description| !description.is_empty()),
+      tags,
+      exclusive_group: group
+        .exclusive_group
+        .as_ref()
+        .map(|exclusive_group| exclusive_group.0.clone())
+        .filter(|exclusive_group| !exclusive_group.is_empty()),
+      members,
+    }
+  }
+
+  /// HSM group to create from this entry.
+  #[must_use]
+  pub fn to_group(&self) -> Group {
+    Group {
+      label: ResourceName(self.label.clone()),
+      description: self.description.clone(),
+      tags: self.tags.iter().cloned().map(ResourceName).collect(),
+      exclusive_group: self.exclusive_group.clone().map(ResourceName),
+      members: Some(Members {
+        ids: self.members.iter().cloned().map(XNameRw100).collect(),
+      }),
+    }
+  }
+}
+
+/// Every HSM group at a point in time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupSnapshot {
+  /// Snapshot format version, see [`SNAPSHOT_FORMAT_VERSION`].
+  pub version: u32,
+  /// When the snapshot was taken.
+  pub created_at: DateTime<Utc>,
+  /// HSM groups, sorted by label.
+  pub groups: Vec<SnapshotGroup>,
+}
+
+impl GroupSnapshot {
+  /// Snapshot of `group_vec`, taken now.
+  #[must_use]
+  pub fn new(group_vec: &[Group]) -> Self {
+    let mut groups: Vec<SnapshotGroup> =
+      group_vec.iter().map(SnapshotGroup::from_group).collect();
+    groups.sort_by(|a, b| a.label.cmp(&b.label));
+
+    Self {
+      version: SNAPSHOT_FORMAT_VERSION,
+      created_at: Utc::now(),
+      groups,
+    }
+  }
+
+  /// Group labelled `label`, if any.
+  #[must_use]
+  pub fn group(&self, label: &str) -> Option<&SnapshotGroup> {
+    self.groups.iter().find(|group| group.label == label)
+  }
+
+  /// Write the snapshot as pretty-printed JSON to `path`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::IoError`] if the file can't be written.
+  pub fn write(&self, path: &Path) -> Result<(), Error> {
+    std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+    Ok(())
+  }
+
+  /// Read a snapshot written by [`Self::write`].
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::IoError`] if the file can't be read, and
+  /// [`Error::GroupSnapshot`] if it isn't a snapshot or was written in
+  /// an unsupported format version.
+  pub fn read(path: &Path) -> Result<Self, Error> {
+    Self::from_json(&std::fs::read_to_string(path)?)
+  }
+
+  fn from_json(json: &str) -> Result<Self, Error> {
+    let value: serde_json::Value = serde_json::from_str(json)
+      .map_err(|e| Error::GroupSnapshot(e.to_string()))?;
+
+    // Checked before parsing the rest, whose shape depends on it
+    let version = value
+      .get("version")
+      .and_then(serde_json::Value::as_u64)
+      .ok_or_else(|| {
+        Error::GroupSnapshot("'version' missing or not a number".to_string())
+      })?;
+    if version != u64::from(SNAPSHOT_FORMAT_VERSION) {
+      return Err(Error::GroupSnapshot(format!(
+        "unsupported format version {version}, expected {SNAPSHOT_FORMAT_VERSION}"
+      )));
+    }
+
+    serde_json::from_value(value)
+      .map_err(|e| Error::GroupSnapshot(e.to_string()))
+  }
+}
+
+/// Snapshot every HSM group and write it to `destination`.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure, and [`Error::IoError`] if the snapshot
+/// can't be written.
+pub async fn exec(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  destination: &Path,
+) -> Result<GroupSnapshot, Error> {
+  let _timer = crate::common::metrics::CommandTimer::start("snapshot_groups");
+
+  let group_vec = client.hsm_group_get_all(shasta_token).await?;
+
+  let snapshot = GroupSnapshot::new(&group_vec);
+  snapshot.write(destination)?;
+
+  events::created(
+    "HSM group snapshot",
+    format!(
+      "{} ({} groups)",
+      destination.display(),
+      snapshot.groups.len()
+    ),
+  );
+
+  Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn snapshot_round_trips_and_rejects_other_versions() {
+    let mut group = Group::new_with_members(
+      "zinal",
+      Some(vec!["x1000c0s1b0n0", "x1000c0s0b0n0"]),
+    );
+    group.tags = vec![ResourceName("compute".to_string())];
+
+    let snapshot = GroupSnapshot::new(&[group]);
+    assert_eq!(
+      snapshot.group("zinal").unwrap().members,
+      vec!["x1000c0s0b0n0".to_string(), "x1000c0s1b0n0".to_string()]
+    );
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    assert_eq!(GroupSnapshot::from_json(&json).unwrap(), snapshot);
+
+    let future = json.replacen("\"version\":1", "\"version\":2", 1);
+    assert!(matches!(
+      GroupSnapshot::from_json(&future),
+      Err(Error::GroupSnapshot(_))
+    ));
+  }
+}
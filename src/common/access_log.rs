@@ -0,0 +1,230 @@
+//! In-memory record of the CSM API calls a command makes.
+//!
+//! When a SAT apply fails deep inside a nested call chain, the error
+//! alone rarely tells which request went wrong. Running the command
+//! inside [`AccessLog::record`] lists every CSM call it made — method,
+//! path, status and duration — for the caller to inspect or dump once
+//! the command returns:
+//!
+//! ```no_run
+//! # async fn example(client: &csm_rs::ShastaClient, token: &str) {
+//! use csm_rs::AccessLog;
+//!
+//! let access_log = AccessLog::new();
+//! let result = access_log.record(client.ims_image_get_all(token)).await;
+//! if result.is_err() {
+//!   eprintln!("{access_log}");
+//! }
+//! # }
+//! ```
+//!
+//! Paths are recorded without their query string. Calls made outside of
+//! [`AccessLog::record`] are not recorded.
+
+use std::{
+  collections::VecDeque,
+  fmt,
+  future::Future,
+  sync::{Arc, Mutex, MutexGuard, PoisonError},
+  time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+tokio::task_local! {
+  static ACCESS_LOG: AccessLog;
+}
+
+/// Calls an [`AccessLog::new`] log keeps; older ones are dropped.
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// One CSM API call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiCall {
+  /// When the request was sent.
+  pub started_at: DateTime<Utc>,
+  /// HTTP method, `*` for calls through the generated HSM client,
+  /// which doesn't expose it.
+  pub method: String,
+  /// URL path, or the CSM service for calls through the generated HSM
+  /// client.
+  pub path: String,
+  /// HTTP status, `None` if no response was received.
+  pub status: Option<u16>,
+  /// Time until the response headers were received.
+  pub duration: Duration,
+}
+
+impl fmt::Display for ApiCall {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} {} {} -> {} ({} ms)",
+      self.started_at.to_rfc3339(),
+      self.method,
+      self.path,
+      self
+        .status
+        .map_or_else(|| "no response".to_string(), |status| status.to_string()),
+      self.duration.as_millis()
+    )
+  }
+}
+
+/// Most recent CSM API calls of the futures run with [`Self::record`],
+/// see the [module docs](self). Clones share the same calls.
+#[derive(Debug, Clone)]
+pub struct AccessLog {
+  capacity: usize,
+  call_deque: Arc<Mutex<VecDeque<ApiCall>>>,
+}
+
+impl Default for AccessLog {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl AccessLog {
+  /// Empty log keeping the last [`DEFAULT_CAPACITY`] calls.
+  #[must_use]
+  pub fn new() -> Self {
+    Self::with_capacity(DEFAULT_CAPACITY)
+  }
+
+  /// Empty log keeping the last `capacity` calls.
+  #[must_use]
+  pub fn with_capacity(capacity: usize) -> Self {
+    Self {
+      capacity,
+      call_deque: Arc::default(),
+    }
+  }
+
+  /// Run `fut`, recording the CSM API calls it makes in this log,
+  /// including those of the tasks it spawns through csm-rs.
+  pub async fn record<F: Future>(&self, fut: F) -> F::Output {
+    ACCESS_LOG.scope(self.clone(), fut).await
+  }
+
+  /// Calls recorded so far, oldest first.
+  #[must_use]
+  pub fn calls(&self) -> Vec<ApiCall> {
+    self.lock().iter().cloned().collect()
+  }
+
+  /// Calls that received no response or a non-2xx status.
+  #[must_use]
+  pub fn failed_calls(&self) -> Vec<ApiCall> {
+    self
+      .lock()
+      .iter()
+      .filter(|call| {
+        call
+          .status
+          .is_none_or(|status| !(200..300).contains(&status))
+      })
+      .cloned()
+      .collect()
+  }
+
+  fn push(&self, call: ApiCall) {
+    let mut call_deque = self.lock();
+    if call_deque.len() == self.capacity {
+      call_deque.pop_front();
+    }
+    if self.capacity > 0 {
+      call_deque.push_back(call);
+    }
+  }
+
+  fn lock(&self) -> MutexGuard<'_, VecDeque<ApiCall>> {
+    self
+      .call_deque
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+impl fmt::Display for AccessLog {
+  /// One call per line, oldest first.
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (index, call) in self.lock().iter().enumerate() {
+      if index > 0 {
+        writeln!(f)?;
+      }
+      write!(f, "{call}")?;
+    }
+    Ok(())
+  }
+}
+
+/// Access log of the command running on the current task, if any.
+pub(crate) fn current() -> Option<AccessLog> {
+  ACCESS_LOG.try_with(Clone::clone).ok()
+}
+
+/// Run `fut` recording in `access_log_opt`, if any; carries the access
+/// log into spawned tasks.
+pub(crate) async fn scope<F: Future>(
+  access_log_opt: Option<AccessLog>,
+  fut: F,
+) -> F::Output {
+  match access_log_opt {
+    Some(access_log) => ACCESS_LOG.scope(access_log, fut).await,
+    None => fut.await,
+  }
+}
+
+/// Add a call that took `duration` and ended now to the current access
+/// log, if any.
+pub(crate) fn record(
+  method: &str,
+  path: &str,
+  status_opt: Option<u16>,
+  duration: Duration,
+) {
+  let _ = ACCESS_LOG.try_with(|access_log| {
+    access_log.push(ApiCall {
+      started_at: Utc::now()
+        - chrono::TimeDelta::from_std(duration).unwrap_or_default(),
+      method: method.to_string(),
+      path: path.to_string(),
+      status: status_opt,
+      duration,
+    });
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn only_calls_inside_record_are_kept_up_to_capacity() {
+    let access_log = AccessLog::with_capacity(2);
+
+    record("GET", "/outside", Some(200), Duration::ZERO);
+    access_log
+      .record(async {
+        record("GET", "/cfs/v2/sessions", Some(200), Duration::ZERO);
+        record("POST", "/bos/v2/sessions", Some(400), Duration::ZERO);
+        record("GET", "/ims/v3/images", None, Duration::from_millis(5));
+      })
+      .await;
+
+    let path_vec: Vec<String> = access_log
+      .calls()
+      .into_iter()
+      .map(|call| call.path)
+      .collect();
+    assert_eq!(path_vec, vec!["/bos/v2/sessions", "/ims/v3/images"]);
+    assert_eq!(access_log.failed_calls().len(), 2);
+    assert!(
+      access_log
+        .to_string()
+        .ends_with("GET /ims/v3/images -> no response (5 ms)")
+    );
+  }
+}
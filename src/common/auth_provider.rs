@@ -0,0 +1,356 @@
+//! Where the bearer token of a [`crate::ShastaClient`] comes from.
+//!
+//! The API methods take the token as `shasta_token: &str`, which is fine
+//! for one-shot calls but breaks long operations (SAT apply, image
+//! builds, watches) once the token expires. An [`AuthTokenProvider`]
+//! hands out a valid token on demand instead:
+//!
+//! - [`StaticToken`] — always the same token, for callers that manage
+//!   tokens themselves.
+//! - [`KeycloakPasswordGrant`] — logs in to Keycloak with a username and
+//!   password, and logs in again shortly before the token expires.
+//! - [`ClientCredentials`] — same for a Keycloak service account, using
+//!   the refresh token when Keycloak issues one.
+//!
+//! A provider set with `ShastaClient::with_token_provider` is asked for
+//! a token before every request of `ShastaClient::with_token` and of
+//! every poll of [`crate::watch::watch`]; the `&str` methods stay
+//! available and keep working with a token obtained from
+//! `ShastaClient::token`.
+
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::future::BoxFuture;
+use tokio::sync::Mutex;
+
+use crate::{
+  common::{
+    authentication::{self, TokenResponse},
+    http, jwt_ops,
+  },
+  error::Error,
+};
+
+/// Tokens expiring within this margin are renewed before use, so they
+/// don't expire in flight.
+const RENEWAL_MARGIN: TimeDelta = TimeDelta::seconds(30);
+
+/// Source of bearer tokens for CSM requests, see the
+/// [module docs](self).
+pub trait AuthTokenProvider: Send + Sync + fmt::Debug {
+  /// A token valid for at least the next few seconds.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant if a new token is needed and can't be
+  /// obtained.
+  fn token(&self) -> BoxFuture<'_, Result<String, Error>>;
+
+  /// Forget the current token, e.g. after CSM rejected it, so the next
+  /// [`Self::token`] obtains a new one. Does nothing by default.
+  fn invalidate(&self) -> BoxFuture<'_, ()> {
+    Box::pin(async {})
+  }
+}
+
+impl<P: AuthTokenProvider + ?Sized> AuthTokenProvider for Arc<P> {
+  fn token(&self) -> BoxFuture<'_, Result<String, Error>> {
+    (**self).token()
+  }
+
+  fn invalidate(&self) -> BoxFuture<'_, ()> {
+    (**self).invalidate()
+  }
+}
+
+/// Always the same token, which is never renewed.
+#[derive(Clone)]
+pub struct StaticToken(String);
+
+impl StaticToken {
+  /// Provider handing out `token`.
+  #[must_use]
+  pub fn new(token: impl Into<String>) -> Self {
+    Self(token.into())
+  }
+}
+
+impl fmt::Debug for StaticToken {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("StaticToken").field(&"<redacted>").finish()
+  }
+}
+
+impl AuthTokenProvider for StaticToken {
+  fn token(&self) -> BoxFuture<'_, Result<String, Error>> {
+    Box::pin(async { Ok(self.0.clone()) })
+  }
+}
+
+/// Keycloak `password` grant of user `username` on the `shasta` client,
+/// renewed shortly before it expires.
+pub struct KeycloakPasswordGrant {
+  username: String,
+  password: String,
+  cache: TokenCache,
+}
+
+impl KeycloakPasswordGrant {
+  /// Provider logging in to the Keycloak at `keycloak_base_url` as
+  /// `username`. Nothing is requested until the first token is needed.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::NetError`] if the HTTP client can't be built from
+  /// `root_cert` and `socks5_proxy`.
+  pub fn new(
+    keycloak_base_url: impl Into<String>,
+    root_cert: &[u8],
+    socks5_proxy: Option<&str>,
+    username: impl Into<String>,
+    password: impl Into<String>,
+  ) -> Result<Self, Error> {
+    Ok(Self {
+      username: username.into(),
+      password: password.into(),
+      cache: TokenCache::new(keycloak_base_url, root_cert, socks5_proxy)?,
+    })
+  }
+}
+
+impl fmt::Debug for KeycloakPasswordGrant {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("KeycloakPasswordGrant")
+      .field("keycloak_base_url", &self.cache.keycloak_base_url)
+      .field("username", &self.username)
+      .finish_non_exhaustive()
+  }
+}
+
+impl AuthTokenProvider for KeycloakPasswordGrant {
+  fn token(&self) -> BoxFuture<'_, Result<String, Error>> {
+    Box::pin(self.cache.token(HashMap::from([
+      ("grant_type", "password"),
+      ("client_id", "shasta"),
+      ("username", self.username.as_str()),
+      ("password", self.password.as_str()),
+    ])))
+  }
+
+  fn invalidate(&self) -> BoxFuture<'_, ()> {
+    Box::pin(self.cache.invalidate())
+  }
+}
+
+/// Keycloak `client_credentials` grant of a service account, refreshed
+/// with its refresh token, if any, or requested again shortly before it
+/// expires.
+pub struct ClientCredentials {
+  client_id: String,
+  client_secret: String,
+  cache: TokenCache,
+}
+
+impl ClientCredentials {
+  /// Provider authenticating as client `client_id` of the Keycloak at
+  /// `keycloak_base_url`. Nothing is requested until the first token is
+  /// needed.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::NetError`] if the HTTP client can't be built from
+  /// `root_cert` and `socks5_proxy`.
+  pub fn new(
+    keycloak_base_url: impl Into<String>,
+    root_cert: &[u8],
+    socks5_proxy: Option<&str>,
+    client_id: impl Into<String>,
+    client_secret: impl Into<String>,
+  ) -> Result<Self, Error> {
+    Ok(Self {
+      client_id: client_id.into(),
+      client_secret: client_secret.into(),
+      cache: TokenCache::new(keycloak_base_url, root_cert, socks5_proxy)?,
+    })
+  }
+}
+
+impl fmt::Debug for ClientCredentials {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ClientCredentials")
+      .field("keycloak_base_url", &self.cache.keycloak_base_url)
+      .field("client_id", &self.client_id)
+      .finish_non_exhaustive()
+  }
+}
+
+impl AuthTokenProvider for ClientCredentials {
+  fn token(&self) -> BoxFuture<'_, Result<String, Error>> {
+    Box::pin(self.cache.token(HashMap::from([
+      ("grant_type", "client_credentials"),
+      ("client_id", self.client_id.as_str()),
+      ("client_secret", self.client_secret.as_str()),
+    ])))
+  }
+
+  fn invalidate(&self) -> BoxFuture<'_, ()> {
+    Box::pin(self.cache.invalidate())
+  }
+}
+
+/// Token obtained from Keycloak, with what's needed to renew it.
+struct CachedToken {
+  access_token: String,
+  refresh_token: Option<String>,
+  expires_at: DateTime<Utc>,
+}
+
+impl CachedToken {
+  fn from_response(token_response: TokenResponse) -> Self {
+    // Prefer the token's own `exp` claim over the advertised lifetime
+    let expires_at = jwt_ops::get_expiration(&token_response.access_token)
+      .unwrap_or_else(|_| {
+        Utc::now()
+          + TimeDelta::seconds(token_response.expires_in.unwrap_or_default())
+      });
+
+    Self {
+      access_token: token_response.access_token,
+      refresh_token: token_response.refresh_token,
+      expires_at,
+    }
+  }
+
+  fn is_fresh(&self) -> bool {
+    Utc::now() + RENEWAL_MARGIN < self.expires_at
+  }
+}
+
+/// Keycloak token shared by the concurrent requests of a provider and
+/// renewed by the first of them to find it about to expire.
+struct TokenCache {
+  keycloak_base_url: String,
+  client: reqwest::Client,
+  token: Mutex<Option<CachedToken>>,
+}
+
+impl TokenCache {
+  fn new(
+    keycloak_base_url: impl Into<String>,
+    root_cert: &[u8],
+    socks5_proxy: Option<&str>,
+  ) -> Result<Self, Error> {
+    Ok(Self {
+      keycloak_base_url: keycloak_base_url.into(),
+      client: http::build_client(root_cert, socks5_proxy)?,
+      token: Mutex::new(None),
+    })
+  }
+
+  /// The cached token if fresh, else one refreshed with the refresh
+  /// token, else one obtained with the `grant` form.
+  async fn token(&self, grant: HashMap<&str, &str>) -> Result<String, Error> {
+    let mut token_opt = self.token.lock().await;
+
+    if let Some(token) = token_opt.as_ref().filter(|token| token.is_fresh()) {
+      return Ok(token.access_token.clone());
+    }
+
+    let refreshed_opt = match token_opt
+      .as_ref()
+      .and_then(|token| token.refresh_token.as_deref())
+    {
+      Some(refresh_token) => {
+        let mut params = HashMap::from([
+          ("grant_type", "refresh_token"),
+          ("refresh_token", refresh_token),
+        ]);
+        for key in ["client_id", "client_secret"] {
+          if let Some(value) = grant.get(key) {
+            params.insert(key, *value);
+          }
+        }
+
+        authentication::request_token(
+          &self.client,
+          &self.keycloak_base_url,
+          &params,
+        )
+        .await
+        .inspect_err(|e| {
+          log::debug!(
+            "Could not refresh Keycloak token, requesting a new one: {e}"
+          );
+        })
+        .ok()
+      }
+      None => None,
+    };
+
+    let token_response = match refreshed_opt {
+      Some(token_response) => token_response,
+      None => {
+        authentication::request_token(
+          &self.client,
+          &self.keycloak_base_url,
+          &grant,
+        )
+        .await?
+      }
+    };
+
+    let token = CachedToken::from_response(token_response);
+    let access_token = token.access_token.clone();
+    *token_opt = Some(token);
+
+    Ok(access_token)
+  }
+
+  async fn invalidate(&self) {
+    // Keep the refresh token, it may still be good
+    if let Some(token) = self.token.lock().await.as_mut() {
+      token.expires_at = DateTime::<Utc>::MIN_UTC;
+    }
+  }
+}
+
+/// Whether `error` is CSM rejecting the bearer token (HTTP 401).
+pub(crate) fn is_unauthorized(error: &Error) -> bool {
+  match error {
+    Error::NetError(e) | Error::RequestError { response: e, .. } => {
+      e.status() == Some(reqwest::StatusCode::UNAUTHORIZED)
+    }
+    Error::CsmError { status, .. } => *status == 401,
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cached_token(expires_in: TimeDelta) -> CachedToken {
+    CachedToken {
+      access_token: "token".to_string(),
+      refresh_token: None,
+      expires_at: Utc::now() + expires_in,
+    }
+  }
+
+  #[test]
+  fn tokens_about_to_expire_are_not_fresh() {
+    assert!(cached_token(TimeDelta::minutes(5)).is_fresh());
+    assert!(!cached_token(TimeDelta::seconds(10)).is_fresh());
+    assert!(!cached_token(TimeDelta::seconds(-10)).is_fresh());
+  }
+
+  #[tokio::test]
+  async fn static_token_never_changes() {
+    let provider = StaticToken::new("token");
+    provider.invalidate().await;
+
+    assert_eq!(provider.token().await.unwrap(), "token");
+    assert_eq!(format!("{provider:?}"), "StaticToken(\"<redacted>\")");
+  }
+}
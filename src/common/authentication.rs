@@ -1,9 +1,11 @@
 //! Keycloak / OIDC bearer-token acquisition for Shasta.
 
+use serde::Deserialize;
 use serde_json::Value;
 
 use std::collections::HashMap;
 
+use crate::common::metrics::MeteredSend;
 use crate::error::Error;
 
 /// Validate a CSM bearer token by issuing `GET /cfs/healthz` and
@@ -20,7 +22,11 @@ pub async fn validate_api_token(
 
   log::debug!("Validate CSM token against {api_url}");
 
-  let resp_rslt = client.get(api_url).bearer_auth(shasta_token).send().await;
+  let resp_rslt = client
+    .get(api_url)
+    .bearer_auth(shasta_token)
+    .send_metered()
+    .await;
 
   match resp_rslt {
     Ok(resp) => Ok(resp.error_for_status().map(|_| ())?),
@@ -45,26 +51,50 @@ pub async fn get_token_from_shasta_endpoint(
 
   let client = crate::common::http::build_client(shasta_root_cert, socks5_proxy)?;
 
+  request_token(&client, keycloak_base_url, &params)
+    .await
+    .map(|token_response| token_response.access_token)
+}
+
+/// Tokens Keycloak answers a grant with.
+#[derive(Debug, Deserialize)]
+pub(crate) struct TokenResponse {
+  pub access_token: String,
+  pub refresh_token: Option<String>,
+  /// Lifetime of `access_token`, in seconds.
+  pub expires_in: Option<i64>,
+}
+
+/// `POST` the form `params` to the token endpoint of the Keycloak
+/// `shasta` realm.
+pub(crate) async fn request_token(
+  client: &reqwest::Client,
+  keycloak_base_url: &str,
+  params: &HashMap<&str, &str>,
+) -> Result<TokenResponse, Error> {
   let api_url = format!(
     "{keycloak_base_url}/realms/shasta/protocol/openid-connect/token"
   );
 
-  log::debug!("Request to fetch authentication token: {api_url}");
+  log::debug!(
+    "Request to fetch authentication token ({}): {api_url}",
+    params.get("grant_type").unwrap_or(&"unknown grant")
+  );
 
-  client
+  let token_response = client
     .post(api_url)
-    .form(&params)
-    .send()
+    .form(params)
+    .send_metered()
     .await?
     .error_for_status()?
     .json::<Value>()
-    .await?
-    .get("access_token")
-    .and_then(Value::as_str)
-    .map(str::to_string)
-    .ok_or_else(|| {
-      Error::Message(
-        "Keycloak token response is missing 'access_token'".to_string(),
-      )
-    })
+    .await?;
+
+  if token_response.get("access_token").is_none() {
+    return Err(Error::Message(
+      "Keycloak token response is missing 'access_token'".to_string(),
+    ));
+  }
+
+  Ok(serde_json::from_value(token_response)?)
 }
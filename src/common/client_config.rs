@@ -0,0 +1,352 @@
+//! Settings of a [`crate::ShastaClient`]: TLS and proxy settings of the
+//! HTTP clients it builds, and the per-client behaviour of csm-rs.
+//!
+//! [`crate::ShastaClient::new`] trusts one root certificate and takes
+//! an optional SOCKS5 proxy, which is not enough for sites whose CSM
+//! certificate is signed by an intermediate CA, or that force HTTPS
+//! through a proxy. A [`ClientConfig`] passed to
+//! [`crate::ShastaClient::with_config`] holds:
+//!
+//! - any number of trusted CA certificates (PEM, possibly several per
+//!   file);
+//! - a client certificate and key, for mutual TLS;
+//! - an `http://`, `https://` or `socks5://` proxy;
+//! - whether to skip TLS certificate verification (test systems only);
+//! - the request rate limit, see [`crate::RateLimit`];
+//! - with the `commands-admin` feature, the retry policy of the image
+//!   builds run through the dispatcher's SAT trait, which has no
+//!   argument for it;
+//! - the BSS history journal, see [`crate::bss::history`];
+//! - the Gitea commit metadata cache, see [`crate::CommitCache`];
+//! - the private key `git ls-remote` uses for `ssh://` repos;
+//! - the sink of the progress events, see [`crate::EventSink`];
+//! - the webhooks notified of changes, see [`crate::webhooks`];
+//! - the names given to the resources created, see
+//!   [`crate::NamingPolicy`].
+//!
+//! Every client the `ShastaClient` builds — its connection pool and the
+//! per-call clients of the generated service clients — uses the TLS
+//! and proxy settings. So do the clients built by csm-rs inside
+//! [`crate::ShastaClient::scope`], which the commands run in: those of
+//! helpers taking `shasta_root_cert` and `socks5_proxy`, and the Vault,
+//! Gitea, STS, CFS health and webhook clients. The other settings apply
+//! to the calls made through the client and to the commands run in its
+//! scope.
+
+use std::{
+  fmt,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+
+#[cfg(feature = "commands-admin")]
+use crate::commands::i_apply_sat_file::utils::images::CfsSessionRetryPolicy;
+use crate::common::{
+  commit_cache::CommitCache,
+  events::EventSink,
+  naming::NamingPolicy,
+  rate_limit::{RateLimit, RateLimiter},
+  webhooks::Webhook,
+};
+use crate::error::Error;
+
+/// Settings of a [`crate::ShastaClient`], see the [module docs](self).
+#[derive(Clone, Default)]
+pub struct ClientConfig {
+  ca_bundle: Vec<u8>,
+  identity_opt: Option<Vec<u8>>,
+  proxy_opt: Option<String>,
+  accept_invalid_certs: bool,
+  rate_limiter: RateLimiter,
+  #[cfg(feature = "commands-admin")]
+  cfs_session_retry_policy: CfsSessionRetryPolicy,
+  bss_history_opt: Option<PathBuf>,
+  commit_cache_opt: Option<CommitCache>,
+  git_ssh_key_opt: Option<PathBuf>,
+  event_sink_opt: Option<Arc<dyn EventSink>>,
+  webhook_vec: Vec<Webhook>,
+  naming_policy: NamingPolicy,
+}
+
+impl fmt::Debug for ClientConfig {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut debug_struct = f.debug_struct("ClientConfig");
+    debug_struct
+      .field("ca_bundle_len", &self.ca_bundle.len())
+      .field(
+        "identity",
+        &self.identity_opt.as_ref().map(|_| "<redacted>"),
+      )
+      .field("proxy", &self.proxy_opt)
+      .field("accept_invalid_certs", &self.accept_invalid_certs)
+      .field("rate_limit", &self.rate_limiter);
+    #[cfg(feature = "commands-admin")]
+    debug_struct
+      .field("cfs_session_retry_policy", &self.cfs_session_retry_policy);
+    debug_struct
+      .field("bss_history", &self.bss_history_opt)
+      .field("commit_cache", &self.commit_cache_opt)
+      .field("git_ssh_key", &self.git_ssh_key_opt)
+      .field(
+        "event_sink",
+        &self.event_sink_opt.as_ref().map(|_| "<custom>"),
+      )
+      .field("webhooks", &self.webhook_vec)
+      .field("naming_policy", &self.naming_policy);
+    debug_struct.finish()
+  }
+}
+
+impl ClientConfig {
+  /// Settings trusting the system roots only, without proxy.
+  #[must_use]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Also trust the PEM certificates in `pem`, e.g. a root CA followed by
+  /// its intermediates.
+  #[must_use]
+  pub fn with_ca_certs(mut self, pem: impl AsRef<[u8]>) -> Self {
+    let pem = pem.as_ref();
+    if pem.is_empty() {
+      return self;
+    }
+    if !self.ca_bundle.is_empty() && !self.ca_bundle.ends_with(b"\n") {
+      self.ca_bundle.push(b'\n');
+    }
+    self.ca_bundle.extend_from_slice(pem);
+    self
+  }
+
+  /// Stop trusting the CA certificates added so far.
+  #[must_use]
+  pub(crate) fn without_ca_certs(mut self) -> Self {
+    self.ca_bundle.clear();
+    self
+  }
+
+  /// Authenticate with the client certificate chain and private key in
+  /// `pem`, both in the same PEM buffer.
+  #[must_use]
+  pub fn with_client_identity(mut self, pem: impl Into<Vec<u8>>) -> Self {
+    self.identity_opt = Some(pem.into());
+    self
+  }
+
+  /// Send every request through the proxy at `proxy_url`
+  /// (`http://`, `https://` or `socks5://`).
+  #[must_use]
+  pub fn with_proxy(mut self, proxy_url: impl Into<String>) -> Self {
+    self.proxy_opt = Some(proxy_url.into());
+    self
+  }
+
+  /// Accept any server certificate, valid or not. Only for test systems
+  /// with self-signed certificates: it makes man-in-the-middle attacks
+  /// trivial.
+  #[must_use]
+  pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+    self.accept_invalid_certs = accept;
+    self
+  }
+
+  /// Throttle the requests of the client, and of its clones, to
+  /// `rate_limit_opt`; `None` disables throttling. By default
+  /// [`RateLimit::DEFAULT`] applies.
+  #[must_use]
+  pub fn with_rate_limit(mut self, rate_limit_opt: Option<RateLimit>) -> Self {
+    self.rate_limiter = RateLimiter::new(rate_limit_opt);
+    self
+  }
+
+  /// Retry the image builds run through the dispatcher's SAT trait as
+  /// `policy` says. By default failed sessions are not retried.
+  #[cfg(feature = "commands-admin")]
+  #[must_use]
+  pub fn with_cfs_session_retry_policy(
+    mut self,
+    policy: CfsSessionRetryPolicy,
+  ) -> Self {
+    self.cfs_session_retry_policy = policy;
+    self
+  }
+
+  /// Record every BSS boot parameters put/patch made through the client
+  /// in the JSON-lines journal at `path_opt`, so they can be undone with
+  /// [`crate::bss::utils::rollback`]; `None` (the default) disables
+  /// recording. The file is created if missing and appended to
+  /// otherwise.
+  #[must_use]
+  pub fn with_bss_history(mut self, path_opt: Option<PathBuf>) -> Self {
+    self.bss_history_opt = path_opt;
+    self
+  }
+
+  /// Cache the Gitea commit and tag metadata fetched by the commands run
+  /// in the client's [`crate::ShastaClient::scope`] in
+  /// `commit_cache_opt`; `None` (the default) disables caching.
+  #[must_use]
+  pub fn with_commit_cache(
+    mut self,
+    commit_cache_opt: Option<CommitCache>,
+  ) -> Self {
+    self.commit_cache_opt = commit_cache_opt;
+    self
+  }
+
+  /// Authenticate the `git ls-remote` calls made for repos with an
+  /// `ssh://` URL, by the commands run in the client's
+  /// [`crate::ShastaClient::scope`], with the private key at `key_opt`;
+  /// `None` (the default) leaves it to the ssh configuration.
+  #[must_use]
+  pub fn with_git_ssh_key(mut self, key_opt: Option<PathBuf>) -> Self {
+    self.git_ssh_key_opt = key_opt;
+    self
+  }
+
+  /// Send the progress [`Event`](crate::Event)s of the commands run in
+  /// the client's [`crate::ShastaClient::scope`] to `sink`. By default
+  /// they are printed to stdout by
+  /// [`StdoutEventSink`](crate::StdoutEventSink), or logged by
+  /// [`LogEventSink`](crate::LogEventSink) without the `stdout-events`
+  /// Cargo feature.
+  #[must_use]
+  pub fn with_event_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+    self.event_sink_opt = Some(sink);
+    self
+  }
+
+  /// POST an [`OperationEvent`](crate::webhooks::OperationEvent) to
+  /// every webhook in `webhook_vec` after each change made through the
+  /// client; an empty list (the default) disables them.
+  #[must_use]
+  pub fn with_webhooks(mut self, webhook_vec: Vec<Webhook>) -> Self {
+    self.webhook_vec = webhook_vec;
+    self
+  }
+
+  /// Name the CFS sessions, BOS session templates and boot sets created
+  /// by the commands run in the client's [`crate::ShastaClient::scope`]
+  /// after `policy`. By default csm-rs's historical names apply.
+  #[must_use]
+  pub fn with_naming_policy(mut self, policy: NamingPolicy) -> Self {
+    self.naming_policy = policy;
+    self
+  }
+
+  /// The trusted CA certificates, concatenated PEM.
+  #[must_use]
+  pub fn ca_bundle(&self) -> &[u8] {
+    &self.ca_bundle
+  }
+
+  /// The proxy URL, if any.
+  #[must_use]
+  pub fn proxy(&self) -> Option<&str> {
+    self.proxy_opt.as_deref()
+  }
+
+  /// The request rate limit, `None` if requests are not throttled.
+  #[must_use]
+  pub fn rate_limit(&self) -> Option<RateLimit> {
+    self.rate_limiter.rate_limit()
+  }
+
+  pub(crate) fn rate_limiter(&self) -> &RateLimiter {
+    &self.rate_limiter
+  }
+
+  /// The retry policy of the image builds run through the dispatcher's
+  /// SAT trait.
+  #[cfg(feature = "commands-admin")]
+  #[must_use]
+  pub fn cfs_session_retry_policy(&self) -> &CfsSessionRetryPolicy {
+    &self.cfs_session_retry_policy
+  }
+
+  /// The BSS history journal, `None` if history is disabled.
+  #[must_use]
+  pub fn bss_history(&self) -> Option<&Path> {
+    self.bss_history_opt.as_deref()
+  }
+
+  /// The Gitea commit metadata cache, `None` if caching is disabled.
+  #[must_use]
+  pub fn commit_cache(&self) -> Option<&CommitCache> {
+    self.commit_cache_opt.as_ref()
+  }
+
+  /// The private key used for `ssh://` repos, `None` if left to the ssh
+  /// configuration.
+  #[must_use]
+  pub fn git_ssh_key(&self) -> Option<&Path> {
+    self.git_ssh_key_opt.as_deref()
+  }
+
+  /// The event sink, `None` if the default one is used.
+  #[must_use]
+  pub fn event_sink(&self) -> Option<&Arc<dyn EventSink>> {
+    self.event_sink_opt.as_ref()
+  }
+
+  /// The webhooks notified of changes.
+  #[must_use]
+  pub fn webhooks(&self) -> &[Webhook] {
+    &self.webhook_vec
+  }
+
+  /// The naming policy of the resources created.
+  #[must_use]
+  pub fn naming_policy(&self) -> &NamingPolicy {
+    &self.naming_policy
+  }
+
+  /// `builder` with these settings applied.
+  pub(crate) fn apply(
+    &self,
+    mut builder: reqwest::ClientBuilder,
+  ) -> Result<reqwest::ClientBuilder, Error> {
+    for certificate in reqwest::Certificate::from_pem_bundle(&self.ca_bundle)? {
+      builder = builder.add_root_certificate(certificate);
+    }
+
+    if let Some(identity) = &self.identity_opt {
+      builder = builder.identity(reqwest::Identity::from_pem(identity)?);
+    }
+
+    if let Some(proxy) = &self.proxy_opt {
+      builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+
+    Ok(builder.danger_accept_invalid_certs(self.accept_invalid_certs))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ca_certs_are_concatenated_and_identity_is_redacted() {
+    let config = ClientConfig::new()
+      .with_ca_certs("-----BEGIN CERTIFICATE-----\nroot\n-----END CERTIFICATE-----")
+      .with_ca_certs("")
+      .with_ca_certs("-----BEGIN CERTIFICATE-----\nintermediate\n-----END CERTIFICATE-----\n")
+      .with_client_identity("secret key")
+      .with_proxy("http://proxy.example.com:3128");
+
+    assert_eq!(
+      config.ca_bundle(),
+      b"-----BEGIN CERTIFICATE-----\nroot\n-----END CERTIFICATE-----\n-----BEGIN CERTIFICATE-----\nintermediate\n-----END CERTIFICATE-----\n"
+    );
+    assert_eq!(config.proxy(), Some("http://proxy.example.com:3128"));
+    assert!(!format!("{config:?}").contains("secret"));
+  }
+
+  #[test]
+  fn rate_limit_defaults_and_can_be_disabled() {
+    assert_eq!(ClientConfig::new().rate_limit(), Some(RateLimit::DEFAULT));
+    assert_eq!(ClientConfig::new().with_rate_limit(None).rate_limit(), None);
+  }
+}
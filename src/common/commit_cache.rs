@@ -0,0 +1,270 @@
+//! On-disk cache of Gitea commit and tag metadata.
+//!
+//! Enriching CFS configuration layers fetches the details of each
+//! layer's commit from Gitea, and configurations sharing layers ask for
+//! the same commits again on every command. Once enabled with
+//! [`crate::ClientConfig::with_commit_cache`], csm-rs keeps on disk,
+//! for the commands run in the client's [`crate::ShastaClient::scope`],
+//! keyed by repo and SHA:
+//!
+//! - the details of commits;
+//! - the commit each annotated tag object points at.
+//!
+//! Both never change for a given SHA, so the TTL of the [`CommitCache`]
+//! only bounds how long unused entries stay around. When Gitea can't be
+//! reached, an expired entry is used rather than failing.
+
+use std::{
+  fs,
+  future::Future,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Time [`CommitCache`] entries are used for unless told otherwise.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Directory and TTL of the commit metadata cache, see the
+/// [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitCache {
+  dir: PathBuf,
+  ttl: Duration,
+}
+
+impl CommitCache {
+  /// Cache in `$XDG_CACHE_HOME/csm-rs/gitea`, or
+  /// `~/.cache/csm-rs/gitea` if `XDG_CACHE_HOME` is not set, keeping
+  /// entries for `ttl`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Message`] if neither `XDG_CACHE_HOME` nor `HOME`
+  /// is set.
+  pub fn new(ttl: Duration) -> Result<Self, Error> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+      .map(PathBuf::from)
+      .filter(|path| path.is_absolute())
+      .or_else(|| {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache"))
+      })
+      .ok_or_else(|| {
+        Error::Message(
+          "Commit cache: neither XDG_CACHE_HOME nor HOME is set".to_string(),
+        )
+      })?;
+
+    Ok(Self::in_dir(cache_home.join("csm-rs").join("gitea"), ttl))
+  }
+
+  /// Cache in `dir`, keeping entries for `ttl`.
+  #[must_use]
+  pub fn in_dir(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+    Self {
+      dir: dir.into(),
+      ttl,
+    }
+  }
+
+  /// Directory holding the entries.
+  #[must_use]
+  pub fn dir(&self) -> &Path {
+    &self.dir
+  }
+
+  /// Time entries are used for.
+  #[must_use]
+  pub fn ttl(&self) -> Duration {
+    self.ttl
+  }
+
+  /// Remove every entry.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::IoError`] if the directory can't be removed.
+  pub fn clear(&self) -> Result<(), Error> {
+    match fs::remove_dir_all(&self.dir) {
+      Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+      _ => Ok(()),
+    }
+  }
+
+  /// Entry of `kind` for `sha` of `repo`, if written less than the TTL
+  /// ago, or at all with `allow_expired`.
+  pub(crate) fn get(
+    &self,
+    kind: EntryKind,
+    repo: &str,
+    sha: &str,
+    allow_expired: bool,
+  ) -> Option<Value> {
+    let path = self.entry_path(kind, repo, sha);
+    let expired = fs::metadata(&path)
+      .and_then(|metadata| metadata.modified())
+      .ok()?
+      .elapsed()
+      .is_ok_and(|age| age >= self.ttl);
+    if expired && !allow_expired {
+      return None;
+    }
+
+    let content = fs::read(&path).ok()?;
+    serde_json::from_slice(&content).ok()
+  }
+
+  /// Store `value` as the entry of `kind` for `sha` of `repo`. Failures
+  /// are logged, a cache that can't be written is only slower.
+  pub(crate) fn put(
+    &self,
+    kind: EntryKind,
+    repo: &str,
+    sha: &str,
+    value: &Value,
+  ) {
+    let path = self.entry_path(kind, repo, sha);
+    // Written aside then renamed, so readers never see half an entry
+    let tmp_path = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+
+    let result = path
+      .parent()
+      .map_or(Ok(()), fs::create_dir_all)
+      .and_then(|()| fs::write(&tmp_path, value.to_string()))
+      .and_then(|()| fs::rename(&tmp_path, &path));
+    if let Err(e) = result {
+      log::warn!("Could not cache {kind:?} '{sha}' of repo '{repo}': {e}");
+      let _ = fs::remove_file(&tmp_path);
+    }
+  }
+
+  /// Entry of `kind` for `sha` of `repo` if fresh, else the value
+  /// returned by `fetch`, which is then cached. If `fetch` fails, an
+  /// expired entry is returned instead of its error.
+  pub(crate) async fn cached<F, Fut>(
+    &self,
+    kind: EntryKind,
+    repo: &str,
+    sha: &str,
+    fetch: F,
+  ) -> Result<Value, Error>
+  where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Value, Error>>,
+  {
+    if let Some(value) = self.get(kind, repo, sha, false) {
+      log::debug!("Using cached {kind:?} '{sha}' of repo '{repo}'");
+      return Ok(value);
+    }
+
+    match fetch().await {
+      Ok(value) => {
+        self.put(kind, repo, sha, &value);
+        Ok(value)
+      }
+      Err(error) => {
+        let value = self.get(kind, repo, sha, true).ok_or(error)?;
+        log::warn!(
+          "Could not fetch {kind:?} '{sha}' of repo '{repo}', using expired cache entry"
+        );
+        Ok(value)
+      }
+    }
+  }
+
+  fn entry_path(&self, kind: EntryKind, repo: &str, sha: &str) -> PathBuf {
+    let kind_dir = match kind {
+      EntryKind::Commit => "commits",
+      EntryKind::Tag => "tags",
+    };
+    // Repo names hold slashes and SHAs come from the caller
+    let file_name =
+      format!("{:x}.json", md5::compute(format!("{repo}\n{sha}")));
+
+    self.dir.join(kind_dir).join(file_name)
+  }
+}
+
+/// What a cache entry holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryKind {
+  /// Gitea commit details.
+  Commit,
+  /// SHA of the commit an annotated tag object points at.
+  Tag,
+}
+
+/// [`CommitCache::cached`] of the cache of the client in scope, or
+/// `fetch` alone if caching is disabled or there is no client in scope.
+pub(crate) async fn cached<F, Fut>(
+  kind: EntryKind,
+  repo: &str,
+  sha: &str,
+  fetch: F,
+) -> Result<Value, Error>
+where
+  F: FnOnce() -> Fut,
+  Fut: Future<Output = Result<Value, Error>>,
+{
+  let commit_cache_opt =
+    crate::client::scoped_config(|config| config.commit_cache().cloned())
+      .flatten();
+
+  match commit_cache_opt {
+    Some(commit_cache) => commit_cache.cached(kind, repo, sha, fetch).await,
+    None => fetch().await,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn cached_fetches_once_and_falls_back_to_expired_entries() {
+    let dir = std::env::temp_dir()
+      .join(format!("csm-rs-commit-cache-{}", uuid::Uuid::new_v4()));
+    let commit = json!({"sha": "aaaaaaa1", "commit": {"message": "Init"}});
+
+    let commit_cache = CommitCache::in_dir(&dir, DEFAULT_TTL);
+    let fetched = commit_cache
+      .cached(EntryKind::Commit, "cray/repo", "aaaaaaa1", || async {
+        Ok(commit.clone())
+      })
+      .await
+      .unwrap();
+    assert_eq!(fetched, commit);
+    let cached = commit_cache
+      .cached(EntryKind::Commit, "cray/repo", "aaaaaaa1", || async {
+        Err(Error::Message("fetched twice".to_string()))
+      })
+      .await
+      .unwrap();
+    assert_eq!(cached, commit);
+    assert_eq!(
+      commit_cache.get(EntryKind::Tag, "cray/repo", "aaaaaaa1", true),
+      None
+    );
+
+    let expiring_cache = CommitCache::in_dir(&dir, Duration::ZERO);
+    assert_eq!(
+      expiring_cache.get(EntryKind::Commit, "cray/repo", "aaaaaaa1", false),
+      None
+    );
+    let stale = expiring_cache
+      .cached(EntryKind::Commit, "cray/repo", "aaaaaaa1", || async {
+        Err(Error::Message("Gitea unreachable".to_string()))
+      })
+      .await
+      .unwrap();
+    assert_eq!(stale, commit);
+
+    commit_cache.clear().unwrap();
+    assert!(!dir.exists());
+  }
+}
@@ -0,0 +1,200 @@
+//! CSM collections fetched at most once per command.
+//!
+//! Filtering, derivative lookups and deletion planning each need some
+//! of the CFS configurations, sessions and components, BOS session
+//! templates, IMS images and BSS boot parameters. Fetching them in
+//! every helper is slow, and two helpers of the same command may see
+//! CSM in different states. A [`CsmSnapshot`] fetches each collection
+//! the first time a helper asks for it and hands the same data to every
+//! later one:
+//!
+//! ```no_run
+//! # async fn example(
+//! #   client: &csm_rs::ShastaClient,
+//! #   token: &str,
+//! # ) -> Result<(), csm_rs::Error> {
+//! use csm_rs::{CsmSnapshot, cfs::configuration::utils};
+//!
+//! let snapshot = CsmSnapshot::new(client, token);
+//! let usage = utils::usage_from_snapshot(&snapshot, "cos-config").await?;
+//! // Reuses the CFS sessions and BOS session templates fetched above
+//! let derivatives =
+//!   utils::get_derivatives_from_snapshot(&snapshot, "cos-config").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A snapshot never refreshes: create one per command run.
+
+use std::fmt;
+
+use tokio::sync::OnceCell;
+
+use crate::{
+  ShastaClient,
+  bos::BosSessionTemplate,
+  bss::types::BootParameters,
+  cfs::v2::{CfsConfigurationResponse, CfsSessionGetResponse, Component},
+  error::Error,
+  ims::Image,
+};
+
+/// Lazily fetched CSM collections of one command run, see the
+/// [module docs](self).
+pub struct CsmSnapshot {
+  client: ShastaClient,
+  shasta_token: String,
+  cfs_configurations: OnceCell<Vec<CfsConfigurationResponse>>,
+  cfs_sessions: OnceCell<Vec<CfsSessionGetResponse>>,
+  cfs_components: OnceCell<Vec<Component>>,
+  bos_sessiontemplates: OnceCell<Vec<BosSessionTemplate>>,
+  ims_images: OnceCell<Vec<Image>>,
+  bss_bootparameters: OnceCell<Vec<BootParameters>>,
+}
+
+impl fmt::Debug for CsmSnapshot {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("CsmSnapshot")
+      .field("client", &self.client)
+      .field(
+        "cfs_configurations",
+        &self.cfs_configurations.get().map(Vec::len),
+      )
+      .field("cfs_sessions", &self.cfs_sessions.get().map(Vec::len))
+      .field("cfs_components", &self.cfs_components.get().map(Vec::len))
+      .field(
+        "bos_sessiontemplates",
+        &self.bos_sessiontemplates.get().map(Vec::len),
+      )
+      .field("ims_images", &self.ims_images.get().map(Vec::len))
+      .field(
+        "bss_bootparameters",
+        &self.bss_bootparameters.get().map(Vec::len),
+      )
+      .finish_non_exhaustive()
+  }
+}
+
+impl CsmSnapshot {
+  /// Empty snapshot fetching with `client` as the owner of
+  /// `shasta_token`.
+  #[must_use]
+  pub fn new(client: &ShastaClient, shasta_token: &str) -> Self {
+    Self {
+      client: client.clone(),
+      shasta_token: shasta_token.to_string(),
+      cfs_configurations: OnceCell::new(),
+      cfs_sessions: OnceCell::new(),
+      cfs_components: OnceCell::new(),
+      bos_sessiontemplates: OnceCell::new(),
+      ims_images: OnceCell::new(),
+      bss_bootparameters: OnceCell::new(),
+    }
+  }
+
+  /// Client the snapshot fetches with.
+  #[must_use]
+  pub fn client(&self) -> &ShastaClient {
+    &self.client
+  }
+
+  /// Token the snapshot fetches with.
+  #[must_use]
+  pub fn shasta_token(&self) -> &str {
+    &self.shasta_token
+  }
+
+  /// Every CFS configuration.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure of the first fetch; a later call fetches
+  /// again.
+  pub async fn cfs_configurations(
+    &self,
+  ) -> Result<&[CfsConfigurationResponse], Error> {
+    self
+      .cfs_configurations
+      .get_or_try_init(|| {
+        self.client.cfs_configuration_get(&self.shasta_token, None)
+      })
+      .await
+      .map(Vec::as_slice)
+  }
+
+  /// Every CFS session.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configurations`].
+  pub async fn cfs_sessions(&self) -> Result<&[CfsSessionGetResponse], Error> {
+    self
+      .cfs_sessions
+      .get_or_try_init(|| self.client.cfs_session_get(&self.shasta_token, None))
+      .await
+      .map(Vec::as_slice)
+  }
+
+  /// Every CFS component.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configurations`].
+  pub async fn cfs_components(&self) -> Result<&[Component], Error> {
+    self
+      .cfs_components
+      .get_or_try_init(|| {
+        self
+          .client
+          .cfs_component_get(&self.shasta_token, None, None)
+      })
+      .await
+      .map(Vec::as_slice)
+  }
+
+  /// Every BOS session template.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configurations`].
+  pub async fn bos_sessiontemplates(
+    &self,
+  ) -> Result<&[BosSessionTemplate], Error> {
+    self
+      .bos_sessiontemplates
+      .get_or_try_init(|| {
+        self.client.bos_template_v2_get_all(&self.shasta_token)
+      })
+      .await
+      .map(Vec::as_slice)
+  }
+
+  /// Every IMS image.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configurations`].
+  pub async fn ims_images(&self) -> Result<&[Image], Error> {
+    self
+      .ims_images
+      .get_or_try_init(|| self.client.ims_image_get_all(&self.shasta_token))
+      .await
+      .map(Vec::as_slice)
+  }
+
+  /// Every BSS boot parameters entry.
+  ///
+  /// # Errors
+  ///
+  /// Same as [`Self::cfs_configurations`].
+  pub async fn bss_bootparameters(&self) -> Result<&[BootParameters], Error> {
+    self
+      .bss_bootparameters
+      .get_or_try_init(|| {
+        self.client.bss_bootparameters_get_all(&self.shasta_token)
+      })
+      .await
+      .map(Vec::as_slice)
+  }
+}
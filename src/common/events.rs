@@ -0,0 +1,273 @@
+//! Progress events of long-running commands.
+//!
+//! The [`crate::commands`] workflows report user-facing progress as
+//! typed [`Event`]s to the [`EventSink`] set with
+//! [`crate::ClientConfig::with_event_sink`] on the client whose
+//! [`crate::ShastaClient::scope`] they run in. The default,
+//! [`StdoutEventSink`], prints one line per event; GUIs and other front
+//! ends install their own sink to render progress however they like.
+//! Without the `stdout-events` Cargo feature the default is
+//! [`LogEventSink`] instead.
+//!
+//! Diagnostics meant for developers (request payloads, timings, ...)
+//! still go to `tracing`/`log`. The only other output of the library
+//! are the progress bars of S3 transfers and file checksums, drawn on
+//! stderr only while events are printed to stdout, see
+//! [`prints_to_terminal`].
+
+use std::fmt;
+
+use serde::Serialize;
+
+/// One step of progress of a command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Event {
+  /// A command started a new step, e.g. processing a SAT file section.
+  StepStarted {
+    /// Human readable step description.
+    step: String,
+  },
+  /// A command created (or, in dry run, would create) a CSM resource.
+  ResourceCreated {
+    /// Resource kind, e.g. `CFS configuration`.
+    kind: &'static str,
+    /// Resource name or id.
+    name: String,
+  },
+  /// A command deleted (or, in dry run, would delete) a CSM resource.
+  ResourceDeleted {
+    /// Resource kind, e.g. `IMS image`.
+    kind: &'static str,
+    /// Resource name or id.
+    name: String,
+  },
+  /// Something the user should know about that doesn't stop the
+  /// command.
+  Warning {
+    /// Warning text.
+    message: String,
+  },
+  /// A CSM call failed and is about to be retried.
+  Retry {
+    /// What is retried, e.g. `GET /cfs/v3/configurations`.
+    operation: String,
+    /// Attempt that failed, starting at 1.
+    attempt: u32,
+    /// Attempts made before giving up.
+    max_attempts: u32,
+    /// Why the attempt failed.
+    reason: String,
+  },
+  /// Any other progress message.
+  Info {
+    /// Message text.
+    message: String,
+  },
+}
+
+impl fmt::Display for Event {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::StepStarted { step } => write!(f, "==> {step}"),
+      Self::ResourceCreated { kind, name } => {
+        write!(f, "{kind} '{name}' created")
+      }
+      Self::ResourceDeleted { kind, name } => {
+        write!(f, "{kind} '{name}' deleted")
+      }
+      Self::Warning { message } => write!(f, "WARNING: {message}"),
+      Self::Retry {
+        operation,
+        attempt,
+        max_attempts,
+        reason,
+      } => write!(
+        f,
+        "{operation} failed (attempt {attempt}/{max_attempts}): {reason}; retrying"
+      ),
+      Self::Info { message } => write!(f, "{message}"),
+    }
+  }
+}
+
+/// Receiver of command progress [`Event`]s.
+///
+/// `emit` is called inline from the command, so it should return
+/// quickly; hand events to another thread or channel if rendering is
+/// slow.
+pub trait EventSink: Send + Sync {
+  /// Handle one event.
+  fn emit(&self, event: &Event);
+}
+
+/// Default [`EventSink`]: prints every event to stdout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutEventSink;
+
+impl EventSink for StdoutEventSink {
+  fn emit(&self, event: &Event) {
+    println!("{event}");
+  }
+}
+
+/// [`EventSink`] forwarding every event to the `log` facade: warnings
+/// at warn level, the others at info level. Default without the
+/// `stdout-events` Cargo feature.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogEventSink;
+
+impl EventSink for LogEventSink {
+  fn emit(&self, event: &Event) {
+    match event {
+      Event::Warning { .. } | Event::Retry { .. } => log::warn!("{event}"),
+      _ => log::info!("{event}"),
+    }
+  }
+}
+
+/// Sink used when the client in scope sets none, or there is no client
+/// in scope.
+#[cfg(feature = "stdout-events")]
+const DEFAULT_SINK: StdoutEventSink = StdoutEventSink;
+#[cfg(not(feature = "stdout-events"))]
+const DEFAULT_SINK: LogEventSink = LogEventSink;
+
+/// Whether events go to the terminal, i.e. the default sink is
+/// [`StdoutEventSink`] and the client in scope sets no other. Progress
+/// bars are only drawn then.
+pub(crate) fn prints_to_terminal() -> bool {
+  cfg!(feature = "stdout-events")
+    && crate::client::scoped_config(|config| config.event_sink().is_none())
+      .unwrap_or(true)
+}
+
+/// Send `event` to the event sink of the client in scope.
+pub(crate) fn emit(event: Event) {
+  match crate::client::scoped_config(|config| config.event_sink().cloned())
+    .flatten()
+  {
+    Some(sink) => sink.emit(&event),
+    None => DEFAULT_SINK.emit(&event),
+  }
+}
+
+/// Emit [`Event::StepStarted`].
+pub(crate) fn step(step: impl Into<String>) {
+  emit(Event::StepStarted { step: step.into() });
+}
+
+/// Emit [`Event::ResourceCreated`].
+pub(crate) fn created(kind: &'static str, name: impl Into<String>) {
+  emit(Event::ResourceCreated {
+    kind,
+    name: name.into(),
+  });
+}
+
+/// Emit [`Event::ResourceDeleted`].
+pub(crate) fn deleted(kind: &'static str, name: impl Into<String>) {
+  emit(Event::ResourceDeleted {
+    kind,
+    name: name.into(),
+  });
+}
+
+/// Emit [`Event::Warning`].
+pub(crate) fn warning(message: impl Into<String>) {
+  emit(Event::Warning {
+    message: message.into(),
+  });
+}
+
+/// Emit [`Event::Info`].
+pub(crate) fn info(message: impl Into<String>) {
+  emit(Event::Info {
+    message: message.into(),
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn events_render_one_line_each() {
+    assert_eq!(
+      Event::ResourceCreated {
+        kind: "CFS configuration",
+        name: "cos-config".to_string(),
+      }
+      .to_string(),
+      "CFS configuration 'cos-config' created"
+    );
+    assert_eq!(
+      Event::Retry {
+        operation: "GET /cfs/v3/configurations".to_string(),
+        attempt: 1,
+        max_attempts: 3,
+        reason: "503".to_string(),
+      }
+      .to_string(),
+      "GET /cfs/v3/configurations failed (attempt 1/3): 503; retrying"
+    );
+    assert_eq!(
+      serde_json::to_value(Event::Warning {
+        message: "careful".to_string(),
+      })
+      .unwrap(),
+      serde_json::json!({ "event": "warning", "message": "careful" })
+    );
+  }
+
+  /// Lines of the non-test code of `source` printing to stdout or
+  /// stderr.
+  fn print_lines(source: &str) -> Vec<String> {
+    source
+      .lines()
+      .take_while(|line| line.trim() != "#[cfg(test)]")
+      .filter(|line| !line.trim_start().starts_with("//"))
+      .filter(|line| {
+        ["print!(", "println!(", "eprint!(", "eprintln!(", "dbg!("]
+          .iter()
+          .any(|print_macro| line.contains(print_macro))
+      })
+      .map(str::to_string)
+      .collect()
+  }
+
+  fn collect_print_lines(dir: &std::path::Path, found_vec: &mut Vec<String>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+      let path = entry.unwrap().path();
+      if path.is_dir() {
+        collect_print_lines(&path, found_vec);
+      } else if path.extension().is_some_and(|extension| extension == "rs")
+        // Test modules and the one sink allowed to print
+        && !path.ends_with("tests.rs")
+        && !path.ends_with("common/events.rs")
+      {
+        let source = std::fs::read_to_string(&path).unwrap();
+        found_vec.extend(
+          print_lines(&source)
+            .into_iter()
+            .map(|line| format!("{}: {}", path.display(), line.trim())),
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn library_reports_through_events_not_stdout() {
+    let mut found_vec = Vec::new();
+    collect_print_lines(
+      &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+      &mut found_vec,
+    );
+    assert!(
+      found_vec.is_empty(),
+      "use crate::common::events or log instead of printing:\n{}",
+      found_vec.join("\n")
+    );
+  }
+}
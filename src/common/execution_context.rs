@@ -0,0 +1,253 @@
+//! Who a mutating command runs for.
+//!
+//! An [`ExecutionContext`] names the acting user, the tenant an admin
+//! acts on behalf of, if any, and why. The mutating
+//! [`crate::commands`] take one, use its [`Tenancy`] to decide which
+//! HSM groups they may touch, and run inside [`ExecutionContext::scope`]
+//! so the [`crate::webhooks::OperationEvent`]s they trigger carry it.
+//!
+//! Admins impersonate a tenant with [`ExecutionContext::on_behalf_of`]:
+//! the command then only sees that tenant's groups, as the tenant would,
+//! while the audit trail still names the admin.
+
+use std::future::Future;
+
+use serde::Serialize;
+
+use crate::{common::jwt_ops, error::Error, hsm::group::tenancy::Tenancy};
+
+tokio::task_local! {
+  static EXECUTION_CONTEXT: ExecutionContext;
+}
+
+/// Acting user, impersonated tenant and reason of a command, see the
+/// [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExecutionContext {
+  acting_user: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  impersonated_tenant: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  reason: Option<String>,
+  #[serde(skip)]
+  tenancy: Tenancy,
+}
+
+impl ExecutionContext {
+  /// Context of `acting_user` with tenancy `tenancy`, e.g. for callers
+  /// that authenticate users themselves. `tenancy` can only narrow what
+  /// the owner of `shasta_token` may act on: the context gets its
+  /// intersection with the tenancy of the token, see
+  /// [`Tenancy::intersect`].
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant if the token can't be decoded.
+  pub fn new(
+    shasta_token: &str,
+    acting_user: impl Into<String>,
+    tenancy: &Tenancy,
+  ) -> Result<Self, Error> {
+    Ok(Self::with_tenancy(
+      acting_user,
+      tenancy.intersect(&Tenancy::from_token(shasta_token)?),
+    ))
+  }
+
+  /// Context of `acting_user` with tenancy `tenancy` as is.
+  pub(crate) fn with_tenancy(
+    acting_user: impl Into<String>,
+    tenancy: Tenancy,
+  ) -> Self {
+    Self {
+      acting_user: acting_user.into(),
+      impersonated_tenant: None,
+      reason: None,
+      tenancy,
+    }
+  }
+
+  /// Context of the owner of `shasta_token`, acting for themselves.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant if the token can't be decoded or has
+  /// no `preferred_username` claim.
+  pub fn from_token(shasta_token: &str) -> Result<Self, Error> {
+    Ok(Self::with_tenancy(
+      jwt_ops::get_preferred_username(shasta_token)?,
+      Tenancy::from_token(shasta_token)?,
+    ))
+  }
+
+  /// The same acting user, restricted to the HSM groups of `tenant`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Impersonation`] unless the acting user is
+  /// unrestricted (an admin), or if it already impersonates a tenant.
+  pub fn on_behalf_of(
+    self,
+    tenant: impl Into<String>,
+    reason: impl Into<String>,
+  ) -> Result<Self, Error> {
+    let tenant = tenant.into();
+
+    if let Some(impersonated_tenant) = &self.impersonated_tenant {
+      return Err(Error::Impersonation(format!(
+        "'{}' already acts on behalf of '{impersonated_tenant}'",
+        self.acting_user
+      )));
+    }
+    if !self.tenancy.is_unrestricted() {
+      return Err(Error::Impersonation(format!(
+        "'{}' is not an admin and can't act on behalf of '{tenant}'",
+        self.acting_user
+      )));
+    }
+
+    Ok(Self {
+      tenancy: Tenancy::from_groups(vec![tenant.clone()]),
+      impersonated_tenant: Some(tenant),
+      reason: Some(reason.into()),
+      acting_user: self.acting_user,
+    })
+  }
+
+  /// The same context, recording why the command runs.
+  #[must_use]
+  pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+    self.reason = Some(reason.into());
+    self
+  }
+
+  /// User running the command.
+  #[must_use]
+  pub fn acting_user(&self) -> &str {
+    &self.acting_user
+  }
+
+  /// Tenant the acting user impersonates, if any.
+  #[must_use]
+  pub fn impersonated_tenant(&self) -> Option<&str> {
+    self.impersonated_tenant.as_deref()
+  }
+
+  /// Why the command runs, if given.
+  #[must_use]
+  pub fn reason(&self) -> Option<&str> {
+    self.reason.as_deref()
+  }
+
+  /// HSM groups the command may act on.
+  #[must_use]
+  pub fn tenancy(&self) -> &Tenancy {
+    &self.tenancy
+  }
+
+  /// `group_vec` without the groups the context can't act on.
+  #[must_use]
+  pub fn filter_groups(&self, group_vec: &[String]) -> Vec<String> {
+    self.tenancy.filter_groups(group_vec)
+  }
+
+  /// Fail unless the context may act on HSM group, or resource,
+  /// `name`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Impersonation`] if the impersonated tenant doesn't
+  /// own `name`, else [`Error::Message`] if the acting user doesn't.
+  pub fn check_owns(&self, name: &str) -> Result<(), Error> {
+    if self.tenancy.owns(name) {
+      return Ok(());
+    }
+
+    match &self.impersonated_tenant {
+      Some(tenant) => Err(Error::Impersonation(format!(
+        "'{name}' does not belong to tenant '{tenant}'"
+      ))),
+      None => Err(Error::Message(format!(
+        "'{}' has no access to '{name}'",
+        self.acting_user
+      ))),
+    }
+  }
+
+  /// Run `fut` with this context as the [`current`] one.
+  pub(crate) async fn scope<F: Future>(&self, fut: F) -> F::Output {
+    EXECUTION_CONTEXT.scope(self.clone(), fut).await
+  }
+}
+
+/// Context of the command running on the current task, if any.
+pub(crate) fn current() -> Option<ExecutionContext> {
+  EXECUTION_CONTEXT.try_with(Clone::clone).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn only_admins_act_on_behalf_of_a_tenant() {
+    let admin =
+      ExecutionContext::with_tenancy("admin", Tenancy::unrestricted());
+    let context = admin
+      .on_behalf_of("zinal", "INC-1234 reconfigure zinal")
+      .unwrap();
+
+    assert_eq!(context.acting_user(), "admin");
+    assert_eq!(context.impersonated_tenant(), Some("zinal"));
+    assert!(context.check_owns("zinal_cta").is_ok());
+    assert!(matches!(
+      context.check_owns("eiger"),
+      Err(Error::Impersonation(_))
+    ));
+    assert_eq!(
+      serde_json::to_value(&context).unwrap(),
+      serde_json::json!({
+        "acting_user": "admin",
+        "impersonated_tenant": "zinal",
+        "reason": "INC-1234 reconfigure zinal",
+      })
+    );
+    assert!(matches!(
+      context.on_behalf_of("eiger", "again"),
+      Err(Error::Impersonation(_))
+    ));
+
+    let tenant = ExecutionContext::with_tenancy(
+      "jdoe",
+      Tenancy::from_groups(vec!["zinal".to_string()]),
+    );
+    assert!(matches!(
+      tenant.on_behalf_of("eiger", "curious"),
+      Err(Error::Impersonation(_))
+    ));
+  }
+
+  #[test]
+  fn new_only_narrows_the_tenancy_of_the_token() {
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+    let claims = serde_json::json!({
+      "preferred_username": "jdoe",
+      "realm_access": {"roles": ["zinal"]},
+    });
+    let shasta_token =
+      format!("header.{}.sig", URL_SAFE_NO_PAD.encode(claims.to_string()));
+
+    let context =
+      ExecutionContext::new(&shasta_token, "jdoe", &Tenancy::unrestricted())
+        .unwrap();
+    assert_eq!(
+      context.tenancy(),
+      &Tenancy::from_groups(vec!["zinal".to_string()])
+    );
+    assert!(
+      ExecutionContext::new("test-token", "jdoe", &Tenancy::unrestricted())
+        .is_err()
+    );
+  }
+}
@@ -0,0 +1,210 @@
+//! Git access over SSH, for sites whose VCS is only reachable that way.
+//!
+//! The refs of repos whose URL is an `ssh://` URL are listed with
+//! `git ls-remote` instead of the Gitea REST API, so CFS layers can
+//! still pin branches and tags to commits. The `git` and `ssh` binaries
+//! must be installed. Authentication uses the key set with
+//! [`crate::ClientConfig::with_git_ssh_key`] on the client in scope, or
+//! the user's ssh configuration if none is set.
+//!
+//! Commit details and playbook checks need the Gitea API, so they are
+//! skipped for these repos.
+
+use std::{collections::HashMap, path::Path, process::Command};
+
+use serde_json::{Value, json};
+
+use crate::{
+  common::gitea::{
+    RefKind, ResolvedRef,
+    http_client::{find_ref, is_commit_sha},
+  },
+  error::Error,
+};
+
+/// Suffix `git ls-remote` appends to annotated tags to list the commit
+/// they point at.
+const PEELED_SUFFIX: &str = "^{}";
+
+/// Whether the repo at `repo_url` is reached over SSH.
+pub(crate) fn is_ssh_url(repo_url: &str) -> bool {
+  repo_url.starts_with("ssh://")
+}
+
+/// Refs of a repo as listed by `git ls-remote`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct RemoteRefs {
+  /// Branches and tags, in the shape of the Gitea refs API:
+  /// `{"ref": ..., "object": {"type": "commit" | "tag", "sha": ...}}`.
+  pub(crate) ref_vec: Vec<Value>,
+  /// Commit each annotated tag points at, by ref.
+  pub(crate) peeled_map: HashMap<String, String>,
+}
+
+/// Branches and tags of the repo at `repo_url`.
+///
+/// # Errors
+///
+/// Returns [`Error::GitSsh`] if `git ls-remote` can't be run or fails.
+pub(crate) async fn ls_remote(repo_url: &str) -> Result<RemoteRefs, Error> {
+  let key_opt = crate::client::scoped_config(|config| {
+    config.git_ssh_key().map(Path::to_path_buf)
+  })
+  .flatten();
+  let url = repo_url.to_string();
+
+  log::debug!("List refs of {repo_url} with git ls-remote");
+
+  let output = tokio::task::spawn_blocking(move || {
+    let mut command = Command::new("git");
+    command
+      .args(["ls-remote", "--heads", "--tags", &url])
+      .env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(key) = key_opt {
+      command.env("GIT_SSH_COMMAND", ssh_command(&key));
+    }
+    command.output()
+  })
+  .await?
+  .map_err(|e| Error::GitSsh(format!("can't run git ls-remote: {e}")))?;
+
+  if !output.status.success() {
+    return Err(Error::GitSsh(format!(
+      "git ls-remote {repo_url} failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    )));
+  }
+
+  Ok(parse_ls_remote(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Resolve `git_ref` in the repo at `repo_url` like
+/// [`super::gitea::http_client::resolve_ref`], reading annotated tags'
+/// commits from `git ls-remote`.
+///
+/// # Errors
+///
+/// Returns [`Error::GitRefNotFound`] if `git_ref` is neither a ref of the
+/// repo nor a commit SHA, or the error of [`ls_remote`].
+pub(crate) async fn resolve_ref(
+  repo_url: &str,
+  git_ref: &str,
+) -> Result<ResolvedRef, Error> {
+  let remote_refs = ls_remote(repo_url).await?;
+
+  match find_ref(&remote_refs.ref_vec, git_ref)? {
+    Some((RefKind::AnnotatedTag, name, sha)) => {
+      let target_sha = remote_refs
+        .peeled_map
+        .get(&format!("refs/tags/{name}"))
+        .cloned()
+        .ok_or_else(|| {
+          Error::GitRepoShape(format!("commit of tag '{name}' in {repo_url}"))
+        })?;
+
+      Ok(ResolvedRef {
+        kind: RefKind::AnnotatedTag,
+        name,
+        sha,
+        target_sha,
+      })
+    }
+    Some((kind, name, sha)) => Ok(ResolvedRef {
+      kind,
+      name,
+      target_sha: sha.clone(),
+      sha,
+    }),
+    None if is_commit_sha(git_ref) => Ok(ResolvedRef {
+      kind: RefKind::Commit,
+      name: git_ref.to_string(),
+      sha: git_ref.to_string(),
+      target_sha: git_ref.to_string(),
+    }),
+    None => Err(Error::GitRefNotFound(format!("{git_ref} in {repo_url}"))),
+  }
+}
+
+/// [`RemoteRefs`] of the `<sha>\t<ref>` lines printed by `git ls-remote`.
+/// Tags followed by a peeled `^{}` line are annotated.
+fn parse_ls_remote(stdout: &str) -> RemoteRefs {
+  let line_vec: Vec<(&str, &str)> = stdout
+    .lines()
+    .filter_map(|line| line.split_once('\t'))
+    .collect();
+
+  let peeled_map: HashMap<String, String> = line_vec
+    .iter()
+    .filter_map(|(sha, git_ref)| {
+      git_ref
+        .strip_suffix(PEELED_SUFFIX)
+        .map(|tag_ref| (tag_ref.to_string(), (*sha).to_string()))
+    })
+    .collect();
+
+  let ref_vec = line_vec
+    .iter()
+    .filter(|(_, git_ref)| !git_ref.ends_with(PEELED_SUFFIX))
+    .map(|(sha, git_ref)| {
+      let object_type = if peeled_map.contains_key(*git_ref) {
+        "tag"
+      } else {
+        "commit"
+      };
+      json!({"ref": git_ref, "object": {"type": object_type, "sha": sha}})
+    })
+    .collect();
+
+  RemoteRefs {
+    ref_vec,
+    peeled_map,
+  }
+}
+
+/// `GIT_SSH_COMMAND` authenticating with the private key at `key`, never
+/// prompting. git runs it through the shell, so `key` is quoted.
+fn ssh_command(key: &Path) -> String {
+  let key = key.to_string_lossy().replace('\'', r"'\''");
+  format!("ssh -i '{key}' -o IdentitiesOnly=yes -o BatchMode=yes")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ls_remote_output_in_gitea_refs_shape() {
+    let remote_refs = parse_ls_remote(
+      "aaaaaaa1\trefs/heads/main\n\
+       ccccccc3\trefs/tags/v1.0\n\
+       ddddddd4\trefs/tags/v2.0\n\
+       eeeeeee5\trefs/tags/v2.0^{}\n",
+    );
+
+    assert_eq!(
+      remote_refs.ref_vec,
+      vec![
+        json!({
+          "ref": "refs/heads/main",
+          "object": {"type": "commit", "sha": "aaaaaaa1"},
+        }),
+        json!({
+          "ref": "refs/tags/v1.0",
+          "object": {"type": "commit", "sha": "ccccccc3"},
+        }),
+        json!({
+          "ref": "refs/tags/v2.0",
+          "object": {"type": "tag", "sha": "ddddddd4"},
+        }),
+      ]
+    );
+    assert_eq!(
+      remote_refs.peeled_map,
+      HashMap::from([("refs/tags/v2.0".to_string(), "eeeeeee5".to_string())])
+    );
+    assert_eq!(
+      ssh_command(Path::new("/home/o'brien/.ssh/id_ed25519")),
+      r"ssh -i '/home/o'\''brien/.ssh/id_ed25519' -o IdentitiesOnly=yes -o BatchMode=yes"
+    );
+  }
+}
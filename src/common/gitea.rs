@@ -6,53 +6,90 @@
 /// it so subsequent Gitea calls hit the in-cluster service.
 pub(crate) const INTERNAL_API_HOST: &str = "api-gw-service-nmn.local";
 
+/// What kind of git object a ref name resolved to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefKind {
+  /// `refs/heads/<name>`.
+  Branch,
+  /// Lightweight tag: `refs/tags/<name>` pointing straight at a commit.
+  Tag,
+  /// Annotated tag: `refs/tags/<name>` pointing at a tag object, which
+  /// in turn points at a commit.
+  AnnotatedTag,
+  /// Not a ref at all; the name was taken as a commit SHA.
+  Commit,
+}
+
+/// A git ref resolved against the refs of a Gitea repo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedRef {
+  pub kind: RefKind,
+  /// Short name of the ref (branch or tag name, or the SHA itself for
+  /// [`RefKind::Commit`]).
+  pub name: String,
+  /// SHA the ref points at. For an annotated tag this is the tag
+  /// object's SHA, which is what CFS layers pin so the tag can still be
+  /// looked up through the Gitea APIs.
+  pub sha: String,
+  /// Commit SHA the ref finally resolves to. Same as `sha` except for
+  /// annotated tags.
+  pub target_sha: String,
+}
+
 /// HTTP helpers for the embedded CSM Gitea instance.
 pub mod http_client {
 
+  use super::{RefKind, ResolvedRef};
+  use crate::common::commit_cache::{self, EntryKind};
+  use crate::common::git_ssh;
+  use crate::common::metrics::MeteredSend;
   use crate::{common::http, error::Error};
+  use reqwest::header::{HeaderMap, LINK};
   use serde_json::Value;
 
-  /// Extract the repo name (the path after `vcs/cray/`) from a Gitea
-  /// URL, trimming the trailing `.git` if present.
+  /// Refs requested per page by [`get_all_refs`].
+  pub const REFS_PAGE_LIMIT: usize = 50;
+
+  /// Pages [`get_all_refs`] lists before failing with
+  /// [`Error::GitRefsTruncated`].
+  pub const DEFAULT_REFS_MAX_PAGES: usize = 100;
+
+  /// Repo name (`<owner>/<repo>`) of a Gitea clone URL, read from the
+  /// URL path: the `/vcs/` prefix CSM serves Gitea under and a trailing
+  /// `.git` are dropped, e.g.
+  /// `https://api-gw-service-nmn.local/vcs/cray/repo.git` gives
+  /// `cray/repo`. Any host is accepted.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Message`] if `repo_url` is not a URL or its path
+  /// has no `<owner>/<repo>`.
   pub fn get_repo_name_from_url(repo_url: &str) -> Result<String, Error> {
-    if repo_url.starts_with("https://api-gw-service-nmn.local") {
-      let gitea_internal_base_url =
-        "https://api-gw-service-nmn.local/vcs/cray/";
-
-      Ok(
-        repo_url
-          .trim_start_matches(gitea_internal_base_url)
-          .trim_end_matches(".git")
-          .to_string(),
-      )
-    } else if repo_url.starts_with("https://vcs.cmn.alps.cscs.ch") {
-      let gitea_external_base_url = "https://vcs.cmn.alps.cscs.ch/vcs/cray/";
-
-      Ok(
-        repo_url
-          .trim_start_matches(gitea_external_base_url)
-          .trim_end_matches(".git")
-          .to_string(),
-      )
-    } else if repo_url.starts_with("https://api.cmn.alps.cscs.ch") {
-      let gitea_external_base_url = "https://api.cmn.alps.cscs.ch/vcs/cray/";
-
-      Ok(
-        repo_url
-          .trim_start_matches(gitea_external_base_url)
-          .trim_end_matches(".git")
-          .to_string(),
-      )
-    } else {
-      Err(Error::Message(
-        "repo url provided does not match gitea internal or external URL"
-          .to_string(),
-      ))
+    let url = reqwest::Url::parse(repo_url).map_err(|e| {
+      Error::Message(format!("repo url '{repo_url}' is not valid: {e}"))
+    })?;
+
+    let path = url.path().trim_matches('/');
+    let repo_name = path
+      .strip_prefix("vcs/")
+      .unwrap_or(path)
+      .trim_end_matches(".git");
+
+    match repo_name.split_once('/') {
+      Some((owner, repo)) if !owner.is_empty() && !repo.is_empty() => {
+        Ok(repo_name.to_string())
+      }
+      _ => Err(Error::Message(format!(
+        "repo url '{repo_url}' does not name a Gitea repo (<owner>/<repo>)"
+      ))),
     }
   }
 
   /// Get all refs for a repository
   /// Used when getting repo details
+  ///
+  /// Refs of `ssh://` repos are listed with `git ls-remote`, see
+  /// [`crate::ClientConfig::with_git_ssh_key`].
   pub async fn get_all_refs_from_repo_url(
     gitea_base_url: &str,
     gitea_token: &str,
@@ -60,6 +97,12 @@ pub mod http_client {
     shasta_root_cert: &[u8],
     socks5_proxy: Option<&str>,
   ) -> Result<Vec<Value>, Error> {
+    if git_ssh::is_ssh_url(repo_url) {
+      return git_ssh::ls_remote(repo_url)
+        .await
+        .map(|remote_refs| remote_refs.ref_vec);
+    }
+
     let repo_name = get_repo_name_from_url(repo_url)?;
 
     get_all_refs(
@@ -74,28 +117,108 @@ pub mod http_client {
 
   /// Get all refs for a repository
   /// Used when getting repo details
+  ///
+  /// `repo_name` is `<owner>/<repo>`, see [`get_repo_name_from_url`].
+  /// Follows up to [`DEFAULT_REFS_MAX_PAGES`] pages, see
+  /// [`get_all_refs_with_max_pages`].
   pub async fn get_all_refs(
     gitea_base_url: &str,
     gitea_token: &str,
     repo_name: &str,
     shasta_root_cert: &[u8],
     socks5_proxy: Option<&str>,
+  ) -> Result<Vec<Value>, Error> {
+    get_all_refs_with_max_pages(
+      gitea_base_url,
+      gitea_token,
+      repo_name,
+      shasta_root_cert,
+      socks5_proxy,
+      DEFAULT_REFS_MAX_PAGES,
+    )
+    .await
+  }
+
+  /// Get all refs for a repository, [`REFS_PAGE_LIMIT`] per page.
+  ///
+  /// Pages are requested while the `Link` header of the last one has a
+  /// `rel="next"` link or its `X-Total-Count` header counts more refs
+  /// than listed so far. The page number is put in our own URL rather
+  /// than following the link, which names Gitea's public URL.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::GitRefsTruncated`] if more than `max_pages` pages
+  /// are needed, or an [`Error`] variant on Gitea, transport, or
+  /// deserialization failure.
+  pub async fn get_all_refs_with_max_pages(
+    gitea_base_url: &str,
+    gitea_token: &str,
+    repo_name: &str,
+    shasta_root_cert: &[u8],
+    socks5_proxy: Option<&str>,
+    max_pages: usize,
   ) -> Result<Vec<Value>, Error> {
     let client = http::build_client(shasta_root_cert, socks5_proxy)?;
-    let api_url = format!(
-      "{gitea_base_url}/api/v1/repos/cray/{repo_name}/git/refs"
-    );
+    let api_url = format!("{gitea_base_url}/api/v1/repos/{repo_name}/git/refs");
 
-    log::debug!("Get refs in gitea using through API call: {api_url}");
+    let mut ref_vec: Vec<Value> = Vec::new();
 
-    let response = client
-      .get(api_url)
-      .header("Authorization", format!("token {gitea_token}"))
-      .send()
-      .await
-      .map_err(Error::NetError)?;
+    for page in 1..=max_pages {
+      log::debug!(
+        "Get refs in gitea using through API call: {api_url} (page {page})"
+      );
+
+      let response = client
+        .get(&api_url)
+        .query(&[("page", page), ("limit", REFS_PAGE_LIMIT)])
+        .header("Authorization", format!("token {gitea_token}"))
+        .send_metered()
+        .await
+        .map_err(Error::NetError)?;
+
+      let headers = response.headers().clone();
+      let page_ref_vec: Vec<Value> =
+        http::handle_json_or_text_response(response).await?;
 
-    http::handle_json_or_text_response(response).await
+      if page_ref_vec.is_empty() {
+        return Ok(ref_vec);
+      }
+
+      ref_vec.extend(page_ref_vec);
+
+      if !more_refs_follow(&headers, ref_vec.len()) {
+        return Ok(ref_vec);
+      }
+    }
+
+    Err(Error::GitRefsTruncated {
+      repo: repo_name.to_string(),
+      max_pages,
+    })
+  }
+
+  /// Whether the `Link` or `X-Total-Count` headers of a refs page say
+  /// more refs follow the `listed` ones.
+  fn more_refs_follow(headers: &HeaderMap, listed: usize) -> bool {
+    let has_next_link = headers
+      .get_all(LINK)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .flat_map(|value| value.split(','))
+      .any(|link| {
+        link
+          .split(';')
+          .skip(1)
+          .any(|param| param.trim() == r#"rel="next""#)
+      });
+
+    let total_opt = headers
+      .get("x-total-count")
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.trim().parse::<usize>().ok());
+
+    has_next_link || total_opt.is_some_and(|total| listed < total)
   }
 
   /// Get most commit id (sha) pointed by a branch
@@ -107,114 +230,255 @@ pub mod http_client {
     repo_url: &str,
     branch_name: &str,
   ) -> Result<String, Error> {
-    let all_ref_vec = get_all_refs_from_repo_url(
+    resolve_ref(
       gitea_base_url,
       gitea_token,
       repo_url,
+      &format!("refs/heads/{branch_name}"),
+      shasta_root_cert,
+      socks5_proxy,
+    )
+    .await
+    .map(|resolved_ref| resolved_ref.sha)
+  }
+
+  /// Resolve `git_ref` in the Gitea repo at `repo_url`.
+  ///
+  /// `git_ref` may be fully qualified (`refs/heads/main`,
+  /// `refs/tags/v1.0`) or a short name, in which case tags win over
+  /// branches, as with `git rev-parse`. A name that matches no ref but
+  /// looks like a commit SHA resolves to [`RefKind::Commit`]. Annotated
+  /// tags are peeled to the commit they point at.
+  ///
+  /// `ssh://` repos are resolved with `git ls-remote`, see
+  /// [`crate::ClientConfig::with_git_ssh_key`].
+  pub async fn resolve_ref(
+    gitea_base_url: &str,
+    gitea_token: &str,
+    repo_url: &str,
+    git_ref: &str,
+    shasta_root_cert: &[u8],
+    socks5_proxy: Option<&str>,
+  ) -> Result<ResolvedRef, Error> {
+    if git_ssh::is_ssh_url(repo_url) {
+      return git_ssh::resolve_ref(repo_url, git_ref).await;
+    }
+
+    let repo_name = get_repo_name_from_url(repo_url)?;
+
+    let ref_vec = get_all_refs(
+      gitea_base_url,
+      gitea_token,
+      &repo_name,
       shasta_root_cert,
       socks5_proxy,
     )
     .await?;
 
-    let want = format!("refs/heads/{branch_name}");
-    let ref_details_opt = all_ref_vec.into_iter().find(|ref_details| {
-      ref_details
-        .get("ref")
-        .and_then(Value::as_str)
-        .is_some_and(|r| r == want)
-    });
+    resolve_ref_from_refs(
+      gitea_base_url,
+      gitea_token,
+      &repo_name,
+      &ref_vec,
+      git_ref,
+      shasta_root_cert,
+      socks5_proxy,
+    )
+    .await
+  }
 
-    match ref_details_opt {
-      Some(ref_details) => ref_details
-        .get("object")
-        .and_then(|object| object.get("sha"))
-        .and_then(Value::as_str)
-        .map(str::to_string)
-        .ok_or_else(|| {
-          Error::Message(
-            "Gitea response: ref object is missing 'sha'".to_string(),
-          )
-        }),
-      None => Err(Error::Message("SHA for branch not found".to_string())),
+  /// [`resolve_ref`] against a list of refs the caller already fetched
+  /// with [`get_all_refs`]. Only annotated tags trigger a Gitea call.
+  pub async fn resolve_ref_from_refs(
+    gitea_base_url: &str,
+    gitea_token: &str,
+    repo_name: &str,
+    ref_vec: &[Value],
+    git_ref: &str,
+    shasta_root_cert: &[u8],
+    socks5_proxy: Option<&str>,
+  ) -> Result<ResolvedRef, Error> {
+    match find_ref(ref_vec, git_ref)? {
+      Some((RefKind::AnnotatedTag, name, sha)) => {
+        let target_sha =
+          commit_cache::cached(EntryKind::Tag, repo_name, &sha, || async {
+            get_annotated_tag_commit(
+              gitea_base_url,
+              gitea_token,
+              repo_name,
+              &name,
+              shasta_root_cert,
+              socks5_proxy,
+            )
+            .await
+            .map(Value::String)
+          })
+          .await?
+          .as_str()
+          .map(str::to_string)
+          .ok_or_else(|| {
+            Error::GitRepoShape(format!(
+              "cached commit of tag '{name}' in {repo_name} is not a string"
+            ))
+          })?;
+
+        Ok(ResolvedRef {
+          kind: RefKind::AnnotatedTag,
+          name,
+          sha,
+          target_sha,
+        })
+      }
+      Some((kind, name, sha)) => Ok(ResolvedRef {
+        kind,
+        name,
+        target_sha: sha.clone(),
+        sha,
+      }),
+      None if is_commit_sha(git_ref) => Ok(ResolvedRef {
+        kind: RefKind::Commit,
+        name: git_ref.to_string(),
+        sha: git_ref.to_string(),
+        target_sha: git_ref.to_string(),
+      }),
+      None => Err(Error::GitRefNotFound(format!("{git_ref} in {repo_name}"))),
     }
   }
 
-  /// Returns the commit id (sha) related to a tag name
-  /// Used to translate CFS configuration layer tag name into commit id values when processing
-  /// SAT files
-  pub async fn get_tag_details(
-    repo_url: &str,
-    tag: &str,
+  /// Commit SHA an annotated tag points at, read from the Gitea tags
+  /// API (`/commit/sha`; `/id` is the tag object itself).
+  async fn get_annotated_tag_commit(
+    gitea_base_url: &str,
     gitea_token: &str,
+    repo_name: &str,
+    tag_name: &str,
     shasta_root_cert: &[u8],
     socks5_proxy: Option<&str>,
-    site_name: &str,
-  ) -> Result<Value, Error> {
-    let gitea_internal_base_url = "https://api-gw-service-nmn.local/vcs/";
-    let gitea_external_base_url =
-      format!("https://api.cmn.{site_name}.cscs.ch/vcs/");
-
-    let gitea_api_base_url = gitea_internal_base_url.to_owned() + "api/v1";
-
-    let repo_name = repo_url
-      .trim_start_matches(gitea_internal_base_url)
-      .trim_end_matches(".git");
-    let repo_name = repo_name
-      .trim_start_matches(&gitea_external_base_url)
-      .trim_end_matches(".git");
-
+  ) -> Result<String, Error> {
     let client = http::build_client(shasta_root_cert, socks5_proxy)?;
     let api_url =
-      format!("{gitea_api_base_url}/repos/{repo_name}/tags/{tag}");
+      format!("{gitea_base_url}/api/v1/repos/{repo_name}/tags/{tag_name}");
 
     log::debug!("Request to {api_url}");
 
     let response = client
       .get(api_url)
       .header("Authorization", format!("token {gitea_token}"))
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
-    http::handle_json_or_text_response(response).await
+    let tag_details: Value =
+      http::handle_json_or_text_response(response).await?;
+
+    tag_details
+      .pointer("/commit/sha")
+      .and_then(Value::as_str)
+      .map(str::to_string)
+      .ok_or_else(|| {
+        Error::GitRepoShape("commit sha from git repo tag".to_string())
+      })
   }
 
-  /// Returns the commit id (sha) related to a tag name
-  /// Used to translate CFS configuration layer tag name into commit id values when processing
-  /// SAT files
-  pub async fn get_commit_from_tag(
-    gitea_api_tag_url: &str,
-    tag: &str,
+  /// Find `git_ref` in a Gitea refs listing and return its kind, short
+  /// name and object SHA. Annotated tags are returned unpeeled.
+  pub(crate) fn find_ref(
+    ref_vec: &[Value],
+    git_ref: &str,
+  ) -> Result<Option<(RefKind, String, String)>, Error> {
+    let candidate_vec = if git_ref.starts_with("refs/") {
+      vec![git_ref.to_string()]
+    } else {
+      vec![
+        format!("refs/tags/{git_ref}"),
+        format!("refs/heads/{git_ref}"),
+      ]
+    };
+
+    for candidate in &candidate_vec {
+      let Some(ref_value) = ref_vec.iter().find(|ref_value| {
+        ref_value.get("ref").and_then(Value::as_str) == Some(candidate.as_str())
+      }) else {
+        continue;
+      };
+
+      let object_type = ref_value
+        .pointer("/object/type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::GitRepoShape("ref type".to_string()))?;
+      let sha = ref_value
+        .pointer("/object/sha")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::GitRepoShape("ref sha".to_string()))?;
+
+      let (kind, name) =
+        if let Some(name) = candidate.strip_prefix("refs/heads/") {
+          (RefKind::Branch, name)
+        } else if let Some(name) = candidate.strip_prefix("refs/tags/") {
+          if object_type == "tag" {
+            (RefKind::AnnotatedTag, name)
+          } else {
+            (RefKind::Tag, name)
+          }
+        } else {
+          continue;
+        };
+
+      return Ok(Some((kind, name.to_string(), sha.to_string())));
+    }
+
+    Ok(None)
+  }
+
+  /// Abbreviated or full hex commit SHA.
+  pub(crate) fn is_commit_sha(git_ref: &str) -> bool {
+    (7..=40).contains(&git_ref.len())
+      && git_ref.chars().all(|c| c.is_ascii_hexdigit())
+  }
+
+  /// Whether `file_path` exists in repo `repo_name` (`<owner>/<repo>`) at
+  /// `git_ref`, according to the Gitea contents API. `gitea_base_url`
+  /// ends with a `/`, as in [`get_commit_details`].
+  pub async fn file_exists(
+    gitea_base_url: &str,
+    repo_name: &str,
+    file_path: &str,
+    git_ref: &str,
     gitea_token: &str,
     shasta_root_cert: &[u8],
     socks5_proxy: Option<&str>,
-    site_name: &str,
-  ) -> Result<Value, Error> {
-    let external_vcs_base_url = format!(
-      "https://vcs.cmn.{site_name}.cscs.ch/vcs/api/v1/repos/cray/"
-    );
-    let repo_name: &str = gitea_api_tag_url
-      .trim_start_matches(&external_vcs_base_url)
-      .split('/')
-      .next()
-      .ok_or_else(|| Error::Message("Invalid repo URL".to_string()))?;
-
+  ) -> Result<bool, Error> {
+    let client = http::build_client(shasta_root_cert, socks5_proxy)?;
     let api_url = format!(
-      "https://api.cmn.{site_name}.cscs.ch/vcs/api/v1/repos/cray/{repo_name}/tags/{tag}"
+      "{gitea_base_url}api/v1/repos/{repo_name}/contents/{}",
+      file_path.trim_start_matches('/')
     );
 
-    let client = http::build_client(shasta_root_cert, socks5_proxy)?;
-
-    log::debug!("Request to {api_url}");
+    log::debug!("Check file exists in gitea using API call: {api_url}");
 
     let response = client
       .get(api_url)
+      .query(&[("ref", git_ref)])
       .header("Authorization", format!("token {gitea_token}"))
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
-    http::handle_json_or_text_response(response).await
+    if response.status().is_success() {
+      Ok(true)
+    } else if response.status() == reqwest::StatusCode::NOT_FOUND {
+      Ok(false)
+    } else {
+      let status = response.status().as_u16();
+      let url = response.url().to_string();
+      let payload = response.text().await?;
+      Err(Error::csm_from_response(
+        "GET",
+        &url,
+        status,
+        serde_json::json!({ "detail": payload }),
+      ))
+    }
   }
 
   /// Fetch commit details for `commitid` from the site's external Gitea
@@ -249,6 +513,8 @@ pub mod http_client {
   /// Fetch commit details for `commitid` from an arbitrary Gitea base
   /// URL. Lower-level companion to
   /// [`get_commit_details_from_external_url`].
+  ///
+  /// Goes through the [`crate::CommitCache`], if one is set.
   pub async fn get_commit_details(
     gitea_base_url: &str,
     repo_name: &str,
@@ -256,6 +522,28 @@ pub mod http_client {
     gitea_token: &str,
     shasta_root_cert: &[u8],
     socks5_proxy: Option<&str>,
+  ) -> Result<Value, crate::error::Error> {
+    commit_cache::cached(EntryKind::Commit, repo_name, commitid, || {
+      fetch_commit_details(
+        gitea_base_url,
+        repo_name,
+        commitid,
+        gitea_token,
+        shasta_root_cert,
+        socks5_proxy,
+      )
+    })
+    .await
+  }
+
+  /// [`get_commit_details`] without the commit cache.
+  async fn fetch_commit_details(
+    gitea_base_url: &str,
+    repo_name: &str,
+    commitid: &str,
+    gitea_token: &str,
+    shasta_root_cert: &[u8],
+    socks5_proxy: Option<&str>,
   ) -> Result<Value, crate::error::Error> {
     let client = http::build_client(shasta_root_cert, socks5_proxy)?;
     let api_url = format!(
@@ -267,7 +555,7 @@ pub mod http_client {
     let response = client
       .get(api_url)
       .header("Authorization", format!("token {gitea_token}"))
-      .send()
+      .send_metered()
       .await?;
 
     if response.status().is_success() {
@@ -289,3 +577,252 @@ pub mod http_client {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::http_client::{
+    file_exists, get_all_refs, get_all_refs_with_max_pages,
+    get_repo_name_from_url, resolve_ref, resolve_ref_from_refs,
+  };
+  use super::{RefKind, ResolvedRef};
+  use crate::error::Error;
+  use serde_json::{Value, json};
+  use wiremock::matchers::{header, method, path, query_param};
+  use wiremock::{Mock, MockServer, ResponseTemplate};
+
+  const TEST_PEM: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBhTCCASugAwIBAgIQIRi6zePL6mKjOipn+dNuaTAKBggqhkjOPQQDAjASMRAw\n\
+DgYDVQQKEwdBY21lIENvMB4XDTE3MTAyMDE5NDMwNloXDTE4MTAyMDE5NDMwNlow\n\
+EjEQMA4GA1UEChMHQWNtZSBDbzBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABD0d\n\
+7VNhbWvZLWPuj/RtHFjvtJBEwOkhbN/BnnE8rnZR8+sbwnc/KhCk3FhnpHZnQz7B\n\
+5aETbbIgmuvewdjvSBSjYzBhMA4GA1UdDwEB/wQEAwICpDATBgNVHSUEDDAKBggr\n\
+BgEFBQcDATAPBgNVHRMBAf8EBTADAQH/MCkGA1UdEQQiMCCCDmxvY2FsaG9zdDo1\n\
+NDUzgg4xMjcuMC4wLjE6NTQ1MzAKBggqhkjOPQQDAgNIADBFAiEA2zpJEPQyz6/l\n\
+Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
+6MF9+Yw1Yy0t\n\
+-----END CERTIFICATE-----\n";
+
+  fn ref_entry(git_ref: &str, object_type: &str, sha: &str) -> Value {
+    json!({"ref": git_ref, "object": {"type": object_type, "sha": sha}})
+  }
+
+  fn ref_vec() -> Vec<Value> {
+    vec![
+      ref_entry("refs/heads/main", "commit", "aaaaaaa1"),
+      ref_entry("refs/heads/v1.0", "commit", "bbbbbbb2"),
+      ref_entry("refs/tags/v1.0", "commit", "ccccccc3"),
+      ref_entry("refs/tags/v2.0", "tag", "ddddddd4"),
+    ]
+  }
+
+  // No Gitea call is made for anything but annotated tags, so an
+  // unreachable base URL is fine.
+  async fn resolve_offline(git_ref: &str) -> Result<ResolvedRef, Error> {
+    resolve_ref_from_refs(
+      "http://127.0.0.1:1",
+      "token",
+      "cray/repo",
+      &ref_vec(),
+      git_ref,
+      TEST_PEM.as_bytes(),
+      None,
+    )
+    .await
+  }
+
+  #[test]
+  fn repo_name_is_read_from_the_url_path() {
+    for repo_url in [
+      "https://api-gw-service-nmn.local/vcs/cray/repo.git",
+      "https://vcs.cmn.example.org/vcs/cray/repo",
+      "https://gitea.example.org/cray/repo.git/",
+    ] {
+      assert_eq!(get_repo_name_from_url(repo_url).unwrap(), "cray/repo");
+    }
+    assert_eq!(
+      get_repo_name_from_url("https://api.cmn.example.org/vcs/site/repo.git")
+        .unwrap(),
+      "site/repo"
+    );
+    assert!(get_repo_name_from_url("https://vcs.example.org/vcs/").is_err());
+    assert!(get_repo_name_from_url("not a url").is_err());
+  }
+
+  #[tokio::test]
+  async fn resolve_branch() {
+    let resolved = resolve_offline("refs/heads/main").await.unwrap();
+    assert_eq!(resolved.kind, RefKind::Branch);
+    assert_eq!(resolved.name, "main");
+    assert_eq!(resolved.sha, "aaaaaaa1");
+    assert_eq!(resolved.target_sha, "aaaaaaa1");
+  }
+
+  #[tokio::test]
+  async fn resolve_lightweight_tag_wins_over_branch_of_same_name() {
+    let resolved = resolve_offline("v1.0").await.unwrap();
+    assert_eq!(resolved.kind, RefKind::Tag);
+    assert_eq!(resolved.sha, "ccccccc3");
+    assert_eq!(resolved.target_sha, "ccccccc3");
+
+    let resolved = resolve_offline("refs/heads/v1.0").await.unwrap();
+    assert_eq!(resolved.kind, RefKind::Branch);
+    assert_eq!(resolved.sha, "bbbbbbb2");
+  }
+
+  #[tokio::test]
+  async fn resolve_commit_sha_not_in_refs() {
+    let resolved = resolve_offline("0123456789abcdef").await.unwrap();
+    assert_eq!(resolved.kind, RefKind::Commit);
+    assert_eq!(resolved.sha, "0123456789abcdef");
+    assert_eq!(resolved.target_sha, "0123456789abcdef");
+  }
+
+  #[tokio::test]
+  async fn resolve_unknown_ref_is_not_found() {
+    let result = resolve_offline("refs/heads/missing").await;
+    assert!(matches!(result, Err(Error::GitRefNotFound(_))));
+  }
+
+  #[tokio::test]
+  async fn resolve_annotated_tag_peels_to_commit() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path("/api/v1/repos/cray/repo/git/refs"))
+      .and(header("Authorization", "token token"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(ref_vec()))
+      .expect(1)
+      .mount(&server)
+      .await;
+    Mock::given(method("GET"))
+      .and(path("/api/v1/repos/cray/repo/tags/v2.0"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "name": "v2.0",
+        "id": "ddddddd4",
+        "commit": {"sha": "eeeeeee5"},
+      })))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let resolved = resolve_ref(
+      &server.uri(),
+      "token",
+      "https://api-gw-service-nmn.local/vcs/cray/repo.git",
+      "refs/tags/v2.0",
+      TEST_PEM.as_bytes(),
+      None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(resolved.kind, RefKind::AnnotatedTag);
+    assert_eq!(resolved.name, "v2.0");
+    assert_eq!(resolved.sha, "ddddddd4");
+    assert_eq!(resolved.target_sha, "eeeeeee5");
+  }
+
+  #[tokio::test]
+  async fn get_all_refs_follows_pages_up_to_max_pages() {
+    let server = MockServer::start().await;
+    let refs_path = "/api/v1/repos/cray/repo/git/refs";
+    let ref_vec = ref_vec();
+    Mock::given(method("GET"))
+      .and(path(refs_path))
+      .and(query_param("page", "1"))
+      .and(query_param("limit", "50"))
+      .respond_with(
+        ResponseTemplate::new(200)
+          .insert_header(
+            "Link",
+            format!(
+              "<https://vcs.example.com{refs_path}?page=2&limit=50>; rel=\"next\""
+            ),
+          )
+          .set_body_json(&ref_vec[..2]),
+      )
+      .expect(2)
+      .mount(&server)
+      .await;
+    Mock::given(method("GET"))
+      .and(path(refs_path))
+      .and(query_param("page", "2"))
+      .respond_with(
+        ResponseTemplate::new(200)
+          .insert_header("X-Total-Count", "4")
+          .set_body_json(&ref_vec[2..3]),
+      )
+      .expect(2)
+      .mount(&server)
+      .await;
+    Mock::given(method("GET"))
+      .and(path(refs_path))
+      .and(query_param("page", "3"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(&ref_vec[3..]))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let listed_ref_vec = get_all_refs(
+      &server.uri(),
+      "token",
+      "cray/repo",
+      TEST_PEM.as_bytes(),
+      None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(listed_ref_vec, ref_vec);
+
+    let result = get_all_refs_with_max_pages(
+      &server.uri(),
+      "token",
+      "cray/repo",
+      TEST_PEM.as_bytes(),
+      None,
+      2,
+    )
+    .await;
+    assert!(matches!(
+      result,
+      Err(Error::GitRefsTruncated { max_pages: 2, .. })
+    ));
+  }
+
+  #[tokio::test]
+  async fn file_exists_maps_404_to_false() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path("/api/v1/repos/cray/repo/contents/site.yml"))
+      .and(query_param("ref", "aaaaaaa1"))
+      .respond_with(
+        ResponseTemplate::new(200).set_body_json(json!({"name": "site.yml"})),
+      )
+      .mount(&server)
+      .await;
+    Mock::given(method("GET"))
+      .and(path("/api/v1/repos/cray/repo/contents/sitee.yml"))
+      .respond_with(ResponseTemplate::new(404))
+      .mount(&server)
+      .await;
+
+    let exists = |file_path: &'static str| {
+      let gitea_base_url = format!("{}/", server.uri());
+      async move {
+        file_exists(
+          &gitea_base_url,
+          "cray/repo",
+          file_path,
+          "aaaaaaa1",
+          "token",
+          TEST_PEM.as_bytes(),
+          None,
+        )
+        .await
+        .unwrap()
+      }
+    };
+
+    assert!(exists("site.yml").await);
+    assert!(!exists("sitee.yml").await);
+  }
+}
@@ -0,0 +1,233 @@
+//! Up-front checks of a CSM connection.
+//!
+//! [`ShastaClient::new`] and [`ShastaClient::with_config`] don't talk to
+//! CSM, so a wrong base URL, an untrusted certificate or a token issued
+//! to another client only shows up deep inside the first command.
+//! [`ShastaClient::connect`] builds the client and checks, in order:
+//!
+//! 1. `base_url` is an absolute `https://` or `http://` URL;
+//! 2. the token was issued to [`EXPECTED_AUDIENCE`] and hasn't expired;
+//! 3. CSM is reachable with the CA certificates and proxy of the
+//!    [`ClientConfig`], and accepts the token.
+//!
+//! The last check fetches the BOS, CFS and IMS versions, kept as the
+//! [`CsmVersions`] of the client. A failed check returns
+//! [`Error::ConnectionCheck`]. Keep using `new` or `with_config` for
+//! offline or mocked use.
+
+use std::{fmt::Write, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  ShastaClient,
+  bos::BosVersion,
+  common::{client_config::ClientConfig, http, jwt_ops},
+  error::Error,
+};
+
+/// Keycloak client CSM tokens are issued to.
+pub const EXPECTED_AUDIENCE: &str = "shasta";
+
+/// Versions of the CSM services, captured by [`ShastaClient::connect`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CsmVersions {
+  /// BOS version.
+  pub bos: BosVersion,
+  /// CFS version, `major.minor.patch`.
+  pub cfs: String,
+  /// IMS version, as reported by IMS.
+  pub ims: String,
+}
+
+/// `GET /cfs/versions` response; `links` is ignored.
+#[derive(Deserialize)]
+struct CfsVersionResponse {
+  major: String,
+  minor: String,
+  patch: String,
+}
+
+impl ShastaClient {
+  /// Build a client like [`ShastaClient::with_config`], then check the
+  /// base URL, the token and the connection to CSM, capturing the
+  /// [`CsmVersions`], see the [module docs](self).
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::ConnectionCheck`] naming the first check that
+  /// failed, or the error of [`ShastaClient::with_config`].
+  pub async fn connect(
+    base_url: impl Into<String>,
+    config: ClientConfig,
+    token: &str,
+  ) -> Result<Self, Error> {
+    let base_url = base_url.into();
+    check_base_url(&base_url)?;
+    check_token(token, Utc::now())?;
+
+    let mut client = Self::with_config(base_url, config)?;
+    let versions = client
+      .csm_versions_get(token)
+      .await
+      .map_err(|e| connection_error(client.base_url(), e))?;
+    log::info!(
+      "Connected to CSM at {}: BOS {}, CFS {}, IMS {}",
+      client.base_url(),
+      versions.bos,
+      versions.cfs,
+      versions.ims
+    );
+    client.versions = Some(Arc::new(versions));
+
+    Ok(client)
+  }
+
+  /// The CSM service versions, if the client was built with
+  /// [`ShastaClient::connect`].
+  #[must_use]
+  pub fn versions(&self) -> Option<&CsmVersions> {
+    self.versions.as_deref()
+  }
+
+  /// `GET` the BOS, CFS and IMS versions.
+  async fn csm_versions_get(&self, token: &str) -> Result<CsmVersions, Error> {
+    let cfs_api_url = format!("{}/cfs/versions", self.base_url());
+    let ims_api_url = format!("{}/ims/version", self.base_url());
+
+    let (bos, cfs, ims) = tokio::try_join!(
+      self.bos_version_v2_get(token),
+      http::get_json::<CfsVersionResponse>(self.http(), &cfs_api_url, token),
+      http::get_json::<String>(self.http(), &ims_api_url, token),
+    )?;
+
+    Ok(CsmVersions {
+      bos,
+      cfs: format!("{}.{}.{}", cfs.major, cfs.minor, cfs.patch),
+      ims,
+    })
+  }
+}
+
+fn check_base_url(base_url: &str) -> Result<(), Error> {
+  let reason = match reqwest::Url::parse(base_url) {
+    Ok(url) if !matches!(url.scheme(), "https" | "http") => {
+      format!("'{base_url}' is not an https:// or http:// URL")
+    }
+    Ok(url) if url.host_str().is_none() => {
+      format!("'{base_url}' has no host")
+    }
+    Ok(_) => return Ok(()),
+    Err(e) => format!("'{base_url}' is not a URL: {e}"),
+  };
+
+  Err(Error::ConnectionCheck {
+    check: "base_url",
+    reason,
+  })
+}
+
+fn check_token(token: &str, now: DateTime<Utc>) -> Result<(), Error> {
+  let token_error = |reason: String| Error::ConnectionCheck {
+    check: "token",
+    reason,
+  };
+
+  let audience_vec =
+    jwt_ops::get_audiences(token).map_err(|e| token_error(e.to_string()))?;
+  if !audience_vec
+    .iter()
+    .any(|audience| audience == EXPECTED_AUDIENCE)
+  {
+    return Err(token_error(format!(
+      "token was issued to [{}], not '{EXPECTED_AUDIENCE}'",
+      audience_vec.join(", ")
+    )));
+  }
+
+  if let Some(expiration) = jwt_ops::get_expiration(token)
+    .ok()
+    .filter(|expiration| *expiration <= now)
+  {
+    return Err(token_error(format!(
+      "token expired at {}",
+      expiration.to_rfc3339()
+    )));
+  }
+
+  Ok(())
+}
+
+/// The [`Error::ConnectionCheck`] of `e`, returned by the first requests
+/// to CSM at `base_url`.
+fn connection_error(base_url: &str, e: Error) -> Error {
+  match e {
+    Error::NetError(e) => {
+      // TLS failures are only named by the sources of the error
+      let mut reason = format!("can't reach CSM at {base_url}: {e}");
+      let mut source_opt = std::error::Error::source(&e);
+      while let Some(source) = source_opt {
+        let _ = write!(reason, ": {source}");
+        source_opt = source.source();
+      }
+      Error::ConnectionCheck {
+        check: "connect",
+        reason,
+      }
+    }
+    Error::CsmError {
+      status: 401 | 403, ..
+    } => Error::ConnectionCheck {
+      check: "token",
+      reason: format!("CSM rejected the token: {e}"),
+    },
+    e => Error::ConnectionCheck {
+      check: "versions",
+      reason: e.to_string(),
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+  use serde_json::json;
+
+  use super::*;
+
+  fn jwt_with_claims(claims: &serde_json::Value) -> String {
+    format!(
+      "header.{}.signature",
+      URL_SAFE_NO_PAD.encode(claims.to_string())
+    )
+  }
+
+  #[test]
+  fn base_url_and_token_checks() {
+    assert!(check_base_url("https://api.shasta.example.com/apis").is_ok());
+    for base_url in ["api.shasta.example.com", "ftp://api.example.com"] {
+      assert!(matches!(
+        check_base_url(base_url),
+        Err(Error::ConnectionCheck {
+          check: "base_url",
+          ..
+        })
+      ));
+    }
+
+    let now = Utc::now();
+    let exp = now.timestamp() + 60;
+    let valid = jwt_with_claims(&json!({"azp": "shasta", "exp": exp}));
+    assert!(check_token(&valid, now).is_ok());
+
+    let other_client = jwt_with_claims(&json!({"azp": "grafana", "exp": exp}));
+    let expired = jwt_with_claims(&json!({"aud": ["shasta"], "exp": 1}));
+    for token in [other_client.as_str(), expired.as_str(), "not-a-jwt"] {
+      assert!(matches!(
+        check_token(token, now),
+        Err(Error::ConnectionCheck { check: "token", .. })
+      ));
+    }
+  }
+}
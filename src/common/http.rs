@@ -21,12 +21,19 @@
 //! at-most-once-or-error semantics for a write should compose their own
 //! retry-with-idempotency-key wrapper.
 
-use std::time::Duration;
+use std::{fmt::Display, future::Future, sync::Arc, time::Duration};
 
+use reqwest::{IntoUrl, Method};
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
+use crate::common::auth_provider::AuthTokenProvider;
+use crate::common::client_config::ClientConfig;
+use crate::common::events::{self, Event};
+use crate::common::metrics::{self, MeteredSend};
+use crate::common::rate_limit::RateLimiter;
+use crate::common::request_id;
 use crate::error::Error;
 
 /// TCP connect deadline for `reqwest::Client`s built by csm-rs. A
@@ -60,7 +67,13 @@ pub(crate) const HTTP_5XX_RETRY_INITIAL_DELAY: Duration =
 /// by the GET-shaped helpers — applying it to POST/PUT/DELETE would
 /// risk double-creating or double-deleting, so write-shaped helpers
 /// don't use it.
-pub(crate) async fn retry_on_5xx<F, Fut, T>(mut op: F) -> Result<T, Error>
+///
+/// Every retry is reported as an [`Event::Retry`] naming `operation`
+/// (e.g. `GET <url>`).
+pub(crate) async fn retry_on_5xx<F, Fut, T>(
+  operation: &str,
+  mut op: F,
+) -> Result<T, Error>
 where
   F: FnMut() -> Fut,
   Fut: std::future::Future<Output = Result<T, Error>>,
@@ -84,6 +97,12 @@ where
           HTTP_5XX_RETRY_ATTEMPTS,
           delay
         );
+        events::emit(Event::Retry {
+          operation: operation.to_string(),
+          attempt: attempt + 1,
+          max_attempts: HTTP_5XX_RETRY_ATTEMPTS,
+          reason: e.to_string(),
+        });
         last_err = Some(e);
         tokio::time::sleep(delay).await;
         delay = (delay * 2).min(Duration::from_secs(8));
@@ -98,32 +117,50 @@ where
   }))
 }
 
-/// Build a `reqwest::Client` configured with the CSM root certificate and an
-/// optional SOCKS5 proxy. This is the per-request setup that used to be
-/// inlined at every call site.
+/// The [`ClientConfig`] of the client of the enclosing
+/// [`crate::ShastaClient::scope`], or outside one, settings trusting
+/// `shasta_root_cert` and going through `socks5_proxy`.
+pub(crate) fn client_config(
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+) -> ClientConfig {
+  if let Some(client) = crate::client::current() {
+    return client.config().clone();
+  }
+
+  let mut config = ClientConfig::new().with_ca_certs(shasta_root_cert);
+  if let Some(proxy) = socks5_proxy {
+    config = config.with_proxy(proxy);
+  }
+  config
+}
+
+/// Build a `reqwest::Client` with the settings of [`client_config`]. This
+/// is the per-request setup that used to be inlined at every call site.
 pub(crate) fn build_client(
   shasta_root_cert: &[u8],
   socks5_proxy: Option<&str>,
 ) -> Result<reqwest::Client, Error> {
-  build_client_with_auth(shasta_root_cert, socks5_proxy, None)
+  build_client_with_auth(&client_config(shasta_root_cert, socks5_proxy), None)
 }
 
-/// Build a `reqwest::Client` like [`build_client`], optionally baking in a
-/// bearer-auth default header. The bearer-token variant is used by the
+/// Build a `reqwest::Client` with the CA certificates, client identity
+/// and proxy of `config`, optionally baking in a bearer-auth default
+/// header. The bearer-token variant is used by the
 /// generated HSM client, where progenitor's `Client` newtype owns the
 /// `reqwest::Client` and there's no convenient hook for per-request auth.
 ///
 /// Returns `Error::Message` if `bearer_token` contains bytes that are not
 /// valid in an HTTP header value (e.g. control characters, `\n`).
 pub(crate) fn build_client_with_auth(
-  shasta_root_cert: &[u8],
-  socks5_proxy: Option<&str>,
+  config: &ClientConfig,
   bearer_token: Option<&str>,
 ) -> Result<reqwest::Client, Error> {
-  let mut builder = reqwest::Client::builder()
-    .connect_timeout(HTTP_CONNECT_TIMEOUT)
-    .timeout(HTTP_REQUEST_TIMEOUT)
-    .add_root_certificate(reqwest::Certificate::from_pem(shasta_root_cert)?);
+  let mut builder = config.apply(
+    reqwest::Client::builder()
+      .connect_timeout(HTTP_CONNECT_TIMEOUT)
+      .timeout(HTTP_REQUEST_TIMEOUT),
+  )?;
 
   if let Some(token) = bearer_token {
     let mut headers = reqwest::header::HeaderMap::new();
@@ -132,15 +169,124 @@ pub(crate) fn build_client_with_auth(
       .map_err(|e| Error::Message(format!("invalid bearer token: {e}")))?;
     value.set_sensitive(true);
     headers.insert(reqwest::header::AUTHORIZATION, value);
+    // Authenticated clients are built per call by the generated-client
+    // wrappers, so they can carry the current operation's correlation id
+    if let Some(value) = request_id::current().and_then(|request_id| {
+      reqwest::header::HeaderValue::from_str(&request_id).ok()
+    }) {
+      headers.insert(request_id::HEADER, value);
+    }
     builder = builder.default_headers(headers);
   }
 
-  let client = match socks5_proxy {
-    Some(proxy) => builder.proxy(reqwest::Proxy::all(proxy)?).build()?,
-    None => builder.build()?,
-  };
+  Ok(builder.build()?)
+}
+
+/// The `reqwest::Client` of a [`crate::ShastaClient`] along with its
+/// [`AuthTokenProvider`], if any. Requests built from it and sent with
+/// [`MeteredSend::send_metered`] carry a token from that provider,
+/// retried once with a new one if CSM answers 401, whether or not they
+/// run inside [`crate::ShastaClient::scope`].
+#[derive(Clone, Copy)]
+pub(crate) struct CsmHttp<'a> {
+  client: &'a reqwest::Client,
+  token_provider_opt: Option<&'a Arc<dyn AuthTokenProvider>>,
+  rate_limiter_opt: Option<&'a RateLimiter>,
+}
+
+impl<'a> CsmHttp<'a> {
+  pub(crate) fn new(
+    client: &'a reqwest::Client,
+    token_provider_opt: Option<&'a Arc<dyn AuthTokenProvider>>,
+    rate_limiter_opt: Option<&'a RateLimiter>,
+  ) -> Self {
+    Self {
+      client,
+      token_provider_opt,
+      rate_limiter_opt,
+    }
+  }
+
+  pub(crate) fn get(self, url: impl IntoUrl) -> CsmRequestBuilder {
+    self.request(Method::GET, url)
+  }
+
+  pub(crate) fn post(self, url: impl IntoUrl) -> CsmRequestBuilder {
+    self.request(Method::POST, url)
+  }
+
+  pub(crate) fn put(self, url: impl IntoUrl) -> CsmRequestBuilder {
+    self.request(Method::PUT, url)
+  }
+
+  pub(crate) fn patch(self, url: impl IntoUrl) -> CsmRequestBuilder {
+    self.request(Method::PATCH, url)
+  }
+
+  pub(crate) fn delete(self, url: impl IntoUrl) -> CsmRequestBuilder {
+    self.request(Method::DELETE, url)
+  }
+
+  fn request(self, method: Method, url: impl IntoUrl) -> CsmRequestBuilder {
+    CsmRequestBuilder {
+      request_builder: self.client.request(method, url),
+      token_provider_opt: self.token_provider_opt.cloned(),
+      rate_limiter_opt: self.rate_limiter_opt.cloned(),
+    }
+  }
+}
+
+/// A client without token provider nor rate limiter of its own, e.g.
+/// for requests outside a [`crate::ShastaClient`].
+impl<'a> From<&'a reqwest::Client> for CsmHttp<'a> {
+  fn from(client: &'a reqwest::Client) -> Self {
+    Self::new(client, None, None)
+  }
+}
+
+/// `reqwest::RequestBuilder` of a [`CsmHttp`].
+pub(crate) struct CsmRequestBuilder {
+  request_builder: reqwest::RequestBuilder,
+  token_provider_opt: Option<Arc<dyn AuthTokenProvider>>,
+  rate_limiter_opt: Option<RateLimiter>,
+}
+
+impl CsmRequestBuilder {
+  pub(crate) fn bearer_auth(self, token: impl Display) -> Self {
+    self.map(|request_builder| request_builder.bearer_auth(token))
+  }
+
+  pub(crate) fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+    self.map(|request_builder| request_builder.json(json))
+  }
 
-  Ok(client)
+  pub(crate) fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+    self.map(|request_builder| request_builder.query(query))
+  }
+
+  fn map(
+    self,
+    f: impl FnOnce(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+  ) -> Self {
+    Self {
+      request_builder: f(self.request_builder),
+      token_provider_opt: self.token_provider_opt,
+      rate_limiter_opt: self.rate_limiter_opt,
+    }
+  }
+}
+
+impl MeteredSend for CsmRequestBuilder {
+  fn send_metered(
+    self,
+  ) -> impl Future<Output = Result<reqwest::Response, reqwest::Error>> + Send
+  {
+    metrics::send_with_provider(
+      self.request_builder,
+      self.token_provider_opt,
+      self.rate_limiter_opt,
+    )
+  }
 }
 
 /// On a 2xx response, deserialize the body as `T`. On any other status,
@@ -178,15 +324,16 @@ pub(crate) async fn handle_json_or_text_response<T: DeserializeOwned>(
 /// Retries transparently on CSM 5xx errors per the module-level
 /// retry policy.
 pub(crate) async fn get_json<T: DeserializeOwned>(
-  client: &reqwest::Client,
+  client: impl Into<CsmHttp<'_>>,
   url: &str,
   shasta_token: &str,
 ) -> Result<T, Error> {
-  retry_on_5xx(|| async {
+  let client: CsmHttp = client.into();
+  retry_on_5xx(&format!("GET {url}"), || async {
     let response = client
       .get(url)
       .bearer_auth(shasta_token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
     handle_json_response(response, "GET").await
@@ -196,7 +343,7 @@ pub(crate) async fn get_json<T: DeserializeOwned>(
 
 /// POST JSON `body` to `url` with bearer auth, deserialize success body as `T`.
 pub(crate) async fn post_json<B, T>(
-  client: &reqwest::Client,
+  client: impl Into<CsmHttp<'_>>,
   url: &str,
   shasta_token: &str,
   body: &B,
@@ -205,11 +352,12 @@ where
   B: Serialize + ?Sized,
   T: DeserializeOwned,
 {
+  let client: CsmHttp = client.into();
   let response = client
     .post(url)
     .json(body)
     .bearer_auth(shasta_token)
-    .send()
+    .send_metered()
     .await
     .map_err(Error::NetError)?;
 
@@ -218,7 +366,7 @@ where
 
 /// PUT JSON `body` to `url` with bearer auth, deserialize success body as `T`.
 pub(crate) async fn put_json<B, T>(
-  client: &reqwest::Client,
+  client: impl Into<CsmHttp<'_>>,
   url: &str,
   shasta_token: &str,
   body: &B,
@@ -227,11 +375,12 @@ where
   B: Serialize + ?Sized,
   T: DeserializeOwned,
 {
+  let client: CsmHttp = client.into();
   let response = client
     .put(url)
     .json(body)
     .bearer_auth(shasta_token)
-    .send()
+    .send_metered()
     .await
     .map_err(Error::NetError)?;
 
@@ -243,7 +392,7 @@ where
 /// Retries transparently on CSM 5xx errors per the module-level
 /// retry policy.
 pub(crate) async fn get_json_with_query<Q, T>(
-  client: &reqwest::Client,
+  client: impl Into<CsmHttp<'_>>,
   url: &str,
   shasta_token: &str,
   query: &Q,
@@ -252,12 +401,13 @@ where
   Q: Serialize + ?Sized,
   T: DeserializeOwned,
 {
-  retry_on_5xx(|| async {
+  let client: CsmHttp = client.into();
+  retry_on_5xx(&format!("GET {url}"), || async {
     let response = client
       .get(url)
       .query(query)
       .bearer_auth(shasta_token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
     handle_json_response(response, "GET").await
@@ -408,7 +558,7 @@ where
 
     let batch_id = idx + 1;
     let in_flight = in_flight.clone();
-    tasks.spawn(async move {
+    tasks.spawn(request_id::propagate(async move {
       let _permit = permit;
       let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
       let pending = total_batches.saturating_sub(batch_id);
@@ -421,7 +571,7 @@ where
         "parallel_batch: batch {batch_id}/{total_batches} done (in_flight={after}/{max_in_flight})"
       );
       result
-    });
+    }));
   }
 
   let mut out = Vec::new();
@@ -438,14 +588,15 @@ where
 /// DELETE `url` with bearer auth. Returns unit on 2xx; otherwise
 /// `Error::CsmError(json)`.
 pub(crate) async fn delete(
-  client: &reqwest::Client,
+  client: impl Into<CsmHttp<'_>>,
   url: &str,
   shasta_token: &str,
 ) -> Result<(), Error> {
+  let client: CsmHttp = client.into();
   let response = client
     .delete(url)
     .bearer_auth(shasta_token)
-    .send()
+    .send_metered()
     .await
     .map_err(Error::NetError)?;
 
@@ -464,7 +615,7 @@ mod tests {
   use serde::Deserialize;
   use serde_json::json;
   use wiremock::matchers::{
-    bearer_token, body_json, header, method, path, query_param,
+    bearer_token, body_json, header, header_exists, method, path, query_param,
   };
   use wiremock::{Mock, MockServer, ResponseTemplate};
 
@@ -491,9 +642,9 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
   }
 
   // NOTE: there is no `build_client_with_invalid_pem_fails` test because
-  // `reqwest::Certificate::from_pem` is lenient: it tolerates input without
-  // PEM blocks and returns Ok with an empty cert chain. So malformed input
-  // is not actually surfaced as an error by build_client.
+  // `reqwest::Certificate::from_pem_bundle` is lenient: it tolerates input
+  // without PEM blocks and returns Ok with an empty cert chain. So malformed
+  // input is not actually surfaced as an error by build_client.
 
   #[test]
   fn build_client_with_socks5_proxy_succeeds() {
@@ -508,12 +659,45 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
     assert!(client.is_err());
   }
 
+  fn test_config() -> ClientConfig {
+    ClientConfig::new().with_ca_certs(TEST_PEM)
+  }
+
+  #[test]
+  fn build_client_with_ca_bundle_and_http_proxy_succeeds() {
+    let config = ClientConfig::new()
+      .with_ca_certs(TEST_PEM)
+      .with_ca_certs(TEST_PEM)
+      .with_proxy("http://proxy.example.com:3128")
+      .danger_accept_invalid_certs(true);
+    assert!(build_client_with_auth(&config, None).is_ok());
+  }
+
+  #[tokio::test]
+  async fn client_config_is_the_scoped_clients() {
+    let config = client_config(TEST_PEM.as_bytes(), Some("socks5://a:1080"));
+    assert_eq!(config.ca_bundle(), TEST_PEM.as_bytes());
+    assert_eq!(config.proxy(), Some("socks5://a:1080"));
+
+    let shasta_client = crate::ShastaClient::with_config(
+      "https://api.example.com",
+      test_config()
+        .with_ca_certs(TEST_PEM)
+        .with_proxy("http://proxy.example.com:3128"),
+    )
+    .expect("should build");
+    let config = shasta_client
+      .scope(async { client_config(b"", None) })
+      .await;
+    assert_eq!(config.ca_bundle(), shasta_client.root_cert());
+    assert_eq!(config.proxy(), Some("http://proxy.example.com:3128"));
+  }
+
   #[test]
   fn build_client_with_auth_invalid_token_bytes_returns_error() {
     // A `\n` byte cannot legally appear in an HTTP header value. Used to
     // panic in the old gen_client; now surfaces as Error::Message.
-    let result =
-      build_client_with_auth(TEST_PEM.as_bytes(), None, Some("bad\ntoken"));
+    let result = build_client_with_auth(&test_config(), Some("bad\ntoken"));
     match result {
       Err(Error::Message(m)) => {
         assert!(
@@ -535,9 +719,8 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
       .mount(&server)
       .await;
 
-    let client =
-      build_client_with_auth(TEST_PEM.as_bytes(), None, Some("token-x"))
-        .expect("should build");
+    let client = build_client_with_auth(&test_config(), Some("token-x"))
+      .expect("should build");
     let resp = client
       .get(format!("{}/ping", server.uri()))
       .send()
@@ -546,6 +729,91 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
     assert_eq!(resp.status(), 200);
   }
 
+  #[tokio::test]
+  async fn request_id_is_sent_inside_an_operation_scope() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path("/ping"))
+      .and(header_exists(request_id::HEADER))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": 1})))
+      .expect(2)
+      .mount(&server)
+      .await;
+
+    request_id::scope("test", async {
+      let client = build_client_with_auth(&test_config(), Some("token-x"))
+        .expect("should build");
+      client
+        .get(format!("{}/ping", server.uri()))
+        .send()
+        .await
+        .expect("generated-client path should carry the id");
+
+      let _: Value = get_json(
+        &reqwest::Client::new(),
+        &format!("{}/ping", server.uri()),
+        "token-x",
+      )
+      .await
+      .expect("send_metered path should carry the id");
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn bearer_comes_from_the_scoped_token_provider() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path("/ping"))
+      .and(bearer_token("provided"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": 1})))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let shasta_client =
+      crate::ShastaClient::new(server.uri(), TEST_PEM.as_bytes(), None)
+        .expect("should build")
+        .with_token_provider(std::sync::Arc::new(crate::StaticToken::new(
+          "provided",
+        )));
+
+    let _: Value = shasta_client
+      .scope(get_json(
+        &reqwest::Client::new(),
+        &format!("{}/ping", server.uri()),
+        "expired",
+      ))
+      .await
+      .expect("the provider's token should replace the caller's");
+  }
+
+  #[tokio::test]
+  async fn access_log_records_calls_inside_record() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path("/ping"))
+      .respond_with(ResponseTemplate::new(404))
+      .mount(&server)
+      .await;
+
+    let access_log = crate::AccessLog::new();
+    let result: Result<Value, Error> = access_log
+      .record(get_json(
+        &reqwest::Client::new(),
+        &format!("{}/ping?limit=1", server.uri()),
+        "token-x",
+      ))
+      .await;
+
+    assert!(result.is_err());
+    let call_vec = access_log.calls();
+    assert_eq!(call_vec.len(), 1);
+    assert_eq!(call_vec[0].method, "GET");
+    assert_eq!(call_vec[0].path, "/ping");
+    assert_eq!(call_vec[0].status, Some(404));
+  }
+
   // ---------- request helpers (use wiremock, plain HTTP) ----------
 
   #[derive(Deserialize, Debug, PartialEq)]
@@ -859,7 +1127,7 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
   async fn retry_on_5xx_returns_eventual_success() {
     use std::sync::atomic::{AtomicU32, Ordering};
     let calls = AtomicU32::new(0);
-    let result: u32 = retry_on_5xx(|| async {
+    let result: u32 = retry_on_5xx("GET test", || async {
       let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
       if n < 3 {
         Err(Error::CsmError {
@@ -883,7 +1151,7 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
   async fn retry_on_5xx_propagates_after_exhausting_attempts() {
     use std::sync::atomic::{AtomicU32, Ordering};
     let calls = AtomicU32::new(0);
-    let result: Result<u32, _> = retry_on_5xx(|| async {
+    let result: Result<u32, _> = retry_on_5xx("GET test", || async {
       calls.fetch_add(1, Ordering::SeqCst);
       Err(Error::CsmError {
         method: "GET".into(),
@@ -905,7 +1173,7 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
   async fn retry_on_5xx_does_not_retry_4xx() {
     use std::sync::atomic::{AtomicU32, Ordering};
     let calls = AtomicU32::new(0);
-    let result: Result<u32, _> = retry_on_5xx(|| async {
+    let result: Result<u32, _> = retry_on_5xx("GET test", || async {
       calls.fetch_add(1, Ordering::SeqCst);
       Err(Error::CsmError {
         method: "GET".into(),
@@ -925,7 +1193,7 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
   async fn retry_on_5xx_does_not_retry_net_error() {
     use std::sync::atomic::{AtomicU32, Ordering};
     let calls = AtomicU32::new(0);
-    let result: Result<u32, _> = retry_on_5xx(|| async {
+    let result: Result<u32, _> = retry_on_5xx("GET test", || async {
       calls.fetch_add(1, Ordering::SeqCst);
       Err(Error::Message("network down".to_string()))
     })
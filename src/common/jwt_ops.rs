@@ -5,6 +5,7 @@ use base64::{
   Engine,
   engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
 };
+use chrono::{DateTime, Utc};
 use serde_json::Value;
 
 fn get_claims_from_jwt_token(token: &str) -> Result<Value, Error> {
@@ -48,9 +49,8 @@ pub fn get_name(token: &str) -> Result<String, Error> {
 }
 
 /// Extract the `preferred_username` claim from a Keycloak JWT — the
-/// stable login identifier. Only used by the SAT-file admin workflow,
-/// so gated behind the `commands-admin` Cargo feature.
-#[cfg(feature = "commands-admin")]
+/// stable login identifier, used as acting user of an
+/// [`crate::ExecutionContext`].
 pub fn get_preferred_username(token: &str) -> Result<String, Error> {
   let jwt_claims = get_claims_from_jwt_token(token)?;
 
@@ -65,6 +65,40 @@ pub fn get_preferred_username(token: &str) -> Result<String, Error> {
   }
 }
 
+/// Extract the `exp` claim from a JWT — when the token expires — used by
+/// the [`crate::AuthTokenProvider`]s to re-authenticate in time.
+pub fn get_expiration(token: &str) -> Result<DateTime<Utc>, Error> {
+  get_claims_from_jwt_token(token)?
+    .get("exp")
+    .and_then(Value::as_i64)
+    .and_then(|exp| DateTime::from_timestamp(exp, 0))
+    .ok_or(Error::JwtShape("claim 'exp' not found in JWT auth token"))
+}
+
+/// Extract the audiences of a JWT: the `aud` claim (a string or a list)
+/// followed by the `azp` (authorized party) claim, which is where
+/// Keycloak puts the client the token was issued to.
+pub fn get_audiences(token: &str) -> Result<Vec<String>, Error> {
+  let jwt_claims = get_claims_from_jwt_token(token)?;
+
+  let mut audience_vec: Vec<String> = match jwt_claims.get("aud") {
+    Some(Value::String(audience)) => vec![audience.clone()],
+    Some(Value::Array(audience_vec)) => audience_vec
+      .iter()
+      .filter_map(|audience| audience.as_str().map(str::to_string))
+      .collect(),
+    _ => Vec::new(),
+  };
+  audience_vec.extend(
+    jwt_claims
+      .get("azp")
+      .and_then(Value::as_str)
+      .map(str::to_string),
+  );
+
+  Ok(audience_vec)
+}
+
 /// Returns the list of available HSM groups in JWT user token. The list is filtered and system HSM
 /// groups (eg alps, alpsm, alpse, etc)
 pub fn get_roles(token: &str) -> Result<Vec<String>, Error> {
@@ -127,6 +161,20 @@ mod tests {
     assert!(get_name("not-a-jwt").is_err());
   }
 
+  // ---------- get_expiration ----------
+
+  #[test]
+  fn get_expiration_returns_exp_claim() {
+    let token = jwt_with_claims(json!({"exp": 1_700_000_000}));
+    assert_eq!(get_expiration(&token).unwrap().timestamp(), 1_700_000_000);
+  }
+
+  #[test]
+  fn get_expiration_errors_when_missing() {
+    let token = jwt_with_claims(json!({"sub": "abc"}));
+    assert!(get_expiration(&token).is_err());
+  }
+
   // ---------- get_preferred_username ----------
 
   #[cfg(feature = "commands-admin")]
@@ -143,6 +191,20 @@ mod tests {
     assert!(get_preferred_username(&token).is_err());
   }
 
+  // ---------- get_audiences ----------
+
+  #[test]
+  fn get_audiences_returns_aud_then_azp() {
+    let token = jwt_with_claims(json!({"aud": "account", "azp": "shasta"}));
+    assert_eq!(get_audiences(&token).unwrap(), vec!["account", "shasta"]);
+
+    let token = jwt_with_claims(json!({"aud": ["shasta", "account"]}));
+    assert_eq!(get_audiences(&token).unwrap(), vec!["shasta", "account"]);
+
+    let token = jwt_with_claims(json!({"sub": "abc"}));
+    assert!(get_audiences(&token).unwrap().is_empty());
+  }
+
   // ---------- get_roles ----------
 
   #[test]
@@ -0,0 +1,334 @@
+//! Optional instrumentation through the [`metrics`](https://docs.rs/metrics)
+//! crate facade, enabled by the `metrics` Cargo feature.
+//!
+//! csm-rs only emits; embedders install whichever recorder they want
+//! (e.g. `metrics-exporter-prometheus`). Emitted series:
+//!
+//! - `csm_rs_http_requests_total{service, method, status}` — counter of
+//!   HTTP requests; `status` is the HTTP status code, or `error` when no
+//!   response was received.
+//! - `csm_rs_http_errors_total{service, method}` — counter of requests
+//!   that failed in transport or returned a non-2xx status.
+//! - `csm_rs_http_request_duration_seconds{service, method}` — latency
+//!   histogram.
+//! - `csm_rs_command_duration_seconds{command}` — histogram of
+//!   [`crate::commands`] workflow durations.
+//!
+//! `service` is the first path segment after `/apis` (`cfs`, `bos`,
+//! `smd`, `ims`, ...). Without the feature nothing is emitted; the
+//! calls are still timed for the [`super::access_log`].
+
+use std::{
+  future::Future,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+use reqwest::header::{AUTHORIZATION, HeaderValue};
+
+use super::{
+  access_log,
+  auth_provider::AuthTokenProvider,
+  rate_limit::{self, RateLimiter},
+  request_id,
+};
+use crate::error::Error;
+
+/// `send()` replacement for `reqwest::RequestBuilder` that records the
+/// request in the HTTP metrics and the current access log (see
+/// [`super::access_log`]), tags it with the current correlation id (see
+/// [`super::request_id`]) and waits for the rate limit of its client (see
+/// [`super::rate_limit`]). Requests of a [`crate::ShastaClient`] with a
+/// token provider (see [`super::http::CsmHttp`]) carry a token from the
+/// provider, and so do other requests to its base URL inside its
+/// [`crate::ShastaClient::scope`].
+pub(crate) trait MeteredSend {
+  /// Send the request, recording count, status and latency.
+  fn send_metered(
+    self,
+  ) -> impl Future<Output = Result<reqwest::Response, reqwest::Error>> + Send;
+}
+
+impl MeteredSend for reqwest::RequestBuilder {
+  fn send_metered(
+    self,
+  ) -> impl Future<Output = Result<reqwest::Response, reqwest::Error>> + Send
+  {
+    send_with_provider(self, None, None)
+  }
+}
+
+/// [`MeteredSend::send_metered`] for `request_builder`, taking the
+/// bearer token from `token_provider_opt`, or without one, from the
+/// token provider of the scoped client serving the request URL, and
+/// throttled by `rate_limiter_opt`, or without one, by the scoped
+/// client.
+pub(crate) async fn send_with_provider(
+  request_builder: reqwest::RequestBuilder,
+  token_provider_opt: Option<Arc<dyn AuthTokenProvider>>,
+  rate_limiter_opt: Option<RateLimiter>,
+) -> Result<reqwest::Response, reqwest::Error> {
+  rate_limit::acquire(rate_limiter_opt.as_ref()).await;
+  send_and_record(request_id::tag(request_builder), token_provider_opt).await
+}
+
+async fn send_and_record(
+  request_builder: reqwest::RequestBuilder,
+  token_provider_opt: Option<Arc<dyn AuthTokenProvider>>,
+) -> Result<reqwest::Response, reqwest::Error> {
+  let (client, request) = request_builder.build_split();
+  let mut request = request?;
+
+  // Only requests already carrying a bearer token get the provider's, not
+  // e.g. the Gitea calls authenticating with `token <gitea token>`
+  let is_bearer = request
+    .headers()
+    .get(AUTHORIZATION)
+    .and_then(|value| value.to_str().ok())
+    .is_some_and(|value| value.starts_with("Bearer "));
+  let token_provider_opt = token_provider_opt
+    .or_else(|| {
+      crate::client::current_for(request.url().as_str())
+        .and_then(|shasta_client| shasta_client.token_provider)
+    })
+    .filter(|_| is_bearer);
+  let Some(token_provider) = token_provider_opt else {
+    return execute_and_record(&client, request).await;
+  };
+
+  authorize(&mut request, &token_provider).await;
+  let retry_opt = request.try_clone();
+  let result = execute_and_record(&client, request).await;
+
+  match (result, retry_opt) {
+    (Ok(response), Some(mut retry))
+      if response.status() == reqwest::StatusCode::UNAUTHORIZED =>
+    {
+      log::info!("CSM rejected the bearer token, requesting a new one");
+      token_provider.invalidate().await;
+      authorize(&mut retry, &token_provider).await;
+      execute_and_record(&client, retry).await
+    }
+    (result, _) => result,
+  }
+}
+
+/// Replace the bearer token of `request` with one from `token_provider`,
+/// keeping the caller's if the provider fails.
+async fn authorize(
+  request: &mut reqwest::Request,
+  token_provider: &Arc<dyn AuthTokenProvider>,
+) {
+  let header_value = token_provider.token().await.and_then(|token| {
+    HeaderValue::from_str(&format!("Bearer {token}"))
+      .map_err(|e| Error::Message(e.to_string()))
+  });
+
+  match header_value {
+    Ok(mut header_value) => {
+      header_value.set_sensitive(true);
+      request.headers_mut().insert(AUTHORIZATION, header_value);
+    }
+    Err(e) => {
+      log::warn!("Token provider failed, keeping the caller's token: {e}");
+    }
+  }
+}
+
+/// Send `request` with `client`, recording it.
+async fn execute_and_record(
+  client: &reqwest::Client,
+  request: reqwest::Request,
+) -> Result<reqwest::Response, reqwest::Error> {
+  let path = request.url().path().to_string();
+  let method = request.method().to_string();
+
+  let start = Instant::now();
+  let result = client.execute(request).await;
+  let elapsed = start.elapsed();
+  let status_opt = result
+    .as_ref()
+    .ok()
+    .map(|response| response.status().as_u16());
+
+  record_http(&service_label(&path), &method, elapsed, status_opt);
+  access_log::record(&method, &path, status_opt, elapsed);
+
+  result
+}
+
+/// Label for the CSM service addressed by `path`: the first segment
+/// after an optional `apis` prefix.
+fn service_label(path: &str) -> String {
+  path
+    .split('/')
+    .filter(|segment| !segment.is_empty())
+    .find(|segment| *segment != "apis")
+    .unwrap_or("unknown")
+    .to_string()
+}
+
+/// Record one HTTP call. `status_opt` is `None` when no response was
+/// received (connect error, timeout, ...).
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_http(
+  service: &str,
+  method: &str,
+  elapsed: Duration,
+  status_opt: Option<u16>,
+) {
+  #[cfg(feature = "metrics")]
+  {
+    let status =
+      status_opt.map_or_else(|| "error".to_string(), |s| s.to_string());
+
+    ::metrics::counter!(
+      "csm_rs_http_requests_total",
+      "service" => service.to_string(),
+      "method" => method.to_string(),
+      "status" => status,
+    )
+    .increment(1);
+
+    if status_opt.is_none_or(|status| !(200..300).contains(&status)) {
+      ::metrics::counter!(
+        "csm_rs_http_errors_total",
+        "service" => service.to_string(),
+        "method" => method.to_string(),
+      )
+      .increment(1);
+    }
+
+    ::metrics::histogram!(
+      "csm_rs_http_request_duration_seconds",
+      "service" => service.to_string(),
+      "method" => method.to_string(),
+    )
+    .record(elapsed.as_secs_f64());
+  }
+}
+
+/// Await a progenitor-generated client call and record it. The generated
+/// clients don't expose the HTTP method nor the path, so they are
+/// recorded as `*` and `service`.
+pub(crate) async fn record_generated<T, E>(
+  service: &str,
+  call: impl Future<
+    Output = Result<
+      progenitor_client::ResponseValue<T>,
+      progenitor_client::Error<E>,
+    >,
+  >,
+) -> Result<progenitor_client::ResponseValue<T>, progenitor_client::Error<E>> {
+  let start = Instant::now();
+
+  let result = call.await;
+
+  let elapsed = start.elapsed();
+  let status_opt = match &result {
+    Ok(response_value) => Some(response_value.status().as_u16()),
+    Err(e) => e.status().map(|status| status.as_u16()),
+  };
+
+  record_http(service, "*", elapsed, status_opt);
+  access_log::record("*", service, status_opt, elapsed);
+
+  result
+}
+
+/// Build a generated client with `gen_client` and a token from
+/// [`crate::ShastaClient::fresh_token`], then await and record `op` on
+/// it like [`record_generated`]. As for [`MeteredSend::send_metered`], if
+/// CSM answers 401 and `client` has a token provider, the provider is
+/// told to invalidate the token and `op` is retried once on a client
+/// with a new one.
+///
+/// # Errors
+///
+/// Returns the error of the token provider or of `gen_client`; the
+/// outcome of `op` is returned as is.
+pub(crate) async fn send_generated<C, T, E, F, Fut>(
+  client: &crate::ShastaClient,
+  token: &str,
+  service: &str,
+  gen_client: fn(&crate::ShastaClient, &str) -> Result<C, Error>,
+  op: F,
+) -> Result<
+  Result<progenitor_client::ResponseValue<T>, progenitor_client::Error<E>>,
+  Error,
+>
+where
+  F: Fn(C) -> Fut,
+  Fut: Future<
+    Output = Result<
+      progenitor_client::ResponseValue<T>,
+      progenitor_client::Error<E>,
+    >,
+  >,
+{
+  let rate_limiter = client.config().rate_limiter();
+  let generated_client = gen_client(client, &client.fresh_token(token).await?)?;
+  rate_limit::acquire(Some(rate_limiter)).await;
+  let result = record_generated(service, op(generated_client)).await;
+
+  match (result, client.token_provider()) {
+    (Err(e), Some(token_provider))
+      if e.status() == Some(reqwest::StatusCode::UNAUTHORIZED) =>
+    {
+      log::info!("CSM rejected the bearer token, requesting a new one");
+      token_provider.invalidate().await;
+      let generated_client =
+        gen_client(client, &token_provider.token().await?)?;
+      rate_limit::acquire(Some(rate_limiter)).await;
+      Ok(record_generated(service, op(generated_client)).await)
+    }
+    (result, _) => Ok(result),
+  }
+}
+
+/// Records the lifetime of a [`crate::commands`] workflow in
+/// `csm_rs_command_duration_seconds` when dropped.
+pub(crate) struct CommandTimer {
+  #[cfg(feature = "metrics")]
+  command: &'static str,
+  #[cfg(feature = "metrics")]
+  start: Instant,
+}
+
+impl CommandTimer {
+  /// Start timing `command`.
+  #[must_use]
+  #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+  pub(crate) fn start(command: &'static str) -> Self {
+    Self {
+      #[cfg(feature = "metrics")]
+      command,
+      #[cfg(feature = "metrics")]
+      start: Instant::now(),
+    }
+  }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for CommandTimer {
+  fn drop(&mut self) {
+    ::metrics::histogram!(
+      "csm_rs_command_duration_seconds",
+      "command" => self.command,
+    )
+    .record(self.start.elapsed().as_secs_f64());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn service_label_skips_apis_prefix() {
+    assert_eq!(service_label("/apis/cfs/v3/sessions"), "cfs");
+    assert_eq!(service_label("/apis/smd/hsm/v2/groups"), "smd");
+    assert_eq!(service_label("/vcs/api/v1/repos"), "vcs");
+    assert_eq!(service_label("/"), "unknown");
+  }
+}
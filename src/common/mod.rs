@@ -5,7 +5,24 @@
 //!
 //! Submodules:
 //!
+//! - [`access_log`] — in-memory record of the CSM API calls of a
+//!   command run; surfaced as [`crate::AccessLog`].
+//! - [`auth_provider`] — [`auth_provider::AuthTokenProvider`]s handing
+//!   out fresh bearer tokens to long operations; surfaced as
+//!   [`crate::AuthTokenProvider`] and its implementations.
 //! - [`authentication`] — Keycloak / OIDC token acquisition for Shasta.
+//! - [`client_config`] — CA certificates, client identity and proxy of
+//!   the HTTP clients of a [`crate::ShastaClient`]; surfaced as
+//!   [`crate::ClientConfig`].
+//! - [`commit_cache`] — on-disk cache of the Gitea commit and tag
+//!   metadata of CFS configuration layers; surfaced as
+//!   [`crate::CommitCache`].
+//! - [`csm_snapshot`] — CSM collections fetched at most once per
+//!   command and shared by the helpers it calls; surfaced as
+//!   [`crate::CsmSnapshot`].
+//! - [`handshake`] — up-front checks of the base URL, token and
+//!   connection of [`crate::ShastaClient::connect`]; surfaces
+//!   [`crate::CsmVersions`].
 //! - [`jwt_ops`] — JWT decoding helpers (RFC 7519 base64url-aware) used
 //!   by callers that need to introspect a Shasta token without verifying
 //!   its signature.
@@ -14,26 +31,71 @@
 //!   `ConfigMap`).
 //! - [`vault`] — fetch K8s service-account secrets from Vault, which is
 //!   the supported way to obtain CSM cluster credentials off-cluster.
+//! - [`execution_context`] — acting user, impersonated tenant and
+//!   reason the mutating commands run with; surfaced as
+//!   [`crate::ExecutionContext`].
+//! - [`events`] — typed progress events of the long-running commands
+//!   and the [`events::EventSink`] receiving them; surfaced as
+//!   [`crate::Event`] and [`crate::EventSink`].
 //! - [`gitea`] — small client for the embedded CSM Gitea instance used
 //!   by CFS configuration layers.
+//! - [`naming`] — templates for the names csm-rs gives to the CFS
+//!   sessions and BOS session templates it creates; surfaced as
+//!   [`crate::NamingPolicy`].
+//! - [`paging`] — `(timestamp, name)` ordering and cursor paging shared
+//!   by the list helpers; surfaced through [`crate::filter`].
+//! - [`product_catalog`] — typed view of the `cray-product-catalog`
+//!   `ConfigMap`; surfaced as [`crate::product_catalog`].
+//! - [`protection`] — description marker protecting BOS session
+//!   templates and CFS configurations from deletion; surfaced as
+//!   [`crate::protection`].
+//! - [`session_profile`] — named sets of CFS session options (Ansible
+//!   verbosity, passthrough, limit, ...); surfaced as
+//!   [`crate::SessionProfile`].
+//! - [`webhooks`] — [`webhooks::Webhook`]s notified of the changes
+//!   csm-rs makes (SAT apply, deletes, power, HSM groups); surfaced as
+//!   [`crate::webhooks`].
+//! - [`xname`] — typed, validated CSM component names ([`xname::XName`],
+//!   [`xname::NodeXName`], [`xname::BmcXName`]).
 //!
-//! `http` and `yaml` exist as crate-internal utilities and are not
-//! part of the public surface.
+//! `git_ssh`, `http`, `metrics`, `poll`, `rate_limit`, `request_id` and
+//! `yaml` exist as crate-internal utilities and are not part of the public
+//! surface ([`crate::RateLimit`] and [`crate::WaitOptions`] are
+//! re-exported at the crate root).
 
+pub mod access_log;
+pub mod auth_provider;
 pub mod authentication;
+pub mod client_config;
+pub mod commit_cache;
+pub mod csm_snapshot;
+pub mod events;
+pub mod execution_context;
+pub(crate) mod git_ssh;
 pub mod gitea;
+pub mod handshake;
 pub(crate) mod http;
 pub mod jwt_ops;
-pub(crate) mod poll;
 /// In-cluster Kubernetes client helpers (used to read `ConfigMaps` such
 /// as `cray-product-catalog`). Requires the `k8s-console` Cargo
 /// feature.
 #[cfg(feature = "k8s-console")]
 pub mod kubernetes;
+pub(crate) mod metrics;
+pub mod naming;
+pub mod paging;
+pub(crate) mod poll;
+pub mod product_catalog;
+pub mod protection;
+pub(crate) mod rate_limit;
+pub(crate) mod request_id;
+pub mod session_profile;
 // The only user of `vault::http_client::fetch_shasta_k8s_secrets_from_vault`
 // is the Kubernetes secret-fetching path (CFS session log streaming
 // and `cfs::session::i_post_sync`), so the whole module rides the
 // same `k8s-console` feature gate.
 #[cfg(feature = "k8s-console")]
 pub mod vault;
+pub mod webhooks;
+pub mod xname;
 pub(crate) mod yaml;
@@ -0,0 +1,202 @@
+//! Names and descriptions csm-rs gives to the resources it creates.
+//!
+//! CFS session names, BOS session template names and descriptions, and
+//! BOS boot set names are rendered from the templates of the
+//! [`NamingPolicy`] set with [`crate::ClientConfig::with_naming_policy`]
+//! on the client whose [`crate::ShastaClient::scope`] the command runs
+//! in. Templates may use these variables:
+//!
+//! - `{date}` / `{time}` / `{timestamp}` — current UTC date
+//!   (`YYYYMMDD`), time (`HHMMSS`) and both (`YYYYMMDDHHMMSS`).
+//! - `{user}` — `preferred_username` of the Shasta token, empty if the
+//!   token has none.
+//! - `{group}` — HSM group(s) targeted, comma separated; empty if none.
+//! - `{name}` — name given by the caller, e.g. the SAT file entry name.
+//! - `{configuration}` — CFS configuration name.
+//! - `{boot_set}` — BOS boot set name.
+//!
+//! A variable that doesn't apply to a name renders empty; unknown
+//! `{...}` placeholders are kept verbatim. A BOS session template name
+//! using `{date}`, `{time}` or `{timestamp}` changes on every run, so
+//! the SAT file workflow never finds the template it created before
+//! and creates a new one each time.
+//!
+//! The `rootfs_provider_passthrough` of SAT file boot sets is rendered
+//! with the same variables, `{group}` being the boot set's own
+//! `node_groups`. Jinja `{{...}}` expressions are kept verbatim for the
+//! caller to render.
+//!
+//! CFS sessions building images are not renamed: CFS names the
+//! resulting image after the session.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Templates for the names and descriptions csm-rs generates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamingPolicy {
+  /// CFS sessions created to configure nodes.
+  pub cfs_session_name: String,
+  /// BOS session templates created from a SAT file.
+  pub bos_template_name: String,
+  /// Description of BOS session templates created from a SAT file.
+  pub bos_template_description: String,
+  /// Boot sets of BOS session templates created from a SAT file.
+  pub boot_set_name: String,
+}
+
+impl Default for NamingPolicy {
+  /// Names csm-rs has always used.
+  fn default() -> Self {
+    Self {
+      cfs_session_name: "{configuration}-{timestamp}".to_string(),
+      bos_template_name: "{name}".to_string(),
+      bos_template_description:
+        "BOS sessiontemplate created by manta from SAT file".to_string(),
+      boot_set_name:
+        "Boot set property '{boot_set}' created by manta from SAT file"
+          .to_string(),
+    }
+  }
+}
+
+impl NamingPolicy {
+  /// Name of a CFS session configuring nodes.
+  #[must_use]
+  pub fn cfs_session_name(&self, context: &NamingContext) -> String {
+    context.render(&self.cfs_session_name)
+  }
+
+  /// Name of a BOS session template.
+  #[must_use]
+  pub fn bos_template_name(&self, context: &NamingContext) -> String {
+    context.render(&self.bos_template_name)
+  }
+
+  /// Description of a BOS session template.
+  #[must_use]
+  pub fn bos_template_description(&self, context: &NamingContext) -> String {
+    context.render(&self.bos_template_description)
+  }
+
+  /// Name of a BOS boot set.
+  #[must_use]
+  pub fn boot_set_name(&self, context: &NamingContext) -> String {
+    context.render(&self.boot_set_name)
+  }
+}
+
+/// Values of the template variables for one generated name.
+#[derive(Debug, Clone, Default)]
+pub struct NamingContext {
+  var_map: BTreeMap<&'static str, String>,
+}
+
+impl NamingContext {
+  /// Context with the current date and time and the user owning
+  /// `shasta_token`.
+  #[must_use]
+  pub fn new(shasta_token: &str) -> Self {
+    Self::now().with(
+      "user",
+      super::jwt_ops::get_preferred_username(shasta_token).unwrap_or_default(),
+    )
+  }
+
+  /// Context with the current date and time only.
+  #[must_use]
+  pub fn now() -> Self {
+    let now = chrono::Utc::now();
+
+    Self::default()
+      .with("date", now.format("%Y%m%d").to_string())
+      .with("time", now.format("%H%M%S").to_string())
+      .with("timestamp", now.format("%Y%m%d%H%M%S").to_string())
+  }
+
+  /// Set variable `var` to `value`.
+  #[must_use]
+  pub fn with(mut self, var: &'static str, value: impl Into<String>) -> Self {
+    self.var_map.insert(var, value.into());
+    self
+  }
+
+  /// `template` with every `{var}` replaced.
+  pub(crate) fn render(&self, template: &str) -> String {
+    const KNOWN_VAR_VEC: [&str; 8] = [
+      "date",
+      "time",
+      "timestamp",
+      "user",
+      "group",
+      "name",
+      "configuration",
+      "boot_set",
+    ];
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+      rendered.push_str(&rest[..start]);
+      let after = &rest[start + 1..];
+
+      let Some(end) = after.find('}') else {
+        rendered.push_str(&rest[start..]);
+        return rendered;
+      };
+
+      let var = &after[..end];
+      if KNOWN_VAR_VEC.contains(&var) {
+        rendered.push_str(self.var_map.get(var).map_or("", String::as_str));
+      } else {
+        rendered.push_str(&rest[start..=start + 1 + end]);
+      }
+      rest = &after[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    rendered
+  }
+}
+
+/// Naming policy of the client in scope, the default one if there is
+/// no client in scope.
+pub(crate) fn current() -> NamingPolicy {
+  crate::client::scoped_config(|config| config.naming_policy().clone())
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn render_replaces_known_vars_only() {
+    let context = NamingContext::default()
+      .with("configuration", "cos-config")
+      .with("timestamp", "20240501120000")
+      .with("user", "alice");
+
+    let policy = NamingPolicy {
+      cfs_session_name: "{user}-{configuration}-{group}{timestamp}-{other}-{"
+        .to_string(),
+      ..Default::default()
+    };
+
+    assert_eq!(
+      policy.cfs_session_name(&context),
+      "alice-cos-config-20240501120000-{other}-{"
+    );
+    assert_eq!(
+      NamingPolicy::default().cfs_session_name(&context),
+      "cos-config-20240501120000"
+    );
+    assert_eq!(
+      context.render("{{default.system_name}}-{configuration}"),
+      "{{default.system_name}}-cos-config"
+    );
+  }
+}
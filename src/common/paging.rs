@@ -0,0 +1,191 @@
+//! Ordering and cursor paging for large listings (CFS configurations,
+//! CFS sessions, BOS session templates, IMS images).
+//!
+//! Listings are ordered by `(timestamp, name)` in ascending order; items
+//! without a timestamp come first. A [`Cursor`] records the position of
+//! the last item handed out so the next page resumes right after it even
+//! if items were added or removed in between.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::NaiveDateTime;
+
+use crate::{error::Error, filter::Filterable};
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Position in a `(timestamp, name)` ordered listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+  timestamp: Option<NaiveDateTime>,
+  name: String,
+}
+
+impl Cursor {
+  /// Cursor pointing at `item`.
+  #[must_use]
+  pub fn at<T: Filterable>(item: &T) -> Self {
+    Self {
+      timestamp: item.filter_timestamp(),
+      name: item.filter_name().unwrap_or_default().to_string(),
+    }
+  }
+
+  /// Opaque URL-safe token for this cursor.
+  #[must_use]
+  pub fn encode(&self) -> String {
+    let timestamp = self
+      .timestamp
+      .map(|timestamp| timestamp.format(TIMESTAMP_FORMAT).to_string())
+      .unwrap_or_default();
+
+    URL_SAFE_NO_PAD.encode(format!("{timestamp}\n{}", self.name))
+  }
+
+  /// Parse a token produced by [`Cursor::encode`].
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::ValidationFailed`] if `token` was not produced by
+  /// [`Cursor::encode`].
+  pub fn decode(token: &str) -> Result<Self, Error> {
+    let invalid = || Error::ValidationFailed("invalid paging cursor");
+
+    let bytes = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(bytes).map_err(|_| invalid())?;
+    let (timestamp, name) = decoded.split_once('\n').ok_or_else(invalid)?;
+
+    let timestamp = if timestamp.is_empty() {
+      None
+    } else {
+      Some(
+        NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+          .map_err(|_| invalid())?,
+      )
+    };
+
+    Ok(Self {
+      timestamp,
+      name: name.to_string(),
+    })
+  }
+
+  fn key(&self) -> (Option<NaiveDateTime>, &str) {
+    (self.timestamp, &self.name)
+  }
+}
+
+/// One page of a listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+  /// Items in this page, in listing order.
+  pub items: Vec<T>,
+  /// Cursor to request the next page with; `None` on the last page.
+  pub next_cursor: Option<Cursor>,
+}
+
+fn sort_key<T: Filterable>(item: &T) -> (Option<NaiveDateTime>, &str) {
+  (
+    item.filter_timestamp(),
+    item.filter_name().unwrap_or_default(),
+  )
+}
+
+/// Stable sort by `(timestamp, name)` in ascending order. Keys are
+/// computed once per item since timestamps are parsed from strings.
+pub(crate) fn sort_by_timestamp<T: Filterable>(item_vec: &mut [T]) {
+  item_vec.sort_by_cached_key(|item| {
+    let (timestamp, name) = sort_key(item);
+    (timestamp, name.to_string())
+  });
+}
+
+/// Keep only the last `limit` items of a sorted listing.
+pub(crate) fn keep_last<T>(item_vec: &mut Vec<T>, limit: usize) {
+  item_vec.drain(..item_vec.len().saturating_sub(limit));
+}
+
+/// Cut the page of at most `page_size` items following `after_opt` out of
+/// a listing sorted with [`sort_by_timestamp`].
+pub(crate) fn paginate<T: Filterable>(
+  item_vec: Vec<T>,
+  after_opt: Option<&Cursor>,
+  page_size: usize,
+) -> Page<T> {
+  let mut item_iter = item_vec
+    .into_iter()
+    .skip_while(|item| {
+      after_opt.is_some_and(|after| sort_key(item) <= after.key())
+    })
+    .peekable();
+
+  let items: Vec<T> = item_iter.by_ref().take(page_size).collect();
+
+  let next_cursor = if item_iter.peek().is_some() {
+    items.last().map(Cursor::at)
+  } else {
+    None
+  };
+
+  Page { items, next_cursor }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ims::image::http_client::types::Image;
+
+  fn image(name: &str, created: Option<&str>) -> Image {
+    Image {
+      name: name.to_string(),
+      created: created.map(str::to_string),
+      ..Default::default()
+    }
+  }
+
+  fn names(image_vec: &[Image]) -> Vec<&str> {
+    image_vec.iter().map(|image| image.name.as_str()).collect()
+  }
+
+  #[test]
+  fn sort_breaks_timestamp_ties_by_name() {
+    let mut image_vec = vec![
+      image("b", Some("2024-01-01T00:00:00+00:00")),
+      image("a", Some("2024-01-01T00:00:00+00:00")),
+      image("undated", None),
+    ];
+
+    sort_by_timestamp(&mut image_vec);
+
+    assert_eq!(names(&image_vec), vec!["undated", "a", "b"]);
+  }
+
+  #[test]
+  fn cursor_round_trips_through_token() {
+    let cursor = Cursor::at(&image("cos", Some("2024-01-01T10:20:30.5Z")));
+    assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+
+    let undated = Cursor::at(&image("cos", None));
+    assert_eq!(Cursor::decode(&undated.encode()).unwrap(), undated);
+
+    assert!(Cursor::decode("not a cursor").is_err());
+  }
+
+  #[test]
+  fn paginate_resumes_after_cursor() {
+    let listing = || {
+      vec![
+        image("a", Some("2024-01-01T00:00:00Z")),
+        image("b", Some("2024-02-01T00:00:00Z")),
+        image("c", Some("2024-03-01T00:00:00Z")),
+      ]
+    };
+
+    let first = paginate(listing(), None, 2);
+    assert_eq!(names(&first.items), vec!["a", "b"]);
+
+    let token = first.next_cursor.unwrap().encode();
+    let second = paginate(listing(), Some(&Cursor::decode(&token).unwrap()), 2);
+    assert_eq!(names(&second.items), vec!["c"]);
+    assert!(second.next_cursor.is_none());
+  }
+}
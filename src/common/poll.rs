@@ -9,11 +9,98 @@
 //! - a max-attempt cap so a stuck remote can't wedge the caller,
 //! - exponential backoff so a recovering peer isn't hammered, and
 //! - jitter so multiple csm-rs callers aren't synchronised.
+//!
+//! Callers choose how long and how often to wait through
+//! [`WaitOptions`], re-exported as [`crate::WaitOptions`].
 
 use std::time::Duration;
 
 use crate::error::Error;
 
+/// How long, and how often, a csm-rs call waits for CSM to reach some
+/// state: an IMS job or CFS session to finish, nodes to power off, ...
+///
+/// The first re-check happens `poll_interval` after the first check.
+/// With `backoff`, every further delay doubles, up to that maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitOptions {
+  /// Give up waiting after this long.
+  pub timeout: Duration,
+  /// Delay before the first re-check.
+  pub poll_interval: Duration,
+  /// Longest delay between checks when backing off, `None` to check
+  /// every `poll_interval`.
+  pub backoff: Option<Duration>,
+}
+
+impl Default for WaitOptions {
+  /// Up to an hour, checking after 2 s and then backing off to 30 s.
+  fn default() -> Self {
+    Self::new(Duration::from_secs(3600), Duration::from_secs(2))
+      .with_backoff(Duration::from_secs(30))
+  }
+}
+
+impl WaitOptions {
+  /// Wait up to `timeout`, checking every `poll_interval`.
+  #[must_use]
+  pub const fn new(timeout: Duration, poll_interval: Duration) -> Self {
+    Self {
+      timeout,
+      poll_interval,
+      backoff: None,
+    }
+  }
+
+  /// Double the delay between checks after each one, up to
+  /// `max_poll_interval`.
+  #[must_use]
+  pub const fn with_backoff(mut self, max_poll_interval: Duration) -> Self {
+    self.backoff = Some(max_poll_interval);
+    self
+  }
+
+  /// Same options with a different `timeout`.
+  #[must_use]
+  pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = timeout;
+    self
+  }
+
+  /// Delay before the check following one waited for `delay`.
+  #[must_use]
+  pub fn next_delay(&self, delay: Duration) -> Duration {
+    match self.backoff {
+      Some(max_poll_interval) => {
+        delay.saturating_mul(2).min(max_poll_interval.max(delay))
+      }
+      None => delay,
+    }
+  }
+
+  /// [`PollBackoff`] making as many checks as fit in `timeout`,
+  /// ignoring jitter.
+  pub(crate) fn poll_backoff(&self) -> PollBackoff {
+    // A zero interval would never exhaust the timeout
+    let initial_delay = self.poll_interval.max(Duration::from_millis(1));
+
+    let mut max_attempts: u32 = 1;
+    let mut elapsed = Duration::ZERO;
+    let mut delay = initial_delay;
+    while elapsed + delay <= self.timeout && max_attempts < u32::MAX {
+      elapsed += delay;
+      max_attempts += 1;
+      delay = self.next_delay(delay);
+    }
+
+    PollBackoff {
+      initial_delay,
+      max_delay: self.backoff.unwrap_or(initial_delay).max(initial_delay),
+      max_attempts,
+    }
+  }
+}
+
 /// How [`poll_until_with_backoff`] paces its retries.
 ///
 /// `initial_delay` is the first sleep between query attempts;
@@ -82,7 +169,7 @@ where
 /// Apply ±25 % jitter to `d`, using the current wall-clock nanos as a
 /// cheap entropy source. Not cryptographic — just enough randomness
 /// to break up synchronised pollers.
-fn jittered(d: Duration) -> Duration {
+pub(crate) fn jittered(d: Duration) -> Duration {
   let entropy = std::time::SystemTime::now()
     .duration_since(std::time::UNIX_EPOCH)
     .map_or(0, |t| t.subsec_nanos());
@@ -161,6 +248,24 @@ mod tests {
     assert_eq!(calls.load(Ordering::SeqCst), 2);
   }
 
+  #[test]
+  fn wait_options_fit_checks_in_timeout() {
+    // 2 + 4 + 8 + 10 + 10 s fit in 35 s, so 6 checks
+    let backoff =
+      WaitOptions::new(Duration::from_secs(35), Duration::from_secs(2))
+        .with_backoff(Duration::from_secs(10))
+        .poll_backoff();
+    assert_eq!(backoff.initial_delay, Duration::from_secs(2));
+    assert_eq!(backoff.max_delay, Duration::from_secs(10));
+    assert_eq!(backoff.max_attempts, 6);
+
+    let constant =
+      WaitOptions::new(Duration::from_secs(10), Duration::from_secs(5))
+        .poll_backoff();
+    assert_eq!(constant.max_delay, Duration::from_secs(5));
+    assert_eq!(constant.max_attempts, 3);
+  }
+
   #[test]
   fn jittered_stays_within_band() {
     let d = Duration::from_secs(1);
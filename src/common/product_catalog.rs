@@ -0,0 +1,727 @@
+//! Typed view of the `cray-product-catalog` `ConfigMap`.
+//!
+//! Each `ConfigMap` entry maps a product name to a YAML document keyed
+//! by product version; every version may list the IMS `images` and
+//! `recipes` it ships and the VCS `configuration` repo holding its
+//! Ansible content. [`ProductCatalog::parse`] reads the whole `ConfigMap`
+//! once, so SAT file processing doesn't re-parse YAML per lookup.
+//!
+//! ```yaml
+//! # data["cos"]
+//! 2.3.101:
+//!   configuration:
+//!     clone_url: https://vcs.cmn.alps.cscs.ch/vcs/cray/cos-config-management.git
+//!     commit: 2a3f...
+//!     import_branch: cray/cos/2.3.101
+//!   images:
+//!     cray-shasta-compute-sles15sp3.x86_64-2.3.101:
+//!       id: 1a2b...
+//!   recipes:
+//!     cray-shasta-compute-sles15sp3.x86_64-2.3.101:
+//!       id: 3c4d...
+//! ```
+//!
+//! [`ProductVersion::select_artifact`] picks an image or recipe by an
+//! [`ArtifactFilter`] on its name — prefix, substring, architecture,
+//! regular expression, or several of them — failing if several match,
+//! or taking the one with the highest version.
+//!
+//! [`fetch`] reads and parses the `ConfigMap` through the
+//! [`crate::ShastaClient`] Kubernetes client cache and keeps the result
+//! for [`CACHE_TTL`], so callers don't have to fetch it themselves.
+
+use std::{cmp::Ordering, collections::BTreeMap, sync::LazyLock};
+
+#[cfg(feature = "k8s-console")]
+use std::{
+  collections::HashMap,
+  future::Future,
+  sync::{Arc, Mutex, PoisonError},
+  time::{Duration, Instant},
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+#[cfg(feature = "k8s-console")]
+use crate::{KubeAuth, ShastaClient, common::kubernetes};
+
+/// The whole product catalog, by product name.
+#[derive(Debug, Clone, Default)]
+pub struct ProductCatalog {
+  product_map: BTreeMap<String, Product>,
+}
+
+/// One product, by version.
+#[derive(Debug, Clone, Default)]
+pub struct Product {
+  version_map: BTreeMap<String, ProductVersion>,
+}
+
+/// What one product version ships.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ProductVersion {
+  /// VCS repo holding the product's Ansible content.
+  #[serde(default)]
+  pub configuration: Option<ProductConfiguration>,
+  #[serde(default)]
+  images: Option<BTreeMap<String, Artifact>>,
+  #[serde(default)]
+  recipes: Option<BTreeMap<String, Artifact>>,
+}
+
+/// `configuration` entry of a product version.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ProductConfiguration {
+  /// Clone URL of the product's VCS repo.
+  pub clone_url: Option<String>,
+  /// Commit the product version was imported at.
+  pub commit: Option<String>,
+  /// Branch the product version was imported into.
+  pub import_branch: Option<String>,
+  /// SSH URL of the product's VCS repo.
+  pub ssh_url: Option<String>,
+}
+
+/// IMS image or recipe shipped by a product version.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Artifact {
+  /// IMS id.
+  pub id: String,
+}
+
+/// Kind of IMS artifact listed in a product version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+  /// `images` entries.
+  Image,
+  /// `recipes` entries.
+  Recipe,
+}
+
+impl ArtifactKind {
+  /// Parse a SAT file product `type`; both the singular (`image`,
+  /// `recipe`) and the catalog's plural (`images`, `recipes`) spellings
+  /// are accepted.
+  #[must_use]
+  pub fn parse(kind: &str) -> Option<Self> {
+    match kind {
+      "image" | "images" => Some(Self::Image),
+      "recipe" | "recipes" => Some(Self::Recipe),
+      _ => None,
+    }
+  }
+}
+
+/// Rule selecting one artifact by its name in the product catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtifactFilter {
+  /// Name starts with the value.
+  Prefix(String),
+  /// Name contains the value.
+  Wildcard(String),
+  /// Name's last `.`-separated segment is the architecture
+  /// (e.g. `x86_64`), compared case-insensitively.
+  Arch(String),
+  /// Name matches the regular expression, anywhere unless anchored. An
+  /// invalid expression matches nothing, see [`Self::validate`].
+  Regex(String),
+  /// Name matches every filter, e.g. an architecture and a prefix.
+  All(Vec<ArtifactFilter>),
+}
+
+impl ArtifactFilter {
+  /// Whether `artifact_name` matches.
+  #[must_use]
+  pub fn matches(&self, artifact_name: &str) -> bool {
+    match self {
+      Self::Prefix(prefix) => artifact_name.starts_with(prefix.as_str()),
+      Self::Wildcard(wildcard) => artifact_name.contains(wildcard.as_str()),
+      Self::Arch(arch) => artifact_name
+        .rsplit('.')
+        .next()
+        .is_some_and(|suffix| suffix.eq_ignore_ascii_case(arch)),
+      Self::Regex(pattern) => {
+        Regex::new(pattern).is_ok_and(|regex| regex.is_match(artifact_name))
+      }
+      Self::All(filter_vec) => filter_vec
+        .iter()
+        .all(|filter| filter.matches(artifact_name)),
+    }
+  }
+
+  /// Check the regular expressions of the filter compile.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::CrayProductCatalog`] naming the first invalid
+  /// expression.
+  pub fn validate(&self) -> Result<(), Error> {
+    match self {
+      Self::Regex(pattern) => Regex::new(pattern).map(|_| ()).map_err(|e| {
+        Error::CrayProductCatalog(format!(
+          "Invalid product catalog filter regex '{pattern}': {e}"
+        ))
+      }),
+      Self::All(filter_vec) => {
+        filter_vec.iter().try_for_each(ArtifactFilter::validate)
+      }
+      Self::Prefix(_) | Self::Wildcard(_) | Self::Arch(_) => Ok(()),
+    }
+  }
+}
+
+/// Which artifact to pick when several match an [`ArtifactFilter`].
+#[derive(
+  Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactSelection {
+  /// Fail: the filter must match exactly one artifact.
+  #[default]
+  Only,
+  /// The artifact whose name has the highest version, comparing
+  /// numeric segments as numbers (`2.3.101` > `2.3.9`).
+  Latest,
+}
+
+impl ProductCatalog {
+  /// Parse the `data` of the `cray-product-catalog` `ConfigMap`.
+  /// Products and versions that don't parse are logged and skipped, so
+  /// one malformed entry doesn't hide the rest of the catalog.
+  #[must_use]
+  pub fn parse(configmap_data: &BTreeMap<String, String>) -> Self {
+    let product_map = configmap_data
+      .iter()
+      .filter_map(|(product_name, product_yaml)| {
+        match Product::parse(product_name, product_yaml) {
+          Ok(product) => Some((product_name.clone(), product)),
+          Err(e) => {
+            tracing::warn!(
+              "Skipping product '{product_name}' in cray product catalog: {e}"
+            );
+            None
+          }
+        }
+      })
+      .collect();
+
+    Self { product_map }
+  }
+
+  /// Product named `product_name`.
+  #[must_use]
+  pub fn product(&self, product_name: &str) -> Option<&Product> {
+    self.product_map.get(product_name)
+  }
+
+  /// Products, by name.
+  pub fn products(&self) -> impl Iterator<Item = (&str, &Product)> {
+    self
+      .product_map
+      .iter()
+      .map(|(product_name, product)| (product_name.as_str(), product))
+  }
+
+  /// Version `product_version` of product `product_name`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::CrayProductCatalog`] if the product or the
+  /// version is not in the catalog.
+  pub fn version(
+    &self,
+    product_name: &str,
+    product_version: &str,
+  ) -> Result<&ProductVersion, Error> {
+    self
+      .product(product_name)
+      .ok_or_else(|| {
+        Error::CrayProductCatalog(format!(
+          "Product {product_name} not found in cray product catalog"
+        ))
+      })?
+      .version(product_version)
+      .ok_or_else(|| {
+        Error::CrayProductCatalog(format!(
+          "Product '{product_name}' version '{product_version}' not found in cray product catalog"
+        ))
+      })
+  }
+}
+
+impl Product {
+  fn parse(product_name: &str, product_yaml: &str) -> Result<Self, Error> {
+    let value: serde_yaml::Value = serde_yaml::from_str(product_yaml)?;
+
+    let serde_yaml::Value::Mapping(mapping) = value else {
+      return Err(Error::YamlShape(
+        "product entry is not a mapping of versions".to_string(),
+      ));
+    };
+
+    let mut version_map = BTreeMap::new();
+    for (version_value, product_version_value) in mapping {
+      // Versions such as `1.5` are read back as YAML numbers
+      let version = match version_value {
+        serde_yaml::Value::String(version) => version,
+        serde_yaml::Value::Number(version) => version.to_string(),
+        other => {
+          tracing::warn!(
+            "Skipping product '{product_name}' version {other:?} in cray product catalog: not a string"
+          );
+          continue;
+        }
+      };
+
+      match serde_yaml::from_value::<ProductVersion>(product_version_value) {
+        Ok(product_version) => {
+          version_map.insert(version, product_version);
+        }
+        Err(e) => tracing::warn!(
+          "Skipping product '{product_name}' version '{version}' in cray product catalog: {e}"
+        ),
+      }
+    }
+
+    Ok(Self { version_map })
+  }
+
+  /// Version `product_version`.
+  #[must_use]
+  pub fn version(&self, product_version: &str) -> Option<&ProductVersion> {
+    self.version_map.get(product_version)
+  }
+
+  /// Versions, oldest first.
+  pub fn versions(
+    &self,
+  ) -> impl DoubleEndedIterator<Item = (&str, &ProductVersion)> {
+    let mut version_vec: Vec<_> = self
+      .version_map
+      .iter()
+      .map(|(version, product_version)| (version.as_str(), product_version))
+      .collect();
+    version_vec.sort_by(|(a, _), (b, _)| compare_versions(a, b));
+    version_vec.into_iter()
+  }
+
+  /// Highest version, comparing numeric segments as numbers
+  /// (`1.10.0` > `1.9.2`).
+  #[must_use]
+  pub fn latest_version(&self) -> Option<(&str, &ProductVersion)> {
+    self.versions().next_back()
+  }
+}
+
+impl ProductVersion {
+  /// Artifacts of `kind`, by name.
+  pub fn artifacts(
+    &self,
+    kind: ArtifactKind,
+  ) -> impl Iterator<Item = (&str, &Artifact)> {
+    let artifact_map_opt = match kind {
+      ArtifactKind::Image => self.images.as_ref(),
+      ArtifactKind::Recipe => self.recipes.as_ref(),
+    };
+
+    artifact_map_opt
+      .into_iter()
+      .flatten()
+      .map(|(artifact_name, artifact)| (artifact_name.as_str(), artifact))
+  }
+
+  /// Artifacts of `kind` matching `filter_opt`, by name, e.g. to let
+  /// the user pick one when several match.
+  pub fn matching_artifacts<'a>(
+    &'a self,
+    kind: ArtifactKind,
+    filter_opt: Option<&'a ArtifactFilter>,
+  ) -> impl Iterator<Item = (&'a str, &'a Artifact)> {
+    self.artifacts(kind).filter(move |(artifact_name, _)| {
+      filter_opt.is_none_or(|filter| filter.matches(artifact_name))
+    })
+  }
+
+  /// IMS id of the only artifact of `kind` matching `filter_opt`. With no
+  /// filter the first artifact, by name, is picked.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::CrayProductCatalog`] if no artifact matches, or
+  /// [`Error::AmbiguousProductImage`] if more than one matches the
+  /// filter. `label` names what is being looked up in the error
+  /// messages.
+  pub fn find_artifact(
+    &self,
+    kind: ArtifactKind,
+    filter_opt: Option<&ArtifactFilter>,
+    label: &str,
+  ) -> Result<&str, Error> {
+    self.select_artifact(kind, filter_opt, ArtifactSelection::Only, label)
+  }
+
+  /// IMS id of the artifact of `kind` matching `filter_opt` picked by
+  /// `selection`. With no filter and [`ArtifactSelection::Only`] the
+  /// first artifact, by name, is picked.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::CrayProductCatalog`] if the filter is invalid or
+  /// no artifact matches, or [`Error::AmbiguousProductImage`] listing
+  /// the matches if more than one matches and `selection` is
+  /// [`ArtifactSelection::Only`]. `label` names the image being looked
+  /// up in the errors.
+  pub fn select_artifact(
+    &self,
+    kind: ArtifactKind,
+    filter_opt: Option<&ArtifactFilter>,
+    selection: ArtifactSelection,
+    label: &str,
+  ) -> Result<&str, Error> {
+    filter_opt.map(ArtifactFilter::validate).transpose()?;
+
+    let matching_vec: Vec<(&str, &Artifact)> =
+      self.matching_artifacts(kind, filter_opt).collect();
+
+    let picked_opt = match selection {
+      ArtifactSelection::Only => {
+        if filter_opt.is_some() && matching_vec.len() > 1 {
+          return Err(Error::AmbiguousProductImage {
+            image_name: label.to_string(),
+            candidates: matching_vec
+              .iter()
+              .map(|(artifact_name, _)| (*artifact_name).to_string())
+              .collect(),
+          });
+        }
+        matching_vec.first()
+      }
+      ArtifactSelection::Latest => {
+        matching_vec.iter().max_by(|(a, _), (b, _)| {
+          compare_versions(artifact_version(a), artifact_version(b))
+        })
+      }
+    };
+
+    picked_opt
+      .map(|(_, artifact)| artifact.id.as_str())
+      .ok_or_else(|| {
+        Error::CrayProductCatalog(format!(
+          "Product catalog for image '{label}' not found. Exit"
+        ))
+      })
+  }
+}
+
+/// How long a catalog read by [`fetch`] is reused before the
+/// `ConfigMap` is read again.
+#[cfg(feature = "k8s-console")]
+pub const CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Parsed catalogs by Kubernetes API URL, with when they were read.
+#[cfg(feature = "k8s-console")]
+static CACHE: LazyLock<Mutex<HashMap<String, (Instant, Arc<ProductCatalog>)>>> =
+  LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Read and parse the `cray-product-catalog` `ConfigMap` of the cluster
+/// at `k8s_api_url`, reusing the catalog read less than [`CACHE_TTL`]
+/// ago if any. The Kubernetes client comes from
+/// [`ShastaClient::with_kube_client`].
+///
+/// # Errors
+///
+/// Returns an [`Error`] if the Kubernetes client can't be built or the
+/// `ConfigMap` can't be read.
+#[cfg(feature = "k8s-console")]
+pub async fn fetch(
+  shasta_client: &ShastaClient,
+  shasta_token: &str,
+  k8s_api_url: &str,
+  auth: &KubeAuth,
+  socks5_proxy: Option<&str>,
+) -> Result<Arc<ProductCatalog>, Error> {
+  cached(k8s_api_url, || {
+    shasta_client.with_kube_client(
+      shasta_token,
+      k8s_api_url,
+      auth,
+      socks5_proxy,
+      |kube_client| {
+        kubernetes::try_get_configmap(
+          kube_client,
+          kubernetes::CRAY_PRODUCT_CATALOG_CONFIGMAP,
+        )
+      },
+    )
+  })
+  .await
+}
+
+/// Drop every catalog cached by [`fetch`], so the next call reads the
+/// `ConfigMap` again.
+#[cfg(feature = "k8s-console")]
+pub fn clear_cache() {
+  CACHE.lock().unwrap_or_else(PoisonError::into_inner).clear();
+}
+
+/// Catalog cached for `k8s_api_url`, or the one parsed from the
+/// `ConfigMap` data returned by `load` if none is cached or it expired.
+#[cfg(feature = "k8s-console")]
+pub(crate) async fn cached<F, Fut>(
+  k8s_api_url: &str,
+  load: F,
+) -> Result<Arc<ProductCatalog>, Error>
+where
+  F: FnOnce() -> Fut,
+  Fut: Future<Output = Result<BTreeMap<String, String>, Error>>,
+{
+  let cached_opt = CACHE
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(k8s_api_url)
+    .filter(|(read_at, _)| read_at.elapsed() < CACHE_TTL)
+    .map(|(_, catalog)| Arc::clone(catalog));
+
+  if let Some(catalog) = cached_opt {
+    tracing::debug!("Using cached cray product catalog of '{k8s_api_url}'");
+    return Ok(catalog);
+  }
+
+  // Read without holding the lock; if two callers race, the last one
+  // wins
+  let catalog = Arc::new(ProductCatalog::parse(&load().await?));
+
+  CACHE.lock().unwrap_or_else(PoisonError::into_inner).insert(
+    k8s_api_url.to_string(),
+    (Instant::now(), Arc::clone(&catalog)),
+  );
+
+  Ok(catalog)
+}
+
+/// Version in artifact name `artifact_name`, its last run of
+/// `.`-separated numbers (`2.3.101` in `cos-2.3.101.x86_64`), or the
+/// whole name if it has none.
+fn artifact_version(artifact_name: &str) -> &str {
+  static VERSION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\d+(\.\d+)+").expect("artifact version regex is valid")
+  });
+
+  VERSION
+    .find_iter(artifact_name)
+    .last()
+    .map_or(artifact_name, |version| version.as_str())
+}
+
+/// Compare product versions segment by segment, numerically when both
+/// segments are numbers.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+  let segments = |version: &str| {
+    version
+      .split(['.', '-', '_'])
+      .map(str::to_string)
+      .collect::<Vec<_>>()
+  };
+
+  let (a_segments, b_segments) = (segments(a), segments(b));
+
+  for (a_segment, b_segment) in a_segments.iter().zip(&b_segments) {
+    let ordering = match (a_segment.parse::<u64>(), b_segment.parse::<u64>()) {
+      (Ok(a_number), Ok(b_number)) => a_number.cmp(&b_number),
+      _ => a_segment.cmp(b_segment),
+    };
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+  }
+
+  a_segments.len().cmp(&b_segments.len())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn catalog() -> ProductCatalog {
+    let configmap_data = BTreeMap::from([
+      (
+        "cos".to_string(),
+        r"
+2.3.9:
+  configuration:
+    clone_url: https://vcs/cray/cos-config-management.git
+    commit: abc
+  images:
+    cos-2.3.9.x86_64:
+      id: image-x86
+    cos-2.3.9.aarch64:
+      id: image-arm
+2.3.10:
+  recipes:
+    cos-2.3.10.x86_64:
+      id: recipe-x86
+"
+        .to_string(),
+      ),
+      ("broken".to_string(), "[not, a, mapping]".to_string()),
+    ]);
+
+    ProductCatalog::parse(&configmap_data)
+  }
+
+  #[test]
+  fn parse_skips_malformed_products() {
+    let catalog = catalog();
+
+    assert!(catalog.product("broken").is_none());
+    assert_eq!(
+      catalog
+        .version("cos", "2.3.9")
+        .unwrap()
+        .configuration
+        .as_ref()
+        .and_then(|configuration| configuration.commit.as_deref()),
+      Some("abc")
+    );
+    assert!(catalog.version("cos", "9.9.9").is_err());
+  }
+
+  #[test]
+  fn latest_version_compares_numerically() {
+    let catalog = catalog();
+
+    let (latest, _) = catalog.product("cos").unwrap().latest_version().unwrap();
+    assert_eq!(latest, "2.3.10");
+  }
+
+  #[test]
+  fn find_artifact_applies_filter() {
+    let catalog = catalog();
+    let product_version = catalog.version("cos", "2.3.9").unwrap();
+
+    assert_eq!(
+      product_version
+        .find_artifact(
+          ArtifactKind::Image,
+          Some(&ArtifactFilter::Arch("aarch64".to_string())),
+          "compute",
+        )
+        .unwrap(),
+      "image-arm"
+    );
+    // Both images match
+    assert!(
+      product_version
+        .find_artifact(
+          ArtifactKind::Image,
+          Some(&ArtifactFilter::Prefix("cos-".to_string())),
+          "compute",
+        )
+        .is_err()
+    );
+    assert!(
+      product_version
+        .find_artifact(ArtifactKind::Recipe, None, "compute")
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn select_artifact_combines_filters_and_picks_latest() {
+    let catalog = ProductCatalog::parse(&BTreeMap::from([(
+      "uss".to_string(),
+      r"
+1.1.0:
+  images:
+    secure-compute-1.1.9.aarch64:
+      id: arm-9
+    secure-compute-1.1.10.x86_64:
+      id: x86-10
+    secure-compute-1.1.9.x86_64:
+      id: x86-9
+    secure-storage-1.1.12.x86_64:
+      id: storage-12
+"
+      .to_string(),
+    )]));
+    let product_version = catalog.version("uss", "1.1.0").unwrap();
+    let select = |filter: &ArtifactFilter, selection| {
+      product_version.select_artifact(
+        ArtifactKind::Image,
+        Some(filter),
+        selection,
+        "compute",
+      )
+    };
+
+    let x86_compute = ArtifactFilter::All(vec![
+      ArtifactFilter::Arch("x86_64".to_string()),
+      ArtifactFilter::Prefix("secure-compute".to_string()),
+    ]);
+    assert_eq!(
+      product_version
+        .matching_artifacts(ArtifactKind::Image, Some(&x86_compute))
+        .map(|(artifact_name, _)| artifact_name)
+        .collect::<Vec<_>>(),
+      vec![
+        "secure-compute-1.1.10.x86_64",
+        "secure-compute-1.1.9.x86_64"
+      ]
+    );
+    let Err(Error::AmbiguousProductImage {
+      image_name,
+      candidates,
+    }) = select(&x86_compute, ArtifactSelection::Only)
+    else {
+      panic!("several artifacts match");
+    };
+    assert_eq!(image_name, "compute");
+    assert_eq!(
+      candidates,
+      vec![
+        "secure-compute-1.1.10.x86_64",
+        "secure-compute-1.1.9.x86_64"
+      ]
+    );
+    assert_eq!(
+      select(&x86_compute, ArtifactSelection::Latest).unwrap(),
+      "x86-10"
+    );
+
+    let regex = ArtifactFilter::Regex(r"^secure-compute-.*\.aarch64$".into());
+    assert_eq!(select(&regex, ArtifactSelection::Only).unwrap(), "arm-9");
+    assert!(
+      select(
+        &ArtifactFilter::Regex("(".to_string()),
+        ArtifactSelection::Only
+      )
+      .is_err()
+    );
+  }
+
+  #[cfg(feature = "k8s-console")]
+  #[tokio::test]
+  async fn cached_reads_configmap_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let k8s_api_url = format!("https://k8s-{}", uuid::Uuid::new_v4());
+    let load_count = AtomicUsize::new(0);
+    let load = || async {
+      load_count.fetch_add(1, Ordering::SeqCst);
+      Ok(BTreeMap::from([(
+        "cos".to_string(),
+        "2.3.9: {}".to_string(),
+      )]))
+    };
+
+    let first = cached(&k8s_api_url, load).await.unwrap();
+    let second = cached(&k8s_api_url, load).await.unwrap();
+
+    assert_eq!(load_count.load(Ordering::SeqCst), 1);
+    assert!(Arc::ptr_eq(&first, &second));
+    assert!(first.version("cos", "2.3.9").is_ok());
+  }
+}
@@ -0,0 +1,92 @@
+//! Protection of BOS session templates and CFS configurations against
+//! deletion.
+//!
+//! A resource is protected when its description has a line reading
+//! [`PROTECTION_MARKER`]. csm-rs manages that line: [`protect`] and
+//! [`unprotect`] add and remove it, and
+//! `ShastaClient::bos_template_v2_set_protected` and
+//! `ShastaClient::cfs_configuration_v3_set_protected` write the result
+//! back to CSM. Only CFS v3 configurations have a description, so
+//! protecting a configuration goes through the v3 API.
+//!
+//! The delete workflows ([`crate::cfs::cleanup::DeletionPlan`] and the
+//! BOS template deletion of the backend dispatcher) keep protected
+//! resources unless told to ignore protection, and so do the overwrites
+//! of `commands::i_apply_sat_file` and `commands::migrate_restore`,
+//! including the images protected BOS session templates boot.
+
+/// Description line marking a resource as protected.
+pub const PROTECTION_MARKER: &str = "csm-rs-protected";
+
+/// Whether `description` marks its resource as protected.
+#[must_use]
+pub fn is_protected(description: Option<&str>) -> bool {
+  description.is_some_and(|description| {
+    description
+      .lines()
+      .any(|line| line.trim() == PROTECTION_MARKER)
+  })
+}
+
+/// `description` with the protection marker appended on its own line,
+/// unless already there.
+#[must_use]
+pub fn protect(description: Option<&str>) -> String {
+  match description.filter(|description| !description.is_empty()) {
+    Some(description) if is_protected(Some(description)) => {
+      description.to_string()
+    }
+    Some(description) => format!("{description}\n{PROTECTION_MARKER}"),
+    None => PROTECTION_MARKER.to_string(),
+  }
+}
+
+/// `description` without the protection marker, `None` if nothing is
+/// left.
+#[must_use]
+pub fn unprotect(description: Option<&str>) -> Option<String> {
+  let description = description?
+    .lines()
+    .filter(|line| line.trim() != PROTECTION_MARKER)
+    .collect::<Vec<&str>>()
+    .join("\n");
+
+  (!description.is_empty()).then_some(description)
+}
+
+/// `description` protected or not, as `protected` says.
+#[must_use]
+pub fn set_protected(
+  description: Option<&str>,
+  protected: bool,
+) -> Option<String> {
+  if protected {
+    Some(protect(description))
+  } else {
+    unprotect(description)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn protect_and_unprotect_round_trip() {
+    assert!(!is_protected(None));
+    assert!(!is_protected(Some("production template")));
+
+    let protected = protect(Some("production template"));
+    assert_eq!(protected, "production template\ncsm-rs-protected");
+    assert!(is_protected(Some(&protected)));
+    assert_eq!(protect(Some(&protected)), protected);
+    assert_eq!(
+      unprotect(Some(&protected)).as_deref(),
+      Some("production template")
+    );
+
+    assert_eq!(protect(None), PROTECTION_MARKER);
+    assert_eq!(unprotect(Some(PROTECTION_MARKER)), None);
+    assert!(!is_protected(Some("not csm-rs-protected")));
+  }
+}
@@ -0,0 +1,224 @@
+//! Per-client request throttling.
+//!
+//! Every CSM request issued by csm-rs — through the hand-written
+//! `http_client` modules or the generated service clients — first takes
+//! a token from the bucket of its [`crate::ShastaClient`], shared with
+//! the clones of the client, so fan-out operations (CFS component
+//! batches, per-xname HSM membership lookups, ...) cannot hammer the API
+//! gateway. Requests made inside [`crate::ShastaClient::scope`] count
+//! against the scoped client. Configure it with
+//! [`crate::ClientConfig::with_rate_limit`].
+
+use std::{
+  fmt,
+  sync::{Arc, LazyLock},
+  time::Duration,
+};
+
+use tokio::{sync::Mutex, time::Instant};
+
+use crate::error::Error;
+
+/// Token-bucket limits: `requests_per_second` sustained, with up to
+/// `burst` requests let through back to back after an idle period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+  requests_per_second: u32,
+  burst: u32,
+}
+
+impl RateLimit {
+  /// Limit applied unless [`crate::ClientConfig::with_rate_limit`] says
+  /// otherwise: 100 requests per second, bursts of 10.
+  pub const DEFAULT: Self = Self {
+    requests_per_second: 100,
+    burst: 10,
+  };
+
+  /// Allow `requests_per_second` sustained, with bursts of up to `burst`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::ValidationFailed`] if either value is zero.
+  pub fn new(requests_per_second: u32, burst: u32) -> Result<Self, Error> {
+    if requests_per_second == 0 || burst == 0 {
+      return Err(Error::ValidationFailed(
+        "rate limit requests per second and burst must be greater than 0",
+      ));
+    }
+
+    Ok(Self {
+      requests_per_second,
+      burst,
+    })
+  }
+
+  /// Sustained requests per second.
+  #[must_use]
+  pub fn requests_per_second(&self) -> u32 {
+    self.requests_per_second
+  }
+
+  /// Maximum number of requests let through back to back.
+  #[must_use]
+  pub fn burst(&self) -> u32 {
+    self.burst
+  }
+}
+
+impl Default for RateLimit {
+  fn default() -> Self {
+    Self::DEFAULT
+  }
+}
+
+#[derive(Debug)]
+struct Bucket {
+  rate_limit: RateLimit,
+  tokens: f64,
+  refilled_at: Instant,
+}
+
+impl Bucket {
+  fn new(rate_limit: RateLimit) -> Self {
+    Self {
+      rate_limit,
+      tokens: f64::from(rate_limit.burst),
+      refilled_at: Instant::now(),
+    }
+  }
+
+  fn refill(&mut self, now: Instant) {
+    let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+    self.tokens = (self.tokens
+      + elapsed * f64::from(self.rate_limit.requests_per_second))
+    .min(f64::from(self.rate_limit.burst));
+    self.refilled_at = now;
+  }
+
+  /// Take a token, returning how long to wait first when the bucket is
+  /// empty. The token is accounted for immediately so concurrent
+  /// callers queue up behind each other.
+  fn take(&mut self, now: Instant) -> Duration {
+    self.refill(now);
+    self.tokens -= 1.0;
+
+    if self.tokens >= 0.0 {
+      Duration::ZERO
+    } else {
+      Duration::from_secs_f64(
+        -self.tokens / f64::from(self.rate_limit.requests_per_second),
+      )
+    }
+  }
+}
+
+/// Bucket of a client, shared with its clones; lets everything through
+/// when built without a limit.
+#[derive(Clone)]
+pub(crate) struct RateLimiter {
+  rate_limit_opt: Option<RateLimit>,
+  bucket_opt: Option<Arc<Mutex<Bucket>>>,
+}
+
+impl RateLimiter {
+  pub(crate) fn new(rate_limit_opt: Option<RateLimit>) -> Self {
+    Self {
+      rate_limit_opt,
+      bucket_opt: rate_limit_opt
+        .map(|rate_limit| Arc::new(Mutex::new(Bucket::new(rate_limit)))),
+    }
+  }
+
+  pub(crate) fn rate_limit(&self) -> Option<RateLimit> {
+    self.rate_limit_opt
+  }
+
+  /// Wait until the limit lets one more request through.
+  pub(crate) async fn acquire(&self) {
+    let Some(bucket) = &self.bucket_opt else {
+      return;
+    };
+
+    let wait = bucket.lock().await.take(Instant::now());
+    if !wait.is_zero() {
+      tokio::time::sleep(wait).await;
+    }
+  }
+}
+
+impl Default for RateLimiter {
+  fn default() -> Self {
+    Self::new(Some(RateLimit::DEFAULT))
+  }
+}
+
+impl fmt::Debug for RateLimiter {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Debug::fmt(&self.rate_limit_opt, f)
+  }
+}
+
+/// Bucket of the requests made outside any client and its scope.
+static UNSCOPED: LazyLock<RateLimiter> = LazyLock::new(RateLimiter::default);
+
+/// Wait until `rate_limiter_opt`, or without one the limiter of the
+/// client of the enclosing [`crate::ShastaClient::scope`], lets one more
+/// request through.
+pub(crate) async fn acquire(rate_limiter_opt: Option<&RateLimiter>) {
+  let rate_limiter = match rate_limiter_opt {
+    Some(rate_limiter) => rate_limiter.clone(),
+    None => {
+      crate::client::scoped_config(|config| config.rate_limiter().clone())
+        .unwrap_or_else(|| UNSCOPED.clone())
+    }
+  };
+
+  rate_limiter.acquire().await;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rate_limit_rejects_zero() {
+    assert!(RateLimit::new(0, 1).is_err());
+    assert!(RateLimit::new(1, 0).is_err());
+  }
+
+  #[test]
+  fn bucket_allows_burst_then_spaces_requests() {
+    let mut bucket = Bucket::new(RateLimit::new(10, 2).unwrap());
+    let now = Instant::now();
+
+    assert_eq!(bucket.take(now), Duration::ZERO);
+    assert_eq!(bucket.take(now), Duration::ZERO);
+    assert!((bucket.take(now).as_secs_f64() - 0.1).abs() < 1e-6);
+    assert!((bucket.take(now).as_secs_f64() - 0.2).abs() < 1e-6);
+
+    // One second later the debt is paid and the bucket is full again
+    let later = now + Duration::from_secs(1);
+    assert_eq!(bucket.take(later), Duration::ZERO);
+  }
+
+  #[test]
+  fn limiter_clones_share_the_bucket() {
+    let rate_limiter = RateLimiter::new(Some(RateLimit::new(10, 1).unwrap()));
+    let clone = rate_limiter.clone();
+    let now = Instant::now();
+
+    let take = |rate_limiter: &RateLimiter| {
+      rate_limiter
+        .bucket_opt
+        .as_ref()
+        .unwrap()
+        .try_lock()
+        .unwrap()
+        .take(now)
+    };
+    assert_eq!(take(&rate_limiter), Duration::ZERO);
+    assert!(!take(&clone).is_zero());
+    assert!(RateLimiter::new(None).bucket_opt.is_none());
+  }
+}
@@ -0,0 +1,113 @@
+//! Per-operation correlation ids.
+//!
+//! Long-running workflows ([`crate::commands`], cascade deletes, node
+//! details) run inside [`scope`], which opens a `csm_rs_operation`
+//! tracing span carrying a `request_id` field and makes the id
+//! available to every HTTP call issued from within. Requests built by
+//! csm-rs then send it as [`HEADER`], so CSM gateway and service logs
+//! can be matched with the caller's own logs.
+//!
+//! Nested scopes reuse the outermost id: an apply-SAT run that fetches
+//! node details is one operation, not two.
+
+use std::future::Future;
+
+use tracing::Instrument;
+
+/// HTTP header carrying the correlation id.
+pub(crate) const HEADER: &str = "X-Request-ID";
+
+tokio::task_local! {
+  static REQUEST_ID: String;
+}
+
+/// Correlation id of the operation running on the current task, if any.
+pub(crate) fn current() -> Option<String> {
+  REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Run `fut` as the `operation` workflow: inside a `csm_rs_operation`
+/// span and with a correlation id, generated unless an enclosing scope
+/// already set one.
+pub(crate) async fn scope<F: Future>(
+  operation: &'static str,
+  fut: F,
+) -> F::Output {
+  if let Some(request_id) = current() {
+    let span = tracing::info_span!(
+      "csm_rs_operation",
+      operation,
+      request_id = request_id.as_str()
+    );
+    return fut.instrument(span).await;
+  }
+
+  let request_id = uuid::Uuid::new_v4().to_string();
+  let span = tracing::info_span!(
+    "csm_rs_operation",
+    operation,
+    request_id = request_id.as_str()
+  );
+  REQUEST_ID.scope(request_id, fut.instrument(span)).await
+}
+
+/// Carry the current correlation id, access log (see
+/// [`super::access_log`]), client scope (see [`crate::ShastaClient::scope`])
+/// and span into `fut`, for futures handed to `tokio::spawn` (task-locals
+/// don't cross task boundaries).
+pub(crate) fn propagate<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+  let request_id_opt = current();
+  let fut = crate::client::propagate(super::access_log::scope(
+    super::access_log::current(),
+    fut.in_current_span(),
+  ));
+
+  async move {
+    match request_id_opt {
+      Some(request_id) => REQUEST_ID.scope(request_id, fut).await,
+      None => fut.await,
+    }
+  }
+}
+
+/// Add the current correlation id, if any, to `request_builder`.
+pub(crate) fn tag(
+  request_builder: reqwest::RequestBuilder,
+) -> reqwest::RequestBuilder {
+  match current() {
+    Some(request_id) => request_builder.header(HEADER, request_id),
+    None => request_builder,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn nested_scopes_share_the_outer_id() {
+    assert!(current().is_none());
+
+    let (outer, inner) = scope("outer", async {
+      let outer = current();
+      let inner = scope("inner", async { current() }).await;
+      (outer, inner)
+    })
+    .await;
+
+    assert!(outer.is_some());
+    assert_eq!(outer, inner);
+    assert!(current().is_none());
+  }
+
+  #[tokio::test]
+  async fn propagate_carries_id_into_spawned_tasks() {
+    let (outer, spawned) = scope("outer", async {
+      let spawned = tokio::spawn(propagate(async { current() })).await.unwrap();
+      (current(), spawned)
+    })
+    .await;
+
+    assert_eq!(outer, spawned);
+  }
+}
@@ -0,0 +1,144 @@
+//! Named sets of CFS session options.
+//!
+//! Sites tend to run CFS sessions with the same Ansible verbosity,
+//! passthrough flags and limits over and over. A [`SessionProfile`]
+//! bundles them under a name, e.g. in the caller's configuration file:
+//!
+//! ```yaml
+//! debug:
+//!   ansible_verbosity: 4
+//!   ansible_passthrough: "--timeout 60 -e cfs_user={user}"
+//!   debug_on_failure: true
+//! gpu:
+//!   require_dkms: true
+//! ```
+//!
+//! deserialized as [`SessionProfiles`] and handed to the SAT apply and
+//! build image commands. Options given explicitly to a command win over
+//! those of its profile.
+//!
+//! `ansible_passthrough` and `ansible_limit` are rendered with the
+//! variables of the [`crate::NamingPolicy`] templates, e.g. `{user}`,
+//! when the command starts.
+
+use std::{collections::BTreeMap, future::Future};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{common::naming::NamingContext, error::Error};
+
+tokio::task_local! {
+  static SESSION_PROFILE: SessionProfile;
+}
+
+/// CFS session options applied together, see the
+/// [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionProfile {
+  /// Ansible verbosity of the CFS sessions, 0 to 4.
+  pub ansible_verbosity: Option<u8>,
+  /// Extra `ansible-playbook` arguments of the CFS sessions.
+  pub ansible_passthrough: Option<String>,
+  /// Ansible limit of the CFS sessions.
+  pub ansible_limit: Option<String>,
+  /// Same as the `debug_on_failure` flag of the SAT apply.
+  pub debug_on_failure: bool,
+  /// Whether the IMS jobs building base images from recipes need DKMS.
+  pub require_dkms: Option<bool>,
+}
+
+impl SessionProfile {
+  /// The profile with `ansible_passthrough` and `ansible_limit`
+  /// rendered with `context`.
+  #[must_use]
+  pub fn render(&self, context: &NamingContext) -> Self {
+    Self {
+      ansible_passthrough: self
+        .ansible_passthrough
+        .as_deref()
+        .map(|passthrough| context.render(passthrough)),
+      ansible_limit: self
+        .ansible_limit
+        .as_deref()
+        .map(|limit| context.render(limit)),
+      ..self.clone()
+    }
+  }
+}
+
+/// [`SessionProfile`]s by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SessionProfiles(BTreeMap<String, SessionProfile>);
+
+impl SessionProfiles {
+  /// Add, or replace, profile `name`.
+  pub fn insert(&mut self, name: impl Into<String>, profile: SessionProfile) {
+    self.0.insert(name.into(), profile);
+  }
+
+  /// Profile `name`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Message`] if there is no profile `name`.
+  pub fn get(&self, name: &str) -> Result<&SessionProfile, Error> {
+    self.0.get(name).ok_or_else(|| {
+      Error::Message(format!(
+        "CFS session profile '{name}' not found, available: {}",
+        self.0.keys().cloned().collect::<Vec<_>>().join(", ")
+      ))
+    })
+  }
+}
+
+/// Run `fut` with `profile` as the [`current`] one.
+pub(crate) async fn scope<F: Future>(
+  profile: SessionProfile,
+  fut: F,
+) -> F::Output {
+  SESSION_PROFILE.scope(profile, fut).await
+}
+
+/// Profile of the command running on the current task, if any.
+pub(crate) fn current() -> Option<SessionProfile> {
+  SESSION_PROFILE.try_with(Clone::clone).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn profiles_load_by_name_and_render() {
+    let profiles: SessionProfiles = serde_yaml::from_str(
+      "
+debug:
+  ansible_verbosity: 4
+  ansible_passthrough: -e cfs_user={user}
+  debug_on_failure: true
+gpu:
+  require_dkms: true
+",
+    )
+    .unwrap();
+
+    let profile = profiles
+      .get("debug")
+      .unwrap()
+      .render(&NamingContext::default().with("user", "alice"));
+    assert_eq!(
+      profile,
+      SessionProfile {
+        ansible_verbosity: Some(4),
+        ansible_passthrough: Some("-e cfs_user=alice".to_string()),
+        ansible_limit: None,
+        debug_on_failure: true,
+        require_dkms: None,
+      }
+    );
+    assert_eq!(profiles.get("gpu").unwrap().require_dkms, Some(true));
+    assert!(matches!(profiles.get("other"), Err(Error::Message(_))));
+  }
+}
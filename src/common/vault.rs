@@ -4,6 +4,7 @@
 /// credentials stored under `secret/manta/data/<site>`.
 pub mod http_client {
 
+  use crate::common::metrics::MeteredSend;
   use crate::error::Error;
   use serde_json::{Value, json};
 
@@ -18,16 +19,7 @@ pub mod http_client {
   ) -> Result<String, Error> {
     let role = "manta";
 
-    let client_builder = reqwest::Client::builder()
-      .connect_timeout(crate::common::http::HTTP_CONNECT_TIMEOUT);
-
-    // Build client
-    let client = match socks5_proxy {
-      Some(proxy) => {
-        client_builder.proxy(reqwest::Proxy::all(proxy)?).build()?
-      }
-      None => client_builder.build()?,
-    };
+    let client = crate::common::http::build_client(&[], socks5_proxy)?;
 
     let api_url =
       format!("{vault_base_url}/v1/auth/jwt-manta-{site_name}/login");
@@ -42,7 +34,7 @@ pub mod http_client {
       .post(api_url)
       .header("X-Vault-Request", "true")
       .json(&request_payload)
-      .send()
+      .send_metered()
       .await?;
 
     match resp.error_for_status() {
@@ -69,16 +61,7 @@ pub mod http_client {
     secret_path: &str,
     socks5_proxy: Option<&str>,
   ) -> Result<Value, Error> {
-    let client_builder = reqwest::Client::builder()
-      .connect_timeout(crate::common::http::HTTP_CONNECT_TIMEOUT);
-
-    // Build client
-    let client = match socks5_proxy {
-      Some(proxy) => {
-        client_builder.proxy(reqwest::Proxy::all(proxy)?).build()?
-      }
-      None => client_builder.build()?,
-    };
+    let client = crate::common::http::build_client(&[], socks5_proxy)?;
 
     let api_url = vault_base_url.to_owned() + secret_path;
 
@@ -87,7 +70,7 @@ pub mod http_client {
     let resp = client
       .get(api_url)
       .header("X-Vault-Token", vault_auth_token)
-      .send()
+      .send_metered()
       .await?;
 
     match resp.error_for_status() {
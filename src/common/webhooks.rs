@@ -0,0 +1,334 @@
+//! Webhooks notified after operations that change the system.
+//!
+//! Once configured with [`crate::ClientConfig::with_webhooks`], csm-rs
+//! POSTs an [`OperationEvent`] as JSON to every [`Webhook`] of the
+//! client making the change after it applies a SAT file, deletes
+//! CFS/BOS/IMS resources, starts a power transition, or creates,
+//! deletes or changes the members of an HSM group. External
+//! change-management systems (CMDB, ticketing, audit) can follow
+//! changes made through csm-rs this way. Events of changes
+//! made by a command carry its [`crate::ExecutionContext`]: who made
+//! the change, on behalf of which tenant and why.
+//!
+//! When a webhook has a secret, the request carries an
+//! `X-Csm-Rs-Signature: sha256=<hex>` header: the HMAC-SHA256 of the
+//! body keyed with the secret, for the receiver to check the event came
+//! from csm-rs.
+//!
+//! Delivery is best effort. A webhook that can't be reached or answers
+//! with an error is reported as an [`crate::Event::Warning`] and never
+//! fails the operation that triggered it.
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::{
+  ShastaClient,
+  common::{
+    events,
+    execution_context::{self, ExecutionContext},
+    request_id,
+  },
+};
+
+/// Time a webhook has to answer before delivery is given up.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Header holding the HMAC-SHA256 signature of the body.
+pub const SIGNATURE_HEADER: &str = "X-Csm-Rs-Signature";
+
+/// Header holding the name of the operation, e.g. `group_deleted`.
+pub const OPERATION_HEADER: &str = "X-Csm-Rs-Operation";
+
+/// An endpoint [`OperationEvent`]s are POSTed to.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+  url: String,
+  secret_opt: Option<Arc<SecretString>>,
+}
+
+impl Webhook {
+  /// Webhook POSTing unsigned events to `url`.
+  #[must_use]
+  pub fn new(url: impl Into<String>) -> Self {
+    Self {
+      url: url.into(),
+      secret_opt: None,
+    }
+  }
+
+  /// Sign the events sent to this webhook with `secret`.
+  #[must_use]
+  pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+    self.secret_opt = Some(Arc::new(SecretString::from(secret.into())));
+    self
+  }
+
+  /// URL the events are POSTed to.
+  #[must_use]
+  pub fn url(&self) -> &str {
+    &self.url
+  }
+}
+
+/// A resource removed by a delete operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeletedResource {
+  /// Resource kind, e.g. `IMS image`.
+  pub kind: &'static str,
+  /// Resource name or id.
+  pub name: String,
+}
+
+/// What changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Operation {
+  /// A SAT file was applied.
+  SatFileApplied {
+    /// CFS configurations created.
+    configurations: Vec<String>,
+    /// IMS image ids created.
+    images: Vec<String>,
+    /// BOS session templates created.
+    session_templates: Vec<String>,
+    /// BOS sessions created to reboot nodes.
+    sessions: Vec<String>,
+  },
+  /// CFS, BOS or IMS resources were deleted.
+  ResourcesDeleted {
+    /// Resources deleted.
+    resources: Vec<DeletedResource>,
+  },
+  /// A PCS power transition was started.
+  PowerTransition {
+    /// PCS operation, e.g. `soft-restart`.
+    operation: String,
+    /// PCS transition id.
+    transition_id: String,
+    /// Components the transition targets.
+    xnames: Vec<String>,
+  },
+  /// An HSM group was created.
+  GroupCreated {
+    /// Group label.
+    group: String,
+    /// Initial members.
+    members: Vec<String>,
+  },
+  /// An HSM group was deleted.
+  GroupDeleted {
+    /// Group label.
+    group: String,
+  },
+  /// Members were added to an HSM group.
+  GroupMembersAdded {
+    /// Group label.
+    group: String,
+    /// Xnames added.
+    xnames: Vec<String>,
+  },
+  /// Members were removed from an HSM group.
+  GroupMembersRemoved {
+    /// Group label.
+    group: String,
+    /// Xnames removed.
+    xnames: Vec<String>,
+  },
+}
+
+impl Operation {
+  /// Name of the operation, as in the `operation` field of the JSON.
+  #[must_use]
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::SatFileApplied { .. } => "sat_file_applied",
+      Self::ResourcesDeleted { .. } => "resources_deleted",
+      Self::PowerTransition { .. } => "power_transition",
+      Self::GroupCreated { .. } => "group_created",
+      Self::GroupDeleted { .. } => "group_deleted",
+      Self::GroupMembersAdded { .. } => "group_members_added",
+      Self::GroupMembersRemoved { .. } => "group_members_removed",
+    }
+  }
+}
+
+/// Body POSTed to the webhooks.
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationEvent {
+  /// Unique id of the event, for receivers to drop duplicates.
+  pub id: uuid::Uuid,
+  /// When the operation finished.
+  pub timestamp: DateTime<Utc>,
+  /// Correlation id of the command that made the change, if any.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub request_id: Option<String>,
+  /// Who made the change, if it was made by a command.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub context: Option<ExecutionContext>,
+  /// What changed.
+  #[serde(flatten)]
+  pub operation: Operation,
+}
+
+/// POST `operation` to every webhook of `client`, if any.
+pub(crate) async fn notify(client: &ShastaClient, operation: Operation) {
+  let webhook_vec = client.config().webhooks();
+  if webhook_vec.is_empty() {
+    return;
+  }
+
+  let event = OperationEvent {
+    id: uuid::Uuid::new_v4(),
+    timestamp: Utc::now(),
+    request_id: request_id::current(),
+    context: execution_context::current(),
+    operation,
+  };
+
+  let body = match serde_json::to_vec(&event) {
+    Ok(body) => body,
+    Err(e) => {
+      tracing::error!("Could not serialize webhook event: {e}");
+      return;
+    }
+  };
+
+  let delivery_vec = webhook_vec.iter().map(|webhook| {
+    deliver(&client.http, webhook, event.operation.name(), &body)
+  });
+
+  for (webhook, result) in webhook_vec
+    .iter()
+    .zip(futures::future::join_all(delivery_vec).await)
+  {
+    if let Err(e) = result {
+      events::warning(format!(
+        "Could not notify webhook '{}' of {}: {e}",
+        webhook.url,
+        event.operation.name()
+      ));
+    }
+  }
+}
+
+/// POST `body` to `webhook` with `client`, signed if it has a secret.
+/// The client is the one of the [`ShastaClient`] making the change, so
+/// the request gets the CA certificates, identity and proxy of its
+/// [`crate::ClientConfig`].
+async fn deliver(
+  client: &reqwest::Client,
+  webhook: &Webhook,
+  operation_name: &str,
+  body: &[u8],
+) -> Result<(), reqwest::Error> {
+  let mut request = client
+    .post(&webhook.url)
+    .timeout(TIMEOUT)
+    .header(reqwest::header::CONTENT_TYPE, "application/json")
+    .header(OPERATION_HEADER, operation_name)
+    .body(body.to_vec());
+
+  if let Some(secret) = &webhook.secret_opt {
+    request = request.header(
+      SIGNATURE_HEADER,
+      format!("sha256={}", sign(secret.expose_secret().as_bytes(), body)),
+    );
+  }
+
+  request.send().await?.error_for_status().map(|_| ())
+}
+
+/// Lowercase hex HMAC-SHA256 of `body` keyed with `secret`.
+fn sign(secret: &[u8], body: &[u8]) -> String {
+  let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+    .expect("HMAC accepts keys of any length");
+  mac.update(body);
+
+  mac
+    .finalize()
+    .into_bytes()
+    .iter()
+    .map(|byte| format!("{byte:02x}"))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+  use wiremock::matchers::{body_json, header, method, path};
+  use wiremock::{Mock, MockServer, ResponseTemplate};
+
+  use super::*;
+
+  #[test]
+  fn sign_is_hmac_sha256_hex() {
+    assert_eq!(
+      sign(b"key", b"The quick brown fox jumps over the lazy dog"),
+      "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    );
+  }
+
+  #[test]
+  fn operation_event_flattens_operation() {
+    let event = OperationEvent {
+      id: uuid::Uuid::nil(),
+      timestamp: DateTime::UNIX_EPOCH,
+      request_id: None,
+      context: None,
+      operation: Operation::GroupMembersAdded {
+        group: "zinal".to_string(),
+        xnames: vec!["x1000c0s0b0n0".to_string()],
+      },
+    };
+
+    assert_eq!(
+      serde_json::to_value(&event).unwrap(),
+      json!({
+        "id": "00000000-0000-0000-0000-000000000000",
+        "timestamp": "1970-01-01T00:00:00Z",
+        "operation": "group_members_added",
+        "group": "zinal",
+        "xnames": ["x1000c0s0b0n0"],
+      })
+    );
+    assert_eq!(event.operation.name(), "group_members_added");
+  }
+
+  #[tokio::test]
+  async fn deliver_posts_signed_body() {
+    let body = json!({ "operation": "group_deleted", "group": "zinal" });
+    let body_bytes = serde_json::to_vec(&body).unwrap();
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+      .and(path("/hook"))
+      .and(header(OPERATION_HEADER, "group_deleted"))
+      .and(header(
+        SIGNATURE_HEADER,
+        format!("sha256={}", sign(b"s3cr3t", &body_bytes)).as_str(),
+      ))
+      .and(body_json(&body))
+      .respond_with(ResponseTemplate::new(204))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let webhook =
+      Webhook::new(format!("{}/hook", server.uri())).with_secret("s3cr3t");
+    deliver(
+      &reqwest::Client::new(),
+      &webhook,
+      "group_deleted",
+      &body_bytes,
+    )
+    .await
+    .unwrap();
+  }
+}
@@ -0,0 +1,595 @@
+//! Typed CSM component names (xnames).
+//!
+//! CSM names hardware by its location, one `<letter><number>` pair per
+//! level of the hierarchy:
+//!
+//! | Kind                            | Example           |
+//! |---------------------------------|-------------------|
+//! | [`XNameKind::Cabinet`]          | `x1000`           |
+//! | [`XNameKind::Chassis`]          | `x1000c0`         |
+//! | [`XNameKind::ChassisBmc`]       | `x1000c0b0`       |
+//! | [`XNameKind::ComputeModule`]    | `x1000c0s7`       |
+//! | [`XNameKind::NodeBmc`]          | `x1000c0s7b1`     |
+//! | [`XNameKind::Node`]             | `x1000c0s7b1n0`   |
+//! | [`XNameKind::RouterModule`]     | `x1000c0r3`       |
+//! | [`XNameKind::RouterBmc`]        | `x1000c0r3b0`     |
+//!
+//! Cabinets have 4 digits, chassis are `c0`-`c7`, compute and router
+//! slots `s0`-`s64` / `r0`-`r64`, node BMCs `b0`-`b1` (chassis and
+//! router BMCs are always `b0`) and nodes `n0`-`n7`; other numbers have
+//! no leading zeros.
+//!
+//! [`XName`] holds any of these, [`NodeXName`] and [`BmcXName`] only
+//! nodes and BMCs. All three parse with [`str::parse`], serialize as
+//! the plain string and order by location, so `x1000c0s2` sorts before
+//! `x1000c0s10`.
+
+use std::{fmt, ops::Deref, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Level of the hardware hierarchy an [`XName`] names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum XNameKind {
+  /// `x<cabinet>`
+  Cabinet,
+  /// `x<cabinet>c<chassis>`
+  Chassis,
+  /// `x<cabinet>c<chassis>b0`
+  ChassisBmc,
+  /// `x<cabinet>c<chassis>s<slot>`
+  ComputeModule,
+  /// `x<cabinet>c<chassis>s<slot>b<bmc>`
+  NodeBmc,
+  /// `x<cabinet>c<chassis>s<slot>b<bmc>n<node>`
+  Node,
+  /// `x<cabinet>c<chassis>r<slot>`
+  RouterModule,
+  /// `x<cabinet>c<chassis>r<slot>b0`
+  RouterBmc,
+}
+
+/// Blade in a chassis slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Module {
+  Compute(u8),
+  Router(u8),
+}
+
+/// A valid CSM xname.
+///
+/// Fields are in hierarchy order so the derived ordering sorts by
+/// location, parents before their children.
+#[derive(
+  Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct XName {
+  cabinet: u16,
+  chassis: Option<u8>,
+  module: Option<Module>,
+  bmc: Option<u8>,
+  node: Option<u8>,
+  name: String,
+}
+
+impl XName {
+  /// Parse `xname`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::InvalidXName`] if `xname` is not one of the
+  /// [`XNameKind`]s, or a number is out of range.
+  pub fn parse(xname: &str) -> Result<Self, Error> {
+    let invalid = |reason: &str| Error::InvalidXName {
+      xname: xname.to_string(),
+      reason: reason.to_string(),
+    };
+
+    let mut part_vec: Vec<(char, &str)> = Vec::new();
+    let mut rest = xname;
+    while let Some(letter) = rest.chars().next() {
+      if !letter.is_ascii_lowercase() {
+        return Err(invalid("expected a lowercase letter"));
+      }
+      let digits = &rest[1..];
+      let len = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+      if len == 0 {
+        return Err(invalid(&format!("'{letter}' needs a number")));
+      }
+      part_vec.push((letter, &digits[..len]));
+      rest = &digits[len..];
+    }
+
+    let letters: String = part_vec.iter().map(|(letter, _)| letter).collect();
+    if !matches!(
+      letters.as_str(),
+      "x" | "xc" | "xcb" | "xcs" | "xcsb" | "xcsbn" | "xcr" | "xcrb"
+    ) {
+      return Err(invalid("not a cabinet, chassis, module, BMC or node"));
+    }
+
+    let (_, cabinet) = part_vec[0];
+    if cabinet.len() != 4 {
+      return Err(invalid("cabinet must have 4 digits"));
+    }
+
+    let number = |index: usize, max: u8| -> Result<Option<u8>, Error> {
+      let Some(&(letter, digits)) = part_vec.get(index) else {
+        return Ok(None);
+      };
+      match digits.parse::<u8>() {
+        Ok(n)
+          if n <= max && (digits.len() == 1 || !digits.starts_with('0')) =>
+        {
+          Ok(Some(n))
+        }
+        _ => Err(invalid(&format!("'{letter}' must be 0-{max}"))),
+      }
+    };
+
+    let (module, bmc, node) = if letters == "xcb" {
+      (None, number(2, 0)?, None)
+    } else if letters.starts_with("xcr") {
+      (number(2, 64)?.map(Module::Router), number(3, 0)?, None)
+    } else {
+      (
+        number(2, 64)?.map(Module::Compute),
+        number(3, 1)?,
+        number(4, 7)?,
+      )
+    };
+
+    Ok(Self {
+      cabinet: cabinet
+        .parse()
+        .map_err(|_| invalid("cabinet must have 4 digits"))?,
+      chassis: number(1, 7)?,
+      module,
+      bmc,
+      node,
+      name: xname.to_string(),
+    })
+  }
+
+  /// Level of the hierarchy this xname names.
+  #[must_use]
+  pub fn kind(&self) -> XNameKind {
+    match (self.chassis, self.module, self.bmc, self.node) {
+      (None, ..) => XNameKind::Cabinet,
+      (Some(_), None, None, _) => XNameKind::Chassis,
+      (Some(_), None, Some(_), _) => XNameKind::ChassisBmc,
+      (Some(_), Some(Module::Compute(_)), None, _) => XNameKind::ComputeModule,
+      (Some(_), Some(Module::Compute(_)), Some(_), None) => XNameKind::NodeBmc,
+      (Some(_), Some(Module::Compute(_)), Some(_), Some(_)) => XNameKind::Node,
+      (Some(_), Some(Module::Router(_)), None, _) => XNameKind::RouterModule,
+      (Some(_), Some(Module::Router(_)), Some(_), _) => XNameKind::RouterBmc,
+    }
+  }
+
+  /// The xname as a string.
+  #[must_use]
+  pub fn as_str(&self) -> &str {
+    &self.name
+  }
+
+  /// Cabinet number.
+  #[must_use]
+  pub fn cabinet(&self) -> u16 {
+    self.cabinet
+  }
+
+  /// Chassis number, `None` for a cabinet.
+  #[must_use]
+  pub fn chassis(&self) -> Option<u8> {
+    self.chassis
+  }
+
+  /// Compute (`s`) or router (`r`) slot number, `None` above module
+  /// level and for chassis BMCs.
+  #[must_use]
+  pub fn slot(&self) -> Option<u8> {
+    self
+      .module
+      .map(|(Module::Compute(slot) | Module::Router(slot))| slot)
+  }
+
+  /// BMC number, `None` above BMC level.
+  #[must_use]
+  pub fn bmc(&self) -> Option<u8> {
+    self.bmc
+  }
+
+  /// Node number, `None` unless this is a node.
+  #[must_use]
+  pub fn node(&self) -> Option<u8> {
+    self.node
+  }
+
+  /// Xname one level up the hierarchy, `None` for a cabinet.
+  #[must_use]
+  pub fn parent(&self) -> Option<Self> {
+    let mut parent = self.clone();
+    if parent.node.take().is_none()
+      && parent.bmc.take().is_none()
+      && parent.module.take().is_none()
+      && parent.chassis.take().is_none()
+    {
+      return None;
+    }
+    parent.name = parent.render();
+    Some(parent)
+  }
+
+  /// `true` if `other` is this xname or below it, e.g. a node of this
+  /// chassis.
+  #[must_use]
+  pub fn contains(&self, other: &Self) -> bool {
+    std::iter::successors(Some(other.clone()), Self::parent)
+      .any(|ancestor| ancestor == *self)
+  }
+
+  /// Xname string of the parsed parts.
+  fn render(&self) -> String {
+    let mut name = format!("x{:04}", self.cabinet);
+    if let Some(chassis) = self.chassis {
+      name.push_str(&format!("c{chassis}"));
+    }
+    match self.module {
+      Some(Module::Compute(slot)) => name.push_str(&format!("s{slot}")),
+      Some(Module::Router(slot)) => name.push_str(&format!("r{slot}")),
+      None => {}
+    }
+    if let Some(bmc) = self.bmc {
+      name.push_str(&format!("b{bmc}"));
+    }
+    if let Some(node) = self.node {
+      name.push_str(&format!("n{node}"));
+    }
+    name
+  }
+}
+
+impl FromStr for XName {
+  type Err = Error;
+
+  fn from_str(xname: &str) -> Result<Self, Error> {
+    Self::parse(xname)
+  }
+}
+
+impl TryFrom<String> for XName {
+  type Error = Error;
+
+  fn try_from(xname: String) -> Result<Self, Error> {
+    Self::parse(&xname)
+  }
+}
+
+impl TryFrom<&str> for XName {
+  type Error = Error;
+
+  fn try_from(xname: &str) -> Result<Self, Error> {
+    Self::parse(xname)
+  }
+}
+
+impl From<XName> for String {
+  fn from(xname: XName) -> Self {
+    xname.name
+  }
+}
+
+impl fmt::Display for XName {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.name)
+  }
+}
+
+impl AsRef<str> for XName {
+  fn as_ref(&self) -> &str {
+    &self.name
+  }
+}
+
+impl PartialEq<str> for XName {
+  fn eq(&self, other: &str) -> bool {
+    self.name == other
+  }
+}
+
+impl PartialEq<&str> for XName {
+  fn eq(&self, other: &&str) -> bool {
+    self.name == *other
+  }
+}
+
+impl PartialEq<String> for XName {
+  fn eq(&self, other: &String) -> bool {
+    self.name == *other
+  }
+}
+
+/// [`XName`] restricted to some [`XNameKind`]s: parsing, conversions
+/// and comparisons, plus `Deref` to the inner [`XName`] for its
+/// accessors.
+macro_rules! xname_newtype {
+  ($name:ident, $what:literal, $($kind:ident)|+) => {
+    impl $name {
+      /// Parse `xname`.
+      ///
+      /// # Errors
+      ///
+      #[doc = concat!(
+        "Returns [`Error::InvalidXName`] if `xname` is not a valid ",
+        $what,
+        " xname."
+      )]
+      pub fn parse(xname: &str) -> Result<Self, Error> {
+        XName::parse(xname)?.try_into()
+      }
+    }
+
+    impl TryFrom<XName> for $name {
+      type Error = Error;
+
+      fn try_from(xname: XName) -> Result<Self, Error> {
+        if matches!(xname.kind(), $(XNameKind::$kind)|+) {
+          Ok(Self(xname))
+        } else {
+          Err(Error::InvalidXName {
+            reason: format!("not a {}, a {:?}", $what, xname.kind()),
+            xname: xname.name,
+          })
+        }
+      }
+    }
+
+    impl FromStr for $name {
+      type Err = Error;
+
+      fn from_str(xname: &str) -> Result<Self, Error> {
+        Self::parse(xname)
+      }
+    }
+
+    impl TryFrom<String> for $name {
+      type Error = Error;
+
+      fn try_from(xname: String) -> Result<Self, Error> {
+        Self::parse(&xname)
+      }
+    }
+
+    impl TryFrom<&str> for $name {
+      type Error = Error;
+
+      fn try_from(xname: &str) -> Result<Self, Error> {
+        Self::parse(xname)
+      }
+    }
+
+    impl From<$name> for XName {
+      fn from(xname: $name) -> Self {
+        xname.0
+      }
+    }
+
+    impl From<$name> for String {
+      fn from(xname: $name) -> Self {
+        xname.0.name
+      }
+    }
+
+    impl Deref for $name {
+      type Target = XName;
+
+      fn deref(&self) -> &XName {
+        &self.0
+      }
+    }
+
+    impl fmt::Display for $name {
+      fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+      }
+    }
+
+    impl AsRef<str> for $name {
+      fn as_ref(&self) -> &str {
+        self.0.as_str()
+      }
+    }
+
+    impl PartialEq<str> for $name {
+      fn eq(&self, other: &str) -> bool {
+        self.0 == *other
+      }
+    }
+
+    impl PartialEq<&str> for $name {
+      fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+      }
+    }
+
+    impl PartialEq<String> for $name {
+      fn eq(&self, other: &String) -> bool {
+        self.0 == *other
+      }
+    }
+  };
+}
+
+/// Xname of a node, `x<cabinet>c<chassis>s<slot>b<bmc>n<node>`.
+#[derive(
+  Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct NodeXName(XName);
+
+xname_newtype!(NodeXName, "node", Node);
+
+impl NodeXName {
+  /// Chassis number.
+  #[must_use]
+  pub fn chassis(&self) -> u8 {
+    self.0.chassis.unwrap_or_default()
+  }
+
+  /// Compute slot number.
+  #[must_use]
+  pub fn slot(&self) -> u8 {
+    self.0.slot().unwrap_or_default()
+  }
+
+  /// Number of the node's BMC.
+  #[must_use]
+  pub fn bmc(&self) -> u8 {
+    self.0.bmc.unwrap_or_default()
+  }
+
+  /// Node number.
+  #[must_use]
+  pub fn node(&self) -> u8 {
+    self.0.node.unwrap_or_default()
+  }
+
+  /// Xname of the node's BMC.
+  #[must_use]
+  pub fn bmc_xname(&self) -> BmcXName {
+    let mut bmc = self.0.clone();
+    bmc.node = None;
+    bmc.name = bmc.render();
+    BmcXName(bmc)
+  }
+}
+
+/// Xname of a node, chassis or router BMC.
+#[derive(
+  Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct BmcXName(XName);
+
+xname_newtype!(BmcXName, "BMC", NodeBmc | ChassisBmc | RouterBmc);
+
+impl BmcXName {
+  /// Chassis number.
+  #[must_use]
+  pub fn chassis(&self) -> u8 {
+    self.0.chassis.unwrap_or_default()
+  }
+
+  /// BMC number.
+  #[must_use]
+  pub fn bmc(&self) -> u8 {
+    self.0.bmc.unwrap_or_default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn xname_parses_every_kind() {
+    for (xname, kind) in [
+      ("x1000", XNameKind::Cabinet),
+      ("x1000c7", XNameKind::Chassis),
+      ("x1000c7b0", XNameKind::ChassisBmc),
+      ("x1000c7s64", XNameKind::ComputeModule),
+      ("x1000c7s64b1", XNameKind::NodeBmc),
+      ("x1000c7s64b1n7", XNameKind::Node),
+      ("x3000c0r15", XNameKind::RouterModule),
+      ("x3000c0r15b0", XNameKind::RouterBmc),
+    ] {
+      let parsed: XName = xname.parse().unwrap();
+      assert_eq!(parsed.kind(), kind, "{xname}");
+      assert_eq!(parsed, xname);
+    }
+  }
+
+  #[test]
+  fn xname_rejects_malformed_names() {
+    for xname in [
+      "",
+      "x100",
+      "x10000",
+      "X1000c0",
+      "x1000c8",
+      "x1000c0s65",
+      "x1000c0s01",
+      "x1000c0b1",
+      "x1000c0r1b1",
+      "x1000c0s0b2",
+      "x1000c0s0b0n8",
+      "x1000c0s0n0",
+      "x1000c0s0b0n0extra",
+      "x1000c0s0b0n0p0",
+      "x1000c",
+      "not-an-xname",
+    ] {
+      assert!(
+        matches!(XName::parse(xname), Err(Error::InvalidXName { .. })),
+        "{xname}"
+      );
+    }
+  }
+
+  #[test]
+  fn xname_accessors_and_hierarchy() {
+    let node = NodeXName::parse("x1000c3s12b1n5").unwrap();
+    assert_eq!(
+      (
+        node.cabinet(),
+        node.chassis(),
+        node.slot(),
+        node.bmc(),
+        node.node()
+      ),
+      (1000, 3, 12, 1, 5)
+    );
+    assert_eq!(node.bmc_xname(), "x1000c3s12b1");
+
+    let parents: Vec<String> =
+      std::iter::successors(node.parent(), XName::parent)
+        .map(String::from)
+        .collect();
+    assert_eq!(parents, ["x1000c3s12b1", "x1000c3s12", "x1000c3", "x1000"]);
+
+    let chassis = XName::parse("x1000c3").unwrap();
+    assert!(chassis.contains(&node));
+    assert!(!XName::parse("x1000c2").unwrap().contains(&node));
+
+    let chassis_bmc = BmcXName::parse("x1000c3b0").unwrap();
+    assert_eq!(chassis_bmc.slot(), None);
+    assert_eq!(chassis_bmc.parent(), Some(chassis));
+  }
+
+  #[test]
+  fn xname_newtypes_check_the_kind() {
+    assert!(NodeXName::parse("x1000c0s0b0").is_err());
+    assert!(BmcXName::parse("x1000c0s0b0n0").is_err());
+    assert!(BmcXName::parse("x3000c0r1b0").is_ok());
+  }
+
+  #[test]
+  fn xname_orders_by_location_and_serializes_as_string() {
+    let mut xname_vec: Vec<NodeXName> = serde_json::from_str(
+      r#"["x1000c0s10b0n0", "x1000c0s2b0n1", "x1000c0s2b0n0"]"#,
+    )
+    .unwrap();
+    xname_vec.sort();
+    assert_eq!(
+      serde_json::to_string(&xname_vec).unwrap(),
+      r#"["x1000c0s2b0n0","x1000c0s2b0n1","x1000c0s10b0n0"]"#
+    );
+
+    assert!(serde_json::from_str::<NodeXName>(r#""x1000c0s2""#).is_err());
+  }
+}
@@ -99,8 +99,21 @@ pub enum Error {
   #[cfg(feature = "k8s-console")]
   #[error("CSM-RS > K8s: {0}")]
   K8sExecError(#[from] kube::Error),
+  #[cfg(feature = "telemetry")]
+  #[error("CSM-RS > Kafka: {0}")]
+  KafkaError(#[from] rskafka::client::error::Error),
   #[error("CSM-RS > CFS Session")]
   ImageNotFound(String),
+  /// Several IMS images match a name equally well; `candidates` holds
+  /// their ids.
+  #[error(
+    "CSM-RS > Image name '{name}' is ambiguous, it matches images {}",
+    candidates.join(", ")
+  )]
+  AmbiguousImageName {
+    name: String,
+    candidates: Vec<String>,
+  },
   #[error("CSM-RS > Group '{0}' not found")]
   GroupNotFound(String),
   #[error("CSM-RS > No derivatives found for CFS Configuration: {0}")]
@@ -121,8 +134,12 @@ pub enum Error {
   SessionConfigurationNotDefined(String),
   #[error("CSM-RS > IMS key '{0}' not found")]
   ImsKeyNotFound(String),
+  #[error("CSM-RS > BSS boot parameters for '{0}' not found")]
+  BootParametersNotFound(String),
   #[error("CSM-RS > HSM component '{0}' not found")]
   HsmComponentNotFound(String),
+  #[error("CSM-RS > CFS source '{0}' not found")]
+  CfsSourceNotFound(String),
   #[error("CSM-RS > HSM component '{0}' does not have a ID defined")]
   HsmComponentIdNotDefined(String),
   #[error("CSM-RS > HSM component '{0}' does not have a NID defined")]
@@ -167,18 +184,106 @@ pub enum Error {
   /// user to fix their SAT file".
   #[error("CSM-RS > SAT file: {0}")]
   SatFile(String),
+  /// SAT-file validation found problems. The string lists every
+  /// error and warning found, one per line.
+  #[error("CSM-RS > SAT file validation failed:\n{0}")]
+  SatFileValidation(String),
+  /// A SAT-file field is missing or has the wrong shape. `path` is the
+  /// YAML path of the field, e.g.
+  /// `session_templates[compute].bos_parameters.boot_sets.compute.kernel_parameters`,
+  /// and `detail` says what's wrong with it.
+  #[error("CSM-RS > SAT file: '{path}' {detail}")]
+  SatFileField { path: String, detail: String },
+  /// A BOS boot set declares an `arch` that does not match the
+  /// architecture of the IMS image it boots. Caught before the
+  /// session template is created instead of when the nodes fail to
+  /// boot.
+  #[error(
+    "CSM-RS > BOS boot set '{boot_set}' arch '{boot_set_arch}' does not match IMS image '{image}' arch '{image_arch}'"
+  )]
+  BootSetArchMismatch {
+    boot_set: String,
+    boot_set_arch: String,
+    image: String,
+    image_arch: String,
+  },
+  /// A BOS boot set's `path`, `etag` or `rootfs_provider_passthrough`
+  /// points at S3 artifacts other than the IMS image's manifest. The
+  /// `detail` string names the offending field and both values.
+  #[error(
+    "CSM-RS > BOS boot set '{boot_set}' does not reference IMS image '{image}': {detail}"
+  )]
+  BootSetImageMismatch {
+    boot_set: String,
+    image: String,
+    detail: String,
+  },
+  /// A BOS session template doesn't match the BOS v2 session template
+  /// schema. The `detail` string lists every violation, one per line.
+  #[error(
+    "CSM-RS > BOS sessiontemplate '{template}' does not match the BOS v2 schema:\n{detail}"
+  )]
+  BosTemplateSchema { template: String, detail: String },
   /// Error encountered by the migrate-backup / migrate-restore
   /// workflows under the `commands-admin` feature: BOS template
   /// shape, IMS bundle parsing, local file I/O, missing CLI
   /// argument, etc. The string carries the operation context.
   #[error("CSM-RS > Migrate: {0}")]
   MigrateOp(String),
+  /// An HSM group snapshot file can't be used: unsupported format
+  /// version or unexpected shape.
+  #[error("CSM-RS > HSM group snapshot: {0}")]
+  GroupSnapshot(String),
+  /// A system state bundle file can't be used, or an operation isn't
+  /// available against one (see [`crate::offline`]).
+  #[error("CSM-RS > State bundle: {0}")]
+  StateBundle(String),
+  /// A BSS boot parameters bundle file can't be used: unsupported
+  /// format version or unexpected shape (see [`crate::bss::bundle`]).
+  #[error("CSM-RS > BSS boot parameters bundle: {0}")]
+  BootParamsBundle(String),
+  /// A delete was refused because the resource is protected (see
+  /// [`crate::protection`]). The string names the resource.
+  #[error("CSM-RS > {0} is protected, ignore protection to delete it")]
+  Protected(String),
+  /// An execution context was refused for acting on behalf of a tenant
+  /// (see [`crate::ExecutionContext::on_behalf_of`]).
+  #[error("CSM-RS > impersonation: {0}")]
+  Impersonation(String),
+  /// The Kea DHCP control agent rejected a command or answered with
+  /// an unexpected shape.
+  #[error("CSM-RS > Kea: {0}")]
+  Kea(String),
   /// A Gitea / git-repo API response didn't decode into the
   /// expected shape. Used when extracting `ref`, `commit/sha`,
   /// `object/type`, tag name/url, etc. from a Gitea response and
   /// the field isn't present or has the wrong type.
   #[error("CSM-RS > Git repo shape: {0}")]
   GitRepoShape(String),
+  /// A branch, tag or commit named by the caller does not exist in the
+  /// Gitea repo.
+  #[error("CSM-RS > Git ref not found: {0}")]
+  GitRefNotFound(String),
+  /// The refs of a Gitea repo span more pages than the caller allows
+  /// listing, so the list would be incomplete.
+  #[error(
+    "CSM-RS > Git refs of repo '{repo}' span more than {max_pages} pages"
+  )]
+  GitRefsTruncated { repo: String, max_pages: usize },
+  /// `git ls-remote` of a repo reached over SSH could not be run or
+  /// failed.
+  #[error("CSM-RS > Git over SSH: {0}")]
+  GitSsh(String),
+  /// A CFS configuration layer names a playbook that does not exist in
+  /// its repo at the commit the layer is pinned to.
+  #[error(
+    "CSM-RS > Playbook '{playbook}' of layer '{layer}' not found at commit '{commit}'"
+  )]
+  PlaybookNotFound {
+    layer: String,
+    playbook: String,
+    commit: String,
+  },
   /// Caller-input validation failed: required field missing,
   /// argument outside the expected shape, no NID/XName found in a
   /// component lookup, etc. The string is a static description of
@@ -215,6 +320,31 @@ pub enum Error {
   /// missing or has the wrong type.
   #[error("CSM-RS > JWT: {0}")]
   JwtShape(&'static str),
+  /// A check of [`crate::ShastaClient::connect`] failed. `check` names
+  /// it (`base_url`, `token`, `connect` or `versions`) and `reason`
+  /// says what's wrong.
+  #[error("CSM-RS > Connection check '{check}' failed: {reason}")]
+  ConnectionCheck { check: &'static str, reason: String },
+  /// A string is not a valid xname of the kind expected (see
+  /// [`crate::common::xname`]). `reason` says which part is wrong.
+  #[error("CSM-RS > Invalid xname '{xname}': {reason}")]
+  InvalidXName { xname: String, reason: String },
+  /// A string is not a valid HSM group label (see
+  /// [`crate::hsm::group::types::GroupLabel`]). `reason` says which
+  /// rule it breaks.
+  #[error("CSM-RS > Invalid HSM group label '{label}': {reason}")]
+  InvalidGroupLabel { label: String, reason: String },
+  /// Several `cray-product-catalog` artifacts match the filter of the
+  /// image `image_name` is built from. `candidates` lists their names,
+  /// for the user to refine the filter or pick one.
+  #[error(
+    "CSM-RS > Product catalog for image '{image_name}' has several matching items: {}",
+    .candidates.join(", ")
+  )]
+  AmbiguousProductImage {
+    image_name: String,
+    candidates: Vec<String>,
+  },
 }
 
 impl Error {
@@ -324,11 +454,18 @@ impl From<crate::error::Error> for MantaError {
       // human-readable subject so dispatcher callers can branch on
       // NotFound vs. other failure classes.
       Error::ImageNotFound(s) => MantaError::NotFound(format!("Image '{s}'")),
+      e @ Error::AmbiguousImageName { .. } => MantaError::Conflict(e.to_string()),
       Error::GroupNotFound(s) => MantaError::NotFound(format!("Group '{s}'")),
       Error::HsmComponentNotFound(s) => {
         MantaError::NotFound(format!("HSM component '{s}'"))
       }
       Error::ImsKeyNotFound(s) => MantaError::NotFound(format!("IMS key '{s}'")),
+      Error::BootParametersNotFound(s) => {
+        MantaError::NotFound(format!("BSS boot parameters for '{s}'"))
+      }
+      Error::CfsSourceNotFound(s) => {
+        MantaError::NotFound(format!("CFS source '{s}'"))
+      }
       Error::ConfigurationDerivativesNotFound(s) => {
         MantaError::NotFound(format!("No derivatives for CFS configuration '{s}'"))
       }
@@ -390,6 +527,8 @@ impl From<crate::error::Error> for MantaError {
       e @ Error::ParseStrIntError(_) => MantaError::Message(e.to_string()),
       #[cfg(feature = "ims-s3")]
       e @ Error::SmithyDataStreamError(_) => MantaError::Message(e.to_string()),
+      #[cfg(feature = "telemetry")]
+      e @ Error::KafkaError(_) => MantaError::Message(e.to_string()),
 
       // New shape/format errors fold into MissingField since the dispatcher
       // doesn't yet have richer structural variants for them.
@@ -402,10 +541,66 @@ impl From<crate::error::Error> for MantaError {
         MantaError::NotFound(format!("Cray product catalog: {s}"))
       }
       Error::SatFile(s) => MantaError::Message(format!("SAT file: {s}")),
+      Error::SatFileValidation(s) => {
+        MantaError::Message(format!("SAT file validation failed:\n{s}"))
+      }
+      Error::SatFileField { path, detail } => {
+        MantaError::MissingField(format!("SAT file: '{path}' {detail}"))
+      }
+      Error::BootSetArchMismatch {
+        boot_set,
+        boot_set_arch,
+        image,
+        image_arch,
+      } => MantaError::Message(format!(
+        "BOS boot set '{boot_set}' arch '{boot_set_arch}' does not match IMS image '{image}' arch '{image_arch}'"
+      )),
+      Error::BootSetImageMismatch {
+        boot_set,
+        image,
+        detail,
+      } => MantaError::Message(format!(
+        "BOS boot set '{boot_set}' does not reference IMS image '{image}': {detail}"
+      )),
+      Error::BosTemplateSchema { template, detail } => {
+        MantaError::Message(format!(
+          "BOS sessiontemplate '{template}' does not match the BOS v2 schema:\n{detail}"
+        ))
+      }
       Error::MigrateOp(s) => MantaError::Message(format!("Migrate: {s}")),
+      Error::GroupSnapshot(s) => {
+        MantaError::Message(format!("HSM group snapshot: {s}"))
+      }
+      Error::StateBundle(s) => {
+        MantaError::Message(format!("State bundle: {s}"))
+      }
+      Error::BootParamsBundle(s) => {
+        MantaError::Message(format!("BSS boot parameters bundle: {s}"))
+      }
+      Error::Protected(s) => MantaError::Message(format!(
+        "{s} is protected, ignore protection to delete it"
+      )),
+      Error::Impersonation(s) => {
+        MantaError::Message(format!("impersonation: {s}"))
+      }
+      Error::Kea(s) => MantaError::Message(format!("Kea: {s}")),
       Error::GitRepoShape(s) => {
         MantaError::MissingField(format!("git repo: {s}"))
       }
+      Error::GitRefNotFound(s) => {
+        MantaError::NotFound(format!("git ref {s}"))
+      }
+      Error::GitRefsTruncated { repo, max_pages } => MantaError::Message(
+        format!("Git refs of repo '{repo}' span more than {max_pages} pages"),
+      ),
+      Error::GitSsh(s) => MantaError::Message(format!("Git over SSH: {s}")),
+      Error::PlaybookNotFound {
+        layer,
+        playbook,
+        commit,
+      } => MantaError::NotFound(format!(
+        "playbook '{playbook}' of layer '{layer}' at commit '{commit}'"
+      )),
       Error::ValidationFailed(s) => {
         MantaError::Message(format!("Validation: {s}"))
       }
@@ -429,6 +624,22 @@ impl From<crate::error::Error> for MantaError {
         body: None,
       },
       Error::JwtShape(s) => MantaError::Message(format!("JWT: {s}")),
+      Error::ConnectionCheck { check, reason } => MantaError::Message(
+        format!("Connection check '{check}' failed: {reason}"),
+      ),
+      Error::InvalidXName { xname, reason } => {
+        MantaError::Message(format!("Invalid xname '{xname}': {reason}"))
+      }
+      Error::InvalidGroupLabel { label, reason } => MantaError::Message(
+        format!("Invalid HSM group label '{label}': {reason}"),
+      ),
+      Error::AmbiguousProductImage {
+        image_name,
+        candidates,
+      } => MantaError::Message(format!(
+        "Product catalog for image '{image_name}' has several matching items: {}",
+        candidates.join(", ")
+      )),
     }
   }
 }
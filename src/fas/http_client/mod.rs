@@ -0,0 +1,72 @@
+//! `ShastaClient` methods for FAS snapshots.
+
+use crate::{
+  ShastaClient,
+  common::http,
+  error::Error,
+  fas::types::{Snapshot, SnapshotCreated, SnapshotParameters},
+};
+
+impl ShastaClient {
+  /// Start taking a FAS snapshot; poll [`Self::fas_snapshot_get`] until
+  /// it is `ready`. Returns the snapshot name.
+  ///
+  /// `POST /fas/v1/snapshots`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn fas_snapshot_post(
+    &self,
+    token: &str,
+    snapshot_parameters: &SnapshotParameters,
+  ) -> Result<String, Error> {
+    let api_url = format!("{}/fas/v1/snapshots", self.base_url());
+
+    let snapshot_created: SnapshotCreated =
+      http::post_json(self.http(), &api_url, token, snapshot_parameters)
+        .await?;
+
+    Ok(snapshot_created.name)
+  }
+
+  /// Get FAS snapshot `name`.
+  ///
+  /// `GET /fas/v1/snapshots/{name}`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn fas_snapshot_get(
+    &self,
+    token: &str,
+    name: &str,
+  ) -> Result<Snapshot, Error> {
+    let api_url = format!("{}/fas/v1/snapshots/{name}", self.base_url());
+
+    http::get_json(self.http(), &api_url, token).await
+  }
+
+  /// Delete FAS snapshot `name`.
+  ///
+  /// `DELETE /fas/v1/snapshots/{name}`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn fas_snapshot_delete(
+    &self,
+    token: &str,
+    name: &str,
+  ) -> Result<(), Error> {
+    let api_url = format!("{}/fas/v1/snapshots/{name}", self.base_url());
+
+    http::delete(self.http(), &api_url, token).await
+  }
+}
@@ -0,0 +1,17 @@
+//! Firmware Action Service (FAS) bindings.
+//!
+//! FAS reads and updates the firmware of the components behind the
+//! Redfish endpoints HSM knows about. Only snapshots are wrapped so far:
+//! a snapshot records the firmware version of every target (`BMC`,
+//! `BIOS`, `Node0.BIOS`, ...) of the selected devices, which is how the
+//! current firmware of a node is read.
+//!
+//! Submodules:
+//!
+//! - [`http_client`] — `ShastaClient` methods for FAS snapshots.
+//! - [`types`] — snapshot request and response shapes.
+
+pub mod http_client;
+pub mod types;
+
+pub use types::Snapshot;
@@ -0,0 +1,79 @@
+//! Wire-format types — mirror the FAS v1 snapshot schema; field names
+//! are dictated by the API.
+
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /fas/v1/snapshots`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotParameters {
+  /// Snapshot name, unique among the FAS snapshots.
+  pub name: String,
+  /// When FAS may delete the snapshot, RFC 3339.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub expiration_time: Option<String>,
+  /// Devices to snapshot, every device FAS knows about if `None`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub state_component_filter: Option<StateComponentFilter>,
+}
+
+/// Devices a [`SnapshotParameters`] selects.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateComponentFilter {
+  /// BMC xnames.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub xnames: Vec<String>,
+}
+
+/// Answer to `POST /fas/v1/snapshots`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotCreated {
+  /// Name of the snapshot being taken.
+  pub name: String,
+}
+
+/// `GET /fas/v1/snapshots/{name}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+  /// Snapshot name.
+  pub name: String,
+  /// When the snapshot was taken.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub capture_time: Option<String>,
+  /// `false` while FAS is still reading the devices.
+  #[serde(default)]
+  pub ready: bool,
+  /// Devices read, with their firmware.
+  #[serde(default)]
+  pub devices: Vec<SnapshotDevice>,
+}
+
+/// Firmware of one device (BMC) of a [`Snapshot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDevice {
+  /// BMC xname.
+  pub xname: String,
+  /// Firmware targets behind the BMC.
+  #[serde(default)]
+  pub targets: Vec<SnapshotTarget>,
+  /// Why FAS could not read the device.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+/// One firmware target of a [`SnapshotDevice`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotTarget {
+  /// Target name, e.g. `BMC` or `Node0.BIOS`.
+  pub name: String,
+  /// Firmware version currently installed.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub firmware_version: Option<String>,
+  /// Why FAS could not read the target.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
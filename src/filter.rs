@@ -0,0 +1,521 @@
+//! Small filter expression language shared by the CFS configuration,
+//! CFS session, BOS session, BOS session template and IMS image
+//! listings.
+//!
+//! Instead of threading `(pattern, since, until, limit)` option tuples
+//! through every helper, callers build one [`Query`]:
+//!
+//! ```
+//! use chrono::TimeDelta;
+//! use csm_rs::filter::{Filter, Query, name_glob, newer_than};
+//!
+//! let query = Query::new(Filter::and(
+//!   name_glob("cos-*"),
+//!   newer_than(TimeDelta::days(30)),
+//! ))
+//! .limit(10);
+//! ```
+//!
+//! and apply it to any [`Filterable`] list with [`Query::apply`], or
+//! walk the matches page by page with [`Query::page`] and the opaque
+//! [`Cursor`] tokens it hands out.
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use globset::{Glob, GlobMatcher};
+
+pub use crate::common::paging::{Cursor, Page};
+
+use crate::{
+  bos::{BosSession, BosSessionTemplate},
+  cfs::{
+    configuration::http_client::v2::types::cfs_configuration_response::CfsConfigurationResponse,
+    session::http_client::v2::types::CfsSessionGetResponse,
+  },
+  common::paging,
+  error::Error,
+  ims::image::http_client::types::Image,
+};
+
+/// Predicate over a [`Filterable`] item.
+///
+/// Time predicates never match items without a (parseable) timestamp.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Filter {
+  /// Matches everything.
+  #[default]
+  All,
+  /// Matches when every inner filter matches.
+  And(Vec<Filter>),
+  /// Matches when at least one inner filter matches.
+  Or(Vec<Filter>),
+  /// Matches when the inner filter does not.
+  Not(Box<Filter>),
+  /// Item name matches the glob pattern.
+  NameGlob(String),
+  /// Item name contains the substring.
+  NameContains(String),
+  /// Name of the CFS configuration the item refers to matches the glob
+  /// pattern.
+  ConfigurationGlob(String),
+  /// Item timestamp is at or after the given instant (UTC).
+  Since(NaiveDateTime),
+  /// Item timestamp is strictly before the given instant (UTC).
+  Until(NaiveDateTime),
+  /// Item timestamp is within the given duration from now.
+  NewerThan(TimeDelta),
+  /// Item label `key` is set, to `value` if given.
+  Label {
+    /// Label key.
+    key: String,
+    /// Label value; `None` matches any value.
+    value: Option<String>,
+  },
+}
+
+impl Filter {
+  /// Conjunction of `a` and `b`. [`Filter::All`] operands are dropped and
+  /// nested [`Filter::And`]s are flattened.
+  #[must_use]
+  pub fn and(a: Filter, b: Filter) -> Filter {
+    match (a, b) {
+      (Filter::All, other) | (other, Filter::All) => other,
+      (Filter::And(mut a_vec), Filter::And(b_vec)) => {
+        a_vec.extend(b_vec);
+        Filter::And(a_vec)
+      }
+      (Filter::And(mut a_vec), other) => {
+        a_vec.push(other);
+        Filter::And(a_vec)
+      }
+      (other, Filter::And(mut b_vec)) => {
+        b_vec.insert(0, other);
+        Filter::And(b_vec)
+      }
+      (a, b) => Filter::And(vec![a, b]),
+    }
+  }
+
+  /// Disjunction of `a` and `b`.
+  #[must_use]
+  pub fn or(a: Filter, b: Filter) -> Filter {
+    Filter::Or(vec![a, b])
+  }
+
+  fn compile(&self, now: NaiveDateTime) -> Result<Compiled, Error> {
+    let compile_vec = |filter_vec: &[Filter]| {
+      filter_vec
+        .iter()
+        .map(|filter| filter.compile(now))
+        .collect::<Result<Vec<Compiled>, Error>>()
+    };
+
+    Ok(match self {
+      Filter::All => Compiled::All,
+      Filter::And(filter_vec) => Compiled::And(compile_vec(filter_vec)?),
+      Filter::Or(filter_vec) => Compiled::Or(compile_vec(filter_vec)?),
+      Filter::Not(filter) => Compiled::Not(Box::new(filter.compile(now)?)),
+      Filter::NameGlob(pattern) => {
+        Compiled::NameGlob(Glob::new(pattern)?.compile_matcher())
+      }
+      Filter::NameContains(needle) => Compiled::NameContains(needle.clone()),
+      Filter::ConfigurationGlob(pattern) => {
+        Compiled::ConfigurationGlob(Glob::new(pattern)?.compile_matcher())
+      }
+      Filter::Since(since) => Compiled::Since(*since),
+      Filter::Until(until) => Compiled::Until(*until),
+      Filter::NewerThan(delta) => Compiled::Since(now - *delta),
+      Filter::Label { key, value } => Compiled::Label {
+        key: key.clone(),
+        value: value.clone(),
+      },
+    })
+  }
+}
+
+impl std::ops::Not for Filter {
+  type Output = Filter;
+
+  fn not(self) -> Filter {
+    Filter::Not(Box::new(self))
+  }
+}
+
+/// [`Filter::NameGlob`].
+#[must_use]
+pub fn name_glob(pattern: &str) -> Filter {
+  Filter::NameGlob(pattern.to_string())
+}
+
+/// [`Filter::NameContains`].
+#[must_use]
+pub fn name_contains(needle: &str) -> Filter {
+  Filter::NameContains(needle.to_string())
+}
+
+/// [`Filter::ConfigurationGlob`].
+#[must_use]
+pub fn configuration_glob(pattern: &str) -> Filter {
+  Filter::ConfigurationGlob(pattern.to_string())
+}
+
+/// [`Filter::NewerThan`].
+#[must_use]
+pub fn newer_than(delta: TimeDelta) -> Filter {
+  Filter::NewerThan(delta)
+}
+
+/// [`Filter::Label`] set to `value`.
+#[must_use]
+pub fn label(key: &str, value: &str) -> Filter {
+  Filter::Label {
+    key: key.to_string(),
+    value: Some(value.to_string()),
+  }
+}
+
+/// [`Filter::Label`] set to any value.
+#[must_use]
+pub fn has_label(key: &str) -> Filter {
+  Filter::Label {
+    key: key.to_string(),
+    value: None,
+  }
+}
+
+/// Timestamp in the half-open range `[since, until)`.
+#[must_use]
+pub fn between(since: NaiveDateTime, until: NaiveDateTime) -> Filter {
+  Filter::and(Filter::Since(since), Filter::Until(until))
+}
+
+/// A [`Filter`] with globs compiled and relative times pinned.
+enum Compiled {
+  All,
+  And(Vec<Compiled>),
+  Or(Vec<Compiled>),
+  Not(Box<Compiled>),
+  NameGlob(GlobMatcher),
+  NameContains(String),
+  ConfigurationGlob(GlobMatcher),
+  Since(NaiveDateTime),
+  Until(NaiveDateTime),
+  Label { key: String, value: Option<String> },
+}
+
+impl Compiled {
+  fn matches<T: Filterable>(&self, item: &T) -> bool {
+    match self {
+      Compiled::All => true,
+      Compiled::And(compiled_vec) => {
+        compiled_vec.iter().all(|compiled| compiled.matches(item))
+      }
+      Compiled::Or(compiled_vec) => {
+        compiled_vec.iter().any(|compiled| compiled.matches(item))
+      }
+      Compiled::Not(compiled) => !compiled.matches(item),
+      Compiled::NameGlob(glob) => {
+        item.filter_name().is_some_and(|name| glob.is_match(name))
+      }
+      Compiled::NameContains(needle) => item
+        .filter_name()
+        .is_some_and(|name| name.contains(needle.as_str())),
+      Compiled::ConfigurationGlob(glob) => item
+        .filter_configuration_name()
+        .is_some_and(|name| glob.is_match(name)),
+      Compiled::Since(since) => {
+        item.filter_timestamp().is_some_and(|ts| *since <= ts)
+      }
+      Compiled::Until(until) => {
+        item.filter_timestamp().is_some_and(|ts| ts < *until)
+      }
+      Compiled::Label { key, value } => {
+        item.filter_label(key).is_some_and(|label_value| {
+          value.as_deref().is_none_or(|value| value == label_value)
+        })
+      }
+    }
+  }
+}
+
+/// A [`Filter`] plus an optional cap on the number of results and a
+/// paging position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+  /// Predicate items must satisfy.
+  pub filter: Filter,
+  /// Keep only the `limit` most recent matching items.
+  pub limit: Option<usize>,
+  /// Start [`Query::page`] right after this item.
+  pub after: Option<Cursor>,
+}
+
+impl Query {
+  /// Query with no limit.
+  #[must_use]
+  pub fn new(filter: Filter) -> Self {
+    Self {
+      filter,
+      ..Self::default()
+    }
+  }
+
+  /// Keep only the `limit` most recent matching items.
+  #[must_use]
+  pub fn limit(mut self, limit: usize) -> Self {
+    self.limit = Some(limit);
+    self
+  }
+
+  /// Resume [`Query::page`] right after the item `cursor` points at.
+  #[must_use]
+  pub fn after(mut self, cursor: Cursor) -> Self {
+    self.after = Some(cursor);
+    self
+  }
+
+  /// Retain the items of `item_vec` matching the filter, sort them by
+  /// `(timestamp, name)` in ascending order (items without a timestamp
+  /// first) and drop all but the last `limit`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::GlobError`] if a glob pattern in the filter is
+  /// invalid.
+  pub fn apply<T: Filterable>(
+    &self,
+    item_vec: &mut Vec<T>,
+  ) -> Result<(), Error> {
+    self.retain_sorted(item_vec)?;
+
+    if let Some(limit) = self.limit {
+      paging::keep_last(item_vec, limit);
+    }
+
+    Ok(())
+  }
+
+  /// Like [`Query::apply`], but return the `page_size` items following
+  /// [`Query::after`] (or the first ones) instead of the last `limit`.
+  /// Feed [`Page::next_cursor`] back through [`Query::after`] to get
+  /// the next page.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::GlobError`] if a glob pattern in the filter is
+  /// invalid.
+  pub fn page<T: Filterable>(
+    &self,
+    mut item_vec: Vec<T>,
+    page_size: usize,
+  ) -> Result<Page<T>, Error> {
+    self.retain_sorted(&mut item_vec)?;
+
+    Ok(paging::paginate(item_vec, self.after.as_ref(), page_size))
+  }
+
+  fn retain_sorted<T: Filterable>(
+    &self,
+    item_vec: &mut Vec<T>,
+  ) -> Result<(), Error> {
+    let compiled = self.filter.compile(Utc::now().naive_utc())?;
+
+    item_vec.retain(|item| compiled.matches(item));
+    paging::sort_by_timestamp(item_vec);
+
+    Ok(())
+  }
+}
+
+/// Parse a CSM timestamp: RFC 3339, or a naive ISO 8601 date-time read
+/// as UTC.
+pub(crate) fn parse_timestamp(timestamp: &str) -> Option<NaiveDateTime> {
+  DateTime::parse_from_rfc3339(timestamp)
+    .map(|date| date.naive_utc())
+    .ok()
+    .or_else(|| {
+      NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f").ok()
+    })
+}
+
+/// Items a [`Query`] can be applied to.
+pub trait Filterable {
+  /// Name matched by [`Filter::NameGlob`] and [`Filter::NameContains`].
+  fn filter_name(&self) -> Option<&str>;
+
+  /// CFS configuration name matched by [`Filter::ConfigurationGlob`].
+  fn filter_configuration_name(&self) -> Option<&str>;
+
+  /// Timestamp matched by the time filters and used for ordering.
+  fn filter_timestamp(&self) -> Option<NaiveDateTime>;
+
+  /// Value of label `key` matched by [`Filter::Label`]. Items without
+  /// labels never match.
+  fn filter_label(&self, _key: &str) -> Option<&str> {
+    None
+  }
+}
+
+impl Filterable for CfsConfigurationResponse {
+  fn filter_name(&self) -> Option<&str> {
+    Some(&self.name)
+  }
+
+  fn filter_configuration_name(&self) -> Option<&str> {
+    Some(&self.name)
+  }
+
+  fn filter_timestamp(&self) -> Option<NaiveDateTime> {
+    parse_timestamp(&self.last_updated)
+  }
+}
+
+impl Filterable for CfsSessionGetResponse {
+  fn filter_name(&self) -> Option<&str> {
+    Some(&self.name)
+  }
+
+  fn filter_configuration_name(&self) -> Option<&str> {
+    self.configuration_name()
+  }
+
+  fn filter_timestamp(&self) -> Option<NaiveDateTime> {
+    self.get_start_time().as_deref().and_then(parse_timestamp)
+  }
+}
+
+impl Filterable for BosSessionTemplate {
+  fn filter_name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+
+  fn filter_configuration_name(&self) -> Option<&str> {
+    self.configuration_name()
+  }
+
+  fn filter_timestamp(&self) -> Option<NaiveDateTime> {
+    None
+  }
+}
+
+impl Filterable for BosSession {
+  fn filter_name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+
+  fn filter_configuration_name(&self) -> Option<&str> {
+    None
+  }
+
+  fn filter_timestamp(&self) -> Option<NaiveDateTime> {
+    self
+      .status
+      .as_ref()
+      .and_then(|status| parse_timestamp(&status.start_time))
+  }
+}
+
+impl Filterable for Image {
+  fn filter_name(&self) -> Option<&str> {
+    Some(&self.name)
+  }
+
+  /// The configuration recorded on images built by a SAT image
+  /// session (`manta.image_session.configuration` metadata key), or
+  /// else the [`crate::ims::image::labels::CONFIGURATION`] label.
+  fn filter_configuration_name(&self) -> Option<&str> {
+    self
+      .label("manta.image_session.configuration")
+      .or_else(|| self.label(crate::ims::image::labels::CONFIGURATION))
+  }
+
+  fn filter_timestamp(&self) -> Option<NaiveDateTime> {
+    self.created.as_deref().and_then(parse_timestamp)
+  }
+
+  fn filter_label(&self, key: &str) -> Option<&str> {
+    self.label(key)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn image(name: &str, created: &str) -> Image {
+    Image {
+      name: name.to_string(),
+      created: Some(created.to_string()),
+      ..Default::default()
+    }
+  }
+
+  fn names(image_vec: &[Image]) -> Vec<&str> {
+    image_vec.iter().map(|image| image.name.as_str()).collect()
+  }
+
+  #[test]
+  fn and_drops_all_and_flattens() {
+    let filter = Filter::and(
+      Filter::and(Filter::All, name_glob("a*")),
+      Filter::and(name_glob("*b"), name_glob("*c*")),
+    );
+    assert_eq!(
+      filter,
+      Filter::And(vec![name_glob("a*"), name_glob("*b"), name_glob("*c*")])
+    );
+  }
+
+  #[test]
+  fn apply_filters_sorts_and_limits() {
+    let mut image_vec = vec![
+      image("cos-2", "2024-03-01T00:00:00+00:00"),
+      image("uan-1", "2024-02-01T00:00:00+00:00"),
+      image("cos-1", "2024-01-01T00:00:00.123456"),
+      image("cos-3", "2024-04-01T00:00:00+00:00"),
+    ];
+
+    Query::new(name_glob("cos-*"))
+      .limit(2)
+      .apply(&mut image_vec)
+      .unwrap();
+
+    assert_eq!(names(&image_vec), vec!["cos-2", "cos-3"]);
+  }
+
+  #[test]
+  fn time_filters_exclude_items_without_timestamp() {
+    let since = parse_timestamp("2024-02-01T00:00:00+00:00").unwrap();
+    let until = parse_timestamp("2024-04-01T00:00:00+00:00").unwrap();
+    let mut image_vec = vec![
+      image("old", "2024-01-01T00:00:00+00:00"),
+      image("in-range", "2024-03-01T00:00:00+00:00"),
+      image("end", "2024-04-01T00:00:00+00:00"),
+      image("malformed", "yesterday"),
+    ];
+
+    Query::new(between(since, until))
+      .apply(&mut image_vec)
+      .unwrap();
+
+    assert_eq!(names(&image_vec), vec!["in-range"]);
+  }
+
+  #[test]
+  fn not_and_newer_than_combine() {
+    let recent = (Utc::now() - TimeDelta::days(1)).to_rfc3339();
+    let mut image_vec = vec![
+      image("cos-old", "2020-01-01T00:00:00+00:00"),
+      image("cos-new", &recent),
+      image("uan-new", &recent),
+    ];
+
+    Query::new(Filter::and(
+      !name_contains("uan"),
+      newer_than(TimeDelta::days(30)),
+    ))
+    .apply(&mut image_vec)
+    .unwrap();
+
+    assert_eq!(names(&image_vec), vec!["cos-new"]);
+  }
+}
@@ -5,6 +5,17 @@
 //!
 //! - [`types`] — re-exports of the progenitor-generated request/response
 //!   shapes for `/State/Components`.
+//! - [`utils`] — inventory helpers, e.g. the xnames of every node
+//!   whether in an HSM group or not.
+//!
+//! [`NodeRole`], [`select_nodes`] and [`get_nodes_by_role`] pick nodes
+//! by HSM `Role` / `SubRole`, optionally within an HSM group.
+//!
+//! [`disable_for_service`] disables nodes before they're moved or
+//! removed so monitoring doesn't alarm on them; re-enable them with
+//! `ShastaClient::hsm_component_patch_enabled`, or run the whole
+//! operation with [`with_disabled_for_service`], which re-enables them
+//! whether it succeeds or not.
 //!
 //! The `ShastaClient` methods for `/smd/hsm/v2/State/Components` live in
 //! `crate::hsm::wrapper::component`. That wrapper file documents per
@@ -12,9 +23,272 @@
 //! the generated progenitor client.
 
 pub mod types;
+pub mod utils;
+
+use std::future::Future;
 
 use types::Component;
 
+use crate::{
+  ShastaClient,
+  error::Error,
+  hsm::group::{
+    GroupExt,
+    hacks::{ROLES, SUBROLES},
+  },
+};
+
+/// HSM `Role`, optionally narrowed to a `SubRole`, to select nodes by.
+///
+/// CFS puts nodes in an Ansible group per role (`Compute`) and per
+/// role and subrole (`Application_UAN`), named by
+/// [`NodeRole::ansible_group`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeRole {
+  /// HSM `Role`, one of [`ROLES`].
+  pub role: &'static str,
+  /// HSM `SubRole`, one of [`SUBROLES`]; `None` matches any subrole.
+  pub subrole: Option<&'static str>,
+}
+
+impl NodeRole {
+  /// Compute nodes.
+  pub const COMPUTE: Self = Self {
+    role: "Compute",
+    subrole: None,
+  };
+  /// Application nodes, whatever their subrole.
+  pub const APPLICATION: Self = Self {
+    role: "Application",
+    subrole: None,
+  };
+  /// User access nodes.
+  pub const APPLICATION_UAN: Self = Self {
+    role: "Application",
+    subrole: Some("UAN"),
+  };
+  /// Kubernetes worker nodes.
+  pub const MANAGEMENT_WORKER: Self = Self {
+    role: "Management",
+    subrole: Some("Worker"),
+  };
+
+  /// Role of CFS Ansible group `group`, `Role` or `Role_SubRole`,
+  /// ignoring case; `None` if `group` doesn't name a known role, e.g.
+  /// because it's an HSM group.
+  #[must_use]
+  pub fn from_ansible_group(group: &str) -> Option<Self> {
+    let known = |name_vec: &[&'static str], name: &str| {
+      name_vec
+        .iter()
+        .copied()
+        .find(|known_name| known_name.eq_ignore_ascii_case(name))
+    };
+
+    match group.split_once('_') {
+      None => Some(Self {
+        role: known(&ROLES, group)?,
+        subrole: None,
+      }),
+      Some((role, subrole)) => Some(Self {
+        role: known(&ROLES, role)?,
+        subrole: Some(known(&SUBROLES, subrole)?),
+      }),
+    }
+  }
+
+  /// Name of the CFS Ansible group of the nodes with this role.
+  #[must_use]
+  pub fn ansible_group(&self) -> String {
+    match self.subrole {
+      Some(subrole) => format!("{}_{subrole}", self.role),
+      None => self.role.to_string(),
+    }
+  }
+
+  /// `true` if `component` is a node with this role.
+  #[must_use]
+  pub fn matches(&self, component: &Component) -> bool {
+    let eq = |value: Option<&String>, expected: &str| {
+      value.is_some_and(|value| value.eq_ignore_ascii_case(expected))
+    };
+
+    component
+      .type_
+      .is_some_and(|hms_type| hms_type.to_string() == "Node")
+      && eq(component.role.as_ref().map(|role| &role.0), self.role)
+      && self.subrole.is_none_or(|subrole| {
+        eq(
+          component.sub_role.as_ref().map(|sub_role| &sub_role.0),
+          subrole,
+        )
+      })
+  }
+}
+
+/// Sorted xnames of the nodes in `component_vec` with `role`, only the
+/// ones in `member_vec_opt` if given.
+#[must_use]
+pub fn select_nodes(
+  component_vec: &[Component],
+  role: NodeRole,
+  member_vec_opt: Option<&[String]>,
+) -> Vec<String> {
+  let mut xname_vec: Vec<String> = component_vec
+    .iter()
+    .filter(|component| role.matches(component))
+    .filter_map(|component| component.id.as_ref().map(|id| id.0.clone()))
+    .filter(|xname| {
+      member_vec_opt.is_none_or(|member_vec| member_vec.contains(xname))
+    })
+    .collect();
+
+  xname_vec.sort();
+  xname_vec
+}
+
+/// Sorted xnames of the nodes with `role`, only the members of HSM
+/// group `hsm_group_name_opt` if given.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant if the HSM group doesn't exist, or on
+/// CSM, transport, or deserialization failure; see the crate-level
+/// `Error` enum for the full set.
+pub async fn get_nodes_by_role(
+  client: &ShastaClient,
+  shasta_token: &str,
+  role: NodeRole,
+  hsm_group_name_opt: Option<&str>,
+) -> Result<Vec<String>, Error> {
+  let member_vec_opt = match hsm_group_name_opt {
+    Some(hsm_group_name) => Some(
+      client
+        .hsm_group_get_one(shasta_token, hsm_group_name)
+        .await?
+        .get_members(),
+    ),
+    None => None,
+  };
+
+  let component_vec = client
+    .hsm_component_get(
+      shasta_token,
+      None,
+      Some("Node"),
+      None,
+      None,
+      Some(role.role),
+      role.subrole,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+    )
+    .await?
+    .components;
+
+  Ok(select_nodes(
+    &component_vec,
+    role,
+    member_vec_opt.as_deref(),
+  ))
+}
+
+/// Sorted xnames of the components in `component_vec` that are
+/// enabled. HSM treats a missing `Enabled` as enabled.
+#[must_use]
+pub fn enabled_xnames(component_vec: &[Component]) -> Vec<String> {
+  let mut xname_vec: Vec<String> = component_vec
+    .iter()
+    .filter(|component| component.enabled != Some(false))
+    .filter_map(|component| component.id.as_ref().map(|id| id.0.clone()))
+    .collect();
+
+  xname_vec.sort();
+  xname_vec
+}
+
+/// Disable the components in `xname_vec` that are currently enabled
+/// and return their xnames, so the caller re-enables exactly those once
+/// it's done servicing them and leaves nodes an operator had already
+/// disabled alone.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn disable_for_service(
+  client: &ShastaClient,
+  shasta_token: &str,
+  xname_vec: &[String],
+) -> Result<Vec<String>, Error> {
+  if xname_vec.is_empty() {
+    return Ok(Vec::new());
+  }
+
+  let component_vec = client
+    .hsm_component_get_and_filter(shasta_token, xname_vec)
+    .await?;
+  let disabled_xname_vec = enabled_xnames(&component_vec);
+
+  client
+    .hsm_component_patch_enabled(shasta_token, &disabled_xname_vec, false)
+    .await?;
+
+  Ok(disabled_xname_vec)
+}
+
+/// Run `operation` with the components in `xname_vec` disabled, see
+/// [`disable_for_service`], and re-enable those it disabled afterwards,
+/// also when `operation` fails.
+///
+/// # Errors
+///
+/// Returns the error of `operation`, else of disabling or re-enabling
+/// the components. A failure to re-enable after `operation` failed is
+/// logged, the error of `operation` is returned.
+pub async fn with_disabled_for_service<T, Fut>(
+  client: &ShastaClient,
+  shasta_token: &str,
+  xname_vec: &[String],
+  operation: Fut,
+) -> Result<T, Error>
+where
+  Fut: Future<Output = Result<T, Error>>,
+{
+  let disabled_xname_vec =
+    disable_for_service(client, shasta_token, xname_vec).await?;
+
+  let result = operation.await;
+
+  let enable_result = client
+    .hsm_component_patch_enabled(shasta_token, &disabled_xname_vec, true)
+    .await;
+
+  match (result, enable_result) {
+    (Ok(value), Ok(())) => Ok(value),
+    (Ok(_), Err(e)) | (Err(e), Ok(())) => Err(e),
+    (Err(e), Err(enable_error)) => {
+      log::warn!(
+        "Could not re-enable nodes '{}': {enable_error}",
+        disabled_xname_vec.join(", ")
+      );
+      Err(e)
+    }
+  }
+}
+
 /// In-place retain of components whose `id` is in `xname_list`.
 ///
 /// The `id` field on the generated `Component` is `Option<XName100>`;
@@ -35,3 +309,93 @@ pub fn filter(component_vec: &mut Vec<Component>, xname_list: &[String]) {
 /// Cargo feature.
 #[cfg(feature = "manta-dispatcher")]
 mod dispatcher_conv;
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn node(xname: &str, role: &str, subrole: Option<&str>) -> Component {
+    serde_json::from_value(json!({
+      "ID": xname,
+      "Type": "Node",
+      "Role": role,
+      "SubRole": subrole,
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn node_role_from_ansible_group() {
+    assert_eq!(
+      NodeRole::from_ansible_group("compute"),
+      Some(NodeRole::COMPUTE)
+    );
+    assert_eq!(
+      NodeRole::from_ansible_group("Application_UAN"),
+      Some(NodeRole::APPLICATION_UAN)
+    );
+    assert_eq!(NodeRole::APPLICATION_UAN.ansible_group(), "Application_UAN");
+    assert_eq!(NodeRole::from_ansible_group("zinal"), None);
+    assert_eq!(NodeRole::from_ansible_group("Compute_zinal"), None);
+  }
+
+  #[test]
+  fn select_nodes_by_role_and_membership() {
+    let component_vec = [
+      node("x1000c0s0b0n1", "Compute", None),
+      node("x1000c0s0b0n0", "Compute", None),
+      node("x3000c0s1b0n0", "Application", Some("UAN")),
+      node("x3000c0s2b0n0", "Application", Some("Gateway")),
+      node("x3000c0s3b0n0", "Management", Some("Worker")),
+    ];
+
+    assert_eq!(
+      select_nodes(&component_vec, NodeRole::COMPUTE, None),
+      ["x1000c0s0b0n0", "x1000c0s0b0n1"]
+    );
+    assert_eq!(
+      select_nodes(&component_vec, NodeRole::APPLICATION, None),
+      ["x3000c0s1b0n0", "x3000c0s2b0n0"]
+    );
+    assert_eq!(
+      select_nodes(&component_vec, NodeRole::APPLICATION_UAN, None),
+      ["x3000c0s1b0n0"]
+    );
+    assert_eq!(
+      select_nodes(&component_vec, NodeRole::MANAGEMENT_WORKER, None),
+      ["x3000c0s3b0n0"]
+    );
+    assert_eq!(
+      select_nodes(
+        &component_vec,
+        NodeRole::COMPUTE,
+        Some(&["x1000c0s0b0n1".to_string()])
+      ),
+      ["x1000c0s0b0n1"]
+    );
+  }
+
+  #[test]
+  fn enabled_xnames_skips_disabled_components() {
+    let component = |xname: &str, enabled: Option<bool>| -> Component {
+      serde_json::from_value(json!({
+        "ID": xname,
+        "Type": "Node",
+        "Enabled": enabled,
+      }))
+      .unwrap()
+    };
+    let component_vec = [
+      component("x1000c0s0b0n1", Some(true)),
+      component("x1000c0s0b0n0", None),
+      component("x1000c0s1b0n0", Some(false)),
+    ];
+
+    assert_eq!(
+      enabled_xnames(&component_vec),
+      ["x1000c0s0b0n0", "x1000c0s0b0n1"]
+    );
+  }
+}
@@ -0,0 +1,57 @@
+//! Inventory helpers built on `ShastaClient::hsm_component_*`.
+
+use crate::{ShastaClient, error::Error, hsm::component::NodeRole};
+
+/// Sorted xnames of every node in HSM, only the ones with `role_filter`
+/// if given.
+///
+/// Unlike collecting the members of every HSM group, this includes the
+/// nodes in no group. HSM filters by type and role itself and returns
+/// only the `ID`/`NID` projection of each node, which keeps the response
+/// small on large systems.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn get_all_node_xnames(
+  client: &ShastaClient,
+  shasta_token: &str,
+  role_filter: Option<NodeRole>,
+) -> Result<Vec<String>, Error> {
+  let component_vec = client
+    .hsm_component_get(
+      shasta_token,
+      None,
+      Some("Node"),
+      None,
+      None,
+      role_filter.map(|role| role.role),
+      role_filter.and_then(|role| role.subrole),
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      Some("true"),
+    )
+    .await?
+    .components;
+
+  let mut xname_vec: Vec<String> = component_vec
+    .into_iter()
+    .filter_map(|component| component.id.map(|id| id.0))
+    .collect();
+  xname_vec.sort();
+  xname_vec.dedup();
+
+  Ok(xname_vec)
+}
@@ -42,6 +42,12 @@ pub trait GroupExt: Sized {
   /// If the `members` field was absent it is created; if its `ids`
   /// array was empty it is extended in place.
   fn add_xnames(&mut self, xnames: &[String]) -> Vec<String>;
+
+  /// All tags as owned `Vec<String>`; empty if the group has none.
+  fn get_tags(&self) -> Vec<String>;
+
+  /// Whether the group is tagged `tag`.
+  fn has_tag(&self, tag: &str) -> bool;
 }
 
 impl GroupExt for Group {
@@ -86,4 +92,12 @@ impl GroupExt for Group {
       .extend(xnames.iter().cloned().map(XNameRw100));
     self.get_members()
   }
+
+  fn get_tags(&self) -> Vec<String> {
+    self.tags.iter().map(|tag| tag.0.clone()).collect()
+  }
+
+  fn has_tag(&self, tag: &str) -> bool {
+    self.tags.iter().any(|group_tag| group_tag.0 == tag)
+  }
 }
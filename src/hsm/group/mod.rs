@@ -9,20 +9,23 @@
 //!   shapes.
 //! - [`ext`] — `GroupExt` trait with the convenience methods that used
 //!   to be inherent on `Group`.
-//! - [`utils`] — composed helpers (membership unions, substring lookup).
+//! - [`utils`] — composed helpers (membership unions, substring lookup,
+//!   tags).
 //! - [`hacks`] — workarounds for CSM behaviour that doesn't fit cleanly
 //!   into the rest of the surface.
+//! - [`tenancy`] — HSM groups a caller owns, derived from their token.
 
 /// `GroupExt` trait with the convenience methods (`new_with_members`,
 /// `get_members`, `get_members_opt`, `add_xnames`) that used to live as
-/// an inherent `impl Group` block. Re-exported at the module root so a
-/// glob import keeps working.
+/// an inherent `impl Group` block, plus `get_tags` and `has_tag`.
+/// Re-exported at the module root so a glob import keeps working.
 pub mod ext;
 pub use ext::GroupExt;
 
 /// Workarounds for CSM HSM behaviour that does not fit cleanly into
 /// the rest of the surface.
 pub mod hacks;
+pub mod tenancy;
 /// Integration-style tests for the HSM group namespace.
 #[cfg(test)]
 pub mod tests;
@@ -0,0 +1,153 @@
+//! Tenancy of the caller: the HSM groups the owner of a Shasta token may
+//! act on.
+//!
+//! There is no TAPMS integration yet, so the tenancy is derived from the
+//! Keycloak roles of the token: every role naming an HSM group (roles,
+//! sub-roles and site-wide groups excluded, see [`super::hacks`]) is a
+//! tenant group. A tenant also owns the groups and resources named
+//! after one of its groups followed by `-` or `_`, e.g. `zinal` owns
+//! `zinal_cta` and the CFS configuration `zinal-cos-config`. Admins
+//! ([`super::hacks::PA_ADMIN`]) are not restricted.
+
+use crate::{common, error::Error, hsm};
+
+/// HSM groups a caller owns, see the [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tenancy {
+  /// Tenant groups, sorted; `None` if unrestricted.
+  group_vec: Option<Vec<String>>,
+}
+
+impl Tenancy {
+  /// Tenancy owning everything, e.g. of an admin.
+  #[must_use]
+  pub fn unrestricted() -> Self {
+    Self { group_vec: None }
+  }
+
+  /// Tenancy owning `group_vec` (and the names prefixed by them).
+  #[must_use]
+  pub fn from_groups(mut group_vec: Vec<String>) -> Self {
+    group_vec.sort();
+    group_vec.dedup();
+
+    Self {
+      group_vec: Some(group_vec),
+    }
+  }
+
+  /// Tenancy of the owner of `shasta_token`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant if the token can't be decoded.
+  pub fn from_token(shasta_token: &str) -> Result<Self, Error> {
+    let role_vec = common::jwt_ops::get_roles(shasta_token)?;
+
+    if role_vec
+      .iter()
+      .any(|role| role == hsm::group::hacks::PA_ADMIN)
+    {
+      return Ok(Self::unrestricted());
+    }
+
+    let role_vec = hsm::group::hacks::filter_keycloak_roles(
+      &role_vec.iter().map(String::as_str).collect::<Vec<&str>>(),
+    );
+    let group_vec = hsm::group::hacks::filter_roles_and_subroles(
+      &role_vec.iter().map(String::as_str).collect::<Vec<&str>>(),
+    );
+
+    Ok(Self::from_groups(
+      hsm::group::hacks::filter_system_hsm_group_names(group_vec),
+    ))
+  }
+
+  /// Whether the tenancy owns everything.
+  #[must_use]
+  pub fn is_unrestricted(&self) -> bool {
+    self.group_vec.is_none()
+  }
+
+  /// Tenant groups; `None` if unrestricted.
+  #[must_use]
+  pub fn groups(&self) -> Option<&[String]> {
+    self.group_vec.as_deref()
+  }
+
+  /// Whether the tenancy owns HSM group, or any resource, `name`.
+  #[must_use]
+  pub fn owns(&self, name: &str) -> bool {
+    self.group_vec.as_ref().is_none_or(|group_vec| {
+      group_vec.iter().any(|group| {
+        name.strip_prefix(group.as_str()).is_some_and(|rest| {
+          rest.is_empty() || rest.starts_with('-') || rest.starts_with('_')
+        })
+      })
+    })
+  }
+
+  /// Tenancy owning what both `self` and `other` own: the groups of
+  /// each owned by the other.
+  #[must_use]
+  pub fn intersect(&self, other: &Self) -> Self {
+    match (&self.group_vec, &other.group_vec) {
+      (None, _) => other.clone(),
+      (_, None) => self.clone(),
+      (Some(group_vec), Some(other_group_vec)) => Self::from_groups(
+        other
+          .filter_groups(group_vec)
+          .into_iter()
+          .chain(self.filter_groups(other_group_vec))
+          .collect(),
+      ),
+    }
+  }
+
+  /// `group_vec` without the groups the tenancy doesn't own.
+  #[must_use]
+  pub fn filter_groups(&self, group_vec: &[String]) -> Vec<String> {
+    group_vec
+      .iter()
+      .filter(|group| self.owns(group))
+      .cloned()
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tenancy_owns_its_groups_and_their_prefixed_names() {
+    let tenancy = Tenancy::from_groups(vec!["zinal".to_string()]);
+
+    assert!(tenancy.owns("zinal"));
+    assert!(tenancy.owns("zinal_cta"));
+    assert!(tenancy.owns("zinal-cos-config"));
+    assert!(!tenancy.owns("zinalx"));
+    assert!(!tenancy.owns("eiger"));
+    assert!(Tenancy::unrestricted().owns("eiger"));
+    assert_eq!(
+      tenancy.filter_groups(&["eiger".to_string(), "zinal_cta".to_string()]),
+      vec!["zinal_cta".to_string()]
+    );
+  }
+
+  #[test]
+  fn intersection_owns_what_both_tenancies_own() {
+    let zinal = Tenancy::from_groups(vec!["zinal".to_string()]);
+    let zinal_cta =
+      Tenancy::from_groups(vec!["zinal_cta".to_string(), "eiger".to_string()]);
+
+    assert_eq!(zinal.intersect(&Tenancy::unrestricted()), zinal);
+    assert_eq!(Tenancy::unrestricted().intersect(&zinal), zinal);
+    assert_eq!(
+      zinal.intersect(&zinal_cta).groups(),
+      Some(["zinal_cta".to_string()].as_slice())
+    );
+    assert_eq!(zinal_cta.intersect(&zinal), zinal.intersect(&zinal_cta));
+    assert!(!zinal.intersect(&zinal_cta).owns("zinal"));
+  }
+}
@@ -34,6 +34,79 @@ fn test_add_xnames() {
   );
 }
 
+#[test]
+fn test_group_labels_by_tag() {
+  use crate::hsm::group::{types::ResourceName, utils::group_labels_by_tag};
+
+  let tagged = |label: &str, tag_vec: &[&str]| {
+    let mut group = Group::new_with_members(label, None);
+    group.tags = tag_vec
+      .iter()
+      .map(|tag| ResourceName((*tag).to_string()))
+      .collect();
+    group
+  };
+  let group_vec = vec![
+    tagged("zinal", &["prod", "gpu"]),
+    tagged("zinal_test", &["staging"]),
+    tagged("eiger", &["prod"]),
+  ];
+
+  assert_eq!(group_vec[0].get_tags(), vec!["prod", "gpu"]);
+  assert!(group_vec[1].has_tag("staging"));
+  assert!(!group_vec[1].has_tag("prod"));
+
+  let label_vec_by_tag = group_labels_by_tag(
+    &group_vec,
+    &["prod".to_string(), "staging".to_string(), "dev".to_string()],
+  );
+  assert_eq!(label_vec_by_tag["prod"], vec!["zinal", "eiger"]);
+  assert_eq!(label_vec_by_tag["staging"], vec!["zinal_test"]);
+  assert!(label_vec_by_tag["dev"].is_empty());
+}
+
+#[test]
+fn test_exclusive_group_moves() {
+  use crate::hsm::group::{
+    types::{ExclusiveGroupMove, ResourceName},
+    utils::exclusive_group_moves,
+  };
+
+  let group =
+    |label: &str, member_vec: Vec<&str>, exclusive_opt: Option<&str>| {
+      let mut group = Group::new_with_members(label, Some(member_vec));
+      group.exclusive_group =
+        exclusive_opt.map(|exclusive| ResourceName(exclusive.to_string()));
+      group
+    };
+  let group_vec = vec![
+    group("zinal", vec![], Some("tenants")),
+    group(
+      "eiger",
+      vec!["x1000c0s0b0n0", "x1000c0s0b0n1"],
+      Some("tenants"),
+    ),
+    group("compute", vec!["x1000c0s0b0n0"], None),
+    group("gpu", vec![], None),
+  ];
+
+  assert_eq!(
+    exclusive_group_moves(&group_vec, "zinal", &["x1000c0s0b0n0"]),
+    vec![ExclusiveGroupMove {
+      xname: "x1000c0s0b0n0".to_string(),
+      exclusive_group: "tenants".to_string(),
+      from_group: "eiger".to_string(),
+      to_group: "zinal".to_string(),
+    }]
+  );
+  assert!(
+    exclusive_group_moves(&group_vec, "gpu", &["x1000c0s0b0n0"]).is_empty()
+  );
+  assert!(
+    exclusive_group_moves(&group_vec, "eiger", &["x1000c0s0b0n1"]).is_empty()
+  );
+}
+
 #[test]
 fn test_validate_groups_tenant() {
   let cfs_session_groups: Vec<String> = vec![
@@ -165,3 +238,34 @@ fn test_validate_groups_admin_2() {
     .is_empty()
   );
 }
+
+#[test]
+fn test_group_label_rules() {
+  use crate::{error::Error, hsm::group::types::GroupLabel};
+
+  assert_eq!(
+    GroupLabel::parse("Zinal_CTA").unwrap().as_str(),
+    "zinal_cta"
+  );
+  assert!(GroupLabel::parse("eiger-gpu.v2:a").is_ok());
+
+  for label in ["", "zinal cta", "zinal/cta", "-zinal", "zïnal"] {
+    assert!(
+      matches!(
+        GroupLabel::parse(label),
+        Err(Error::InvalidGroupLabel { .. })
+      ),
+      "'{label}' should be rejected"
+    );
+  }
+  assert!(GroupLabel::parse(&"a".repeat(GroupLabel::MAX_LEN)).is_ok());
+  assert!(GroupLabel::parse(&"a".repeat(GroupLabel::MAX_LEN + 1)).is_err());
+
+  assert!(GroupLabel::parse("ALPS").unwrap().is_reserved());
+  assert!(GroupLabel::for_new_group("prealps").is_err());
+  assert!(GroupLabel::for_new_group("zinal").is_ok());
+
+  let label: GroupLabel = serde_json::from_str("\"Zinal\"").unwrap();
+  assert_eq!(serde_json::to_string(&label).unwrap(), "\"zinal\"");
+  assert!(serde_json::from_str::<GroupLabel>("\"zinal cta\"").is_err());
+}
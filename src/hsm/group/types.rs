@@ -21,9 +21,16 @@
 //! `hsm_group_post_member(label, Member)` signature accepts it; on the
 //! wire it serialises to `{"id": "..."}` which is also what the generated
 //! `MemberId` produces. The wrapper translates between them.
+//!
+//! [`GroupLabel`] checks a label against the HSM label rules before it
+//! reaches CSM, so callers get a clear error instead of a 400.
+
+use std::{fmt, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
+use crate::{error::Error, hsm::group::hacks::SYSTEM_WIDE_HSM_GROUPS};
+
 pub use crate::hsm::generated::types::Group100 as Group;
 pub use crate::hsm::generated::types::Members100 as Members;
 pub use crate::hsm::generated::types::ResourceName;
@@ -40,3 +47,144 @@ pub struct Member {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub id: Option<String>,
 }
+
+/// A node taken out of an HSM group because it joined another group of
+/// the same exclusive group, where a node can only be in one group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExclusiveGroupMove {
+  /// Node moved.
+  pub xname: String,
+  /// Exclusive group both groups belong to.
+  pub exclusive_group: String,
+  /// Group the node was removed from.
+  pub from_group: String,
+  /// Group the node was added to.
+  pub to_group: String,
+}
+
+impl fmt::Display for ExclusiveGroupMove {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Node '{}' moved from HSM group '{}' to '{}' (exclusive group '{}')",
+      self.xname, self.from_group, self.to_group, self.exclusive_group
+    )
+  }
+}
+
+/// A valid HSM group label, lowercased.
+///
+/// HSM compares labels case-insensitively and stores them lowercased.
+/// A label has 1 to [`GroupLabel::MAX_LEN`] characters among ASCII
+/// letters, digits, `_`, `-`, `.` and `:`, and starts with a letter or
+/// a digit.
+#[derive(
+  Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct GroupLabel(String);
+
+impl GroupLabel {
+  /// Longest label accepted.
+  pub const MAX_LEN: usize = 255;
+
+  /// Check and lowercase `label`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::InvalidGroupLabel`] if `label` breaks the label
+  /// rules.
+  pub fn parse(label: &str) -> Result<Self, Error> {
+    let invalid = |reason: &str| Error::InvalidGroupLabel {
+      label: label.to_string(),
+      reason: reason.to_string(),
+    };
+
+    if label.is_empty() {
+      return Err(invalid("empty"));
+    }
+    if label.len() > Self::MAX_LEN {
+      return Err(invalid(&format!(
+        "longer than {} characters",
+        Self::MAX_LEN
+      )));
+    }
+    if let Some(c) = label.chars().find(|c| {
+      !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+    }) {
+      return Err(invalid(&format!("'{c}' not allowed")));
+    }
+    if !label.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+      return Err(invalid("must start with a letter or a digit"));
+    }
+
+    Ok(Self(label.to_ascii_lowercase()))
+  }
+
+  /// Check and lowercase the label of a group about to be created.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::InvalidGroupLabel`] if `label` breaks the label
+  /// rules or is reserved, see [`Self::is_reserved`].
+  pub fn for_new_group(label: &str) -> Result<Self, Error> {
+    let group_label = Self::parse(label)?;
+
+    if group_label.is_reserved() {
+      return Err(Error::InvalidGroupLabel {
+        label: label.to_string(),
+        reason: "reserved for site-wide groups".to_string(),
+      });
+    }
+
+    Ok(group_label)
+  }
+
+  /// Whether the label names one of the site-wide groups (`alps`, ...)
+  /// csm-rs leaves out of access control, see
+  /// [`crate::hsm::group::hacks`].
+  #[must_use]
+  pub fn is_reserved(&self) -> bool {
+    SYSTEM_WIDE_HSM_GROUPS.contains(&self.0.as_str())
+  }
+
+  /// The label.
+  #[must_use]
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+}
+
+impl FromStr for GroupLabel {
+  type Err = Error;
+
+  fn from_str(label: &str) -> Result<Self, Error> {
+    Self::parse(label)
+  }
+}
+
+impl TryFrom<String> for GroupLabel {
+  type Error = Error;
+
+  fn try_from(label: String) -> Result<Self, Error> {
+    Self::parse(&label)
+  }
+}
+
+impl From<GroupLabel> for String {
+  fn from(label: GroupLabel) -> Self {
+    label.0
+  }
+}
+
+impl From<GroupLabel> for ResourceName {
+  fn from(label: GroupLabel) -> Self {
+    ResourceName(label.0)
+  }
+}
+
+impl fmt::Display for GroupLabel {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
@@ -1,10 +1,12 @@
 //! Helpers built on top of `ShastaClient::hsm_group_*` methods.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use serde_json::Value;
 
 use crate::{
+  ExecutionContext,
+  common::{events, execution_context},
   error::Error,
   hsm::{
     self,
@@ -13,7 +15,7 @@ use crate::{
   node::utils::validate_xnames_format_and_membership_against_single_hsm,
 };
 
-use super::types::Member;
+use super::types::{ExclusiveGroupMove, Member};
 
 /// Return the full HSM groups visible to the caller — all groups for
 /// admins (`pa_admin` realm role), otherwise filtered to those named in
@@ -30,7 +32,7 @@ pub async fn get_group_available(
   shasta_root_cert: &[u8],
   socks5_proxy: Option<&str>,
 ) -> Result<Vec<Group>, Error> {
-  let mut group_vec = crate::ShastaClient::new(
+  let mut group_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -90,7 +92,7 @@ pub async fn get_group_name_available(
 
   if realm_access_role_vec.contains(&crate::hsm::group::hacks::PA_ADMIN.to_string()) {
     log::debug!("User is admin, getting all HSM groups in the system");
-    let all_hsm_groups = crate::ShastaClient::new(
+    let all_hsm_groups = crate::ShastaClient::scoped_or_new(
       shasta_base_url,
       shasta_root_cert.to_vec(),
       socks5_proxy.map(str::to_owned),
@@ -136,14 +138,269 @@ pub async fn get_group_name_available(
   }
 }
 
-/// Add a list of xnames to target HSM group
-/// Returns the new list of nodes in target HSM group
+/// HSM groups tagged `tag`.
+///
+/// Filters with `GET /smd/hsm/v2/groups?tag=…`, then keeps the groups
+/// actually carrying `tag`, in case CSM ignores the filter.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn get_groups_by_tag(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  tag: &str,
+) -> Result<Vec<Group>, Error> {
+  let mut group_vec = client
+    .hsm_group_get(shasta_token, None, Some(&[tag.to_string()]))
+    .await?;
+
+  group_vec.retain(|group| group.has_tag(tag));
+
+  Ok(group_vec)
+}
+
+/// Labels of the groups in `group_vec` carrying each tag of `tag_vec`,
+/// e.g. the groups of each environment for tags `prod` and `staging`.
+/// Tags no group carries map to an empty list.
+#[must_use]
+pub fn group_labels_by_tag(
+  group_vec: &[Group],
+  tag_vec: &[String],
+) -> BTreeMap<String, Vec<String>> {
+  tag_vec
+    .iter()
+    .map(|tag| {
+      let label_vec = group_vec
+        .iter()
+        .filter(|group| group.has_tag(tag))
+        .map(|group| group.label.0.clone())
+        .collect();
+      (tag.clone(), label_vec)
+    })
+    .collect()
+}
+
+/// Tag HSM group `group_label` with `tag_vec`, keeping its other tags.
+/// Returns the new tags of the group.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn add_tags(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  group_label: &str,
+  tag_vec: &[String],
+) -> Result<Vec<String>, Error> {
+  update_tags(client, shasta_token, group_label, |group_tag_vec| {
+    for tag in tag_vec {
+      if !group_tag_vec.contains(tag) {
+        group_tag_vec.push(tag.clone());
+      }
+    }
+  })
+  .await
+}
+
+/// Remove `tag_vec` from the tags of HSM group `group_label`. Returns
+/// the new tags of the group.
 ///
 /// # Errors
 ///
 /// Returns an [`Error`] variant on CSM, transport, or
 /// deserialization failure; see the crate-level `Error` enum
 /// for the full set.
+pub async fn remove_tags(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  group_label: &str,
+  tag_vec: &[String],
+) -> Result<Vec<String>, Error> {
+  update_tags(client, shasta_token, group_label, |group_tag_vec| {
+    group_tag_vec.retain(|tag| !tag_vec.contains(tag));
+  })
+  .await
+}
+
+/// Apply `update` to the tags of HSM group `group_label`, patching the
+/// group only if they changed.
+async fn update_tags(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  group_label: &str,
+  update: impl FnOnce(&mut Vec<String>),
+) -> Result<Vec<String>, Error> {
+  let group_tag_vec = client
+    .hsm_group_get_one(shasta_token, group_label)
+    .await?
+    .get_tags();
+
+  let mut new_group_tag_vec = group_tag_vec.clone();
+  update(&mut new_group_tag_vec);
+
+  if new_group_tag_vec != group_tag_vec {
+    client
+      .hsm_group_patch(
+        shasta_token,
+        group_label,
+        None,
+        Some(&new_group_tag_vec),
+      )
+      .await?;
+  }
+
+  Ok(new_group_tag_vec)
+}
+
+/// Nodes of `xname_vec` to take out of the other groups of the exclusive
+/// group of HSM group `target_group_label`, if it has one, before adding
+/// them to it. Groups not found in `group_vec` have no moves.
+#[must_use]
+pub fn exclusive_group_moves(
+  group_vec: &[Group],
+  target_group_label: &str,
+  xname_vec: &[&str],
+) -> Vec<ExclusiveGroupMove> {
+  let Some(exclusive_group) = group_vec
+    .iter()
+    .find(|group| group.label.0 == target_group_label)
+    .and_then(|group| group.exclusive_group.as_ref())
+  else {
+    return Vec::new();
+  };
+
+  group_vec
+    .iter()
+    .filter(|group| {
+      group.label.0 != target_group_label
+        && group.exclusive_group.as_ref() == Some(exclusive_group)
+    })
+    .flat_map(|group| {
+      let member_vec = group.get_members();
+      xname_vec
+        .iter()
+        .filter(move |xname| member_vec.iter().any(|member| member == *xname))
+        .map(move |xname| ExclusiveGroupMove {
+          xname: (*xname).to_string(),
+          exclusive_group: exclusive_group.0.clone(),
+          from_group: group.label.0.clone(),
+          to_group: target_group_label.to_string(),
+        })
+    })
+    .collect()
+}
+
+/// Take the nodes of `xname_vec` out of the other groups of the
+/// exclusive group of HSM group `target_group_label`, so CSM lets them
+/// join it. Each move is reported as an [`crate::Event::Info`] and
+/// returned; with `dryrun` nothing is changed. If a node can't be taken
+/// out of a group, the nodes already moved are put back, see
+/// [`restore_exclusive_group_moves`].
+///
+/// # Errors
+///
+/// Fails before changing anything if `execution_context` doesn't own
+/// one of the groups the nodes would be taken out of, see
+/// [`ExecutionContext::check_owns`]. Otherwise returns an [`Error`]
+/// variant on CSM, transport, or deserialization failure; see the
+/// crate-level `Error` enum for the full set.
+pub async fn enforce_exclusive_group(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  execution_context: &ExecutionContext,
+  target_group_label: &str,
+  xname_vec: &[&str],
+  dryrun: bool,
+) -> Result<Vec<ExclusiveGroupMove>, Error> {
+  let group_vec = client.hsm_group_get_all(shasta_token).await?;
+  let move_vec =
+    exclusive_group_moves(&group_vec, target_group_label, xname_vec);
+
+  for exclusive_group_move in &move_vec {
+    execution_context.check_owns(&exclusive_group_move.from_group)?;
+  }
+
+  for (index, exclusive_group_move) in move_vec.iter().enumerate() {
+    if !dryrun
+      && let Err(e) = client
+        .hsm_group_delete_member(
+          shasta_token,
+          &exclusive_group_move.from_group,
+          &exclusive_group_move.xname,
+        )
+        .await
+    {
+      restore_exclusive_group_moves(client, shasta_token, &move_vec[..index])
+        .await;
+      return Err(e);
+    }
+    events::info(exclusive_group_move.to_string());
+  }
+
+  Ok(move_vec)
+}
+
+/// Put the nodes of `move_vec` back in the groups
+/// [`enforce_exclusive_group`] took them out of, after they couldn't be
+/// added to the target group. Best effort: failures are reported as
+/// [`crate::Event::Warning`]s.
+pub async fn restore_exclusive_group_moves(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  move_vec: &[ExclusiveGroupMove],
+) {
+  for exclusive_group_move in move_vec {
+    let member = Member {
+      id: Some(exclusive_group_move.xname.clone()),
+    };
+
+    match client
+      .hsm_group_post_member(
+        shasta_token,
+        &exclusive_group_move.from_group,
+        member,
+      )
+      .await
+    {
+      Ok(_) => events::info(format!(
+        "Node '{}' put back in HSM group '{}'",
+        exclusive_group_move.xname, exclusive_group_move.from_group
+      )),
+      Err(e) => events::warning(format!(
+        "Could not put node '{}' back in HSM group '{}': {e}",
+        exclusive_group_move.xname, exclusive_group_move.from_group
+      )),
+    }
+  }
+}
+
+/// Context of the command running on the current task, or outside one,
+/// of the owner of `shasta_token` acting for themselves.
+fn caller_context(shasta_token: &str) -> Result<ExecutionContext, Error> {
+  match execution_context::current() {
+    Some(execution_context) => Ok(execution_context),
+    None => ExecutionContext::from_token(shasta_token),
+  }
+}
+
+/// Add a list of xnames to target HSM group
+/// Returns the new list of nodes in target HSM group, and the moves
+/// made to free the node.
+///
+/// If the group belongs to an exclusive group, the node is first taken
+/// out of the other groups of it, see [`enforce_exclusive_group`], and
+/// put back in them if it can't be added.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant if the caller doesn't own a group the
+/// node has to leave, or on CSM, transport, or deserialization failure;
+/// see the crate-level `Error` enum for the full set.
 pub async fn add_member(
   auth_token: &str,
   base_url: &str,
@@ -151,9 +408,9 @@ pub async fn add_member(
   socks5_proxy: Option<&str>,
   group_label: &str,
   new_member: &str,
-) -> Result<Vec<String>, Error> {
+) -> Result<(Vec<String>, Vec<ExclusiveGroupMove>), Error> {
   // Get HSM group from CSM
-  let shasta_client = crate::ShastaClient::new(
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     base_url,
     root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -164,6 +421,20 @@ pub async fn add_member(
 
   // Check if HSM group found
   if let Some(group) = group_vec.first().cloned().as_mut() {
+    let exclusive_group_move_vec = if group.exclusive_group.is_some() {
+      enforce_exclusive_group(
+        &shasta_client,
+        auth_token,
+        &caller_context(auth_token)?,
+        group_label,
+        &[new_member],
+        false,
+      )
+      .await?
+    } else {
+      Vec::new()
+    };
+
     // Update HSM group with new memebers
     // Create Member struct
     let new_member = new_member.to_string();
@@ -172,9 +443,18 @@ pub async fn add_member(
     };
 
     // Update HSM group in CSM
-    let _ = shasta_client
+    if let Err(e) = shasta_client
       .hsm_group_post_member(auth_token, group_label, member)
-      .await?;
+      .await
+    {
+      restore_exclusive_group_moves(
+        &shasta_client,
+        auth_token,
+        &exclusive_group_move_vec,
+      )
+      .await;
+      return Err(e);
+    }
 
     // Push the new id into the in-memory members list. The earlier
     // shape (`group.get_members().push(new_member)`) was a bug —
@@ -193,7 +473,7 @@ pub async fn add_member(
       .ids
       .push(crate::hsm::group::types::XNameRw100(new_member));
 
-    Ok(group.get_members())
+    Ok((group.get_members(), exclusive_group_move_vec))
   } else {
     Err(Error::GroupNotFound(group_label.to_string()))
   }
@@ -258,7 +538,7 @@ pub async fn remove_hsm_members(
 
     log::debug!("dry-run enabled, changes not persisted.");
   } else {
-    let shasta_client = crate::ShastaClient::new(
+    let shasta_client = crate::ShastaClient::scoped_or_new(
       shasta_base_url,
       shasta_root_cert.to_vec(),
       socks5_proxy.map(str::to_owned),
@@ -274,6 +554,16 @@ pub async fn remove_hsm_members(
 }
 
 /// Moves list of xnames from parent to target HSM group
+/// Returns the new members of the target and parent groups, and the
+/// moves made to free the nodes.
+///
+/// If the target group belongs to an exclusive group, the nodes are also
+/// taken out of the other groups of it, see [`enforce_exclusive_group`].
+/// Nodes that can't be added to the target group are put back in those
+/// groups.
+///
+/// The nodes are disabled in HSM while they change groups, see
+/// [`crate::hsm::component::with_disabled_for_service`].
 ///
 /// # Errors
 ///
@@ -290,7 +580,7 @@ pub async fn migrate_hsm_members(
   parent_hsm_group_name: &str,
   new_target_hsm_members: &[&str],
   dryrun: bool,
-) -> Result<(Vec<String>, Vec<String>), Error> {
+) -> Result<(Vec<String>, Vec<String>, Vec<ExclusiveGroupMove>), Error> {
   // Check nodes are valid xnames and they belong to parent HSM group
   if let Ok(false) = validate_xnames_format_and_membership_against_single_hsm(
     shasta_token,
@@ -343,31 +633,96 @@ pub async fn migrate_hsm_members(
   parent_hsm_group_member_vec.sort();
   parent_hsm_group_member_vec.dedup();
 
-  // *********************************************************************************************************
-  // UPDATE HSM GROUP MEMBERS IN CSM
-  if dryrun {
-  } else {
-    let shasta_client = crate::ShastaClient::new(
-      shasta_base_url,
-      shasta_root_cert.to_vec(),
-      socks5_proxy.map(str::to_owned),
-    )?;
-    for xname in new_target_hsm_members {
-      let member = Member {
-        id: Some(xname.to_string()),
-      };
+  let shasta_client = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
 
-      let _ = shasta_client
-        .hsm_group_post_member(shasta_token, target_hsm_group_name, member)
-        .await;
+  let execution_context = caller_context(shasta_token)?;
 
-      let _ = shasta_client
-        .hsm_group_delete_member(shasta_token, parent_hsm_group_name, xname)
-        .await;
+  let migration = async {
+    // Exclusive groups, possibly including the parent one, must let go of
+    // the nodes before the target group can take them
+    let exclusive_group_move_vec = enforce_exclusive_group(
+      &shasta_client,
+      shasta_token,
+      &execution_context,
+      target_hsm_group_name,
+      new_target_hsm_members,
+      dryrun,
+    )
+    .await?;
+
+    // *****************************************************************************************************
+    // UPDATE HSM GROUP MEMBERS IN CSM
+    if !dryrun {
+      for (index, xname) in new_target_hsm_members.iter().enumerate() {
+        let member = Member {
+          id: Some(xname.to_string()),
+        };
+
+        if let Err(e) = shasta_client
+          .hsm_group_post_member(shasta_token, target_hsm_group_name, member)
+          .await
+        {
+          // Give back the nodes not added yet, this one included
+          let pending_xname_vec = &new_target_hsm_members[index..];
+          let pending_move_vec: Vec<ExclusiveGroupMove> =
+            exclusive_group_move_vec
+              .iter()
+              .filter(|exclusive_group_move| {
+                pending_xname_vec.contains(&exclusive_group_move.xname.as_str())
+              })
+              .cloned()
+              .collect();
+          restore_exclusive_group_moves(
+            &shasta_client,
+            shasta_token,
+            &pending_move_vec,
+          )
+          .await;
+          return Err(e);
+        }
+
+        let moved_from_parent =
+          exclusive_group_move_vec.iter().any(|exclusive_group_move| {
+            exclusive_group_move.xname == *xname
+              && exclusive_group_move.from_group == parent_hsm_group_name
+          });
+        if !moved_from_parent {
+          shasta_client
+            .hsm_group_delete_member(shasta_token, parent_hsm_group_name, xname)
+            .await?;
+        }
+      }
     }
-  }
 
-  Ok((target_hsm_group_member_vec, parent_hsm_group_member_vec))
+    Ok::<_, Error>(exclusive_group_move_vec)
+  };
+
+  let exclusive_group_move_vec = if dryrun {
+    migration.await?
+  } else {
+    let xname_vec: Vec<String> = new_target_hsm_members
+      .iter()
+      .copied()
+      .map(str::to_string)
+      .collect();
+    crate::hsm::component::with_disabled_for_service(
+      &shasta_client,
+      shasta_token,
+      &xname_vec,
+      migration,
+    )
+    .await?
+  };
+
+  Ok((
+    target_hsm_group_member_vec,
+    parent_hsm_group_member_vec,
+    exclusive_group_move_vec,
+  ))
 }
 
 /// Receives 2 lists of xnames old xnames to remove from parent HSM group and new xhanges to add to target HSM group, and does just that
@@ -386,7 +741,7 @@ pub async fn update_hsm_group_members(
   old_target_hsm_group_members: &[&str],
   new_target_hsm_group_members: &[&str],
 ) -> Result<(), Error> {
-  let shasta_client = crate::ShastaClient::new(
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -431,7 +786,7 @@ pub async fn get_xname_map_and_filter_by_xname_vec(
   socks5_proxy: Option<&str>,
   xname_vec: Vec<&str>,
 ) -> Result<HashMap<String, Vec<String>>, Error> {
-  let hsm_group_vec = crate::ShastaClient::new(
+  let hsm_group_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -470,7 +825,7 @@ pub async fn get_hsm_map_and_filter_by_hsm_name_vec(
   socks5_proxy: Option<&str>,
   hsm_name_vec: &[&str],
 ) -> Result<HashMap<String, Vec<String>>, Error> {
-  let hsm_group_vec = crate::ShastaClient::new(
+  let hsm_group_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -499,7 +854,7 @@ pub async fn get_hsm_group_map_and_filter_by_hsm_group_member_vec(
   socks5_proxy: Option<&str>,
   member_vec: &[&str],
 ) -> Result<HashMap<String, Vec<String>>, Error> {
-  let hsm_group_vec = crate::ShastaClient::new(
+  let hsm_group_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -618,7 +973,7 @@ pub async fn get_member_vec_from_hsm_name_vec(
   log::debug!("Get xnames from HSM groups");
   log::debug!("Get xnames from HSM groups: {hsm_name_vec:?}");
 
-  let hsm_group_vec = crate::ShastaClient::new(
+  let hsm_group_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -716,7 +1071,7 @@ pub async fn get_members_for_groups_matching(
   socks5_proxy: Option<&str>,
   hsm_group_name_substring: &str,
 ) -> Result<Vec<GroupMembers>, Error> {
-  let hsm_group_value_vec = crate::ShastaClient::new(
+  let hsm_group_value_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -753,7 +1108,7 @@ pub async fn get_member_vec_from_hsm_group_name(
 ) -> Result<Vec<String>, Error> {
   // Take all nodes for all hsm_groups found and put them in a Vec
   Ok(
-    crate::ShastaClient::new(
+    crate::ShastaClient::scoped_or_new(
       shasta_base_url,
       shasta_root_cert.to_vec(),
       socks5_proxy.map(str::to_owned),
@@ -0,0 +1,57 @@
+//! Bidirectional `From` impls between csm-rs's HSM ethernet interface
+//! types and the dispatcher's mirrors. Gated behind the
+//! `manta-dispatcher` Cargo feature so users not on Manta don't pull the
+//! dispatcher dep.
+
+use manta_backend_dispatcher::types::hsm::inventory::{
+  ComponentEthernetInterface as FrontEndComponentEthernetInterface,
+  IpAddressMapping as FrontEndIpAddressMapping,
+};
+
+use super::types::{ComponentEthernetInterface, IpAddressMapping};
+
+impl From<FrontEndIpAddressMapping> for IpAddressMapping {
+  fn from(mapping: FrontEndIpAddressMapping) -> Self {
+    IpAddressMapping {
+      ip_address: mapping.ip_address,
+      network: mapping.network,
+    }
+  }
+}
+
+impl From<IpAddressMapping> for FrontEndIpAddressMapping {
+  fn from(val: IpAddressMapping) -> Self {
+    FrontEndIpAddressMapping {
+      ip_address: val.ip_address,
+      network: val.network,
+    }
+  }
+}
+
+impl From<FrontEndComponentEthernetInterface> for ComponentEthernetInterface {
+  fn from(interface: FrontEndComponentEthernetInterface) -> Self {
+    ComponentEthernetInterface {
+      description: interface.description,
+      ip_addresses: interface
+        .ip_addresses
+        .into_iter()
+        .map(std::convert::Into::into)
+        .collect(),
+      component_id: interface.component_id,
+    }
+  }
+}
+
+impl From<ComponentEthernetInterface> for FrontEndComponentEthernetInterface {
+  fn from(val: ComponentEthernetInterface) -> Self {
+    FrontEndComponentEthernetInterface {
+      description: val.description,
+      ip_addresses: val
+        .ip_addresses
+        .into_iter()
+        .map(std::convert::Into::into)
+        .collect(),
+      component_id: val.component_id,
+    }
+  }
+}
@@ -2,3 +2,10 @@
 //! `/smd/hsm/v2/Inventory/EthernetInterfaces`.
 
 pub mod types;
+pub mod utils;
+
+/// Bidirectional `From` impls between [`types`] and the dispatcher's
+/// HSM ethernet interface mirror types. Gated behind the
+/// `manta-dispatcher` Cargo feature.
+#[cfg(feature = "manta-dispatcher")]
+mod dispatcher_conv;
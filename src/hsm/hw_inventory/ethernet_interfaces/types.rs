@@ -99,3 +99,13 @@ pub struct EthernetInterface {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub r#type: Option<ComponentType>,
 }
+
+impl From<EthernetInterface> for ComponentEthernetInterface {
+  fn from(interface: EthernetInterface) -> Self {
+    ComponentEthernetInterface {
+      description: interface.description,
+      ip_addresses: interface.ip_addresses,
+      component_id: interface.component_id,
+    }
+  }
+}
@@ -0,0 +1,207 @@
+//! Per-node network report, to diagnose DHCP and boot problems.
+//!
+//! HSM learns a node's ethernet interfaces (MAC address, IP addresses,
+//! last time seen) from DHCP and discovery; SLS knows which network and
+//! subnet each address range belongs to. [`report`] joins both.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+  error::Error,
+  sls::{Network, network::utils::find_subnet},
+};
+
+use super::types::EthernetInterface;
+
+/// One IP address of an ethernet interface.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AddressReport {
+  /// IP address.
+  pub ip_address: String,
+  /// Network name recorded by HSM for the address, if any.
+  pub hsm_network: Option<String>,
+  /// SLS network whose subnets contain the address.
+  pub sls_network: Option<String>,
+  /// SLS subnet containing the address.
+  pub subnet: Option<String>,
+  /// VLAN of `subnet`.
+  pub vlan_id: Option<u16>,
+}
+
+/// One ethernet interface of a node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InterfaceReport {
+  /// HSM ethernet interface id.
+  pub id: Option<String>,
+  /// MAC address.
+  pub mac_address: String,
+  /// Interface description.
+  pub description: Option<String>,
+  /// Last time HSM updated the interface, e.g. on a DHCP request.
+  pub last_update: Option<String>,
+  /// IP addresses of the interface; empty if it never got one.
+  pub addresses: Vec<AddressReport>,
+}
+
+/// Ethernet interfaces of one node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeNetworkReport {
+  /// Node xname.
+  pub xname: String,
+  /// Interfaces of the node, sorted by MAC address; empty if HSM has
+  /// none.
+  pub interfaces: Vec<InterfaceReport>,
+}
+
+/// MAC address, IP addresses, network and last-seen time of every
+/// ethernet interface of the nodes in `xname_vec`.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn report(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  xname_vec: &[String],
+) -> Result<Vec<NodeNetworkReport>, Error> {
+  let (interface_vec, network_vec) = tokio::try_join!(
+    client.hsm_eth_get_all(shasta_token),
+    client.sls_network_get_all(shasta_token),
+  )?;
+
+  Ok(build_report(xname_vec, &interface_vec, &network_vec))
+}
+
+/// Pure part of [`report`], split out for testing.
+fn build_report(
+  xname_vec: &[String],
+  interface_vec: &[EthernetInterface],
+  network_vec: &[Network],
+) -> Vec<NodeNetworkReport> {
+  let mut interfaces_by_xname: HashMap<&str, Vec<&EthernetInterface>> =
+    HashMap::new();
+  for interface in interface_vec {
+    if let Some(xname) = interface.component_id.as_deref() {
+      interfaces_by_xname
+        .entry(xname)
+        .or_default()
+        .push(interface);
+    }
+  }
+
+  let mut report_vec: Vec<NodeNetworkReport> = xname_vec
+    .iter()
+    .map(|xname| {
+      let mut interfaces: Vec<InterfaceReport> = interfaces_by_xname
+        .get(xname.as_str())
+        .into_iter()
+        .flatten()
+        .map(|interface| InterfaceReport {
+          id: interface.id.clone(),
+          mac_address: interface.mac_address.clone(),
+          description: interface.description.clone(),
+          last_update: interface.last_update.clone(),
+          addresses: interface
+            .ip_addresses
+            .iter()
+            .map(|mapping| {
+              let subnet_opt = find_subnet(network_vec, &mapping.ip_address);
+              AddressReport {
+                ip_address: mapping.ip_address.clone(),
+                hsm_network: mapping
+                  .network
+                  .clone()
+                  .filter(|network| !network.is_empty()),
+                sls_network: subnet_opt
+                  .map(|(network, _)| network.name.clone()),
+                subnet: subnet_opt.map(|(_, subnet)| subnet.name.clone()),
+                vlan_id: subnet_opt.and_then(|(_, subnet)| subnet.vlan_id),
+              }
+            })
+            .collect(),
+        })
+        .collect();
+      interfaces.sort_by(|a, b| a.mac_address.cmp(&b.mac_address));
+
+      NodeNetworkReport {
+        xname: xname.clone(),
+        interfaces,
+      }
+    })
+    .collect();
+  report_vec.sort_by(|a, b| a.xname.cmp(&b.xname));
+
+  report_vec
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::{
+    hsm::hw_inventory::ethernet_interfaces::types::IpAddressMapping,
+    sls::network::types::{NetworkExtraProperties, Subnet},
+  };
+
+  #[test]
+  fn build_report_joins_hsm_and_sls() {
+    let interface_vec = vec![
+      EthernetInterface {
+        mac_address: "b4:2e:99:00:00:02".to_string(),
+        component_id: Some("x1000c0s0b0n0".to_string()),
+        ..Default::default()
+      },
+      EthernetInterface {
+        mac_address: "b4:2e:99:00:00:01".to_string(),
+        component_id: Some("x1000c0s0b0n0".to_string()),
+        last_update: Some("2024-05-01T10:00:00Z".to_string()),
+        ip_addresses: vec![IpAddressMapping {
+          ip_address: "10.252.1.12".to_string(),
+          network: None,
+        }],
+        ..Default::default()
+      },
+    ];
+    let network_vec = vec![Network {
+      name: "NMN".to_string(),
+      extra_properties: Some(NetworkExtraProperties {
+        subnets: vec![Subnet {
+          name: "bootstrap_dhcp".to_string(),
+          cidr: "10.252.1.0/24".to_string(),
+          vlan_id: Some(2),
+          ..Default::default()
+        }],
+        ..Default::default()
+      }),
+      ..Default::default()
+    }];
+
+    let report_vec = build_report(
+      &["x1000c0s1b0n0".to_string(), "x1000c0s0b0n0".to_string()],
+      &interface_vec,
+      &network_vec,
+    );
+
+    assert_eq!(report_vec[0].xname, "x1000c0s0b0n0");
+    assert_eq!(report_vec[0].interfaces.len(), 2);
+    assert_eq!(
+      report_vec[0].interfaces[0].addresses,
+      vec![AddressReport {
+        ip_address: "10.252.1.12".to_string(),
+        hsm_network: None,
+        sls_network: Some("NMN".to_string()),
+        subnet: Some("bootstrap_dhcp".to_string()),
+        vlan_id: Some(2),
+      }]
+    );
+    assert!(report_vec[0].interfaces[1].addresses.is_empty());
+
+    // Node unknown to HSM is still listed
+    assert_eq!(report_vec[1].xname, "x1000c0s1b0n0");
+    assert!(report_vec[1].interfaces.is_empty());
+  }
+}
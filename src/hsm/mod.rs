@@ -18,6 +18,8 @@
 //!   endpoints, ethernet interfaces.
 //! - [`service`] — service-discovery values (e.g. node roles) exposed
 //!   by HSM.
+//! - [`scn`] — State Change Notification subscriptions and a node state
+//!   change listener.
 //!
 //! ## How this module is built
 //!
@@ -53,6 +55,7 @@ pub mod component_status;
 pub mod group;
 pub mod hw_inventory;
 pub mod memberships;
+pub mod scn;
 pub mod service;
 pub(crate) mod generated;
 mod wrapper;
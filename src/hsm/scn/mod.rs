@@ -0,0 +1,263 @@
+//! HSM State Change Notifications (SCN) — get told when nodes change
+//! state instead of polling for it.
+//!
+//! Two ways to receive node state changes:
+//!
+//! - **Callbacks.** Create a subscription with
+//!   `ShastaClient::hsm_scn_subscription_post` and HSM POSTs an
+//!   [`types::ScnPayload`] to its URL on every matching change. Serving
+//!   that URL is up to the embedder's HTTP server; turn each body into
+//!   [`NodeStateChange`]s with [`types::ScnPayload::events`], and delete
+//!   the subscription with `ShastaClient::hsm_scn_subscription_delete`
+//!   on shutdown.
+//! - **Polling.** Where HSM can't reach the daemon, [`watch_node_state`]
+//!   polls `/State/Components` and yields the same events from the
+//!   differences between polls.
+//!
+//! The `ShastaClient` methods for `/smd/hsm/v2/Subscriptions/SCN` live
+//! in `crate::hsm::wrapper::scn`.
+
+pub mod types;
+
+use std::{collections::BTreeMap, collections::VecDeque, time::Duration};
+
+use futures::Stream;
+
+pub use types::NodeStateChange;
+use types::ScnPayload;
+
+use crate::{
+  ShastaClient,
+  error::Error,
+  hsm::component::{self, types::Component},
+};
+
+/// What [`watch_node_state`] compares between polls.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct NodeSnapshot {
+  state: Option<String>,
+  enabled: Option<bool>,
+  flag: Option<String>,
+}
+
+fn snapshot(component_vec: &[Component]) -> BTreeMap<String, NodeSnapshot> {
+  component_vec
+    .iter()
+    .filter_map(|component| {
+      let xname = component.id.as_ref()?.0.clone();
+      Some((
+        xname,
+        NodeSnapshot {
+          state: component.state.as_ref().map(ToString::to_string),
+          enabled: component.enabled,
+          flag: component.flag.as_ref().map(ToString::to_string),
+        },
+      ))
+    })
+    .collect()
+}
+
+/// Changes from `previous` to `current`, ordered by xname. Nodes that
+/// appear or disappear between polls aren't reported.
+fn diff(
+  previous: &BTreeMap<String, NodeSnapshot>,
+  current: &BTreeMap<String, NodeSnapshot>,
+) -> Vec<NodeStateChange> {
+  let mut event_vec = Vec::new();
+
+  for (xname, node) in current {
+    let Some(previous_node) = previous.get(xname) else {
+      continue;
+    };
+
+    if let Some(state) = &node.state
+      && node.state != previous_node.state
+    {
+      event_vec.push(NodeStateChange::State {
+        xname: xname.clone(),
+        previous: previous_node.state.clone(),
+        state: state.clone(),
+      });
+    }
+    if let Some(enabled) = node.enabled
+      && node.enabled != previous_node.enabled
+    {
+      event_vec.push(NodeStateChange::Enabled {
+        xname: xname.clone(),
+        enabled,
+      });
+    }
+    if let Some(flag) = &node.flag
+      && node.flag != previous_node.flag
+    {
+      event_vec.push(NodeStateChange::Flag {
+        xname: xname.clone(),
+        flag: flag.clone(),
+      });
+    }
+  }
+
+  event_vec
+}
+
+struct WatchState {
+  client: ShastaClient,
+  shasta_token: String,
+  xname_vec_opt: Option<Vec<String>>,
+  interval: Duration,
+  snapshot_opt: Option<BTreeMap<String, NodeSnapshot>>,
+  pending: VecDeque<NodeStateChange>,
+}
+
+impl WatchState {
+  async fn poll(&self) -> Result<BTreeMap<String, NodeSnapshot>, Error> {
+    let mut component_vec = self
+      .client
+      .hsm_component_get_all_nodes(&self.shasta_token, None)
+      .await?
+      .components;
+
+    if let Some(xname_vec) = &self.xname_vec_opt {
+      component::filter(&mut component_vec, xname_vec);
+    }
+
+    Ok(snapshot(&component_vec))
+  }
+}
+
+/// Poll HSM every `interval` and yield the state, enabled and flag
+/// changes of the nodes in `xname_vec_opt` (all nodes if `None`).
+///
+/// The first poll only records the nodes' current state, so the stream
+/// yields changes from the moment it's first polled. It never ends: a
+/// failed poll yields the [`Error`] and the next poll carries on from
+/// the last successful one, so drop the stream to stop watching.
+pub fn watch_node_state(
+  client: ShastaClient,
+  shasta_token: &str,
+  xname_vec_opt: Option<Vec<String>>,
+  interval: Duration,
+) -> impl Stream<Item = Result<NodeStateChange, Error>> + Send {
+  let state = WatchState {
+    client,
+    shasta_token: shasta_token.to_string(),
+    xname_vec_opt,
+    interval,
+    snapshot_opt: None,
+    pending: VecDeque::new(),
+  };
+
+  futures::stream::unfold(state, |mut state| async move {
+    loop {
+      if let Some(event) = state.pending.pop_front() {
+        return Some((Ok(event), state));
+      }
+
+      if state.snapshot_opt.is_some() {
+        tokio::time::sleep(state.interval).await;
+      }
+
+      let current = match state.poll().await {
+        Ok(current) => current,
+        Err(e) => {
+          if state.snapshot_opt.is_none() {
+            // Don't spin on a failing first poll.
+            tokio::time::sleep(state.interval).await;
+          }
+          return Some((Err(e), state));
+        }
+      };
+
+      if let Some(previous) = &state.snapshot_opt {
+        state.pending.extend(diff(previous, &current));
+      }
+      state.snapshot_opt = Some(current);
+    }
+  })
+}
+
+/// Parse the body of an SCN callback into node state changes; see
+/// [`ScnPayload::events`].
+///
+/// # Errors
+///
+/// Returns [`Error::SerdeJsonError`] if `body` isn't an SCN payload.
+pub fn parse_notification(body: &[u8]) -> Result<Vec<NodeStateChange>, Error> {
+  let payload: ScnPayload = serde_json::from_slice(body)?;
+  Ok(payload.events())
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn node(xname: &str, state: &str, enabled: bool, flag: &str) -> Component {
+    serde_json::from_value(json!({
+      "ID": xname,
+      "Type": "Node",
+      "State": state,
+      "Enabled": enabled,
+      "Flag": flag,
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn diff_reports_changed_fields_of_known_nodes() {
+    let previous = snapshot(&[
+      node("x1000c0s0b0n0", "Ready", true, "OK"),
+      node("x1000c0s0b0n1", "Ready", true, "OK"),
+    ]);
+    let current = snapshot(&[
+      node("x1000c0s0b0n0", "Off", false, "OK"),
+      node("x1000c0s0b0n1", "Ready", true, "Alert"),
+      node("x1000c0s1b0n0", "Ready", true, "OK"),
+    ]);
+
+    assert_eq!(
+      diff(&previous, &current),
+      [
+        NodeStateChange::State {
+          xname: "x1000c0s0b0n0".to_string(),
+          previous: Some("Ready".to_string()),
+          state: "Off".to_string(),
+        },
+        NodeStateChange::Enabled {
+          xname: "x1000c0s0b0n0".to_string(),
+          enabled: false,
+        },
+        NodeStateChange::Flag {
+          xname: "x1000c0s0b0n1".to_string(),
+          flag: "Alert".to_string(),
+        },
+      ]
+    );
+    assert!(diff(&current, &current).is_empty());
+  }
+
+  #[test]
+  fn parse_notification_splits_per_component() {
+    let body = json!({
+      "Components": ["x1000c0s0b0n0", "x1000c0s0b0n1"],
+      "State": "Off",
+    })
+    .to_string();
+
+    let event_vec = parse_notification(body.as_bytes()).unwrap();
+
+    assert_eq!(
+      event_vec
+        .iter()
+        .map(NodeStateChange::xname)
+        .collect::<Vec<_>>(),
+      ["x1000c0s0b0n0", "x1000c0s0b0n1"]
+    );
+    assert!(event_vec.iter().all(|event| matches!(
+      event,
+      NodeStateChange::State { previous: None, state, .. } if state == "Off"
+    )));
+    assert!(parse_notification(b"not json").is_err());
+  }
+}
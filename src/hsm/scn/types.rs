@@ -0,0 +1,175 @@
+//! Wire-format types for HSM State Change Notifications — mirror the
+//! upstream CSM `OpenAPI` schema (`Subscriptions_SCN*`) and the SCN
+//! payload HSM POSTs to subscribers.
+
+use serde::{Deserialize, Serialize};
+
+/// An SCN subscription: who gets notified, where, and about which
+/// changes. `id` is assigned by HSM and left out when creating one.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ScnSubscription {
+  /// Subscription id, as returned by HSM.
+  #[serde(rename = "ID")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub id: Option<String>,
+  /// Name of the subscriber, e.g. `scnfd@sms02.cray.com`.
+  #[serde(rename = "Subscriber")]
+  pub subscriber: String,
+  /// Notify about components being enabled or disabled.
+  #[serde(rename = "Enabled")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub enabled: Option<bool>,
+  /// Roles to be notified about role changes to.
+  #[serde(rename = "Roles")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub roles: Vec<String>,
+  /// Subroles to be notified about subrole changes to.
+  #[serde(rename = "SubRoles")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub sub_roles: Vec<String>,
+  /// Software statuses to be notified about changes to.
+  #[serde(rename = "SoftwareStatus")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub software_status: Vec<String>,
+  /// States (`Ready`, `Off`, …) to be notified about changes to.
+  #[serde(rename = "States")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub states: Vec<String>,
+  /// URL HSM POSTs the [`ScnPayload`]s to.
+  #[serde(rename = "Url")]
+  pub url: String,
+}
+
+impl ScnSubscription {
+  /// Subscription of `subscriber` to components entering one of
+  /// `states` or being enabled/disabled, delivered to `url`.
+  #[must_use]
+  pub fn node_state(subscriber: &str, url: &str, states: &[&str]) -> Self {
+    Self {
+      subscriber: subscriber.to_string(),
+      enabled: Some(true),
+      states: states.iter().map(|state| (*state).to_string()).collect(),
+      url: url.to_string(),
+      ..Default::default()
+    }
+  }
+}
+
+/// `GET /Subscriptions/SCN` response.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ScnSubscriptionArray {
+  /// Subscriptions currently held by HSM.
+  #[serde(rename = "SubscriptionList")]
+  #[serde(default)]
+  pub subscription_list: Vec<ScnSubscription>,
+}
+
+/// Body of the notification HSM POSTs to a subscription's URL: the
+/// components that changed and the field(s) they changed to.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ScnPayload {
+  /// Xnames of the components that changed.
+  #[serde(rename = "Components")]
+  #[serde(default)]
+  pub components: Vec<String>,
+  /// New `Enabled` value, if that's what changed.
+  #[serde(rename = "Enabled")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub enabled: Option<bool>,
+  /// New `Flag`, if that's what changed.
+  #[serde(rename = "Flag")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub flag: Option<String>,
+  /// New `Role`, if that's what changed.
+  #[serde(rename = "Role")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub role: Option<String>,
+  /// New `SubRole`, if that's what changed.
+  #[serde(rename = "SubRole")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sub_role: Option<String>,
+  /// New `SoftwareStatus`, if that's what changed.
+  #[serde(rename = "SoftwareStatus")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub software_status: Option<String>,
+  /// New `State`, if that's what changed.
+  #[serde(rename = "State")]
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub state: Option<String>,
+}
+
+/// A change to one node, as yielded by
+/// [`watch_node_state`](super::watch_node_state) or unpacked from an
+/// [`ScnPayload`] by [`ScnPayload::events`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeStateChange {
+  /// The node's `State` changed, e.g. from `Ready` to `Off`.
+  State {
+    /// Node xname.
+    xname: String,
+    /// Previous state; `None` when unknown (SCN callbacks).
+    previous: Option<String>,
+    /// New state.
+    state: String,
+  },
+  /// The node was enabled or disabled.
+  Enabled {
+    /// Node xname.
+    xname: String,
+    /// New `Enabled` value.
+    enabled: bool,
+  },
+  /// The node's `Flag` changed, e.g. from `OK` to `Alert`.
+  Flag {
+    /// Node xname.
+    xname: String,
+    /// New flag.
+    flag: String,
+  },
+}
+
+impl NodeStateChange {
+  /// Xname of the node that changed.
+  #[must_use]
+  pub fn xname(&self) -> &str {
+    match self {
+      Self::State { xname, .. }
+      | Self::Enabled { xname, .. }
+      | Self::Flag { xname, .. } => xname,
+    }
+  }
+}
+
+impl ScnPayload {
+  /// One [`NodeStateChange`] per component and changed field. Role,
+  /// subrole and software status changes aren't node state changes and
+  /// are left out.
+  #[must_use]
+  pub fn events(&self) -> Vec<NodeStateChange> {
+    let mut event_vec = Vec::new();
+
+    for xname in &self.components {
+      if let Some(state) = &self.state {
+        event_vec.push(NodeStateChange::State {
+          xname: xname.clone(),
+          previous: None,
+          state: state.clone(),
+        });
+      }
+      if let Some(enabled) = self.enabled {
+        event_vec.push(NodeStateChange::Enabled {
+          xname: xname.clone(),
+          enabled,
+        });
+      }
+      if let Some(flag) = &self.flag {
+        event_vec.push(NodeStateChange::Flag {
+          xname: xname.clone(),
+          flag: flag.clone(),
+        });
+      }
+    }
+
+    event_vec
+  }
+}
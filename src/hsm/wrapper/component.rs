@@ -37,6 +37,13 @@
 //!   fields as required and rejects the looser shape. Keeping these on
 //!   `handle_json_response` preserves both contracts in one move.
 //!
+//! - `hsm_component_patch_enabled`, `hsm_component_patch_flag` and
+//!   `hsm_component_patch_state` take `&[impl AsRef<str>]` xnames and a
+//!   plain flag string, in line with `hsm_component_get`; the generated
+//!   `doCompBulk*Patch` bodies want owned `XName100` ids and the closed
+//!   `HmsFlag100` enum. Errors follow the same HSM 401-RequestError +
+//!   JSON-CsmError contract as the POST/PUT methods above.
+//!
 //! The body types passed to these methods (`ComponentArrayPostArray`,
 //! `ComponentArrayPostQuery`, `ComponentArrayPostByNidQuery`,
 //! `ComponentPut`) are still the progenitor-generated structs (now
@@ -46,6 +53,7 @@
 
 use serde_json::Value;
 
+use crate::common::metrics::MeteredSend;
 use crate::{
   ShastaClient,
   common::http,
@@ -219,7 +227,7 @@ impl ShastaClient {
       .get(api_url)
       .query(&query_params)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_json_or_text_response(response).await
@@ -246,7 +254,12 @@ impl ShastaClient {
     let api_url =
       format!("{}/hsm/v2/State/Components/{}", self.base_url(), xname);
 
-    let response = self.http().get(api_url).bearer_auth(token).send().await?;
+    let response = self
+      .http()
+      .get(api_url)
+      .bearer_auth(token)
+      .send_metered()
+      .await?;
     http::handle_json_or_request_error(response, "GET").await
   }
 
@@ -269,7 +282,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&component)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_unit_or_request_error(response, "POST").await
@@ -295,7 +308,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&component)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_json_or_request_error::<ComponentArray>(response, "POST")
@@ -323,7 +336,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&component)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_json_or_request_error::<ComponentArray>(response, "POST")
@@ -351,7 +364,7 @@ impl ShastaClient {
       .put(api_url)
       .bearer_auth(token)
       .json(&component)
-      .send()
+      .send_metered()
       .await?;
 
     if !response.status().is_success() {
@@ -406,7 +419,7 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
     http::handle_json_response(response, "DELETE").await
@@ -428,9 +441,134 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
     http::handle_json_response(response, "DELETE").await
   }
+
+  /// `PATCH /smd/hsm/v2/State/Components/BulkEnabled` — set `Enabled`
+  /// on every component in `xnames`.
+  ///
+  /// Disabled components are ignored by HSM-driven monitoring and
+  /// state changes, so disabling nodes before servicing them keeps
+  /// them from raising alarms. A no-op when `xnames` is empty.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_component_patch_enabled(
+    &self,
+    token: &str,
+    xnames: &[impl AsRef<str>],
+    enabled: bool,
+  ) -> Result<(), Error> {
+    if xnames.is_empty() {
+      return Ok(());
+    }
+
+    log::debug!(
+      "Set HSM components Enabled={enabled} for {} xnames",
+      xnames.len()
+    );
+
+    let component_ids: Vec<&str> = xnames.iter().map(AsRef::as_ref).collect();
+    let payload = serde_json::json!({
+      "ComponentIDs": component_ids,
+      "Enabled": enabled,
+    });
+
+    let api_url = format!(
+      "{}/smd/hsm/v2/State/Components/BulkEnabled",
+      self.base_url()
+    );
+
+    let response = self
+      .http()
+      .patch(api_url)
+      .bearer_auth(token)
+      .json(&payload)
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    http::handle_unit_or_request_error(response, "PATCH").await
+  }
+
+  /// `PATCH /smd/hsm/v2/State/Components/BulkFlagOnly` — set `Flag`
+  /// (`OK`, `Warning`, `Alert`, `Locked` or `Unknown`) on every
+  /// component in `xnames`, leaving `State` untouched. A no-op when
+  /// `xnames` is empty.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_component_patch_flag(
+    &self,
+    token: &str,
+    xnames: &[impl AsRef<str>],
+    flag: &str,
+  ) -> Result<(), Error> {
+    if xnames.is_empty() {
+      return Ok(());
+    }
+
+    log::debug!("Set HSM components Flag={flag} for {} xnames", xnames.len());
+
+    let component_ids: Vec<&str> = xnames.iter().map(AsRef::as_ref).collect();
+    let payload = serde_json::json!({
+      "ComponentIDs": component_ids,
+      "Flag": flag,
+    });
+
+    let api_url = format!(
+      "{}/smd/hsm/v2/State/Components/BulkFlagOnly",
+      self.base_url()
+    );
+
+    let response = self
+      .http()
+      .patch(api_url)
+      .bearer_auth(token)
+      .json(&payload)
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    http::handle_unit_or_request_error(response, "PATCH").await
+  }
+
+  /// Bulk update the `Enabled` and/or `Flag` of the components in
+  /// `xnames`. `None` leaves the field as is.
+  ///
+  /// HSM has no single endpoint for both, so this calls
+  /// [`Self::hsm_component_patch_enabled`] then
+  /// [`Self::hsm_component_patch_flag`], stopping at the first error.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_component_patch_state(
+    &self,
+    token: &str,
+    xnames: &[impl AsRef<str>],
+    enabled: Option<bool>,
+    flag: Option<&str>,
+  ) -> Result<(), Error> {
+    if let Some(enabled) = enabled {
+      self
+        .hsm_component_patch_enabled(token, xnames, enabled)
+        .await?;
+    }
+    if let Some(flag) = flag {
+      self.hsm_component_patch_flag(token, xnames, flag).await?;
+    }
+    Ok(())
+  }
 }
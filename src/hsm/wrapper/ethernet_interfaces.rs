@@ -1,7 +1,7 @@
 //! Wrapper for `/Inventory/EthernetInterfaces`. Replaces
 //! `src/hsm/hw_inventory/ethernet_interfaces/http_client.rs`.
 //!
-//! **All four methods stay on raw `reqwest`.** Routing through the
+//! **All methods stay on raw `reqwest`.** Routing through the
 //! generated client would change either the on-wire URL or the public
 //! return type — neither is acceptable without a separate breaking-change
 //! PR. Per-method rationale:
@@ -20,6 +20,10 @@
 //!   and `do_comp_eth_interface_patch_v2` returns `()`. Routing through
 //!   either would change the public return type to a typed payload,
 //!   which is a public-API break we are explicitly avoiding here.
+//! - `hsm_eth_get_all`, `hsm_eth_get_one`, `hsm_eth_delete` and
+//!   `hsm_eth_delete_all` return the local `EthernetInterface` /
+//!   `serde_json::Value` shapes used by the rest of this file rather
+//!   than the generated `CompEthInterface100`.
 //!
 //! BEHAVIOUR DELTA (from Task 11): the hand-written `EthernetInterface`
 //! and (to a lesser extent) `IpAddressMapping` / `ComponentEthernetInterface`
@@ -32,8 +36,12 @@
 //! shapes. Callers that previously serialised the broken snake_case
 //! variants will see their JSON change to spec-conformant PascalCase.
 
+use serde_json::Value;
+
+use crate::common::metrics::MeteredSend;
 use crate::{
   ShastaClient,
+  common::http,
   error::Error,
   hsm::hw_inventory::ethernet_interfaces::types::{
     ComponentEthernetInterface, EthernetInterface, IpAddressMapping,
@@ -62,7 +70,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&eht_interface)
-      .send()
+      .send_metered()
       .await?;
 
     if let Err(e) = response.error_for_status_ref() {
@@ -113,7 +121,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&eht_interface)
-      .send()
+      .send_metered()
       .await?;
 
     if let Err(e) = response.error_for_status_ref() {
@@ -171,7 +179,7 @@ impl ShastaClient {
         ("NewerThan", newer_than),
       ])
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await?
       .error_for_status()
       .map_err(Error::NetError)
@@ -217,10 +225,107 @@ impl ShastaClient {
       .query(&[("ethInterfaceID", ip_address), ("ipAddress", ip_address)])
       .bearer_auth(token)
       .json(&cei)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?
       .error_for_status()
       .map_err(Error::NetError)
   }
+
+  /// `GET /smd/hsm/v2/Inventory/EthernetInterfaces` — every ethernet
+  /// interface known to HSM.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_eth_get_all(
+    &self,
+    token: &str,
+  ) -> Result<Vec<EthernetInterface>, Error> {
+    let api_url = format!(
+      "{}/smd/hsm/v2/Inventory/EthernetInterfaces",
+      self.base_url()
+    );
+
+    http::get_json(self.http(), &api_url, token).await
+  }
+
+  /// `GET /smd/hsm/v2/Inventory/EthernetInterfaces/{id}` — one ethernet
+  /// interface.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_eth_get_one(
+    &self,
+    token: &str,
+    eth_interface_id: &str,
+  ) -> Result<EthernetInterface, Error> {
+    let api_url = format!(
+      "{}/smd/hsm/v2/Inventory/EthernetInterfaces/{}",
+      self.base_url(),
+      eth_interface_id
+    );
+
+    http::get_json(self.http(), &api_url, token).await
+  }
+
+  /// `DELETE /smd/hsm/v2/Inventory/EthernetInterfaces/{id}` — remove
+  /// one ethernet interface.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_eth_delete(
+    &self,
+    token: &str,
+    eth_interface_id: &str,
+  ) -> Result<Value, Error> {
+    let api_url = format!(
+      "{}/smd/hsm/v2/Inventory/EthernetInterfaces/{}",
+      self.base_url(),
+      eth_interface_id
+    );
+
+    let response = self
+      .http()
+      .delete(api_url)
+      .bearer_auth(token)
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    http::handle_json_response(response, "DELETE").await
+  }
+
+  /// `DELETE /smd/hsm/v2/Inventory/EthernetInterfaces` — remove every
+  /// ethernet interface.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_eth_delete_all(&self, token: &str) -> Result<Value, Error> {
+    let api_url = format!(
+      "{}/smd/hsm/v2/Inventory/EthernetInterfaces",
+      self.base_url()
+    );
+
+    let response = self
+      .http()
+      .delete(api_url)
+      .bearer_auth(token)
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    http::handle_json_response(response, "DELETE").await
+  }
 }
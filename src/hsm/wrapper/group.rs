@@ -29,16 +29,20 @@
 //! - `hsm_group_delete_member` accepts `204 No Content` from
 //!   production CSM; the generated `do_group_member_delete` is
 //!   200-only.
+//! - `hsm_group_patch` takes the description and tags as plain
+//!   optional arguments and, like `hsm_group_delete_member`, accepts
+//!   the `204 No Content` CSM returns on success.
 //! - `hsm_group_get_hsm_group_vec` and `hsm_group_create_new_group`
 //!   are convenience wrappers built on top of the above, not endpoint
 //!   bindings of their own.
 
+use crate::common::metrics::MeteredSend;
 use crate::{
   ShastaClient,
-  common::http,
+  common::{http, webhooks},
   error::Error,
   hsm::{
-    group::types::{Group, Member, Members, XNameRw100},
+    group::types::{Group, GroupLabel, Member, Members, XNameRw100},
     types::HsmActionResponse,
   },
 };
@@ -72,7 +76,7 @@ impl ShastaClient {
       .http()
       .get(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)
   }
@@ -101,7 +105,12 @@ impl ShastaClient {
   ) -> Result<Group, Error> {
     let api_url = format!("{}/smd/hsm/v2/groups/{}", self.base_url(), label);
 
-    let response = self.http().get(api_url).bearer_auth(token).send().await?;
+    let response = self
+      .http()
+      .get(api_url)
+      .bearer_auth(token)
+      .send_metered()
+      .await?;
     http::handle_json_or_request_error_text::<Group>(response, "GET").await
   }
 
@@ -144,7 +153,7 @@ impl ShastaClient {
       .get(api_url)
       .query(query.as_slice())
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await?;
     http::handle_json_or_request_error_text::<Vec<Group>>(response, "GET")
       .await
@@ -231,7 +240,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&group)
-      .send()
+      .send_metered()
       .await?;
 
     log::debug!("Response:\n{response:#?}");
@@ -256,20 +265,36 @@ impl ShastaClient {
       }
     }
 
-    response.text().await.map_err(Error::NetError)
+    let body = response.text().await.map_err(Error::NetError)?;
+
+    webhooks::notify(
+      self,
+      webhooks::Operation::GroupCreated {
+        group: group.label.0,
+        members: group
+          .members
+          .map(|members| members.ids.into_iter().map(|id| id.0).collect())
+          .unwrap_or_default(),
+      },
+    )
+    .await;
+
+    Ok(body)
   }
 
   /// Build a [`Group`] from its individual fields and create it via
   /// [`Self::hsm_group_post`].
   ///
   /// Returns the constructed [`Group`] regardless of the response body
-  /// shape; success of the underlying POST is logged.
+  /// shape; success of the underlying POST is logged. The label is
+  /// lowercased, see [`GroupLabel`].
   ///
   /// # Errors
   ///
-  /// Returns an [`Error`] variant on CSM, transport, or
-  /// deserialization failure; see the crate-level `Error` enum
-  /// for the full set.
+  /// Returns [`Error::InvalidGroupLabel`] before contacting CSM if
+  /// `hsm_group_name_opt` isn't a valid label for a new group, or an
+  /// [`Error`] variant on CSM, transport, or deserialization failure;
+  /// see the crate-level `Error` enum for the full set.
   pub async fn hsm_group_create_new_group(
     &self,
     token: &str,
@@ -279,14 +304,14 @@ impl ShastaClient {
     description: &str,
     tags: &[String],
   ) -> Result<Group, Error> {
+    let label = GroupLabel::for_new_group(hsm_group_name_opt)?;
+
     let myxnames = Members {
       ids: xnames.iter().map(|x| XNameRw100(x.clone())).collect(),
     };
 
     let group = Group {
-      label: crate::hsm::group::types::ResourceName(
-        hsm_group_name_opt.to_owned(),
-      ),
+      label: label.into(),
       description: Some(description.to_string()),
       tags: tags
         .iter()
@@ -335,10 +360,21 @@ impl ShastaClient {
       .http()
       .delete(url_api)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
-    http::handle_json_response(response, "DELETE").await
+    let action_response =
+      http::handle_json_response(response, "DELETE").await?;
+
+    webhooks::notify(
+      self,
+      webhooks::Operation::GroupDeleted {
+        group: hsm_group_name.to_string(),
+      },
+    )
+    .await;
+
+    Ok(action_response)
   }
 
   /// Add a member (component xname) to an HSM group.
@@ -373,10 +409,21 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&member)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
-    http::handle_json_response(response, "POST").await
+    let action_response = http::handle_json_response(response, "POST").await?;
+
+    webhooks::notify(
+      self,
+      webhooks::Operation::GroupMembersAdded {
+        group: hsm_group_name.to_string(),
+        xnames: member.id.into_iter().collect(),
+      },
+    )
+    .await;
+
+    Ok(action_response)
   }
 
   /// Remove a member (component xname) from an HSM group.
@@ -412,11 +459,20 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
 
     if response.status().is_success() {
+      webhooks::notify(
+        self,
+        webhooks::Operation::GroupMembersRemoved {
+          group: hsm_group_name.to_string(),
+          xnames: vec![member_id.to_string()],
+        },
+      )
+      .await;
+
       Ok(())
     } else {
       let status = response.status().as_u16();
@@ -427,4 +483,57 @@ impl ShastaClient {
       ))
     }
   }
+
+  /// Update the description and/or tags of an HSM group.
+  ///
+  /// `PATCH /smd/hsm/v2/groups/{hsm_group_name}`. `None` leaves the
+  /// field as is. Members can't be changed this way; use
+  /// [`Self::hsm_group_post_member`] and
+  /// [`Self::hsm_group_delete_member`].
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_group_patch(
+    &self,
+    token: &str,
+    hsm_group_name: &str,
+    description: Option<&str>,
+    tags: Option<&[String]>,
+  ) -> Result<(), Error> {
+    log::debug!("Update HSM group '{hsm_group_name}'");
+
+    let mut payload = serde_json::Map::new();
+    if let Some(description) = description {
+      payload.insert("description".to_string(), description.into());
+    }
+    if let Some(tags) = tags {
+      payload.insert("tags".to_string(), tags.into());
+    }
+
+    let api_url =
+      format!("{}/smd/hsm/v2/groups/{}", self.base_url(), hsm_group_name);
+
+    let response = self
+      .http()
+      .patch(api_url)
+      .bearer_auth(token)
+      .json(&payload)
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    if response.status().is_success() {
+      Ok(())
+    } else {
+      let status = response.status().as_u16();
+      let url = response.url().to_string();
+      let payload = response.text().await.map_err(Error::NetError)?;
+      Err(Error::csm_text_from_response(
+        "PATCH", &url, status, payload,
+      ))
+    }
+  }
 }
@@ -56,6 +56,7 @@
 
 use serde_json::Value;
 
+use crate::common::metrics::MeteredSend;
 use crate::{
   ShastaClient,
   common::http,
@@ -88,7 +89,7 @@ impl ShastaClient {
         .http()
         .get(api_url)
         .bearer_auth(token)
-        .send()
+        .send_metered()
         .await
         .map_err(Error::NetError)?,
     )
@@ -144,7 +145,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&hw_inventory_by_location)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?;
     http::handle_json_response(response, "POST").await
@@ -1,8 +1,8 @@
 //! Wrapper for `/memberships`. Replaces `src/hsm/memberships/http_client.rs`.
 //!
 //! Maintains the historical public method names (`hsm_memberships_get_all`,
-//! `hsm_memberships_get_xname`) that callers (e.g. `src/node/utils.rs`) and
-//! the integration tests in `tests/shasta_client_hsm.rs` rely on. The
+//! `hsm_memberships_get_xname`) that callers and the integration tests in
+//! `tests/shasta_client_hsm.rs` rely on. The
 //! generated `do_memberships_get` returns `Vec<Membership100>` straight
 //! (no wrapper struct), so the `run` adapter unwraps directly to the
 //! public return type `Vec<Membership>` via the
@@ -12,6 +12,8 @@
 //! historical API took none, so all 16 are passed as `None` to preserve
 //! the "get every membership record" semantics.
 
+use std::collections::HashSet;
+
 use crate::{ShastaClient, error::Error, hsm::memberships::types::Membership};
 
 use super::run;
@@ -38,6 +40,34 @@ impl ShastaClient {
     .await
   }
 
+  /// `GET /smd/hsm/v2/memberships`, keeping only the records of
+  /// `xname_vec`. One request regardless of the number of xnames, where
+  /// [`Self::hsm_memberships_get_xname`] needs one per node.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_memberships_get_all_filtered(
+    &self,
+    token: &str,
+    xname_vec: &[impl AsRef<str>],
+  ) -> Result<Vec<Membership>, Error> {
+    let xname_set: HashSet<&str> =
+      xname_vec.iter().map(AsRef::as_ref).collect();
+
+    let mut membership_vec = self.hsm_memberships_get_all(token).await?;
+    membership_vec.retain(|membership| {
+      membership
+        .id
+        .as_ref()
+        .is_some_and(|xname| xname_set.contains(xname.0.as_str()))
+    });
+
+    Ok(membership_vec)
+  }
+
   /// `GET /smd/hsm/v2/memberships/{xname}` — membership record for a
   /// single component.
   ///
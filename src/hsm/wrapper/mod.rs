@@ -8,7 +8,7 @@
 //! `impl ShastaClient { pub async fn hsm_*() }` blocks that delegate
 //! to the generated client.
 
-use crate::{ShastaClient, error::Error, hsm::generated};
+use crate::{ShastaClient, common::metrics, error::Error, hsm::generated};
 
 mod component;
 mod component_status;
@@ -18,6 +18,7 @@ mod hw_component;
 pub(crate) mod hw_component_types;
 mod memberships;
 mod redfish_endpoint;
+mod scn;
 mod service_values;
 
 /// Build a generated HSM `Client` bound to the caller's token.
@@ -38,11 +39,8 @@ pub(crate) fn gen_client(
   client: &ShastaClient,
   token: &str,
 ) -> Result<generated::Client, Error> {
-  let inner = crate::common::http::build_client_with_auth(
-    client.root_cert(),
-    client.socks5_proxy(),
-    Some(token),
-  )?;
+  let inner =
+    crate::common::http::build_client_with_auth(client.config(), Some(token))?;
   // Override spec basePath: csm-rs's `base_url` already ends in `/apis`.
   let baseurl = format!("{}/smd/hsm/v2", client.base_url());
   Ok(generated::Client::new_with_client(&baseurl, inner))
@@ -118,14 +116,16 @@ pub(crate) async fn map_err<E: std::fmt::Debug>(
 /// `map_err`. `op` receives the generated `Client` by value (it is
 /// cheap to construct per call) and returns a future producing a
 /// `progenitor_client::ResponseValue<T>`; on success the inner value
-/// is unwrapped, on error `map_err` is awaited.
+/// is unwrapped, on error `map_err` is awaited. `op` is called once
+/// more with a new token if CSM rejects the first one, see
+/// [`metrics::send_generated`].
 pub(crate) async fn run<F, Fut, T, E>(
   client: &ShastaClient,
   token: &str,
   op: F,
 ) -> Result<T, Error>
 where
-  F: FnOnce(generated::Client) -> Fut,
+  F: Fn(generated::Client) -> Fut,
   Fut: std::future::Future<
       Output = Result<
         progenitor_client::ResponseValue<T>,
@@ -134,8 +134,7 @@ where
     >,
   E: std::fmt::Debug,
 {
-  let gc = gen_client(client, token)?;
-  match op(gc).await {
+  match metrics::send_generated(client, token, "smd", gen_client, op).await? {
     Ok(rv) => Ok(rv.into_inner()),
     Err(e) => Err(map_err(e).await),
   }
@@ -49,6 +49,7 @@
 //!   `InvalidResponsePayload`. The historical lenient parse keeps
 //!   downstream callers + mocks portable.
 
+use crate::common::metrics::MeteredSend;
 use crate::{
   ShastaClient,
   common::http,
@@ -87,7 +88,7 @@ impl ShastaClient {
       .get(api_url)
       .query(&[xname])
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_json_or_request_error(response, "GET").await
@@ -122,7 +123,7 @@ impl ShastaClient {
       .get(api_url)
       .query(&[id, fqdn, r#type, uuid, macaddr, ip_address, last_status])
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_json_or_request_error(response, "GET").await
@@ -148,7 +149,12 @@ impl ShastaClient {
       xname
     );
 
-    let response = self.http().get(api_url).bearer_auth(token).send().await?;
+    let response = self
+      .http()
+      .get(api_url)
+      .bearer_auth(token)
+      .send_metered()
+      .await?;
     http::handle_json_or_request_error(response, "GET").await
   }
 
@@ -175,7 +181,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&redfish_endpoint)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_json_or_request_error(response, "POST").await
@@ -207,7 +213,7 @@ impl ShastaClient {
       .put(api_url)
       .bearer_auth(token)
       .json(&redfish_endpoint)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_json_or_request_error(response, "PUT").await
@@ -232,7 +238,7 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_json_or_request_error(response, "DELETE").await
@@ -261,7 +267,7 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await?;
 
     http::handle_json_or_request_error(response, "DELETE").await
@@ -0,0 +1,107 @@
+//! Wrapper for `/Subscriptions/SCN`.
+//!
+//! **All methods stay on raw `reqwest`.** The SCN endpoints are tagged
+//! `cli_ignore` upstream and the subscription types are hand-rolled in
+//! `crate::hsm::scn::types` with `Vec` role/state lists rather than the
+//! generated `HmsRole100` / `HmsState100` wrappers, so callers can pass
+//! plain strings. Errors follow the HSM 401-RequestError +
+//! JSON-CsmError contract.
+
+use crate::common::metrics::MeteredSend;
+use crate::{
+  ShastaClient,
+  common::http,
+  error::Error,
+  hsm::scn::types::{ScnSubscription, ScnSubscriptionArray},
+};
+
+impl ShastaClient {
+  /// `POST /smd/hsm/v2/Subscriptions/SCN` — create a State Change
+  /// Notification subscription. Returns it with its HSM-assigned id.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure (HSM answers 409 if `subscriber` already
+  /// has a subscription for the same URL); see the crate-level `Error`
+  /// enum for the full set.
+  pub async fn hsm_scn_subscription_post(
+    &self,
+    token: &str,
+    subscription: &ScnSubscription,
+  ) -> Result<ScnSubscription, Error> {
+    log::debug!(
+      "Create HSM SCN subscription for '{}' to '{}'",
+      subscription.subscriber,
+      subscription.url
+    );
+
+    let api_url = format!("{}/smd/hsm/v2/Subscriptions/SCN", self.base_url());
+
+    let response = self
+      .http()
+      .post(api_url)
+      .bearer_auth(token)
+      .json(subscription)
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    http::handle_json_or_request_error(response, "POST").await
+  }
+
+  /// `GET /smd/hsm/v2/Subscriptions/SCN` — list the SCN subscriptions.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_scn_subscription_get_all(
+    &self,
+    token: &str,
+  ) -> Result<Vec<ScnSubscription>, Error> {
+    let api_url = format!("{}/smd/hsm/v2/Subscriptions/SCN", self.base_url());
+
+    let response = self
+      .http()
+      .get(api_url)
+      .bearer_auth(token)
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    http::handle_json_or_request_error::<ScnSubscriptionArray>(response, "GET")
+      .await
+      .map(|subscription_array| subscription_array.subscription_list)
+  }
+
+  /// `DELETE /smd/hsm/v2/Subscriptions/SCN/{id}` — delete an SCN
+  /// subscription.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn hsm_scn_subscription_delete(
+    &self,
+    token: &str,
+    id: &str,
+  ) -> Result<(), Error> {
+    log::debug!("Delete HSM SCN subscription '{id}'");
+
+    let api_url =
+      format!("{}/smd/hsm/v2/Subscriptions/SCN/{}", self.base_url(), id);
+
+    let response = self
+      .http()
+      .delete(api_url)
+      .bearer_auth(token)
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    http::handle_unit_or_request_error(response, "DELETE").await
+  }
+}
@@ -12,6 +12,7 @@ use serde_json::Value;
 
 use types::{Image, PatchImage};
 
+use crate::common::metrics::MeteredSend;
 use crate::{ShastaClient, error::Error};
 
 impl ShastaClient {
@@ -43,7 +44,7 @@ impl ShastaClient {
       .http()
       .get(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?
       .error_for_status()
@@ -102,7 +103,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&ims_image)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?
       .error_for_status()
@@ -140,7 +141,7 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?
       .error_for_status()
@@ -154,7 +155,7 @@ impl ShastaClient {
       .http()
       .delete(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?
       .error_for_status()
@@ -182,7 +183,7 @@ impl ShastaClient {
       .patch(api_url)
       .bearer_auth(token)
       .json(&ims_link)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?
       .error_for_status()
@@ -193,4 +194,38 @@ impl ShastaClient {
 
     Ok(())
   }
+
+  /// Set the labels in `label_vec` on IMS image `image_id`, keeping its
+  /// other labels and metadata (see [`crate::ims::image::labels`]).
+  /// Returns the updated image.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::ImageNotFound`] if the image doesn't exist, or an
+  /// [`Error`] variant on CSM, transport, or deserialization failure;
+  /// see the crate-level `Error` enum for the full set.
+  pub async fn ims_image_add_labels(
+    &self,
+    token: &str,
+    image_id: &str,
+    label_vec: &[(&str, &str)],
+  ) -> Result<Image, Error> {
+    log::debug!("Label IMS image '{image_id}' with {label_vec:?}");
+
+    let image = self
+      .ims_image_get(token, Some(image_id))
+      .await?
+      .into_iter()
+      .next()
+      .ok_or_else(|| Error::ImageNotFound(image_id.to_string()))?
+      .with_labels(label_vec.iter().copied());
+
+    let patch = PatchImage {
+      metadata: image.metadata.clone(),
+      ..Default::default()
+    };
+    self.ims_image_patch(token, image_id, &patch).await?;
+
+    Ok(image)
+  }
 }
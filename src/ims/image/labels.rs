@@ -0,0 +1,161 @@
+//! Labels on IMS images, stored in the image metadata.
+//!
+//! IMS v3 keeps a free-form string map on every image. Labels are
+//! entries of that map, e.g. `purpose=compute` or `built_by=csm-rs`:
+//! set them when creating an image with [`Image::with_labels`], add
+//! them to an existing one with `ShastaClient::ims_image_add_labels`,
+//! and select images by label with [`crate::filter::label`] or a
+//! selector parsed by [`parse_selector`].
+//!
+//! SAT builds label the images they produce with [`SAT_REF_NAME`],
+//! [`CONFIGURATION`] and [`BUILT_BY`].
+
+use std::collections::HashMap;
+
+use crate::{
+  error::Error,
+  filter::{self, Filter},
+  ims::image::http_client::types::Image,
+};
+
+/// Label holding the `ref_name` of the SAT file image entry that built
+/// the image.
+pub const SAT_REF_NAME: &str = "sat_ref_name";
+
+/// Label holding the CFS configuration the image was built with.
+pub const CONFIGURATION: &str = "configuration";
+
+/// Label holding the tool that built the image.
+pub const BUILT_BY: &str = "built_by";
+
+/// Label holding what the image is for, e.g. `compute` or `uan`.
+pub const PURPOSE: &str = "purpose";
+
+/// [`BUILT_BY`] value of the images csm-rs builds.
+pub const BUILT_BY_CSM_RS: &str = "csm-rs";
+
+impl Image {
+  /// Value of label `key`, if set.
+  #[must_use]
+  pub fn label(&self, key: &str) -> Option<&str> {
+    self
+      .metadata
+      .as_ref()
+      .and_then(|metadata| metadata.get(key))
+      .map(String::as_str)
+  }
+
+  /// Set label `key` to `value`, replacing any previous value.
+  pub fn set_label(
+    &mut self,
+    key: impl Into<String>,
+    value: impl Into<String>,
+  ) {
+    self
+      .metadata
+      .get_or_insert_with(HashMap::new)
+      .insert(key.into(), value.into());
+  }
+
+  /// The image with `label_vec` set, for [`crate::ShastaClient::ims_image_post`].
+  #[must_use]
+  pub fn with_labels<K, V>(
+    mut self,
+    label_vec: impl IntoIterator<Item = (K, V)>,
+  ) -> Self
+  where
+    K: Into<String>,
+    V: Into<String>,
+  {
+    for (key, value) in label_vec {
+      self.set_label(key, value);
+    }
+    self
+  }
+}
+
+/// Parse a label selector: comma separated `key=value` (label set to
+/// value) and `key` (label set) requirements, all of which must match.
+///
+/// # Errors
+///
+/// Returns [`Error::Message`] if a requirement has an empty key.
+pub fn parse_selector(selector: &str) -> Result<Filter, Error> {
+  selector
+    .split(',')
+    .map(str::trim)
+    .filter(|requirement| !requirement.is_empty())
+    .try_fold(Filter::All, |filter, requirement| {
+      let (key, value_opt) = match requirement.split_once('=') {
+        Some((key, value)) => (key.trim(), Some(value.trim())),
+        None => (requirement, None),
+      };
+
+      if key.is_empty() {
+        return Err(Error::Message(format!(
+          "Invalid label selector '{selector}': empty label key"
+        )));
+      }
+
+      let requirement_filter = match value_opt {
+        Some(value) => filter::label(key, value),
+        None => filter::has_label(key),
+      };
+
+      Ok(Filter::and(filter, requirement_filter))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::filter::Query;
+
+  #[test]
+  fn labels_are_image_metadata() {
+    let mut image = Image::default().with_labels([(PURPOSE, "compute")]);
+    image.set_label(BUILT_BY, BUILT_BY_CSM_RS);
+
+    assert_eq!(image.label(PURPOSE), Some("compute"));
+    assert_eq!(
+      image
+        .metadata
+        .as_ref()
+        .unwrap()
+        .get(BUILT_BY)
+        .map(String::as_str),
+      Some(BUILT_BY_CSM_RS)
+    );
+    assert_eq!(image.label(SAT_REF_NAME), None);
+  }
+
+  #[test]
+  fn selector_keeps_images_with_every_label() {
+    let image = |name: &str, purpose: &str| {
+      Image {
+        name: name.to_string(),
+        ..Default::default()
+      }
+      .with_labels([(PURPOSE, purpose), (BUILT_BY, BUILT_BY_CSM_RS)])
+    };
+
+    let mut image_vec = vec![
+      image("compute", "compute"),
+      image("uan", "uan"),
+      Image {
+        name: "unlabelled".to_string(),
+        ..Default::default()
+      },
+    ];
+
+    Query::new(parse_selector("purpose=compute, built_by").unwrap())
+      .apply(&mut image_vec)
+      .unwrap();
+
+    assert_eq!(image_vec.len(), 1);
+    assert_eq!(image_vec[0].name, "compute");
+
+    assert!(parse_selector("=compute").is_err());
+    assert_eq!(parse_selector("").unwrap(), Filter::All);
+  }
+}
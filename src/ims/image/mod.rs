@@ -3,7 +3,9 @@
 //! Submodules:
 //!
 //! - [`http_client`] — `ShastaClient` methods for `/ims/v3/images`.
+//! - [`labels`] — image labels, stored in the IMS image metadata.
 //! - [`utils`] — helpers built on top of the raw client.
 
 pub mod http_client;
+pub mod labels;
 pub mod utils;
@@ -1,23 +1,146 @@
 //! Helpers built on top of `ShastaClient::ims_image_*` methods.
 
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
 use crate::{
-  bos, common,
+  bos,
+  cfs::session::utils::{SessionFilter, SessionVisibility},
+  common,
   error::Error,
+  filter::{Page, Query},
   hsm::group::utils::get_member_vec_from_hsm_name_vec,
   ims::{self, image::http_client::types::Image},
 };
 
-/// Fuzzy lookup: return every image whose name *contains*
-/// `image_name_opt`, restricted to the caller's available HSM groups.
+/// Timestamp formats recognised after an image name by
+/// [`match_score`], most specific first.
+const TIMESTAMP_FORMATS: [&str; 4] = [
+  "%Y%m%dT%H%M%S",
+  "%Y%m%d%H%M%S",
+  "%Y-%m-%dT%H:%M:%S",
+  "%Y-%m-%d-%H-%M-%S",
+];
+
+/// Date formats recognised after an image name by [`match_score`].
+const DATE_FORMATS: [&str; 2] = ["%Y%m%d", "%Y-%m-%d"];
+
+/// How [`get_fuzzy`] and [`rank_by_name`] match image names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+  /// Names equal to the searched name, made of it and a timestamp, or
+  /// containing it.
+  #[default]
+  Fuzzy,
+  /// Names equal to the searched name or made of it and a timestamp
+  /// only, and finding none is an error. Used by SAT file processing.
+  Strict,
+}
+
+/// How well an image name matches a searched name; greater is better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchScore {
+  /// The name contains the searched name.
+  Substring,
+  /// The name is the searched name followed by a timestamp, e.g.
+  /// `compute-20240131T101500`; more recent is better.
+  TimestampSuffix(NaiveDateTime),
+  /// The name is the searched name.
+  Exact,
+}
+
+/// Score `image_name` against the searched `name`, `None` if it doesn't
+/// contain it.
+#[must_use]
+pub fn match_score(image_name: &str, name: &str) -> Option<MatchScore> {
+  if image_name == name {
+    return Some(MatchScore::Exact);
+  }
+
+  let suffix = image_name
+    .strip_prefix(name)
+    .map(|suffix| suffix.trim_start_matches(['-', '_', '.']))
+    .map(|suffix| suffix.trim_end_matches('Z'));
+
+  if let Some(timestamp) = suffix.and_then(parse_timestamp) {
+    return Some(MatchScore::TimestampSuffix(timestamp));
+  }
+
+  image_name.contains(name).then_some(MatchScore::Substring)
+}
+
+fn parse_timestamp(text: &str) -> Option<NaiveDateTime> {
+  TIMESTAMP_FORMATS
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    .or_else(|| {
+      DATE_FORMATS.iter().find_map(|format| {
+        NaiveDate::parse_from_str(text, format)
+          .ok()
+          .map(|date| date.and_time(NaiveTime::MIN))
+      })
+    })
+}
+
+/// Images of `image_vec` matching `name`, best match first (see
+/// [`MatchScore`]).
+///
+/// # Errors
+///
+/// Returns [`Error::AmbiguousImageName`] if the best matches score the
+/// same, e.g. two images with the exact name, and
+/// [`Error::ImageNotFound`] if nothing matches in
+/// [`MatchMode::Strict`].
+pub fn rank_by_name(
+  image_vec: Vec<Image>,
+  name: &str,
+  mode: MatchMode,
+) -> Result<Vec<Image>, Error> {
+  let mut scored_vec: Vec<(MatchScore, Image)> = image_vec
+    .into_iter()
+    .filter_map(|image| Some((match_score(&image.name, name)?, image)))
+    .filter(|(score, _)| {
+      mode == MatchMode::Fuzzy || *score != MatchScore::Substring
+    })
+    .collect();
+
+  scored_vec.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+  match scored_vec.as_slice() {
+    [] if mode == MatchMode::Strict => {
+      return Err(Error::ImageNotFound(name.to_string()));
+    }
+    [(best, _), (second, _), ..] if best == second => {
+      return Err(Error::AmbiguousImageName {
+        name: name.to_string(),
+        candidates: scored_vec
+          .iter()
+          .take_while(|(score, _)| score == best)
+          .map(|(_, image)| image.id.clone().unwrap_or_default())
+          .collect(),
+      });
+    }
+    _ => {}
+  }
+
+  Ok(scored_vec.into_iter().map(|(_, image)| image).collect())
+}
+
+/// Fuzzy lookup: return the images whose name matches `image_name_opt`
+/// in `mode`, best match first (see [`rank_by_name`]), restricted to
+/// the caller's available HSM groups. With no name, return the last
+/// `limit_number_opt` available images, in the order IMS lists them.
 ///
 /// Used to find images created by a CFS session that manta deliberately
 /// leaves un-renamed (so the CFS session retains its original image ID).
 ///
 /// # Errors
 ///
-/// Returns an [`Error`] variant on CSM, transport, or
-/// deserialization failure; see the crate-level `Error` enum
-/// for the full set.
+/// Returns [`Error::AmbiguousImageName`] if the best matches tie, and
+/// [`Error::ImageNotFound`] if nothing matches in
+/// [`MatchMode::Strict`]. Otherwise returns an [`Error`] variant on
+/// CSM, transport, or deserialization failure; see the crate-level
+/// `Error` enum for the full set.
+#[allow(clippy::too_many_arguments)]
 pub async fn get_fuzzy(
   shasta_token: &str,
   shasta_base_url: &str,
@@ -26,6 +149,7 @@ pub async fn get_fuzzy(
   hsm_name_available_vec: &[String],
   image_name_opt: Option<&str>,
   limit_number_opt: Option<&u8>,
+  mode: MatchMode,
 ) -> Result<Vec<Image>, Error> {
   let mut image_available_vec: Vec<Image> = get_image_available_vec(
     shasta_token,
@@ -38,19 +162,56 @@ pub async fn get_fuzzy(
   )
   .await?;
 
-  if let Some(image_name) = image_name_opt {
-    image_available_vec.retain(|image| image.name.contains(image_name));
-  }
+  let Some(image_name) = image_name_opt else {
+    // Keep the order IMS listed the images in
+    if let Some(limit_number) = limit_number_opt {
+      common::paging::keep_last(
+        &mut image_available_vec,
+        usize::from(*limit_number),
+      );
+    }
+
+    return Ok(image_available_vec);
+  };
+
+  let mut image_vec = rank_by_name(image_available_vec, image_name, mode)?;
 
   if let Some(limit_number) = limit_number_opt {
-    // Limiting the number of results to return to client
-    image_available_vec = image_available_vec[image_available_vec
-      .len()
-      .saturating_sub(*limit_number as usize)..]
-      .to_vec();
+    image_vec.truncate(usize::from(*limit_number));
   }
 
-  Ok(image_available_vec.clone())
+  Ok(image_vec)
+}
+
+/// The image best matching `image_name` among every IMS image, in
+/// [`MatchMode::Strict`]. Used by SAT file processing, where an image
+/// name must resolve to exactly one image.
+///
+/// # Errors
+///
+/// Returns [`Error::ImageNotFound`] if no image matches,
+/// [`Error::AmbiguousImageName`] if the best matches tie, or an
+/// [`Error`] variant on CSM, transport, or deserialization failure; see
+/// the crate-level `Error` enum for the full set.
+pub async fn get_strict(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  image_name: &str,
+) -> Result<Image, Error> {
+  let image_vec: Vec<Image> = crate::ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?
+  .ims_image_get_all(shasta_token)
+  .await?;
+
+  rank_by_name(image_vec, image_name, MatchMode::Strict)?
+    .into_iter()
+    .next()
+    .ok_or_else(|| Error::ImageNotFound(image_name.to_string()))
 }
 
 /// Return images whose name *exactly equals* `image_name`, restricted
@@ -123,7 +284,7 @@ pub async fn try_get_by_name(
   // )
   // .await?;
 
-  let mut image_vec: Vec<Image> = crate::ShastaClient::new(
+  let mut image_vec: Vec<Image> = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -189,19 +350,20 @@ pub async fn get_with_details(
   let mut image_vec: Vec<Image> =
     client.ims_image_get(shasta_token, id_opt).await?;
 
-  get_image_cfs_config_name_hsm_group_name(
-    shasta_token,
-    client.base_url(),
-    client.root_cert(),
-    client.socks5_proxy(),
-    &mut image_vec,
-    hsm_group_name_vec,
-    limit_number,
-  )
-  .await
-  .map_err(|e| {
-    Error::Message(format!("ERROR - Failed to get image details: {e}"))
-  })
+  client
+    .scope(get_image_cfs_config_name_hsm_group_name(
+      shasta_token,
+      client.base_url(),
+      client.root_cert(),
+      client.socks5_proxy(),
+      &mut image_vec,
+      hsm_group_name_vec,
+      limit_number,
+    ))
+    .await
+    .map_err(|e| {
+      Error::Message(format!("ERROR - Failed to get image details: {e}"))
+    })
 }
 
 /// Resolve each IMS image to its CFS configuration, the HSM groups (or
@@ -243,7 +405,7 @@ pub async fn get_image_cfs_config_name_hsm_group_name(
 
   // Sort images by creation time order ASC
   // We need BOS session templates to find an image created by SAT
-  let mut bos_sessiontemplate_value_vec = crate::ShastaClient::new(
+  let mut bos_sessiontemplate_value_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -275,15 +437,13 @@ pub async fn get_image_cfs_config_name_hsm_group_name(
   )
   .await?;
 
-  crate::cfs::session::utils::filter(
-    &mut cfs_session_vec,
-    None,
-    hsm_group_name_vec,
-    &xname_vec,
-    None,
-    None,
-    common::jwt_ops::is_user_admin(shasta_token),
-  )?;
+  SessionFilter::default()
+    .visible_to(SessionVisibility {
+      hsm_group_names: hsm_group_name_vec,
+      xnames: &xname_vec,
+      keep_generic: common::jwt_ops::is_user_admin(shasta_token),
+    })
+    .apply(&mut cfs_session_vec)?;
 
   let mut image_id_cfs_configuration_from_cfs_session: Vec<(String, String, Vec<String>)> =
         crate::cfs::session::utils::get_image_id_cfs_configuration_target_for_existing_images_tuple_vec(
@@ -305,7 +465,7 @@ pub async fn get_image_cfs_config_name_hsm_group_name(
   )
   .await?;
 
-  let boot_param_vec = crate::ShastaClient::new(
+  let boot_param_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -398,7 +558,7 @@ pub async fn get_image_available_vec(
   hsm_name_available_vec: &[String],
   limit_number_opt: Option<&u8>,
 ) -> Result<Vec<Image>, Error> {
-  let mut image_vec: Vec<Image> = crate::ShastaClient::new(
+  let mut image_vec: Vec<Image> = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -409,7 +569,7 @@ pub async fn get_image_available_vec(
   ims::image::utils::filter(&mut image_vec);
 
   // We need BOS session templates to find an image created by SAT
-  let mut bos_sessiontemplate_vec = crate::ShastaClient::new(
+  let mut bos_sessiontemplate_vec = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
@@ -451,15 +611,13 @@ pub async fn get_image_available_vec(
   .await?;
 
   // Filter CFS sessions to the ones the user has access to
-  crate::cfs::session::utils::filter(
-    &mut cfs_session_vec,
-    None,
-    hsm_name_available_vec,
-    &xname_from_group_vec,
-    None,
-    None,
-    true,
-  )?;
+  SessionFilter::default()
+    .visible_to(SessionVisibility {
+      hsm_group_names: hsm_name_available_vec,
+      xnames: &xname_from_group_vec,
+      keep_generic: true,
+    })
+    .apply(&mut cfs_session_vec)?;
 
   let mut image_id_cfs_configuration_from_bos_sessiontemplate: Vec<(
         String,
@@ -535,6 +693,39 @@ pub async fn get_image_available_vec(
   Ok(image_available_vec)
 }
 
+/// Page through the IMS images available to the user, as
+/// [`get_image_available_vec`] selects them, keeping the ones matching
+/// `query` and returning the `page_size` following [`Query::after`].
+/// Feed [`Page::next_cursor`] back through [`Query::after`] to get the
+/// next page.
+///
+/// # Errors
+///
+/// Returns [`Error::GlobError`] if a glob pattern in `query` is invalid,
+/// or an [`Error`] variant on CSM, transport, or deserialization
+/// failure; see the crate-level `Error` enum for the full set.
+pub async fn get_available_page(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  hsm_name_available_vec: &[String],
+  query: &Query,
+  page_size: usize,
+) -> Result<Page<Image>, Error> {
+  let image_available_vec = get_image_available_vec(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    hsm_name_available_vec,
+    None,
+  )
+  .await?;
+
+  query.page(image_available_vec, page_size)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -592,6 +783,76 @@ mod tests {
     assert_eq!(images[0].name, "only");
   }
 
+  // ---------- rank_by_name ----------
+
+  #[test]
+  fn match_score_prefers_exact_then_recent_timestamp_then_substring() {
+    let exact = match_score("compute", "compute").unwrap();
+    let old = match_score("compute-20240101T000000", "compute").unwrap();
+    let new = match_score("compute_2024-02-01", "compute").unwrap();
+    let substring = match_score("compute-gpu", "compute").unwrap();
+
+    assert!(exact > new && new > old && old > substring);
+    assert_eq!(substring, MatchScore::Substring);
+    assert_eq!(match_score("login", "compute"), None);
+  }
+
+  #[test]
+  fn rank_by_name_orders_best_first_and_filters_by_mode() {
+    let image_vec = vec![
+      image("compute-gpu", None),
+      image("compute-20240101T000000", None),
+      image("compute-20240201T000000", None),
+    ];
+
+    let names = |image_vec: Vec<Image>| -> Vec<String> {
+      image_vec.into_iter().map(|image| image.name).collect()
+    };
+
+    assert_eq!(
+      names(
+        rank_by_name(image_vec.clone(), "compute", MatchMode::Fuzzy).unwrap()
+      ),
+      [
+        "compute-20240201T000000",
+        "compute-20240101T000000",
+        "compute-gpu"
+      ]
+    );
+    assert_eq!(
+      rank_by_name(image_vec.clone(), "compute", MatchMode::Strict)
+        .unwrap()
+        .len(),
+      2
+    );
+    assert!(matches!(
+      rank_by_name(image_vec, "compute-gpu-", MatchMode::Strict),
+      Err(Error::ImageNotFound(_))
+    ));
+  }
+
+  #[test]
+  fn rank_by_name_rejects_ties() {
+    let mut duplicate = image("compute", None);
+    duplicate.id = Some("id-compute-2".to_string());
+    let image_vec = vec![image("compute", None), duplicate];
+
+    assert!(matches!(
+      rank_by_name(image_vec, "compute", MatchMode::Fuzzy),
+      Err(Error::AmbiguousImageName { candidates, .. })
+        if candidates == ["id-compute", "id-compute-2"]
+    ));
+
+    assert!(matches!(
+      rank_by_name(
+        vec![image("compute-gpu", None), image("compute-cpu", None)],
+        "compute",
+        MatchMode::Fuzzy
+      ),
+      Err(Error::AmbiguousImageName { .. })
+    ));
+  }
+
   #[test]
   fn filter_treats_missing_created_as_empty_and_sorts_first() {
     let mut images = vec![
@@ -2,7 +2,12 @@
 
 use serde_json::Value;
 
-use crate::{ShastaClient, common::http, error::Error};
+use crate::common::metrics::MeteredSend;
+use crate::{
+  ShastaClient,
+  common::{http, poll::WaitOptions},
+  error::Error,
+};
 
 use super::{
   types::{Job, SshContainer},
@@ -51,6 +56,7 @@ impl ShastaClient {
       resultant_image_id: None,
       kubernetes_namespace: None,
       arch: None,
+      template_dictionary: None,
     };
 
     let url = format!("{}/ims/v3/jobs", self.base_url());
@@ -76,7 +82,7 @@ impl ShastaClient {
       .post(api_url)
       .bearer_auth(token)
       .json(&ims_job)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?
       .error_for_status()
@@ -86,7 +92,9 @@ impl ShastaClient {
       .map_err(Error::NetError)
   }
 
-  /// Like `ims_job_post`, but waits for the job to finish before returning.
+  /// Like `ims_job_post`, but waits for the job to finish, as
+  /// `wait_options` says, before returning. Pass
+  /// [`crate::ims::job::utils::DEFAULT_WAIT`] for the usual pacing.
   ///
   /// # Errors
   ///
@@ -97,6 +105,7 @@ impl ShastaClient {
     &self,
     token: &str,
     ims_job: &Job,
+    wait_options: WaitOptions,
   ) -> Result<Job, Error> {
     log::debug!("Create IMS job");
     log::debug!(
@@ -117,6 +126,7 @@ impl ShastaClient {
       self.root_cert(),
       self.socks5_proxy(),
       &ims_job_id,
+      wait_options,
     )
     .await?;
 
@@ -153,7 +163,7 @@ impl ShastaClient {
       .http()
       .get(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await
       .map_err(Error::NetError)?
       .error_for_status()
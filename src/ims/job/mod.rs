@@ -4,7 +4,8 @@
 //!
 //! - [`http_client`] — `ShastaClient` methods for `/ims/v3/jobs`.
 //! - [`types`] — request/response shapes.
-//! - [`utils`] — helpers built on top of the raw client.
+//! - [`utils`] — helpers built on top of the raw client: waiting for a
+//!   job to finish and streaming its build logs.
 
 pub mod http_client;
 pub mod types;
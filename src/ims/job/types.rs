@@ -4,6 +4,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::ims::recipe::types::RecipeKeyValuePair;
+
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct SshContainer {
   pub name: String,
@@ -48,4 +50,6 @@ pub struct Job {
   pub arch: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub require_dkms: Option<bool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub template_dictionary: Option<Vec<RecipeKeyValuePair>>,
 }
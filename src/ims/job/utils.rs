@@ -1,59 +1,238 @@
 //! Helpers built on top of `ShastaClient::ims_job_*` methods.
 
-use crate::{ShastaClient, error::Error, ims::job::types::Job};
+use std::time::Duration;
 
-/// Wait for an IMS job to finish (polls every 2s, max 1800 attempts ~ 1h).
+#[cfg(feature = "k8s-console")]
+use futures::Stream;
+use tokio::time::Instant;
+
+#[cfg(feature = "k8s-console")]
+use crate::common::kubernetes::{
+  PodWaitTimeouts, SessionLogEvent, SessionLogStreamer,
+};
+use crate::{
+  ShastaClient, common::poll::WaitOptions, error::Error, ims::job::types::Job,
+};
+
+/// Namespace IMS runs its jobs in unless the job says otherwise.
+#[cfg(feature = "k8s-console")]
+const IMS_JOB_NAMESPACE: &str = "ims";
+
+/// Sensible default for [`wait`] and [`wait_ims_job_to_finish`]: recipe
+/// builds take tens of minutes, so up to an hour, checking every 2 s.
+pub const DEFAULT_WAIT: WaitOptions =
+  WaitOptions::new(Duration::from_secs(3600), Duration::from_secs(2));
+
+/// How an IMS job ended, as reported by [`wait`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobOutcome {
+  /// The job succeeded.
+  Success {
+    /// Image the job produced, if IMS reported one.
+    image_id: Option<String>,
+  },
+  /// The job failed.
+  Error {
+    /// What failed, and where to look for details.
+    reason: String,
+  },
+  /// The job was still running when the timeout expired.
+  TimedOut,
+}
+
+/// Terminal state of `ims_job`, `None` while it is still running.
+fn job_outcome(ims_job: &Job) -> Option<JobOutcome> {
+  match ims_job.status.as_deref() {
+    Some("success") => Some(JobOutcome::Success {
+      image_id: ims_job.resultant_image_id.clone(),
+    }),
+    Some("error") => Some(JobOutcome::Error {
+      reason: format!(
+        "IMS job '{}' failed, see the logs of Kubernetes job '{}'",
+        ims_job.id.as_deref().unwrap_or_default(),
+        ims_job.kubernetes_job.as_deref().unwrap_or("<unknown>")
+      ),
+    }),
+    _ => None,
+  }
+}
+
+/// Poll IMS job `ims_job_id`, as `wait_options` says, until it
+/// succeeds, fails, or `wait_options.timeout` expires.
 ///
 /// # Errors
 ///
 /// Returns an [`Error`] variant on CSM, transport, or
-/// deserialization failure; see the crate-level `Error` enum
-/// for the full set.
-pub async fn wait_ims_job_to_finish(
+/// deserialization failure, or if the job doesn't exist. A failed or
+/// timed out job is not an error; see [`JobOutcome`].
+pub async fn wait(
   shasta_token: &str,
   shasta_base_url: &str,
   shasta_root_cert: &[u8],
   socks5_proxy: Option<&str>,
   ims_job_id: &str,
-) -> Result<(), Error> {
-  let client = ShastaClient::new(
+  wait_options: WaitOptions,
+) -> Result<JobOutcome, Error> {
+  let client = ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
   )?;
-  let mut i = 0;
-  let max = 1800;
+
+  let deadline = Instant::now() + wait_options.timeout;
+  let mut delay = wait_options.poll_interval;
+
   loop {
     let ims_job: Job = client
       .ims_job_get(shasta_token, Some(ims_job_id))
       .await?
-      .first()
-      .cloned()
+      .into_iter()
+      .next()
       .ok_or_else(|| {
         Error::Message(format!("ERROR - IMS job '{ims_job_id}' not found"))
       })?;
 
-    log::debug!(
+    tracing::debug!(
       "IMS job details:\n{}",
       serde_json::to_string_pretty(&ims_job).unwrap_or_default()
     );
 
-    let ims_job_status = ims_job.status.unwrap_or_default();
+    if let Some(job_outcome) = job_outcome(&ims_job) {
+      tracing::debug!("IMS job '{ims_job_id}' finished: {job_outcome:?}");
+      return Ok(job_outcome);
+    }
 
-    if (ims_job_status != "error" && ims_job_status != "success") && i < max {
-      log::debug!(
-        "Waiting IMS job '{ims_job_id}' with job status '{ims_job_status}'. Checking again in 2 secs. Attempt {i} of {max}."
+    if Instant::now() + delay > deadline {
+      tracing::warn!(
+        "IMS job '{ims_job_id}' still running after {}s",
+        wait_options.timeout.as_secs()
       );
-      tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+      return Ok(JobOutcome::TimedOut);
+    }
 
-      i += 1;
-    } else {
-      log::debug!(
-        "\nIMS job '{ims_job_id}' finished with job status '{ims_job_status}'"
-      );
-      break;
+    tracing::debug!(
+      "Waiting IMS job '{ims_job_id}' with job status '{}'. Checking again in {} secs.",
+      ims_job.status.as_deref().unwrap_or_default(),
+      delay.as_secs()
+    );
+    tokio::time::sleep(delay).await;
+    delay = wait_options.next_delay(delay);
+  }
+}
+
+/// Wait for an IMS job to finish, as `wait_options` says. Use [`wait`]
+/// to know how the job ended.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn wait_ims_job_to_finish(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  ims_job_id: &str,
+  wait_options: WaitOptions,
+) -> Result<(), Error> {
+  wait(
+    shasta_token,
+    shasta_base_url,
+    shasta_root_cert,
+    socks5_proxy,
+    ims_job_id,
+    wait_options,
+  )
+  .await
+  .map(|_| ())
+}
+
+/// Stream the build logs of IMS job `ims_job_id` from its Kubernetes pod,
+/// init containers first, in the same way as CFS session logs (see
+/// [`SessionLogStreamer`]): containers are followed one after the other
+/// and the stream stops after the first one that fails. The pod and
+/// each container are waited for as `wait_timeouts` says.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if the IMS job can't be fetched, has no
+/// Kubernetes job yet, or its pod isn't created in time.
+#[cfg(feature = "k8s-console")]
+pub async fn get_logs_stream(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  kube_client: kube::Client,
+  ims_job_id: &str,
+  timestamps: bool,
+  wait_timeouts: PodWaitTimeouts,
+) -> Result<impl Stream<Item = Result<SessionLogEvent, Error>> + Send, Error> {
+  let client = ShastaClient::scoped_or_new(
+    shasta_base_url,
+    shasta_root_cert.to_vec(),
+    socks5_proxy.map(str::to_owned),
+  )?;
+
+  let ims_job: Job = client
+    .ims_job_get(shasta_token, Some(ims_job_id))
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| {
+      Error::Message(format!("ERROR - IMS job '{ims_job_id}' not found"))
+    })?;
+
+  let kubernetes_job = ims_job.kubernetes_job.ok_or_else(|| {
+    Error::Message(format!(
+      "ERROR - IMS job '{ims_job_id}' has no Kubernetes job yet"
+    ))
+  })?;
+
+  let namespace = ims_job
+    .kubernetes_namespace
+    .unwrap_or_else(|| IMS_JOB_NAMESPACE.to_string());
+
+  let streamer = SessionLogStreamer::for_pod(
+    kube_client,
+    &namespace,
+    &format!("job-name={kubernetes_job}"),
+    Vec::new(),
+    timestamps,
+    wait_timeouts,
+  )
+  .await?;
+
+  Ok(streamer.into_stream())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn job(status: &str) -> Job {
+    Job {
+      id: Some("1234".to_string()),
+      status: Some(status.to_string()),
+      kubernetes_job: Some("cray-ims-1234-create".to_string()),
+      resultant_image_id: Some("5678".to_string()),
+      ..Default::default()
     }
   }
 
-  Ok(())
+  #[test]
+  fn job_outcome_only_for_terminal_states() {
+    assert_eq!(job_outcome(&job("building_image")), None);
+    assert_eq!(
+      job_outcome(&job("success")),
+      Some(JobOutcome::Success {
+        image_id: Some("5678".to_string())
+      })
+    );
+    assert!(matches!(
+      job_outcome(&job("error")),
+      Some(JobOutcome::Error { reason }) if reason.contains("cray-ims-1234-create")
+    ));
+  }
 }
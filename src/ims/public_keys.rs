@@ -1,4 +1,10 @@
 //! IMS `/v3/public-keys` endpoint bindings.
+//!
+//! IMS jobs need the id of a registered SSH public key so the build
+//! environment can be accessed for debugging. [`PublicKeySelector`]
+//! picks which key to use when several are registered.
+
+use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +23,71 @@ pub struct PublicKey {
   pub public_key: String,
 }
 
+impl PublicKey {
+  /// User part of the key comment (`ssh-ed25519 AAAA... user@host`),
+  /// `None` if the key has no comment.
+  #[must_use]
+  pub fn user(&self) -> Option<&str> {
+    let comment = self.public_key.split_whitespace().nth(2)?;
+    comment.split('@').next().filter(|user| !user.is_empty())
+  }
+}
+
+/// Name of the key CSM registers for the management nodes' root user.
+pub const MGMT_ROOT_KEY_NAME: &str = "mgmt root key";
+
+/// How to pick the IMS public key passed to IMS jobs.
+///
+/// When several keys match, the most recently created one is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKeySelector {
+  /// Key registered under this name.
+  ByName(String),
+  /// Key whose comment belongs to this user (see [`PublicKey::user`]).
+  ByUser(String),
+  /// Most recently created key.
+  Newest,
+}
+
+impl Default for PublicKeySelector {
+  /// The management nodes' root key ([`MGMT_ROOT_KEY_NAME`]).
+  fn default() -> Self {
+    Self::ByName(MGMT_ROOT_KEY_NAME.to_string())
+  }
+}
+
+impl PublicKeySelector {
+  /// Pick the key matching this selector out of `key_vec`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::ImsKeyNotFound`] if no key matches.
+  pub fn select<'a>(
+    &self,
+    key_vec: &'a [PublicKey],
+  ) -> Result<&'a PublicKey, Error> {
+    key_vec
+      .iter()
+      .filter(|key| match self {
+        Self::ByName(name) => key.name == *name,
+        Self::ByUser(user) => key.user() == Some(user.as_str()),
+        Self::Newest => true,
+      })
+      .max_by(|a, b| a.created.cmp(&b.created))
+      .ok_or_else(|| Error::ImsKeyNotFound(self.to_string()))
+  }
+}
+
+impl std::fmt::Display for PublicKeySelector {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::ByName(name) => write!(f, "{name}"),
+      Self::ByUser(user) => write!(f, "<any key of user {user}>"),
+      Self::Newest => write!(f, "<newest key>"),
+    }
+  }
+}
+
 impl ShastaClient {
   /// Get one user public key in IMS. Returns `None` if no key matches the
   /// username or more than one matches.
@@ -63,4 +134,125 @@ impl ShastaClient {
       None => keys,
     })
   }
+
+  /// Register `public_key` (OpenSSH format) in IMS under `name`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn ims_public_keys_v3_post(
+    &self,
+    token: &str,
+    name: &str,
+    public_key: &str,
+  ) -> Result<PublicKey, Error> {
+    let api_url = format!("{}/ims/v3/public-keys", self.base_url());
+    let key = PublicKey {
+      name: name.to_string(),
+      public_key: public_key.trim().to_string(),
+      ..Default::default()
+    };
+
+    http::post_json(self.http(), &api_url, token, &key).await
+  }
+
+  /// Register the public key in file `path` (e.g. `~/.ssh/id_ed25519.pub`)
+  /// in IMS under `name`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::IoError`] if the file can't be read, or any error
+  /// of [`Self::ims_public_keys_v3_post`].
+  pub async fn ims_public_keys_v3_post_from_file(
+    &self,
+    token: &str,
+    name: &str,
+    path: &Path,
+  ) -> Result<PublicKey, Error> {
+    let public_key = std::fs::read_to_string(path)?;
+    self.ims_public_keys_v3_post(token, name, &public_key).await
+  }
+
+  /// Delete IMS public key `public_key_id`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM or transport failure; see the
+  /// crate-level `Error` enum for the full set.
+  pub async fn ims_public_keys_v3_delete(
+    &self,
+    token: &str,
+    public_key_id: &str,
+  ) -> Result<(), Error> {
+    let api_url =
+      format!("{}/ims/v3/public-keys/{public_key_id}", self.base_url());
+    http::delete(self.http(), &api_url, token).await
+  }
+
+  /// Get the IMS public key picked by `selector`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::ImsKeyNotFound`] if no key matches, or an
+  /// [`Error`] variant on CSM, transport, or deserialization failure.
+  pub async fn ims_public_keys_v3_select(
+    &self,
+    token: &str,
+    selector: &PublicKeySelector,
+  ) -> Result<PublicKey, Error> {
+    let key_vec = self.ims_public_keys_v3_get(token, None).await?;
+    selector.select(&key_vec).cloned()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn key(name: &str, public_key: &str, created: &str) -> PublicKey {
+    PublicKey {
+      id: Some(format!("{name}-{created}")),
+      created: Some(created.to_string()),
+      name: name.to_string(),
+      public_key: public_key.to_string(),
+    }
+  }
+
+  #[test]
+  fn selector_picks_newest_match() {
+    let key_vec = vec![
+      key(
+        "mgmt root key",
+        "ssh-ed25519 AAAA root@ncn-m001",
+        "2024-01-01",
+      ),
+      key(
+        "mgmt root key",
+        "ssh-ed25519 BBBB root@ncn-m001",
+        "2024-03-01",
+      ),
+      key("alice", "ssh-ed25519 CCCC alice@laptop", "2024-02-01"),
+      key("nocomment", "ssh-ed25519 DDDD", "2024-04-01"),
+    ];
+
+    let pick = |selector: PublicKeySelector| {
+      selector.select(&key_vec).map(|key| key.id.clone().unwrap())
+    };
+
+    assert_eq!(
+      pick(PublicKeySelector::default()).unwrap(),
+      "mgmt root key-2024-03-01"
+    );
+    assert_eq!(
+      pick(PublicKeySelector::ByUser("alice".to_string())).unwrap(),
+      "alice-2024-02-01"
+    );
+    assert_eq!(
+      pick(PublicKeySelector::Newest).unwrap(),
+      "nocomment-2024-04-01"
+    );
+    assert!(pick(PublicKeySelector::ByName("bob".to_string())).is_err());
+  }
 }
@@ -1,5 +1,6 @@
 //! `ShastaClient` methods for `/ims/v3/recipes`.
 
+use crate::common::metrics::MeteredSend;
 use crate::{ShastaClient, error::Error};
 
 use super::types::RecipeGetResponse;
@@ -28,7 +29,7 @@ impl ShastaClient {
       .http()
       .get(api_url)
       .bearer_auth(token)
-      .send()
+      .send_metered()
       .await?
       .error_for_status()?;
 
@@ -12,6 +12,12 @@ pub struct Link {
   pub r#type: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub struct RecipeKeyValuePair {
+  pub key: String,
+  pub value: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct RecipeGetResponse {
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -23,4 +29,6 @@ pub struct RecipeGetResponse {
   pub recipe_type: String,
   pub linux_distribution: String,
   pub name: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub template_dictionary: Option<Vec<RecipeKeyValuePair>>,
 }
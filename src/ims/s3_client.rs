@@ -10,8 +10,9 @@ use std::time::Duration;
 use serde_json::Value;
 
 use aws_sdk_s3::{Client, primitives::ByteStream};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 
+use crate::common::events;
 use crate::error::Error;
 
 /// Per-S3-operation deadline. The CSM reqwest client uses
@@ -48,6 +49,18 @@ fn parse_sts_credentials(
 /// `indicatif` progress bar template used by the S3 upload/download
 /// helpers in this module.
 pub const BAR_FORMAT: &str = "[{elapsed_precise}] {bar:40.cyan/blue} ({bytes_per_sec}) {bytes:>7}/{total_bytes:7} {msg} [ETA {eta}]";
+
+/// Progress bar of `len` bytes, drawn on stderr only while progress
+/// events are printed to the terminal (see
+/// [`events::prints_to_terminal`]), hidden otherwise.
+pub(crate) fn progress_bar(len: u64) -> ProgressBar {
+  if events::prints_to_terminal() {
+    ProgressBar::new(len)
+  } else {
+    ProgressBar::with_draw_target(Some(len), ProgressDrawTarget::hidden())
+  }
+}
+
 /// Fetch an AWS STS token for the CSM-backing S3 store and return the
 /// raw STS JSON response.
 ///
@@ -245,7 +258,7 @@ pub async fn s3_download_object(
     Error::S3Transport("could not get S3 object size.".to_string())
   })?;
 
-  let bar = ProgressBar::new(bar_size as u64);
+  let bar = progress_bar(bar_size as u64);
   bar.set_style(ProgressStyle::with_template(BAR_FORMAT).map_err(|e| {
     Error::S3Transport(format!(
       "ERROR - Could not create progress bar.\nReason:\n{e}"
@@ -425,7 +438,7 @@ pub async fn s3_multipart_upload_object(
     chunk_count -= 1;
   }
 
-  let bar = ProgressBar::new(file_size);
+  let bar = progress_bar(file_size);
   bar.set_style(ProgressStyle::with_template(BAR_FORMAT).map_err(|e| {
     Error::S3Transport(format!(
       "ERROR - Could not create progress bar.\nReason:\n{e}"
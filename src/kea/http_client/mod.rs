@@ -0,0 +1,100 @@
+//! `ShastaClient` methods for the Kea control agent.
+
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::common::metrics::MeteredSend;
+use crate::{
+  ShastaClient,
+  common::http,
+  error::Error,
+  kea::types::{
+    Command, CommandResponse, Lease, Leases, RESULT_EMPTY, RESULT_SUCCESS,
+  },
+};
+
+impl ShastaClient {
+  /// Send `command` to the Kea control agent and return the `arguments`
+  /// of the answer; `None` if Kea found nothing.
+  ///
+  /// `POST /dhcp-kea`.
+  async fn kea_command<T: DeserializeOwned>(
+    &self,
+    token: &str,
+    command: &Command,
+  ) -> Result<Option<T>, Error> {
+    log::debug!("Kea command '{}'", command.command);
+
+    let api_url = format!("{}/dhcp-kea", self.base_url());
+
+    let response = self
+      .http()
+      .post(api_url)
+      .bearer_auth(token)
+      .json(command)
+      .send_metered()
+      .await
+      .map_err(Error::NetError)?;
+
+    // One answer per service the command was sent to
+    let answer =
+      http::handle_json_response::<Vec<CommandResponse<T>>>(response, "POST")
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+          Error::Kea(format!("no answer to command '{}'", command.command))
+        })?;
+
+    match answer.result {
+      RESULT_SUCCESS => Ok(answer.arguments),
+      RESULT_EMPTY => Ok(None),
+      result => Err(Error::Kea(format!(
+        "command '{}' failed with result {result}: {}",
+        command.command,
+        answer.text.unwrap_or_default()
+      ))),
+    }
+  }
+
+  /// Get every DHCPv4 lease.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Kea`] if Kea rejects the command, or an [`Error`]
+  /// variant on CSM, transport, or deserialization failure.
+  pub async fn kea_lease4_get_all(
+    &self,
+    token: &str,
+  ) -> Result<Vec<Lease>, Error> {
+    let leases: Option<Leases> = self
+      .kea_command(token, &Command::dhcp4("lease4-get-all", None))
+      .await?;
+
+    Ok(leases.map(|leases| leases.leases).unwrap_or_default())
+  }
+
+  /// Get the DHCPv4 leases of MAC address `hw_address`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::Kea`] if Kea rejects the command, or an [`Error`]
+  /// variant on CSM, transport, or deserialization failure.
+  pub async fn kea_lease4_get_by_hw_address(
+    &self,
+    token: &str,
+    hw_address: &str,
+  ) -> Result<Vec<Lease>, Error> {
+    let leases: Option<Leases> = self
+      .kea_command(
+        token,
+        &Command::dhcp4(
+          "lease4-get-by-hw-address",
+          Some(json!({ "hw-address": hw_address })),
+        ),
+      )
+      .await?;
+
+    Ok(leases.map(|leases| leases.leases).unwrap_or_default())
+  }
+}
@@ -0,0 +1,18 @@
+//! Kea DHCP bindings.
+//!
+//! CSM runs Kea as its DHCP server and exposes the Kea control agent
+//! at `/apis/dhcp-kea`. Knowing whether a node got a lease, and which
+//! address, is usually the first step when it doesn't boot.
+//!
+//! Submodules:
+//!
+//! - [`http_client`] — `ShastaClient` methods that send Kea commands.
+//! - [`types`] — command and lease shapes.
+//! - [`utils`] — lease lookups by MAC address or xname, merged with the
+//!   HSM ethernet interfaces.
+
+pub mod http_client;
+pub mod types;
+pub mod utils;
+
+pub use types::Lease;
@@ -0,0 +1,98 @@
+//! Wire-format types — mirror the Kea control agent API; field names
+//! are dictated by the API.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Kea command sent to the control agent.
+#[derive(Debug, Clone, Serialize)]
+pub struct Command {
+  /// Command name, e.g. `lease4-get-all`.
+  pub command: String,
+  /// Kea services the command is for, e.g. `dhcp4`.
+  pub service: Vec<String>,
+  /// Command arguments.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub arguments: Option<Value>,
+}
+
+impl Command {
+  /// Command `command` for the `dhcp4` service.
+  #[must_use]
+  pub fn dhcp4(command: &str, arguments: Option<Value>) -> Self {
+    Self {
+      command: command.to_string(),
+      service: vec!["dhcp4".to_string()],
+      arguments,
+    }
+  }
+}
+
+/// Kea result code for success.
+pub const RESULT_SUCCESS: i64 = 0;
+/// Kea result code for a command that succeeded but found nothing.
+pub const RESULT_EMPTY: i64 = 3;
+
+/// Answer of one Kea service to a [`Command`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommandResponse<T> {
+  /// Result code, see [`RESULT_SUCCESS`] and [`RESULT_EMPTY`].
+  pub result: i64,
+  /// Human readable outcome.
+  #[serde(default)]
+  pub text: Option<String>,
+  /// Command output.
+  #[serde(default)]
+  pub arguments: Option<T>,
+}
+
+/// `arguments` of the `lease4-get-*` commands returning several leases.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Leases {
+  /// Leases found.
+  #[serde(default)]
+  pub leases: Vec<Lease>,
+}
+
+/// DHCPv4 lease.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+  /// Leased address.
+  #[serde(rename = "ip-address")]
+  pub ip_address: String,
+  /// MAC address of the client.
+  #[serde(rename = "hw-address")]
+  pub hw_address: String,
+  /// Host name sent by the client or assigned by Kea.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub hostname: Option<String>,
+  /// Kea subnet id.
+  #[serde(rename = "subnet-id")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub subnet_id: Option<u32>,
+  /// Client last transmission time, in seconds since the epoch.
+  #[serde(default)]
+  pub cltt: i64,
+  /// Lease lifetime, in seconds.
+  #[serde(rename = "valid-lft")]
+  #[serde(default)]
+  pub valid_lft: i64,
+  /// Lease state: 0 default, 1 declined, 2 expired-reclaimed.
+  #[serde(default)]
+  pub state: i64,
+}
+
+impl Lease {
+  /// Time the lease expires, in seconds since the epoch.
+  #[must_use]
+  pub fn expires_at(&self) -> i64 {
+    self.cltt + self.valid_lft
+  }
+
+  /// Whether the lease is in the default state and not expired at
+  /// `now` (seconds since the epoch).
+  #[must_use]
+  pub fn is_active(&self, now: i64) -> bool {
+    self.state == 0 && self.expires_at() > now
+  }
+}
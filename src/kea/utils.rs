@@ -0,0 +1,182 @@
+//! Lease lookups by MAC address or xname.
+//!
+//! Kea only knows MAC addresses; the xname of a node comes from its HSM
+//! ethernet interfaces (see
+//! [`crate::hsm::hw_inventory::ethernet_interfaces`]).
+
+use serde::Serialize;
+
+use crate::{
+  error::Error,
+  hsm::hw_inventory::ethernet_interfaces::types::EthernetInterface,
+};
+
+use super::types::Lease;
+
+/// `mac_address` in lowercase hex digits without separators, so
+/// `B4:2E:99:00:00:01`, `b42e.9900.0001` and `b4-2e-99-00-00-01`
+/// compare equal.
+#[must_use]
+pub fn normalize_mac(mac_address: &str) -> String {
+  mac_address
+    .chars()
+    .filter(char::is_ascii_hexdigit)
+    .map(|c| c.to_ascii_lowercase())
+    .collect()
+}
+
+/// Most recent lease of MAC address `mac_address`.
+#[must_use]
+pub fn find_lease_by_mac<'a>(
+  lease_vec: &'a [Lease],
+  mac_address: &str,
+) -> Option<&'a Lease> {
+  let mac_address = normalize_mac(mac_address);
+
+  lease_vec
+    .iter()
+    .filter(|lease| normalize_mac(&lease.hw_address) == mac_address)
+    .max_by_key(|lease| lease.cltt)
+}
+
+/// Most recent lease of node `xname`: of any of its MAC addresses in
+/// `interface_vec`, or else with `xname` as host name.
+#[must_use]
+pub fn find_lease_by_xname<'a>(
+  lease_vec: &'a [Lease],
+  interface_vec: &[EthernetInterface],
+  xname: &str,
+) -> Option<&'a Lease> {
+  interface_vec
+    .iter()
+    .filter(|interface| interface.component_id.as_deref() == Some(xname))
+    .filter_map(|interface| {
+      find_lease_by_mac(lease_vec, &interface.mac_address)
+    })
+    .max_by_key(|lease| lease.cltt)
+    .or_else(|| {
+      lease_vec
+        .iter()
+        .filter(|lease| lease.hostname.as_deref() == Some(xname))
+        .max_by_key(|lease| lease.cltt)
+    })
+}
+
+/// DHCP lease of one node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeLease {
+  /// Node xname.
+  pub xname: String,
+  /// MAC addresses HSM knows for the node.
+  pub mac_addresses: Vec<String>,
+  /// Most recent lease of the node, if it ever got one.
+  pub lease: Option<Lease>,
+  /// Whether `lease` is still valid.
+  pub active: bool,
+}
+
+/// DHCP lease of every node in `xname_vec`.
+///
+/// # Errors
+///
+/// Returns an [`Error`] variant on CSM, Kea, transport, or
+/// deserialization failure; see the crate-level `Error` enum
+/// for the full set.
+pub async fn get_node_leases(
+  client: &crate::ShastaClient,
+  shasta_token: &str,
+  xname_vec: &[String],
+) -> Result<Vec<NodeLease>, Error> {
+  let (lease_vec, interface_vec) = tokio::try_join!(
+    client.kea_lease4_get_all(shasta_token),
+    client.hsm_eth_get_all(shasta_token),
+  )?;
+
+  Ok(build_node_leases(
+    xname_vec,
+    &lease_vec,
+    &interface_vec,
+    chrono::Utc::now().timestamp(),
+  ))
+}
+
+/// Pure part of [`get_node_leases`], split out for testing.
+fn build_node_leases(
+  xname_vec: &[String],
+  lease_vec: &[Lease],
+  interface_vec: &[EthernetInterface],
+  now: i64,
+) -> Vec<NodeLease> {
+  xname_vec
+    .iter()
+    .map(|xname| {
+      let lease = find_lease_by_xname(lease_vec, interface_vec, xname);
+
+      NodeLease {
+        xname: xname.clone(),
+        mac_addresses: interface_vec
+          .iter()
+          .filter(|interface| {
+            interface.component_id.as_deref() == Some(xname.as_str())
+          })
+          .map(|interface| interface.mac_address.clone())
+          .collect(),
+        active: lease.is_some_and(|lease| lease.is_active(now)),
+        lease: lease.cloned(),
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lease(hw_address: &str, cltt: i64) -> Lease {
+    Lease {
+      ip_address: format!("10.252.1.{cltt}"),
+      hw_address: hw_address.to_string(),
+      cltt,
+      valid_lft: 100,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn node_lease_uses_latest_lease_of_any_mac() {
+    let lease_vec = vec![
+      lease("b4:2e:99:00:00:01", 10),
+      lease("b4:2e:99:00:00:02", 20),
+      lease("b4:2e:99:00:00:03", 30),
+    ];
+    let interface_vec = vec![
+      EthernetInterface {
+        mac_address: "B4-2E-99-00-00-01".to_string(),
+        component_id: Some("x1000c0s0b0n0".to_string()),
+        ..Default::default()
+      },
+      EthernetInterface {
+        mac_address: "b42e.9900.0002".to_string(),
+        component_id: Some("x1000c0s0b0n0".to_string()),
+        ..Default::default()
+      },
+    ];
+
+    let node_lease_vec = build_node_leases(
+      &["x1000c0s0b0n0".to_string(), "x1000c0s1b0n0".to_string()],
+      &lease_vec,
+      &interface_vec,
+      115,
+    );
+
+    assert_eq!(
+      node_lease_vec[0].lease.as_ref().unwrap().ip_address,
+      "10.252.1.20"
+    );
+    assert!(node_lease_vec[0].active);
+    assert_eq!(node_lease_vec[0].mac_addresses.len(), 2);
+
+    assert!(node_lease_vec[1].lease.is_none());
+    assert!(!node_lease_vec[1].active);
+  }
+}
@@ -58,7 +58,7 @@
 //! reviewers don't have to track per-module conventions:
 //!
 //! ```text
-//! <namespace>/                 // bos, bss, capmc, cfs, hsm, ims, pcs
+//! <namespace>/                 // bos, bss, capmc, cfs, fas, hsm, ims, kea, pcs, sls
 //!   mod.rs                     // module docs + canonical `pub use` aliases
 //!   <resource>/                // e.g. cfs/configuration, bos/session
 //!     mod.rs                   // resource docs; declares the items below
@@ -76,6 +76,8 @@
 //! Higher-level composed operations that combine multiple namespaces
 //! live in `commands/`, with the most CLI-shaped ones (file I/O, YAML,
 //! progress bars) gated behind the `commands-admin` Cargo feature.
+//! `offline/` answers the read-only queries from an exported state
+//! bundle instead of a live system.
 
 #![allow(clippy::doc_lazy_continuation)]
 #![deny(rustdoc::broken_intra_doc_links)]
@@ -116,6 +118,7 @@ pub mod bos;
 pub mod bss;
 // pub mod capmc;
 pub mod cfs;
+pub mod fas;
 // `client` and `error` are not `pub mod` — the canonical paths are
 // `csm_rs::ShastaClient` and `csm_rs::Error` (re-exports below). The
 // modules stay private so a future internal reshuffle doesn't break
@@ -124,12 +127,45 @@ mod client;
 pub mod commands;
 pub(crate) mod common;
 pub mod error;
+pub mod filter;
 pub mod hsm;
 pub mod ims;
+pub mod kea;
 pub mod node;
+pub mod offline;
 pub mod pcs;
+pub mod report;
+pub mod sls;
+/// Kafka consumer of the SMA telemetry bus: typed streams of node state
+/// changes and console lines. Requires the `telemetry` Cargo feature.
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod watch;
 
 pub use client::ShastaClient;
+pub use common::access_log::{AccessLog, ApiCall};
+pub use common::auth_provider::{
+  AuthTokenProvider, ClientCredentials, KeycloakPasswordGrant, StaticToken,
+};
+pub use common::client_config::ClientConfig;
+pub use common::commit_cache::CommitCache;
+pub use common::csm_snapshot::CsmSnapshot;
+pub use common::events::{Event, EventSink, LogEventSink, StdoutEventSink};
+pub use common::execution_context::ExecutionContext;
+pub use common::handshake::CsmVersions;
+#[cfg(feature = "k8s-console")]
+pub use common::kubernetes::{
+  ExitStatus, KubeAuth, PodWaitTimeouts, SessionLogEvent, SessionLogStreamer,
+  wait_container_terminated,
+};
+pub use common::naming::{NamingContext, NamingPolicy};
+pub use common::poll::WaitOptions;
+pub use common::product_catalog;
+pub use common::protection;
+pub use common::rate_limit::RateLimit;
+pub use common::session_profile::{SessionProfile, SessionProfiles};
+pub use common::webhooks;
+pub use common::xname::{BmcXName, NodeXName, XName};
 pub use error::Error;
 
 // Canonical type re-exports lifted from each namespace's `mod.rs`. Only
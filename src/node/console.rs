@@ -1,21 +1,38 @@
-//! Open and interact with a node serial console via the CSM `cray-console-*` services.
+//! Open and interact with a node serial console via the CSM `cray-console-*` services,
+//! or capture console output to rotated files ([`capture_console_logs`]).
 
 use core::time;
+use std::{
+  fs::{self, File, OpenOptions},
+  io::Write,
+  path::{Path, PathBuf},
+};
 
+use futures_channel::mpsc::Sender;
 use k8s_openapi::api::core::v1::Pod;
 use kube::{
   Api,
-  api::{AttachParams, AttachedProcess},
+  api::{AttachParams, AttachedProcess, TerminalSize},
 };
 use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_stream::StreamExt;
-use tokio_util::io::ReaderStream;
+use tokio_util::{io::ReaderStream, sync::CancellationToken};
 
 use crate::{
-  common::kubernetes::{self, get_client},
+  common::{
+    kubernetes::{self, get_client},
+    poll::WaitOptions,
+  },
   error::Error,
 };
 
+/// How long [`get_container_attachment_to_cfs_session_image_target`]
+/// waits for each pod it attaches to unless told otherwise: a minute,
+/// checking every 2 s.
+pub const DEFAULT_POD_WAIT: WaitOptions =
+  WaitOptions::new(time::Duration::from_secs(60), time::Duration::from_secs(2));
+
 /// Attach to the `cray-console-node` pod that owns the given xname's
 /// serial console (via conman) and return the open process handle.
 ///
@@ -37,6 +54,163 @@ pub async fn get_container_attachment_to_conman(
 
   let pods_fabric: Api<Pod> = Api::namespaced(client, "services");
 
+  attach_conman(&pods_fabric, xname)
+    .await
+    .map(|(_, attached)| attached)
+}
+
+/// Interactive `conman` session on a node serial console, opened with
+/// [`attach_node`].
+///
+/// Console output is read from [`NodeConsole::stdout`], keystrokes are
+/// written to [`NodeConsole::stdin`] and terminal resizes are sent through
+/// [`NodeConsole::terminal_size`]. Dropping the streams ends the session;
+/// [`NodeConsole::join`] waits for the remote `conman` to exit.
+pub struct NodeConsole {
+  /// Node whose console is attached.
+  pub xname: String,
+  /// `cray-console-node` pod serving the console.
+  pub pod_name: String,
+  /// Console input.
+  pub stdin: Box<dyn AsyncWrite + Send + Unpin>,
+  /// Console output.
+  pub stdout: Box<dyn AsyncRead + Send + Unpin>,
+  /// Terminal size updates for the remote tty.
+  pub terminal_size: Sender<TerminalSize>,
+  attached: AttachedProcess,
+}
+
+impl NodeConsole {
+  /// Wait for the remote `conman` process to exit.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::ConsoleAttach`] if the exec session failed.
+  pub async fn join(self) -> Result<(), Error> {
+    let pod = self.pod_name;
+    self
+      .attached
+      .join()
+      .await
+      .map_err(|e| Error::ConsoleAttach {
+        pod,
+        cause: e.to_string(),
+      })
+  }
+}
+
+/// Open an interactive console session on `xname`.
+///
+/// Resolves the `cray-console-node` pod holding the node's console, runs
+/// `conman -j <xname>` in it on a tty and, if given, sets the initial
+/// terminal size.
+///
+/// # Errors
+///
+/// Returns [`Error::ConsoleError`] if the console pod can't be found or
+/// attached to, and [`Error::ConsoleAttach`] if the exec session lacks
+/// one of the stdin, stdout or terminal size streams.
+pub async fn attach_node(
+  xname: &str,
+  k8s_api_url: &str,
+  shasta_k8s_secrets: Value,
+  socks5_proxy: Option<&str>,
+  initial_size_opt: Option<TerminalSize>,
+) -> Result<NodeConsole, Error> {
+  let client =
+    get_client(k8s_api_url, shasta_k8s_secrets, socks5_proxy).await?;
+
+  attach_node_with_client(client, xname, initial_size_opt).await
+}
+
+/// [`attach_node`] through an existing `kube::Client`, e.g. one cached
+/// by [`crate::ShastaClient::kube_client`].
+///
+/// # Errors
+///
+/// Same as [`attach_node`].
+pub async fn attach_node_with_client(
+  client: kube::Client,
+  xname: &str,
+  initial_size_opt: Option<TerminalSize>,
+) -> Result<NodeConsole, Error> {
+  let pods_fabric: Api<Pod> = Api::namespaced(client, "services");
+
+  let (pod_name, mut attached) = attach_conman(&pods_fabric, xname).await?;
+
+  let missing = |stream: &str| Error::ConsoleAttach {
+    pod: pod_name.clone(),
+    cause: format!("kube exec did not provide a {stream} stream"),
+  };
+
+  let mut terminal_size = attached
+    .terminal_size()
+    .ok_or_else(|| missing("terminal-size"))?;
+  let stdin = attached.stdin().ok_or_else(|| missing("stdin"))?;
+  let stdout = attached.stdout().ok_or_else(|| missing("stdout"))?;
+
+  if let Some(initial_size) = initial_size_opt {
+    terminal_size
+      .try_send(initial_size)
+      .map_err(|e| Error::ConsoleAttach {
+        pod: pod_name.clone(),
+        cause: e.to_string(),
+      })?;
+  }
+
+  Ok(NodeConsole {
+    xname: xname.to_string(),
+    pod_name,
+    stdin: Box::new(stdin),
+    stdout: Box::new(stdout),
+    terminal_size,
+    attached,
+  })
+}
+
+/// Resolve the `cray-console-node` pod holding the console of `xname`
+/// and open an interactive `conman` session on it. Returns the pod name
+/// along with the attached process.
+async fn attach_conman(
+  pods_fabric: &Api<Pod>,
+  xname: &str,
+) -> Result<(String, AttachedProcess), Error> {
+  let console_pod_name = get_console_pod_name(pods_fabric, xname).await?;
+
+  let command = vec!["conman", "-j", xname]; // Enter the container and open conman to access node's console
+  // let command = vec!["bash"]; // Enter the container and open bash to start an interactive
+  // terminal session
+
+  log::info!("Console pod name: {console_pod_name}");
+
+  log::info!("Connecting to console {xname}");
+
+  pods_fabric
+        .exec(
+            &console_pod_name,
+            command,
+            &AttachParams::default()
+                .container("cray-console-node")
+                .stdin(true)
+                .stdout(true)
+                .stderr(false) // Note to self: tty and stderr cannot both be true
+                .tty(true),
+        )
+        .await
+        .map_err(|e| {
+            Error::ConsoleError(format!(
+                "Error attaching to container 'cray-console-node' in pod '{console_pod_name}'. Reason:\n{e}. Exit"
+            ))
+        })
+    .map(|attached| (console_pod_name, attached))
+}
+
+/// Ask `cray-console-operator` which `cray-console-node` pod holds the
+/// console of `xname`.
+async fn get_console_pod_name(
+  pods_fabric: &Api<Pod>,
+  xname: &str,
+) -> Result<String, Error> {
   let params = kube::api::ListParams::default()
     .limit(1)
     .labels("app.kubernetes.io/name=cray-console-operator");
@@ -82,44 +256,55 @@ pub async fn get_container_attachment_to_conman(
   let stdout_str = std::str::from_utf8(&next_stdout)?;
   let output_json: Value = serde_json::from_str(stdout_str)?;
 
-  let console_pod_name = output_json
+  output_json
     .get("podname")
     .and_then(Value::as_str)
+    .map(str::to_string)
     .ok_or_else(|| {
       Error::ConsoleError(format!(
         "console-operator response missing string field 'podname' (got: {output_json})"
       ))
-    })?;
+    })
+}
 
-  let command = vec!["conman", "-j", xname]; // Enter the container and open conman to access node's console
-  // let command = vec!["bash"]; // Enter the container and open bash to start an interactive
-  // terminal session
+/// List the pods matching `params`, polling as `wait_options` says
+/// until at least one exists.
+async fn wait_for_pods(
+  pods_fabric: &Api<Pod>,
+  params: &kube::api::ListParams,
+  cfs_session_name: &str,
+  wait_options: WaitOptions,
+) -> Result<kube::api::ObjectList<Pod>, Error> {
+  let deadline = tokio::time::Instant::now() + wait_options.timeout;
+  let mut delay = wait_options.poll_interval;
 
-  log::info!("Console pod name: {console_pod_name}");
+  let mut pods = pods_fabric.list(params).await?;
 
-  log::info!("Connecting to console {xname}");
+  // Waiting for pod to start
+  while pods.items.is_empty() && tokio::time::Instant::now() + delay <= deadline
+  {
+    log::info!(
+      "Pod for cfs session {} not ready. Trying again in {} secs.",
+      cfs_session_name,
+      delay.as_secs()
+    );
+    tokio::time::sleep(delay).await;
+    delay = wait_options.next_delay(delay);
+    pods = pods_fabric.list(params).await?;
+  }
 
-  pods_fabric
-        .exec(
-            console_pod_name,
-            command,
-            &AttachParams::default()
-                .container("cray-console-node")
-                .stdin(true)
-                .stdout(true)
-                .stderr(false) // Note to self: tty and stderr cannot both be true
-                .tty(true),
-        )
-        .await
-        .map_err(|e| {
-            Error::ConsoleError(format!(
-                "Error attaching to container 'cray-console-node' in pod '{console_pod_name}'. Reason:\n{e}. Exit"
-            ))
-        })
+  if pods.items.is_empty() {
+    return Err(Error::ConsoleError(format!(
+      "Pod for cfs session {cfs_session_name} not ready. Aborting operation"
+    )));
+  }
+
+  Ok(pods)
 }
 
 /// Attach to the Ansible container of a CFS session's image-build pod
-/// so the caller can stream its logs / shell.
+/// so the caller can stream its logs / shell, waiting for each pod as
+/// `wait_options` says ([`DEFAULT_POD_WAIT`] for the usual pacing).
 ///
 /// # Errors
 ///
@@ -131,6 +316,7 @@ pub async fn get_container_attachment_to_cfs_session_image_target(
   k8s_api_url: &str,
   shasta_k8s_secrets: Value,
   socks5_proxy: Option<&str>,
+  wait_options: WaitOptions,
 ) -> Result<AttachedProcess, Error> {
   let client =
     get_client(k8s_api_url, shasta_k8s_secrets, socks5_proxy).await?;
@@ -141,29 +327,9 @@ pub async fn get_container_attachment_to_cfs_session_image_target(
     .limit(1)
     .labels(format!("cfsession={cfs_session_name}").as_str());
 
-  let mut pods = pods_fabric.list(&params).await?;
-
-  let mut i = 0;
-  let max = 30;
-
-  // Waiting for pod to start
-  while pods.items.is_empty() && i <= max {
-    log::info!(
-      "Pod for cfs session {} not ready. Trying again in 2 secs. Attempt {} of {}",
-      cfs_session_name,
-      i + 1,
-      max
-    );
-    i += 1;
-    tokio::time::sleep(time::Duration::from_secs(2)).await;
-    pods = pods_fabric.list(&params).await?;
-  }
-
-  if pods.items.is_empty() {
-    return Err(Error::ConsoleError(format!(
-      "Pod for cfs session {cfs_session_name} not ready. Aborting operation"
-    )));
-  }
+  let pods =
+    wait_for_pods(&pods_fabric, &params, cfs_session_name, wait_options)
+      .await?;
 
   let console_operator_pod_name = &pods
     .items
@@ -214,29 +380,9 @@ pub async fn get_container_attachment_to_cfs_session_image_target(
     .limit(1)
     .labels(format!("job-name={ansible_target_container_label}").as_str());
 
-  let mut pods = pods_fabric.list(&params).await?;
-
-  let mut i = 0;
-  let max = 30;
-
-  // Waiting for pod to start
-  while pods.items.is_empty() && i <= max {
-    log::info!(
-      "Pod for cfs session {} not ready. Trying again in 2 secs. Attempt {} of {}",
-      cfs_session_name,
-      i + 1,
-      max
-    );
-    i += 1;
-    tokio::time::sleep(time::Duration::from_secs(2)).await;
-    pods = pods_fabric.list(&params).await?;
-  }
-
-  if pods.items.is_empty() {
-    return Err(Error::ConsoleError(format!(
-      "Pod for cfs session {cfs_session_name} not ready. Aborting operation"
-    )));
-  }
+  let pods =
+    wait_for_pods(&pods_fabric, &params, cfs_session_name, wait_options)
+      .await?;
 
   let console_operator_pod = &pods.items[0].clone();
 
@@ -269,3 +415,226 @@ pub async fn get_container_attachment_to_cfs_session_image_target(
       ))
     })
 }
+
+/// Size-based rotation for [`capture_console_logs`] output files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation {
+  /// Rotate `<xname>.log` once writing more would make it larger than
+  /// this many bytes.
+  pub max_file_bytes: u64,
+  /// Number of rotated files (`<xname>.log.1` newest, ...) to keep.
+  pub max_rotated_files: usize,
+}
+
+impl Default for Rotation {
+  fn default() -> Self {
+    Self {
+      max_file_bytes: 10 * 1024 * 1024,
+      max_rotated_files: 5,
+    }
+  }
+}
+
+/// Append-only log file rotated according to a [`Rotation`].
+struct RotatingFile {
+  path: PathBuf,
+  rotation: Rotation,
+  file: File,
+  written: u64,
+}
+
+impl RotatingFile {
+  fn open(path: PathBuf, rotation: Rotation) -> std::io::Result<Self> {
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let written = file.metadata()?.len();
+
+    Ok(Self {
+      path,
+      rotation,
+      file,
+      written,
+    })
+  }
+
+  fn rotated_path(&self, index: usize) -> PathBuf {
+    let mut path = self.path.clone().into_os_string();
+    path.push(format!(".{index}"));
+    PathBuf::from(path)
+  }
+
+  fn rotate(&mut self) -> std::io::Result<()> {
+    if self.rotation.max_rotated_files > 0 {
+      for index in (1..self.rotation.max_rotated_files).rev() {
+        let from = self.rotated_path(index);
+        if from.exists() {
+          fs::rename(&from, self.rotated_path(index + 1))?;
+        }
+      }
+      fs::rename(&self.path, self.rotated_path(1))?;
+    }
+
+    self.file = OpenOptions::new()
+      .create(true)
+      .write(true)
+      .truncate(true)
+      .open(&self.path)?;
+    self.written = 0;
+
+    Ok(())
+  }
+
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<()> {
+    let len = buf.len() as u64;
+    if self.written > 0 && self.written + len > self.rotation.max_file_bytes {
+      self.rotate()?;
+    }
+
+    self.file.write_all(buf)?;
+    self.written += len;
+
+    Ok(())
+  }
+}
+
+/// Attach read-only (`conman -m`) to the console of every node in
+/// `xname_vec` and tee its output to `<output_dir>/<xname>.log`, rotated
+/// according to `rotation`. Meant for unattended capture, e.g. of boot
+/// logs during a rolling reboot.
+///
+/// Capture runs until `cancellation_token` is cancelled or every console
+/// stream ends. A node whose console cannot be attached or written
+/// stops being captured without affecting the others; its error is
+/// logged and returned in the per-node results.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if `output_dir` cannot be created or the
+/// Kubernetes client cannot be built. Per-node failures are reported in
+/// the returned `(xname, result)` list.
+pub async fn capture_console_logs(
+  xname_vec: &[String],
+  k8s_api_url: &str,
+  shasta_k8s_secrets: Value,
+  socks5_proxy: Option<&str>,
+  output_dir: &Path,
+  rotation: Rotation,
+  cancellation_token: CancellationToken,
+) -> Result<Vec<(String, Result<(), Error>)>, Error> {
+  fs::create_dir_all(output_dir)?;
+
+  let client =
+    get_client(k8s_api_url, shasta_k8s_secrets, socks5_proxy).await?;
+  let pods_fabric: Api<Pod> = Api::namespaced(client, "services");
+
+  let mut tasks = tokio::task::JoinSet::new();
+
+  for xname in xname_vec {
+    let xname = xname.clone();
+    let pods_fabric = pods_fabric.clone();
+    let path = output_dir.join(format!("{xname}.log"));
+    let cancellation_token = cancellation_token.clone();
+
+    tasks.spawn(async move {
+      let result = capture_console_log(
+        &pods_fabric,
+        &xname,
+        path,
+        rotation,
+        cancellation_token,
+      )
+      .await;
+      if let Err(e) = &result {
+        log::error!("Console capture of '{xname}' stopped: {e}");
+      }
+      (xname, result)
+    });
+  }
+
+  let mut result_vec = Vec::with_capacity(xname_vec.len());
+  while let Some(message) = tasks.join_next().await {
+    result_vec.push(message.map_err(|e| Error::ConsoleError(e.to_string()))?);
+  }
+
+  Ok(result_vec)
+}
+
+/// Capture one node console for [`capture_console_logs`].
+async fn capture_console_log(
+  pods_fabric: &Api<Pod>,
+  xname: &str,
+  path: PathBuf,
+  rotation: Rotation,
+  cancellation_token: CancellationToken,
+) -> Result<(), Error> {
+  let console_pod_name = get_console_pod_name(pods_fabric, xname).await?;
+
+  log::info!("Capturing console {xname} from pod {console_pod_name}");
+
+  let mut attached = pods_fabric
+    .exec(
+      &console_pod_name,
+      vec!["conman", "-m", xname],
+      &AttachParams::default()
+        .container("cray-console-node")
+        .stdin(false)
+        .stdout(true)
+        .stderr(false),
+    )
+    .await
+    .map_err(|e| Error::ConsoleAttach {
+      pod: console_pod_name.clone(),
+      cause: e.to_string(),
+    })?;
+
+  let stdout = attached.stdout().ok_or_else(|| {
+    Error::ConsoleError(format!("console of '{xname}' has no stdout"))
+  })?;
+  let mut stdout_stream = ReaderStream::new(stdout);
+  let mut log_file = RotatingFile::open(path, rotation)?;
+
+  loop {
+    tokio::select! {
+      () = cancellation_token.cancelled() => break,
+      frame_opt = stdout_stream.next() => {
+        let Some(frame) = frame_opt else {
+          break;
+        };
+        log_file.write(&frame?)?;
+      }
+    }
+  }
+
+  attached.abort();
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rotating_file_keeps_max_rotated_files() {
+    let dir = std::env::temp_dir()
+      .join(format!("csm-rs-console-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("x1000c0s0b0n0.log");
+
+    let rotation = Rotation {
+      max_file_bytes: 4,
+      max_rotated_files: 2,
+    };
+    let mut log_file = RotatingFile::open(path.clone(), rotation).unwrap();
+    for chunk in ["aaaa", "bbbb", "cccc", "dddd"] {
+      log_file.write(chunk.as_bytes()).unwrap();
+    }
+
+    let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+    assert_eq!(read("x1000c0s0b0n0.log"), "dddd");
+    assert_eq!(read("x1000c0s0b0n0.log.1"), "cccc");
+    assert_eq!(read("x1000c0s0b0n0.log.2"), "bbbb");
+    assert!(!dir.join("x1000c0s0b0n0.log.3").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
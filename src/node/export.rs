@@ -0,0 +1,219 @@
+//! Serialize [`NodeDetails`] collections for other tools: canonical
+//! JSON, CSV and the Prometheus text exposition format.
+//!
+//! Every format lists the nodes sorted by xname, so exports of the same
+//! cluster state are byte for byte identical and diff cleanly. JSON
+//! keeps the [`NodeDetails`] field names; CSV uses the same names as
+//! header (see [`crate::report::Tabular`]).
+//!
+//! The Prometheus output has one gauge per node and metric, labelled
+//! with `xname`, `nid` and `hsm`, for a thin exporter to serve as is:
+//!
+//! - `csm_node_power_state{state="..."}` — 1, with the HSM state as
+//!   label.
+//! - `csm_node_configuration_status{status="..."}` — 1, with the CFS
+//!   configuration status as label.
+//! - `csm_node_cfs_enabled` — 1 if CFS configures the node, 0 if not.
+//! - `csm_node_cfs_error_count` — CFS error count of the node.
+//!
+//! Nodes whose value is unknown (e.g. the CFS component is missing)
+//! have no sample for the metric.
+
+use std::fmt::Write;
+
+use crate::{
+  error::Error,
+  node::types::NodeDetails,
+  report::{Table, Tabular},
+};
+
+/// Prefix of the Prometheus metric names.
+pub const METRIC_PREFIX: &str = "csm_node";
+
+/// `node_details_vec` sorted by xname.
+fn sorted(node_details_vec: &[NodeDetails]) -> Vec<&NodeDetails> {
+  let mut sorted_vec: Vec<&NodeDetails> = node_details_vec.iter().collect();
+  sorted_vec.sort_by(|a, b| a.xname.cmp(&b.xname));
+  sorted_vec
+}
+
+/// `node_details_vec` as a pretty printed JSON array, sorted by xname.
+///
+/// # Errors
+///
+/// Returns [`Error::SerdeJsonError`] if serialization fails.
+pub fn to_json(node_details_vec: &[NodeDetails]) -> Result<String, Error> {
+  Ok(serde_json::to_string_pretty(&sorted(node_details_vec))?)
+}
+
+/// `node_details_vec` as CSV with a header line, sorted by xname.
+#[must_use]
+pub fn to_csv(node_details_vec: &[NodeDetails]) -> String {
+  let mut table = Table::new(NodeDetails::columns());
+  table.rows = sorted(node_details_vec)
+    .into_iter()
+    .flat_map(Tabular::rows)
+    .collect();
+
+  table.to_csv()
+}
+
+/// `node_details_vec` in the Prometheus text exposition format.
+#[must_use]
+pub fn to_prometheus(node_details_vec: &[NodeDetails]) -> String {
+  let node_details_vec = sorted(node_details_vec);
+  let mut exposition = String::new();
+
+  write_gauge(
+    &mut exposition,
+    &node_details_vec,
+    "power_state",
+    "HSM state of the node, as the state label.",
+    |node_details| {
+      let state = escape_label_value(&node_details.power_status);
+      Some((format!(",state=\"{state}\""), 1))
+    },
+  );
+  write_gauge(
+    &mut exposition,
+    &node_details_vec,
+    "configuration_status",
+    "CFS configuration status of the node, as the status label.",
+    |node_details| {
+      let status = escape_label_value(&node_details.configuration_status);
+      Some((format!(",status=\"{status}\""), 1))
+    },
+  );
+  write_gauge(
+    &mut exposition,
+    &node_details_vec,
+    "cfs_enabled",
+    "1 if CFS configures the node, 0 if not.",
+    |node_details| {
+      let enabled = node_details.enabled.parse::<bool>().ok()?;
+      Some((String::new(), i64::from(enabled)))
+    },
+  );
+  write_gauge(
+    &mut exposition,
+    &node_details_vec,
+    "cfs_error_count",
+    "CFS error count of the node.",
+    |node_details| {
+      let error_count = node_details.error_count.parse::<i64>().ok()?;
+      Some((String::new(), error_count))
+    },
+  );
+
+  exposition
+}
+
+/// Append gauge `name` to `exposition`, with one sample per node
+/// `sample` gives extra labels (`,key="value"...`) and a value for.
+fn write_gauge(
+  exposition: &mut String,
+  node_details_vec: &[&NodeDetails],
+  name: &str,
+  help: &str,
+  sample: impl Fn(&NodeDetails) -> Option<(String, i64)>,
+) {
+  let _ = writeln!(exposition, "# HELP {METRIC_PREFIX}_{name} {help}");
+  let _ = writeln!(exposition, "# TYPE {METRIC_PREFIX}_{name} gauge");
+
+  for node_details in node_details_vec {
+    if let Some((extra_labels, value)) = sample(node_details) {
+      let _ = writeln!(
+        exposition,
+        "{METRIC_PREFIX}_{name}{{xname=\"{}\",nid=\"{}\",hsm=\"{}\"{extra_labels}}} {value}",
+        escape_label_value(&node_details.xname),
+        escape_label_value(&node_details.nid),
+        escape_label_value(&node_details.hsm),
+      );
+    }
+  }
+}
+
+/// `value` escaped for a Prometheus label value.
+fn escape_label_value(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn node_details(
+    xname: &str,
+    nid: &str,
+    enabled: &str,
+    error_count: &str,
+  ) -> NodeDetails {
+    NodeDetails {
+      xname: xname.to_string(),
+      nid: nid.to_string(),
+      hsm: "zinal".to_string(),
+      power_status: "READY".to_string(),
+      desired_configuration: "cos-config".to_string(),
+      configuration_status: "configured".to_string(),
+      enabled: enabled.to_string(),
+      error_count: error_count.to_string(),
+      boot_image_id: "1234".to_string(),
+      boot_configuration: "cos-config".to_string(),
+      kernel_params: "console=ttyS0,115200 quiet".to_string(),
+      hsm_flag: "OK".to_string(),
+      last_boot_time: None,
+      last_configuration_time: None,
+      boot_image_created: None,
+    }
+  }
+
+  fn node_details_vec() -> Vec<NodeDetails> {
+    vec![
+      node_details("x1000c0s0b0n1", "nid000002", "Not found", "Not found"),
+      node_details("x1000c0s0b0n0", "nid000001", "true", "3"),
+    ]
+  }
+
+  #[test]
+  fn json_and_csv_are_sorted_by_xname() {
+    let json: serde_json::Value =
+      serde_json::from_str(&to_json(&node_details_vec()).unwrap()).unwrap();
+    assert_eq!(json[0]["xname"], "x1000c0s0b0n0");
+    assert_eq!(json[1]["error_count"], "Not found");
+
+    let csv = to_csv(&node_details_vec());
+    let line_vec: Vec<&str> = csv.lines().collect();
+    assert_eq!(line_vec.len(), 3);
+    assert!(line_vec[0].starts_with("xname,nid,hsm,power_status,"));
+    assert!(line_vec[1].starts_with("x1000c0s0b0n0,nid000001,zinal,READY,"));
+    assert!(line_vec[1].contains(",\"console=ttyS0,115200 quiet\","));
+  }
+
+  #[test]
+  fn prometheus_has_one_sample_per_known_value() {
+    let exposition = to_prometheus(&node_details_vec());
+
+    assert!(exposition.contains("# TYPE csm_node_power_state gauge\n"));
+    assert!(exposition.contains(
+      "csm_node_power_state{xname=\"x1000c0s0b0n0\",nid=\"nid000001\",hsm=\"zinal\",state=\"READY\"} 1\n"
+    ));
+    assert!(exposition.contains(
+      "csm_node_cfs_error_count{xname=\"x1000c0s0b0n0\",nid=\"nid000001\",hsm=\"zinal\"} 3\n"
+    ));
+    assert!(exposition.contains(
+      "csm_node_cfs_enabled{xname=\"x1000c0s0b0n0\",nid=\"nid000001\",hsm=\"zinal\"} 1\n"
+    ));
+    assert!(
+      !exposition.contains("csm_node_cfs_enabled{xname=\"x1000c0s0b0n1\"")
+    );
+    assert_eq!(
+      exposition.matches("csm_node_configuration_status{").count(),
+      2
+    );
+
+    assert_eq!(escape_label_value("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+  }
+}
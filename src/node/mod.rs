@@ -7,7 +7,10 @@
 //! Submodules:
 //!
 //! - [`console`] — open and interact with a node's serial console via
-//!   the CSM `cray-console-operator` / `cray-console-node` services.
+//!   the CSM `cray-console-operator` / `cray-console-node` services, or
+//!   capture it to rotated log files.
+//! - [`export`] — serialize node details as JSON, CSV or Prometheus
+//!   gauges.
 //!
 //! `node::types` and `node::utils` are crate-internal — their helpers
 //! are surfaced through the `ShastaClient` and `commands` layers.
@@ -17,5 +20,6 @@
 /// the `k8s-console` Cargo feature (Kubernetes client).
 #[cfg(feature = "k8s-console")]
 pub mod console;
+pub mod export;
 pub mod types;
 pub mod utils;
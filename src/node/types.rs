@@ -18,4 +18,33 @@ pub struct NodeDetails {
   pub boot_image_id: String,
   pub boot_configuration: String,
   pub kernel_params: String,
+  /// HSM component flag (`OK`, `Warning`, `Alert`, `Locked`, ...).
+  pub hsm_flag: String,
+  /// Last power state change reported by PCS while the node is on, which
+  /// is when it last booted.
+  pub last_boot_time: Option<String>,
+  /// Most recent `lastUpdated` of the node's CFS component layers.
+  pub last_configuration_time: Option<String>,
+  /// Creation date of `boot_image_id` in IMS.
+  pub boot_image_created: Option<String>,
+}
+
+/// A CSM service [`super::utils::get_node_details_partial`] could not get
+/// data from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnavailableSource {
+  /// Service and resource, e.g. `BSS boot parameters`.
+  pub source: String,
+  /// Error returned when querying it.
+  pub reason: String,
+}
+
+/// Result of [`super::utils::get_node_details_partial`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PartialNodeDetails {
+  /// Details of every requested node; fields backed by an unavailable
+  /// source read `Unavailable(<source>)` or are `None`.
+  pub node_details: Vec<NodeDetails>,
+  /// Sources that could not be queried.
+  pub unavailable_sources: Vec<UnavailableSource>,
 }
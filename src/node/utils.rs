@@ -5,14 +5,20 @@
 //! non-test builds.
 #![allow(dead_code)]
 
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use std::{collections::HashMap, time::Instant};
 
 use regex::Regex;
-use tokio::sync::Semaphore;
 
-use crate::{bss, cfs, error::Error, hsm};
+use crate::{
+  bss, cfs,
+  common::xname::NodeXName,
+  error::Error,
+  hsm,
+  ims::image::http_client::types::Image,
+  pcs::power_status::types::{PowerState, PowerStatus},
+};
 
-use super::types::NodeDetails;
+use super::types::{NodeDetails, PartialNodeDetails, UnavailableSource};
 
 /// Validate user has access to a list of HSM group members provided.
 /// HSM members user is asking for are taken from cli command
@@ -98,13 +104,9 @@ pub fn validate_xname_format_vec(node_vec: Vec<String>) -> bool {
   node_vec.iter().all(|nid| validate_xname_format(nid))
 }
 
-/// Validate xname is correct (it uses regex taken from HPE Cray CSM docs)
+/// Validate xname is a node xname; see [`NodeXName`].
 pub fn validate_xname_format(xname: &str) -> bool {
-  let xname_re =
-    Regex::new(r"^x\d{4}c[0-7]s([0-9]|[1-5][0-9]|6[0-4])b[0-1]n[0-7]$")
-      .unwrap();
-
-  xname_re.is_match(xname)
+  NodeXName::parse(xname).is_ok()
 }
 
 /// Validates a list of xnames.
@@ -150,6 +152,9 @@ pub async fn validate_xnames_format_and_membership_against_single_hsm(
 ///
 /// CSM rejects requests that include too many xnames in a single call;
 /// this helper chunks `xnames` and dispatches the batches concurrently.
+///
+/// Fails if any CSM service is unreachable; see
+/// [`get_node_details_partial`] to get what could be collected instead.
 pub async fn get_node_details(
   shasta_token: &str,
   shasta_base_url: &str,
@@ -157,18 +162,122 @@ pub async fn get_node_details(
   socks5_proxy: Option<&str>,
   xname_list: Vec<String>,
 ) -> Result<Vec<NodeDetails>, Error> {
+  crate::common::request_id::scope(
+    "get_node_details",
+    fetch_node_details(
+      shasta_token,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      xname_list,
+      false,
+    ),
+  )
+  .await
+  .map(|partial_node_details| partial_node_details.node_details)
+}
+
+/// Like [`get_node_details`], but keeps going when a CSM service is
+/// down: fields coming from it read `Unavailable(<source>)` (or `None`
+/// for optional fields) and the failure is listed in
+/// [`PartialNodeDetails::unavailable_sources`].
+///
+/// # Errors
+///
+/// Returns an [`Error`] if the client cannot be built, or if a service
+/// answers but its data is inconsistent (e.g. a node missing from HSM).
+pub async fn get_node_details_partial(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  xname_list: Vec<String>,
+) -> Result<PartialNodeDetails, Error> {
+  crate::common::request_id::scope(
+    "get_node_details",
+    fetch_node_details(
+      shasta_token,
+      shasta_base_url,
+      shasta_root_cert,
+      socks5_proxy,
+      xname_list,
+      true,
+    ),
+  )
+  .await
+}
+
+const CFS_COMPONENTS: &str = "CFS components";
+const BSS_BOOT_PARAMETERS: &str = "BSS boot parameters";
+const HSM_COMPONENTS: &str = "HSM components";
+const HSM_MEMBERSHIPS: &str = "HSM memberships";
+const PCS_POWER_STATUS: &str = "PCS power status";
+const IMS_IMAGES: &str = "IMS images";
+const CFS_SESSIONS: &str = "CFS sessions";
+
+/// Data fetched from each CSM service; `None` when the service was
+/// unavailable in partial mode.
+struct NodeSources {
+  cfs_component_vec: Option<Vec<cfs::v2::Component>>,
+  boot_param_vec: Option<Vec<bss::types::BootParameters>>,
+  hsm_component_vec: Option<Vec<hsm::component::types::Component>>,
+  membership_vec: Option<Vec<hsm::memberships::types::Membership>>,
+  power_status_vec: Option<Vec<PowerStatus>>,
+  image_vec: Option<Vec<Image>>,
+  cfs_session_vec: Option<Vec<cfs::v2::CfsSessionGetResponse>>,
+}
+
+/// `Ok(Some(value))` on success. On failure, records the source as
+/// unavailable and returns `Ok(None)` in partial mode, or the error
+/// otherwise.
+fn available<T>(
+  source: &'static str,
+  result: Result<T, Error>,
+  partial: bool,
+  unavailable_source_vec: &mut Vec<UnavailableSource>,
+) -> Result<Option<T>, Error> {
+  match result {
+    Ok(value) => Ok(Some(value)),
+    Err(e) if partial => {
+      tracing::warn!("{source} unavailable: {e}");
+      unavailable_source_vec.push(UnavailableSource {
+        source: source.to_string(),
+        reason: e.to_string(),
+      });
+      Ok(None)
+    }
+    Err(e) => Err(e),
+  }
+}
+
+/// Body of [`get_node_details`] and [`get_node_details_partial`], run
+/// inside their correlation scope.
+async fn fetch_node_details(
+  shasta_token: &str,
+  shasta_base_url: &str,
+  shasta_root_cert: &[u8],
+  socks5_proxy: Option<&str>,
+  xname_list: Vec<String>,
+  partial: bool,
+) -> Result<PartialNodeDetails, Error> {
   let start = Instant::now();
 
-  let shasta_client = crate::ShastaClient::new(
+  let shasta_client = crate::ShastaClient::scoped_or_new(
     shasta_base_url,
     shasta_root_cert.to_vec(),
     socks5_proxy.map(str::to_owned),
   )?;
 
+  let xname_str_vec: Vec<&str> =
+    xname_list.iter().map(String::as_str).collect();
+
   let (
     components_status_rslt,
     node_boot_params_vec_rslt,
     node_hsm_info_rslt,
+    node_membership_vec_rslt,
+    power_status_all_rslt,
+    image_vec_rslt,
     cfs_session_vec_rslt,
   ) = tokio::join!(
     // Get CFS component status
@@ -177,6 +286,17 @@ pub async fn get_node_details(
     shasta_client.bss_bootparameters_get_multiple(shasta_token, &xname_list),
     // Get HSM component status (needed to get NIDS)
     shasta_client.hsm_component_get_and_filter(shasta_token, &xname_list),
+    // Get HSM group memberships, in one request for all nodes
+    shasta_client.hsm_memberships_get_all_filtered(shasta_token, &xname_list),
+    // Get power status (its last change is the last boot time)
+    shasta_client.pcs_power_status_post(
+      shasta_token,
+      Some(xname_str_vec.as_slice()),
+      None,
+      None,
+    ),
+    // Get images to know when each boot image was built
+    shasta_client.ims_image_get_all(shasta_token),
     // Get CFS sessions
     cfs::session::get_and_sort(
       shasta_token,
@@ -191,155 +311,302 @@ pub async fn get_node_details(
     )
   );
 
-  let node_hsm_info = node_hsm_info_rslt?;
-  let node_boot_params_vec = node_boot_params_vec_rslt?;
-  let cfs_session_vec = cfs_session_vec_rslt?;
-  let components_status = components_status_rslt?;
+  let mut unavailable_sources = Vec::new();
+  let sources = NodeSources {
+    hsm_component_vec: available(
+      HSM_COMPONENTS,
+      node_hsm_info_rslt,
+      partial,
+      &mut unavailable_sources,
+    )?,
+    boot_param_vec: available(
+      BSS_BOOT_PARAMETERS,
+      node_boot_params_vec_rslt,
+      partial,
+      &mut unavailable_sources,
+    )?,
+    cfs_session_vec: available(
+      CFS_SESSIONS,
+      cfs_session_vec_rslt,
+      partial,
+      &mut unavailable_sources,
+    )?,
+    cfs_component_vec: available(
+      CFS_COMPONENTS,
+      components_status_rslt,
+      partial,
+      &mut unavailable_sources,
+    )?,
+    membership_vec: available(
+      HSM_MEMBERSHIPS,
+      node_membership_vec_rslt,
+      partial,
+      &mut unavailable_sources,
+    )?,
+    power_status_vec: available(
+      PCS_POWER_STATUS,
+      power_status_all_rslt.map(|power_status_all| power_status_all.status),
+      partial,
+      &mut unavailable_sources,
+    )?,
+    image_vec: available(
+      IMS_IMAGES,
+      image_vec_rslt,
+      partial,
+      &mut unavailable_sources,
+    )?,
+  };
+
+  let node_details = assemble_node_details(xname_list, &sources)?;
+
+  let duration = start.elapsed();
+  tracing::debug!("Time elapsed to get node details is: {duration:?}");
+
+  Ok(PartialNodeDetails {
+    node_details,
+    unavailable_sources,
+  })
+}
+
+/// Placeholder for a field whose CSM `source` was unavailable.
+fn unavailable(source: &str) -> String {
+  format!("Unavailable({source})")
+}
+
+/// Build one [`NodeDetails`] per xname out of the fetched `sources`.
+fn assemble_node_details(
+  xname_list: Vec<String>,
+  sources: &NodeSources,
+) -> Result<Vec<NodeDetails>, Error> {
+  // HSM group labels per xname
+  let hsm_by_xname_opt: Option<HashMap<&str, String>> =
+    sources.membership_vec.as_ref().map(|membership_vec| {
+      membership_vec
+        .iter()
+        .filter_map(|membership| {
+          let xname = membership.id.as_ref()?;
+          Some((xname.0.as_str(), membership.group_labels.join(", ")))
+        })
+        .collect()
+    });
+
+  let last_boot_time_by_xname: HashMap<&str, &str> = sources
+    .power_status_vec
+    .iter()
+    .flatten()
+    .filter(|power_status| {
+      matches!(power_status.power_state, Some(PowerState::On))
+    })
+    .map(|power_status| {
+      (
+        power_status.xname.as_str(),
+        power_status.last_updated.as_str(),
+      )
+    })
+    .collect();
+
+  let image_created_by_id: HashMap<&str, &str> = sources
+    .image_vec
+    .iter()
+    .flatten()
+    .filter_map(|image| Some((image.id.as_deref()?, image.created.as_deref()?)))
+    .collect();
 
   // ------------------------------------------------------------------------
-  // Get and collect HSM members
+  // Collect node details
   let mut node_details_map = HashMap::new();
-  let mut tasks = tokio::task::JoinSet::new();
-
-  let sem = Arc::new(Semaphore::new(10)); // CSM 1.3.1 higher number of concurrent tasks won't
 
   for xname in xname_list {
-    let shasta_token_string = shasta_token.to_string();
-    let shasta_base_url_string = shasta_base_url.to_string();
-    let shasta_root_cert_vec = shasta_root_cert.to_vec();
-    let socks5_proxy_opt = socks5_proxy.map(str::to_owned);
-
-    // find component details
-    let component_details_opt = components_status
-      .iter()
-      .find(|component_status| component_status.id.as_ref().eq(&Some(&xname)));
-
-    let Some(component_details) = component_details_opt else {
-      return Err(Error::Message(format!(
-        "ERROR - CFS component details for node {xname}"
-      )));
+    let hsm = hsm_by_xname_opt.as_ref().map_or_else(
+      || unavailable(HSM_MEMBERSHIPS),
+      |hsm_by_xname| {
+        hsm_by_xname
+          .get(xname.as_str())
+          .cloned()
+          .unwrap_or_default()
+      },
+    );
+
+    // CFS component fields are all optional on the wire (a node may
+    // have no assigned configuration, no recorded state, etc.). Fall
+    // back to the "Not found" sentinel used elsewhere in this function
+    // rather than panicking on None.
+    let (
+      desired_configuration_str,
+      configuration_status_str,
+      enabled_str,
+      error_count_str,
+      last_configuration_time,
+    ) = if let Some(components_status) = &sources.cfs_component_vec {
+      // find component details
+      let component_details_opt = components_status
+        .iter()
+        .find(|component_status| component_status.id.as_ref() == Some(&xname));
+
+      let Some(component_details) = component_details_opt else {
+        return Err(Error::Message(format!(
+          "ERROR - CFS component details for node {xname}"
+        )));
+      };
+
+      (
+        component_details
+          .desired_config
+          .clone()
+          .unwrap_or_else(|| "Not found".to_string()),
+        component_details
+          .configuration_status
+          .clone()
+          .unwrap_or_else(|| "Not found".to_string()),
+        component_details
+          .enabled
+          .as_ref()
+          .map_or_else(|| "Not found".to_string(), bool::to_string),
+        component_details
+          .error_count
+          .as_ref()
+          .map_or_else(|| "Not found".to_string(), u64::to_string),
+        // CFS timestamps are RFC 3339 in UTC, so they compare as strings
+        component_details.state.as_ref().and_then(|state_vec| {
+          state_vec
+            .iter()
+            .filter_map(|state| state.last_updated.clone())
+            .max()
+        }),
+      )
+    } else {
+      (
+        unavailable(CFS_COMPONENTS),
+        unavailable(CFS_COMPONENTS),
+        unavailable(CFS_COMPONENTS),
+        unavailable(CFS_COMPONENTS),
+        None,
+      )
     };
 
-    let desired_configuration = &component_details.desired_config;
-    let configuration_status = &component_details.configuration_status;
-    let enabled = component_details.enabled;
-    let error_count = component_details.error_count;
-
-    // Get node HSM details. `Component100Component.id` is
-    // `Option<XName100>`; compare via the inner `String` for parity
-    // with the historical `Option<String>` shape.
-    let node_hsm_info = node_hsm_info
-      .iter()
-      .find(|component| component.id.as_ref().map(|x| &x.0) == Some(&xname))
-      .ok_or_else(|| Error::HsmComponentNotFound(xname.clone()))?;
-
-    // `id` unwraps to an `XName100` reference; `.0` is the inner
-    // `String`, and `.clone()` matches the historical owned-string
-    // path.
-    let node_hsm_id: String = node_hsm_info
-      .id
-      .as_ref()
-      .ok_or_else(|| Error::HsmComponentIdNotDefined(xname.clone()))?
-      .0
-      .clone();
-
-    // Get power status. `state` is now `Option<HmsState100>` (a `Copy`
-    // enum with `Display` showing the wire name); `Display` already
-    // emits the upper/mixed-case wire form (e.g. "Ready") so we keep
-    // the historical uppercasing via `to_string().to_uppercase()`.
-    let node_power_status = node_hsm_info
-      .state
-      .as_ref()
-      .ok_or_else(|| Error::HsmComponentPowerStateNotDefined(xname.clone()))?
-      .to_string()
-      .to_uppercase();
-
-    // Get NID. The OpenAPI schema declares NID as `type: integer`
-    // (no `minimum: 0`), so progenitor picked `i64`; the
-    // `HsmComponentNidNotDefined` error variant takes the xname string,
-    // which is `node_hsm_id` (already an owned `String`).
-    let nid = node_hsm_info
-      .nid
-      .ok_or_else(|| Error::HsmComponentNidNotDefined(node_hsm_id.clone()))?;
-
-    // Calculate NID
-    let node_nid = format!("nid{:0>6}", nid.to_string());
+    let (node_nid, node_power_status, hsm_flag) = if let Some(node_hsm_info) =
+      &sources.hsm_component_vec
+    {
+      // Get node HSM details. `Component100Component.id` is
+      // `Option<XName100>`; compare via the inner `String` for parity
+      // with the historical `Option<String>` shape.
+      let node_hsm_info = node_hsm_info
+        .iter()
+        .find(|component| component.id.as_ref().map(|x| &x.0) == Some(&xname))
+        .ok_or_else(|| Error::HsmComponentNotFound(xname.clone()))?;
+
+      // `id` unwraps to an `XName100` reference; `.0` is the inner
+      // `String`, and `.clone()` matches the historical owned-string
+      // path.
+      let node_hsm_id: String = node_hsm_info
+        .id
+        .as_ref()
+        .ok_or_else(|| Error::HsmComponentIdNotDefined(xname.clone()))?
+        .0
+        .clone();
+
+      // Get power status. `state` is now `Option<HmsState100>` (a `Copy`
+      // enum with `Display` showing the wire name); `Display` already
+      // emits the upper/mixed-case wire form (e.g. "Ready") so we keep
+      // the historical uppercasing via `to_string().to_uppercase()`.
+      let node_power_status = node_hsm_info
+        .state
+        .as_ref()
+        .ok_or_else(|| Error::HsmComponentPowerStateNotDefined(xname.clone()))?
+        .to_string()
+        .to_uppercase();
+
+      // Get NID. The OpenAPI schema declares NID as `type: integer`
+      // (no `minimum: 0`), so progenitor picked `i64`; the
+      // `HsmComponentNidNotDefined` error variant takes the xname string,
+      // which is `node_hsm_id` (already an owned `String`).
+      let nid = node_hsm_info
+        .nid
+        .ok_or_else(|| Error::HsmComponentNidNotDefined(node_hsm_id.clone()))?;
+
+      let hsm_flag = node_hsm_info
+        .flag
+        .as_ref()
+        .map_or_else(|| "Not found".to_string(), ToString::to_string);
+
+      // Calculate NID
+      (
+        format!("nid{:0>6}", nid.to_string()),
+        node_power_status,
+        hsm_flag,
+      )
+    } else {
+      (
+        unavailable(HSM_COMPONENTS),
+        unavailable(HSM_COMPONENTS),
+        unavailable(HSM_COMPONENTS),
+      )
+    };
 
     // get node boot params (these are the boot params of the nodes with the image the node
     // boot with). the image in the bos sessiontemplate may be different i don't know why. need
     // to investigate
     let (image_id_in_kernel_params, kernel_params): (String, String) =
-      if let Some(node_boot_params) =
-        bss::utils::find_boot_params_related_to_node(
-          &node_boot_params_vec,
-          &xname,
-        )
-      {
-        (node_boot_params.get_boot_image(), node_boot_params.params)
+      if let Some(node_boot_params_vec) = &sources.boot_param_vec {
+        if let Some(node_boot_params) =
+          bss::utils::find_boot_params_related_to_node(
+            node_boot_params_vec,
+            &xname,
+          )
+        {
+          (node_boot_params.get_boot_image(), node_boot_params.params)
+        } else {
+          tracing::warn!("BSS boot parameters for node '{xname}' - NOT FOUND");
+          ("Not found".to_string(), "Not found".to_string())
+        }
       } else {
-        log::warn!("BSS boot parameters for node '{xname}' - NOT FOUND");
-        ("Not found".to_string(), "Not found".to_string())
+        (
+          unavailable(BSS_BOOT_PARAMETERS),
+          unavailable(BSS_BOOT_PARAMETERS),
+        )
       };
 
     // Get CFS configuration related to image id
-    let cfs_session_related_to_image_id_opt =
-      cfs::session::utils::find_cfs_session_related_to_image_id(
-        &cfs_session_vec,
-        &image_id_in_kernel_params,
-      );
-
-    let cfs_configuration_boot = if let Some(cfs_session_related_to_image_id) =
-      cfs_session_related_to_image_id_opt
-    {
-      let session_name = cfs_session_related_to_image_id.name;
-
-      cfs_session_related_to_image_id
-        .configuration
-        .ok_or_else(|| {
-          Error::SessionConfigurationNotDefined(session_name.clone())
-        })?
-        .name
-        .ok_or_else(|| {
-          Error::SessionConfigurationNotDefined(session_name.clone())
-        })?
-    } else {
-      "Not found".to_string()
+    let cfs_configuration_boot = match &sources.cfs_session_vec {
+      _ if sources.boot_param_vec.is_none() => unavailable(BSS_BOOT_PARAMETERS),
+      None => unavailable(CFS_SESSIONS),
+      Some(cfs_session_vec) => {
+        if let Some(cfs_session_related_to_image_id) =
+          cfs::session::utils::find_cfs_session_related_to_image_id(
+            cfs_session_vec,
+            &image_id_in_kernel_params,
+          )
+        {
+          let session_name = cfs_session_related_to_image_id.name;
+
+          cfs_session_related_to_image_id
+            .configuration
+            .ok_or_else(|| {
+              Error::SessionConfigurationNotDefined(session_name.clone())
+            })?
+            .name
+            .ok_or_else(|| {
+              Error::SessionConfigurationNotDefined(session_name.clone())
+            })?
+        } else {
+          "Not found".to_string()
+        }
+      }
     };
 
-    // CFS component fields are all optional on the wire (a node may
-    // have no assigned configuration, no recorded state, etc.). Fall
-    // back to the "Not found" sentinel used elsewhere in this function
-    // rather than panicking on None.
-    let desired_configuration_str = desired_configuration
-      .clone()
-      .unwrap_or_else(|| "Not found".to_string());
-    let configuration_status_str = configuration_status
-      .clone()
-      .unwrap_or_else(|| "Not found".to_string());
-    let enabled_str = enabled
-      .as_ref().map_or_else(|| "Not found".to_string(), bool::to_string);
-    let error_count_str = error_count
-      .as_ref().map_or_else(|| "Not found".to_string(), u64::to_string);
-
-    node_details_map
-      .entry(xname.clone())
-      .and_modify(|node_details: &mut NodeDetails| {
-        node_details.xname = xname.clone();
-        node_details.nid = node_nid.clone();
-        node_details.hsm = String::new();
-        node_details.power_status = node_power_status.clone();
-        node_details.desired_configuration = desired_configuration_str.clone();
-        node_details.configuration_status = configuration_status_str.clone();
-        node_details.enabled = enabled_str.clone();
-        node_details.error_count = error_count_str.clone();
-        node_details.boot_image_id = image_id_in_kernel_params.clone();
-        node_details.boot_configuration = cfs_configuration_boot.clone();
-        node_details.kernel_params = kernel_params.clone();
-      })
-      .or_insert(NodeDetails {
-        xname: xname.clone(),
+    let last_boot_time = last_boot_time_by_xname
+      .get(xname.as_str())
+      .map(|time| (*time).to_string());
+    let boot_image_created = image_created_by_id
+      .get(image_id_in_kernel_params.as_str())
+      .map(|created| (*created).to_string());
+
+    node_details_map.insert(
+      xname.clone(),
+      NodeDetails {
+        xname,
         nid: node_nid,
-        hsm: String::new(),
+        hsm,
         power_status: node_power_status,
         desired_configuration: desired_configuration_str,
         configuration_status: configuration_status_str,
@@ -348,61 +615,17 @@ pub async fn get_node_details(
         boot_image_id: image_id_in_kernel_params,
         boot_configuration: cfs_configuration_boot,
         kernel_params,
-      });
-
-    let permit = Arc::clone(&sem).acquire_owned().await;
-
-    tasks.spawn(async move {
-      let _permit = permit; // Wait semaphore to allow new tasks https://github.com/tokio-rs/tokio/discussions/2648#discussioncomment-34885
-
-      crate::ShastaClient::new(
-        &shasta_base_url_string,
-        shasta_root_cert_vec.clone(),
-        socks5_proxy_opt.clone(),
-      )?
-      .hsm_memberships_get_xname(&shasta_token_string, &xname)
-      .await
-    });
-  }
-
-  while let Some(message) = tasks.join_next().await {
-    let node_membership = message??;
-
-    let node_details = NodeDetails {
-      xname: String::new(),
-      nid: String::new(),
-      hsm: node_membership.group_labels.join(", "),
-      power_status: String::new(),
-      desired_configuration: String::new(),
-      configuration_status: String::new(),
-      enabled: String::new(),
-      error_count: String::new(),
-      boot_image_id: String::new(),
-      boot_configuration: String::new(),
-      kernel_params: String::new(),
-    };
-
-    let membership_id = node_membership
-      .id
-      .as_ref()
-      .map(|x| x.0.clone())
-      .unwrap_or_default();
-    node_details_map
-      .entry(membership_id)
-      .and_modify(|node_details: &mut NodeDetails| {
-        node_details.hsm = node_membership.group_labels.join(", ");
-      })
-      .or_insert(node_details);
+        hsm_flag,
+        last_boot_time,
+        last_configuration_time,
+        boot_image_created,
+      },
+    );
   }
 
-  let duration = start.elapsed();
-  log::debug!("Time elapsed to get node details is: {duration:?}");
-  // ------------------------------------------------------------------------
-
   Ok(node_details_map.into_values().collect())
 }
 
-
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -500,4 +723,33 @@ mod tests {
     ]));
     assert!(validate_xname_format_vec(vec![]));
   }
+
+  // ---------- assemble_node_details ----------
+
+  #[test]
+  fn assemble_node_details_marks_fields_of_unavailable_sources() {
+    let sources = NodeSources {
+      cfs_component_vec: None,
+      boot_param_vec: Some(Vec::new()),
+      hsm_component_vec: None,
+      membership_vec: None,
+      power_status_vec: None,
+      image_vec: None,
+      cfs_session_vec: None,
+    };
+
+    let node_details_vec =
+      assemble_node_details(vec!["x1000c0s0b0n0".to_string()], &sources)
+        .unwrap();
+
+    assert_eq!(node_details_vec.len(), 1);
+    let node_details = &node_details_vec[0];
+    assert_eq!(node_details.nid, "Unavailable(HSM components)");
+    assert_eq!(node_details.hsm, "Unavailable(HSM memberships)");
+    assert_eq!(node_details.enabled, "Unavailable(CFS components)");
+    // BSS answered but has no entry for the node
+    assert_eq!(node_details.kernel_params, "Not found");
+    assert_eq!(node_details.boot_configuration, "Unavailable(CFS sessions)");
+    assert!(node_details.last_boot_time.is_none());
+  }
 }
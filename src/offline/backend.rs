@@ -0,0 +1,475 @@
+//! Read-only queries over a [`StateBundle`].
+
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+
+use crate::{
+  bos::{self, template::http_client::v2::types::BosSessionTemplate},
+  bss::types::BootParameters,
+  cfs::{
+    self, component::http_client::v2::types::Component,
+    configuration::http_client::v2::types::cfs_configuration_response::CfsConfigurationResponse,
+    session::{
+      http_client::v2::types::CfsSessionGetResponse,
+      utils::{SessionFilter, SessionVisibility},
+    },
+  },
+  error::Error,
+  hsm::group::{GroupExt, types::Group},
+  ims::{self, image::http_client::types::Image},
+};
+
+use super::StateBundle;
+
+/// Backend answering the read-only queries of the reporting and
+/// filtering features from a [`StateBundle`] instead of CSM.
+///
+/// Every HSM group in the bundle counts as available: the bundle holds
+/// what the exporting user could read, whoever analyses it later.
+/// Generic image sessions are listed, as they are for admins.
+#[derive(Debug, Clone)]
+pub struct OfflineBackend {
+  bundle: StateBundle,
+}
+
+impl OfflineBackend {
+  /// Backend over `bundle`.
+  #[must_use]
+  pub fn new(bundle: StateBundle) -> Self {
+    Self { bundle }
+  }
+
+  /// Backend over the bundle in file `path`.
+  ///
+  /// # Errors
+  ///
+  /// See [`StateBundle::read`].
+  pub fn load(path: &Path) -> Result<Self, Error> {
+    StateBundle::read(path).map(Self::new)
+  }
+
+  /// The bundle queries are answered from.
+  #[must_use]
+  pub fn bundle(&self) -> &StateBundle {
+    &self.bundle
+  }
+
+  /// HSM groups named in `hsm_name_vec_opt`, or all of them.
+  #[must_use]
+  pub fn groups(&self, hsm_name_vec_opt: Option<&[String]>) -> Vec<Group> {
+    self
+      .bundle
+      .groups
+      .iter()
+      .filter(|group| {
+        hsm_name_vec_opt
+          .is_none_or(|hsm_name_vec| hsm_name_vec.contains(&group.label.0))
+      })
+      .cloned()
+      .collect()
+  }
+
+  /// HSM group `hsm_name`.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::GroupNotFound`] if the bundle has no such group.
+  pub fn group(&self, hsm_name: &str) -> Result<Group, Error> {
+    self
+      .bundle
+      .groups
+      .iter()
+      .find(|group| group.label.0 == hsm_name)
+      .cloned()
+      .ok_or_else(|| Error::GroupNotFound(hsm_name.to_string()))
+  }
+
+  /// Members of the HSM groups in `hsm_name_vec`.
+  #[must_use]
+  pub fn group_members(&self, hsm_name_vec: &[String]) -> Vec<String> {
+    self
+      .groups(Some(hsm_name_vec))
+      .iter()
+      .flat_map(GroupExt::get_members)
+      .collect()
+  }
+
+  /// Boot parameters of the nodes in `xname_vec`.
+  #[must_use]
+  pub fn boot_parameters(&self, xname_vec: &[String]) -> Vec<BootParameters> {
+    self
+      .bundle
+      .boot_parameters
+      .iter()
+      .filter(|boot_parameters| {
+        boot_parameters
+          .hosts
+          .iter()
+          .any(|host| xname_vec.contains(host))
+      })
+      .cloned()
+      .collect()
+  }
+
+  /// IMS image `image_id_opt`, or all of them, oldest first.
+  #[must_use]
+  pub fn images(&self, image_id_opt: Option<&str>) -> Vec<Image> {
+    let mut image_vec: Vec<Image> = self
+      .bundle
+      .images
+      .iter()
+      .filter(|image| {
+        image_id_opt
+          .is_none_or(|image_id| image.id.as_deref() == Some(image_id))
+      })
+      .cloned()
+      .collect();
+
+    ims::image::utils::filter(&mut image_vec);
+
+    image_vec
+  }
+
+  /// BOS session template `name_opt`, or all of them.
+  #[must_use]
+  pub fn templates(&self, name_opt: Option<&str>) -> Vec<BosSessionTemplate> {
+    self
+      .bundle
+      .templates
+      .iter()
+      .filter(|template| {
+        name_opt.is_none_or(|name| template.name.as_deref() == Some(name))
+      })
+      .cloned()
+      .collect()
+  }
+
+  /// BOS session templates targeting the HSM groups in
+  /// `hsm_group_name_vec` or the nodes in `hsm_member_vec`; see
+  /// [`bos::template::utils::filter`].
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] if the filter can't be applied.
+  pub fn filter_templates(
+    &self,
+    hsm_group_name_vec: &[String],
+    hsm_member_vec: &[String],
+    name_opt: Option<&str>,
+    limit_number_opt: Option<&u8>,
+  ) -> Result<Vec<BosSessionTemplate>, Error> {
+    bos::template::utils::filter(
+      &mut self.templates(name_opt),
+      None,
+      hsm_group_name_vec,
+      hsm_member_vec,
+      limit_number_opt,
+    )
+  }
+
+  /// CFS configuration `name_opt`, or all of them.
+  #[must_use]
+  pub fn configurations(
+    &self,
+    name_opt: Option<&str>,
+  ) -> Vec<CfsConfigurationResponse> {
+    self
+      .bundle
+      .configurations
+      .iter()
+      .filter(|configuration| {
+        name_opt.is_none_or(|name| configuration.name == name)
+      })
+      .cloned()
+      .collect()
+  }
+
+  /// Offline counterpart of
+  /// [`cfs::configuration::utils::get_and_filter`].
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] if the filter can't be applied.
+  pub fn filter_configurations(
+    &self,
+    configuration_name: Option<&str>,
+    configuration_name_pattern: Option<&str>,
+    hsm_group_name_vec: &[String],
+    since_opt: Option<NaiveDateTime>,
+    until_opt: Option<NaiveDateTime>,
+    limit_number_opt: Option<&u8>,
+  ) -> Result<Vec<CfsConfigurationResponse>, Error> {
+    let xname_from_groups_vec = self.group_members(hsm_group_name_vec);
+
+    let cfs_component_vec: Vec<Component> = self
+      .bundle
+      .components
+      .iter()
+      .filter(|component| {
+        component
+          .id
+          .as_ref()
+          .is_some_and(|id| xname_from_groups_vec.contains(id))
+      })
+      .cloned()
+      .collect();
+
+    cfs::configuration::utils::filter(
+      &mut self.configurations(configuration_name),
+      &xname_from_groups_vec,
+      &mut self.bundle.sessions.clone(),
+      &mut self.bundle.templates.clone(),
+      &cfs_component_vec,
+      configuration_name_pattern,
+      hsm_group_name_vec,
+      since_opt,
+      until_opt,
+      limit_number_opt,
+      true,
+    )
+  }
+
+  /// CFS sessions, `name_opt` or all of them, oldest first.
+  #[must_use]
+  pub fn sessions(&self, name_opt: Option<&str>) -> Vec<CfsSessionGetResponse> {
+    let mut cfs_session_vec: Vec<CfsSessionGetResponse> = self
+      .bundle
+      .sessions
+      .iter()
+      .filter(|cfs_session| {
+        name_opt.is_none_or(|name| cfs_session.name == name)
+      })
+      .cloned()
+      .collect();
+
+    cfs_session_vec.sort_by_key(CfsSessionGetResponse::get_start_time);
+
+    cfs_session_vec
+  }
+
+  /// Offline counterpart of the CFS session listing: sessions targeting
+  /// the HSM groups in `hsm_group_name_vec` or the nodes in
+  /// `xname_vec`, or any group in the bundle if both are empty.
+  ///
+  /// The age filters of CSM can't be reproduced offline, and aren't
+  /// accepted here.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::StateBundle`] if both groups and xnames are given
+  /// or none of them is in the bundle.
+  #[allow(clippy::too_many_arguments)]
+  pub fn filter_sessions(
+    &self,
+    hsm_group_name_vec: &[String],
+    xname_vec: &[String],
+    type_opt: Option<&String>,
+    status_opt: Option<&str>,
+    cfs_session_name_opt: Option<&str>,
+    limit_number_opt: Option<&u8>,
+    is_succeded_opt: Option<bool>,
+  ) -> Result<Vec<CfsSessionGetResponse>, Error> {
+    if !hsm_group_name_vec.is_empty() && !xname_vec.is_empty() {
+      return Err(Error::StateBundle(
+        "Cannot filter by both HSM group names and xnames simultaneously"
+          .to_string(),
+      ));
+    }
+
+    let group_vec: Vec<Group> = if !hsm_group_name_vec.is_empty() {
+      self.groups(Some(hsm_group_name_vec))
+    } else if !xname_vec.is_empty() {
+      self
+        .groups(None)
+        .into_iter()
+        .filter(|group| {
+          group
+            .get_members()
+            .iter()
+            .any(|member| xname_vec.contains(member))
+        })
+        .collect()
+    } else {
+      self.groups(None)
+    };
+
+    if group_vec.is_empty() {
+      return Err(Error::StateBundle(
+        "None of the requested HSM groups or xnames are in the state bundle"
+          .to_string(),
+      ));
+    }
+
+    let group_name_vec: Vec<String> = group_vec
+      .iter()
+      .map(|group| group.label.0.clone())
+      .collect();
+    let member_vec: Vec<String> = if xname_vec.is_empty() {
+      group_vec.iter().flat_map(GroupExt::get_members).collect()
+    } else {
+      xname_vec.to_vec()
+    };
+
+    let mut cfs_session_vec = self.sessions(cfs_session_name_opt);
+
+    cfs_session_vec.retain(|cfs_session| {
+      let session_opt = cfs_session
+        .status
+        .as_ref()
+        .and_then(|status| status.session.as_ref());
+
+      status_opt.is_none_or(|status| {
+        session_opt.and_then(|session| session.status.as_deref())
+          == Some(status)
+      }) && is_succeded_opt
+        .is_none_or(|is_succeded| cfs_session.is_success() == is_succeded)
+    });
+
+    SessionFilter {
+      visibility: Some(SessionVisibility {
+        hsm_group_names: &group_name_vec,
+        xnames: &member_vec,
+        keep_generic: true,
+      }),
+      target_definition: type_opt.map(String::as_str),
+      limit: limit_number_opt.map(|limit_number| usize::from(*limit_number)),
+      ..SessionFilter::default()
+    }
+    .apply(&mut cfs_session_vec)?;
+
+    Ok(cfs_session_vec)
+  }
+
+  /// CFS sessions, BOS session templates and IMS images related to CFS
+  /// configuration `configuration_name`; see
+  /// [`cfs::configuration::utils::filter_derivatives`].
+  #[must_use]
+  pub fn derivatives(
+    &self,
+    configuration_name: &str,
+  ) -> (
+    Vec<CfsSessionGetResponse>,
+    Vec<BosSessionTemplate>,
+    Vec<Image>,
+  ) {
+    cfs::configuration::utils::filter_derivatives(
+      configuration_name,
+      self.bundle.sessions.clone(),
+      self.bundle.templates.clone(),
+      self.bundle.images.clone(),
+    )
+  }
+
+  /// CFS components with desired configuration `configuration_name_opt`,
+  /// id in `id_vec_opt` and configuration status `status_opt`.
+  #[must_use]
+  pub fn components(
+    &self,
+    configuration_name_opt: Option<&str>,
+    id_vec_opt: Option<&[String]>,
+    status_opt: Option<&str>,
+  ) -> Vec<Component> {
+    self
+      .bundle
+      .components
+      .iter()
+      .filter(|component| {
+        configuration_name_opt.is_none_or(|configuration_name| {
+          component.desired_config.as_deref() == Some(configuration_name)
+        }) && id_vec_opt.is_none_or(|id_vec| {
+          component.id.as_ref().is_some_and(|id| id_vec.contains(id))
+        }) && status_opt.is_none_or(|status| {
+          component.configuration_status.as_deref() == Some(status)
+        })
+      })
+      .cloned()
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn session(
+    name: &str,
+    group: &str,
+    start_time: &str,
+  ) -> CfsSessionGetResponse {
+    serde_json::from_value(serde_json::json!({
+      "name": name,
+      "configuration": { "name": format!("{group}-config") },
+      "target": {
+        "definition": "dynamic",
+        "groups": [{ "name": group, "members": [] }]
+      },
+      "status": {
+        "session": { "startTime": start_time, "status": "complete", "succeeded": "true" }
+      }
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn sessions_are_filtered_by_group_in_memory() {
+    let backend = OfflineBackend::new(StateBundle {
+      groups: vec![
+        Group::new_with_members("zinal", Some(vec!["x1000c0s0b0n0"])),
+        Group::new_with_members("eiger", Some(vec!["x1000c0s1b0n0"])),
+      ],
+      sessions: vec![
+        session("zinal-2", "zinal", "2024-05-02T00:00:00"),
+        session("eiger-1", "eiger", "2024-05-01T00:00:00"),
+        session("zinal-1", "zinal", "2024-05-01T00:00:00"),
+      ],
+      ..StateBundle::new()
+    });
+
+    let cfs_session_vec = backend
+      .filter_sessions(
+        &["zinal".to_string()],
+        &[],
+        None,
+        None,
+        None,
+        None,
+        Some(true),
+      )
+      .unwrap();
+    assert_eq!(
+      cfs_session_vec
+        .iter()
+        .map(|cfs_session| cfs_session.name.as_str())
+        .collect::<Vec<_>>(),
+      vec!["zinal-1", "zinal-2"]
+    );
+
+    assert!(
+      backend
+        .filter_sessions(
+          &["zinal".to_string()],
+          &[],
+          None,
+          None,
+          None,
+          None,
+          Some(false),
+        )
+        .unwrap()
+        .is_empty()
+    );
+    assert!(matches!(
+      backend.filter_sessions(
+        &["unknown".to_string()],
+        &[],
+        None,
+        None,
+        None,
+        None,
+        None,
+      ),
+      Err(Error::StateBundle(_))
+    ));
+  }
+}
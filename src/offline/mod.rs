@@ -0,0 +1,21 @@
+//! Offline mode: reporting and filtering against exported system state.
+//!
+//! [`bundle::export`] writes a [`StateBundle`] — HSM groups, CFS
+//! configurations, sessions and components, BOS session templates, IMS
+//! images and BSS boot parameters — to a JSON file. [`OfflineBackend`]
+//! answers the read-only queries from such a file, so support tickets
+//! can be analysed without connectivity to the system. With the
+//! `manta-dispatcher` feature it implements the read-only dispatcher
+//! traits (see `backend_connector::offline`); operations that would
+//! change the system fail with [`crate::Error::StateBundle`].
+//!
+//! Submodules:
+//!
+//! - [`bundle`] — the versioned bundle file and its export.
+//! - [`backend`] — in-memory queries over a bundle.
+
+pub mod backend;
+pub mod bundle;
+
+pub use backend::OfflineBackend;
+pub use bundle::{STATE_BUNDLE_FORMAT_VERSION, StateBundle};
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::pcs::transitions::types::Operation;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum PowerState {
   #[serde(rename = "on")]
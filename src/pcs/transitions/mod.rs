@@ -17,3 +17,14 @@ pub use types::{
   Location, Operation, Task, TaskCounts, Transition, TransitionResponse,
   TransitionResponseList, TransitionStartOutput,
 };
+
+use std::time::Duration;
+
+use crate::common::poll::WaitOptions;
+
+/// How long `ShastaClient::pcs_transitions_wait_to_complete` waits
+/// unless told otherwise: about 18 minutes, checking after 3 s and
+/// backing off to 30 s.
+pub const DEFAULT_WAIT: WaitOptions =
+  WaitOptions::new(Duration::from_secs(1080), Duration::from_secs(3))
+    .with_backoff(Duration::from_secs(30));
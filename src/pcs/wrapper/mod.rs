@@ -13,17 +13,14 @@
 //! blocks that delegate to the generated client via the `run` adapter.
 //! No version split: PCS exposes a single API version.
 
-use crate::{ShastaClient, error::Error, pcs::generated};
+use crate::{ShastaClient, common::metrics, error::Error, pcs::generated};
 
 pub(crate) fn gen_client(
   client: &ShastaClient,
   token: &str,
 ) -> Result<generated::Client, Error> {
-  let inner = crate::common::http::build_client_with_auth(
-    client.root_cert(),
-    client.socks5_proxy(),
-    Some(token),
-  )?;
+  let inner =
+    crate::common::http::build_client_with_auth(client.config(), Some(token))?;
   let baseurl = format!("{}/power-control/v1", client.base_url());
   Ok(generated::Client::new_with_client(&baseurl, inner))
 }
@@ -67,7 +64,7 @@ pub(crate) async fn run<F, Fut, T, E>(
   op: F,
 ) -> Result<T, Error>
 where
-  F: FnOnce(generated::Client) -> Fut,
+  F: Fn(generated::Client) -> Fut,
   Fut: std::future::Future<
       Output = Result<
         progenitor_client::ResponseValue<T>,
@@ -76,8 +73,9 @@ where
     >,
   E: std::fmt::Debug,
 {
-  let gc = gen_client(client, token)?;
-  match op(gc).await {
+  match metrics::send_generated(client, token, "power-control", gen_client, op)
+    .await?
+  {
     Ok(rv) => Ok(rv.into_inner()),
     Err(e) => Err(map_err(e).await),
   }
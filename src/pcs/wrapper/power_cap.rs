@@ -91,8 +91,9 @@ impl ShastaClient {
     let parsed: TaskId = task_id.parse().map_err(|e| {
       Error::Message(format!("invalid power-cap task id {task_id:?}: {e}"))
     })?;
+    let parsed = &parsed;
     run(self, token, move |c| async move {
-      c.get_power_cap_task(&parsed).await
+      c.get_power_cap_task(parsed).await
     })
     .await
   }
@@ -130,8 +131,9 @@ impl ShastaClient {
 
     let body = PowerCapSnapshotReq { xnames };
 
+    let body = &body;
     run(self, token, move |c| async move {
-      c.post_power_cap_snapshot(&body).await
+      c.post_power_cap_snapshot(body).await
     })
     .await
   }
@@ -154,8 +156,9 @@ impl ShastaClient {
   ) -> Result<OpTaskStartResponse, Error> {
     log::debug!("Patch PCS power cap:\n{power_cap:#?}");
 
+    let power_cap = &power_cap;
     run(self, token, move |c| async move {
-      c.patch_power_cap(&power_cap).await
+      c.patch_power_cap(power_cap).await
     })
     .await
   }
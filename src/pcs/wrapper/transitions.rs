@@ -60,9 +60,9 @@
 //!   `pcs_transitions_post` then `pcs_transitions_wait_to_complete`.
 //!   Not a single-endpoint binding.
 //! - `pcs_transitions_wait_to_complete` — polling wrapper around
-//!   `pcs_transitions_get_by_id` with exponential backoff (3 s → 30
-//!   s, capped at 40 attempts ≈ 18 min wall-clock). Not a
-//!   single-endpoint binding.
+//!   `pcs_transitions_get_by_id`, paced by a caller-supplied
+//!   `WaitOptions` (`pcs::transitions::DEFAULT_WAIT`: 3 s → 30 s
+//!   backoff, ≈ 18 min wall-clock). Not a single-endpoint binding.
 //!
 //! The `gen_client` / `map_err` / `run` helpers in
 //! `crate::pcs::wrapper` are retained so a future spec revision (or a
@@ -71,11 +71,10 @@
 //! without a second scaffolding pass.
 
 use std::str::FromStr;
-use std::time;
 
 use crate::{
   ShastaClient,
-  common::http,
+  common::{http, poll::WaitOptions, webhooks},
   error::Error,
   pcs::transitions::types::{
     Location, Operation, Transition, TransitionResponse, TransitionResponseList,
@@ -130,7 +129,9 @@ impl ShastaClient {
   ///
   /// `POST /power-control/v1/transitions`. Use
   /// [`Self::pcs_transitions_post_block`] if you want to wait for the
-  /// transition to complete.
+  /// transition to complete. `xname_vec` takes `String`s as well as
+  /// [`crate::XName`]s. The transition is reported to the configured
+  /// [`webhooks`](crate::webhooks).
   ///
   /// # Errors
   ///
@@ -139,18 +140,24 @@ impl ShastaClient {
     &self,
     token: &str,
     operation: &str,
-    xname_vec: &[String],
+    xname_vec: &[impl AsRef<str>],
   ) -> Result<TransitionStartOutput, Error> {
-    log::debug!("Create PCS transition '{operation}' on {xname_vec:?}");
-
     let location_vec: Vec<Location> = xname_vec
       .iter()
       .map(|xname| Location {
-        xname: xname.clone(),
+        xname: xname.as_ref().to_string(),
         deputy_key: None,
       })
       .collect();
 
+    log::debug!(
+      "Create PCS transition '{operation}' on {:?}",
+      location_vec
+        .iter()
+        .map(|location| location.xname.as_str())
+        .collect::<Vec<_>>()
+    );
+
     let request_payload = Transition {
       operation: Operation::from_str(operation)?,
       task_deadline_minutes: None,
@@ -158,11 +165,29 @@ impl ShastaClient {
     };
 
     let url = format!("{}/power-control/v1/transitions", self.base_url());
-    http::post_json(self.http(), &url, token, &request_payload).await
+    let started: TransitionStartOutput =
+      http::post_json(self.http(), &url, token, &request_payload).await?;
+
+    webhooks::notify(
+      self,
+      webhooks::Operation::PowerTransition {
+        operation: operation.to_string(),
+        transition_id: started.transition_id.clone(),
+        xnames: request_payload
+          .location
+          .into_iter()
+          .map(|location| location.xname)
+          .collect(),
+      },
+    )
+    .await;
+
+    Ok(started)
   }
 
   /// Like [`Self::pcs_transitions_post`] but waits for the transition to
-  /// finish before returning.
+  /// finish, as `wait_options` says, before returning. Pass
+  /// [`crate::pcs::transitions::DEFAULT_WAIT`] for the usual pacing.
   ///
   /// # Errors
   ///
@@ -173,7 +198,8 @@ impl ShastaClient {
     &self,
     token: &str,
     operation: &str,
-    xname_vec: &[String],
+    xname_vec: &[impl AsRef<str>],
+    wait_options: WaitOptions,
   ) -> Result<TransitionResponse, Error> {
     let started = self
       .pcs_transitions_post(token, operation, xname_vec)
@@ -182,13 +208,16 @@ impl ShastaClient {
     log::debug!("PCS transition ID: {}", started.transition_id);
 
     self
-      .pcs_transitions_wait_to_complete(token, &started.transition_id)
+      .pcs_transitions_wait_to_complete(
+        token,
+        &started.transition_id,
+        wait_options,
+      )
       .await
   }
 
-  /// Polls a transition until it reaches `completed` status, with
-  /// exponential backoff (3 s → 30 s, capped at 40 attempts ≈ 18 min
-  /// wall-clock).
+  /// Polls a transition until it reaches `completed` status or
+  /// `wait_options.timeout` expires, returning the last status seen.
   ///
   /// # Errors
   ///
@@ -199,15 +228,10 @@ impl ShastaClient {
     &self,
     token: &str,
     transition_id: &str,
+    wait_options: WaitOptions,
   ) -> Result<TransitionResponse, Error> {
-    let backoff = crate::common::poll::PollBackoff {
-      initial_delay: time::Duration::from_secs(3),
-      max_delay: time::Duration::from_secs(30),
-      max_attempts: 40,
-    };
-
     crate::common::poll::poll_until_with_backoff(
-      backoff,
+      wait_options.poll_backoff(),
       || async {
         let transition =
           self.pcs_transitions_get_by_id(token, transition_id).await?;
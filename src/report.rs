@@ -0,0 +1,493 @@
+//! Frontend-agnostic output model for reports.
+//!
+//! Helpers return typed data ([`NodeDetails`], [`GroupBootImageReport`],
+//! [`ResolvedLayers`], ...); how it is shown is up to the frontend. To
+//! keep tables consistent across frontends, those types implement
+//! [`Tabular`], which describes them as [`Table`]s: named [`Column`]s
+//! and typed [`Cell`]s, serializable to JSON and exportable as CSV.
+//!
+//! ```
+//! use csm_rs::report::{Table, Tabular};
+//! # fn example(node_details_vec: &[csm_rs::node::types::NodeDetails]) {
+//! let table = Table::from_items(node_details_vec);
+//! let csv = table.to_csv();
+//! let records = table.records();
+//! # }
+//! ```
+//!
+//! Several tables that belong together, e.g. node details and the
+//! services they could not be read from, make up a [`Report`].
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+  cfs::configuration::types::{ResolvedLayer, ResolvedLayers},
+  commands::{
+    get_boot_image_report::GroupBootImageReport,
+    get_firmware_report::{FirmwareReport, NodeFirmware},
+  },
+  node::types::{NodeDetails, PartialNodeDetails, UnavailableSource},
+};
+
+/// One column of a [`Table`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Column {
+  /// Stable identifier, used as JSON field name and CSV header.
+  pub key: String,
+  /// Human readable header.
+  pub title: String,
+}
+
+impl Column {
+  /// Column `key` headed `title`.
+  #[must_use]
+  pub fn new(key: impl Into<String>, title: impl Into<String>) -> Self {
+    Self {
+      key: key.into(),
+      title: title.into(),
+    }
+  }
+}
+
+/// One value of a [`Table`] row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Cell {
+  /// No value.
+  Empty,
+  /// Free text.
+  Text(String),
+  /// Integer, e.g. a count.
+  Integer(i64),
+  /// Flag.
+  Bool(bool),
+  /// List of values, e.g. xnames.
+  List(Vec<String>),
+}
+
+impl Cell {
+  /// Value as a CSV field; list items are joined with `,`.
+  #[must_use]
+  pub fn to_csv_field(&self) -> String {
+    match self {
+      Cell::Empty => String::new(),
+      Cell::Text(text) => text.clone(),
+      Cell::Integer(integer) => integer.to_string(),
+      Cell::Bool(flag) => flag.to_string(),
+      Cell::List(item_vec) => item_vec.join(","),
+    }
+  }
+
+  fn to_value(&self) -> Value {
+    match self {
+      Cell::Empty => Value::Null,
+      Cell::Text(text) => Value::from(text.as_str()),
+      Cell::Integer(integer) => Value::from(*integer),
+      Cell::Bool(flag) => Value::from(*flag),
+      Cell::List(item_vec) => Value::from(item_vec.clone()),
+    }
+  }
+}
+
+impl From<String> for Cell {
+  fn from(text: String) -> Self {
+    Cell::Text(text)
+  }
+}
+
+impl From<&str> for Cell {
+  fn from(text: &str) -> Self {
+    Cell::Text(text.to_string())
+  }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+  fn from(value_opt: Option<T>) -> Self {
+    value_opt.map_or(Cell::Empty, Into::into)
+  }
+}
+
+impl From<usize> for Cell {
+  fn from(integer: usize) -> Self {
+    Cell::Integer(i64::try_from(integer).unwrap_or(i64::MAX))
+  }
+}
+
+impl From<bool> for Cell {
+  fn from(flag: bool) -> Self {
+    Cell::Bool(flag)
+  }
+}
+
+impl From<Vec<String>> for Cell {
+  fn from(item_vec: Vec<String>) -> Self {
+    Cell::List(item_vec)
+  }
+}
+
+/// Rows of values under named columns.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Table {
+  /// What the table shows, e.g. `Node details`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub title: Option<String>,
+  /// Columns, in display order.
+  pub columns: Vec<Column>,
+  /// Rows, each with one cell per column.
+  pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+  /// Empty table with `columns`.
+  #[must_use]
+  pub fn new(columns: Vec<Column>) -> Self {
+    Self {
+      title: None,
+      columns,
+      rows: Vec::new(),
+    }
+  }
+
+  /// Table with one row per item of `item_slice`.
+  #[must_use]
+  pub fn from_items<T: Tabular>(item_slice: &[T]) -> Self {
+    let mut table = Self::new(T::columns());
+    table.rows = item_slice.iter().flat_map(Tabular::rows).collect();
+    table
+  }
+
+  /// The table titled `title`.
+  #[must_use]
+  pub fn with_title(mut self, title: impl Into<String>) -> Self {
+    self.title = Some(title.into());
+    self
+  }
+
+  /// Append `row`, padded with [`Cell::Empty`] or truncated to the
+  /// number of columns.
+  pub fn push_row(&mut self, mut row: Vec<Cell>) {
+    row.resize(self.columns.len(), Cell::Empty);
+    self.rows.push(row);
+  }
+
+  /// Rows as JSON objects keyed by [`Column::key`].
+  #[must_use]
+  pub fn records(&self) -> Vec<Map<String, Value>> {
+    self
+      .rows
+      .iter()
+      .map(|row| {
+        self
+          .columns
+          .iter()
+          .zip(row)
+          .map(|(column, cell)| (column.key.clone(), cell.to_value()))
+          .collect()
+      })
+      .collect()
+  }
+
+  /// Table as CSV (RFC 4180): a header line of [`Column::key`]s, then
+  /// one line per row.
+  #[must_use]
+  pub fn to_csv(&self) -> String {
+    let mut csv =
+      csv_line(self.columns.iter().map(|column| column.key.clone()));
+    for row in &self.rows {
+      csv.push_str(&csv_line(row.iter().map(Cell::to_csv_field)));
+    }
+    csv
+  }
+}
+
+/// `field_iter` as one CSV line, quoting fields as needed.
+fn csv_line(field_iter: impl Iterator<Item = String>) -> String {
+  let mut line = field_iter
+    .map(|field| {
+      if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+      } else {
+        field
+      }
+    })
+    .collect::<Vec<_>>()
+    .join(",");
+  line.push_str("\r\n");
+  line
+}
+
+/// Tables that belong together, e.g. node details and the services
+/// they could not be read from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Report {
+  /// What the report is about.
+  pub title: String,
+  /// Tables, in display order.
+  pub tables: Vec<Table>,
+}
+
+/// Types that can be shown as rows of a [`Table`].
+pub trait Tabular {
+  /// Columns of the table.
+  fn columns() -> Vec<Column>;
+
+  /// Rows of `self`, one cell per column. Most types produce one row;
+  /// aggregates may produce several.
+  fn rows(&self) -> Vec<Vec<Cell>>;
+}
+
+impl Tabular for NodeDetails {
+  fn columns() -> Vec<Column> {
+    vec![
+      Column::new("xname", "XNAME"),
+      Column::new("nid", "NID"),
+      Column::new("hsm", "HSM"),
+      Column::new("power_status", "Power status"),
+      Column::new("desired_configuration", "Desired configuration"),
+      Column::new("configuration_status", "Configuration status"),
+      Column::new("enabled", "Enabled"),
+      Column::new("error_count", "Error count"),
+      Column::new("boot_image_id", "Boot image id"),
+      Column::new("boot_configuration", "Boot configuration"),
+      Column::new("kernel_params", "Kernel params"),
+      Column::new("hsm_flag", "HSM flag"),
+      Column::new("last_boot_time", "Last boot"),
+      Column::new("last_configuration_time", "Last configuration"),
+      Column::new("boot_image_created", "Boot image created"),
+    ]
+  }
+
+  fn rows(&self) -> Vec<Vec<Cell>> {
+    vec![vec![
+      self.xname.as_str().into(),
+      self.nid.as_str().into(),
+      self.hsm.as_str().into(),
+      self.power_status.as_str().into(),
+      self.desired_configuration.as_str().into(),
+      self.configuration_status.as_str().into(),
+      self.enabled.as_str().into(),
+      self.error_count.as_str().into(),
+      self.boot_image_id.as_str().into(),
+      self.boot_configuration.as_str().into(),
+      self.kernel_params.as_str().into(),
+      self.hsm_flag.as_str().into(),
+      self.last_boot_time.as_deref().into(),
+      self.last_configuration_time.as_deref().into(),
+      self.boot_image_created.as_deref().into(),
+    ]]
+  }
+}
+
+impl Tabular for UnavailableSource {
+  fn columns() -> Vec<Column> {
+    vec![
+      Column::new("source", "Source"),
+      Column::new("reason", "Reason"),
+    ]
+  }
+
+  fn rows(&self) -> Vec<Vec<Cell>> {
+    vec![vec![
+      self.source.as_str().into(),
+      self.reason.as_str().into(),
+    ]]
+  }
+}
+
+impl From<&PartialNodeDetails> for Report {
+  fn from(partial_node_details: &PartialNodeDetails) -> Self {
+    let mut table_vec = vec![
+      Table::from_items(&partial_node_details.node_details)
+        .with_title("Node details"),
+    ];
+    if !partial_node_details.unavailable_sources.is_empty() {
+      table_vec.push(
+        Table::from_items(&partial_node_details.unavailable_sources)
+          .with_title("Unavailable sources"),
+      );
+    }
+
+    Self {
+      title: "Node details".to_string(),
+      tables: table_vec,
+    }
+  }
+}
+
+impl Tabular for GroupBootImageReport {
+  fn columns() -> Vec<Column> {
+    vec![
+      Column::new("hsm_group", "HSM group"),
+      Column::new("image_id", "Image id"),
+      Column::new("image_name", "Image name"),
+      Column::new("node_count", "Nodes"),
+      Column::new("expected", "Expected"),
+      Column::new("latest_bos_template", "Latest BOS template"),
+    ]
+  }
+
+  fn rows(&self) -> Vec<Vec<Cell>> {
+    self
+      .images
+      .iter()
+      .map(|image_usage| {
+        vec![
+          self.hsm_group.as_str().into(),
+          image_usage.image_id.as_str().into(),
+          image_usage.image_name.as_deref().into(),
+          image_usage.node_count().into(),
+          (self.expected_image_id.as_deref()
+            == Some(image_usage.image_id.as_str()))
+          .into(),
+          self.latest_bos_template.as_deref().into(),
+        ]
+      })
+      .collect()
+  }
+}
+
+impl Tabular for NodeFirmware {
+  fn columns() -> Vec<Column> {
+    vec![
+      Column::new("xname", "XNAME"),
+      Column::new("model", "Model"),
+      Column::new("redfish_endpoint", "Redfish endpoint"),
+      Column::new("target", "Target"),
+      Column::new("current", "Current version"),
+      Column::new("expected", "Expected version"),
+      Column::new("out_of_date", "Out of date"),
+      Column::new("error", "Error"),
+    ]
+  }
+
+  fn rows(&self) -> Vec<Vec<Cell>> {
+    self
+      .targets
+      .iter()
+      .map(|target| {
+        vec![
+          self.xname.as_str().into(),
+          self.model.as_deref().into(),
+          self.redfish_endpoint.as_deref().into(),
+          target.target.as_str().into(),
+          target.current.as_deref().into(),
+          target.expected.as_deref().into(),
+          target.out_of_date.into(),
+          target.error.as_deref().into(),
+        ]
+      })
+      .collect()
+  }
+}
+
+impl From<&FirmwareReport> for Table {
+  fn from(firmware_report: &FirmwareReport) -> Self {
+    Table::from_items(&firmware_report.nodes).with_title(format!(
+      "Firmware of HSM group '{}'",
+      firmware_report.hsm_group
+    ))
+  }
+}
+
+impl Tabular for ResolvedLayer {
+  fn columns() -> Vec<Column> {
+    vec![
+      Column::new("layer_name", "Layer"),
+      Column::new("requested_ref", "Requested ref"),
+      Column::new("resolved_sha", "Resolved commit"),
+      Column::new("resolver", "Resolved through"),
+    ]
+  }
+
+  fn rows(&self) -> Vec<Vec<Cell>> {
+    vec![vec![
+      self.layer_name.as_str().into(),
+      self.requested_ref.as_deref().into(),
+      self.resolved_sha.as_deref().into(),
+      self.resolver.to_string().into(),
+    ]]
+  }
+}
+
+impl From<&ResolvedLayers> for Table {
+  fn from(resolved_layers: &ResolvedLayers) -> Self {
+    Table::from_items(&resolved_layers.layers).with_title(format!(
+      "CFS configuration '{}'",
+      resolved_layers.configuration_name
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+  use crate::cfs::configuration::types::RefResolver;
+
+  fn resolved_layers() -> ResolvedLayers {
+    let mut resolved_layers = ResolvedLayers::new("cos-config");
+    resolved_layers.push(
+      "cos",
+      Some("main"),
+      Some("abc123"),
+      RefResolver::GitBranch,
+    );
+    resolved_layers.push(
+      "site, \"custom\"",
+      None,
+      None,
+      RefResolver::Unresolved,
+    );
+    resolved_layers
+  }
+
+  #[test]
+  fn table_serializes_to_records_and_csv() {
+    let table = Table::from(&resolved_layers());
+
+    assert_eq!(
+      table.title.as_deref(),
+      Some("CFS configuration 'cos-config'")
+    );
+    assert_eq!(
+      serde_json::to_value(table.records()).unwrap(),
+      json!([
+        {
+          "layer_name": "cos",
+          "requested_ref": "main",
+          "resolved_sha": "abc123",
+          "resolver": "git branch",
+        },
+        {
+          "layer_name": "site, \"custom\"",
+          "requested_ref": null,
+          "resolved_sha": null,
+          "resolver": "unresolved",
+        },
+      ])
+    );
+    assert_eq!(
+      table.to_csv(),
+      "layer_name,requested_ref,resolved_sha,resolver\r\n\
+       cos,main,abc123,git branch\r\n\
+       \"site, \"\"custom\"\"\",,,unresolved\r\n"
+    );
+  }
+
+  #[test]
+  fn push_row_pads_to_column_count() {
+    let mut table = Table::new(vec![
+      Column::new("source", "Source"),
+      Column::new("reason", "Reason"),
+    ]);
+    table.push_row(vec!["BSS".into()]);
+
+    assert_eq!(table.rows, vec![vec![Cell::from("BSS"), Cell::Empty]]);
+    assert_eq!(
+      serde_json::to_value(&table).unwrap()["rows"],
+      json!([["BSS", null]])
+    );
+  }
+}
@@ -0,0 +1,13 @@
+//! System Layout Service (SLS) bindings.
+//!
+//! SLS holds the intended layout of the system: hardware, cabling and
+//! networks. Only the network part is wrapped so far, to map the IP
+//! addresses HSM knows about to the network and subnet they belong to.
+//!
+//! Submodules:
+//!
+//! - [`network`] — networks with their subnets and IP reservations.
+
+pub mod network;
+
+pub use network::types::Network;
@@ -0,0 +1,25 @@
+//! `ShastaClient` methods for SLS networks.
+
+use crate::{ShastaClient, common::http, error::Error};
+
+use super::types::Network;
+
+impl ShastaClient {
+  /// Get every SLS network.
+  ///
+  /// `GET /sls/v1/networks`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant on CSM, transport, or
+  /// deserialization failure; see the crate-level `Error` enum
+  /// for the full set.
+  pub async fn sls_network_get_all(
+    &self,
+    token: &str,
+  ) -> Result<Vec<Network>, Error> {
+    let api_url = format!("{}/sls/v1/networks", self.base_url());
+
+    http::get_json(self.http(), &api_url, token).await
+  }
+}
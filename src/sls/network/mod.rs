@@ -0,0 +1,5 @@
+//! SLS networks under `/sls/v1/networks`.
+
+pub mod http_client;
+pub mod types;
+pub mod utils;
@@ -0,0 +1,95 @@
+//! Wire-format types — mirror the SLS network schema; field names are
+//! dictated by the API.
+
+use serde::{Deserialize, Serialize};
+
+/// One SLS network (`HMN`, `NMN`, `CAN`, ...).
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Network {
+  /// Short name, e.g. `NMN`.
+  #[serde(rename = "Name")]
+  pub name: String,
+  /// Human readable name.
+  #[serde(rename = "FullName")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub full_name: Option<String>,
+  /// CIDRs covered by the network.
+  #[serde(rename = "IPRanges")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub ip_ranges: Vec<String>,
+  /// Network type, e.g. `ethernet`.
+  #[serde(rename = "Type")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub r#type: Option<String>,
+  /// Subnets and other details.
+  #[serde(rename = "ExtraProperties")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub extra_properties: Option<NetworkExtraProperties>,
+}
+
+/// `ExtraProperties` of an SLS [`Network`].
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct NetworkExtraProperties {
+  /// CIDR of the whole network.
+  #[serde(rename = "CIDR")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub cidr: Option<String>,
+  /// Subnets of the network.
+  #[serde(rename = "Subnets")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub subnets: Vec<Subnet>,
+}
+
+/// Subnet of an SLS [`Network`].
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Subnet {
+  /// Subnet name, e.g. `bootstrap_dhcp`.
+  #[serde(rename = "Name")]
+  pub name: String,
+  /// Human readable name.
+  #[serde(rename = "FullName")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub full_name: Option<String>,
+  /// Subnet CIDR.
+  #[serde(rename = "CIDR")]
+  pub cidr: String,
+  /// Default gateway.
+  #[serde(rename = "Gateway")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub gateway: Option<String>,
+  /// VLAN the subnet is on.
+  #[serde(rename = "VlanID")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub vlan_id: Option<u16>,
+  /// First address handed out by DHCP.
+  #[serde(rename = "DHCPStart")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub dhcp_start: Option<String>,
+  /// Last address handed out by DHCP.
+  #[serde(rename = "DHCPEnd")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub dhcp_end: Option<String>,
+  /// Static IP reservations.
+  #[serde(rename = "IPReservations")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub ip_reservations: Vec<IpReservation>,
+}
+
+/// Static IP reservation in an SLS [`Subnet`].
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct IpReservation {
+  /// Reservation name, usually an xname or a service name.
+  #[serde(rename = "Name")]
+  pub name: String,
+  /// Reserved address.
+  #[serde(rename = "IPAddress")]
+  pub ip_address: String,
+  /// Extra host names.
+  #[serde(rename = "Aliases")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub aliases: Vec<String>,
+  /// Free-form comment.
+  #[serde(rename = "Comment")]
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub comment: Option<String>,
+}
@@ -0,0 +1,92 @@
+//! Helpers to place IP addresses in the SLS network layout.
+
+use std::net::Ipv4Addr;
+
+use super::types::{Network, Subnet};
+
+/// Whether IPv4 `ip_address` is in `cidr` (e.g. `10.252.0.0/17`).
+/// `false` if either doesn't parse.
+#[must_use]
+pub fn ipv4_in_cidr(ip_address: &str, cidr: &str) -> bool {
+  let Some((network, prefix_len)) = cidr.split_once('/') else {
+    return false;
+  };
+  let (Ok(ip_address), Ok(network), Ok(prefix_len)) = (
+    ip_address.parse::<Ipv4Addr>(),
+    network.parse::<Ipv4Addr>(),
+    prefix_len.parse::<u32>(),
+  ) else {
+    return false;
+  };
+  if prefix_len > 32 {
+    return false;
+  }
+
+  let mask = u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0);
+  u32::from(ip_address) & mask == u32::from(network) & mask
+}
+
+/// Network and subnet `ip_address` belongs to. When subnets overlap the
+/// most specific one wins.
+#[must_use]
+pub fn find_subnet<'a>(
+  network_vec: &'a [Network],
+  ip_address: &str,
+) -> Option<(&'a Network, &'a Subnet)> {
+  network_vec
+    .iter()
+    .flat_map(|network| {
+      network
+        .extra_properties
+        .iter()
+        .flat_map(|extra_properties| &extra_properties.subnets)
+        .map(move |subnet| (network, subnet))
+    })
+    .filter(|(_, subnet)| ipv4_in_cidr(ip_address, &subnet.cidr))
+    .max_by_key(|(_, subnet)| {
+      subnet
+        .cidr
+        .split_once('/')
+        .and_then(|(_, prefix_len)| prefix_len.parse::<u32>().ok())
+        .unwrap_or(0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::sls::network::types::NetworkExtraProperties;
+
+  #[test]
+  fn find_subnet_picks_most_specific_match() {
+    let subnet = |name: &str, cidr: &str| Subnet {
+      name: name.to_string(),
+      cidr: cidr.to_string(),
+      ..Default::default()
+    };
+    let network_vec = vec![Network {
+      name: "NMN".to_string(),
+      extra_properties: Some(NetworkExtraProperties {
+        cidr: Some("10.252.0.0/17".to_string()),
+        subnets: vec![
+          subnet("network_hardware", "10.252.0.0/17"),
+          subnet("bootstrap_dhcp", "10.252.1.0/24"),
+        ],
+      }),
+      ..Default::default()
+    }];
+
+    let (network, subnet) = find_subnet(&network_vec, "10.252.1.12").unwrap();
+    assert_eq!(network.name, "NMN");
+    assert_eq!(subnet.name, "bootstrap_dhcp");
+
+    assert_eq!(
+      find_subnet(&network_vec, "10.252.2.1").unwrap().1.name,
+      "network_hardware"
+    );
+    assert!(find_subnet(&network_vec, "10.254.0.1").is_none());
+    assert!(ipv4_in_cidr("10.0.0.1", "0.0.0.0/0"));
+    assert!(!ipv4_in_cidr("not-an-ip", "10.0.0.0/8"));
+  }
+}
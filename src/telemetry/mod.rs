@@ -0,0 +1,357 @@
+//! Consumer of the SMA Kafka telemetry bus.
+//!
+//! CSM's System Monitoring Application publishes node telemetry on
+//! Kafka: HSM state change notifications (heartbeats starting and
+//! stopping), Redfish events, and console/log lines forwarded by the
+//! log aggregation pipeline. [`TelemetryConsumer`] subscribes to a set
+//! of topics and yields them as typed [`TelemetryEvent`]s, narrowed to
+//! some nodes with a [`TelemetryFilter`], so tools can surface boot
+//! errors while a rollout is in progress instead of after it.
+//!
+//! Payload shapes differ between CSM releases and topics, so parsing
+//! is lenient: SCN payloads become [`TelemetryEventKind::StateChange`],
+//! records with a node and a message become
+//! [`TelemetryEventKind::ConsoleLine`], and anything else JSON is
+//! passed through as [`TelemetryEventKind::Other`].
+//!
+//! Requires the `telemetry` Cargo feature.
+
+use std::{collections::HashSet, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt, TryStreamExt};
+use rskafka::client::{
+  ClientBuilder,
+  consumer::{StartOffset, StreamConsumerBuilder},
+  partition::UnknownTopicHandling,
+};
+use serde_json::Value;
+
+use crate::{
+  ShastaClient,
+  error::Error,
+  hsm::{
+    group::GroupExt,
+    scn::{NodeStateChange, types::ScnPayload},
+  },
+};
+
+/// Topic HSM and the heartbeat tracker publish State Change
+/// Notifications on.
+pub const STATE_CHANGE_TOPIC: &str = "cray-hmsstatechange-notifications";
+
+/// Topic the HMS collector publishes Redfish events from the BMCs on.
+pub const REDFISH_EVENT_TOPIC: &str = "cray-dmtf-resource-event";
+
+/// Longest time the broker holds a fetch waiting for new records.
+const MAX_WAIT_MS: i32 = 500;
+
+/// Fields an xname is read from, in order of preference.
+const XNAME_FIELDS: [&str; 4] = ["xname", "Xname", "hostname", "Context"];
+
+/// Fields a console or log line is read from, in order of preference.
+const MESSAGE_FIELDS: [&str; 3] = ["message", "log", "Message"];
+
+/// What a [`TelemetryEvent`] is about.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TelemetryEventKind {
+  /// A node changed state, e.g. its heartbeat stopped (`Ready` to
+  /// `Standby`).
+  StateChange(NodeStateChange),
+  /// A line a node wrote to its console or log.
+  ConsoleLine {
+    /// Node xname.
+    xname: String,
+    /// Line text.
+    line: String,
+  },
+  /// Any other JSON record.
+  Other {
+    /// Node xname, if the record names one.
+    xname: Option<String>,
+    /// Record value.
+    payload: Value,
+  },
+}
+
+/// One record received from the telemetry bus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryEvent {
+  /// Topic the record was published on.
+  pub topic: String,
+  /// Record timestamp.
+  pub timestamp: DateTime<Utc>,
+  /// What the record is about.
+  pub kind: TelemetryEventKind,
+}
+
+impl TelemetryEvent {
+  /// Xname of the node the event is about, if known.
+  #[must_use]
+  pub fn xname(&self) -> Option<&str> {
+    match &self.kind {
+      TelemetryEventKind::StateChange(change) => Some(change.xname()),
+      TelemetryEventKind::ConsoleLine { xname, .. } => Some(xname),
+      TelemetryEventKind::Other { xname, .. } => xname.as_deref(),
+    }
+  }
+}
+
+/// Nodes to keep the [`TelemetryEvent`]s of. The default keeps every
+/// event, including the ones not about a node.
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryFilter {
+  xname_set_opt: Option<HashSet<String>>,
+}
+
+impl TelemetryFilter {
+  /// Keep only the events about the nodes in `xname_vec`.
+  #[must_use]
+  pub fn xnames(xname_vec: impl IntoIterator<Item = String>) -> Self {
+    Self {
+      xname_set_opt: Some(xname_vec.into_iter().collect()),
+    }
+  }
+
+  /// Keep only the events about the members of HSM group
+  /// `hsm_group_name`.
+  ///
+  /// # Errors
+  ///
+  /// Returns an [`Error`] variant if the HSM group doesn't exist, or on
+  /// CSM, transport, or deserialization failure; see the crate-level
+  /// `Error` enum for the full set.
+  pub async fn hsm_group(
+    client: &ShastaClient,
+    shasta_token: &str,
+    hsm_group_name: &str,
+  ) -> Result<Self, Error> {
+    let member_vec = client
+      .hsm_group_get_one(shasta_token, hsm_group_name)
+      .await?
+      .get_members();
+
+    Ok(Self::xnames(member_vec))
+  }
+
+  /// `true` if `event` passes the filter.
+  #[must_use]
+  pub fn matches(&self, event: &TelemetryEvent) -> bool {
+    self.xname_set_opt.as_ref().is_none_or(|xname_set| {
+      event.xname().is_some_and(|xname| xname_set.contains(xname))
+    })
+  }
+}
+
+/// Parse one record published on `topic` into events. A SCN payload
+/// yields one event per node and field changed; a record that isn't
+/// JSON yields none.
+#[must_use]
+pub fn parse_record(
+  topic: &str,
+  timestamp: DateTime<Utc>,
+  value: &[u8],
+) -> Vec<TelemetryEvent> {
+  let event = |kind| TelemetryEvent {
+    topic: topic.to_string(),
+    timestamp,
+    kind,
+  };
+
+  let payload: Value = match serde_json::from_slice(value) {
+    Ok(payload) => payload,
+    Err(e) => {
+      tracing::debug!("Skipping non JSON record on topic '{topic}': {e}");
+      return Vec::new();
+    }
+  };
+
+  if payload.get("Components").is_some()
+    && let Ok(scn_payload) =
+      serde_json::from_value::<ScnPayload>(payload.clone())
+  {
+    return scn_payload
+      .events()
+      .into_iter()
+      .map(|change| event(TelemetryEventKind::StateChange(change)))
+      .collect();
+  }
+
+  let field = |field_name_vec: &[&str]| {
+    field_name_vec
+      .iter()
+      .find_map(|field_name| payload.get(field_name)?.as_str())
+      .map(str::to_string)
+  };
+
+  let kind = match (field(&XNAME_FIELDS), field(&MESSAGE_FIELDS)) {
+    (Some(xname), Some(line)) => {
+      TelemetryEventKind::ConsoleLine { xname, line }
+    }
+    (xname, _) => TelemetryEventKind::Other { xname, payload },
+  };
+
+  vec![event(kind)]
+}
+
+/// Connection to the SMA Kafka brokers.
+pub struct TelemetryConsumer {
+  client: rskafka::client::Client,
+}
+
+impl TelemetryConsumer {
+  /// Connect to the Kafka `brokers` (`host:port`), through
+  /// `socks5_proxy` if given.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::KafkaError`] if the brokers can't be reached.
+  pub async fn connect(
+    brokers: Vec<String>,
+    socks5_proxy: Option<&str>,
+  ) -> Result<Self, Error> {
+    let mut builder = ClientBuilder::new(brokers);
+    if let Some(socks5_proxy) = socks5_proxy {
+      builder = builder.socks5_proxy(socks5_proxy.to_string());
+    }
+
+    Ok(Self {
+      client: builder.build().await?,
+    })
+  }
+
+  /// Follow every partition of `topic_vec` from now on and yield the
+  /// events `filter` keeps. Records published before the call are
+  /// skipped. The stream doesn't end on its own; drop it to
+  /// unsubscribe.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`Error::KafkaError`] if the topics can't be listed or a
+  /// partition can't be opened, and [`Error::Message`] if none of
+  /// `topic_vec` exist.
+  pub async fn subscribe(
+    &self,
+    topic_vec: &[&str],
+    filter: TelemetryFilter,
+  ) -> Result<impl Stream<Item = Result<TelemetryEvent, Error>> + Send, Error>
+  {
+    let topic_partition_vec: Vec<(String, i32)> = self
+      .client
+      .list_topics()
+      .await?
+      .into_iter()
+      .filter(|topic| topic_vec.contains(&topic.name.as_str()))
+      .flat_map(|topic| {
+        topic
+          .partitions
+          .into_iter()
+          .map(move |partition| (topic.name.clone(), partition))
+      })
+      .collect();
+
+    if topic_partition_vec.is_empty() {
+      return Err(Error::Message(format!(
+        "None of the Kafka topics {topic_vec:?} exist"
+      )));
+    }
+
+    let mut partition_stream_vec = Vec::new();
+    for (topic, partition) in topic_partition_vec {
+      let partition_client = self
+        .client
+        .partition_client(topic.clone(), partition, UnknownTopicHandling::Error)
+        .await?;
+
+      let partition_stream = StreamConsumerBuilder::new(
+        Arc::new(partition_client),
+        StartOffset::Latest,
+      )
+      .with_max_wait_ms(MAX_WAIT_MS)
+      .build()
+      .map_err(Error::from)
+      .map_ok(move |(record_and_offset, _high_watermark)| {
+        let record = record_and_offset.record;
+        futures::stream::iter(
+          parse_record(
+            &topic,
+            record.timestamp,
+            record.value.as_deref().unwrap_or_default(),
+          )
+          .into_iter()
+          .map(Ok),
+        )
+      })
+      .try_flatten();
+
+      partition_stream_vec.push(partition_stream.boxed());
+    }
+
+    Ok(
+      futures::stream::select_all(partition_stream_vec)
+        .try_filter(move |event| std::future::ready(filter.matches(event))),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn parse(value: &Value) -> Vec<TelemetryEvent> {
+    parse_record("topic", DateTime::UNIX_EPOCH, value.to_string().as_bytes())
+  }
+
+  #[test]
+  fn parse_record_recognises_scn_console_and_other_records() {
+    let event_vec = parse(&json!({
+      "Components": ["x1000c0s0b0n0", "x1000c0s0b0n1"],
+      "State": "Standby",
+    }));
+    assert_eq!(event_vec.len(), 2);
+    assert!(matches!(
+      &event_vec[1].kind,
+      TelemetryEventKind::StateChange(NodeStateChange::State { xname, state, .. })
+        if xname == "x1000c0s0b0n1" && state == "Standby"
+    ));
+
+    assert_eq!(
+      parse(
+        &json!({ "hostname": "x1000c0s0b0n0", "message": "dracut: FATAL" })
+      )[0]
+        .kind,
+      TelemetryEventKind::ConsoleLine {
+        xname: "x1000c0s0b0n0".to_string(),
+        line: "dracut: FATAL".to_string(),
+      }
+    );
+
+    assert_eq!(
+      parse(&json!({ "Context": "x1000c0s0b0", "Events": [] }))[0].xname(),
+      Some("x1000c0s0b0")
+    );
+
+    assert!(
+      parse_record("topic", DateTime::UNIX_EPOCH, b"not json").is_empty()
+    );
+  }
+
+  #[test]
+  fn filter_keeps_only_events_about_selected_nodes() {
+    let event_vec = parse(&json!({
+      "Components": ["x1000c0s0b0n0", "x1000c0s0b0n1"],
+      "State": "Off",
+    }));
+    let other_vec = parse(&json!({ "Events": [] }));
+
+    let filter = TelemetryFilter::xnames(["x1000c0s0b0n1".to_string()]);
+    assert!(!filter.matches(&event_vec[0]));
+    assert!(filter.matches(&event_vec[1]));
+    assert!(!filter.matches(&other_vec[0]));
+
+    assert!(TelemetryFilter::default().matches(&other_vec[0]));
+  }
+}
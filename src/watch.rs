@@ -0,0 +1,437 @@
+//! Follow CSM resources as they change.
+//!
+//! [`Watchable`] is implemented by the resources frontends follow while
+//! an operation runs: CFS sessions, BOS sessions, IMS jobs and HSM
+//! components. [`watch`] polls one as [`WaitOptions`] says and yields it
+//! every time its [`Watchable::state`] changes, until it reaches a
+//! terminal state or the timeout expires. While nothing changes the
+//! polling backs off; after a change it goes back to `poll_interval`.
+//!
+//! Frontends that don't want to name the resource type can go through
+//! `ShastaClient::watch` (see `backend_connector::watch`, under the
+//! `manta-dispatcher` feature), which takes a [`ResourceRef`] and
+//! yields [`WatchUpdate`]s.
+
+use std::{future::Future, time::Duration};
+
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::{
+  ShastaClient,
+  bos::{BosSession, StatusLabel},
+  cfs::v3::CfsSessionGetResponse,
+  common::poll::{WaitOptions, jittered},
+  error::Error,
+  hsm::component::types::Component,
+  ims::job::types::Job,
+};
+
+/// A CSM resource [`watch`] can follow.
+pub trait Watchable: Serialize + Sized + Send + 'static {
+  /// Kind of resource, for messages, e.g. `CFS session`.
+  const KIND: &'static str;
+
+  /// Fetch resource `id`.
+  fn poll(
+    client: &ShastaClient,
+    shasta_token: &str,
+    id: &str,
+  ) -> impl Future<Output = Result<Self, Error>> + Send;
+
+  /// State of the resource; [`watch`] reports the resource when it
+  /// changes.
+  fn state(&self) -> String;
+
+  /// Whether the resource won't change anymore, which ends the watch.
+  fn is_terminal(&self) -> bool;
+}
+
+impl Watchable for CfsSessionGetResponse {
+  const KIND: &'static str = "CFS session";
+
+  async fn poll(
+    client: &ShastaClient,
+    shasta_token: &str,
+    id: &str,
+  ) -> Result<Self, Error> {
+    client
+      .cfs_session_v3_get(
+        shasta_token,
+        Some(&id.to_string()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+      )
+      .await?
+      .into_iter()
+      .next()
+      .ok_or_else(|| Error::SessionNotFound(id.to_string()))
+  }
+
+  /// `status.session.status`, followed by `succeeded` once complete.
+  fn state(&self) -> String {
+    let session = self
+      .status
+      .as_ref()
+      .and_then(|status| status.session.as_ref());
+    let status = session
+      .and_then(|session| session.status.as_deref())
+      .unwrap_or("pending");
+
+    match session.and_then(|session| session.succeeded.as_deref()) {
+      Some(succeeded) if self.is_terminal() => {
+        format!("{status} (succeeded: {succeeded})")
+      }
+      _ => status.to_string(),
+    }
+  }
+
+  fn is_terminal(&self) -> bool {
+    self
+      .status
+      .as_ref()
+      .and_then(|status| status.session.as_ref())
+      .and_then(|session| session.status.as_deref())
+      == Some("complete")
+  }
+}
+
+impl Watchable for BosSession {
+  const KIND: &'static str = "BOS session";
+
+  async fn poll(
+    client: &ShastaClient,
+    shasta_token: &str,
+    id: &str,
+  ) -> Result<Self, Error> {
+    client
+      .bos_session_v2_get(shasta_token, Some(id))
+      .await?
+      .into_iter()
+      .next()
+      .ok_or_else(|| Error::SessionNotFound(id.to_string()))
+  }
+
+  /// `status.status`, followed by `status.error` if any.
+  fn state(&self) -> String {
+    let Some(status) = &self.status else {
+      return "pending".to_string();
+    };
+
+    let label = match status.status {
+      StatusLabel::Pending => "pending",
+      StatusLabel::Running => "running",
+      StatusLabel::Complete => "complete",
+    };
+
+    match &status.error {
+      Some(error) => format!("{label} (error: {error})"),
+      None => label.to_string(),
+    }
+  }
+
+  fn is_terminal(&self) -> bool {
+    self
+      .status
+      .as_ref()
+      .is_some_and(|status| status.status == StatusLabel::Complete)
+  }
+}
+
+impl Watchable for Job {
+  const KIND: &'static str = "IMS job";
+
+  async fn poll(
+    client: &ShastaClient,
+    shasta_token: &str,
+    id: &str,
+  ) -> Result<Self, Error> {
+    client
+      .ims_job_get(shasta_token, Some(id))
+      .await?
+      .into_iter()
+      .next()
+      .ok_or_else(|| Error::Message(format!("IMS job '{id}' not found")))
+  }
+
+  fn state(&self) -> String {
+    self.status.clone().unwrap_or_else(|| "unknown".to_string())
+  }
+
+  fn is_terminal(&self) -> bool {
+    matches!(self.status.as_deref(), Some("success" | "error"))
+  }
+}
+
+impl Watchable for Component {
+  const KIND: &'static str = "HSM component";
+
+  async fn poll(
+    client: &ShastaClient,
+    shasta_token: &str,
+    id: &str,
+  ) -> Result<Self, Error> {
+    client.hsm_component_get_one(shasta_token, id).await
+  }
+
+  /// HSM state and flag, e.g. `Ready/OK`.
+  fn state(&self) -> String {
+    format!(
+      "{}/{}",
+      self
+        .state
+        .as_ref()
+        .map_or_else(|| "Unknown".to_string(), ToString::to_string),
+      self
+        .flag
+        .as_ref()
+        .map_or_else(|| "Unknown".to_string(), ToString::to_string)
+    )
+  }
+
+  /// HSM components never settle: watch them until the timeout.
+  fn is_terminal(&self) -> bool {
+    false
+  }
+}
+
+/// Where [`watch`] is at between two items.
+struct Watch {
+  client: ShastaClient,
+  shasta_token: String,
+  id: String,
+  wait_options: WaitOptions,
+  deadline: Instant,
+  delay: Duration,
+  last_state: Option<String>,
+}
+
+/// Follow resource `id` of type `T`, polling as `wait_options` says.
+///
+/// The stream yields the resource when first fetched and then every time
+/// its [`Watchable::state`] changes. It ends after yielding a terminal
+/// resource, when `wait_options.timeout` expires, or after yielding the
+/// first error.
+///
+/// If `client` has an [`AuthTokenProvider`](crate::AuthTokenProvider),
+/// each poll uses a fresh token from it instead of `shasta_token`.
+pub fn watch<T: Watchable>(
+  client: ShastaClient,
+  shasta_token: &str,
+  id: &str,
+  wait_options: WaitOptions,
+) -> impl Stream<Item = Result<T, Error>> + Send {
+  let watch = Watch {
+    client,
+    shasta_token: shasta_token.to_string(),
+    id: id.to_string(),
+    wait_options,
+    deadline: Instant::now() + wait_options.timeout,
+    // A zero interval would hammer CSM
+    delay: wait_options.poll_interval.max(Duration::from_millis(1)),
+    last_state: None,
+  };
+
+  futures::stream::unfold(Some(watch), |watch_opt| async move {
+    let mut watch = watch_opt?;
+
+    loop {
+      if watch.last_state.is_some() {
+        if Instant::now() + watch.delay > watch.deadline {
+          tracing::debug!(
+            "{} '{}' still not settled after {}s, stop watching",
+            T::KIND,
+            watch.id,
+            watch.wait_options.timeout.as_secs()
+          );
+          return None;
+        }
+        tokio::time::sleep(jittered(watch.delay)).await;
+      }
+
+      // Long watches outlive tokens: ask the provider, if any, each poll
+      let resource = match watch.client.fresh_token(&watch.shasta_token).await {
+        Ok(shasta_token) => {
+          T::poll(&watch.client, &shasta_token, &watch.id).await
+        }
+        Err(e) => Err(e),
+      };
+      let resource = match resource {
+        Ok(resource) => resource,
+        Err(e) => return Some((Err(e), None)),
+      };
+
+      let state = resource.state();
+      if watch.last_state.as_ref() == Some(&state) {
+        watch.delay = watch.wait_options.next_delay(watch.delay);
+        continue;
+      }
+
+      tracing::debug!("{} '{}' is now '{state}'", T::KIND, watch.id);
+      watch.delay = watch
+        .wait_options
+        .poll_interval
+        .max(Duration::from_millis(1));
+      watch.last_state = Some(state);
+
+      let next = (!resource.is_terminal()).then_some(watch);
+      return Some((Ok(resource), next));
+    }
+  })
+}
+
+/// A resource to watch, whatever its type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ResourceRef {
+  /// CFS session, by name.
+  CfsSession(String),
+  /// BOS session, by name.
+  BosSession(String),
+  /// IMS job, by id.
+  ImsJob(String),
+  /// HSM component, by xname.
+  HsmComponent(String),
+}
+
+impl ResourceRef {
+  /// Name, id or xname of the resource.
+  #[must_use]
+  pub fn id(&self) -> &str {
+    match self {
+      Self::CfsSession(id)
+      | Self::BosSession(id)
+      | Self::ImsJob(id)
+      | Self::HsmComponent(id) => id,
+    }
+  }
+
+  /// Kind of resource, e.g. `CFS session`.
+  #[must_use]
+  pub fn kind(&self) -> &'static str {
+    match self {
+      Self::CfsSession(_) => CfsSessionGetResponse::KIND,
+      Self::BosSession(_) => BosSession::KIND,
+      Self::ImsJob(_) => Job::KIND,
+      Self::HsmComponent(_) => Component::KIND,
+    }
+  }
+}
+
+impl std::fmt::Display for ResourceRef {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} '{}'", self.kind(), self.id())
+  }
+}
+
+/// A change of a watched resource, whatever its type.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WatchUpdate {
+  /// Resource that changed.
+  pub resource: ResourceRef,
+  /// New [`Watchable::state`].
+  pub state: String,
+  /// Whether this is the last update of the watch.
+  pub terminal: bool,
+  /// When the change was seen.
+  pub observed_at: DateTime<Utc>,
+  /// The resource, as returned by CSM.
+  pub details: Value,
+}
+
+impl WatchUpdate {
+  /// Update reporting `resource` as the current value of `resource_ref`.
+  #[must_use]
+  pub fn new<T: Watchable>(resource_ref: ResourceRef, resource: &T) -> Self {
+    Self {
+      resource: resource_ref,
+      state: resource.state(),
+      terminal: resource.is_terminal(),
+      observed_at: Utc::now(),
+      details: serde_json::to_value(resource).unwrap_or_default(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn cfs_session_state_follows_status() {
+    let session = |status: &str, succeeded: &str| -> CfsSessionGetResponse {
+      serde_json::from_value(json!({
+        "name": "batcher-1234",
+        "debug_on_failure": false,
+        "status": {
+          "session": { "status": status, "succeeded": succeeded }
+        }
+      }))
+      .unwrap()
+    };
+
+    assert_eq!(session("running", "none").state(), "running");
+    assert!(!session("running", "none").is_terminal());
+    assert_eq!(
+      session("complete", "false").state(),
+      "complete (succeeded: false)"
+    );
+    assert!(session("complete", "false").is_terminal());
+  }
+
+  #[test]
+  fn bos_session_state_follows_status() {
+    let session = |status: serde_json::Value| -> BosSession {
+      serde_json::from_value(json!({
+        "name": "boot-zinal",
+        "template_name": "zinal-template",
+        "status": status,
+      }))
+      .unwrap()
+    };
+
+    let running = session(
+      json!({ "start_time": "2024-01-01T00:00:00", "status": "running" }),
+    );
+    assert_eq!(running.state(), "running");
+    assert!(!running.is_terminal());
+
+    let failed = session(json!({
+      "start_time": "2024-01-01T00:00:00",
+      "status": "complete",
+      "error": "timed out",
+    }));
+    assert_eq!(failed.state(), "complete (error: timed out)");
+    assert!(failed.is_terminal());
+  }
+
+  #[test]
+  fn resource_ref_serializes_with_kind() {
+    let resource_ref = ResourceRef::ImsJob("4e1b".to_string());
+
+    assert_eq!(resource_ref.to_string(), "IMS job '4e1b'");
+    assert_eq!(
+      serde_json::to_value(&resource_ref).unwrap(),
+      json!({ "kind": "ims_job", "id": "4e1b" })
+    );
+    assert_eq!(
+      serde_json::from_value::<ResourceRef>(
+        json!({ "kind": "hsm_component", "id": "x1000c0s0b0n0" })
+      )
+      .unwrap(),
+      ResourceRef::HsmComponent("x1000c0s0b0n0".to_string())
+    );
+  }
+}
@@ -11,7 +11,7 @@
 #![cfg(feature = "manta-dispatcher")]
 
 mod common;
-use common::{TEST_PEM, TEST_TOKEN};
+use common::{TEST_JWT, TEST_PEM, TEST_TOKEN};
 
 use csm_rs::ShastaClient;
 use manta_backend_dispatcher::interfaces::{
@@ -789,6 +789,60 @@ async fn group_add_members_to_group_does_get_then_post() {
     .expect("ok");
 }
 
+#[tokio::test]
+async fn group_add_members_restores_exclusive_group_on_failure() {
+  let server = MockServer::start().await;
+  // zinal and eiger share exclusive group `tenants`, so the node has to
+  // leave eiger before joining zinal
+  Mock::given(method("GET"))
+    .and(path("/smd/hsm/v2/groups"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+      {
+        "label": "zinal",
+        "exclusiveGroup": "tenants",
+        "members": {"ids": []}
+      },
+      {
+        "label": "eiger",
+        "exclusiveGroup": "tenants",
+        "members": {"ids": ["x1000c0s0b0n0"]}
+      }
+    ])))
+    .mount(&server)
+    .await;
+  Mock::given(method("DELETE"))
+    .and(path("/smd/hsm/v2/groups/eiger/members/x1000c0s0b0n0"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+    .expect(1)
+    .mount(&server)
+    .await;
+  Mock::given(method("POST"))
+    .and(path("/smd/hsm/v2/groups/zinal/members"))
+    .respond_with(ResponseTemplate::new(500).set_body_json(json!({
+      "title": "Internal Server Error", "status": 500
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+  Mock::given(method("POST"))
+    .and(path("/smd/hsm/v2/groups/eiger/members"))
+    .and(body_partial_json(json!({"id": "x1000c0s0b0n0"})))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+      "code": 0, "message": "added"
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+  let csm = make_csm(&server.uri());
+  assert!(
+    csm
+      .add_members_to_group(TEST_JWT, "zinal", &["x1000c0s0b0n0"])
+      .await
+      .is_err()
+  );
+}
+
 // ---------- ComponentEthernetInterfaceTrait stubs (no network) ----------
 
 #[tokio::test]
@@ -11,11 +11,13 @@
 //! their command's flow demands.
 
 mod common;
-use common::{TEST_PEM, TEST_TOKEN};
+use common::{TEST_JWT, TEST_PEM, TEST_TOKEN};
 
 use csm_rs::{
+  ExecutionContext,
   cfs::v2::{CfsSessionGetResponse, Target},
   commands::delete_and_cancel_session::command as delete_and_cancel_session,
+  hsm::group::tenancy::Tenancy,
 };
 
 use wiremock::matchers::{bearer_token, method, path};
@@ -62,6 +64,8 @@ async fn delete_and_cancel_image_session_with_no_results_only_hits_cfs_delete()
   delete_and_cancel_session::exec(
     &client,
     TEST_TOKEN,
+    &ExecutionContext::new(TEST_JWT, "test", &Tenancy::unrestricted())
+      .expect("execution context of the test JWT"),
     Vec::new(),    // no HSM groups available — no group-based xname expansion
     &cfs_session,
     &[],           // no CFS components
@@ -20,6 +20,11 @@ Wf86aX6PepsntZv2GYlA5UpabfT2EZICICpJ5h/iI+i341gBmLiAFQOyTDT+/wQc\n\
 /// Default bearer token used by all wiremock tests.
 pub const TEST_TOKEN: &str = "test-token";
 
+/// JWT-shaped token of user `test`, for the APIs reading the token's
+/// claims; only those are decoded. Without roles it counts as an admin.
+#[allow(dead_code)]
+pub const TEST_JWT: &str = "header.eyJwcmVmZXJyZWRfdXNlcm5hbWUiOiJ0ZXN0In0.sig";
+
 // Some integration-test crates don't call this (e.g. the dispatcher
 // smoke tests in `tests/backend_connector.rs` construct a `Csm`
 // directly), so the `dead_code` lint trips per crate. Allow it here.
@@ -0,0 +1,217 @@
+//! Wiremock smoke tests for the `AuthTokenProvider`s,
+//! `ShastaClient::with_token` and `ShastaClient::connect`.
+
+mod common;
+use common::{TEST_PEM, make_client};
+
+use std::sync::Arc;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use csm_rs::{
+  AuthTokenProvider, ClientConfig, ClientCredentials, Error, ShastaClient,
+};
+use serde_json::json;
+use wiremock::matchers::{bearer_token, body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TOKEN_PATH: &str =
+  "/keycloak/realms/shasta/protocol/openid-connect/token";
+
+fn token_response(access_token: &str) -> ResponseTemplate {
+  ResponseTemplate::new(200).set_body_json(json!({
+    "access_token": access_token,
+    "expires_in": 300,
+  }))
+}
+
+fn client_credentials(server: &MockServer) -> ClientCredentials {
+  ClientCredentials::new(
+    format!("{}/keycloak", server.uri()),
+    TEST_PEM.as_bytes(),
+    None,
+    "admin-client",
+    "secret",
+  )
+  .expect("provider construction should succeed")
+}
+
+#[tokio::test]
+async fn client_credentials_reuses_token_until_it_expires() {
+  let server = MockServer::start().await;
+  Mock::given(method("POST"))
+    .and(path(TOKEN_PATH))
+    .and(body_string_contains("grant_type=client_credentials"))
+    .and(body_string_contains("client_id=admin-client"))
+    .respond_with(token_response("first-token"))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+  let provider = client_credentials(&server);
+  assert_eq!(provider.token().await.unwrap(), "first-token");
+  assert_eq!(provider.token().await.unwrap(), "first-token");
+}
+
+/// Token endpoint handing out a revoked token first and a new one
+/// afterwards, and a GET `api_path` rejecting the revoked one.
+async fn mount_token_rotation(server: &MockServer, api_path: &str) {
+  Mock::given(method("POST"))
+    .and(path(TOKEN_PATH))
+    .respond_with(token_response("revoked-token"))
+    .up_to_n_times(1)
+    .expect(1)
+    .mount(server)
+    .await;
+  Mock::given(method("POST"))
+    .and(path(TOKEN_PATH))
+    .respond_with(token_response("new-token"))
+    .expect(1)
+    .mount(server)
+    .await;
+  Mock::given(method("GET"))
+    .and(path(api_path))
+    .and(bearer_token("revoked-token"))
+    .respond_with(ResponseTemplate::new(401))
+    .expect(1)
+    .mount(server)
+    .await;
+  Mock::given(method("GET"))
+    .and(path(api_path))
+    .and(bearer_token("new-token"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+    .expect(1)
+    .mount(server)
+    .await;
+}
+
+#[tokio::test]
+async fn with_token_retries_once_with_a_new_token_after_401() {
+  let server = MockServer::start().await;
+  mount_token_rotation(&server, "/ims/v3/images").await;
+
+  let client = make_client(&server.uri())
+    .with_token_provider(Arc::new(client_credentials(&server)));
+  let images = client
+    .with_token(|token| {
+      let client = client.clone();
+      async move { client.ims_image_get_all(&token).await }
+    })
+    .await
+    .expect("retry with the new token should succeed");
+  assert!(images.is_empty());
+}
+
+#[tokio::test]
+async fn direct_calls_retry_once_with_a_new_token_after_401() {
+  let server = MockServer::start().await;
+  mount_token_rotation(&server, "/ims/v3/images").await;
+
+  let client = make_client(&server.uri())
+    .with_token_provider(Arc::new(client_credentials(&server)));
+  let images = client
+    .ims_image_get_all("expired-token")
+    .await
+    .expect("the client's provider should replace the token");
+  assert!(images.is_empty());
+}
+
+#[tokio::test]
+async fn generated_client_calls_retry_once_with_a_new_token_after_401() {
+  let server = MockServer::start().await;
+  mount_token_rotation(&server, "/smd/hsm/v2/groups").await;
+
+  let client = make_client(&server.uri())
+    .with_token_provider(Arc::new(client_credentials(&server)));
+  let groups = client
+    .hsm_group_get_all("expired-token")
+    .await
+    .expect("the client's provider should replace the token");
+  assert!(groups.is_empty());
+}
+
+/// Unsigned JWT issued to the `shasta` client, valid for an hour.
+fn shasta_jwt() -> String {
+  let claims = json!({
+    "azp": "shasta",
+    "exp": chrono::Utc::now().timestamp() + 3600,
+  });
+  format!(
+    "header.{}.signature",
+    URL_SAFE_NO_PAD.encode(claims.to_string())
+  )
+}
+
+async fn mount_versions(server: &MockServer, token: &str) {
+  for (version_path, body) in [
+    (
+      "/bos/v2/version",
+      json!({"major": "2", "minor": "31", "patch": "0"}),
+    ),
+    (
+      "/cfs/versions",
+      json!({"major": "1", "minor": "25", "patch": "3"}),
+    ),
+    ("/ims/version", json!("3.22.0")),
+  ] {
+    Mock::given(method("GET"))
+      .and(path(version_path))
+      .and(bearer_token(token))
+      .respond_with(ResponseTemplate::new(200).set_body_json(body))
+      .expect(1)
+      .mount(server)
+      .await;
+  }
+}
+
+#[tokio::test]
+async fn connect_captures_the_csm_versions() {
+  let server = MockServer::start().await;
+  let token = shasta_jwt();
+  mount_versions(&server, &token).await;
+
+  let client = ShastaClient::connect(
+    server.uri(),
+    ClientConfig::new().with_ca_certs(TEST_PEM),
+    &token,
+  )
+  .await
+  .expect("connect should succeed");
+
+  let versions = client.versions().expect("versions should be captured");
+  assert_eq!(versions.bos.to_string(), "2.31.0");
+  assert_eq!(versions.cfs, "1.25.3");
+  assert_eq!(versions.ims, "3.22.0");
+  assert!(make_client(&server.uri()).versions().is_none());
+}
+
+#[tokio::test]
+async fn connect_fails_early_on_a_rejected_token_or_unreachable_csm() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .respond_with(ResponseTemplate::new(401))
+    .mount(&server)
+    .await;
+
+  let result =
+    ShastaClient::connect(server.uri(), ClientConfig::new(), &shasta_jwt())
+      .await;
+  assert!(matches!(
+    result,
+    Err(Error::ConnectionCheck { check: "token", .. })
+  ));
+
+  // Nothing listens on port 9 (discard) of localhost
+  let result = ShastaClient::connect(
+    "http://127.0.0.1:9",
+    ClientConfig::new(),
+    &shasta_jwt(),
+  )
+  .await;
+  assert!(matches!(
+    result,
+    Err(Error::ConnectionCheck {
+      check: "connect",
+      ..
+    })
+  ));
+}
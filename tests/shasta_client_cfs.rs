@@ -209,6 +209,35 @@ async fn cfs_session_v3_get_returns_sessions_from_wrapped_payload() {
   assert_eq!(sessions[0].name, "sess-1");
 }
 
+// ---------- cfs/source v3 ----------
+
+#[tokio::test]
+async fn cfs_source_v3_get_returns_sources_from_wrapped_payload() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .and(path("/cfs/v3/sources"))
+    .and(bearer_token(TEST_TOKEN))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+      "sources": [{
+        "name": "site-repo",
+        "clone_url": "https://git.example.com/site.git",
+        "credentials": {
+          "authentication_method": "password",
+          "secret_name": "cfs-source-site-repo",
+        },
+      }],
+      "next": null,
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+  let client = make_client(&server.uri());
+  let sources = client.cfs_source_v3_get(TEST_TOKEN, None).await.unwrap();
+  assert_eq!(sources.len(), 1);
+  assert_eq!(sources[0].name, "site-repo");
+}
+
 // ---------- cfs/common (health_check) ----------
 
 #[tokio::test]
@@ -256,3 +285,43 @@ async fn cfs_configuration_v2_put_sends_layers_body_to_singular_endpoint() {
     .expect("ok");
   assert_eq!(response.name, "cfg-1");
 }
+
+// ---------- CsmSnapshot ----------
+
+#[tokio::test]
+async fn csm_snapshot_fetches_each_collection_once() {
+  let server = MockServer::start().await;
+  for collection_path in [
+    "/cfs/v2/components",
+    "/cfs/v2/sessions",
+    "/bos/v2/sessiontemplates",
+    "/bss/boot/v1/bootparameters",
+    "/ims/v3/images",
+  ] {
+    Mock::given(method("GET"))
+      .and(path(collection_path))
+      .and(bearer_token(TEST_TOKEN))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+      .expect(1)
+      .mount(&server)
+      .await;
+  }
+
+  let client = make_client(&server.uri());
+  let snapshot = csm_rs::CsmSnapshot::new(&client, TEST_TOKEN);
+  let usage =
+    csm_rs::cfs::configuration::utils::usage_from_snapshot(&snapshot, "cfg")
+      .await
+      .expect("ok");
+  assert!(!usage.is_in_use());
+
+  let (cfs_session_vec_opt, bos_sessiontemplate_vec_opt, image_vec_opt) =
+    csm_rs::cfs::configuration::utils::get_derivatives_from_snapshot(
+      &snapshot, "cfg",
+    )
+    .await
+    .expect("ok");
+  assert_eq!(cfs_session_vec_opt.map(|v| v.len()), Some(0));
+  assert_eq!(bos_sessiontemplate_vec_opt.map(|v| v.len()), Some(0));
+  assert_eq!(image_vec_opt.map(|v| v.len()), Some(0));
+}
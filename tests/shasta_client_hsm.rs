@@ -4,7 +4,7 @@ mod common;
 use common::{TEST_TOKEN, make_client};
 
 use serde_json::json;
-use wiremock::matchers::{bearer_token, body_json, method, path};
+use wiremock::matchers::{bearer_token, body_json, method, path, query_param};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 // ---------- hsm/group ----------
@@ -104,6 +104,75 @@ async fn hsm_component_get_all_hits_smd_v2_state_components() {
   assert!(arr.components.is_empty());
 }
 
+#[tokio::test]
+async fn hsm_component_patch_state_hits_bulk_endpoints() {
+  let server = MockServer::start().await;
+  Mock::given(method("PATCH"))
+    .and(path("/smd/hsm/v2/State/Components/BulkEnabled"))
+    .and(bearer_token(TEST_TOKEN))
+    .and(body_json(json!({
+      "ComponentIDs": ["x1000c0s0b0n0", "x1000c0s0b0n1"],
+      "Enabled": false,
+    })))
+    .respond_with(ResponseTemplate::new(204))
+    .expect(1)
+    .mount(&server)
+    .await;
+  Mock::given(method("PATCH"))
+    .and(path("/smd/hsm/v2/State/Components/BulkFlagOnly"))
+    .and(bearer_token(TEST_TOKEN))
+    .and(body_json(json!({
+      "ComponentIDs": ["x1000c0s0b0n0", "x1000c0s0b0n1"],
+      "Flag": "Locked",
+    })))
+    .respond_with(ResponseTemplate::new(204))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+  let client = make_client(&server.uri());
+  client
+    .hsm_component_patch_state(
+      TEST_TOKEN,
+      &["x1000c0s0b0n0", "x1000c0s0b0n1"],
+      Some(false),
+      Some("Locked"),
+    )
+    .await
+    .expect("ok");
+}
+
+#[tokio::test]
+async fn get_all_node_xnames_queries_nodes_of_role_with_nid_projection() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .and(path("/smd/hsm/v2/State/Components"))
+    .and(query_param("type", "Node"))
+    .and(query_param("role", "Application"))
+    .and(query_param("subrole", "UAN"))
+    .and(query_param("nidonly", "true"))
+    .and(bearer_token(TEST_TOKEN))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+      "Components": [
+        { "ID": "x3000c0s19b0n0", "NID": 2 },
+        { "ID": "x3000c0s17b0n0", "NID": 1 }
+      ]
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+  let client = make_client(&server.uri());
+  let xname_vec = csm_rs::hsm::component::utils::get_all_node_xnames(
+    &client,
+    TEST_TOKEN,
+    Some(csm_rs::hsm::component::NodeRole::APPLICATION_UAN),
+  )
+  .await
+  .unwrap();
+  assert_eq!(xname_vec, vec!["x3000c0s17b0n0", "x3000c0s19b0n0"]);
+}
+
 // ---------- hsm/component_status ----------
 
 #[tokio::test]
@@ -145,6 +214,32 @@ async fn hsm_memberships_get_all_hits_v2_memberships() {
     .expect("ok");
 }
 
+#[tokio::test]
+async fn hsm_memberships_get_all_filtered_keeps_requested_xnames() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .and(path("/smd/hsm/v2/memberships"))
+    .and(bearer_token(TEST_TOKEN))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+      {"id": "x1000c0s0b0n0", "groupLabels": ["zinal"]},
+      {"id": "x1000c0s0b0n1", "groupLabels": ["daint"]},
+    ])))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+  let client = make_client(&server.uri());
+  let membership_vec = client
+    .hsm_memberships_get_all_filtered(
+      TEST_TOKEN,
+      &["x1000c0s0b0n1".to_string()],
+    )
+    .await
+    .unwrap();
+  assert_eq!(membership_vec.len(), 1);
+  assert_eq!(membership_vec[0].group_labels, vec!["daint".to_string()]);
+}
+
 #[tokio::test]
 async fn hsm_memberships_get_xname_hits_singular_endpoint() {
   let server = MockServer::start().await;
@@ -257,3 +352,44 @@ async fn hsm_group_post_member_sends_id_body_to_members_endpoint() {
     .expect("ok");
   assert_eq!(ack.message, "ok");
 }
+
+// ---------- hsm/scn ----------
+
+#[tokio::test]
+async fn hsm_scn_subscription_post_returns_subscription_with_id() {
+  use csm_rs::hsm::scn::types::ScnSubscription;
+  let server = MockServer::start().await;
+  Mock::given(method("POST"))
+    .and(path("/smd/hsm/v2/Subscriptions/SCN"))
+    .and(bearer_token(TEST_TOKEN))
+    .and(body_json(json!({
+      "Subscriber": "daemon@host",
+      "Enabled": true,
+      "States": ["Off", "Ready"],
+      "Url": "https://host/scn",
+    })))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+      "ID": "42",
+      "Subscriber": "daemon@host",
+      "Enabled": true,
+      "States": ["Off", "Ready"],
+      "Url": "https://host/scn",
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+  let client = make_client(&server.uri());
+  let subscription = client
+    .hsm_scn_subscription_post(
+      TEST_TOKEN,
+      &ScnSubscription::node_state(
+        "daemon@host",
+        "https://host/scn",
+        &["Off", "Ready"],
+      ),
+    )
+    .await
+    .expect("ok");
+  assert_eq!(subscription.id.as_deref(), Some("42"));
+}
@@ -93,6 +93,37 @@ async fn ims_image_delete_issues_soft_then_permanent_deletes() {
     .expect("should succeed");
 }
 
+#[tokio::test]
+async fn ims_image_add_labels_merges_into_existing_metadata() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .and(path("/ims/v3/images/abc"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+      "id": "abc",
+      "name": "img-a",
+      "metadata": {"owner": "cscs"},
+    })))
+    .expect(1).mount(&server)
+    .await;
+  Mock::given(method("PATCH"))
+    .and(path("/ims/v3/images/abc"))
+    .and(bearer_token(TEST_TOKEN))
+    .and(body_json(json!({
+      "metadata": {"owner": "cscs", "purpose": "compute"},
+    })))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+    .expect(1).mount(&server)
+    .await;
+
+  let client = make_client(&server.uri());
+  let image = client
+    .ims_image_add_labels(TEST_TOKEN, "abc", &[("purpose", "compute")])
+    .await
+    .unwrap();
+  assert_eq!(image.label("purpose"), Some("compute"));
+  assert_eq!(image.label("owner"), Some("cscs"));
+}
+
 // ---------- ims/recipe ----------
 
 #[tokio::test]